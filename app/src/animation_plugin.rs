@@ -0,0 +1,36 @@
+use bevy_app::{App, Plugin, Update};
+use bevy_ecs::{
+    schedule::{common_conditions::not, IntoSystemConfigs},
+    system::{Query, Res},
+};
+use data::skinning::{Animator, Skin};
+
+use crate::{
+    frame_pacing_plugin::FramePacing, photo_mode_plugin::photo_mode_active,
+    render_plugin::update_instances,
+};
+
+/// Plays every [`Animator`]'s clip on its entity's [`Skin`], which the renderer skins the
+/// entity's mesh by. Paused with the rest of the world in photo mode.
+pub struct AnimationPlugin;
+
+impl Plugin for AnimationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            advance_animators
+                .before(update_instances)
+                .run_if(not(photo_mode_active)),
+        );
+    }
+}
+
+fn advance_animators(
+    frame_pacing: Res<FramePacing>,
+    mut animated: Query<(&mut Animator, &mut Skin)>,
+) {
+    let delta = frame_pacing.smoothed_delta_secs();
+    for (mut animator, mut skin) in &mut animated {
+        animator.advance(delta, &mut skin);
+    }
+}
@@ -0,0 +1,249 @@
+use bevy_app::{App, Plugin, PreUpdate, Update};
+use bevy_ecs::{
+    event::{Event, EventReader, EventWriter},
+    schedule::IntoSystemConfigs,
+    system::{Res, ResMut, Resource},
+};
+use bevy_input::{
+    keyboard::{Key, KeyCode, KeyboardInput},
+    ButtonInput, ButtonState, InputSystem,
+};
+use data::{
+    accessibility::AccessibilitySettings,
+    chat::{chat_command, sanitize_chat, ChatLog, ChatMessage, MAX_CHAT_LEN},
+    prelude::{IVec2, UVec2},
+    ui_layout::{Anchor, UiLayout},
+};
+use renderer::hud::{Hud, HudRect};
+
+use crate::{
+    frame_pacing_plugin::FramePacing, hud_plugin::build_hud, localization::Localization,
+    notification_plugin::wrap, player_plugin::PlayerSettings,
+};
+
+/// Chat in the top left of the [`Hud`]: the latest messages until they fade, and a line to
+/// type into, opened by [`CHAT_KEY`], or by [`COMMAND_KEY`] with the `/` of a command already
+/// typed. Enter sends the line as a [`ChatSent`] and Escape closes it without sending. While
+/// it's open, keys go to the chat rather than the game.
+pub struct ChatPlugin;
+
+impl Plugin for ChatPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ChatLog>()
+            .init_resource::<ChatEntry>()
+            .add_event::<ChatSent>()
+            .add_systems(PreUpdate, type_chat.after(InputSystem))
+            .add_systems(
+                Update,
+                (update_chat_log, echo_chat, draw_chat.after(build_hud)).chain(),
+            );
+    }
+}
+
+pub const CHAT_KEY: KeyCode = KeyCode::KeyT;
+pub const COMMAND_KEY: KeyCode = KeyCode::Slash;
+
+/// Seconds a message stays on screen while the chat is closed
+const FADE_SECS: f32 = 10.0;
+/// Messages shown while the chat is closed, and while it's open
+const CLOSED_MESSAGES: usize = 8;
+const OPEN_MESSAGES: usize = 20;
+
+const MARGIN: i32 = 12;
+const CHAT_WIDTH: u32 = 360;
+const PADDING: u32 = 8;
+/// At a text scale of 1
+const TEXT_SCALE: u32 = 2;
+const LINE_GAP: u32 = 4;
+const PANEL_COLOR: [u8; 4] = [20, 20, 20, 255];
+const TEXT_COLOR: [u8; 4] = [230, 230, 230, 255];
+const SYSTEM_COLOR: [u8; 4] = [255, 200, 80, 255];
+const ENTRY_COLOR: [u8; 4] = [120, 200, 255, 255];
+
+/// The line being typed, if the chat is open
+#[derive(Resource, Debug, Default)]
+pub struct ChatEntry {
+    text: Option<String>,
+}
+
+impl ChatEntry {
+    pub fn is_open(&self) -> bool {
+        self.text.is_some()
+    }
+}
+
+/// A line of chat or a `/` command the player sent, already sanitized, for the transport to
+/// pass on to the server
+#[derive(Event, Debug, Clone, PartialEq, Eq)]
+pub struct ChatSent {
+    pub text: String,
+}
+
+/// What a key pressed while typing does to the chat
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Edit {
+    Typed,
+    Send,
+    Close,
+}
+
+fn edit_entry(text: &mut String, key: &Key) -> Edit {
+    let typed = match key {
+        Key::Enter => return Edit::Send,
+        Key::Escape => return Edit::Close,
+        Key::Backspace => {
+            text.pop();
+            return Edit::Typed;
+        }
+        Key::Space => " ",
+        Key::Character(typed) => typed.as_str(),
+        _ => return Edit::Typed,
+    };
+    let room = MAX_CHAT_LEN.saturating_sub(text.chars().count());
+    text.extend(typed.chars().filter(|c| !c.is_control()).take(room));
+    Edit::Typed
+}
+
+/// Runs right after the input is read, so that while the chat is open the rest of the game
+/// sees no keys pressed, including the Escape that would otherwise close the window
+fn type_chat(
+    mut keys: ResMut<ButtonInput<KeyCode>>,
+    mut key_reader: EventReader<KeyboardInput>,
+    mut entry: ResMut<ChatEntry>,
+    mut sent_writer: EventWriter<ChatSent>,
+) {
+    let Some(text) = &mut entry.text else {
+        let typed = if keys.just_pressed(CHAT_KEY) {
+            ""
+        } else if keys.just_pressed(COMMAND_KEY) {
+            "/"
+        } else {
+            return;
+        };
+        entry.text = Some(typed.to_owned());
+        // The key that opened the chat isn't typed into it
+        key_reader.clear();
+        keys.reset_all();
+        return;
+    };
+
+    let mut edit = Edit::Typed;
+    for input in key_reader.read() {
+        if input.state == ButtonState::Pressed {
+            edit = edit_entry(text, &input.logical_key);
+            if edit != Edit::Typed {
+                break;
+            }
+        }
+    }
+    if edit == Edit::Send {
+        if let Some(text) = sanitize_chat(text) {
+            sent_writer.send(ChatSent { text });
+        }
+    }
+    if edit != Edit::Typed {
+        entry.text = None;
+    }
+    keys.reset_all();
+}
+
+fn update_chat_log(frame_pacing: Res<FramePacing>, mut log: ResMut<ChatLog>) {
+    log.update(frame_pacing.smoothed_delta_secs());
+}
+
+/// There's no transport to relay chat through the server yet, so sent lines go straight to
+/// the log, the way the server would send them back
+fn echo_chat(
+    settings: Res<PlayerSettings>,
+    localization: Res<Localization>,
+    mut sent_reader: EventReader<ChatSent>,
+    mut log: ResMut<ChatLog>,
+) {
+    for ChatSent { text } in sent_reader.read() {
+        log.push(match chat_command(text) {
+            Some(_) => ChatMessage::system(localization.text("commands-need-server")),
+            None => ChatMessage::player(settings.name.as_str(), text.as_str()),
+        });
+    }
+}
+
+fn draw_chat(
+    log: Res<ChatLog>,
+    entry: Res<ChatEntry>,
+    accessibility: Res<AccessibilitySettings>,
+    layout: Res<UiLayout>,
+    mut hud: ResMut<Hud>,
+) {
+    let text_scale = accessibility.clamped_text_scale();
+    let scale = layout.px(TEXT_SCALE * text_scale);
+    let width = layout.px(CHAT_WIDTH * text_scale);
+    let (padding, line_gap) = (layout.px(PADDING), layout.px(LINE_GAP));
+    let text_width = width - 2 * padding;
+
+    let messages = match entry.text {
+        Some(_) => log.recent(OPEN_MESSAGES, f32::INFINITY),
+        None => log.recent(CLOSED_MESSAGES, FADE_SECS),
+    };
+    let mut lines: Vec<(String, [u8; 4])> = Vec::new();
+    for message in messages {
+        let color = match message.sender {
+            Some(_) => TEXT_COLOR,
+            None => SYSTEM_COLOR,
+        };
+        lines.extend(
+            wrap(&message.line(), text_width, scale)
+                .into_iter()
+                .map(|line| (line, color)),
+        );
+    }
+    if let Some(text) = &entry.text {
+        let mut typed = wrap(&format!("> {text}_"), text_width, scale);
+        // Only the end of a long line, where the typing happens
+        lines.extend(typed.pop().map(|line| (line, ENTRY_COLOR)));
+    }
+    if lines.is_empty() {
+        return;
+    }
+
+    let line_height = Hud::text_size("", scale).1;
+    let height = lines.len() as u32 * (line_height + line_gap) - line_gap + 2 * padding;
+    let IVec2 { x: left, y: top } = layout.place(
+        Anchor::TopLeft,
+        UVec2::new(width, height),
+        IVec2::splat(MARGIN),
+    );
+    hud.push(HudRect::new(left, top, width, height, PANEL_COLOR));
+    for (i, (line, color)) in lines.iter().enumerate() {
+        let y = top + (padding + i as u32 * (line_height + line_gap)) as i32;
+        hud.push_text(left + padding as i32, y, scale, line, *color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn typing_edits_the_entry_until_sent() {
+        let mut text = "/".to_owned();
+        for key in [
+            Key::Character("k".into()),
+            Key::Character("x".into()),
+            Key::Backspace,
+            Key::Character("ick".into()),
+            Key::Space,
+            Key::Shift,
+            Key::Character("bob".into()),
+        ] {
+            assert_eq!(edit_entry(&mut text, &key), Edit::Typed);
+        }
+        assert_eq!(text, "/kick bob");
+        assert_eq!(edit_entry(&mut text, &Key::Enter), Edit::Send);
+        assert_eq!(edit_entry(&mut text, &Key::Escape), Edit::Close);
+
+        let mut text = "a".repeat(MAX_CHAT_LEN - 1);
+        edit_entry(&mut text, &Key::Character("bc".into()));
+        assert_eq!(text.chars().count(), MAX_CHAT_LEN);
+        assert!(text.ends_with('b'));
+    }
+}
@@ -0,0 +1,139 @@
+use bevy_app::{App, Plugin, Update};
+use bevy_ecs::{
+    query::With,
+    schedule::IntoSystemConfigs,
+    system::{Res, ResMut, Single},
+};
+use bevy_input::{keyboard::KeyCode, ButtonInput};
+use data::{
+    accessibility::AccessibilitySettings,
+    chunk_heatmap::{heat_color, ChunkHeatmap},
+    chunk_map::chunk_of,
+    floating_origin::FloatingOrigin,
+    prelude::{IVec2, UVec2},
+    transform::Transform,
+    ui_layout::{Anchor, UiLayout},
+};
+use renderer::{
+    hud::{Hud, HudRect},
+    settings::RendererSettings,
+};
+
+use crate::{hud_plugin::build_hud, player_plugin::Player};
+
+/// Debug view of the loaded chunks from above, each column of chunks colored by what its
+/// costliest chunk took to generate, how many triangles its mesh has, or how much GPU memory
+/// its mesh and BLAS take, relative to the costliest column. [`HEATMAP_KEY`] cycles through
+/// the metrics and then hides it again. The costs are recorded by the streaming plugin, with
+/// BLAS memory estimated per triangle rather than queried from the driver.
+pub struct ChunkHeatmapPlugin;
+
+impl Plugin for ChunkHeatmapPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ChunkHeatmap>().add_systems(
+            Update,
+            (cycle_heatmap, draw_heatmap.after(build_hud)).chain(),
+        );
+    }
+}
+
+pub const HEATMAP_KEY: KeyCode = KeyCode::F8;
+
+/// Largest side of the map in UI units; each column is drawn as large as fits
+const MAP_SIZE: u32 = 192;
+const MARGIN: i32 = 12;
+const PADDING: u32 = 8;
+/// At a text scale of 1
+const TEXT_SCALE: u32 = 2;
+const LINE_GAP: u32 = 4;
+const PANEL_COLOR: [u8; 4] = [20, 20, 20, 255];
+const TEXT_COLOR: [u8; 4] = [230, 230, 230, 255];
+/// Columns not loaded yet, or loaded rather than generated when showing generation time
+const NO_DATA_COLOR: [u8; 4] = [50, 50, 50, 255];
+const PLAYER_COLOR: [u8; 4] = [255, 255, 255, 255];
+const PLAYER_SIZE: u32 = 4;
+
+fn cycle_heatmap(keys: Res<ButtonInput<KeyCode>>, mut heatmap: ResMut<ChunkHeatmap>) {
+    if keys.just_pressed(HEATMAP_KEY) {
+        heatmap.cycle();
+    }
+}
+
+/// Drawn on the left edge, north up, out of the way of the inspector and the toasts
+fn draw_heatmap(
+    heatmap: Res<ChunkHeatmap>,
+    settings: Res<RendererSettings>,
+    accessibility: Res<AccessibilitySettings>,
+    origin: Res<FloatingOrigin>,
+    layout: Res<UiLayout>,
+    mut hud: ResMut<Hud>,
+    player: Single<&Transform, With<Player>>,
+) {
+    let Some(metric) = heatmap.metric else {
+        return;
+    };
+    let columns = heatmap.columns(metric);
+    let hottest = columns.values().copied().fold(0.0, f64::max);
+    let lines = [
+        metric.name().to_owned(),
+        format!("max {}", metric.format(hottest)),
+    ];
+
+    let radius = settings.view_distance.chunks() as i32;
+    let across = 2 * radius + 1;
+    let cell = (layout.px(MAP_SIZE) / across as u32).max(1);
+    let map_size = across as u32 * cell;
+    let scale = layout.px(TEXT_SCALE * accessibility.clamped_text_scale());
+    let (padding, line_gap) = (layout.px(PADDING), layout.px(LINE_GAP));
+    let line_height = Hud::text_size("", scale).1;
+    let text_width = lines
+        .iter()
+        .map(|line| Hud::text_size(line, scale).0)
+        .max()
+        .unwrap_or_default();
+    let text_height = lines.len() as u32 * (line_height + line_gap);
+    let width = text_width.max(map_size) + 2 * padding;
+    let height = text_height + map_size + 2 * padding;
+    let IVec2 { x: left, y: top } = layout.place(
+        Anchor::Left,
+        UVec2::new(width, height),
+        IVec2::splat(MARGIN),
+    );
+    hud.push(HudRect::new(left, top, width, height, PANEL_COLOR));
+
+    let x = left + padding as i32;
+    for (i, line) in lines.iter().enumerate() {
+        let y = top + (padding + i as u32 * (line_height + line_gap)) as i32;
+        hud.push_text(x, y, scale, line, TEXT_COLOR);
+    }
+
+    let map_top = top + (padding + text_height) as i32;
+    let center = chunk_of(origin.world_voxel(player.translation));
+    let corner = IVec2::new(center.x, center.z) - radius;
+    for row in 0..across {
+        for column in 0..across {
+            let color =
+                columns
+                    .get(&(corner + IVec2::new(column, row)))
+                    .map_or(NO_DATA_COLOR, |value| {
+                        let heat = if hottest > 0.0 { value / hottest } else { 0.0 };
+                        heat_color(heat as f32)
+                    });
+            hud.push(HudRect::new(
+                x + column * cell as i32,
+                map_top + row * cell as i32,
+                cell,
+                cell,
+                color,
+            ));
+        }
+    }
+    let middle = (radius * cell as i32) + cell as i32 / 2;
+    hud.push(HudRect::centered(
+        x + middle,
+        map_top + middle,
+        layout.px(PLAYER_SIZE),
+        layout.px(PLAYER_SIZE),
+        PLAYER_COLOR,
+    ));
+}
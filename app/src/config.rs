@@ -0,0 +1,108 @@
+use std::{collections::BTreeSet, error::Error, fs, io, path::Path};
+
+use data::{accessibility::AccessibilitySettings, streaming::StreamingBudget};
+use renderer::settings::RendererSettings;
+use serde::{Deserialize, Serialize};
+
+use crate::player_plugin::PlayerSettings;
+
+/// Read from and written to the working directory
+pub const CONFIG_PATH: &str = "vx.toml";
+
+/// Keys only read at startup, which a reloaded file can't change
+pub const RESTART_KEYS: &[&str] = &["renderer.render_path"];
+
+/// User-facing settings persisted between runs. Missing keys fall back to their defaults, so
+/// older files keep loading as settings are added.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Language of the string tables, see [`Localization`](crate::localization::Localization);
+    /// `None` for the default one
+    pub language: Option<String>,
+    pub renderer: RendererSettings,
+    pub player: PlayerSettings,
+    pub streaming: StreamingBudget,
+    pub accessibility: AccessibilitySettings,
+}
+
+impl Config {
+    /// A missing file is not an error and yields the defaults
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        match fs::read_to_string(path) {
+            Ok(contents) => Ok(toml::from_str(&contents)?),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(Box::new(e)),
+        }
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Box<dyn Error>> {
+        fs::write(path, toml::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Dotted paths of the keys whose values differ from `other`, e.g. `renderer.vsync`
+    pub fn changed_keys(&self, other: &Self) -> Result<Vec<String>, Box<dyn Error>> {
+        let mut changed = Vec::new();
+        diff_tables(
+            "",
+            &toml::Table::try_from(self)?,
+            &toml::Table::try_from(other)?,
+            &mut changed,
+        );
+        Ok(changed)
+    }
+}
+
+fn diff_tables(prefix: &str, a: &toml::Table, b: &toml::Table, changed: &mut Vec<String>) {
+    let keys: BTreeSet<&String> = a.keys().chain(b.keys()).collect();
+    for key in keys {
+        let path = match prefix {
+            "" => key.clone(),
+            prefix => format!("{prefix}.{key}"),
+        };
+        match (a.get(key), b.get(key)) {
+            (Some(toml::Value::Table(a)), Some(toml::Value::Table(b))) => {
+                diff_tables(&path, a, b, changed)
+            }
+            (a, b) if a != b => changed.push(path),
+            _ => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use renderer::capabilities::RenderPath;
+
+    use super::*;
+
+    #[test]
+    fn partial_file_keeps_defaults() {
+        let config: Config = toml::from_str("[renderer]\nvsync = false\n").unwrap();
+        assert!(!config.renderer.vsync);
+        assert_eq!(config.player, PlayerSettings::default());
+
+        let round_trip: Config = toml::from_str(&toml::to_string(&config).unwrap()).unwrap();
+        assert_eq!(round_trip, config);
+    }
+
+    #[test]
+    fn changed_keys_are_dotted_paths() {
+        let config = Config::default();
+        assert!(config.changed_keys(&config).unwrap().is_empty());
+
+        let mut edited = config.clone();
+        edited.player.mouse_sensitivity = 2.0;
+        edited.player.third_person.length = 6.0;
+        edited.renderer.render_path = Some(RenderPath::Compute);
+        assert_eq!(
+            config.changed_keys(&edited).unwrap(),
+            [
+                "player.mouse_sensitivity",
+                "player.third_person.length",
+                "renderer.render_path",
+            ]
+        );
+    }
+}
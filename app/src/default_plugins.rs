@@ -0,0 +1,92 @@
+use bevy_a11y::AccessibilityPlugin;
+use bevy_app::{PluginGroup, PluginGroupBuilder};
+use bevy_ecs::event::Event;
+use bevy_input::InputPlugin;
+use bevy_state::app::StatesPlugin;
+use bevy_window::{CursorGrabMode, CursorOptions, Window, WindowPlugin, WindowResolution};
+use bevy_winit::WinitPlugin;
+
+use crate::{
+    animation_plugin::AnimationPlugin, chat_plugin::ChatPlugin,
+    chunk_heatmap_plugin::ChunkHeatmapPlugin, diagnostics_plugin::DiagnosticsPlugin,
+    edit_prediction_plugin::EditPredictionPlugin, frame_dump_plugin::FrameDumpPlugin,
+    frame_pacing_plugin::FramePacingPlugin, haptics_plugin::HapticsPlugin, hud_plugin::HudPlugin,
+    inspector_plugin::InspectorPlugin, interaction_plugin::InteractionPlugin,
+    inventory_plugin::InventoryPlugin, loading_plugin::LoadingPlugin,
+    localization::LocalizationPlugin, minimap_plugin::MinimapPlugin,
+    model_editor_plugin::ModelEditorPlugin, mods_plugin::ModsPlugin,
+    notification_plugin::NotificationPlugin, npc_plugin::NpcPlugin,
+    particle_plugin::ParticlePlugin, photo_mode_plugin::PhotoModePlugin,
+    player_plugin::PlayerPlugin, prefab_plugin::PrefabPlugin, profiler::ScheduleSpansPlugin,
+    protocol::ProtocolPlugin, render_plugin::RenderPlugin, save_plugin::SavePlugin,
+    schematic_plugin::SchematicPlugin, settings_plugin::SettingsPlugin,
+    simulation_plugin::SimulationPlugin, streaming_plugin::StreamingPlugin,
+    task_plugin::TaskPlugin, time_plugin::TimePlugin, window_plugin, world_plugin::WorldPlugin,
+};
+
+/// Everything the game runs with: the window, renderer, player and world
+pub struct DefaultPlugins;
+
+impl PluginGroup for DefaultPlugins {
+    fn build(self) -> PluginGroupBuilder {
+        let group = PluginGroupBuilder::start::<Self>()
+            .add(ScheduleSpansPlugin)
+            .add(TaskPlugin::default())
+            .add(AccessibilityPlugin)
+            .add(InputPlugin)
+            .add(StatesPlugin)
+            .add(WinitPlugin::<WinitEvent>::default())
+            .add(WindowPlugin {
+                primary_window: Some(Window {
+                    cursor_options: CursorOptions {
+                        visible: false,
+                        grab_mode: CursorGrabMode::Locked,
+                        ..Default::default()
+                    },
+                    resolution: WindowResolution::new(800.0, 600.0),
+                    ..Default::default()
+                }),
+                close_when_requested: true,
+                ..Default::default()
+            })
+            .add(window_plugin::WindowPlugin)
+            .add(TimePlugin)
+            .add(FramePacingPlugin)
+            .add(LocalizationPlugin)
+            .add(ProtocolPlugin)
+            .add(LoadingPlugin)
+            .add(SettingsPlugin)
+            .add(RenderPlugin)
+            .add(PlayerPlugin)
+            .add(HudPlugin)
+            .add(NotificationPlugin)
+            .add(ChatPlugin)
+            .add(DiagnosticsPlugin)
+            .add(InventoryPlugin)
+            .add(InteractionPlugin)
+            .add(InspectorPlugin)
+            .add(SavePlugin)
+            .add(SimulationPlugin)
+            .add(WorldPlugin)
+            .add(EditPredictionPlugin)
+            .add(StreamingPlugin)
+            .add(PrefabPlugin)
+            .add(NpcPlugin)
+            .add(AnimationPlugin)
+            .add(ParticlePlugin)
+            .add(HapticsPlugin)
+            .add(MinimapPlugin)
+            .add(ChunkHeatmapPlugin)
+            .add(PhotoModePlugin)
+            .add(FrameDumpPlugin)
+            .add(SchematicPlugin)
+            .add(ModelEditorPlugin)
+            .add(ModsPlugin);
+        #[cfg(feature = "renderdoc")]
+        let group = group.add_before::<RenderPlugin>(crate::renderdoc_plugin::RenderDocPlugin);
+        group
+    }
+}
+
+#[derive(Event, Default)]
+struct WinitEvent;
@@ -0,0 +1,416 @@
+use std::{
+    collections::VecDeque,
+    fs,
+    time::{Duration, Instant},
+};
+
+use bevy_app::{App, Last, Plugin, Update};
+use bevy_ecs::{
+    schedule::IntoSystemConfigs,
+    system::{Res, ResMut, Resource},
+};
+use bevy_input::{keyboard::KeyCode, ButtonInput};
+use data::{
+    accessibility::AccessibilitySettings,
+    notification::{Notifications, Severity},
+    prelude::{IVec2, UVec2},
+    streaming::StreamingStats,
+    ui_layout::{Anchor, UiLayout},
+};
+use renderer::{
+    command_state::CommandState,
+    hud::{Hud, HudRect},
+    pass_timer::PassTiming,
+    MAX_FRAMES_IN_FLIGHT,
+};
+use serde::Serialize;
+
+use crate::{
+    hud_plugin::{build_hud, HOTBAR_CLEARANCE},
+    localization::Localization,
+    profiler::{self, SystemSpan},
+    task_plugin::{TaskGroup, TaskPools},
+};
+
+/// Overlay with a graph of the recent frame times. While it's open the instrumented systems
+/// (see [`profiler::span`]) and the render graph's passes are timed, and whenever a frame
+/// hitches badly enough to be the slowest in the graph, its breakdown is written to
+/// [`FRAME_REPORT_PATH`].
+pub struct DiagnosticsPlugin;
+
+impl Plugin for DiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Diagnostics>()
+            .add_systems(
+                Update,
+                (toggle_diagnostics, draw_diagnostics.after(build_hud)).chain(),
+            )
+            .add_systems(Last, record_frame);
+    }
+}
+
+pub const DIAGNOSTICS_KEY: KeyCode = KeyCode::F2;
+
+/// Overwritten by every report, relative to the working directory
+pub const FRAME_REPORT_PATH: &str = "frame-report.json";
+
+/// Frames in the graph, one pixel wide each
+const HISTORY_LEN: usize = 240;
+/// Frames at least this slow, and [`HITCH_FACTOR`] times the median, are hitches
+const HITCH_THRESHOLD: Duration = Duration::from_millis(33);
+const HITCH_FACTOR: u32 = 2;
+/// Least time between two reports, so a burst of hitches doesn't rewrite the file every frame
+const REPORT_COOLDOWN: Duration = Duration::from_secs(5);
+
+const MARGIN: i32 = 12;
+const PADDING: u32 = 8;
+const GRAPH_HEIGHT: u32 = 64;
+/// Frame time at the top of the graph; slower frames are cut off
+const GRAPH_MAX: Duration = Duration::from_millis(50);
+/// Frame times marked by a line across the graph: 60 and 30 frames per second
+const GRAPH_LINES: [Duration; 2] = [Duration::from_micros(16_667), Duration::from_micros(33_333)];
+/// At a text scale of 1
+const TEXT_SCALE: u32 = 2;
+const LINE_GAP: u32 = 4;
+const PANEL_COLOR: [u8; 4] = [20, 20, 20, 255];
+const LINE_COLOR: [u8; 4] = [90, 90, 90, 255];
+const TEXT_COLOR: [u8; 4] = [230, 230, 230, 255];
+const FAST_COLOR: [u8; 4] = [90, 200, 90, 255];
+const SLOW_COLOR: [u8; 4] = [255, 200, 80, 255];
+const HITCH_COLOR: [u8; 4] = [230, 70, 60, 255];
+
+/// A frame's time and what it was spent on
+#[derive(Debug, Clone, Default)]
+struct FrameRecord {
+    duration: Duration,
+    systems: Vec<SystemSpan>,
+    passes: Vec<PassTiming>,
+    streaming: Option<StreamingStats>,
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct Diagnostics {
+    open: bool,
+    /// Oldest first
+    history: VecDeque<Duration>,
+    /// Frames whose passes haven't been read back yet, oldest first
+    pending: VecDeque<FrameRecord>,
+    last_frame: Option<Instant>,
+    last_report: Option<Instant>,
+    /// The last frame reported
+    reported: Option<FrameRecord>,
+}
+
+impl Diagnostics {
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    fn push(&mut self, duration: Duration) {
+        if self.history.len() == HISTORY_LEN {
+            self.history.pop_front();
+        }
+        self.history.push_back(duration);
+    }
+
+    /// Queues `record` until its passes arrive, returning the frame `passes` belong to. A
+    /// frame's passes are read back when its frame in flight comes around again, assuming a
+    /// frame is drawn every update.
+    fn complete(
+        &mut self,
+        record: FrameRecord,
+        passes: Option<Vec<PassTiming>>,
+    ) -> Option<FrameRecord> {
+        self.pending.push_back(record);
+        if self.pending.len() <= MAX_FRAMES_IN_FLIGHT as usize {
+            return None;
+        }
+        let mut completed = self.pending.pop_front()?;
+        completed.passes = passes.unwrap_or_default();
+        Some(completed)
+    }
+
+    fn median(&self) -> Duration {
+        let mut sorted: Vec<_> = self.history.iter().copied().collect();
+        sorted.sort_unstable();
+        sorted.get(sorted.len() / 2).copied().unwrap_or_default()
+    }
+
+    fn slowest(&self) -> Duration {
+        self.history.iter().copied().max().unwrap_or_default()
+    }
+
+    /// Whether `duration` is a hitch that's the slowest frame in the graph
+    fn is_reportable(&self, duration: Duration) -> bool {
+        duration >= HITCH_THRESHOLD
+            && duration >= self.median() * HITCH_FACTOR
+            && duration >= self.slowest()
+    }
+
+    fn reset(&mut self) {
+        self.history.clear();
+        self.pending.clear();
+        self.last_frame = None;
+    }
+}
+
+fn toggle_diagnostics(keys: Res<ButtonInput<KeyCode>>, mut diagnostics: ResMut<Diagnostics>) {
+    if keys.just_pressed(DIAGNOSTICS_KEY) {
+        diagnostics.open = !diagnostics.open;
+    }
+}
+
+/// Runs last so the frame's spans have all been recorded
+fn record_frame(
+    task_pools: Res<TaskPools>,
+    localization: Res<Localization>,
+    notifications: Option<Res<Notifications>>,
+    streaming: Option<Res<StreamingStats>>,
+    mut diagnostics: ResMut<Diagnostics>,
+    mut command_state: ResMut<CommandState>,
+) {
+    let systems = profiler::take_spans();
+    let passes = command_state.take_pass_timings();
+    profiler::set_recording(diagnostics.open);
+    command_state.set_pass_timing(diagnostics.open);
+    if !diagnostics.open {
+        diagnostics.reset();
+        return;
+    }
+
+    let now = Instant::now();
+    let Some(last_frame) = diagnostics.last_frame.replace(now) else {
+        return;
+    };
+    let duration = now - last_frame;
+    diagnostics.push(duration);
+    let record = FrameRecord {
+        duration,
+        systems,
+        passes: Vec::new(),
+        streaming: streaming.as_deref().copied(),
+    };
+    let Some(completed) = diagnostics.complete(record, passes) else {
+        return;
+    };
+    let cooled_down = diagnostics
+        .last_report
+        .is_none_or(|last_report| now - last_report >= REPORT_COOLDOWN);
+    if !cooled_down || !diagnostics.is_reportable(completed.duration) {
+        return;
+    }
+
+    diagnostics.last_report = Some(now);
+    let report = FrameReport::new(&completed, diagnostics.median());
+    diagnostics.reported = Some(completed);
+    let localization = localization.clone();
+    let notifications = Notifications::sender_or_log(notifications.as_deref());
+    task_pools
+        .spawn(TaskGroup::Io, async move {
+            let written = serde_json::to_string_pretty(&report)
+                .map_err(|e| e.to_string())
+                .and_then(|json| fs::write(FRAME_REPORT_PATH, json).map_err(|e| e.to_string()));
+            match written {
+                Ok(()) => notifications.send(
+                    Severity::Info,
+                    localization.format("saved", &[("path", FRAME_REPORT_PATH.into())]),
+                ),
+                Err(e) => notifications.send(
+                    Severity::Error,
+                    localization.format(
+                        "save-failed",
+                        &[("path", FRAME_REPORT_PATH.into()), ("error", e.into())],
+                    ),
+                ),
+            }
+        })
+        .detach();
+}
+
+#[derive(Debug, Serialize)]
+struct Timing {
+    name: &'static str,
+    ms: f64,
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// Written as JSON, with the systems slowest first. Systems that ran more than once in the
+/// frame, e.g. fixed updates catching up, are added up.
+#[derive(Debug, Serialize)]
+struct FrameReport {
+    frame_ms: f64,
+    median_frame_ms: f64,
+    /// CPU time of the instrumented systems
+    systems: Vec<Timing>,
+    /// GPU time of the render graph's passes, in the order they ran
+    passes: Vec<Timing>,
+    streaming: Option<StreamingStats>,
+}
+
+impl FrameReport {
+    fn new(record: &FrameRecord, median: Duration) -> Self {
+        let mut systems: Vec<(&'static str, Duration)> = Vec::new();
+        for span in &record.systems {
+            match systems.iter_mut().find(|(name, _)| *name == span.name) {
+                Some((_, duration)) => *duration += span.duration,
+                None => systems.push((span.name, span.duration)),
+            }
+        }
+        systems.sort_by_key(|(_, duration)| std::cmp::Reverse(*duration));
+        Self {
+            frame_ms: millis(record.duration),
+            median_frame_ms: millis(median),
+            systems: systems
+                .into_iter()
+                .map(|(name, duration)| Timing {
+                    name,
+                    ms: millis(duration),
+                })
+                .collect(),
+            passes: record
+                .passes
+                .iter()
+                .map(|pass| Timing {
+                    name: pass.name,
+                    ms: millis(pass.duration),
+                })
+                .collect(),
+            streaming: record.streaming,
+        }
+    }
+
+    /// The system or pass that took longest, for the overlay
+    fn worst(&self) -> Option<&Timing> {
+        self.systems
+            .iter()
+            .chain(&self.passes)
+            .max_by(|a, b| a.ms.total_cmp(&b.ms))
+    }
+}
+
+fn bar_color(duration: Duration) -> [u8; 4] {
+    if duration <= GRAPH_LINES[0] {
+        FAST_COLOR
+    } else if duration <= GRAPH_LINES[1] {
+        SLOW_COLOR
+    } else {
+        HITCH_COLOR
+    }
+}
+
+/// Pixels up a graph `height` pixels tall `duration` reaches
+fn graph_height(duration: Duration, height: u32) -> u32 {
+    let fraction = (duration.as_secs_f32() / GRAPH_MAX.as_secs_f32()).min(1.0);
+    (fraction * height as f32).round() as u32
+}
+
+/// Drawn in the bottom right corner above the hotbar, clear of the minimap and the toasts
+fn draw_diagnostics(
+    diagnostics: Res<Diagnostics>,
+    accessibility: Res<AccessibilitySettings>,
+    layout: Res<UiLayout>,
+    mut hud: ResMut<Hud>,
+) {
+    if !diagnostics.open {
+        return;
+    }
+    let scale = layout.px(TEXT_SCALE * accessibility.clamped_text_scale());
+    let last = diagnostics.history.back().copied().unwrap_or_default();
+    let mut lines = vec![format!(
+        "{:.1} ms  max {:.1} ms",
+        millis(last),
+        millis(diagnostics.slowest())
+    )];
+    if let Some(reported) = &diagnostics.reported {
+        let report = FrameReport::new(reported, Duration::ZERO);
+        lines.push(match report.worst() {
+            Some(worst) => format!(
+                "hitch {:.1} ms  {} {:.1} ms",
+                report.frame_ms, worst.name, worst.ms
+            ),
+            None => format!("hitch {:.1} ms", report.frame_ms),
+        });
+    }
+
+    let (padding, line_gap) = (layout.px(PADDING), layout.px(LINE_GAP));
+    // Each frame is a bar one UI unit wide
+    let bar_width = layout.px(1);
+    let graph_width = HISTORY_LEN as u32 * bar_width;
+    let graph_max = layout.px(GRAPH_HEIGHT);
+    let line_height = Hud::text_size("", scale).1;
+    let text_width = lines
+        .iter()
+        .map(|line| Hud::text_size(line, scale).0)
+        .max()
+        .unwrap_or_default();
+    let width = text_width.max(graph_width) + 2 * padding;
+    let text_height = lines.len() as u32 * (line_height + line_gap);
+    let height = text_height + graph_max + 2 * padding;
+    let IVec2 { x: left, y: top } = layout.place(
+        Anchor::BottomRight,
+        UVec2::new(width, height),
+        IVec2::new(MARGIN, HOTBAR_CLEARANCE as i32 + MARGIN),
+    );
+    hud.push(HudRect::new(left, top, width, height, PANEL_COLOR));
+
+    let x = left + padding as i32;
+    for (i, line) in lines.iter().enumerate() {
+        let y = top + (padding + i as u32 * (line_height + line_gap)) as i32;
+        hud.push_text(x, y, scale, line, TEXT_COLOR);
+    }
+
+    let graph_bottom = top + (padding + text_height + graph_max) as i32;
+    for line in GRAPH_LINES {
+        let y = graph_bottom - graph_height(line, graph_max) as i32;
+        hud.push(HudRect::new(x, y, graph_width, bar_width, LINE_COLOR));
+    }
+    // Newest on the right
+    let first = x + ((HISTORY_LEN - diagnostics.history.len()) as u32 * bar_width) as i32;
+    for (i, duration) in diagnostics.history.iter().enumerate() {
+        let bar = graph_height(*duration, graph_max).max(1);
+        hud.push(HudRect::new(
+            first + (i as u32 * bar_width) as i32,
+            graph_bottom - bar as i32,
+            bar_width,
+            bar,
+            bar_color(*duration),
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_slowest_hitch_in_the_graph_is_reported() {
+        let mut diagnostics = Diagnostics::default();
+        let frame = |millis| FrameRecord {
+            duration: Duration::from_millis(millis),
+            ..Default::default()
+        };
+        let timings = |millis| {
+            Some(vec![PassTiming {
+                name: "trace",
+                duration: Duration::from_millis(millis),
+            }])
+        };
+
+        for millis in [16, 17, 80, 16, 40] {
+            diagnostics.push(Duration::from_millis(millis));
+        }
+        assert!(diagnostics.is_reportable(Duration::from_millis(80)));
+        // Slow, but not the slowest in the graph
+        assert!(!diagnostics.is_reportable(Duration::from_millis(40)));
+
+        // Passes arrive once the frame in flight comes around again
+        assert!(diagnostics.complete(frame(16), timings(1)).is_none());
+        assert!(diagnostics.complete(frame(17), timings(2)).is_none());
+        let completed = diagnostics.complete(frame(80), timings(9)).unwrap();
+        assert_eq!(completed.duration, Duration::from_millis(16));
+        assert_eq!(completed.passes, timings(9).unwrap());
+    }
+}
@@ -0,0 +1,73 @@
+use bevy_app::{App, Plugin, Update};
+use bevy_ecs::{
+    event::{Event, EventReader},
+    system::Res,
+};
+use data::{
+    edit_prediction::{EditId, EditRejection},
+    notification::{Notifications, Severity},
+    prelude::IVec3,
+};
+
+use crate::{localization::Localization, world_plugin::VoxelEdits};
+
+/// Reconciles the player's edits with the server's answers. While connected, the transport
+/// inserts [`PredictedEdits`](data::edit_prediction::PredictedEdits), sends the edits it
+/// takes from there, and passes the server's answers on as [`EditAnswer`]s and the chunks it
+/// resends as [`ChunkReplaced`]. Edits show right away; rejected ones are rolled back and
+/// remeshed, and the player is told why.
+pub struct EditPredictionPlugin;
+
+impl Plugin for EditPredictionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<EditAnswer>()
+            .add_event::<ChunkReplaced>()
+            .add_systems(Update, reconcile_edits);
+    }
+}
+
+/// The server's answer to a transaction of predicted edits
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditAnswer {
+    Accepted(EditId),
+    Rejected(EditId, EditRejection),
+}
+
+/// Sent after a chunk in the [`ChunkMap`](data::chunk_map::ChunkMap) was replaced by the
+/// server's
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkReplaced(pub IVec3);
+
+fn rejection_message(rejection: EditRejection) -> &'static str {
+    match rejection {
+        EditRejection::Protected => "edit-rejected-protected",
+        EditRejection::Outdated => "edit-rejected-outdated",
+        EditRejection::Unloaded => "edit-rejected-unloaded",
+    }
+}
+
+/// Chunks first, so a rejection rolls back to the voxels the server last sent
+fn reconcile_edits(
+    mut replaced_reader: EventReader<ChunkReplaced>,
+    mut answer_reader: EventReader<EditAnswer>,
+    localization: Res<Localization>,
+    notifications: Option<Res<Notifications>>,
+    mut edits: VoxelEdits,
+) {
+    for ChunkReplaced(chunk) in replaced_reader.read() {
+        edits.reapply(*chunk);
+    }
+    let notifications = Notifications::sender_or_log(notifications.as_deref());
+    for answer in answer_reader.read() {
+        match *answer {
+            EditAnswer::Accepted(id) => edits.acknowledge(id),
+            EditAnswer::Rejected(id, rejection) => {
+                edits.reject(id);
+                notifications.send(
+                    Severity::Warning,
+                    localization.text(rejection_message(rejection)),
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,336 @@
+use std::{
+    env, fs,
+    io::Write,
+    path::PathBuf,
+    process::{Command, Stdio},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc::{self, Receiver, SyncSender, TrySendError},
+        Arc,
+    },
+    thread,
+};
+
+use bevy_app::{App, Plugin, Update};
+use bevy_ecs::{
+    schedule::IntoSystemConfigs,
+    system::{Res, ResMut, Resource},
+};
+use bevy_input::{keyboard::KeyCode, ButtonInput};
+use data::{
+    notification::{NotificationSender, Notifications, Severity},
+    prelude::{IVec2, UVec2},
+    ui_layout::{Anchor, UiLayout},
+};
+use renderer::{
+    capture::Capture,
+    command_state::CommandState,
+    hud::{Hud, HudRect},
+    MAX_FRAMES_IN_FLIGHT,
+};
+
+use crate::{hud_plugin::build_hud, localization::Localization, photo_mode_plugin::save_photo};
+
+/// Records every frame while dumping, toggled by [`FRAME_DUMP_KEY`], for trailers and videos
+/// of bugs. Frames are written as numbered PNGs to [`FRAME_DUMP_DIR`], or piped as raw RGBA
+/// to the encoder in [`FRAME_ENCODER_VAR`], on a thread of their own behind a queue of
+/// [`QUEUE_LEN`] frames. When the writer falls behind, frames are dropped rather than
+/// stalling the game, and counted when the dump ends.
+///
+/// Frames are captured like photos, without the HUD, read back through the renderer's
+/// per-frame capture buffers rather than a staging ring of their own, and photos taken while
+/// dumping go to the dump instead.
+pub struct FrameDumpPlugin;
+
+impl Plugin for FrameDumpPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FrameDump>().add_systems(
+            Update,
+            (
+                (toggle_frame_dump, dump_frames).chain().before(save_photo),
+                draw_dump_indicator.after(build_hud),
+            ),
+        );
+    }
+}
+
+pub const FRAME_DUMP_KEY: KeyCode = KeyCode::F10;
+
+/// Relative to the working directory
+pub const FRAME_DUMP_DIR: &str = "frames";
+
+/// Command line of an encoder that reads raw RGBA frames from stdin, with `{width}` and
+/// `{height}` replaced by the frame size, e.g. `VX_FRAME_ENCODER="ffmpeg -f rawvideo
+/// -pixel_format rgba -video_size {width}x{height} -framerate 60 -i - dump.mp4"`. Arguments
+/// are split on whitespace, without any quoting.
+pub const FRAME_ENCODER_VAR: &str = "VX_FRAME_ENCODER";
+
+/// Frames read back but not written yet; more than this are dropped
+const QUEUE_LEN: usize = 8;
+
+const INDICATOR_SIZE: u32 = 8;
+const INDICATOR_MARGIN: i32 = 12;
+const INDICATOR_COLOR: [u8; 4] = [230, 40, 40, 255];
+
+#[derive(Resource, Debug, Default)]
+pub struct FrameDump {
+    writer: Option<FrameWriter>,
+}
+
+impl FrameDump {
+    /// Whether frames are being captured, including the last few after it was stopped
+    pub fn is_dumping(&self) -> bool {
+        self.writer.is_some()
+    }
+}
+
+/// Where the writer thread puts the frames
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum FrameSink {
+    Png(PathBuf),
+    Encoder(String),
+}
+
+impl FrameSink {
+    fn from_env() -> Self {
+        match env::var(FRAME_ENCODER_VAR) {
+            Ok(command) if !command.trim().is_empty() => Self::Encoder(command),
+            _ => Self::Png(PathBuf::from(FRAME_DUMP_DIR)),
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Self::Png(dir) => dir.display().to_string(),
+            Self::Encoder(command) => command.split_whitespace().next().unwrap_or("").to_owned(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct FrameWriter {
+    sender: SyncSender<Capture>,
+    dropped: Arc<AtomicU64>,
+    /// Frames left to capture after the dump was stopped, for the captures still in flight
+    draining: Option<u8>,
+}
+
+impl FrameWriter {
+    fn spawn(
+        sink: FrameSink,
+        localization: Localization,
+        notifications: NotificationSender,
+    ) -> Self {
+        let (sender, receiver) = mpsc::sync_channel(QUEUE_LEN);
+        let dropped = Arc::new(AtomicU64::new(0));
+        let thread_dropped = dropped.clone();
+        // Writing blocks on the disk or the encoder, so it gets a thread of its own rather
+        // than tying one of the pools up for the whole dump
+        let spawned = thread::Builder::new()
+            .name("Frame dump".to_owned())
+            .spawn(move || {
+                let result = write_frames(&sink, receiver, &thread_dropped);
+                let dropped = thread_dropped.load(Ordering::Relaxed);
+                match result {
+                    Ok(frames) => notifications.send(
+                        Severity::Info,
+                        localization.format(
+                            "frame-dump-finished",
+                            &[("frames", frames.into()), ("dropped", dropped.into())],
+                        ),
+                    ),
+                    Err(e) => notifications.send(
+                        Severity::Error,
+                        localization.format("frame-dump-failed", &[("error", e.into())]),
+                    ),
+                }
+            });
+        if let Err(e) = spawned {
+            eprintln!("Could not spawn the frame dump thread: {e}");
+        }
+        Self {
+            sender,
+            dropped,
+            draining: None,
+        }
+    }
+
+    fn push(&self, capture: Capture) {
+        match self.sender.try_send(capture) {
+            Ok(()) | Err(TrySendError::Disconnected(_)) => {}
+            Err(TrySendError::Full(_)) => {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+/// Writes frames until the sender is dropped, returning how many were written
+fn write_frames(
+    sink: &FrameSink,
+    receiver: Receiver<Capture>,
+    dropped: &AtomicU64,
+) -> Result<u64, String> {
+    let mut frames = 0;
+    match sink {
+        FrameSink::Png(dir) => {
+            fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+            for capture in receiver {
+                let path = dir.join(format!("frame-{frames:06}.png"));
+                fs::write(&path, capture.to_png())
+                    .map_err(|e| format!("{}: {e}", path.display()))?;
+                frames += 1;
+            }
+        }
+        FrameSink::Encoder(template) => {
+            // The encoder is started on the first frame, once the frame size is known
+            let Ok(first) = receiver.recv() else {
+                return Ok(0);
+            };
+            let size = (first.width, first.height);
+            let args = encoder_args(template, size.0, size.1);
+            let (program, args) = args.split_first().ok_or("empty encoder command")?;
+            let mut encoder = Command::new(program)
+                .args(args)
+                .stdin(Stdio::piped())
+                .spawn()
+                .map_err(|e| format!("{program}: {e}"))?;
+            let mut stdin = encoder.stdin.take().ok_or("encoder has no stdin")?;
+            for capture in [first].into_iter().chain(receiver) {
+                // A raw stream can't change size, e.g. when the window is resized
+                if (capture.width, capture.height) != size {
+                    dropped.fetch_add(1, Ordering::Relaxed);
+                    continue;
+                }
+                stdin
+                    .write_all(&capture.pixels)
+                    .map_err(|e| format!("{program}: {e}"))?;
+                frames += 1;
+            }
+            drop(stdin);
+            let status = encoder.wait().map_err(|e| format!("{program}: {e}"))?;
+            if !status.success() {
+                return Err(format!("{program} exited with {status}"));
+            }
+        }
+    }
+    Ok(frames)
+}
+
+/// The encoder's command line for frames `width` by `height` pixels
+fn encoder_args(template: &str, width: u32, height: u32) -> Vec<String> {
+    template
+        .split_whitespace()
+        .map(|arg| {
+            arg.replace("{width}", &width.to_string())
+                .replace("{height}", &height.to_string())
+        })
+        .collect()
+}
+
+fn toggle_frame_dump(
+    keys: Res<ButtonInput<KeyCode>>,
+    localization: Res<Localization>,
+    notifications: Option<Res<Notifications>>,
+    mut frame_dump: ResMut<FrameDump>,
+) {
+    if !keys.just_pressed(FRAME_DUMP_KEY) {
+        return;
+    }
+    match &mut frame_dump.writer {
+        Some(writer) if writer.draining.is_none() => {
+            writer.draining = Some(MAX_FRAMES_IN_FLIGHT);
+        }
+        // Still draining the last dump
+        Some(_) => {}
+        None => {
+            let sink = FrameSink::from_env();
+            let notifications = Notifications::sender_or_log(notifications.as_deref());
+            notifications.send(
+                Severity::Info,
+                localization.format("frame-dump-started", &[("target", sink.describe().into())]),
+            );
+            frame_dump.writer = Some(FrameWriter::spawn(
+                sink,
+                localization.clone(),
+                notifications,
+            ));
+        }
+    }
+}
+
+/// Hands the frame read back this update to the writer and asks for the next one. A capture
+/// arrives [`MAX_FRAMES_IN_FLIGHT`] frames after it's requested, so after stopping, the
+/// writer is kept that many frames longer for the captures still on the way.
+fn dump_frames(mut frame_dump: ResMut<FrameDump>, mut command_state: ResMut<CommandState>) {
+    let Some(writer) = &mut frame_dump.writer else {
+        return;
+    };
+    if let Some(capture) = command_state.take_capture() {
+        writer.push(capture);
+    }
+    match &mut writer.draining {
+        None => command_state.request_capture(),
+        Some(0) => frame_dump.writer = None,
+        Some(frames) => *frames -= 1,
+    }
+}
+
+fn draw_dump_indicator(frame_dump: Res<FrameDump>, layout: Res<UiLayout>, mut hud: ResMut<Hud>) {
+    if !frame_dump.is_dumping() {
+        return;
+    }
+    let size = layout.px(INDICATOR_SIZE);
+    let IVec2 { x, y } = layout.place(
+        Anchor::Top,
+        UVec2::splat(size),
+        IVec2::splat(INDICATOR_MARGIN),
+    );
+    hud.push(HudRect::new(x, y, size, size, INDICATOR_COLOR));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encoder_gets_the_frame_size_and_a_full_queue_drops_frames() {
+        assert_eq!(
+            encoder_args(
+                "ffmpeg -f rawvideo -video_size {width}x{height}  -i - out.mp4",
+                1280,
+                720
+            ),
+            [
+                "ffmpeg",
+                "-f",
+                "rawvideo",
+                "-video_size",
+                "1280x720",
+                "-i",
+                "-",
+                "out.mp4"
+            ]
+        );
+
+        let (sender, receiver) = mpsc::sync_channel(QUEUE_LEN);
+        let dropped = Arc::new(AtomicU64::new(0));
+        let writer = FrameWriter {
+            sender,
+            dropped: dropped.clone(),
+            draining: None,
+        };
+        let frame = Capture {
+            width: 1,
+            height: 1,
+            pixels: vec![0; 4],
+        };
+        for _ in 0..QUEUE_LEN + 2 {
+            writer.push(frame.clone());
+        }
+        // A full queue drops frames rather than blocking
+        assert_eq!(dropped.load(Ordering::Relaxed), 2);
+        drop(writer);
+        assert_eq!(receiver.iter().count(), QUEUE_LEN);
+    }
+}
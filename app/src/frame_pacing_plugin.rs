@@ -0,0 +1,159 @@
+use std::{
+    collections::VecDeque,
+    mem,
+    time::{Duration, Instant},
+};
+
+use bevy_app::{Plugin, PreUpdate};
+use bevy_ecs::system::{ResMut, Resource};
+
+pub struct FramePacingPlugin;
+
+impl Plugin for FramePacingPlugin {
+    fn build(&self, app: &mut bevy_app::App) {
+        app.init_resource::<FramePacing>()
+            .add_systems(PreUpdate, update_frame_pacing);
+    }
+}
+
+/// Tracks when frames reach the display, so that simulation can be driven by the
+/// presentation rate rather than by how often bevy happens to run `Update`. With
+/// `VK_KHR_present_wait` the renderer's present thread reports when each present is displayed;
+/// without it, the time `queue_present` returns stands in.
+#[derive(Resource)]
+pub struct FramePacing {
+    intervals: VecDeque<Duration>,
+    last_present: Option<Instant>,
+    pending_presents: Vec<Instant>,
+    /// Presents queued but not yet displayed
+    in_flight: u64,
+    /// ID of the last present reported displayed
+    last_present_id: u64,
+    smoothed_delta: Duration,
+}
+
+impl Default for FramePacing {
+    fn default() -> Self {
+        Self {
+            intervals: VecDeque::with_capacity(Self::HISTORY_LEN),
+            last_present: None,
+            pending_presents: Vec::new(),
+            in_flight: 0,
+            last_present_id: 0,
+            smoothed_delta: Self::FALLBACK_DELTA,
+        }
+    }
+}
+
+impl FramePacing {
+    const HISTORY_LEN: usize = 16;
+    const FALLBACK_DELTA: Duration = Duration::from_micros(16_667);
+    /// Intervals longer than this (window drags, breakpoints) are ignored
+    const MAX_INTERVAL: Duration = Duration::from_millis(250);
+
+    /// Called by the renderer right after `queue_present` returns, when it can't tell when
+    /// presents are displayed
+    pub fn record_present(&mut self, now: Instant) {
+        self.pending_presents.push(now);
+        self.in_flight = 0;
+    }
+
+    /// Called by the renderer after queueing the present with ID `queued`, with the IDs and
+    /// times of the presents displayed since the last call, oldest first
+    pub fn record_displayed(
+        &mut self,
+        queued: u64,
+        displayed: impl IntoIterator<Item = (u64, Instant)>,
+    ) {
+        for (id, at) in displayed {
+            self.pending_presents.push(at);
+            self.last_present_id = id;
+        }
+        self.in_flight = queued.saturating_sub(self.last_present_id);
+    }
+
+    /// Delta time averaged over recent presentation intervals
+    pub fn smoothed_delta(&self) -> Duration {
+        self.smoothed_delta
+    }
+
+    pub fn smoothed_delta_secs(&self) -> f32 {
+        self.smoothed_delta.as_secs_f32()
+    }
+
+    /// Estimated time at which the frame currently being simulated reaches the display: one
+    /// interval after each present still queued ahead of it
+    pub fn predicted_present(&self) -> Option<Instant> {
+        self.last_present
+            .map(|last_present| last_present + self.smoothed_delta * (self.in_flight as u32 + 1))
+    }
+
+    fn advance(&mut self) {
+        for present in mem::take(&mut self.pending_presents) {
+            if let Some(last_present) = self.last_present {
+                let interval = present.saturating_duration_since(last_present);
+                if interval <= Self::MAX_INTERVAL {
+                    if self.intervals.len() == Self::HISTORY_LEN {
+                        self.intervals.pop_front();
+                    }
+                    self.intervals.push_back(interval);
+                }
+            }
+            self.last_present = Some(present);
+        }
+
+        if !self.intervals.is_empty() {
+            self.smoothed_delta =
+                self.intervals.iter().sum::<Duration>() / self.intervals.len() as u32;
+        }
+    }
+}
+
+fn update_frame_pacing(mut frame_pacing: ResMut<FramePacing>) {
+    frame_pacing.advance();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delta_averages_recent_presents_and_skips_stalls() {
+        let start = Instant::now();
+        let ms = |ms| start + Duration::from_millis(ms);
+        let mut pacing = FramePacing::default();
+        assert_eq!(pacing.smoothed_delta(), FramePacing::FALLBACK_DELTA);
+        assert_eq!(pacing.predicted_present(), None);
+
+        // Two 10 ms intervals and one 20 ms, reported in one batch
+        pacing.record_displayed(3, [(1, ms(0)), (2, ms(10)), (3, ms(20))]);
+        pacing.advance();
+        pacing.record_displayed(4, [(4, ms(40))]);
+        pacing.advance();
+        let delta = Duration::from_millis(40) / 3;
+        assert_eq!(pacing.smoothed_delta(), delta);
+        assert_eq!(pacing.predicted_present(), Some(ms(40) + delta));
+
+        // Two presents still queued ahead of the frame being simulated
+        pacing.record_displayed(6, []);
+        pacing.advance();
+        assert_eq!(pacing.predicted_present(), Some(ms(40) + delta * 3));
+
+        // A stall isn't averaged in, but the next interval counts from after it
+        pacing.record_present(ms(1040));
+        pacing.advance();
+        pacing.record_present(ms(1050));
+        pacing.advance();
+        assert_eq!(pacing.smoothed_delta(), Duration::from_micros(50_000 / 4));
+        assert_eq!(
+            pacing.predicted_present(),
+            Some(ms(1050) + Duration::from_micros(12_500))
+        );
+
+        for i in 1..=FramePacing::HISTORY_LEN as u64 {
+            pacing.record_present(ms(1050 + i * 5));
+        }
+        pacing.advance();
+        assert_eq!(pacing.smoothed_delta(), Duration::from_millis(5));
+    }
+}
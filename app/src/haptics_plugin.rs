@@ -0,0 +1,87 @@
+use std::time::Duration;
+
+use bevy_app::{App, Plugin, Update};
+use bevy_ecs::{
+    entity::Entity,
+    event::{EventReader, EventWriter},
+    query::With,
+    schedule::IntoSystemConfigs,
+    system::{Local, Query, Res, ResMut},
+};
+use bevy_input::gamepad::{Gamepad, GamepadRumbleIntensity, GamepadRumbleRequest};
+use data::{
+    haptics::{Haptic, Haptics, RumbleIntensity},
+    voxel::Voxel,
+};
+
+use crate::{
+    frame_pacing_plugin::FramePacing, player_plugin::PlayerSettings, world_plugin::VoxelEdited,
+};
+
+/// Plays [`Haptic`]s for game events and rumbles every connected gamepad with their mix, unless
+/// [`PlayerSettings::rumble`] is off. Gamepads only exist once an input backend for them, e.g.
+/// `bevy_gilrs`, is added; until then the requests go nowhere.
+pub struct HapticsPlugin;
+
+impl Plugin for HapticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Haptics>()
+            .add_systems(Update, (play_block_break_haptics, rumble_gamepads).chain());
+    }
+}
+
+/// Change in a motor's strength below which the running rumble is kept
+const RUMBLE_RESEND_THRESHOLD: f32 = 0.05;
+
+fn play_block_break_haptics(
+    mut edited_reader: EventReader<VoxelEdited>,
+    mut haptics: ResMut<Haptics>,
+) {
+    // Undoing a large paste shouldn't rumble once per voxel
+    let broke = edited_reader
+        .read()
+        .any(|edited| edited.0.old != Voxel::Air && edited.0.new == Voxel::Air);
+    if broke {
+        haptics.play(Haptic::BLOCK_BREAK);
+    }
+}
+
+/// Gamepads can only play a constant rumble for a duration, so the envelope is followed by
+/// replacing the rumble whenever the mix has changed enough
+fn rumble_gamepads(
+    frame_pacing: Res<FramePacing>,
+    settings: Res<PlayerSettings>,
+    mut haptics: ResMut<Haptics>,
+    gamepads: Query<Entity, With<Gamepad>>,
+    mut rumble_writer: EventWriter<GamepadRumbleRequest>,
+    mut playing: Local<RumbleIntensity>,
+) {
+    haptics.update(frame_pacing.smoothed_delta_secs());
+    // The haptics keep playing for the camera shake
+    let intensity = if settings.rumble {
+        haptics.intensity()
+    } else {
+        RumbleIntensity::default()
+    };
+    let changed = (intensity.strong_motor - playing.strong_motor).abs() >= RUMBLE_RESEND_THRESHOLD
+        || (intensity.weak_motor - playing.weak_motor).abs() >= RUMBLE_RESEND_THRESHOLD;
+    let finished = intensity == RumbleIntensity::default() && *playing != intensity;
+    if !changed && !finished {
+        return;
+    }
+    *playing = intensity;
+
+    for gamepad in &gamepads {
+        rumble_writer.send(GamepadRumbleRequest::Stop { gamepad });
+        if intensity != RumbleIntensity::default() {
+            rumble_writer.send(GamepadRumbleRequest::Add {
+                duration: Duration::from_secs_f32(haptics.remaining_secs()),
+                intensity: GamepadRumbleIntensity {
+                    strong_motor: intensity.strong_motor,
+                    weak_motor: intensity.weak_motor,
+                },
+                gamepad,
+            });
+        }
+    }
+}
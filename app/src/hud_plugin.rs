@@ -0,0 +1,199 @@
+use bevy_app::{Plugin, Update};
+use bevy_ecs::{
+    change_detection::DetectChangesMut,
+    event::EventReader,
+    query::With,
+    schedule::IntoSystemConfigs,
+    system::{Res, ResMut, Resource, Single},
+};
+use bevy_input::{
+    keyboard::KeyCode,
+    mouse::{MouseScrollUnit, MouseWheel},
+    ButtonInput,
+};
+use bevy_window::{PrimaryWindow, Window};
+use data::{
+    prelude::{IVec2, UVec2},
+    ui_layout::{Anchor, UiLayout},
+    voxel::Voxel,
+};
+use renderer::hud::{Hud, HudRect};
+
+/// Keeps the [`UiLayout`] up to date with the window and draws the crosshair and [`Hotbar`]
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut bevy_app::App) {
+        app.init_resource::<Hotbar>()
+            .init_resource::<UiLayout>()
+            .add_systems(
+                Update,
+                (select_hotbar_slot, update_ui_layout, build_hud).chain(),
+            );
+    }
+}
+
+/// Voxels the player can place, one of which is selected. Mirrors the player's inventory.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct Hotbar {
+    slots: [Option<Voxel>; Self::SLOT_COUNT],
+    selected: usize,
+}
+
+impl Hotbar {
+    pub const SLOT_COUNT: usize = 9;
+
+    pub fn slots(&self) -> &[Option<Voxel>; Self::SLOT_COUNT] {
+        &self.slots
+    }
+
+    /// Does nothing for a slot past [`Self::SLOT_COUNT`]
+    pub fn set_slot(&mut self, slot: usize, voxel: Option<Voxel>) {
+        if let Some(slot) = self.slots.get_mut(slot) {
+            *slot = voxel;
+        }
+    }
+
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    pub fn select(&mut self, slot: usize) {
+        self.selected = slot % Self::SLOT_COUNT;
+    }
+
+    /// Moves the selection by `steps`, wrapping around both ends
+    pub fn scroll(&mut self, steps: i32) {
+        let slot_count = Self::SLOT_COUNT as i32;
+        self.selected = (self.selected as i32 + steps).rem_euclid(slot_count) as usize;
+    }
+
+    /// The voxel block placement uses
+    pub fn selected_voxel(&self) -> Option<Voxel> {
+        self.slots[self.selected]
+    }
+}
+
+const SLOT_KEYS: [KeyCode; Hotbar::SLOT_COUNT] = [
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+    KeyCode::Digit6,
+    KeyCode::Digit7,
+    KeyCode::Digit8,
+    KeyCode::Digit9,
+];
+
+/// Holding this turns the mouse wheel into camera zoom instead of slot selection
+pub const ZOOM_MODIFIER: KeyCode = KeyCode::ControlLeft;
+
+fn select_hotbar_slot(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut wheel_reader: EventReader<MouseWheel>,
+    mut hotbar: ResMut<Hotbar>,
+) {
+    if let Some(slot) = SLOT_KEYS.iter().position(|key| keys.just_pressed(*key)) {
+        hotbar.select(slot);
+    }
+
+    let zooming = keys.pressed(ZOOM_MODIFIER);
+    for wheel in wheel_reader.read() {
+        if zooming {
+            continue;
+        }
+        let steps = match wheel.unit {
+            MouseScrollUnit::Line => wheel.y.round() as i32,
+            MouseScrollUnit::Pixel => wheel.y.signum() as i32,
+        };
+        // Scrolling down moves right, as in most block games
+        hotbar.scroll(-steps);
+    }
+}
+
+// Sizes are in UI units, see `UiLayout`
+const CROSSHAIR_COLOR: [u8; 4] = [255, 255, 255, 255];
+const CROSSHAIR_LENGTH: u32 = 16;
+const CROSSHAIR_THICKNESS: u32 = 2;
+
+const SLOT_SIZE: u32 = 40;
+const SLOT_GAP: u32 = 4;
+const SLOT_BORDER: u32 = 3;
+const ICON_INSET: u32 = 8;
+const HOTBAR_MARGIN: u32 = 12;
+/// UI units the hotbar takes up from the bottom of the window
+pub const HOTBAR_CLEARANCE: u32 = HOTBAR_MARGIN + SLOT_SIZE;
+const SLOT_COLOR: [u8; 4] = [40, 40, 40, 255];
+const SELECTED_COLOR: [u8; 4] = [230, 230, 230, 255];
+
+fn update_ui_layout(mut layout: ResMut<UiLayout>, window: Single<&Window, With<PrimaryWindow>>) {
+    let updated = UiLayout::new(
+        UVec2::new(window.physical_width(), window.physical_height()),
+        window.scale_factor(),
+    );
+    // Only marked changed on resizes
+    layout.set_if_neq(updated);
+}
+
+pub fn build_hud(hotbar: Res<Hotbar>, layout: Res<UiLayout>, mut hud: ResMut<Hud>) {
+    hud.clear();
+
+    let center = layout.center();
+    let (length, thickness) = (layout.px(CROSSHAIR_LENGTH), layout.px(CROSSHAIR_THICKNESS));
+    hud.push(HudRect::centered(
+        center.x,
+        center.y,
+        length,
+        thickness,
+        CROSSHAIR_COLOR,
+    ));
+    hud.push(HudRect::centered(
+        center.x,
+        center.y,
+        thickness,
+        length,
+        CROSSHAIR_COLOR,
+    ));
+
+    let slot_size = layout.px(SLOT_SIZE);
+    let slot_step = layout.px(SLOT_SIZE + SLOT_GAP);
+    let border = layout.px(SLOT_BORDER);
+    let inset = layout.px(ICON_INSET);
+    let hotbar_width = (Hotbar::SLOT_COUNT as u32 - 1) * slot_step + slot_size;
+    let corner = layout.place(
+        Anchor::Bottom,
+        UVec2::new(hotbar_width, slot_size),
+        IVec2::new(0, HOTBAR_MARGIN as i32),
+    );
+
+    for (slot, voxel) in hotbar.slots().iter().enumerate() {
+        let x = corner.x + (slot as u32 * slot_step) as i32;
+
+        if slot == hotbar.selected() {
+            hud.push(HudRect::new(
+                x - border as i32,
+                corner.y - border as i32,
+                slot_size + 2 * border,
+                slot_size + 2 * border,
+                SELECTED_COLOR,
+            ));
+        }
+        hud.push(HudRect::new(x, corner.y, slot_size, slot_size, SLOT_COLOR));
+
+        // Flat material color until voxels have atlas icons
+        if let Some(voxel) = voxel {
+            let [r, g, b] = voxel
+                .material()
+                .color
+                .map(|c| (c.clamp(0.0, 1.0) * 255.0) as u8);
+            hud.push(HudRect::new(
+                x + inset as i32,
+                corner.y + inset as i32,
+                slot_size.saturating_sub(2 * inset),
+                slot_size.saturating_sub(2 * inset),
+                [r, g, b, 255],
+            ));
+        }
+    }
+}
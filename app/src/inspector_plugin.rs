@@ -0,0 +1,243 @@
+use bevy_app::{Plugin, Update};
+use bevy_ecs::{
+    change_detection::Mut,
+    entity::Entity,
+    query::With,
+    schedule::IntoSystemConfigs,
+    system::{Query, Res, ResMut, Resource, Single},
+};
+use bevy_input::{keyboard::KeyCode, ButtonInput};
+use bevy_window::{PrimaryWindow, Window};
+use data::{
+    camera::CameraFov,
+    inspect::{Field, Inspect},
+    instance::Instance,
+    name::Name,
+    transform::Transform,
+};
+use renderer::hud::{Hud, HudRect};
+
+use crate::{hud_plugin::build_hud, settings_plugin::SettingsMenu};
+
+/// Debug panel that steps through entities and edits their inspectable components. There is
+/// no text rendering yet, so the selected entity's fields are printed whenever they change and
+/// the HUD only shows which entity and field are selected.
+pub struct InspectorPlugin;
+
+impl Plugin for InspectorPlugin {
+    fn build(&self, app: &mut bevy_app::App) {
+        app.init_resource::<Inspector>().add_systems(
+            Update,
+            (navigate_inspector, draw_inspector.after(build_hud)).chain(),
+        );
+    }
+}
+
+pub const INSPECTOR_KEY: KeyCode = KeyCode::F3;
+
+/// Multiplies the nudge step while held
+const FAST_MODIFIER: KeyCode = KeyCode::ShiftRight;
+const NUDGE_STEP: f32 = 0.1;
+const FAST_NUDGE_STEP: f32 = 5.0;
+
+#[derive(Resource, Debug, Default)]
+pub struct Inspector {
+    open: bool,
+    entity: usize,
+    field: usize,
+    entity_count: usize,
+    field_count: usize,
+}
+
+impl Inspector {
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+}
+
+type Inspected<'a> = (
+    Entity,
+    Option<&'a mut Name>,
+    Option<&'a mut Transform>,
+    Option<&'a mut Instance>,
+    Option<&'a mut CameraFov>,
+);
+type InspectedItem<'a> = (
+    Entity,
+    Option<Mut<'a, Name>>,
+    Option<Mut<'a, Transform>>,
+    Option<Mut<'a, Instance>>,
+    Option<Mut<'a, CameraFov>>,
+);
+
+/// The entity's inspectable components in display order, borrowed without triggering change
+/// detection
+fn components<'a>((_, name, transform, instance, fov): &'a InspectedItem) -> Vec<&'a dyn Inspect> {
+    let components: [Option<&dyn Inspect>; 4] = [
+        name.as_deref().map(|c| c as &dyn Inspect),
+        transform.as_deref().map(|c| c as &dyn Inspect),
+        instance.as_deref().map(|c| c as &dyn Inspect),
+        fov.as_deref().map(|c| c as &dyn Inspect),
+    ];
+    components.into_iter().flatten().collect()
+}
+
+/// The `index`-th of [`components`], marking only that component as changed
+fn component_mut<'a>(
+    (_, name, transform, instance, fov): &'a mut InspectedItem,
+    index: usize,
+) -> Option<&'a mut dyn Inspect> {
+    let components: [Option<&mut dyn Inspect>; 4] = [
+        name.as_mut().map(|c| c.as_mut() as &mut dyn Inspect),
+        transform.as_mut().map(|c| c.as_mut() as &mut dyn Inspect),
+        instance.as_mut().map(|c| c.as_mut() as &mut dyn Inspect),
+        fov.as_mut().map(|c| c.as_mut() as &mut dyn Inspect),
+    ];
+    components.into_iter().flatten().nth(index)
+}
+
+fn print_entity(entity: Entity, components: &[&dyn Inspect], selected_field: usize) {
+    println!("{entity}");
+    let mut index = 0;
+    for component in components {
+        println!("  {}", component.type_name());
+        for Field { name, value } in component.fields() {
+            let marker = if index == selected_field { '>' } else { ' ' };
+            println!("  {marker} {name}: {value}");
+            index += 1;
+        }
+    }
+}
+
+fn navigate_inspector(
+    keys: Res<ButtonInput<KeyCode>>,
+    settings_menu: Res<SettingsMenu>,
+    mut inspector: ResMut<Inspector>,
+    mut entities: Query<Inspected>,
+) {
+    if keys.just_pressed(INSPECTOR_KEY) {
+        inspector.open = !inspector.open;
+    }
+    if !inspector.open || settings_menu.is_open() {
+        return;
+    }
+
+    let mut entities: Vec<_> = entities.iter_mut().collect();
+    entities.sort_by_key(|(entity, ..)| *entity);
+    inspector.entity_count = entities.len();
+    if entities.is_empty() {
+        return;
+    }
+
+    let mut changed = keys.just_pressed(INSPECTOR_KEY);
+    if keys.just_pressed(KeyCode::Tab) {
+        inspector.entity += 1;
+        inspector.field = 0;
+        changed = true;
+    }
+    inspector.entity %= entities.len();
+
+    let inspected = &mut entities[inspector.entity];
+    let field_counts: Vec<usize> = components(inspected)
+        .iter()
+        .map(|component| component.fields().len())
+        .collect();
+    let field_count = field_counts.iter().sum();
+    inspector.field_count = field_count;
+    if field_count == 0 {
+        return;
+    }
+
+    if keys.just_pressed(KeyCode::ArrowUp) {
+        inspector.field = (inspector.field + field_count - 1) % field_count;
+        changed = true;
+    }
+    if keys.just_pressed(KeyCode::ArrowDown) {
+        inspector.field += 1;
+        changed = true;
+    }
+    inspector.field %= field_count;
+
+    let steps = keys.just_pressed(KeyCode::ArrowRight) as i32
+        - keys.just_pressed(KeyCode::ArrowLeft) as i32;
+    if steps != 0 {
+        let step = if keys.pressed(FAST_MODIFIER) {
+            FAST_NUDGE_STEP
+        } else {
+            NUDGE_STEP
+        };
+
+        let mut field = inspector.field;
+        let mut component_index = 0;
+        while field >= field_counts[component_index] {
+            field -= field_counts[component_index];
+            component_index += 1;
+        }
+        if let Some(component) = component_mut(inspected, component_index) {
+            let name = component.fields()[field].name;
+            if let Err(e) = component.nudge_field(name, steps, step) {
+                eprintln!("{e}");
+            }
+        }
+        changed = true;
+    }
+
+    if changed {
+        print_entity(inspected.0, &components(inspected), inspector.field);
+    }
+}
+
+const MARGIN: i32 = 16;
+const ENTITY_SIZE: u32 = 8;
+const ENTITY_GAP: u32 = 4;
+const FIELD_WIDTH: u32 = 120;
+const FIELD_HEIGHT: u32 = 6;
+const FIELD_GAP: u32 = 3;
+const IDLE_COLOR: [u8; 4] = [60, 60, 70, 255];
+const SELECTED_COLOR: [u8; 4] = [255, 200, 80, 255];
+
+fn draw_inspector(
+    inspector: Res<Inspector>,
+    mut hud: ResMut<Hud>,
+    window: Single<&Window, With<PrimaryWindow>>,
+) {
+    if !inspector.open {
+        return;
+    }
+
+    let max_columns = ((window.physical_width() as i32 - 2 * MARGIN).max(0) as u32
+        / (ENTITY_SIZE + ENTITY_GAP))
+        .max(1) as usize;
+    for index in 0..inspector.entity_count {
+        let (column, row) = (index % max_columns, index / max_columns);
+        let color = if index == inspector.entity {
+            SELECTED_COLOR
+        } else {
+            IDLE_COLOR
+        };
+        hud.push(HudRect::new(
+            MARGIN + (column as u32 * (ENTITY_SIZE + ENTITY_GAP)) as i32,
+            MARGIN + (row as u32 * (ENTITY_SIZE + ENTITY_GAP)) as i32,
+            ENTITY_SIZE,
+            ENTITY_SIZE,
+            color,
+        ));
+    }
+
+    let rows = inspector.entity_count.div_ceil(max_columns) as u32;
+    let top = MARGIN + (rows * (ENTITY_SIZE + ENTITY_GAP) + ENTITY_GAP) as i32;
+    for field in 0..inspector.field_count {
+        let color = if field == inspector.field {
+            SELECTED_COLOR
+        } else {
+            IDLE_COLOR
+        };
+        hud.push(HudRect::new(
+            MARGIN,
+            top + (field as u32 * (FIELD_HEIGHT + FIELD_GAP)) as i32,
+            FIELD_WIDTH,
+            FIELD_HEIGHT,
+            color,
+        ));
+    }
+}
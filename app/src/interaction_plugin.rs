@@ -0,0 +1,286 @@
+use bevy_app::{App, Plugin, Update};
+use bevy_ecs::{
+    entity::Entity,
+    event::{Event, EventReader, EventWriter},
+    query::With,
+    schedule::{common_conditions::not, IntoSystemConfigs},
+    system::{Res, ResMut, Resource, Single},
+};
+use bevy_input::{keyboard::KeyCode, mouse::MouseButton, ButtonInput};
+use bevy_window::{PrimaryWindow, Window};
+use data::{
+    chunk_map::ChunkMap,
+    edit_history::VoxelEdit,
+    floating_origin::FloatingOrigin,
+    interaction::Interaction,
+    inventory::Inventory,
+    item::ItemRegistry,
+    prelude::{IVec3, Vec3},
+    spring_arm::SpringArm,
+    transform::Transform,
+    voxel::Voxel,
+};
+use renderer::hud::{Hud, HudRect};
+
+use crate::{
+    hud_plugin::{build_hud, Hotbar},
+    loading_plugin::in_game,
+    model_editor_plugin::model_editor_active,
+    player_plugin::{move_player, view_transform, Player},
+    world_plugin::VoxelEdits,
+};
+
+/// Right-clicking a voxel sends an [`Interact`] with the [`Interaction`] its
+/// [`Voxel::interaction`] declares, carried out here: lights are toggled and containers are
+/// opened, shown over the [`Hud`] while the player stays in reach
+pub struct InteractionPlugin;
+
+impl Plugin for InteractionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<OpenContainer>()
+            .add_event::<Interact>()
+            .add_systems(
+                Update,
+                (
+                    (
+                        interact.run_if(in_game).run_if(not(model_editor_active)),
+                        (toggle_lights, open_containers),
+                        (close_containers, move_container_items).chain(),
+                    )
+                        .chain()
+                        .after(move_player),
+                    draw_open_container.after(build_hud),
+                ),
+            );
+    }
+}
+
+/// Sent when `entity` uses the voxel at the world `position`
+#[derive(Event, Debug, Clone, Copy)]
+pub struct Interact {
+    pub entity: Entity,
+    pub position: IVec3,
+    pub voxel: Voxel,
+    pub interaction: Interaction,
+}
+
+/// The container the player has open, at a world position
+#[derive(Resource, Debug, Default)]
+pub struct OpenContainer(pub Option<IVec3>);
+
+pub const INTERACT_BUTTON: MouseButton = MouseButton::Right;
+/// While a container is open, moves the selected hotbar stack into it
+pub const STORE_BUTTON: MouseButton = MouseButton::Left;
+/// While a container is open, moves its first stack into the player's inventory
+pub const TAKE_BUTTON: MouseButton = MouseButton::Middle;
+pub const CLOSE_KEY: KeyCode = KeyCode::KeyE;
+
+/// Voxels from the camera a voxel can be used within
+pub const REACH: f32 = 6.0;
+
+const CONTAINER_COLUMNS: usize = 9;
+const SLOT_SIZE: u32 = 32;
+const SLOT_GAP: u32 = 4;
+const ICON_INSET: u32 = 6;
+const PANEL_PADDING: u32 = 8;
+const PANEL_COLOR: [u8; 4] = [20, 20, 20, 255];
+const SLOT_COLOR: [u8; 4] = [60, 60, 60, 255];
+/// Bar along the bottom of a slot, as long as the stack is full
+const COUNT_BAR_HEIGHT: u32 = 3;
+const COUNT_BAR_COLOR: [u8; 4] = [230, 230, 230, 255];
+
+/// The loaded voxel the camera looks at within [`REACH`]
+fn targeted_voxel(
+    chunks: &ChunkMap,
+    origin: &FloatingOrigin,
+    camera: &Transform,
+) -> Option<(IVec3, Voxel)> {
+    let direction = camera.rotation * Vec3::NEG_Z;
+    let distance = chunks.raycast(origin.origin(), camera.translation, direction, REACH)?;
+    // Just past the border the ray entered the voxel through
+    let inside = camera.translation + direction * (distance + 1e-3);
+    let position = inside.floor().as_ivec3() + origin.origin();
+    Some((position, chunks.voxel(position)?))
+}
+
+fn interact(
+    buttons: Res<ButtonInput<MouseButton>>,
+    chunks: Res<ChunkMap>,
+    origin: Res<FloatingOrigin>,
+    mut interact_writer: EventWriter<Interact>,
+    player: Single<(Entity, &Transform, Option<&SpringArm>), With<Player>>,
+) {
+    if !buttons.just_pressed(INTERACT_BUTTON) {
+        return;
+    }
+    let (entity, transform, spring_arm) = *player;
+    let camera = view_transform(transform, spring_arm);
+    let Some((position, voxel)) = targeted_voxel(&chunks, &origin, &camera) else {
+        return;
+    };
+    if let Some(interaction) = voxel.interaction() {
+        interact_writer.send(Interact {
+            entity,
+            position,
+            voxel,
+            interaction,
+        });
+    }
+}
+
+fn toggle_lights(mut interact_reader: EventReader<Interact>, mut edits: VoxelEdits) {
+    for interact in interact_reader.read() {
+        let Interaction::ToggleLight { to } = interact.interaction else {
+            continue;
+        };
+        // The voxel may have changed since the click
+        if edits.chunks.voxel(interact.position) != Some(interact.voxel) {
+            continue;
+        }
+        edits.chunks.set_voxel(interact.position, to);
+        edits.commit(vec![VoxelEdit {
+            position: interact.position,
+            old: interact.voxel,
+            new: to,
+        }]);
+    }
+}
+
+/// Opens the used container, or closes it when used again
+fn open_containers(
+    mut interact_reader: EventReader<Interact>,
+    mut chunks: ResMut<ChunkMap>,
+    mut open: ResMut<OpenContainer>,
+) {
+    for interact in interact_reader.read() {
+        let Interaction::OpenContainer { slots } = interact.interaction else {
+            continue;
+        };
+        if open.0 == Some(interact.position) {
+            open.0 = None;
+            continue;
+        }
+        if chunks
+            .block_entity::<Inventory>(interact.position)
+            .is_none()
+        {
+            chunks.insert_block_entity(interact.position, Inventory::new(slots));
+        }
+        open.0 = Some(interact.position);
+    }
+}
+
+/// Closes the container once it is gone, out of reach or dismissed
+fn close_containers(
+    keys: Res<ButtonInput<KeyCode>>,
+    chunks: Res<ChunkMap>,
+    origin: Res<FloatingOrigin>,
+    mut open: ResMut<OpenContainer>,
+    player: Single<&Transform, With<Player>>,
+) {
+    let Some(position) = open.0 else {
+        return;
+    };
+    let center = origin.to_translation(position.as_vec3() + 0.5);
+    let out_of_reach = center.distance(player.translation) > REACH + 1.0;
+    if keys.just_pressed(CLOSE_KEY)
+        || out_of_reach
+        || chunks.block_entity::<Inventory>(position).is_none()
+    {
+        open.0 = None;
+    }
+}
+
+fn move_container_items(
+    buttons: Res<ButtonInput<MouseButton>>,
+    registry: Res<ItemRegistry>,
+    hotbar: Res<Hotbar>,
+    open: Res<OpenContainer>,
+    mut chunks: ResMut<ChunkMap>,
+    player: Single<&mut Inventory, With<Player>>,
+) {
+    let Some(position) = open.0 else {
+        return;
+    };
+    let Some(container) = chunks.block_entity_mut::<Inventory>(position) else {
+        return;
+    };
+    let mut inventory = player.into_inner();
+    // Whatever doesn't fit stays where it was
+    let (from, to, slot) = if buttons.just_pressed(STORE_BUTTON) {
+        (&mut *inventory, container, hotbar.selected())
+    } else if buttons.just_pressed(TAKE_BUTTON) {
+        let Some(slot) = container.slots().iter().position(Option::is_some) else {
+            return;
+        };
+        (container, &mut *inventory, slot)
+    } else {
+        return;
+    };
+    let Some(stack) = from.slot(slot) else {
+        return;
+    };
+    let left_over = to.add(stack.item, stack.count, &registry);
+    from.take(slot, stack.count - left_over);
+}
+
+fn draw_open_container(
+    open: Res<OpenContainer>,
+    chunks: Res<ChunkMap>,
+    registry: Res<ItemRegistry>,
+    mut hud: ResMut<Hud>,
+    window: Single<&Window, With<PrimaryWindow>>,
+) {
+    let Some(container) = open
+        .0
+        .and_then(|position| chunks.block_entity::<Inventory>(position))
+    else {
+        return;
+    };
+    let slots = container.slots();
+    let rows = slots.len().div_ceil(CONTAINER_COLUMNS) as u32;
+    let columns = slots.len().min(CONTAINER_COLUMNS) as u32;
+    let width = columns * (SLOT_SIZE + SLOT_GAP) - SLOT_GAP;
+    let height = rows * (SLOT_SIZE + SLOT_GAP) - SLOT_GAP;
+    let left = (window.physical_width() as i32 - width as i32) / 2;
+    let top = (window.physical_height() as i32 - height as i32) / 2;
+
+    hud.push(HudRect::new(
+        left - PANEL_PADDING as i32,
+        top - PANEL_PADDING as i32,
+        width + 2 * PANEL_PADDING,
+        height + 2 * PANEL_PADDING,
+        PANEL_COLOR,
+    ));
+    for (slot, stack) in slots.iter().enumerate() {
+        let x = left + ((slot % CONTAINER_COLUMNS) as u32 * (SLOT_SIZE + SLOT_GAP)) as i32;
+        let y = top + ((slot / CONTAINER_COLUMNS) as u32 * (SLOT_SIZE + SLOT_GAP)) as i32;
+        hud.push(HudRect::new(x, y, SLOT_SIZE, SLOT_SIZE, SLOT_COLOR));
+        let Some(stack) = stack else {
+            continue;
+        };
+
+        // Flat material color until voxels have atlas icons, like the hotbar
+        if let Some(voxel) = registry.get(stack.item).and_then(|item| item.voxel) {
+            let [r, g, b] = voxel
+                .material()
+                .color
+                .map(|c| (c.clamp(0.0, 1.0) * 255.0) as u8);
+            hud.push(HudRect::new(
+                x + ICON_INSET as i32,
+                y + ICON_INSET as i32,
+                SLOT_SIZE - 2 * ICON_INSET,
+                SLOT_SIZE - 2 * ICON_INSET,
+                [r, g, b, 255],
+            ));
+        }
+        let fill = stack.count as u32 * SLOT_SIZE / registry.max_stack(stack.item) as u32;
+        hud.push(HudRect::new(
+            x,
+            y + (SLOT_SIZE - COUNT_BAR_HEIGHT) as i32,
+            fill.clamp(1, SLOT_SIZE),
+            COUNT_BAR_HEIGHT,
+            COUNT_BAR_COLOR,
+        ));
+    }
+}
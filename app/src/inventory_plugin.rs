@@ -0,0 +1,102 @@
+use bevy_app::{Plugin, PostStartup, Update};
+use bevy_ecs::{
+    entity::Entity,
+    event::{Event, EventReader},
+    query::{Changed, With},
+    schedule::IntoSystemConfigs,
+    system::{Commands, Query, Res, ResMut, Single},
+};
+use data::{
+    inventory::{Inventory, ItemStack},
+    item::{ItemDrop, ItemRegistry},
+    transform::Transform,
+    voxel::Voxel,
+};
+
+use crate::{
+    hud_plugin::Hotbar,
+    player_plugin::Player,
+    save_plugin::{PersistenceAppExt, Persistent},
+};
+
+pub struct InventoryPlugin;
+
+impl Plugin for InventoryPlugin {
+    fn build(&self, app: &mut bevy_app::App) {
+        app.init_resource::<ItemRegistry>()
+            .add_event::<BlockBroken>()
+            .register_persistent::<Inventory>("inventory")
+            .register_persistent::<ItemDrop>("item_drop")
+            .add_systems(PostStartup, stock_player_inventory)
+            .add_systems(Update, (collect_broken_blocks, sync_hotbar).chain());
+    }
+}
+
+/// Sent when `entity` breaks a voxel, so its drop can go into the entity's inventory. Undos,
+/// fills and other edits that happen to leave air don't send it.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct BlockBroken {
+    pub entity: Entity,
+    pub voxel: Voxel,
+}
+
+const STARTING_STACK: u16 = 64;
+
+fn stock_player_inventory(
+    mut commands: Commands,
+    registry: Res<ItemRegistry>,
+    player: Single<Entity, With<Player>>,
+) {
+    let mut inventory = Inventory::default();
+    for voxel in Voxel::ALL {
+        if let Some(item) = registry.voxel_drop(voxel) {
+            inventory.add(item, STARTING_STACK, &registry);
+        }
+    }
+    commands.entity(*player).insert(inventory);
+}
+
+fn collect_broken_blocks(
+    mut commands: Commands,
+    mut broken_reader: EventReader<BlockBroken>,
+    registry: Res<ItemRegistry>,
+    mut inventories: Query<(&mut Inventory, &Transform)>,
+) {
+    for broken in broken_reader.read() {
+        let Some(item) = registry.voxel_drop(broken.voxel) else {
+            continue;
+        };
+        let Ok((mut inventory, transform)) = inventories.get_mut(broken.entity) else {
+            continue;
+        };
+        // Anything that doesn't fit is dropped where the entity stands
+        let left_over = inventory.add(item, 1, &registry);
+        if left_over > 0 {
+            commands.spawn((
+                ItemDrop(ItemStack::new(item, left_over)),
+                Transform::from_translation(transform.translation),
+                Persistent::Spawned,
+            ));
+        }
+    }
+}
+
+fn sync_hotbar(
+    registry: Res<ItemRegistry>,
+    mut hotbar: ResMut<Hotbar>,
+    inventory: Query<&Inventory, (With<Player>, Changed<Inventory>)>,
+) {
+    let Ok(inventory) = inventory.get_single() else {
+        return;
+    };
+    // Slots past a small inventory's end are cleared
+    for slot in 0..Hotbar::SLOT_COUNT {
+        let voxel = inventory
+            .hotbar()
+            .get(slot)
+            .copied()
+            .flatten()
+            .and_then(|stack| registry.get(stack.item)?.voxel);
+        hotbar.set_slot(slot, voxel);
+    }
+}
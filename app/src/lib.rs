@@ -1,4 +1,67 @@
+#[cfg(feature = "client")]
+pub mod animation_plugin;
+#[cfg(feature = "client")]
+pub mod chat_plugin;
+#[cfg(feature = "client")]
+pub mod chunk_heatmap_plugin;
+#[cfg(feature = "client")]
+pub mod config;
+#[cfg(feature = "client")]
+pub mod default_plugins;
+#[cfg(feature = "client")]
+pub mod diagnostics_plugin;
+#[cfg(feature = "client")]
+pub mod edit_prediction_plugin;
+#[cfg(feature = "client")]
+pub mod frame_dump_plugin;
+pub mod frame_pacing_plugin;
+#[cfg(feature = "client")]
+pub mod haptics_plugin;
+#[cfg(feature = "client")]
+pub mod hud_plugin;
+#[cfg(feature = "client")]
+pub mod inspector_plugin;
+#[cfg(feature = "client")]
+pub mod interaction_plugin;
+#[cfg(feature = "client")]
+pub mod inventory_plugin;
+#[cfg(feature = "client")]
+pub mod loading_plugin;
+pub mod localization;
+#[cfg(feature = "client")]
+pub mod minimap_plugin;
+#[cfg(feature = "client")]
+pub mod model_editor_plugin;
+pub mod mods_plugin;
+#[cfg(feature = "client")]
+pub mod notification_plugin;
+#[cfg(feature = "client")]
+pub mod npc_plugin;
+#[cfg(feature = "client")]
+pub mod particle_plugin;
+#[cfg(feature = "client")]
+pub mod photo_mode_plugin;
+#[cfg(feature = "client")]
 pub mod player_plugin;
+pub mod prefab_plugin;
+pub mod profiler;
+pub mod protocol;
+#[cfg(feature = "client")]
 pub mod render_plugin;
+#[cfg(feature = "renderdoc")]
+pub mod renderdoc_plugin;
+pub mod save_migration;
+pub mod save_plugin;
+#[cfg(feature = "client")]
+pub mod schematic_plugin;
+#[cfg(feature = "client")]
+pub mod settings_plugin;
+pub mod simulation_plugin;
+#[cfg(feature = "client")]
+pub mod streaming_plugin;
+pub mod task_plugin;
 pub mod time_plugin;
+#[cfg(feature = "client")]
 pub mod window_plugin;
+#[cfg(feature = "client")]
+pub mod world_plugin;
@@ -0,0 +1,171 @@
+use std::{collections::HashMap, time::Instant};
+
+use bevy_app::{App, Plugin, Update};
+use bevy_ecs::{
+    query::With,
+    schedule::IntoSystemConfigs,
+    system::{Res, ResMut, Resource, Single},
+};
+use bevy_state::{
+    app::AppExtStates,
+    condition::in_state,
+    state::{NextState, State, States},
+};
+use bevy_window::{PrimaryWindow, Window};
+use renderer::hud::{Hud, HudRect};
+
+use crate::{hud_plugin::build_hud, localization::Localization};
+
+/// Starts the app in [`AppState::Loading`], where the work that would otherwise stall the
+/// first frames, like compiling pipelines and generating the chunks around the player, runs
+/// while a progress bar is shown. Plugins report their work to [`LoadingProgress`] and the
+/// game starts once all of it is done.
+pub struct LoadingPlugin;
+
+impl Plugin for LoadingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_state::<AppState>()
+            .init_resource::<LoadingProgress>()
+            .add_systems(
+                Update,
+                (finish_loading, draw_loading_bar.after(build_hud))
+                    .run_if(in_state(AppState::Loading)),
+            );
+    }
+}
+
+#[derive(States, Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum AppState {
+    #[default]
+    Loading,
+    InGame,
+}
+
+/// Run condition for gameplay systems, which also holds when the plugin isn't added
+pub fn in_game(state: Option<Res<State<AppState>>>) -> bool {
+    state.is_none_or(|state| *state.get() == AppState::InGame)
+}
+
+/// How far along each piece of loading work is. Work must be registered with
+/// [`Self::expect`] during `Startup`, so loading can't finish before it was even started.
+#[derive(Resource, Debug)]
+pub struct LoadingProgress {
+    /// Done and total units of each piece of work
+    work: HashMap<&'static str, (u32, u32)>,
+    started: Instant,
+}
+
+impl Default for LoadingProgress {
+    fn default() -> Self {
+        Self {
+            work: HashMap::new(),
+            started: Instant::now(),
+        }
+    }
+}
+
+impl LoadingProgress {
+    pub fn expect(&mut self, work: &'static str, total: u32) {
+        self.work.insert(work, (0, total));
+    }
+
+    /// Also changes the total, for work only sized once it starts
+    pub fn set(&mut self, work: &'static str, done: u32, total: u32) {
+        self.work.insert(work, (done.min(total), total));
+    }
+
+    /// Every piece of work counts the same, however many units it has
+    pub fn fraction(&self) -> f32 {
+        if self.work.is_empty() {
+            return 1.0;
+        }
+        let sum: f32 = self
+            .work
+            .values()
+            .map(|(done, total)| match total {
+                0 => 1.0,
+                total => *done as f32 / *total as f32,
+            })
+            .sum();
+        sum / self.work.len() as f32
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.work.values().all(|(done, total)| done >= total)
+    }
+}
+
+fn finish_loading(
+    progress: Res<LoadingProgress>,
+    localization: Res<Localization>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if progress.is_done() {
+        let seconds = format!("{:.1}", progress.started.elapsed().as_secs_f32());
+        println!(
+            "{}",
+            localization.format("loaded-in", &[("seconds", seconds.into())])
+        );
+        next_state.set(AppState::InGame);
+    }
+}
+
+const LOADING_BAR_WIDTH: u32 = 300;
+const LOADING_BAR_HEIGHT: u32 = 8;
+const LOADING_BAR_BACKGROUND: [u8; 4] = [40, 40, 40, 255];
+const LOADING_BAR_COLOR: [u8; 4] = [230, 230, 230, 255];
+
+/// Replaces the HUD with a bar in the middle of the screen
+fn draw_loading_bar(
+    progress: Res<LoadingProgress>,
+    mut hud: ResMut<Hud>,
+    window: Single<&Window, With<PrimaryWindow>>,
+) {
+    hud.clear();
+    let left = (window.physical_width() as i32 - LOADING_BAR_WIDTH as i32) / 2;
+    let top = (window.physical_height() as i32 - LOADING_BAR_HEIGHT as i32) / 2;
+    hud.push(HudRect::new(
+        left,
+        top,
+        LOADING_BAR_WIDTH,
+        LOADING_BAR_HEIGHT,
+        LOADING_BAR_BACKGROUND,
+    ));
+    hud.push(HudRect::new(
+        left,
+        top,
+        (progress.fraction() * LOADING_BAR_WIDTH as f32) as u32,
+        LOADING_BAR_HEIGHT,
+        LOADING_BAR_COLOR,
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn progress_counts_each_piece_of_work_the_same() {
+        let mut progress = LoadingProgress::default();
+        assert_eq!(progress.fraction(), 1.0);
+        assert!(progress.is_done());
+
+        progress.expect("pipelines", 0);
+        assert_eq!(progress.fraction(), 1.0);
+        assert!(progress.is_done());
+
+        progress.expect("chunks", 4);
+        assert_eq!(progress.fraction(), 0.5);
+        assert!(!progress.is_done());
+
+        progress.set("chunks", 2, 4);
+        assert_eq!(progress.fraction(), 0.75);
+        assert!(!progress.is_done());
+
+        // Done is clamped to the total
+        progress.set("chunks", 9, 4);
+        assert_eq!(progress.work["chunks"], (4, 4));
+        assert_eq!(progress.fraction(), 1.0);
+        assert!(progress.is_done());
+    }
+}
@@ -0,0 +1,150 @@
+use std::{
+    error::Error,
+    fs,
+    path::PathBuf,
+    sync::{Arc, LazyLock},
+};
+
+use bevy_app::{App, Plugin};
+use bevy_ecs::system::Resource;
+use fluent_bundle::{concurrent::FluentBundle, FluentArgs, FluentResource, FluentValue};
+use unic_langid::LanguageIdentifier;
+
+/// Directory the string tables are read from, relative to the working directory, with one
+/// `<language>.ftl` file per language
+pub const LOCALES_DIR: &str = "locales";
+
+/// Built into the binary, so every message has text even without the `locales` directory
+pub const DEFAULT_LANGUAGE: &str = "en-US";
+const DEFAULT_TABLE: &str = include_str!("../../locales/en-US.ftl");
+
+/// Inserts the [`Localization`] for [`DEFAULT_LANGUAGE`] unless another one was inserted
+/// already, e.g. from the config
+pub struct LocalizationPlugin;
+
+impl Plugin for LocalizationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Localization>();
+    }
+}
+
+/// Text shown to the player, looked up by message id in a [Fluent](https://projectfluent.org)
+/// string table. Messages missing from the selected language fall back to
+/// [`DEFAULT_LANGUAGE`], and ids missing from both are shown as they are. Cheap to clone,
+/// e.g. into a task that reports its own result.
+#[derive(Resource, Clone)]
+pub struct Localization {
+    /// `None` for the default language
+    language: Option<String>,
+    bundle: Option<Arc<FluentBundle<FluentResource>>>,
+    fallback: Arc<FluentBundle<FluentResource>>,
+}
+
+static DEFAULT_BUNDLE: LazyLock<Arc<FluentBundle<FluentResource>>> = LazyLock::new(|| {
+    let language: LanguageIdentifier = DEFAULT_LANGUAGE.parse().unwrap();
+    Arc::new(bundle(language, DEFAULT_TABLE.to_owned()).unwrap())
+});
+
+impl Default for Localization {
+    fn default() -> Self {
+        Self {
+            language: None,
+            bundle: None,
+            fallback: DEFAULT_BUNDLE.clone(),
+        }
+    }
+}
+
+impl Localization {
+    /// Reads the string table of `language` from [`LOCALES_DIR`]. `None` and
+    /// [`DEFAULT_LANGUAGE`] need no file.
+    pub fn load(language: Option<&str>) -> Result<Self, Box<dyn Error>> {
+        let Some(language) = language.filter(|language| *language != DEFAULT_LANGUAGE) else {
+            return Ok(Self::default());
+        };
+        let identifier: LanguageIdentifier = language.parse()?;
+        let path = PathBuf::from(LOCALES_DIR).join(format!("{language}.ftl"));
+        let table = fs::read_to_string(&path)
+            .map_err(|e| format!("could not read {}: {e}", path.display()))?;
+        Ok(Self {
+            language: Some(language.to_owned()),
+            bundle: Some(Arc::new(bundle(identifier, table)?)),
+            fallback: DEFAULT_BUNDLE.clone(),
+        })
+    }
+
+    /// The language passed to [`Self::load`]; `None` for the default one
+    pub fn language(&self) -> Option<&str> {
+        self.language.as_deref()
+    }
+
+    /// A message without arguments
+    pub fn text(&self, id: &str) -> String {
+        self.format(id, &[])
+    }
+
+    /// A message with its `{ $name }` arguments filled in
+    pub fn format(&self, id: &str, args: &[(&str, FluentValue)]) -> String {
+        let args: FluentArgs = args.iter().cloned().collect();
+        self.bundle
+            .iter()
+            .chain([&self.fallback])
+            .find_map(|bundle| {
+                let pattern = bundle.get_message(id)?.value()?;
+                let mut errors = Vec::new();
+                Some(
+                    bundle
+                        .format_pattern(pattern, Some(&args), &mut errors)
+                        .into_owned(),
+                )
+            })
+            .unwrap_or_else(|| id.to_owned())
+    }
+}
+
+fn bundle(
+    language: LanguageIdentifier,
+    table: String,
+) -> Result<FluentBundle<FluentResource>, Box<dyn Error>> {
+    let resource = FluentResource::try_new(table)
+        .map_err(|(_, errors)| format!("invalid string table: {errors:?}"))?;
+    let mut bundle = FluentBundle::new_concurrent(vec![language]);
+    // Isolation marks only help bidirectional text in a UI, and show up in a terminal
+    bundle.set_use_isolating(false);
+    bundle
+        .add_resource(resource)
+        .map_err(|errors| format!("invalid string table: {errors:?}"))?;
+    Ok(bundle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_fall_back_to_the_default_language() {
+        let english = Localization::default();
+        assert_eq!(
+            english.format("copied-voxels", &[("voxels", 12.into())]),
+            "Copied 12 voxels"
+        );
+        assert_eq!(english.text("no-such-message"), "no-such-message");
+
+        let german = Localization {
+            language: Some("de".to_owned()),
+            bundle: Some(Arc::new(
+                bundle(
+                    "de".parse().unwrap(),
+                    "copied-voxels = { $voxels } Voxel kopiert\n".to_owned(),
+                )
+                .unwrap(),
+            )),
+            ..Default::default()
+        };
+        assert_eq!(
+            german.format("copied-voxels", &[("voxels", 12.into())]),
+            "12 Voxel kopiert"
+        );
+        assert_eq!(german.text("goodbye"), english.text("goodbye"));
+    }
+}
@@ -0,0 +1,224 @@
+use std::{cmp::Ordering, collections::HashSet};
+
+use bevy_app::{App, Plugin, Update};
+use bevy_ecs::{
+    event::EventReader,
+    query::With,
+    schedule::IntoSystemConfigs,
+    system::{Local, Res, ResMut, Resource, Single},
+};
+use bevy_input::{keyboard::KeyCode, ButtonInput};
+use data::{
+    chunk_map::ChunkMap,
+    floating_origin::FloatingOrigin,
+    minimap::{MapColumn, Minimap},
+    prelude::{IVec2, IVec3, UVec2, Vec2, Vec3},
+    transform::Transform,
+    ui_layout::{Anchor, UiLayout},
+};
+use renderer::hud::{Hud, HudRect};
+
+use crate::{hud_plugin::build_hud, player_plugin::Player, simulation_plugin::VoxelChanged};
+
+/// Keeps the [`Minimap`] surveyed as chunks load and voxels change, and draws it north up
+/// around the player in the top right corner of the [`Hud`], or larger in the middle of the
+/// screen after pressing [`MAP_KEY`]
+pub struct MinimapPlugin;
+
+impl Plugin for MinimapPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Minimap>()
+            .init_resource::<MapView>()
+            .add_systems(
+                Update,
+                (
+                    toggle_map,
+                    (survey_loaded_chunks, survey_changed_voxels),
+                    draw_map.after(build_hud),
+                )
+                    .chain(),
+            );
+    }
+}
+
+pub const MAP_KEY: KeyCode = KeyCode::KeyM;
+
+#[derive(Resource, Debug, Default)]
+pub struct MapView {
+    /// Showing the large map in the middle of the screen instead of the corner one
+    pub full: bool,
+}
+
+/// Newly loaded chunks surveyed per frame; the rest wait for the next frames
+const MAX_SURVEYS_PER_FRAME: usize = 8;
+
+/// Columns across the corner map and the UI units each is drawn as
+const MINIMAP_COLUMNS: i32 = 64;
+const MINIMAP_COLUMN_SIZE: u32 = 2;
+const MINIMAP_MARGIN: i32 = 12;
+
+const FULL_MAP_COLUMNS: i32 = 256;
+/// Largest side of the full map in pixels, so it fits in the HUD's staging buffer at any
+/// window size
+const FULL_MAP_MAX_SIZE: u32 = 1024;
+
+const MAP_BORDER: u32 = 2;
+const BORDER_COLOR: [u8; 4] = [20, 20, 20, 255];
+const UNEXPLORED_COLOR: [u8; 4] = [50, 50, 50, 255];
+const PLAYER_COLOR: [u8; 4] = [255, 255, 255, 255];
+const PLAYER_SIZE: u32 = 4;
+/// Dots drawn from the player marker in the direction the player faces, this many UI units
+/// apart
+const HEADING_DOTS: u32 = 3;
+const HEADING_SPACING: f32 = 4.0;
+const HEADING_SIZE: u32 = 2;
+
+/// Columns lower than the one to their north are shaded darker and higher ones lighter, so
+/// the terrain's relief shows
+const SLOPE_SHADE: f32 = 0.2;
+
+fn toggle_map(keys: Res<ButtonInput<KeyCode>>, mut view: ResMut<MapView>) {
+    if keys.just_pressed(MAP_KEY) {
+        view.full = !view.full;
+    }
+}
+
+/// Surveys chunks loaded since the last frame, however they were loaded, and again after
+/// being unloaded and loaded back
+fn survey_loaded_chunks(
+    chunks: Res<ChunkMap>,
+    mut minimap: ResMut<Minimap>,
+    mut surveyed: Local<HashSet<IVec3>>,
+) {
+    surveyed.retain(|chunk| chunks.contains(*chunk));
+    let unsurveyed: Vec<IVec3> = chunks
+        .chunks()
+        .map(|(chunk, _)| chunk)
+        .filter(|chunk| !surveyed.contains(chunk))
+        .take(MAX_SURVEYS_PER_FRAME)
+        .collect();
+    for chunk in unsurveyed {
+        minimap.survey_chunk(&chunks, chunk);
+        surveyed.insert(chunk);
+    }
+}
+
+fn survey_changed_voxels(
+    chunks: Res<ChunkMap>,
+    mut minimap: ResMut<Minimap>,
+    mut changed_reader: EventReader<VoxelChanged>,
+) {
+    for changed in changed_reader.read() {
+        minimap.survey_column(&chunks, changed.position);
+    }
+}
+
+fn draw_map(
+    minimap: Res<Minimap>,
+    view: Res<MapView>,
+    origin: Res<FloatingOrigin>,
+    layout: Res<UiLayout>,
+    mut hud: ResMut<Hud>,
+    player: Single<&Transform, With<Player>>,
+) {
+    let (columns, column_size, anchor, margin) = if view.full {
+        let fit = layout.window_size.min_element().min(FULL_MAP_MAX_SIZE) * 9 / 10;
+        (
+            FULL_MAP_COLUMNS,
+            (fit / FULL_MAP_COLUMNS as u32).max(1),
+            Anchor::Center,
+            IVec2::ZERO,
+        )
+    } else {
+        (
+            MINIMAP_COLUMNS,
+            layout.px(MINIMAP_COLUMN_SIZE),
+            Anchor::TopRight,
+            IVec2::splat(MINIMAP_MARGIN),
+        )
+    };
+    let size = columns as u32 * column_size;
+    let IVec2 { x: left, y: top } = layout.place(anchor, UVec2::splat(size), margin);
+    let border = layout.px(MAP_BORDER);
+
+    hud.push(HudRect::new(
+        left - border as i32,
+        top - border as i32,
+        size + 2 * border,
+        size + 2 * border,
+        BORDER_COLOR,
+    ));
+    hud.push(HudRect::new(left, top, size, size, UNEXPLORED_COLOR));
+
+    let position = origin.world_voxel(player.translation);
+    let corner = IVec2::new(position.x, position.z) - columns / 2;
+    for row in 0..columns {
+        let z = corner.y + row;
+        let y = top + row * column_size as i32;
+        // Runs of one color are drawn as one rect
+        let mut run: Option<(i32, [u8; 4])> = None;
+        for column in 0..=columns {
+            let color = (column < columns)
+                .then(|| {
+                    let x = corner.x + column;
+                    let north = minimap.column(x, z - 1);
+                    minimap.column(x, z).map(|column| shade(column, north))
+                })
+                .flatten();
+            if run.is_some_and(|(_, run_color)| Some(run_color) == color) {
+                continue;
+            }
+            if let Some((start, run_color)) = run.take() {
+                hud.push(HudRect::new(
+                    left + start * column_size as i32,
+                    y,
+                    (column - start) as u32 * column_size,
+                    column_size,
+                    run_color,
+                ));
+            }
+            run = color.map(|color| (column, color));
+        }
+    }
+
+    let center = Vec2::new(
+        left as f32 + (columns / 2) as f32 * column_size as f32,
+        top as f32 + (columns / 2) as f32 * column_size as f32,
+    ) + column_size as f32 * 0.5;
+    hud.push(HudRect::centered(
+        center.x as i32,
+        center.y as i32,
+        layout.px(PLAYER_SIZE),
+        layout.px(PLAYER_SIZE),
+        PLAYER_COLOR,
+    ));
+    // North is up, so the map's x and y are the world's x and z
+    let forward = player.rotation * Vec3::NEG_Z;
+    let heading = Vec2::new(forward.x, forward.z).normalize_or_zero();
+    for dot in 1..=HEADING_DOTS {
+        let at = center + heading * layout.px_f32(HEADING_SPACING) * dot as f32;
+        hud.push(HudRect::centered(
+            at.x.round() as i32,
+            at.y.round() as i32,
+            layout.px(HEADING_SIZE),
+            layout.px(HEADING_SIZE),
+            PLAYER_COLOR,
+        ));
+    }
+}
+
+/// The column's material color, shaded by the slope towards its `north` neighbor
+fn shade(column: MapColumn, north: Option<MapColumn>) -> [u8; 4] {
+    // Up is -Y
+    let shade = match north.map(|north| north.y.cmp(&column.y)) {
+        Some(Ordering::Less) => 1.0 - SLOPE_SHADE,
+        Some(Ordering::Greater) => 1.0 + SLOPE_SHADE,
+        _ => 1.0,
+    };
+    let [r, g, b] = column
+        .voxel
+        .material()
+        .color
+        .map(|c| ((c * shade).clamp(0.0, 1.0) * 255.0) as u8);
+    [r, g, b, 255]
+}
@@ -0,0 +1,291 @@
+use std::{
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bevy_app::{App, Plugin, Update};
+use bevy_ecs::{
+    entity::Entity,
+    event::EventWriter,
+    query::With,
+    schedule::{common_conditions::not, IntoSystemConfigs},
+    system::{Res, ResMut, Resource, Single},
+};
+use bevy_input::{keyboard::KeyCode, mouse::MouseButton, ButtonInput};
+use data::{
+    chunk_map::ChunkMap,
+    edit_history::VoxelEdit,
+    floating_origin::FloatingOrigin,
+    notification::{Notifications, Severity},
+    prelude::{IVec3, Quat, Vec3},
+    schematic::{Region, Schematic, SchematicFile},
+    spring_arm::SpringArm,
+    transform::Transform,
+    vox::encode_vox,
+    voxel::Voxel,
+    voxel_block::VoxelBlock,
+};
+
+use crate::{
+    hud_plugin::Hotbar,
+    inventory_plugin::BlockBroken,
+    localization::Localization,
+    photo_mode_plugin::photo_mode_active,
+    player_plugin::{move_player, view_transform, Player},
+    schematic_plugin::{save_schematic, WorldEdit},
+    task_plugin::{TaskGroup, TaskPools},
+    time_plugin::Time,
+    world_plugin::{rebase_origin, teleport, Teleport, VoxelEdits},
+};
+
+/// A workbench for small voxel models, sculpted in the one [`VoxelBlock`] at [`MODEL_CHUNK`],
+/// high above the terrain. Entering stops time, moves the player in front of the block and
+/// selects it for the schematic tools. The left mouse button breaks voxels, into the player's
+/// inventory, and the right one places the hotbar's, only inside the block; every edit can be undone like any other, and
+/// photo mode previews the model path traced.
+///
+/// [`SAVE_MODEL_KEY`] writes the model, trimmed to its voxels, to [`MODELS_DIR`] both as a
+/// schematic and as a MagicaVoxel `.vox`, and copies it to the schematic clipboard so it can be
+/// pasted into the world after leaving.
+pub struct ModelEditorPlugin;
+
+impl Plugin for ModelEditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ModelEditor>().add_systems(
+            Update,
+            (
+                toggle_model_editor
+                    .run_if(not(photo_mode_active))
+                    .before(teleport),
+                (keep_time_stopped, save_model)
+                    .run_if(model_editor_active)
+                    .after(move_player),
+                sculpt
+                    .run_if(model_editor_active)
+                    .after(move_player)
+                    .after(rebase_origin),
+            ),
+        );
+    }
+}
+
+pub const MODEL_EDITOR_KEY: KeyCode = KeyCode::F11;
+pub const SAVE_MODEL_KEY: KeyCode = KeyCode::Enter;
+pub const BREAK_BUTTON: MouseButton = MouseButton::Left;
+pub const PLACE_BUTTON: MouseButton = MouseButton::Right;
+
+/// Relative to the working directory
+pub const MODELS_DIR: &str = "models";
+
+/// Far up, where up is -Y, so terrain never reaches the model
+pub const MODEL_CHUNK: IVec3 = IVec3::new(0, -64, 0);
+
+/// How far in front of the model's center the camera starts. Aiming at nothing places a voxel
+/// this far ahead, to start a model from.
+const VIEW_DISTANCE: f32 = VoxelBlock::WIDTH as f32 * 1.25;
+/// Enough to reach the far side of the model from where the camera starts
+const SCULPT_REACH: f32 = VIEW_DISTANCE * 2.0;
+
+/// World voxels of the block models are sculpted in
+pub fn model_region() -> Region {
+    let min = MODEL_CHUNK * VoxelBlock::WIDTH as i32;
+    Region::from_corners(min, min + VoxelBlock::WIDTH as i32 - 1)
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct ModelEditor {
+    /// World position, rotation and schematic selection to restore on leaving; `Some` while
+    /// editing
+    saved: Option<(Vec3, Quat, [Option<IVec3>; 2])>,
+}
+
+impl ModelEditor {
+    pub const fn is_active(&self) -> bool {
+        self.saved.is_some()
+    }
+}
+
+/// Run condition that also works when the plugin isn't added
+pub fn model_editor_active(editor: Option<Res<ModelEditor>>) -> bool {
+    editor.is_some_and(|editor| editor.is_active())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn toggle_model_editor(
+    keys: Res<ButtonInput<KeyCode>>,
+    origin: Res<FloatingOrigin>,
+    localization: Res<Localization>,
+    notifications: Option<Res<Notifications>>,
+    mut editor: ResMut<ModelEditor>,
+    mut edit: ResMut<WorldEdit>,
+    mut time: ResMut<Time>,
+    mut teleport_writer: EventWriter<Teleport>,
+    player: Single<&mut Transform, With<Player>>,
+) {
+    if !keys.just_pressed(MODEL_EDITOR_KEY) {
+        return;
+    }
+    let mut transform = player.into_inner();
+    match editor.saved.take() {
+        Some((position, rotation, corners)) => {
+            teleport_writer.send(Teleport { position });
+            transform.rotation = rotation;
+            edit.corners = corners;
+        }
+        None => {
+            editor.saved = Some((
+                origin.to_world(transform.translation),
+                transform.rotation,
+                edit.corners,
+            ));
+            let region = model_region();
+            edit.corners = [Some(region.min), Some(region.max)];
+            let center = region.min.as_vec3() + VoxelBlock::WIDTH as f32 / 2.0;
+            teleport_writer.send(Teleport {
+                position: center + Vec3::Z * VIEW_DISTANCE,
+            });
+            // Facing -Z, towards the model
+            transform.rotation = Quat::IDENTITY;
+            Notifications::sender_or_log(notifications.as_deref())
+                .send(Severity::Info, localization.text("model-editor-entered"));
+        }
+    }
+    time.set_paused(editor.is_active());
+}
+
+/// Leaving photo mode starts time again, but the model shouldn't see block ticks either
+fn keep_time_stopped(mut time: ResMut<Time>) {
+    if !time.is_paused() {
+        time.set_paused(true);
+    }
+}
+
+/// The voxel a click changes: the one under the crosshair when breaking, or the one in front
+/// of its face when placing
+fn sculpt_target(
+    chunks: &ChunkMap,
+    origin: &FloatingOrigin,
+    camera: &Transform,
+    place: bool,
+) -> Option<IVec3> {
+    let direction = camera.rotation * Vec3::NEG_Z;
+    let hit = chunks.raycast(origin.origin(), camera.translation, direction, SCULPT_REACH);
+    let distance = match hit {
+        Some(distance) if place => distance - 1e-3,
+        Some(distance) => distance + 1e-3,
+        None if place => VIEW_DISTANCE,
+        None => return None,
+    };
+    Some(origin.world_voxel(camera.translation + direction * distance))
+}
+
+fn sculpt(
+    buttons: Res<ButtonInput<MouseButton>>,
+    hotbar: Res<Hotbar>,
+    origin: Res<FloatingOrigin>,
+    mut edits: VoxelEdits,
+    mut broken_writer: EventWriter<BlockBroken>,
+    player: Single<(Entity, &Transform, Option<&SpringArm>), With<Player>>,
+) {
+    let place = buttons.just_pressed(PLACE_BUTTON);
+    if !place && !buttons.just_pressed(BREAK_BUTTON) {
+        return;
+    }
+    let new = match (place, hotbar.selected_voxel()) {
+        (true, Some(voxel)) => voxel,
+        (true, None) => return,
+        (false, _) => Voxel::Air,
+    };
+    let (entity, transform, spring_arm) = player.into_inner();
+    let camera = view_transform(transform, spring_arm);
+    let Some(position) = sculpt_target(&edits.chunks, &origin, &camera, place) else {
+        return;
+    };
+    if !model_region().contains(position) {
+        return;
+    }
+    match edits.chunks.set_voxel(position, new) {
+        Some(old) if old != new => {
+            edits.commit(vec![VoxelEdit { position, old, new }]);
+            if new == Voxel::Air {
+                broken_writer.send(BlockBroken { entity, voxel: old });
+            }
+        }
+        _ => (),
+    }
+}
+
+fn save_model(
+    keys: Res<ButtonInput<KeyCode>>,
+    chunks: Res<ChunkMap>,
+    tasks: Res<TaskPools>,
+    localization: Res<Localization>,
+    notifications: Option<Res<Notifications>>,
+    mut edit: ResMut<WorldEdit>,
+) {
+    if !keys.just_pressed(SAVE_MODEL_KEY) {
+        return;
+    }
+    let notifications = Notifications::sender_or_log(notifications.as_deref());
+    let Some(block) = Schematic::copy(&chunks, model_region()) else {
+        return;
+    };
+    let Some(model) = block.trimmed() else {
+        notifications.send(Severity::Warning, localization.text("model-empty"));
+        return;
+    };
+    let file = SchematicFile::from(&model);
+    // A block is far below the size limit of a .vox file
+    let vox = encode_vox(&model).unwrap_or_default();
+    edit.clipboard = Some(model);
+
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    let path = Path::new(MODELS_DIR).join(format!("model-{secs}"));
+    let localization = localization.clone();
+    tasks
+        .spawn(TaskGroup::Io, async move {
+            match write_model(&path, &file, &vox) {
+                Ok((schematic_path, vox_path)) => notifications.send(
+                    Severity::Info,
+                    localization.format(
+                        "model-saved",
+                        &[
+                            ("path", schematic_path.display().to_string().into()),
+                            ("vox", vox_path.display().to_string().into()),
+                        ],
+                    ),
+                ),
+                Err(e) => notifications.send(
+                    Severity::Error,
+                    localization.format(
+                        "save-failed",
+                        &[
+                            ("path", path.display().to_string().into()),
+                            ("error", e.to_string().into()),
+                        ],
+                    ),
+                ),
+            }
+        })
+        .detach();
+}
+
+/// Writes `path` with a `.toml` and a `.vox` extension, returning both paths
+fn write_model(
+    path: &Path,
+    file: &SchematicFile,
+    vox: &[u8],
+) -> Result<(PathBuf, PathBuf), Box<dyn Error>> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let schematic_path = path.with_extension("toml");
+    save_schematic(file, &schematic_path)?;
+    let vox_path = path.with_extension("vox");
+    fs::write(&vox_path, vox)?;
+    Ok((schematic_path, vox_path))
+}
@@ -0,0 +1,224 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use bevy_app::{App, Plugin};
+use bevy_ecs::system::Resource;
+use libloading::{Library, Symbol};
+use thiserror::Error;
+
+use crate::localization::Localization;
+
+/// Loads the mods in [`MODS_DIR`] at startup, each a `cdylib` declared with [`declare_mod!`],
+/// in file name order, and lets each add its systems and resources to the [`App`]. Mods built
+/// for another [`MOD_API_VERSION`] are skipped. Added last, so mods can build on every other
+/// plugin.
+///
+/// Mods are plain Rust compiled against this engine: they must be built with the same
+/// compiler and engine version, and run with full access to the game. Only load mods you trust.
+pub struct ModsPlugin;
+
+impl Plugin for ModsPlugin {
+    fn build(&self, app: &mut App) {
+        let localization = app
+            .world()
+            .get_resource::<Localization>()
+            .cloned()
+            .unwrap_or_default();
+        let paths = match find_mods(Path::new(MODS_DIR)) {
+            Ok(paths) => paths,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => {
+                eprintln!(
+                    "{}",
+                    localization.format(
+                        "mods-dir-unreadable",
+                        &[("path", MODS_DIR.into()), ("error", e.to_string().into())],
+                    )
+                );
+                Vec::new()
+            }
+        };
+
+        let mut loaded = LoadedMods::default();
+        for path in paths {
+            let name = mod_name(&path);
+            // Safety: loading runs the library's initializers, and mods are trusted to be
+            // built for this engine as the API version says
+            match unsafe { load_mod(&path) } {
+                Ok((library, register)) => {
+                    register(app);
+                    // Mods stay loaded until exit, since their systems live in the schedules
+                    std::mem::forget(library);
+                    loaded.0.push(name);
+                }
+                Err(e) => eprintln!("{}", e.localized(&localization, &name)),
+            }
+        }
+        if !loaded.0.is_empty() {
+            println!(
+                "{}",
+                localization.format(
+                    "mods-loaded",
+                    &[
+                        ("count", loaded.0.len().into()),
+                        ("mods", loaded.0.join(", ").into()),
+                    ],
+                )
+            );
+        }
+        app.insert_resource(loaded);
+    }
+}
+
+/// Relative to the working directory
+pub const MODS_DIR: &str = "mods";
+
+/// Changes whenever mods built before would no longer work, e.g. with a new Bevy version
+pub const MOD_API_VERSION: u32 = 1;
+
+/// Exported by every mod, returning the [`MOD_API_VERSION`] it was built for
+pub const API_VERSION_SYMBOL: &[u8] = b"vx_plugin_api_version\0";
+/// Exported by every mod, adding it to the [`App`]
+pub const REGISTER_SYMBOL: &[u8] = b"vx_plugin_register\0";
+
+type ApiVersionFn = extern "C" fn() -> u32;
+type RegisterFn = extern "C" fn(&mut App);
+
+/// Names of the mods that were loaded, in load order
+#[derive(Resource, Debug, Default, Clone)]
+pub struct LoadedMods(pub Vec<String>);
+
+/// Exports the entry points a mod is loaded through, with `$register` adding the mod to the
+/// [`App`]:
+///
+/// ```ignore
+/// fn register(app: &mut App) {
+///     app.add_systems(Update, greet);
+/// }
+///
+/// app::declare_mod!(register);
+/// ```
+#[macro_export]
+macro_rules! declare_mod {
+    ($register:path) => {
+        #[no_mangle]
+        pub extern "C" fn vx_plugin_api_version() -> u32 {
+            $crate::mods_plugin::MOD_API_VERSION
+        }
+
+        #[no_mangle]
+        pub extern "C" fn vx_plugin_register(app: &mut ::bevy_app::App) {
+            $register(app)
+        }
+    };
+}
+
+#[derive(Debug, Error)]
+pub enum ModError {
+    #[error(transparent)]
+    Load(#[from] libloading::Error),
+    #[error("not a vx mod, missing {0}")]
+    MissingSymbol(&'static str),
+    #[error("built for mod API {found}, this game has {expected}")]
+    ApiVersion { found: u32, expected: u32 },
+}
+
+impl ModError {
+    pub fn localized(&self, localization: &Localization, name: &str) -> String {
+        match self {
+            Self::ApiVersion { found, expected } => localization.format(
+                "mod-api-mismatch",
+                &[
+                    ("mod", name.into()),
+                    ("found", (*found).into()),
+                    ("expected", (*expected).into()),
+                ],
+            ),
+            e => localization.format(
+                "mod-load-failed",
+                &[("mod", name.into()), ("error", e.to_string().into())],
+            ),
+        }
+    }
+}
+
+/// The dynamic libraries in `dir`, sorted by file name so mods load in the same order everywhere
+pub fn find_mods(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let is_library = path
+            .extension()
+            .is_some_and(|extension| extension == std::env::consts::DLL_EXTENSION);
+        if path.is_file() && is_library {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+/// The file name without the extension or a `lib` prefix, e.g. `weather` for `libweather.so`
+fn mod_name(path: &Path) -> String {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy())
+        .unwrap_or_default();
+    stem.strip_prefix(std::env::consts::DLL_PREFIX)
+        .filter(|name| !name.is_empty())
+        .unwrap_or(&stem)
+        .to_owned()
+}
+
+/// Loads the library at `path` and checks its API version, returning it with its entry point.
+///
+/// # Safety
+///
+/// Loading a library runs its initializers, and the entry points are trusted to have the
+/// signatures [`declare_mod!`] gives them.
+unsafe fn load_mod(path: &Path) -> Result<(Library, RegisterFn), ModError> {
+    let library = Library::new(path)?;
+    let api_version: Symbol<ApiVersionFn> = library
+        .get(API_VERSION_SYMBOL)
+        .map_err(|_| ModError::MissingSymbol("vx_plugin_api_version"))?;
+    let found = api_version();
+    if found != MOD_API_VERSION {
+        return Err(ModError::ApiVersion {
+            found,
+            expected: MOD_API_VERSION,
+        });
+    }
+    let register: Symbol<RegisterFn> = library
+        .get(REGISTER_SYMBOL)
+        .map_err(|_| ModError::MissingSymbol("vx_plugin_register"))?;
+    let register = *register;
+    Ok((library, register))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env::consts::{DLL_EXTENSION, DLL_PREFIX};
+
+    use super::*;
+
+    #[test]
+    fn only_libraries_are_found_and_broken_ones_are_reported() {
+        let dir = std::env::temp_dir().join(format!("vx-mods-{}", std::process::id()));
+        fs::create_dir_all(dir.join("assets")).unwrap();
+        let library = |name: &str| dir.join(format!("{DLL_PREFIX}{name}.{DLL_EXTENSION}"));
+        for path in [library("weather"), library("birds"), dir.join("README.md")] {
+            fs::write(path, "not a library").unwrap();
+        }
+
+        let found = find_mods(&dir).unwrap();
+        assert_eq!(found, [library("birds"), library("weather")]);
+        assert_eq!(mod_name(&found[0]), "birds");
+        // Safety: the file isn't a library, so nothing gets to run
+        let loaded = unsafe { load_mod(&found[0]) };
+        assert!(matches!(loaded, Err(ModError::Load(_))));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
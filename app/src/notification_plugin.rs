@@ -0,0 +1,172 @@
+use bevy_app::{App, Plugin, Update};
+use bevy_ecs::{
+    schedule::IntoSystemConfigs,
+    system::{Res, ResMut},
+};
+use data::{
+    accessibility::AccessibilitySettings,
+    notification::{Notification, Notifications, Severity},
+    prelude::{IVec2, UVec2},
+    ui_layout::{Anchor, UiLayout},
+};
+use renderer::{
+    hud::{Hud, HudRect},
+    hud_font::GLYPH_ADVANCE,
+};
+
+use crate::{
+    frame_pacing_plugin::FramePacing,
+    hud_plugin::{build_hud, HOTBAR_CLEARANCE},
+};
+
+/// Shows [`Notifications`] as toasts stacked up from above the hotbar on the left of the [`Hud`],
+/// newest at the bottom, each with a stripe in its severity's color that shrinks until it
+/// expires
+pub struct NotificationPlugin;
+
+impl Plugin for NotificationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Notifications>().add_systems(
+            Update,
+            (update_notifications, draw_notifications.after(build_hud)).chain(),
+        );
+    }
+}
+
+const MARGIN: i32 = 12;
+const TOAST_WIDTH: u32 = 320;
+const TOAST_GAP: u32 = 6;
+const PADDING: u32 = 8;
+const STRIPE_WIDTH: u32 = 4;
+/// At a text scale of 1; the toasts grow with larger text
+const TEXT_SCALE: u32 = 2;
+const LINE_GAP: u32 = 4;
+const PANEL_COLOR: [u8; 4] = [20, 20, 20, 255];
+const TEXT_COLOR: [u8; 4] = [230, 230, 230, 255];
+
+const fn severity_color(severity: Severity) -> [u8; 4] {
+    match severity {
+        Severity::Info => [80, 160, 255, 255],
+        Severity::Warning => [255, 200, 80, 255],
+        Severity::Error => [230, 70, 60, 255],
+    }
+}
+
+fn update_notifications(frame_pacing: Res<FramePacing>, mut notifications: ResMut<Notifications>) {
+    notifications.update(frame_pacing.smoothed_delta_secs());
+}
+
+/// Splits `message` into lines that fit in `width` pixels, breaking between words where it can
+pub(crate) fn wrap(message: &str, width: u32, scale: u32) -> Vec<String> {
+    // The last glyph of a line needs no gap after it
+    let max_chars = ((width / scale + 1) / GLYPH_ADVANCE).max(1) as usize;
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in message.split_whitespace() {
+        let mut word = word;
+        // Words too long for a line of their own are broken anywhere
+        while word.chars().count() > max_chars {
+            if !line.is_empty() {
+                lines.push(std::mem::take(&mut line));
+            }
+            let split = word
+                .char_indices()
+                .nth(max_chars)
+                .map_or(word.len(), |(i, _)| i);
+            lines.push(word[..split].to_owned());
+            word = &word[split..];
+        }
+        let needed = line.chars().count() + usize::from(!line.is_empty()) + word.chars().count();
+        if needed > max_chars && !line.is_empty() {
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+/// Draws the toast with its bottom left corner at `corner`, returning its height
+fn draw_toast(
+    hud: &mut Hud,
+    layout: &UiLayout,
+    notification: &Notification,
+    corner: IVec2,
+    text_scale: u32,
+) -> u32 {
+    let scale = layout.px(TEXT_SCALE * text_scale);
+    let width = layout.px(TOAST_WIDTH * text_scale);
+    let (padding, stripe_width, line_gap) = (
+        layout.px(PADDING),
+        layout.px(STRIPE_WIDTH),
+        layout.px(LINE_GAP),
+    );
+    let text_width = width - stripe_width - 2 * padding;
+    let lines = wrap(&notification.message, text_width, scale);
+    let line_height = Hud::text_size("", scale).1;
+    let height = lines.len().max(1) as u32 * (line_height + line_gap) - line_gap + 2 * padding;
+    let top = corner.y - height as i32;
+
+    hud.push(HudRect::new(corner.x, top, width, height, PANEL_COLOR));
+    let stripe = ((1.0 - notification.progress()) * height as f32).ceil() as u32;
+    hud.push(HudRect::new(
+        corner.x,
+        top + (height - stripe) as i32,
+        stripe_width,
+        stripe,
+        severity_color(notification.severity),
+    ));
+    let left = corner.x + (stripe_width + padding) as i32;
+    for (i, line) in lines.iter().enumerate() {
+        let y = top + (padding + i as u32 * (line_height + line_gap)) as i32;
+        hud.push_text(left, y, scale, line, TEXT_COLOR);
+    }
+    height
+}
+
+fn draw_notifications(
+    notifications: Res<Notifications>,
+    accessibility: Res<AccessibilitySettings>,
+    layout: Res<UiLayout>,
+    mut hud: ResMut<Hud>,
+) {
+    let mut corner = layout.place(
+        Anchor::BottomLeft,
+        UVec2::ZERO,
+        IVec2::new(MARGIN, HOTBAR_CLEARANCE as i32 + MARGIN),
+    );
+    for notification in notifications.iter().rev() {
+        let height = draw_toast(
+            &mut hud,
+            &layout,
+            notification,
+            corner,
+            accessibility.clamped_text_scale(),
+        );
+        corner.y -= (height + layout.px(TOAST_GAP)) as i32;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_wrap_between_words() {
+        let width = Hud::text_size("twelve chars", TEXT_SCALE).0;
+        assert_eq!(
+            wrap(
+                "Saved photo-1234.png to the pictures folder",
+                width,
+                TEXT_SCALE
+            ),
+            ["Saved", "photo-1234.p", "ng to the", "pictures", "folder"]
+        );
+        assert_eq!(wrap("Saved world", width, TEXT_SCALE), ["Saved world"]);
+    }
+}
@@ -0,0 +1,327 @@
+use std::f32::consts::TAU;
+
+use bevy_app::{App, FixedUpdate, Plugin, Startup};
+use bevy_ecs::{
+    entity::Entity,
+    query::With,
+    schedule::IntoSystemConfigs,
+    system::{Commands, Query, Res, ResMut, Resource, Single},
+};
+use data::{
+    block_tick::GameTick,
+    chunk_map::{chunk_of, ChunkMap},
+    floating_origin::FloatingOrigin,
+    instance::Instance,
+    mesh::{Mesh, MeshHandle, Meshes},
+    name::Name,
+    npc::{find_path, is_walkable, standing_voxel, Behavior, Hostile, Npc},
+    prelude::{IVec3, Quat, Vec3},
+    rng::Rng,
+    transform::{PreviousTransform, Transform},
+    voxel::Voxel,
+    weather::Weather,
+};
+
+use crate::{
+    player_plugin::Player,
+    prefab_plugin::{PrefabAppExt, Prefabs},
+};
+
+/// Spawns [`Npc`]s around the player by the [`NpcSpawnRules`], and every tick has them idle,
+/// wander about or chase the player, walking paths found over the loaded voxels. NPCs are
+/// drawn as cube [`Instance`]s and despawned once far from the player. Prefabs can list
+/// `"hostile"` to be hostile NPCs, and be drawn with the NPC cube as `Named("npc")`.
+pub struct NpcPlugin;
+
+impl Plugin for NpcPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<NpcSpawnRules>()
+            .register_prefab_component::<Hostile>("hostile")
+            .add_systems(Startup, setup)
+            .add_systems(
+                FixedUpdate,
+                (despawn_far_npcs, spawn_npcs, think, walk).chain(),
+            );
+    }
+}
+
+/// Where, when and how many NPCs spawn around the player. The world has no biomes or day
+/// and night yet, so the ground voxel stands in for the biome and the [`Weather`]'s sunlight
+/// for the time of day.
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct NpcSpawnRules {
+    /// NPCs alive at once, beyond which none spawn
+    pub max_npcs: usize,
+    /// Ticks between attempts to spawn one
+    pub interval: u64,
+    /// Horizontal voxels from the player NPCs spawn between
+    pub min_distance: i32,
+    pub max_distance: i32,
+    /// Voxels from the player beyond which NPCs are despawned
+    pub despawn_distance: f32,
+    /// Voxels NPCs spawn standing on
+    pub ground: Vec<Voxel>,
+    /// Sunlight below which [`Hostile`] NPCs spawn instead of passive ones
+    pub hostile_below_sunlight: f32,
+}
+
+impl Default for NpcSpawnRules {
+    fn default() -> Self {
+        Self {
+            max_npcs: 12,
+            interval: 2 * GameTick::PER_SECOND as u64,
+            min_distance: 16,
+            max_distance: 40,
+            despawn_distance: 64.0,
+            ground: vec![Voxel::Grass],
+            hostile_below_sunlight: 0.6,
+        }
+    }
+}
+
+#[derive(Resource, Debug, Clone, Copy)]
+struct NpcMesh(MeshHandle);
+
+/// Edge of the cube NPCs are drawn as
+const NPC_SIZE: f32 = 0.8;
+/// From the corner of the voxel an NPC stands in to the middle of its body, resting on the
+/// voxel below. Up is -Y.
+const BODY_OFFSET: Vec3 = Vec3::new(0.5, 1.0 - NPC_SIZE / 2.0, 0.5);
+
+const PASSIVE_MATERIAL: Voxel = Voxel::Dirt;
+const HOSTILE_MATERIAL: Voxel = Voxel::Stone;
+const PASSIVE_SPEED: f32 = 2.0;
+const HOSTILE_SPEED: f32 = 3.0;
+
+/// Voxels above and below the player searched for ground to spawn on
+const SPAWN_HEIGHT_RANGE: i32 = 16;
+/// Voxels from where it stands an NPC wanders to at most, and the rise searched there
+const WANDER_RADIUS: i32 = 8;
+const WANDER_HEIGHT_RANGE: i32 = 3;
+/// Ticks an NPC idles between walks
+const MIN_IDLE_TICKS: u64 = GameTick::PER_SECOND as u64;
+const MAX_IDLE_TICKS: u64 = 6 * GameTick::PER_SECOND as u64;
+/// Ticks between a chasing NPC's paths to the player
+const REPATH_TICKS: u64 = GameTick::PER_SECOND as u64 / 2;
+
+/// Paths searched per tick across all NPCs; the rest wait for the next ticks
+const MAX_PATHS_PER_TICK: usize = 4;
+/// Voxels one path search visits at most
+const MAX_PATH_VISITS: usize = 512;
+
+fn setup(mut commands: Commands, mut meshes: ResMut<Meshes>, mut prefabs: ResMut<Prefabs>) {
+    let mesh = meshes.add(Mesh::cube(NPC_SIZE));
+    prefabs.name_mesh("npc", mesh);
+    commands.insert_resource(NpcMesh(mesh));
+}
+
+/// The world voxel an NPC at `translation` stands in
+fn feet(origin: &FloatingOrigin, translation: Vec3) -> IVec3 {
+    origin.world_voxel(translation - BODY_OFFSET + 0.5)
+}
+
+fn body_translation(origin: &FloatingOrigin, feet: IVec3) -> Vec3 {
+    origin.to_translation(feet.as_vec3() + BODY_OFFSET)
+}
+
+fn despawn_far_npcs(
+    mut commands: Commands,
+    rules: Res<NpcSpawnRules>,
+    chunks: Res<ChunkMap>,
+    origin: Res<FloatingOrigin>,
+    npcs: Query<(Entity, &Transform), With<Npc>>,
+    player: Single<&Transform, With<Player>>,
+) {
+    for (entity, transform) in &npcs {
+        let far = transform.translation.distance(player.translation) > rules.despawn_distance;
+        if far || !chunks.contains(chunk_of(feet(&origin, transform.translation))) {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn spawn_npcs(
+    mut commands: Commands,
+    rules: Res<NpcSpawnRules>,
+    tick: Res<GameTick>,
+    rng: Res<Rng>,
+    chunks: Res<ChunkMap>,
+    weather: Res<Weather>,
+    origin: Res<FloatingOrigin>,
+    mesh: Res<NpcMesh>,
+    npcs: Query<(), With<Npc>>,
+    player: Single<&Transform, With<Player>>,
+) {
+    if !tick.0.is_multiple_of(rules.interval) || npcs.iter().count() >= rules.max_npcs {
+        return;
+    }
+
+    let mut roll = rng.tick("npc_spawn", *tick);
+    let angle = roll.next_f32() * TAU;
+    let distance = roll.range(
+        rules.min_distance as u64,
+        rules.max_distance.max(rules.min_distance) as u64,
+    ) as f32;
+    let center = origin.world_voxel(player.translation);
+    let (x, z) = (
+        center.x + (angle.cos() * distance) as i32,
+        center.z + (angle.sin() * distance) as i32,
+    );
+    let Some(position) = standing_voxel(&chunks, x, z, center.y, SPAWN_HEIGHT_RANGE) else {
+        return;
+    };
+    let ground = chunks.voxel(position + IVec3::Y);
+    if !ground.is_some_and(|ground| rules.ground.contains(&ground)) {
+        return;
+    }
+
+    let transform = Transform::from_translation(body_translation(&origin, position));
+    let hostile = weather.sunlight() < rules.hostile_below_sunlight;
+    let (speed, material) = if hostile {
+        (HOSTILE_SPEED, HOSTILE_MATERIAL)
+    } else {
+        (PASSIVE_SPEED, PASSIVE_MATERIAL)
+    };
+    let mut npc = commands.spawn((
+        Name::new(if hostile { "hostile npc" } else { "npc" }),
+        Npc {
+            speed,
+            ..Npc::default()
+        },
+        Instance::new(mesh.0, material),
+        transform,
+        PreviousTransform(transform),
+    ));
+    if hostile {
+        npc.insert(Hostile::default());
+    }
+}
+
+/// Picks every NPC's [`Behavior`] for the tick and the path it follows
+fn think(
+    tick: Res<GameTick>,
+    rng: Res<Rng>,
+    chunks: Res<ChunkMap>,
+    origin: Res<FloatingOrigin>,
+    mut npcs: Query<(Entity, &mut Npc, &Transform, Option<&Hostile>)>,
+    player: Single<(Entity, &Transform), With<Player>>,
+) {
+    let (player, player_transform) = player.into_inner();
+    let mut paths_left = MAX_PATHS_PER_TICK;
+    for (entity, mut npc, transform, hostile) in &mut npcs {
+        let npc = &mut *npc;
+        let mut roll = rng.tick("npc", *tick).split(entity.to_bits());
+        let idle = Behavior::Idle {
+            until: tick.after(roll.range(MIN_IDLE_TICKS, MAX_IDLE_TICKS)),
+        };
+
+        if let Some(hostile) = hostile {
+            let distance = transform.translation.distance(player_transform.translation);
+            let chasing = matches!(npc.behavior, Behavior::Chase { .. });
+            if chasing && distance > hostile.give_up {
+                npc.behavior = idle;
+                npc.path.clear();
+            } else if !chasing && distance <= hostile.sight {
+                npc.behavior = Behavior::Chase { target: player };
+                npc.path.clear();
+            }
+        }
+
+        // Paths start from the voxel being walked to, so the NPC doesn't turn back mid-step
+        let from = npc
+            .path
+            .front()
+            .copied()
+            .unwrap_or_else(|| feet(&origin, transform.translation));
+        let goal = match npc.behavior {
+            Behavior::Idle { until } if *tick >= until => {
+                let mut offset = || roll.range(0, 2 * WANDER_RADIUS as u64) as i32 - WANDER_RADIUS;
+                let goal = standing_voxel(
+                    &chunks,
+                    from.x + offset(),
+                    from.z + offset(),
+                    from.y,
+                    WANDER_HEIGHT_RANGE,
+                );
+                match goal {
+                    Some(goal) => {
+                        npc.behavior = Behavior::Wander { goal };
+                        Some(goal)
+                    }
+                    None => {
+                        npc.behavior = idle;
+                        None
+                    }
+                }
+            }
+            Behavior::Idle { .. } => None,
+            Behavior::Wander { .. } if npc.path.is_empty() => {
+                npc.behavior = idle;
+                None
+            }
+            Behavior::Wander { .. } => None,
+            Behavior::Chase { target } => {
+                let due = npc.path.is_empty()
+                    || (tick.0 + entity.index() as u64).is_multiple_of(REPATH_TICKS);
+                // The player flies, so NPCs head for the ground below them
+                let target = (target == player).then(|| {
+                    let position = origin.world_voxel(player_transform.translation);
+                    standing_voxel(
+                        &chunks,
+                        position.x,
+                        position.z,
+                        position.y,
+                        SPAWN_HEIGHT_RANGE,
+                    )
+                    .unwrap_or(position)
+                });
+                target.filter(|_| due)
+            }
+        };
+
+        let Some(goal) = goal else {
+            continue;
+        };
+        if paths_left == 0 {
+            continue;
+        }
+        paths_left -= 1;
+        let walking_to = npc.path.front().copied();
+        npc.path = find_path(&chunks, from, goal, MAX_PATH_VISITS).unwrap_or_default();
+        if let Some(walking_to) = walking_to {
+            npc.path.push_front(walking_to);
+        }
+    }
+}
+
+/// Moves every NPC along its path at its speed, facing the way it walks. Paths blocked by
+/// voxels placed since they were found are dropped.
+fn walk(
+    chunks: Res<ChunkMap>,
+    origin: Res<FloatingOrigin>,
+    mut npcs: Query<(&mut Npc, &mut Transform)>,
+) {
+    for (mut npc, mut transform) in &mut npcs {
+        let Some(next) = npc.path.front().copied() else {
+            continue;
+        };
+        if !is_walkable(&chunks, next) {
+            npc.path.clear();
+            continue;
+        }
+
+        let step = npc.speed / GameTick::PER_SECOND as f32;
+        let delta = body_translation(&origin, next) - transform.translation;
+        if delta.length() <= step {
+            transform.translation += delta;
+            npc.path.pop_front();
+        } else {
+            transform.translation += delta.normalize() * step;
+        }
+        // Forward is -Z
+        if delta.x != 0.0 || delta.z != 0.0 {
+            transform.rotation = Quat::from_rotation_y(f32::atan2(-delta.x, -delta.z));
+        }
+    }
+}
@@ -0,0 +1,291 @@
+use std::cmp::Ordering;
+
+use bevy_app::{App, Plugin, Update};
+use bevy_ecs::{
+    event::EventReader,
+    query::With,
+    schedule::{common_conditions::not, IntoSystemConfigs},
+    system::{Local, Res, ResMut, Single},
+};
+use bevy_window::{PrimaryWindow, Window};
+use data::{
+    camera::{CameraFov, CameraGpu},
+    chunk_map::ChunkMap,
+    floating_origin::FloatingOrigin,
+    particles::{Particle, Particles},
+    prelude::{IVec3, Vec3},
+    spring_arm::SpringArm,
+    transform::Transform,
+    voxel::Voxel,
+    weather::{Precipitation, Weather},
+};
+use renderer::hud::{Hud, HudRect};
+
+use crate::{
+    frame_pacing_plugin::FramePacing,
+    hud_plugin::build_hud,
+    photo_mode_plugin::photo_mode_active,
+    player_plugin::{view_transform, Player},
+    world_plugin::{rebase_origin, VoxelEdited},
+};
+
+/// Debris where a voxel is broken, dust where one is placed, and rain or snow around the
+/// player with the [`Weather`], simulated on the CPU and drawn as squares in the [`Hud`],
+/// nearest over furthest, hidden behind opaque voxels. Paused with the rest of the world in
+/// photo mode.
+pub struct ParticlePlugin;
+
+impl Plugin for ParticlePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Particles>().add_systems(
+            Update,
+            (
+                (
+                    follow_floating_origin.after(rebase_origin),
+                    emit_edit_particles,
+                    (emit_precipitation, simulate_particles)
+                        .chain()
+                        .run_if(not(photo_mode_active)),
+                )
+                    .chain(),
+                draw_particles.after(simulate_particles).after(build_hud),
+            ),
+        );
+    }
+}
+
+/// Edits in one frame that emit particles; the rest of a large fill is left out, since its
+/// bursts would only replace each other in the pool
+const MAX_BURSTS_PER_FRAME: usize = 32;
+
+const DEBRIS_COUNT: usize = 12;
+/// Up is -Y
+const DEBRIS_LAUNCH: Vec3 = Vec3::new(0.0, -3.0, 0.0);
+const DEBRIS_SPEED: f32 = 4.0;
+const DEBRIS: Particle = Particle {
+    position: Vec3::ZERO,
+    velocity: DEBRIS_LAUNCH,
+    color: [0; 3],
+    size: 0.12,
+    age: 0.0,
+    lifetime: 1.5,
+    gravity: 1.0,
+    drag: 0.5,
+    expires_on_impact: false,
+};
+
+const DUST_COUNT: usize = 6;
+const DUST_SPEED: f32 = 1.5;
+const DUST: Particle = Particle {
+    position: Vec3::ZERO,
+    velocity: Vec3::ZERO,
+    color: [0; 3],
+    size: 0.08,
+    age: 0.0,
+    lifetime: 0.8,
+    gravity: 0.05,
+    drag: 3.0,
+    expires_on_impact: false,
+};
+/// Dust is the placed voxel's color mixed this far towards white
+const DUST_WHITENESS: f32 = 0.5;
+
+/// Particles per second falling around the player in a downpour, fewer for lighter weather
+const RAIN_RATE: f32 = 800.0;
+const SNOW_RATE: f32 = 150.0;
+/// Precipitation starts this far from the player on either side, and above them
+const PRECIPITATION_RADIUS: f32 = 16.0;
+const PRECIPITATION_HEIGHT: f32 = 12.0;
+
+/// Up is -Y
+const RAIN: Particle = Particle {
+    position: Vec3::ZERO,
+    velocity: Vec3::new(0.0, 15.0, 0.0),
+    color: [170, 190, 220],
+    size: 0.05,
+    age: 0.0,
+    lifetime: 2.0,
+    gravity: 1.0,
+    drag: 0.0,
+    expires_on_impact: true,
+};
+/// Settles where it lands until it melts away
+const SNOW: Particle = Particle {
+    position: Vec3::ZERO,
+    velocity: Vec3::new(0.0, 1.5, 0.0),
+    color: [240, 240, 250],
+    size: 0.08,
+    age: 0.0,
+    lifetime: 10.0,
+    gravity: 0.05,
+    drag: 1.0,
+    expires_on_impact: false,
+};
+
+/// Particles beyond this many voxels from the camera aren't drawn, or raycast against
+const MAX_DRAW_DISTANCE: f32 = 48.0;
+/// How far in front of a particle a voxel may be hit before it counts as hidden, so
+/// particles resting on a voxel aren't hidden by it
+const OCCLUSION_SLACK: f32 = 0.25;
+/// Longest step simulated at once, so a hitch doesn't tunnel particles through voxels
+const MAX_STEP_SECS: f32 = 0.1;
+
+/// Shifts the particles with every translation when the origin is rebased
+fn follow_floating_origin(
+    origin: Res<FloatingOrigin>,
+    mut particles: ResMut<Particles>,
+    mut last_origin: Local<Option<IVec3>>,
+) {
+    let last = last_origin.replace(origin.origin());
+    if let Some(last) = last.filter(|last| *last != origin.origin()) {
+        particles.shift((origin.origin() - last).as_vec3());
+    }
+}
+
+fn emit_edit_particles(
+    origin: Res<FloatingOrigin>,
+    mut particles: ResMut<Particles>,
+    mut edited_reader: EventReader<VoxelEdited>,
+) {
+    for VoxelEdited(edit) in edited_reader.read().take(MAX_BURSTS_PER_FRAME) {
+        let corner = (edit.position - origin.origin()).as_vec3();
+        match (edit.old.is_opaque(), edit.new.is_opaque()) {
+            (true, false) => particles.burst(
+                corner,
+                DEBRIS_COUNT,
+                Particle {
+                    color: color(edit.old, 0.0),
+                    ..DEBRIS
+                },
+                DEBRIS_SPEED,
+            ),
+            (false, true) => particles.burst(
+                corner,
+                DUST_COUNT,
+                Particle {
+                    color: color(edit.new, DUST_WHITENESS),
+                    ..DUST
+                },
+                DUST_SPEED,
+            ),
+            _ => {}
+        }
+    }
+    // Whatever is past the cap this frame is dropped rather than emitted later
+    edited_reader.clear();
+}
+
+/// The voxel's material color mixed `whiteness` of the way towards white
+fn color(voxel: Voxel, whiteness: f32) -> [u8; 3] {
+    voxel
+        .material()
+        .color
+        .map(|c| ((c.clamp(0.0, 1.0) * (1.0 - whiteness) + whiteness) * 255.0) as u8)
+}
+
+/// Spawns rain, or snow above [`Precipitation::SNOW_LINE_Y`], in a layer above the player.
+/// Fractions of a particle carry over to the next frame, so light weather still falls.
+fn emit_precipitation(
+    frame_pacing: Res<FramePacing>,
+    weather: Res<Weather>,
+    origin: Res<FloatingOrigin>,
+    mut particles: ResMut<Particles>,
+    mut owed: Local<f32>,
+    player: Single<&Transform, With<Player>>,
+) {
+    let (particle, rate) = match Precipitation::at(origin.to_world(player.translation).y) {
+        Precipitation::Rain => (RAIN, RAIN_RATE),
+        Precipitation::Snow => (SNOW, SNOW_RATE),
+    };
+    *owed += rate * weather.precipitation() * frame_pacing.smoothed_delta_secs().min(MAX_STEP_SECS);
+    let count = *owed as usize;
+    if count == 0 {
+        return;
+    }
+    *owed -= count as f32;
+    let center = player.translation - Vec3::Y * PRECIPITATION_HEIGHT;
+    let extent = Vec3::new(PRECIPITATION_RADIUS, 1.0, PRECIPITATION_RADIUS);
+    particles.scatter(center - extent, center + extent, count, particle);
+}
+
+fn simulate_particles(
+    frame_pacing: Res<FramePacing>,
+    chunks: Res<ChunkMap>,
+    origin: Res<FloatingOrigin>,
+    mut particles: ResMut<Particles>,
+) {
+    if particles.is_empty() {
+        return;
+    }
+    let delta = frame_pacing.smoothed_delta_secs().min(MAX_STEP_SECS);
+    particles.step(delta, &chunks, origin.origin());
+}
+
+/// Projects the particles like the rendered frame, the same way the schematic selection is
+/// outlined
+fn draw_particles(
+    particles: Res<Particles>,
+    chunks: Res<ChunkMap>,
+    origin: Res<FloatingOrigin>,
+    mut hud: ResMut<Hud>,
+    window: Single<&Window, With<PrimaryWindow>>,
+    player: Single<(&Transform, &CameraFov, Option<&SpringArm>), With<Player>>,
+) {
+    if particles.is_empty() {
+        return;
+    }
+    let (transform, fov, spring_arm) = player.into_inner();
+    let (width, height) = (window.physical_width(), window.physical_height());
+    let camera = view_transform(transform, spring_arm);
+    let view_proj = CameraGpu::new(&camera, fov.degrees(), width as f32, height as f32).view_proj();
+    // Pixels per voxel at a depth of one voxel
+    let focal_length = height as f32 * 0.5 / (fov.radians() * 0.5).tan();
+
+    let mut visible: Vec<_> = particles
+        .iter()
+        .filter_map(|particle| {
+            let to_particle = particle.position - camera.translation;
+            let distance = to_particle.length();
+            if distance > MAX_DRAW_DISTANCE {
+                return None;
+            }
+            let clip = view_proj * particle.position.extend(1.0);
+            // Behind the camera
+            if clip.w <= CameraGpu::NEAR {
+                return None;
+            }
+            let ndc = clip.truncate() / clip.w;
+            if ndc.x.abs() > 1.0 || ndc.y.abs() > 1.0 {
+                return None;
+            }
+            let hidden = chunks
+                .raycast(
+                    origin.origin(),
+                    camera.translation,
+                    to_particle / distance,
+                    distance,
+                )
+                .is_some_and(|hit| hit < distance - OCCLUSION_SLACK);
+            if hidden {
+                return None;
+            }
+            let size = (particle.current_size() * focal_length / clip.w).round() as u32;
+            let [r, g, b] = particle.color;
+            Some((
+                clip.w,
+                HudRect::centered(
+                    ((ndc.x + 1.0) * 0.5 * width as f32) as i32,
+                    ((ndc.y + 1.0) * 0.5 * height as f32) as i32,
+                    size.max(1),
+                    size.max(1),
+                    [r, g, b, 255],
+                ),
+            ))
+        })
+        .collect();
+    // Later rects are drawn over earlier ones
+    visible.sort_unstable_by(|(a, _), (b, _)| b.partial_cmp(a).unwrap_or(Ordering::Equal));
+    for (_, rect) in visible {
+        hud.push(rect);
+    }
+}
@@ -0,0 +1,195 @@
+use std::{
+    fs,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bevy_app::{App, Plugin, Update};
+use bevy_ecs::{
+    query::With,
+    schedule::IntoSystemConfigs,
+    system::{Res, ResMut, Resource, Single},
+};
+use bevy_input::{keyboard::KeyCode, ButtonInput};
+use data::{
+    notification::{Notifications, Severity},
+    transform::Transform,
+};
+use renderer::{
+    command_state::CommandState,
+    hud::{Hud, HudRect},
+    settings::{ReflectionQuality, RendererSettings},
+};
+
+use crate::{
+    frame_pacing_plugin::FramePacing,
+    hud_plugin::build_hud,
+    localization::Localization,
+    player_plugin::{move_player, Player},
+    task_plugin::{TaskGroup, TaskPools},
+    time_plugin::Time,
+};
+
+/// Freezes the simulation, frees the camera and raises the renderer's quality, then saves a
+/// PNG once the camera has held still for [`PHOTO_ACCUMULATION_SECS`]
+pub struct PhotoModePlugin;
+
+impl Plugin for PhotoModePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PhotoMode>().add_systems(
+            Update,
+            (
+                toggle_photo_mode.before(move_player),
+                (accumulate_photo, save_photo).chain().after(move_player),
+                draw_photo_progress.after(build_hud),
+            ),
+        );
+    }
+}
+
+pub const PHOTO_KEY: KeyCode = KeyCode::F12;
+
+/// How long the camera must stay still before the photo is taken
+pub const PHOTO_ACCUMULATION_SECS: f32 = 5.0;
+
+const PROGRESS_BAR_WIDTH: u32 = 200;
+const PROGRESS_BAR_HEIGHT: u32 = 4;
+const PROGRESS_BAR_COLOR: [u8; 4] = [255, 255, 255, 255];
+
+#[derive(Resource, Debug, Default)]
+pub struct PhotoMode {
+    /// Settings and player position to restore on leaving; `Some` while in photo mode
+    saved: Option<(RendererSettings, Transform)>,
+    /// Camera transform accumulation started from
+    still_since: Option<Transform>,
+    accumulated_secs: f32,
+    /// One photo per still camera; moving it starts the next one
+    captured: bool,
+}
+
+impl PhotoMode {
+    pub const fn is_active(&self) -> bool {
+        self.saved.is_some()
+    }
+
+    /// Settings that trade frame rate for image quality
+    fn quality(settings: &RendererSettings) -> RendererSettings {
+        RendererSettings {
+            global_illumination: true,
+            ambient_occlusion: true,
+            adaptive_sampling: true,
+            shadows: true,
+            shadow_rays: RendererSettings::MAX_SHADOW_RAYS,
+            reflection_quality: ReflectionQuality::High,
+            render_scale: RendererSettings::MAX_RENDER_SCALE,
+            picking: false,
+            ..settings.clone()
+        }
+    }
+}
+
+/// Run condition that also works when the plugin isn't added
+pub fn photo_mode_active(photo_mode: Option<Res<PhotoMode>>) -> bool {
+    photo_mode.is_some_and(|photo_mode| photo_mode.is_active())
+}
+
+fn toggle_photo_mode(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut photo_mode: ResMut<PhotoMode>,
+    mut settings: ResMut<RendererSettings>,
+    mut time: ResMut<Time>,
+    player: Single<&mut Transform, With<Player>>,
+) {
+    if !keys.just_pressed(PHOTO_KEY) {
+        return;
+    }
+    let mut transform = player.into_inner();
+    match photo_mode.saved.take() {
+        Some((saved_settings, saved_transform)) => {
+            *settings = saved_settings;
+            *transform = saved_transform;
+            *photo_mode = PhotoMode::default();
+        }
+        None => {
+            photo_mode.saved = Some((settings.clone(), *transform));
+            *settings = PhotoMode::quality(&settings);
+        }
+    }
+    time.set_paused(photo_mode.is_active());
+}
+
+fn accumulate_photo(
+    frame_pacing: Res<FramePacing>,
+    mut photo_mode: ResMut<PhotoMode>,
+    mut command_state: ResMut<CommandState>,
+    player: Single<&Transform, With<Player>>,
+) {
+    if !photo_mode.is_active() {
+        return;
+    }
+    // The renderer restarts accumulating whenever the camera moves, so the timer does too
+    if photo_mode.still_since != Some(**player) {
+        photo_mode.still_since = Some(**player);
+        photo_mode.accumulated_secs = 0.0;
+        photo_mode.captured = false;
+        return;
+    }
+    if photo_mode.captured {
+        return;
+    }
+    photo_mode.accumulated_secs += frame_pacing.smoothed_delta_secs();
+    if photo_mode.accumulated_secs >= PHOTO_ACCUMULATION_SECS {
+        command_state.request_capture();
+        photo_mode.captured = true;
+    }
+}
+
+pub fn save_photo(
+    task_pools: Res<TaskPools>,
+    localization: Res<Localization>,
+    notifications: Option<Res<Notifications>>,
+    mut command_state: ResMut<CommandState>,
+) {
+    let Some(capture) = command_state.take_capture() else {
+        return;
+    };
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    let path = format!("photo-{secs}.png");
+    let localization = localization.clone();
+    let notifications = Notifications::sender_or_log(notifications.as_deref());
+    task_pools
+        .spawn(TaskGroup::Io, async move {
+            match fs::write(&path, capture.to_png()) {
+                Ok(()) => notifications.send(
+                    Severity::Info,
+                    localization.format("saved", &[("path", path.into())]),
+                ),
+                Err(e) => notifications.send(
+                    Severity::Error,
+                    localization.format(
+                        "save-failed",
+                        &[("path", path.into()), ("error", e.to_string().into())],
+                    ),
+                ),
+            }
+        })
+        .detach();
+}
+
+/// A bar along the top of the screen that fills up until the photo is taken. The HUD is drawn
+/// after the capture, so it never shows up in the photo.
+fn draw_photo_progress(photo_mode: Res<PhotoMode>, mut hud: ResMut<Hud>) {
+    if !photo_mode.is_active() {
+        return;
+    }
+    let progress = (photo_mode.accumulated_secs / PHOTO_ACCUMULATION_SECS).min(1.0);
+    let width = (progress * PROGRESS_BAR_WIDTH as f32) as u32;
+    hud.push(HudRect::new(
+        PROGRESS_BAR_HEIGHT as i32,
+        PROGRESS_BAR_HEIGHT as i32,
+        width,
+        PROGRESS_BAR_HEIGHT,
+        PROGRESS_BAR_COLOR,
+    ));
+}
@@ -2,12 +2,13 @@ use std::f32;
 
 use bevy_app::{Plugin, Startup, Update};
 use bevy_ecs::{
+    change_detection::DetectChanges,
     component::Component,
     entity::Entity,
     event::EventReader,
     query::With,
     schedule::IntoSystemConfigs,
-    system::{Commands, Res, ResMut, Resource, Single},
+    system::{Commands, Query, Res, ResMut, Resource, Single},
 };
 use bevy_input::{
     keyboard::KeyCode,
@@ -15,23 +16,41 @@ use bevy_input::{
     ButtonInput,
 };
 use bevy_window::{PrimaryWindow, WindowFocused};
-use data::{camera::CameraFov, transform::Transform};
-use glam::{EulerRot, Quat, Vec3};
+use data::{
+    camera::CameraFov,
+    chunk_map::ChunkMap,
+    exposure::Exposure,
+    floating_origin::FloatingOrigin,
+    name::Name,
+    prelude::{EulerRot, Quat, Vec3},
+    spring_arm::SpringArm,
+    transform::Transform,
+};
+use serde::{Deserialize, Serialize};
 
-use crate::time_plugin::Time;
+use crate::{
+    frame_pacing_plugin::FramePacing, hud_plugin::ZOOM_MODIFIER, loading_plugin::in_game,
+    save_plugin::Persistent,
+};
 
 pub struct PlayerPlugin;
 
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut bevy_app::App) {
         app.init_resource::<IgnoreNextDelta>()
+            .init_resource::<PlayerSettings>()
             .add_systems(Startup, setup)
             .add_systems(
                 Update,
                 (
-                    move_player,
-                    (ignore_deltas, rotate_player).chain(),
-                    zoom_player,
+                    move_player.run_if(in_game),
+                    (ignore_deltas, rotate_player.run_if(in_game)).chain(),
+                    (apply_player_settings, zoom_player).chain(),
+                    toggle_third_person.after(apply_player_settings),
+                    extend_spring_arms
+                        .after(move_player)
+                        .after(rotate_player)
+                        .after(toggle_third_person),
                 ),
             );
     }
@@ -40,6 +59,40 @@ impl Plugin for PlayerPlugin {
 #[derive(Component, Clone, Copy)]
 pub struct Player;
 
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PlayerSettings {
+    /// Field of view the camera resets to whenever the settings change
+    pub fov_degrees: f32,
+    /// Multiplier on mouse look speed
+    pub mouse_sensitivity: f32,
+    /// Camera arm used in third person
+    pub third_person: SpringArm,
+    /// Whether gamepads rumble, see [`HapticsPlugin`](crate::haptics_plugin::HapticsPlugin)
+    pub rumble: bool,
+    /// Shown to other players, e.g. in chat
+    pub name: String,
+}
+
+impl PlayerSettings {
+    /// Range the settings menu offers. Wide views help with motion sickness; past this the
+    /// rectilinear projection stretches the edges too far to be useful.
+    pub const MIN_FOV_DEGREES: f32 = 30.0;
+    pub const MAX_FOV_DEGREES: f32 = 150.0;
+}
+
+impl Default for PlayerSettings {
+    fn default() -> Self {
+        Self {
+            fov_degrees: 45.0,
+            mouse_sensitivity: 1.0,
+            third_person: SpringArm::default(),
+            rumble: true,
+            name: "Player".to_owned(),
+        }
+    }
+}
+
 #[derive(Resource)]
 pub struct IgnoreNextDelta(bool);
 
@@ -49,14 +102,19 @@ impl Default for IgnoreNextDelta {
     }
 }
 
-fn setup(mut commands: Commands) {
+fn setup(mut commands: Commands, settings: Res<PlayerSettings>) {
     commands.spawn((
         Player,
-        CameraFov::from_degrees(45.0),
+        Name::new("Player"),
+        Persistent::unique("player"),
+        CameraFov::from_degrees(settings.fov_degrees),
+        Exposure::auto(),
         Transform::from_xyz(0.0, 0.0, 16.0),
     ));
 }
 
+pub const THIRD_PERSON_KEY: KeyCode = KeyCode::F6;
+
 const MOVE_SPEED: f32 = 5.0;
 
 const YAW_SPEED: f32 = 0.5;
@@ -67,13 +125,13 @@ const PITCH_LIMIT: f32 = f32::consts::FRAC_PI_2 - 0.01;
 const SCROLL_SPEED: f32 = 10.0;
 
 pub fn move_player(
-    time: Res<Time>,
+    frame_pacing: Res<FramePacing>,
     keys: Res<ButtonInput<KeyCode>>,
     transform: Single<&mut Transform, With<Player>>,
 ) {
     let mut transform = transform.into_inner();
 
-    let speed = MOVE_SPEED * time.delta_secs();
+    let speed = MOVE_SPEED * frame_pacing.smoothed_delta_secs();
 
     let remove_y = Vec3::X + Vec3::Z;
     let local_x = (transform.rotation * Vec3::X * remove_y).normalize() * speed;
@@ -105,7 +163,8 @@ pub fn ignore_deltas(
 }
 
 pub fn rotate_player(
-    time: Res<Time>,
+    frame_pacing: Res<FramePacing>,
+    settings: Res<PlayerSettings>,
     mut mouse_motion: ResMut<AccumulatedMouseMotion>,
     mut ignore_next_delta: ResMut<IgnoreNextDelta>,
     transform: Single<&mut Transform, With<Player>>,
@@ -121,13 +180,14 @@ pub fn rotate_player(
         return;
     }
 
-    let delta_time = time.delta_secs();
+    let delta_time = frame_pacing.smoothed_delta_secs();
     let mut transform = transform.into_inner();
 
     let delta = mouse_motion.delta;
 
-    let dyaw = delta.x * YAW_SPEED * delta_time;
-    let dpitch = -delta.y * PITCH_SPEED * delta_time;
+    let speed = settings.mouse_sensitivity * delta_time;
+    let dyaw = delta.x * YAW_SPEED * speed;
+    let dpitch = -delta.y * PITCH_SPEED * speed;
 
     let (yaw, pitch, _roll) = transform.rotation.to_euler(EulerRot::YXZ);
     let yaw = yaw - dyaw;
@@ -136,11 +196,73 @@ pub fn rotate_player(
     transform.rotation = Quat::from_euler(EulerRot::YXZ, yaw, pitch, 0.0);
 }
 
+pub fn apply_player_settings(
+    settings: Res<PlayerSettings>,
+    player: Single<(&mut CameraFov, Option<&mut SpringArm>), With<Player>>,
+) {
+    if settings.is_changed() {
+        let (mut fov, spring_arm) = player.into_inner();
+        *fov = CameraFov::from_degrees(settings.fov_degrees);
+        if let Some(mut spring_arm) = spring_arm {
+            *spring_arm = settings.third_person;
+        }
+    }
+}
+
+/// Switches the camera between the player's eyes and a [`SpringArm`] behind them
+fn toggle_third_person(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    settings: Res<PlayerSettings>,
+    player: Single<(Entity, Option<&SpringArm>), With<Player>>,
+) {
+    if !keys.just_pressed(THIRD_PERSON_KEY) {
+        return;
+    }
+    let (entity, spring_arm) = player.into_inner();
+    match spring_arm {
+        Some(_) => commands.entity(entity).remove::<SpringArm>(),
+        None => commands.entity(entity).insert(settings.third_person),
+    };
+}
+
+fn extend_spring_arms(
+    frame_pacing: Res<FramePacing>,
+    chunks: Res<ChunkMap>,
+    origin: Res<FloatingOrigin>,
+    mut spring_arms: Query<(&Transform, &mut SpringArm)>,
+) {
+    for (transform, mut spring_arm) in &mut spring_arms {
+        spring_arm.update(
+            &chunks,
+            origin.origin(),
+            transform,
+            frame_pacing.smoothed_delta_secs(),
+        );
+    }
+}
+
+/// Where the camera of an entity with this transform and optional [`SpringArm`] is
+pub fn view_transform(transform: &Transform, spring_arm: Option<&SpringArm>) -> Transform {
+    spring_arm.map_or(*transform, |spring_arm| {
+        spring_arm.camera_transform(transform)
+    })
+}
+
+/// The wheel selects hotbar slots unless the zoom modifier is held
 pub fn zoom_player(
-    time: Res<Time>,
+    frame_pacing: Res<FramePacing>,
+    keys: Res<ButtonInput<KeyCode>>,
     mouse_scroll: Res<AccumulatedMouseScroll>,
     player: Single<&mut CameraFov, With<Player>>,
 ) {
+    if !keys.pressed(ZOOM_MODIFIER) {
+        return;
+    }
+
     let mut fov = player.into_inner();
-    fov.zoom(mouse_scroll.delta.y, SCROLL_SPEED * time.delta_secs());
+    fov.zoom(
+        mouse_scroll.delta.y,
+        SCROLL_SPEED * frame_pacing.smoothed_delta_secs(),
+    );
 }
@@ -0,0 +1,407 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs, io,
+    path::Path,
+};
+
+use bevy_app::{App, Plugin, Startup};
+use bevy_ecs::{
+    component::Component,
+    entity::Entity,
+    system::{Commands, EntityCommands, Resource},
+    world::{EntityWorldMut, Mut, World},
+};
+use data::{
+    collider::Collider,
+    instance::Instance,
+    inventory::Inventory,
+    item::ItemDrop,
+    mesh::{Mesh, MeshHandle, Meshes},
+    name::Name,
+    notification::{Notifications, Severity},
+    transform::Transform,
+    voxel::Voxel,
+};
+use ron::{extensions::Extensions, Options};
+use serde::{de::DeserializeOwned, Deserialize};
+use thiserror::Error;
+
+use crate::localization::Localization;
+
+/// Loads the [`Prefab`]s in [`PREFAB_DIR`] at startup, so entities can be authored without
+/// recompiling, and spawned by name with [`PrefabCommandsExt::spawn_prefab`]. Which
+/// components a prefab may list besides its transform, mesh and collider is opted into per
+/// component with [`PrefabAppExt::register_prefab_component`].
+pub struct PrefabPlugin;
+
+impl Plugin for PrefabPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PrefabComponents>()
+            .init_resource::<Prefabs>()
+            .register_prefab_component::<Name>("name")
+            .register_prefab_component::<Inventory>("inventory")
+            .register_prefab_component::<ItemDrop>("item_drop")
+            .add_systems(Startup, load_prefabs);
+    }
+}
+
+/// Relative to the working directory. Each `<name>.ron` in it is the prefab `name`.
+pub const PREFAB_DIR: &str = "prefabs";
+
+/// An entity as authored, e.g.
+///
+/// ```ron
+/// (
+///     transform: (scale: (0.5, 0.5, 0.5)),
+///     mesh: Cube(1.0),
+///     material: Grass,
+///     collider: (half_extents: (0.25, 0.25, 0.25)),
+///     components: {
+///         "name": "slime",
+///         "hostile": (sight: 8.0),
+///     },
+/// )
+/// ```
+///
+/// Options may be written without `Some`.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Prefab {
+    pub transform: Option<Transform>,
+    pub mesh: Option<PrefabMesh>,
+    /// Material of the mesh, stone if not given
+    pub material: Option<Voxel>,
+    pub collider: Option<Collider>,
+    /// Each component by its registered name
+    pub components: BTreeMap<String, ron::Value>,
+}
+
+impl Prefab {
+    pub fn from_ron(ron: &str) -> Result<Self, PrefabError> {
+        Ok(Options::default()
+            .with_default_extension(Extensions::IMPLICIT_SOME)
+            .from_str(ron)?)
+    }
+}
+
+/// The mesh a prefab is drawn with
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub enum PrefabMesh {
+    /// With sides this long
+    Cube(f32),
+    /// With this radius
+    Sphere(f32),
+    /// Named in code with [`Prefabs::name_mesh`]
+    Named(String),
+}
+
+#[derive(Debug, Error)]
+pub enum PrefabError {
+    #[error("no prefab named {0:?}")]
+    Unknown(String),
+    #[error("no mesh named {0:?}")]
+    UnknownMesh(String),
+    #[error("no component registered as {0:?}")]
+    UnknownComponent(String),
+    #[error("component {name:?}: {error}")]
+    Component { name: String, error: ron::Error },
+    #[error(transparent)]
+    Parse(#[from] ron::error::SpannedError),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+type InsertComponent = fn(&mut EntityWorldMut, ron::Value) -> Result<(), ron::Error>;
+
+/// Components prefabs may list, by the name they're listed under
+#[derive(Resource, Default)]
+pub struct PrefabComponents {
+    components: HashMap<&'static str, InsertComponent>,
+}
+
+impl PrefabComponents {
+    pub fn register<C: Component + DeserializeOwned>(&mut self, name: &'static str) {
+        let previous = self.components.insert(name, |entity, value| {
+            entity.insert(value.into_rust::<C>()?);
+            Ok(())
+        });
+        assert!(
+            previous.is_none(),
+            "prefab component name {name:?} registered twice"
+        );
+    }
+
+    /// Whether every component of `prefab` is registered and reads as its type
+    fn check(&self, prefab: &Prefab) -> Result<(), PrefabError> {
+        let mut scratch = World::new();
+        let mut entity = scratch.spawn_empty();
+        self.insert(&mut entity, prefab)
+    }
+
+    fn insert(&self, entity: &mut EntityWorldMut, prefab: &Prefab) -> Result<(), PrefabError> {
+        for (name, value) in &prefab.components {
+            let insert = self
+                .components
+                .get(name.as_str())
+                .ok_or_else(|| PrefabError::UnknownComponent(name.clone()))?;
+            insert(entity, value.clone()).map_err(|error| PrefabError::Component {
+                name: name.clone(),
+                error,
+            })?;
+        }
+        Ok(())
+    }
+}
+
+pub trait PrefabAppExt {
+    /// Lets prefabs list `C` under `name`
+    fn register_prefab_component<C: Component + DeserializeOwned>(
+        &mut self,
+        name: &'static str,
+    ) -> &mut Self;
+}
+
+impl PrefabAppExt for App {
+    fn register_prefab_component<C: Component + DeserializeOwned>(
+        &mut self,
+        name: &'static str,
+    ) -> &mut Self {
+        self.init_resource::<PrefabComponents>();
+        self.world_mut()
+            .resource_mut::<PrefabComponents>()
+            .register::<C>(name);
+        self
+    }
+}
+
+/// Every prefab by name, and the meshes they can be drawn with
+#[derive(Resource, Debug, Default)]
+pub struct Prefabs {
+    prefabs: HashMap<String, Prefab>,
+    /// Named in code with [`Self::name_mesh`]
+    named_meshes: HashMap<String, MeshHandle>,
+    /// Added for the shapes prefabs asked for so far
+    shape_meshes: HashMap<String, MeshHandle>,
+}
+
+impl Prefabs {
+    pub fn get(&self, name: &str) -> Option<&Prefab> {
+        self.prefabs.get(name)
+    }
+
+    /// Adds a prefab, replacing any of the same name
+    pub fn insert(&mut self, name: impl Into<String>, prefab: Prefab) {
+        self.prefabs.insert(name.into(), prefab);
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.prefabs.keys().map(String::as_str)
+    }
+
+    /// Lets prefabs use `mesh` as [`PrefabMesh::Named`]
+    pub fn name_mesh(&mut self, name: impl Into<String>, mesh: MeshHandle) {
+        self.named_meshes.insert(name.into(), mesh);
+    }
+
+    fn mesh(&mut self, mesh: &PrefabMesh, meshes: &mut Meshes) -> Result<MeshHandle, PrefabError> {
+        // Every prefab with the same shape shares its mesh
+        let key = format!("{mesh:?}");
+        if let Some(handle) = self.shape_meshes.get(&key) {
+            return Ok(*handle);
+        }
+        let shape = match mesh {
+            PrefabMesh::Named(name) => {
+                return self
+                    .named_meshes
+                    .get(name)
+                    .copied()
+                    .ok_or_else(|| PrefabError::UnknownMesh(name.clone()));
+            }
+            PrefabMesh::Cube(size) => Mesh::cube(*size),
+            PrefabMesh::Sphere(radius) => Mesh::uv_sphere(*radius, 16, 8),
+        };
+        let handle = meshes.add(shape);
+        self.shape_meshes.insert(key, handle);
+        Ok(handle)
+    }
+}
+
+/// Inserts the prefab `name`'s components into `entity`. Without [`Meshes`], e.g. on a server,
+/// prefabs aren't given their mesh.
+pub fn insert_prefab(world: &mut World, entity: Entity, name: &str) -> Result<(), PrefabError> {
+    let prefab = world
+        .resource::<Prefabs>()
+        .get(name)
+        .cloned()
+        .ok_or_else(|| PrefabError::Unknown(name.to_owned()))?;
+    let instance = match &prefab.mesh {
+        Some(mesh) if world.contains_resource::<Meshes>() => {
+            let mesh = world.resource_scope(|world, mut prefabs: Mut<Prefabs>| {
+                prefabs.mesh(mesh, &mut world.resource_mut::<Meshes>())
+            })?;
+            Some(Instance::new(mesh, prefab.material.unwrap_or(Voxel::Stone)))
+        }
+        _ => None,
+    };
+
+    world.resource_scope(|world, components: Mut<PrefabComponents>| {
+        let mut entity = world.entity_mut(entity);
+        if let Some(transform) = prefab.transform {
+            entity.insert(transform);
+        }
+        if let Some(collider) = prefab.collider {
+            entity.insert(collider);
+        }
+        if let Some(instance) = instance {
+            entity.insert(instance);
+        }
+        components.insert(&mut entity, &prefab)
+    })
+}
+
+pub trait PrefabCommandsExt {
+    /// Spawns the prefab `name` once the commands are applied. Components inserted through
+    /// the returned commands go on top of the prefab's, e.g. a [`Transform`] to place it.
+    fn spawn_prefab(&mut self, name: impl Into<String>) -> EntityCommands<'_>;
+}
+
+impl PrefabCommandsExt for Commands<'_, '_> {
+    fn spawn_prefab(&mut self, name: impl Into<String>) -> EntityCommands<'_> {
+        let name = name.into();
+        let mut entity = self.spawn_empty();
+        entity.queue(move |entity: Entity, world: &mut World| {
+            if let Err(e) = insert_prefab(world, entity, &name) {
+                let localization = world
+                    .get_resource::<Localization>()
+                    .cloned()
+                    .unwrap_or_default();
+                Notifications::sender_or_log(world.get_resource::<Notifications>()).send(
+                    Severity::Error,
+                    localization.format(
+                        "prefab-spawn-failed",
+                        &[("prefab", name.into()), ("error", e.to_string().into())],
+                    ),
+                );
+            }
+        });
+        entity
+    }
+}
+
+/// Reads the prefabs in `dir`, each checked against the registered components
+fn read_prefabs(
+    dir: &Path,
+    components: &PrefabComponents,
+) -> io::Result<Vec<(String, Result<Prefab, PrefabError>)>> {
+    let mut read = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().is_none_or(|extension| extension != "ron") {
+            continue;
+        }
+        let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+            continue;
+        };
+        let prefab = fs::read_to_string(&path)
+            .map_err(PrefabError::from)
+            .and_then(|ron| Prefab::from_ron(&ron))
+            .and_then(|prefab| components.check(&prefab).map(|()| prefab));
+        read.push((name.to_owned(), prefab));
+    }
+    read.sort_by(|(a, _), (b, _)| a.cmp(b));
+    Ok(read)
+}
+
+/// At startup, once every plugin has registered its components
+fn load_prefabs(world: &mut World) {
+    let localization = world
+        .get_resource::<Localization>()
+        .cloned()
+        .unwrap_or_default();
+    let notifications = Notifications::sender_or_log(world.get_resource::<Notifications>());
+    let read = match read_prefabs(Path::new(PREFAB_DIR), world.resource::<PrefabComponents>()) {
+        Ok(read) => read,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return,
+        Err(e) => {
+            notifications.send(
+                Severity::Error,
+                localization.format(
+                    "prefab-load-failed",
+                    &[
+                        ("prefab", PREFAB_DIR.into()),
+                        ("error", e.to_string().into()),
+                    ],
+                ),
+            );
+            return;
+        }
+    };
+    let mut prefabs = world.resource_mut::<Prefabs>();
+    for (name, prefab) in read {
+        match prefab {
+            Ok(prefab) => prefabs.insert(name, prefab),
+            Err(e) => notifications.send(
+                Severity::Error,
+                localization.format(
+                    "prefab-load-failed",
+                    &[("prefab", name.into()), ("error", e.to_string().into())],
+                ),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use data::prelude::Vec3;
+
+    use super::*;
+
+    #[test]
+    fn prefabs_spawn_with_their_registered_components() {
+        let mut app = App::new();
+        app.init_resource::<Meshes>().add_plugins(PrefabPlugin);
+        let prefab = Prefab::from_ron(
+            r#"(
+                transform: (translation: (1.0, 2.0, 3.0)),
+                mesh: Cube(0.5),
+                material: Grass,
+                collider: (half_extents: (0.25, 0.25, 0.25)),
+                components: { "name": "slime" },
+            )"#,
+        )
+        .unwrap();
+        let components = app.world().resource::<PrefabComponents>();
+        components.check(&prefab).unwrap();
+        let mut unknown = prefab.clone();
+        unknown
+            .components
+            .insert("wings".to_owned(), ron::Value::Unit);
+        assert!(matches!(
+            components.check(&unknown),
+            Err(PrefabError::UnknownComponent(name)) if name == "wings"
+        ));
+
+        let world = app.world_mut();
+        world.resource_mut::<Prefabs>().insert("slime", prefab);
+        let first = world.spawn_empty().id();
+        insert_prefab(world, first, "slime").unwrap();
+        let second = world.spawn_empty().id();
+        insert_prefab(world, second, "slime").unwrap();
+        assert!(matches!(
+            insert_prefab(world, second, "ghost"),
+            Err(PrefabError::Unknown(_))
+        ));
+
+        let slime = world.entity(first);
+        assert_eq!(slime.get::<Name>().unwrap().as_str(), "slime");
+        assert_eq!(
+            slime.get::<Transform>().unwrap().translation,
+            Vec3::new(1.0, 2.0, 3.0)
+        );
+        assert_eq!(slime.get::<Instance>().unwrap().material, Voxel::Grass);
+        assert!(slime.contains::<Collider>());
+        // Both share one cube
+        assert_eq!(world.resource::<Meshes>().len(), 1);
+    }
+}
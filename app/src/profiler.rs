@@ -0,0 +1,112 @@
+//! Spans are recorded through the `profiling` crate and compile to nothing unless one of the
+//! `profile-with-*` features picks a backend
+
+use std::{
+    mem,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use bevy_app::{App, Main, MainScheduleOrder, Plugin};
+use bevy_ecs::{
+    schedule::{ExecutorKind, Schedule},
+    system::Local,
+    world::{Mut, World},
+};
+
+/// Starts the enabled backend. Must run before the first instrumented function.
+pub fn start() {
+    #[cfg(feature = "profile-with-tracy")]
+    profiling::tracy_client::Client::start();
+
+    // Scopes are recorded for a viewer such as puffin_viewer to connect to
+    #[cfg(feature = "profile-with-puffin")]
+    profiling::puffin::set_scopes_on(true);
+}
+
+/// With a backend enabled, runs each schedule of [`Main`] (`PreUpdate`, `Update` and so on)
+/// inside a span named after it, so the backends show where a frame's CPU time goes stage by
+/// stage
+pub struct ScheduleSpansPlugin;
+
+impl Plugin for ScheduleSpansPlugin {
+    fn build(&self, app: &mut App) {
+        if cfg!(any(
+            feature = "profile-with-puffin",
+            feature = "profile-with-tracy"
+        )) {
+            // Replaces the schedule holding `Main::run_main`, set up the same way
+            let mut main = Schedule::new(Main);
+            main.set_executor_kind(ExecutorKind::SingleThreaded);
+            main.add_systems(run_main_with_spans);
+            app.add_schedule(main);
+        }
+    }
+}
+
+/// [`Main::run_main`] with a span around each schedule
+fn run_main_with_spans(world: &mut World, mut run_at_least_once: Local<bool>) {
+    world.resource_scope(|world, order: Mut<MainScheduleOrder>| {
+        if !*run_at_least_once {
+            for &label in &order.startup_labels {
+                profiling::scope!("startup schedule", &format!("{label:?}"));
+                let _ = world.try_run_schedule(label);
+            }
+            *run_at_least_once = true;
+        }
+        for &label in &order.labels {
+            profiling::scope!("schedule", &format!("{label:?}"));
+            let _ = world.try_run_schedule(label);
+        }
+    });
+}
+
+/// CPU time an instrumented system took, see [`span`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SystemSpan {
+    pub name: &'static str,
+    pub duration: Duration,
+}
+
+static RECORDING: AtomicBool = AtomicBool::new(false);
+static SPANS: Mutex<Vec<SystemSpan>> = Mutex::new(Vec::new());
+
+/// Records the time until it's dropped, see [`span`]
+#[must_use]
+pub struct Span {
+    name: &'static str,
+    start: Option<Instant>,
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        if let Some(start) = self.start {
+            SPANS.lock().unwrap().push(SystemSpan {
+                name: self.name,
+                duration: start.elapsed(),
+            });
+        }
+    }
+}
+
+/// Times the rest of the scope for the frame breakdown of the diagnostics overlay while
+/// [`set_recording`] is on. Unlike the backends' spans these are recorded in every build, so
+/// only the systems that can take a frame's worth of time are timed.
+pub fn span(name: &'static str) -> Span {
+    Span {
+        name,
+        start: RECORDING.load(Ordering::Relaxed).then(Instant::now),
+    }
+}
+
+pub fn set_recording(enabled: bool) {
+    RECORDING.store(enabled, Ordering::Relaxed);
+}
+
+/// The spans finished since the last call, in the order they finished
+pub fn take_spans() -> Vec<SystemSpan> {
+    mem::take(&mut *SPANS.lock().unwrap())
+}
@@ -0,0 +1,490 @@
+use std::{fmt, io};
+
+use bevy_app::{App, Plugin, Update};
+use bevy_ecs::{
+    event::{Event, EventReader},
+    system::Res,
+};
+use data::notification::{Notifications, Severity};
+use thiserror::Error;
+
+use crate::localization::Localization;
+
+/// Bumped whenever a message's layout changes, so peers from different builds refuse each
+/// other at the handshake rather than misreading each other's packets
+pub const PROTOCOL_VERSION: u16 = 1;
+
+/// Starts every [`Hello`] and [`Reply`], so anything that isn't a vx peer is turned away
+/// before its bytes are read as a version
+const MAGIC: [u8; 4] = *b"VXNP";
+
+/// Largest packet payload, before or after decompression, so a hostile peer can't make the
+/// other side allocate without limit
+pub const MAX_PACKET_SIZE: usize = 16 << 20;
+
+/// Payloads smaller than this go out uncompressed, since zstd's frame costs more than it saves
+pub const COMPRESSION_THRESHOLD: usize = 256;
+
+/// Reports the connections whose handshake failed, in the player's language, e.g. that the
+/// server runs a newer version. There is no transport yet: whatever ends up carrying the
+/// bytes runs the handshake with [`Hello`], [`negotiate`] and [`Reply`], frames its packets
+/// with the negotiated [`Connection`], and sends [`HandshakeFailed`] when it's turned away.
+pub struct ProtocolPlugin;
+
+impl Plugin for ProtocolPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<HandshakeFailed>()
+            .add_systems(Update, report_handshake_failures);
+    }
+}
+
+/// Optional protocol features, one bit each. Bits a peer doesn't know are ignored, so a newer
+/// peer can offer features an older one never heard of.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Capabilities(u32);
+
+impl Capabilities {
+    pub const NONE: Self = Self(0);
+    /// Packets may be compressed with zstd
+    pub const ZSTD: Self = Self(1 << 0);
+
+    /// Everything this build can do
+    pub const SUPPORTED: Self = Self::ZSTD;
+
+    pub const fn from_bits(bits: u32) -> Self {
+        Self(bits)
+    }
+
+    pub const fn bits(&self) -> u32 {
+        self.0
+    }
+
+    pub const fn contains(&self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub const fn union(&self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    /// The features both sides have
+    pub const fn intersection(&self, other: Self) -> Self {
+        Self(self.0 & other.0)
+    }
+}
+
+impl fmt::Debug for Capabilities {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Capabilities({:#b})", self.0)
+    }
+}
+
+/// How a connection's packets are compressed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Compression {
+    #[default]
+    None,
+    /// Higher levels are smaller and slower; see [`zstd::compression_level_range`]
+    Zstd { level: i32 },
+}
+
+impl Compression {
+    /// A cheap level that still roughly halves chunk data
+    pub const DEFAULT_ZSTD: Self = Self::Zstd { level: 3 };
+
+    const NONE_TAG: u8 = 0;
+    const ZSTD_TAG: u8 = 1;
+
+    fn write(&self, bytes: &mut Vec<u8>) {
+        match self {
+            Self::None => bytes.extend_from_slice(&[Self::NONE_TAG, 0, 0, 0, 0]),
+            Self::Zstd { level } => {
+                bytes.push(Self::ZSTD_TAG);
+                bytes.extend_from_slice(&level.to_le_bytes());
+            }
+        }
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, HandshakeError> {
+        let tag = reader.u8()?;
+        let level = reader.i32()?;
+        match tag {
+            Self::NONE_TAG => Ok(Self::None),
+            Self::ZSTD_TAG => Ok(Self::Zstd { level }),
+            tag => Err(HandshakeError::UnknownCompression(tag)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum HandshakeError {
+    #[error("not a vx peer")]
+    NotVx,
+    #[error("handshake cut short")]
+    Truncated,
+    #[error("protocol version mismatch: {local} here, {remote} on the other side")]
+    VersionMismatch { local: u16, remote: u16 },
+    #[error("unknown compression {0}")]
+    UnknownCompression(u8),
+}
+
+impl HandshakeError {
+    pub fn localized(&self, localization: &Localization) -> String {
+        match self {
+            Self::VersionMismatch { local, remote } => localization.format(
+                "protocol-version-mismatch",
+                &[("local", (*local).into()), ("remote", (*remote).into())],
+            ),
+            e => localization.format("handshake-failed", &[("error", e.to_string().into())]),
+        }
+    }
+}
+
+/// Reads a handshake message front to back
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], HandshakeError> {
+        let (bytes, rest) = self
+            .0
+            .split_first_chunk()
+            .ok_or(HandshakeError::Truncated)?;
+        self.0 = rest;
+        Ok(*bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, HandshakeError> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, HandshakeError> {
+        self.take().map(u16::from_le_bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32, HandshakeError> {
+        self.take().map(u32::from_le_bytes)
+    }
+
+    fn i32(&mut self) -> Result<i32, HandshakeError> {
+        self.take().map(i32::from_le_bytes)
+    }
+
+    /// Checks the magic and returns the sender's protocol version
+    fn header(&mut self) -> Result<u16, HandshakeError> {
+        if self.take()? != MAGIC {
+            return Err(HandshakeError::NotVx);
+        }
+        self.u16()
+    }
+}
+
+/// What the connecting side sends first: its version, its capabilities and the compression
+/// it would like. The accepting side describes itself with one too, to [`negotiate`] from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hello {
+    pub version: u16,
+    pub capabilities: Capabilities,
+    /// The strongest compression this side is willing to spend time on
+    pub compression: Compression,
+}
+
+impl Default for Hello {
+    fn default() -> Self {
+        Self {
+            version: PROTOCOL_VERSION,
+            capabilities: Capabilities::SUPPORTED,
+            compression: Compression::DEFAULT_ZSTD,
+        }
+    }
+}
+
+impl Hello {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&self.version.to_le_bytes());
+        bytes.extend_from_slice(&self.capabilities.bits().to_le_bytes());
+        self.compression.write(&mut bytes);
+        bytes
+    }
+
+    /// Only the header is read from a peer of another version, whose layout may differ
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, HandshakeError> {
+        let mut reader = Reader(bytes);
+        let version = reader.header()?;
+        if version != PROTOCOL_VERSION {
+            return Err(HandshakeError::VersionMismatch {
+                local: PROTOCOL_VERSION,
+                remote: version,
+            });
+        }
+        Ok(Self {
+            version,
+            capabilities: Capabilities::from_bits(reader.u32()?),
+            compression: Compression::read(&mut reader)?,
+        })
+    }
+}
+
+/// What both sides agreed on, for the rest of the connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Negotiated {
+    pub capabilities: Capabilities,
+    pub compression: Compression,
+}
+
+/// The accepting side's answer to a [`Hello`]. A rejection carries the accepting side's
+/// version, so the connecting side can tell the player which one needs updating.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reply {
+    Accepted(Negotiated),
+    Rejected { version: u16 },
+}
+
+impl Reply {
+    const ACCEPTED_TAG: u8 = 0;
+    const REJECTED_TAG: u8 = 1;
+
+    /// The reply to the result of [`negotiate`]
+    pub fn new(negotiated: &Result<Negotiated, HandshakeError>) -> Self {
+        match negotiated {
+            Ok(negotiated) => Self::Accepted(*negotiated),
+            Err(_) => Self::Rejected {
+                version: PROTOCOL_VERSION,
+            },
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&PROTOCOL_VERSION.to_le_bytes());
+        match self {
+            Self::Accepted(negotiated) => {
+                bytes.push(Self::ACCEPTED_TAG);
+                bytes.extend_from_slice(&negotiated.capabilities.bits().to_le_bytes());
+                negotiated.compression.write(&mut bytes);
+            }
+            Self::Rejected { .. } => bytes.push(Self::REJECTED_TAG),
+        }
+        bytes
+    }
+
+    /// What the connecting side makes of the reply: the connection's settings, or why it was
+    /// turned away
+    pub fn from_bytes(bytes: &[u8]) -> Result<Negotiated, HandshakeError> {
+        let mut reader = Reader(bytes);
+        let version = reader.header()?;
+        let mismatch = HandshakeError::VersionMismatch {
+            local: PROTOCOL_VERSION,
+            remote: version,
+        };
+        if version != PROTOCOL_VERSION {
+            return Err(mismatch);
+        }
+        match reader.u8()? {
+            Self::ACCEPTED_TAG => Ok(Negotiated {
+                capabilities: Capabilities::from_bits(reader.u32()?),
+                compression: Compression::read(&mut reader)?,
+            }),
+            _ => Err(mismatch),
+        }
+    }
+}
+
+/// Run by the accepting side on the connecting side's `hello`, with its own as `local`.
+/// Capabilities are the ones both have, and compression is zstd only if both offer it, at
+/// the lower of the two levels.
+pub fn negotiate(hello: &Hello, local: &Hello) -> Result<Negotiated, HandshakeError> {
+    if hello.version != local.version {
+        return Err(HandshakeError::VersionMismatch {
+            local: local.version,
+            remote: hello.version,
+        });
+    }
+    let capabilities = hello.capabilities.intersection(local.capabilities);
+    let compression = match (hello.compression, local.compression) {
+        (Compression::Zstd { level: a }, Compression::Zstd { level: b })
+            if capabilities.contains(Capabilities::ZSTD) =>
+        {
+            Compression::Zstd { level: a.min(b) }
+        }
+        _ => Compression::None,
+    };
+    Ok(Negotiated {
+        capabilities,
+        compression,
+    })
+}
+
+#[derive(Debug, Error)]
+pub enum PacketError {
+    #[error("packet cut short")]
+    Truncated,
+    #[error("packet of {0} bytes is over the limit")]
+    TooLarge(usize),
+    #[error("compressed packet on a connection without compression")]
+    UnexpectedCompression,
+    #[error("unknown packet flag {0}")]
+    UnknownFlag(u8),
+    #[error("could not compress or decompress a packet: {0}")]
+    Zstd(#[from] io::Error),
+}
+
+/// Frames the packets of an established connection: a little-endian length, a byte telling
+/// whether the payload is compressed, then the payload
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Connection {
+    pub negotiated: Negotiated,
+}
+
+impl Connection {
+    const HEADER_SIZE: usize = 5;
+
+    pub const fn new(negotiated: Negotiated) -> Self {
+        Self { negotiated }
+    }
+
+    pub fn encode(&self, payload: &[u8]) -> Result<Vec<u8>, PacketError> {
+        if payload.len() > MAX_PACKET_SIZE {
+            return Err(PacketError::TooLarge(payload.len()));
+        }
+        let (compressed, body) = match self.negotiated.compression {
+            Compression::Zstd { level } if payload.len() >= COMPRESSION_THRESHOLD => {
+                (true, zstd::bulk::compress(payload, level)?)
+            }
+            _ => (false, payload.to_vec()),
+        };
+        let mut packet = Vec::with_capacity(Self::HEADER_SIZE + body.len());
+        packet.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        packet.push(compressed as u8);
+        packet.extend_from_slice(&body);
+        Ok(packet)
+    }
+
+    /// Decodes the first packet in `bytes`, returning its payload and the bytes it took up,
+    /// or `None` if it hasn't fully arrived yet
+    pub fn decode(&self, bytes: &[u8]) -> Result<Option<(Vec<u8>, usize)>, PacketError> {
+        let Some((header, rest)) = bytes.split_first_chunk::<{ Self::HEADER_SIZE }>() else {
+            return Ok(None);
+        };
+        let len = u32::from_le_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        if len > MAX_PACKET_SIZE {
+            return Err(PacketError::TooLarge(len));
+        }
+        let Some(body) = rest.get(..len) else {
+            return Ok(None);
+        };
+        let payload = match header[4] {
+            0 => body.to_vec(),
+            1 if self.negotiated.compression == Compression::None => {
+                return Err(PacketError::UnexpectedCompression)
+            }
+            1 => zstd::bulk::decompress(body, MAX_PACKET_SIZE)?,
+            flag => return Err(PacketError::UnknownFlag(flag)),
+        };
+        Ok(Some((payload, Self::HEADER_SIZE + len)))
+    }
+}
+
+/// A connection turned away at the handshake
+#[derive(Event, Debug, Clone, PartialEq, Eq)]
+pub struct HandshakeFailed {
+    /// Whom the connection was with, e.g. a server address or a player's name
+    pub peer: String,
+    pub error: HandshakeError,
+}
+
+fn report_handshake_failures(
+    localization: Res<Localization>,
+    notifications: Option<Res<Notifications>>,
+    mut failed_reader: EventReader<HandshakeFailed>,
+) {
+    let notifications = Notifications::sender_or_log(notifications.as_deref());
+    for failed in failed_reader.read() {
+        notifications.send(
+            Severity::Error,
+            format!("{}: {}", failed.peer, failed.error.localized(&localization)),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn handshake_agrees_on_the_weaker_compression() {
+        let client = Hello {
+            compression: Compression::Zstd { level: 9 },
+            ..Default::default()
+        };
+        let server = Hello::default();
+        let hello = Hello::from_bytes(&client.to_bytes()).unwrap();
+        assert_eq!(hello, client);
+        let negotiated = negotiate(&hello, &server);
+        assert_eq!(
+            Reply::from_bytes(&Reply::new(&negotiated).to_bytes()),
+            Ok(Negotiated {
+                capabilities: Capabilities::SUPPORTED,
+                compression: Compression::DEFAULT_ZSTD,
+            })
+        );
+
+        // A peer without zstd, but with a feature from a newer build
+        let older = Hello {
+            capabilities: Capabilities::from_bits(1 << 7),
+            ..Default::default()
+        };
+        assert_eq!(
+            negotiate(&older, &server).unwrap(),
+            Negotiated {
+                capabilities: Capabilities::NONE,
+                compression: Compression::None,
+            }
+        );
+
+        let newer = Hello {
+            version: PROTOCOL_VERSION + 1,
+            ..Default::default()
+        };
+        let mismatch = HandshakeError::VersionMismatch {
+            local: PROTOCOL_VERSION,
+            remote: PROTOCOL_VERSION + 1,
+        };
+        assert_eq!(Hello::from_bytes(&newer.to_bytes()), Err(mismatch.clone()));
+        assert_eq!(negotiate(&newer, &server), Err(mismatch));
+        assert_eq!(
+            Hello::from_bytes(b"GET / HTTP/1.1"),
+            Err(HandshakeError::NotVx)
+        );
+        assert_eq!(Hello::from_bytes(&MAGIC), Err(HandshakeError::Truncated));
+    }
+
+    #[test]
+    fn packets_round_trip_and_wait_for_the_rest() {
+        let connection = Connection::new(Negotiated {
+            capabilities: Capabilities::SUPPORTED,
+            compression: Compression::DEFAULT_ZSTD,
+        });
+        let large = vec![7; 4096];
+        let small = b"hi".to_vec();
+        let mut stream = connection.encode(&large).unwrap();
+        assert!(stream.len() < large.len());
+        stream.extend(connection.encode(&small).unwrap());
+
+        let (payload, used) = connection.decode(&stream).unwrap().unwrap();
+        assert_eq!(payload, large);
+        assert_eq!(connection.decode(&stream[used..used + 3]).unwrap(), None);
+        let (payload, rest) = connection.decode(&stream[used..]).unwrap().unwrap();
+        assert_eq!(payload, small);
+        assert_eq!(used + rest, stream.len());
+
+        let uncompressed = Connection::new(Negotiated {
+            capabilities: Capabilities::NONE,
+            compression: Compression::None,
+        });
+        assert!(matches!(
+            uncompressed.decode(&stream),
+            Err(PacketError::UnexpectedCompression)
+        ));
+    }
+}
@@ -1,44 +1,195 @@
+use std::{collections::VecDeque, fs, time::Instant};
+
 use bevy_app::{App, Last, Plugin, Startup, Update};
 use bevy_ecs::{
+    change_detection::DetectChanges,
     entity::Entity,
     event::{Event, EventReader},
-    query::With,
-    system::{Commands, NonSend, Res, ResMut, Single},
+    query::{Changed, Or, With},
+    removal_detection::RemovedComponents,
+    schedule::{
+        common_conditions::{resource_exists, resource_exists_and_changed},
+        IntoSystemConfigs,
+    },
+    system::{Commands, Local, NonSend, Query, Res, ResMut, Resource, Single, SystemParam},
 };
-use bevy_window::{PrimaryWindow, RawHandleWrapper, Window};
+use bevy_window::{PrimaryWindow, RawHandleWrapper, Window, WindowOccluded, WindowResized};
 use bevy_winit::WinitWindows;
 use data::{
+    accessibility::AccessibilitySettings,
     camera::{CameraFov, CameraGpu},
-    transform::Transform,
+    exposure::{AutoExposure, Exposure},
+    floating_origin::FloatingOrigin,
+    haptics::Haptics,
+    instance::{batch_instances, Instance},
+    light::{gather_lights, PointLight, SpotLight},
+    mesh::Meshes,
+    prelude::{IVec3, Quat, Vec2},
+    rng::RngStream,
+    skinning::{skin_mesh, Skin},
+    spring_arm::SpringArm,
+    transform::{PreviousTransform, Transform},
+    voxel_block::VoxelBlock,
+    weather::Weather,
+    worldgen::{generate_chunk, WorldSeed},
 };
-use glam::Vec2;
 use renderer::{
-    acceleration_structure_state::AccelerationStructureState, buffer_state::BufferState,
-    command_state::CommandState, init_state::InitState, pipeline_state::PipelineState,
-    swapchain_state::SwapchainState, CurrentFrame,
+    acceleration_structure_state::AccelerationStructureState,
+    blue_noise::BlueNoise,
+    buffer_state::BufferState,
+    capabilities::RenderPath,
+    command_state::{CommandState, FramePath},
+    compute_state::{ComputeState, VoxelGrid},
+    hud::Hud,
+    init_state::InitState,
+    picking::PickHit,
+    pipeline_state::PipelineState,
+    raster_state::{RasterShading, RasterState},
+    settings::RendererSettings,
+    swapchain_state::SwapchainState,
+    tracking::tracker,
+    CurrentFrame,
 };
 
-use crate::player_plugin::Player;
+use crate::{
+    frame_pacing_plugin::FramePacing,
+    loading_plugin::LoadingProgress,
+    localization::Localization,
+    player_plugin::{view_transform, Player},
+    profiler,
+    task_plugin::{TaskGroup, TaskPools},
+    time_plugin::FixedTimestep,
+};
 
 pub struct RenderPlugin;
 
 #[derive(Event)]
 pub struct CleanupEvent;
 
+/// What is under the crosshair, when `RendererSettings::picking` is enabled
+#[derive(Resource, Debug, Default)]
+pub struct Picked {
+    pub hit: Option<PickHit>,
+    /// Entity of the hit instance; `None` for the static scene
+    pub entity: Option<Entity>,
+}
+
+/// Why frames are currently being skipped. A minimized window has no extent to render at and
+/// an occluded one isn't visible, so both pause rendering until the window comes back; the
+/// swapchain is recreated on the resize that restores it.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RenderSuspended {
+    pub minimized: bool,
+    pub occluded: bool,
+}
+
+impl RenderSuspended {
+    pub fn is_suspended(&self) -> bool {
+        self.minimized || self.occluded
+    }
+}
+
+/// The resources of whichever [`RenderPath`] `setup` picked for the device
+#[derive(SystemParam)]
+pub struct RenderPathState<'w> {
+    pipeline_state: Option<ResMut<'w, PipelineState<'static>>>,
+    acceleration_structure_state: Option<ResMut<'w, AccelerationStructureState<'static>>>,
+    raster_state: Option<ResMut<'w, RasterState>>,
+    compute_state: Option<ResMut<'w, ComputeState<'static>>>,
+    pending: Option<Res<'w, PendingPathStates>>,
+}
+
+impl RenderPathState<'_> {
+    /// A splash frame until every resource of the path has been built
+    pub fn frame_path(&mut self) -> FramePath<'_, 'static> {
+        if self.pending.is_some() {
+            return FramePath::Splash;
+        }
+        if let Some(compute_state) = &mut self.compute_state {
+            return FramePath::Compute(compute_state);
+        }
+        match (
+            &self.pipeline_state,
+            &mut self.acceleration_structure_state,
+            &mut self.raster_state,
+        ) {
+            (Some(pipeline_state), Some(acceleration_structure_state), None) => {
+                FramePath::RayTracing {
+                    pipeline_state,
+                    acceleration_structure_state,
+                }
+            }
+            (_, Some(acceleration_structure_state), Some(raster_state)) => FramePath::Hybrid {
+                acceleration_structure_state,
+                raster_state,
+            },
+            (_, None, Some(raster_state)) => FramePath::Raster(raster_state),
+            _ => panic!("render path resources are inserted at startup"),
+        }
+    }
+}
+
 impl Plugin for RenderPlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<CleanupEvent>()
             .init_resource::<CurrentFrame>()
+            .init_resource::<RendererSettings>()
+            .init_resource::<Meshes>()
+            .init_resource::<Picked>()
+            .init_resource::<Hud>()
+            .init_resource::<RenderSuspended>()
+            .init_resource::<LoadingProgress>()
             .add_systems(Startup, setup)
-            .add_systems(Update, update)
+            .add_systems(
+                Update,
+                (
+                    update_render_suspended,
+                    build_path_state.run_if(resource_exists::<PendingPathStates>),
+                    apply_settings,
+                    update_instances.run_if(resource_exists::<AccelerationStructureState>),
+                    follow_floating_origin
+                        .run_if(resource_exists::<ComputeState>)
+                        .run_if(resource_exists_and_changed::<FloatingOrigin>),
+                    update.run_if(rendering_active),
+                    update_exposure,
+                    update_picked.run_if(resource_exists::<AccelerationStructureState>),
+                )
+                    .chain(),
+            )
             .add_systems(Last, cleanup);
     }
 }
 
+/// Read at startup and written on exit, relative to the working directory
+pub const PIPELINE_CACHE_PATH: &str = "pipeline_cache.bin";
+
+/// Name of the renderer's work in [`LoadingProgress`]
+const LOADING_WORK: &str = "render path";
+
+/// Resources of the render path still to be built, one per frame while loading, in this
+/// order since the acceleration structures need the ray tracing pipeline
+#[derive(Resource, Debug)]
+pub struct PendingPathStates {
+    states: VecDeque<PathStateKind>,
+    total: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum PathStateKind {
+    RayTracingPipeline,
+    AccelerationStructures,
+    Raster(RasterShading),
+    Compute,
+}
+
+/// Creates what the splash screen needs to be drawn; the path's own pipelines are built by
+/// [`build_path_state`] over the following frames
 fn setup(
     mut commands: Commands,
     window: Single<(Entity, &Window), With<PrimaryWindow>>,
     winit_windows: NonSend<WinitWindows>,
+    settings: Res<RendererSettings>,
+    mut progress: ResMut<LoadingProgress>,
 ) {
     let (window_entity, window) = window.into_inner();
 
@@ -50,58 +201,451 @@ fn setup(
 
     commands.entity(window_entity).insert(wrapper);
 
-    let init_state = InitState::new("Hello", 1, display_handle, window_handle).unwrap();
-
-    let swapchain_state =
-        SwapchainState::new(&init_state, Vec2::new(window.width(), window.height())).unwrap();
-
-    let pipeline_state = PipelineState::new(&init_state).unwrap();
-
-    let buffer_state = BufferState::new(&init_state).unwrap();
+    let init_state = InitState::new(
+        "Hello",
+        1,
+        display_handle,
+        window_handle,
+        settings.render_path,
+        // A missing or stale cache only means compiling from scratch
+        &fs::read(PIPELINE_CACHE_PATH).unwrap_or_default(),
+    )
+    .unwrap();
 
-    let acceleration_structure_state = AccelerationStructureState::new(
+    let swapchain_state = SwapchainState::new(
         &init_state,
-        &swapchain_state,
-        &pipeline_state,
-        &buffer_state,
+        Vec2::new(window.width(), window.height()),
+        &settings,
     )
     .unwrap();
 
+    let buffer_state = BufferState::new(&init_state, &BlueNoise::default()).unwrap();
+
     let command_state = CommandState::new(&init_state).unwrap();
 
+    let render_path = init_state.render_path();
+    let mut states = VecDeque::new();
+    if render_path.uses_acceleration_structures() {
+        // The hybrid path still builds its TLAS through the ray tracing pipeline's state
+        states.push_back(PathStateKind::RayTracingPipeline);
+        states.push_back(PathStateKind::AccelerationStructures);
+    }
+    match render_path {
+        RenderPath::RayTracing => (),
+        RenderPath::Hybrid => states.push_back(PathStateKind::Raster(RasterShading::RayQuery)),
+        RenderPath::Raster => states.push_back(PathStateKind::Raster(RasterShading::Flat)),
+        RenderPath::Compute => states.push_back(PathStateKind::Compute),
+    }
+    progress.expect(LOADING_WORK, states.len() as u32);
+    commands.insert_resource(PendingPathStates {
+        total: states.len(),
+        states,
+    });
+
+    commands.insert_resource(render_path);
     commands.insert_resource(init_state);
     commands.insert_resource(swapchain_state);
-    commands.insert_resource(pipeline_state);
     commands.insert_resource(buffer_state);
-    commands.insert_resource(acceleration_structure_state);
     commands.insert_resource(command_state);
 }
 
-fn update(
+/// Builds the next of the [`PendingPathStates`]. Each can block for a while compiling
+/// pipelines, so only one is built per frame and the loading bar moves in between.
+#[allow(clippy::too_many_arguments)]
+fn build_path_state(
+    mut commands: Commands,
+    init_state: Res<InitState>,
+    swapchain_state: Res<SwapchainState>,
+    buffer_state: Res<BufferState<'static>>,
+    settings: Res<RendererSettings>,
+    tasks: Res<TaskPools>,
+    pipeline_state: Option<Res<PipelineState<'static>>>,
+    mut pending: ResMut<PendingPathStates>,
+    mut progress: ResMut<LoadingProgress>,
+) {
+    let Some(kind) = pending.states.pop_front() else {
+        commands.remove_resource::<PendingPathStates>();
+        return;
+    };
+    match kind {
+        PathStateKind::RayTracingPipeline => {
+            commands.insert_resource(PipelineState::new(&init_state, &settings).unwrap());
+        }
+        PathStateKind::AccelerationStructures => {
+            let pipeline_state = pipeline_state.expect("built in the frame before");
+            commands.insert_resource(
+                AccelerationStructureState::new(
+                    &init_state,
+                    &swapchain_state,
+                    &pipeline_state,
+                    &buffer_state,
+                )
+                .unwrap(),
+            );
+        }
+        PathStateKind::Raster(shading) => {
+            commands
+                .insert_resource(RasterState::new(&init_state, &swapchain_state, shading).unwrap());
+        }
+        PathStateKind::Compute => {
+            commands.insert_resource(
+                ComputeState::new(
+                    &init_state,
+                    &swapchain_state,
+                    &buffer_state,
+                    generate_compute_grid(&tasks),
+                )
+                .unwrap(),
+            );
+        }
+    }
+    let done = pending.total - pending.states.len();
+    progress.set(LOADING_WORK, done as u32, pending.total as u32);
+    if pending.states.is_empty() {
+        commands.remove_resource::<PendingPathStates>();
+    }
+}
+
+/// Chunks around the origin marched by [`RenderPath::Compute`], in chunk coordinates. Fixed
+/// for now: the grid is uploaded once and doesn't follow the player.
+const COMPUTE_GRID_CHUNKS: (IVec3, IVec3) = (IVec3::new(-4, -2, -4), IVec3::new(4, 2, 4));
+
+fn generate_compute_grid(tasks: &TaskPools) -> VoxelGrid {
+    let (min, max) = COMPUTE_GRID_CHUNKS;
+    let width = VoxelBlock::WIDTH as i32;
+    let mut grid = VoxelGrid::new(min * width, ((max - min) * width).as_uvec3());
+
+    let chunks: Vec<IVec3> = (min.y..max.y)
+        .flat_map(|y| (min.z..max.z).flat_map(move |z| (min.x..max.x).map(move |x| (x, y, z))))
+        .map(|(x, y, z)| IVec3::new(x, y, z))
+        .collect();
+    let generated = tasks.scope(TaskGroup::Compute, |scope| {
+        for chunk in chunks {
+            scope.spawn(async move { (chunk, generate_chunk(WorldSeed(0), chunk)) });
+        }
+    });
+    for (chunk, data) in &generated {
+        grid.insert_chunk(*chunk, data);
+    }
+    grid
+}
+
+fn update_render_suspended(
+    mut resized_reader: EventReader<WindowResized>,
+    mut occluded_reader: EventReader<WindowOccluded>,
+    mut suspended: ResMut<RenderSuspended>,
+    window: Single<Entity, With<PrimaryWindow>>,
+) {
+    for resize in resized_reader.read() {
+        if resize.window == *window {
+            suspended.minimized = resize.width <= 0.0 || resize.height <= 0.0;
+        }
+    }
+    for occlusion in occluded_reader.read() {
+        if occlusion.window == *window {
+            suspended.occluded = occlusion.occluded;
+        }
+    }
+}
+
+fn rendering_active(suspended: Res<RenderSuspended>) -> bool {
+    !suspended.is_suspended()
+}
+
+/// Rebuilds the pipeline when a baked-in shader constant changed and recreates the swapchain
+/// when the present mode or render scale changed. Restarts accumulation since the converged
+/// image depends on the other settings.
+#[profiling::function]
+fn apply_settings(
+    settings: Res<RendererSettings>,
     init_state: Res<InitState>,
     mut swapchain_state: ResMut<SwapchainState>,
-    mut buffer_state: ResMut<BufferState<'static>>,
+    mut path_state: RenderPathState,
+    mut command_state: ResMut<CommandState>,
+    window: Single<&Window, With<PrimaryWindow>>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    if let Some(pipeline_state) = &mut path_state.pipeline_state {
+        pipeline_state
+            .apply_settings(&init_state, &settings)
+            .unwrap();
+    }
+    if swapchain_state.apply_settings(&settings) {
+        path_state
+            .frame_path()
+            .recreate_swapchain(
+                &init_state,
+                &mut swapchain_state,
+                Vec2::new(window.width(), window.height()),
+            )
+            .unwrap();
+    }
+    command_state.reset_accumulation();
+}
+
+/// Instances follow the origin through their transforms, but the compute path's grid is in
+/// world space
+fn follow_floating_origin(
+    origin: Res<FloatingOrigin>,
+    mut compute_state: ResMut<ComputeState<'static>>,
+) {
+    compute_state.set_render_origin(origin.origin());
+}
+
+type ChangedInstance = (With<Instance>, Or<(Changed<Transform>, Changed<Instance>)>);
+
+type ChangedSkin = (With<Skin>, Or<(Changed<Skin>, Changed<Instance>)>);
+
+type InterpolatedInstance<'a> = (
+    Entity,
+    &'a Transform,
+    Option<&'a PreviousTransform>,
+    &'a Instance,
+);
+
+/// Uploads new meshes and rebuilds the TLAS whenever an instance is added, moved or removed,
+/// and every frame while one is being interpolated between ticks. Instances with a [`Skin`]
+/// are skinned on the CPU into BLASes of their own whenever their pose changes.
+#[allow(clippy::too_many_arguments)]
+#[profiling::function]
+pub fn update_instances(
+    init_state: Res<InitState>,
     pipeline_state: Res<PipelineState<'static>>,
     mut acceleration_structure_state: ResMut<AccelerationStructureState<'static>>,
+    command_state: Res<CommandState>,
+    settings: Res<RendererSettings>,
+    current_frame: Res<CurrentFrame>,
+    meshes: Res<Meshes>,
+    timestep: Res<FixedTimestep>,
+    instances: Query<InterpolatedInstance>,
+    changed: Query<(), ChangedInstance>,
+    mut removed: RemovedComponents<Instance>,
+    skins: Query<(Entity, &Instance, &Skin)>,
+    changed_skins: Query<(), ChangedSkin>,
+    mut removed_skins: RemovedComponents<Skin>,
+) {
+    let _span = profiler::span("update_instances");
+    if meshes.is_changed() {
+        acceleration_structure_state
+            .sync_meshes(&init_state, &pipeline_state, &meshes)
+            .unwrap();
+    }
+
+    let skins_changed = !changed_skins.is_empty() || removed_skins.read().count() > 0;
+    if skins_changed {
+        let skinned: Vec<_> = skins
+            .iter()
+            .filter_map(|(entity, instance, skin)| {
+                let mesh = meshes.get(instance.mesh)?;
+                skin_mesh(mesh, &skin.skeleton.joint_matrices(&skin.pose))
+                    .inspect_err(|e| eprintln!("Not skinning {entity}: {e}"))
+                    .ok()
+                    .map(|mesh| (entity, mesh))
+            })
+            .collect();
+        command_state
+            .wait_for_frame(
+                &init_state,
+                current_frame.0,
+                settings.clamped_frames_in_flight(),
+            )
+            .unwrap();
+        acceleration_structure_state
+            .update_skins(&init_state, &pipeline_state, &skinned)
+            .unwrap();
+    }
+
+    let removed_any = removed.read().count() > 0;
+    let interpolating = instances.iter().any(|(_, transform, previous, _)| {
+        previous.is_some_and(|previous| previous.0 != *transform)
+    });
+    if changed.is_empty() && !removed_any && !interpolating && !skins_changed {
+        return;
+    }
+
+    let instances: Vec<_> = instances
+        .iter()
+        .map(|(entity, transform, previous, instance)| {
+            (entity, timestep.interpolate(transform, previous), instance)
+        })
+        .collect();
+    acceleration_structure_state
+        .update_instances(
+            &init_state,
+            &pipeline_state,
+            &batch_instances(
+                instances
+                    .iter()
+                    .map(|(entity, transform, instance)| (*entity, transform, *instance)),
+            ),
+        )
+        .unwrap();
+}
+
+/// What changes the view besides the player's camera
+#[derive(SystemParam)]
+struct ViewEffects<'w, 's> {
+    weather: Option<Res<'w, Weather>>,
+    haptics: Option<Res<'w, Haptics>>,
+    accessibility: Res<'w, AccessibilitySettings>,
+    /// Camera shakes so far, seeding each one
+    shakes: Local<'s, u64>,
+}
+
+type PlayerCamera<'a> = (
+    &'a Transform,
+    Option<&'a PreviousTransform>,
+    &'a CameraFov,
+    Option<&'a Exposure>,
+    Option<&'a SpringArm>,
+);
+
+#[allow(clippy::too_many_arguments)]
+#[profiling::function]
+fn update(
+    init_state: Res<InitState>,
+    mut swapchain_state: ResMut<SwapchainState>,
+    mut buffer_state: ResMut<BufferState<'static>>,
+    mut path_state: RenderPathState,
     mut command_state: ResMut<CommandState>,
+    settings: Res<RendererSettings>,
+    hud: Res<Hud>,
     mut current_frame: ResMut<CurrentFrame>,
+    mut frame_pacing: ResMut<FramePacing>,
+    timestep: Res<FixedTimestep>,
+    mut effects: ViewEffects,
     window: Single<&Window, With<PrimaryWindow>>,
-    player: Single<(&Transform, &CameraFov), With<Player>>,
+    player: Single<PlayerCamera, With<Player>>,
+    point_lights: Query<(&Transform, Option<&PreviousTransform>, &PointLight)>,
+    spot_lights: Query<(&Transform, Option<&PreviousTransform>, &SpotLight)>,
+    instances: Query<(&Transform, Option<&PreviousTransform>, &Instance)>,
 ) {
-    let (transform, fov) = player.into_inner();
+    let _span = profiler::span("update");
+    let (transform, previous, fov, exposure, spring_arm) = player.into_inner();
+    let transform = &view_transform(&timestep.interpolate(transform, previous), spring_arm);
+    // Heavy impacts play on the strong motor, and shake the view with it
+    let shake = effects
+        .haptics
+        .as_deref()
+        .map_or(0.0, |haptics| haptics.intensity().strong_motor)
+        * effects.accessibility.camera_shake.clamp(0.0, 1.0);
+    let transform = &if shake > 0.0 {
+        *effects.shakes += 1;
+        shaken(transform, shake, *effects.shakes)
+    } else {
+        *transform
+    };
+    let point_lights: Vec<_> = point_lights
+        .iter()
+        .map(|(transform, previous, light)| (timestep.interpolate(transform, previous), light))
+        .collect();
+    let spot_lights: Vec<_> = spot_lights
+        .iter()
+        .map(|(transform, previous, light)| (timestep.interpolate(transform, previous), light))
+        .collect();
+    let instances: Vec<_> = instances
+        .iter()
+        .map(|(transform, previous, instance)| {
+            (timestep.interpolate(transform, previous), instance)
+        })
+        .collect();
+    let lights = gather_lights(
+        transform.translation,
+        point_lights
+            .iter()
+            .map(|(transform, light)| (transform, *light)),
+        spot_lights
+            .iter()
+            .map(|(transform, light)| (transform, *light)),
+        instances
+            .iter()
+            .map(|(transform, instance)| (transform, *instance)),
+    );
     command_state
         .draw_frame(
             &init_state,
             &mut swapchain_state,
-            &pipeline_state,
+            path_state.frame_path(),
             &mut buffer_state,
-            &mut acceleration_structure_state,
+            &settings,
+            &hud,
             Vec2::new(window.width(), window.height()),
-            CameraGpu::new(transform, fov.degrees(), window.width(), window.height()),
+            CameraGpu {
+                exposure: exposure.map_or(1.0, Exposure::multiplier),
+                sunlight: effects.weather.as_deref().map_or(1.0, Weather::sunlight),
+                color_filter: effects.accessibility.color_filter.to_gpu(),
+                ..CameraGpu::new(transform, fov.degrees(), window.width(), window.height())
+            },
+            &lights,
             current_frame.0,
         )
         .unwrap();
+    match swapchain_state.present_waiter() {
+        Some(waiter) => frame_pacing.record_displayed(waiter.last_id(), waiter.take_presented()),
+        None => frame_pacing.record_present(Instant::now()),
+    }
     current_frame.0 = current_frame.next();
+    profiling::finish_frame!();
+}
+
+/// Radians the view turns by at most at full shake
+const MAX_SHAKE_RADIANS: f32 = 0.02;
+
+/// `transform` turned by a yaw and pitch of up to `strength` times [`MAX_SHAKE_RADIANS`],
+/// different for every `shake`
+fn shaken(transform: &Transform, strength: f32, shake: u64) -> Transform {
+    let mut rng = RngStream::new(shake);
+    let mut angle = || (rng.next_f32() * 2.0 - 1.0) * strength * MAX_SHAKE_RADIANS;
+    let (yaw, pitch) = (angle(), angle());
+    Transform {
+        rotation: transform.rotation * Quat::from_rotation_y(yaw) * Quat::from_rotation_x(pitch),
+        ..*transform
+    }
+}
+
+/// Asks for a luminance histogram while the player's exposure is automatic, and adapts it to
+/// each one read back. Photosensitive mode caps how fast it adapts, so the screen never
+/// brightens or darkens abruptly.
+fn update_exposure(
+    mut command_state: ResMut<CommandState>,
+    frame_pacing: Res<FramePacing>,
+    accessibility: Res<AccessibilitySettings>,
+    player: Single<Option<&mut Exposure>, With<Player>>,
+) {
+    let Some(mut exposure) = player.into_inner() else {
+        command_state.set_luminance_histogram(false);
+        return;
+    };
+    command_state.set_luminance_histogram(exposure.auto.is_some());
+    let Some(histogram) = command_state.take_luminance_histogram() else {
+        return;
+    };
+    let delta = frame_pacing.smoothed_delta_secs();
+    match exposure.auto {
+        Some(auto) if accessibility.photosensitive => {
+            let auto = AutoExposure {
+                speed: auto
+                    .speed
+                    .min(AccessibilitySettings::PHOTOSENSITIVE_EXPOSURE_SPEED),
+                ..auto
+            };
+            exposure.ev = auto.adapt(exposure.ev, &histogram, delta);
+        }
+        _ => exposure.adapt(&histogram, delta),
+    }
+}
+
+fn update_picked(
+    command_state: Res<CommandState>,
+    acceleration_structure_state: Res<AccelerationStructureState<'static>>,
+    mut picked: ResMut<Picked>,
+) {
+    let hit = command_state.last_pick();
+    picked.hit = hit;
+    picked.entity = hit.and_then(|hit| acceleration_structure_state.instance_entity(hit.instance));
 }
 
 fn cleanup(
@@ -109,17 +653,55 @@ fn cleanup(
     init_state: Res<InitState>,
     swapchain_state: Res<SwapchainState>,
     mut buffer_state: ResMut<BufferState<'static>>,
-    mut pipeline_state: ResMut<PipelineState<'static>>,
-    mut acceleration_structure_state: ResMut<AccelerationStructureState<'static>>,
-    command_state: Res<CommandState>,
+    mut path_state: RenderPathState,
+    mut command_state: ResMut<CommandState>,
+    localization: Res<Localization>,
 ) {
     for _ in cleanup_reader.read() {
-        println!("Goodbye!");
+        println!("{}", localization.text("goodbye"));
         init_state.wait_idle().unwrap();
+        match init_state.pipeline_cache_data() {
+            Ok(data) => {
+                if let Err(e) = fs::write(PIPELINE_CACHE_PATH, data) {
+                    eprintln!(
+                        "{}",
+                        localization.format(
+                            "save-failed",
+                            &[
+                                ("path", PIPELINE_CACHE_PATH.into()),
+                                ("error", e.to_string().into()),
+                            ],
+                        )
+                    );
+                }
+            }
+            Err(e) => eprintln!(
+                "{}",
+                localization.format(
+                    "pipeline-cache-read-failed",
+                    &[("error", e.to_string().into())],
+                )
+            ),
+        }
         command_state.cleanup(&init_state);
-        acceleration_structure_state.cleanup(&init_state);
+        if let Some(acceleration_structure_state) = &mut path_state.acceleration_structure_state {
+            acceleration_structure_state.cleanup(&init_state);
+        }
         buffer_state.cleanup(&init_state);
-        pipeline_state.cleanup(&init_state);
+        if let Some(pipeline_state) = &mut path_state.pipeline_state {
+            pipeline_state.cleanup(&init_state);
+        }
+        if let Some(raster_state) = &path_state.raster_state {
+            raster_state.cleanup(&init_state);
+        }
+        if let Some(compute_state) = &mut path_state.compute_state {
+            compute_state.cleanup(&init_state);
+        }
         swapchain_state.cleanup(&init_state);
+
+        let report = tracker().report();
+        if !report.is_empty() {
+            eprint!("{report}");
+        }
     }
 }
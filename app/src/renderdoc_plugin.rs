@@ -0,0 +1,98 @@
+//! Only with the `renderdoc` feature. Captures are taken through RenderDoc's in-app API, so the
+//! game has to be launched from RenderDoc, or with its library injected; otherwise the plugin
+//! does nothing. Render graph passes show up as labeled regions in captures either way.
+
+use std::env;
+
+use bevy_app::{App, Plugin, Update};
+use bevy_ecs::system::{NonSendMut, Res, ResMut, Resource};
+use bevy_input::{keyboard::KeyCode, ButtonInput};
+use renderdoc::{InputButton, RenderDoc, V141};
+
+use crate::localization::Localization;
+
+/// Triggers RenderDoc frame captures from [`CAPTURE_KEY`], or on the frames listed in
+/// [`CAPTURE_FRAMES_VAR`] so captures can be scripted
+pub struct RenderDocPlugin;
+
+impl Plugin for RenderDocPlugin {
+    fn build(&self, app: &mut App) {
+        let mut renderdoc = match RenderDoc::<V141>::new() {
+            Ok(renderdoc) => renderdoc,
+            Err(e) => {
+                println!("RenderDoc isn't attached, frame capture is off: {e}");
+                return;
+            }
+        };
+        // RenderDoc's own default is F12, which takes photos
+        renderdoc.set_capture_keys::<InputButton>(&[]);
+        renderdoc.set_capture_file_path_template(CAPTURE_PATH_TEMPLATE);
+
+        app.insert_non_send_resource(renderdoc)
+            .insert_resource(ScheduledCaptures::from_env())
+            .add_systems(Update, trigger_capture);
+    }
+}
+
+pub const CAPTURE_KEY: KeyCode = KeyCode::F7;
+
+/// Comma-separated frame numbers to capture, counted from startup, e.g. `VX_CAPTURE_FRAMES=1,300`
+pub const CAPTURE_FRAMES_VAR: &str = "VX_CAPTURE_FRAMES";
+
+/// Relative to the working directory; RenderDoc appends the frame number and extension
+const CAPTURE_PATH_TEMPLATE: &str = "captures/vx";
+
+#[derive(Resource, Debug, Default)]
+struct ScheduledCaptures {
+    frame: u64,
+    /// Latest first, so the next one is at the end
+    frames: Vec<u64>,
+}
+
+impl ScheduledCaptures {
+    fn from_env() -> Self {
+        let Ok(value) = env::var(CAPTURE_FRAMES_VAR) else {
+            return Self::default();
+        };
+        let mut frames: Vec<u64> = Vec::new();
+        for frame in value
+            .split(',')
+            .map(str::trim)
+            .filter(|frame| !frame.is_empty())
+        {
+            match frame.parse() {
+                Ok(frame) => frames.push(frame),
+                Err(e) => eprintln!("Ignoring {frame:?} in {CAPTURE_FRAMES_VAR}: {e}"),
+            }
+        }
+        frames.sort_unstable_by(|a, b| b.cmp(a));
+        frames.dedup();
+        Self { frame: 0, frames }
+    }
+
+    /// Counts a frame, returning whether it was scheduled
+    fn advance(&mut self) -> bool {
+        self.frame += 1;
+        let mut due = false;
+        while self.frames.last().is_some_and(|frame| *frame <= self.frame) {
+            due |= self.frames.pop() == Some(self.frame);
+        }
+        due
+    }
+}
+
+/// RenderDoc captures the next frame presented after the trigger
+fn trigger_capture(
+    keys: Res<ButtonInput<KeyCode>>,
+    localization: Res<Localization>,
+    mut scheduled: ResMut<ScheduledCaptures>,
+    mut renderdoc: NonSendMut<RenderDoc<V141>>,
+) {
+    if scheduled.advance() || keys.just_pressed(CAPTURE_KEY) {
+        renderdoc.trigger_capture();
+        println!(
+            "{}",
+            localization.format("capturing-frame", &[("frame", scheduled.frame.into())])
+        );
+    }
+}
@@ -0,0 +1,199 @@
+use std::{collections::BTreeMap, error::Error, fmt};
+
+use bevy_ecs::system::Resource;
+
+/// Upgrades a save document from one version to the next, in place
+pub type Migrate = fn(&mut toml::Table) -> Result<(), MigrationError>;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Migration {
+    /// Version the migration upgrades from, to the one after it
+    pub from: u32,
+    /// What changed, logged when the migration is applied
+    pub description: &'static str,
+    pub migrate: Migrate,
+}
+
+/// Steps that upgrade older world saves to
+/// [`WorldSave::VERSION`](crate::save_plugin::WorldSave::VERSION), one version at a time, so
+/// format changes don't orphan existing worlds. A change to the save format bumps the version
+/// and registers the step from the previous one with
+/// [`PersistenceAppExt::add_save_migration`](crate::save_plugin::PersistenceAppExt::add_save_migration).
+#[derive(Resource, Debug, Clone)]
+pub struct SaveMigrations {
+    migrations: BTreeMap<u32, Migration>,
+}
+
+impl Default for SaveMigrations {
+    fn default() -> Self {
+        let mut migrations = Self {
+            migrations: BTreeMap::new(),
+        };
+        migrations.register(Migration {
+            from: 0,
+            description: "tag a save from before versioning",
+            migrate: |_| Ok(()),
+        });
+        migrations
+    }
+}
+
+impl SaveMigrations {
+    pub fn register(&mut self, migration: Migration) {
+        assert!(
+            !self.migrations.contains_key(&migration.from),
+            "save migration from version {} registered twice",
+            migration.from
+        );
+        self.migrations.insert(migration.from, migration);
+    }
+
+    /// Upgrades `save` from the version it is tagged with, or 0 if untagged, to `to`, and tags
+    /// it. Returns the migrations applied, oldest first.
+    pub fn migrate(
+        &self,
+        save: &mut toml::Table,
+        to: u32,
+    ) -> Result<Vec<Migration>, MigrationError> {
+        let mut version = match save.get(VERSION_KEY) {
+            None => 0,
+            Some(toml::Value::Integer(version)) => {
+                u32::try_from(*version).map_err(|_| MigrationError::InvalidVersion)?
+            }
+            Some(_) => return Err(MigrationError::InvalidVersion),
+        };
+        if version > to {
+            return Err(MigrationError::TooNew {
+                version,
+                supported: to,
+            });
+        }
+
+        let mut applied = Vec::new();
+        while version < to {
+            let migration = self
+                .migrations
+                .get(&version)
+                .ok_or(MigrationError::Missing { from: version })?;
+            (migration.migrate)(save)?;
+            applied.push(*migration);
+            version += 1;
+        }
+        save.insert(VERSION_KEY.to_owned(), toml::Value::Integer(to.into()));
+        Ok(applied)
+    }
+}
+
+/// Key the version is saved under
+const VERSION_KEY: &str = "version";
+
+/// Runs `migrate` on the component saved as `name` on every saved entity that has one, e.g.
+/// to rename a field
+pub fn migrate_components(
+    save: &mut toml::Table,
+    name: &str,
+    mut migrate: impl FnMut(&mut toml::Value) -> Result<(), MigrationError>,
+) -> Result<(), MigrationError> {
+    let Some(toml::Value::Array(entities)) = save.get_mut("entities") else {
+        return Ok(());
+    };
+    for entity in entities {
+        let component = entity
+            .get_mut("components")
+            .and_then(|components| components.get_mut(name));
+        if let Some(component) = component {
+            migrate(component)?;
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MigrationError {
+    /// Written by a newer version of the game
+    TooNew {
+        version: u32,
+        supported: u32,
+    },
+    /// No migration is registered from the version
+    Missing {
+        from: u32,
+    },
+    InvalidVersion,
+    /// A migration couldn't make sense of the save
+    Failed(String),
+}
+
+impl fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooNew { version, supported } => write!(
+                f,
+                "save version {version} is newer than the supported version {supported}"
+            ),
+            Self::Missing { from } => write!(f, "no migration from save version {from}"),
+            Self::InvalidVersion => write!(f, "save version is not a valid version number"),
+            Self::Failed(reason) => write!(f, "migration failed: {reason}"),
+        }
+    }
+}
+
+impl Error for MigrationError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saves_upgrade_one_version_at_a_time() {
+        let mut migrations = SaveMigrations::default();
+        migrations.register(Migration {
+            from: 1,
+            description: "rename health to hit_points",
+            migrate: |save| {
+                migrate_components(save, "health", |health| {
+                    let table = health
+                        .as_table_mut()
+                        .ok_or_else(|| MigrationError::Failed("health is not a table".into()))?;
+                    if let Some(current) = table.remove("current") {
+                        table.insert("hit_points".into(), current);
+                    }
+                    Ok(())
+                })
+            },
+        });
+
+        let mut save: toml::Table = toml::from_str(
+            r#"
+            [[entities]]
+            unique = "player"
+            components.health = { current = 7 }
+
+            [[entities]]
+            components.transform = { translation = [0.0, 0.0, 0.0] }
+            "#,
+        )
+        .unwrap();
+        let applied = migrations.migrate(&mut save, 2).unwrap();
+        assert_eq!(applied.iter().map(|m| m.from).collect::<Vec<_>>(), [0, 1]);
+        assert_eq!(save["version"].as_integer(), Some(2));
+        assert_eq!(
+            save["entities"][0]["components"]["health"]["hit_points"].as_integer(),
+            Some(7)
+        );
+
+        // Already current, nothing to do
+        assert!(migrations.migrate(&mut save, 2).unwrap().is_empty());
+        assert_eq!(
+            migrations.migrate(&mut save, 1).unwrap_err(),
+            MigrationError::TooNew {
+                version: 2,
+                supported: 1
+            }
+        );
+        assert_eq!(
+            migrations.migrate(&mut save, 4).unwrap_err(),
+            MigrationError::Missing { from: 2 }
+        );
+    }
+}
@@ -0,0 +1,475 @@
+use std::{collections::BTreeMap, error::Error, fs, io, path::Path};
+
+use bevy_app::{App, Plugin, Update};
+use bevy_ecs::{
+    component::Component,
+    entity::Entity,
+    system::Resource,
+    world::{EntityRef, EntityWorldMut, World},
+};
+use bevy_input::{keyboard::KeyCode, ButtonInput};
+use data::{
+    block_tick::{BlockTicks, GameTick, ScheduledTick},
+    floating_origin::FloatingOrigin,
+    notification::{Notifications, Severity},
+    prelude::IVec3,
+    transform::Transform,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    localization::Localization,
+    save_migration::{Migration, SaveMigrations},
+    task_plugin::{TaskGroup, TaskPools},
+};
+
+/// Read from and written to the working directory
+pub const WORLD_SAVE_PATH: &str = "world.toml";
+
+pub const SAVE_KEY: KeyCode = KeyCode::F5;
+pub const LOAD_KEY: KeyCode = KeyCode::F9;
+
+/// Name [`Transform`] is saved under
+const TRANSFORM: &str = "transform";
+
+/// Saves entities marked [`Persistent`] to [`WORLD_SAVE_PATH`] and loads them back, upgrading
+/// saves from older versions with the [`SaveMigrations`]. Which of their components are saved
+/// is opted into per component with [`PersistenceAppExt::register_persistent`].
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PersistentComponents>()
+            .init_resource::<SaveMigrations>()
+            .register_persistent::<Transform>(TRANSFORM)
+            .add_systems(Update, quick_save_and_load);
+    }
+}
+
+/// Marks an entity for world saves
+#[derive(Component, Debug, Clone, PartialEq, Eq)]
+pub enum Persistent {
+    /// Exists whether or not a save is loaded, e.g. the player. Loading restores its
+    /// components onto the entity with the same key.
+    Unique(String),
+    /// Created during play, e.g. item drops. Loading despawns these and respawns the saved
+    /// ones.
+    Spawned,
+}
+
+impl Persistent {
+    pub fn unique(key: impl Into<String>) -> Self {
+        Self::Unique(key.into())
+    }
+}
+
+/// Entities section of a world save, along with the simulation state they live in
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WorldSave {
+    /// Format the save was written in; see [`Self::VERSION`]
+    pub version: u32,
+    /// [`FloatingOrigin`] the saved translations are relative to
+    pub origin: IVec3,
+    pub tick: GameTick,
+    /// Pending [`BlockTicks`], due relative to `tick`
+    pub block_ticks: Vec<ScheduledTick>,
+    pub entities: Vec<SavedEntity>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SavedEntity {
+    /// Key of a [`Persistent::Unique`] entity; `None` for spawned ones
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unique: Option<String>,
+    /// Each saved component by its registered name
+    #[serde(default)]
+    pub components: BTreeMap<String, toml::Value>,
+}
+
+impl WorldSave {
+    /// Current save format. Bumped, with a [`Migration`] from the previous version, whenever
+    /// the format or the meaning of saved values changes.
+    pub const VERSION: u32 = 1;
+
+    /// A missing file is not an error and yields an empty save. Older saves are upgraded with
+    /// `migrations` first, which are returned to be logged.
+    pub fn load(
+        path: impl AsRef<Path>,
+        migrations: &SaveMigrations,
+    ) -> Result<(Self, Vec<Migration>), Box<dyn Error>> {
+        match fs::read_to_string(path) {
+            Ok(contents) => {
+                let mut save: toml::Table = toml::from_str(&contents)?;
+                let applied = migrations.migrate(&mut save, Self::VERSION)?;
+                Ok((save.try_into()?, applied))
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok((
+                Self {
+                    version: Self::VERSION,
+                    ..Self::default()
+                },
+                Vec::new(),
+            )),
+            Err(e) => Err(Box::new(e)),
+        }
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Box<dyn Error>> {
+        fs::write(path, toml::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+type SaveComponent = fn(&EntityRef) -> Option<Result<toml::Value, toml::ser::Error>>;
+type LoadComponent = fn(&mut EntityWorldMut, toml::Value) -> Result<(), toml::de::Error>;
+
+/// Components written to world saves, by the name they are saved under
+#[derive(Resource, Default)]
+pub struct PersistentComponents {
+    components: Vec<(&'static str, SaveComponent, LoadComponent)>,
+}
+
+impl PersistentComponents {
+    /// `name` must stay the same across versions for existing saves to keep loading
+    pub fn register<C: Component + Serialize + DeserializeOwned>(&mut self, name: &'static str) {
+        assert!(
+            self.components.iter().all(|(other, ..)| *other != name),
+            "persistent component name {name:?} registered twice"
+        );
+        self.components.push((
+            name,
+            |entity| entity.get::<C>().map(toml::Value::try_from),
+            |entity, value| {
+                entity.insert(value.try_into::<C>()?);
+                Ok(())
+            },
+        ));
+    }
+}
+
+pub trait PersistenceAppExt {
+    /// Saves `C` on every [`Persistent`] entity that has it
+    fn register_persistent<C: Component + Serialize + DeserializeOwned>(
+        &mut self,
+        name: &'static str,
+    ) -> &mut Self;
+
+    /// Upgrades saves from `migration.from` to the next version
+    fn add_save_migration(&mut self, migration: Migration) -> &mut Self;
+}
+
+impl PersistenceAppExt for App {
+    fn register_persistent<C: Component + Serialize + DeserializeOwned>(
+        &mut self,
+        name: &'static str,
+    ) -> &mut Self {
+        self.init_resource::<PersistentComponents>();
+        self.world_mut()
+            .resource_mut::<PersistentComponents>()
+            .register::<C>(name);
+        self
+    }
+
+    fn add_save_migration(&mut self, migration: Migration) -> &mut Self {
+        self.init_resource::<SaveMigrations>();
+        self.world_mut()
+            .resource_mut::<SaveMigrations>()
+            .register(migration);
+        self
+    }
+}
+
+/// The registered components of every [`Persistent`] entity
+pub fn save_world(world: &World) -> Result<WorldSave, toml::ser::Error> {
+    let registered = world.resource::<PersistentComponents>();
+    let mut entities = Vec::new();
+    for entity in world.iter_entities() {
+        let Some(persistent) = entity.get::<Persistent>() else {
+            continue;
+        };
+        let mut components = BTreeMap::new();
+        for (name, save, _) in &registered.components {
+            if let Some(value) = save(&entity) {
+                components.insert(name.to_string(), value?);
+            }
+        }
+        entities.push(SavedEntity {
+            unique: match persistent {
+                Persistent::Unique(key) => Some(key.clone()),
+                Persistent::Spawned => None,
+            },
+            components,
+        });
+    }
+    Ok(WorldSave {
+        version: WorldSave::VERSION,
+        origin: current_origin(world),
+        tick: world
+            .get_resource::<GameTick>()
+            .copied()
+            .unwrap_or_default(),
+        block_ticks: world
+            .get_resource::<BlockTicks>()
+            .map(Vec::from)
+            .unwrap_or_default(),
+        entities,
+    })
+}
+
+fn current_origin(world: &World) -> IVec3 {
+    world
+        .get_resource::<FloatingOrigin>()
+        .map_or(IVec3::ZERO, FloatingOrigin::origin)
+}
+
+/// Replaces the spawned persistent entities with the saved ones and restores unique entities'
+/// components. Components no longer registered are skipped. Saved translations are moved from
+/// the saved origin to the current one. The game tick and block ticks are restored as saved.
+pub fn load_world(world: &mut World, save: &WorldSave) -> Result<(), toml::de::Error> {
+    let shift = (save.origin - current_origin(world)).as_vec3();
+    let localization = world
+        .get_resource::<Localization>()
+        .cloned()
+        .unwrap_or_default();
+    if world.contains_resource::<GameTick>() {
+        world.insert_resource(save.tick);
+    }
+    if world.contains_resource::<BlockTicks>() {
+        world.insert_resource(BlockTicks::from(save.block_ticks.clone()));
+    }
+    let loaders: BTreeMap<&'static str, LoadComponent> = world
+        .resource::<PersistentComponents>()
+        .components
+        .iter()
+        .map(|(name, _, load)| (*name, *load))
+        .collect();
+
+    let mut persistent = world.query::<(Entity, &Persistent)>();
+    let mut unique = BTreeMap::new();
+    let mut spawned = Vec::new();
+    for (entity, persistent) in persistent.iter(world) {
+        match persistent {
+            Persistent::Unique(key) => {
+                unique.insert(key.clone(), entity);
+            }
+            Persistent::Spawned => spawned.push(entity),
+        }
+    }
+    for entity in spawned {
+        world.despawn(entity);
+    }
+
+    for saved in &save.entities {
+        let entity = match &saved.unique {
+            Some(key) => match unique.get(key) {
+                Some(entity) => *entity,
+                None => world.spawn(Persistent::Unique(key.clone())).id(),
+            },
+            None => world.spawn(Persistent::Spawned).id(),
+        };
+        let mut entity = world.entity_mut(entity);
+        for (name, value) in &saved.components {
+            match loaders.get(name.as_str()) {
+                Some(load) => load(&mut entity, value.clone())?,
+                None => eprintln!(
+                    "{}",
+                    localization.format("unknown-saved-component", &[("name", name.into())])
+                ),
+            }
+        }
+        if saved.components.contains_key(TRANSFORM) {
+            if let Some(mut transform) = entity.get_mut::<Transform>() {
+                transform.translation += shift;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Reads the save at `path`, logging the migrations it needed, and loads it with
+/// [`load_world`]
+pub fn load_world_file(world: &mut World, path: &str) -> Result<(), Box<dyn Error>> {
+    let migrations = world
+        .get_resource::<SaveMigrations>()
+        .cloned()
+        .unwrap_or_default();
+    let (save, applied) = WorldSave::load(path, &migrations)?;
+    if !applied.is_empty() {
+        let localization = world
+            .get_resource::<Localization>()
+            .cloned()
+            .unwrap_or_default();
+        let notifications = Notifications::sender_or_log(world.get_resource::<Notifications>());
+        for migration in applied {
+            notifications.send(
+                Severity::Warning,
+                localization.format(
+                    "save-migrated",
+                    &[
+                        ("path", path.into()),
+                        ("from", migration.from.into()),
+                        ("description", migration.description.into()),
+                    ],
+                ),
+            );
+        }
+    }
+    load_world(world, &save)?;
+    Ok(())
+}
+
+fn quick_save_and_load(world: &mut World) {
+    // Headless apps have no keyboard
+    let Some(keys) = world.get_resource::<ButtonInput<KeyCode>>() else {
+        return;
+    };
+    let (save, load) = (keys.just_pressed(SAVE_KEY), keys.just_pressed(LOAD_KEY));
+    let localization = world
+        .get_resource::<Localization>()
+        .cloned()
+        .unwrap_or_default();
+    let notifications = Notifications::sender_or_log(world.get_resource::<Notifications>());
+
+    if save {
+        match save_world(world) {
+            Ok(save) => world
+                .resource::<TaskPools>()
+                .spawn(TaskGroup::Io, async move {
+                    match save.save(WORLD_SAVE_PATH) {
+                        Ok(()) => notifications.send(
+                            Severity::Info,
+                            localization.format("saved", &[("path", WORLD_SAVE_PATH.into())]),
+                        ),
+                        Err(e) => notifications.send(
+                            Severity::Error,
+                            localization.format(
+                                "save-failed",
+                                &[
+                                    ("path", WORLD_SAVE_PATH.into()),
+                                    ("error", e.to_string().into()),
+                                ],
+                            ),
+                        ),
+                    }
+                })
+                .detach(),
+            Err(e) => notifications.send(
+                Severity::Error,
+                localization.format("world-save-failed", &[("error", e.to_string().into())]),
+            ),
+        }
+    } else if load {
+        match load_world_file(world, WORLD_SAVE_PATH) {
+            Ok(()) => notifications.send(
+                Severity::Info,
+                localization.format("loaded", &[("path", WORLD_SAVE_PATH.into())]),
+            ),
+            Err(e) => notifications.send(
+                Severity::Error,
+                localization.format(
+                    "load-failed",
+                    &[
+                        ("path", WORLD_SAVE_PATH.into()),
+                        ("error", e.to_string().into()),
+                    ],
+                ),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use data::{
+        inventory::{Inventory, ItemStack},
+        item::{ItemDrop, ItemId},
+        prelude::Vec3,
+        voxel::Voxel,
+    };
+
+    use super::*;
+
+    fn world() -> World {
+        let mut world = World::new();
+        let mut components = PersistentComponents::default();
+        components.register::<Transform>(TRANSFORM);
+        components.register::<Inventory>("inventory");
+        components.register::<ItemDrop>("item_drop");
+        world.insert_resource(components);
+        world
+    }
+
+    #[test]
+    fn entities_round_trip_through_a_save() {
+        let mut world = world();
+        let mut inventory = Inventory::new(4);
+        inventory.set(2, Some(ItemStack::new(ItemId(1), 5)));
+        let player = world
+            .spawn((
+                Persistent::unique("player"),
+                Transform::from_translation(Vec3::new(1.0, 2.0, 3.0)),
+                inventory.clone(),
+            ))
+            .id();
+        world.spawn((
+            Persistent::Spawned,
+            ItemDrop(ItemStack::new(ItemId(3), 1)),
+            Transform::from_translation(Vec3::X),
+        ));
+        // Not persistent, so never saved
+        world.spawn(Transform::default());
+        world.insert_resource(GameTick(40));
+        let mut block_ticks = BlockTicks::default();
+        block_ticks.schedule(GameTick(45), IVec3::new(-3, 7, 2), Voxel::Water);
+        world.insert_resource(block_ticks.clone());
+
+        let contents = toml::to_string_pretty(&save_world(&world).unwrap()).unwrap();
+        let save: WorldSave = toml::from_str(&contents).unwrap();
+        assert_eq!(save.entities.len(), 2);
+
+        // Play on, then load: the player moves back and the drop isn't duplicated
+        *world.get_mut::<Transform>(player).unwrap() = Transform::default();
+        world.get_mut::<Inventory>(player).unwrap().set(2, None);
+        world.insert_resource(GameTick(90));
+        world.insert_resource(BlockTicks::default());
+        load_world(&mut world, &save).unwrap();
+        assert_eq!(*world.resource::<GameTick>(), GameTick(40));
+        assert_eq!(*world.resource::<BlockTicks>(), block_ticks);
+
+        assert_eq!(
+            world.get::<Transform>(player).unwrap().translation,
+            Vec3::new(1.0, 2.0, 3.0)
+        );
+        assert_eq!(world.get::<Inventory>(player), Some(&inventory));
+        let mut drops = world.query::<&ItemDrop>();
+        assert_eq!(
+            drops.iter(&world).collect::<Vec<_>>(),
+            [&ItemDrop(ItemStack::new(ItemId(3), 1))]
+        );
+    }
+
+    #[test]
+    fn translations_follow_the_floating_origin() {
+        let mut world = world();
+        let mut origin = FloatingOrigin::default();
+        origin.rebase(Vec3::new(2000.0, 0.0, 0.0)).unwrap();
+        world.insert_resource(origin);
+        let player = world
+            .spawn((
+                Persistent::unique("player"),
+                Transform::from_translation(Vec3::X),
+            ))
+            .id();
+        let save = save_world(&world).unwrap();
+        assert_eq!(save.origin, IVec3::new(2000, 0, 0));
+
+        // Loaded after a restart, before the origin has moved out again
+        world.insert_resource(FloatingOrigin::default());
+        load_world(&mut world, &save).unwrap();
+        assert_eq!(
+            world.get::<Transform>(player).unwrap().translation,
+            Vec3::new(2001.0, 0.0, 0.0)
+        );
+    }
+}
@@ -0,0 +1,292 @@
+use std::{error::Error, fs, path::Path};
+
+use bevy_app::{App, Plugin, Update};
+use bevy_ecs::{
+    query::With,
+    schedule::IntoSystemConfigs,
+    system::{Res, ResMut, Resource, Single},
+};
+use bevy_input::{keyboard::KeyCode, ButtonInput};
+use bevy_window::{PrimaryWindow, Window};
+use data::{
+    camera::{CameraFov, CameraGpu},
+    floating_origin::FloatingOrigin,
+    prelude::{BVec3, IVec3, Vec3},
+    schematic::{fill, replace, Region, Schematic, SchematicFile},
+    spring_arm::SpringArm,
+    transform::Transform,
+    voxel::Voxel,
+};
+use renderer::hud::{Hud, HudRect};
+
+use crate::{
+    hud_plugin::{build_hud, Hotbar},
+    localization::Localization,
+    player_plugin::{move_player, view_transform, Player},
+    render_plugin::Picked,
+    task_plugin::{TaskGroup, TaskPools},
+    world_plugin::{rebase_origin, VoxelEdits},
+};
+
+/// World-edit style building tools: select a region between two corners, then fill it,
+/// replace within it, or copy it to a clipboard to paste elsewhere, rotated if need be. The
+/// clipboard can be saved to and loaded from [`SCHEMATIC_PATH`] to share builds.
+///
+/// Every key is pressed together with [`EDIT_MODIFIER`]. Corners and pastes go to the voxel
+/// under the crosshair, which needs picking enabled, or else the one the player is in. Each
+/// edit can be undone on its own.
+pub struct SchematicPlugin;
+
+impl Plugin for SchematicPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WorldEdit>().add_systems(
+            Update,
+            (
+                world_edit.after(move_player).after(rebase_origin),
+                draw_selection.after(build_hud),
+            ),
+        );
+    }
+}
+
+/// Read from and written to the working directory
+pub const SCHEMATIC_PATH: &str = "schematic.toml";
+
+pub const EDIT_MODIFIER: KeyCode = KeyCode::AltLeft;
+pub const FIRST_CORNER_KEY: KeyCode = KeyCode::BracketLeft;
+pub const SECOND_CORNER_KEY: KeyCode = KeyCode::BracketRight;
+pub const COPY_KEY: KeyCode = KeyCode::KeyC;
+pub const PASTE_KEY: KeyCode = KeyCode::KeyV;
+/// Turns the clipboard a quarter turn for the next paste
+pub const ROTATE_KEY: KeyCode = KeyCode::KeyR;
+/// Fills the selection with the selected hotbar voxel, or air for an empty slot
+pub const FILL_KEY: KeyCode = KeyCode::KeyF;
+/// Replaces the voxel under the crosshair throughout the selection like [`FILL_KEY`]
+pub const REPLACE_KEY: KeyCode = KeyCode::KeyG;
+pub const SAVE_SCHEMATIC_KEY: KeyCode = KeyCode::KeyK;
+pub const LOAD_SCHEMATIC_KEY: KeyCode = KeyCode::KeyL;
+
+/// Largest region edited or copied at once, so a misplaced corner can't stall the game
+pub const MAX_EDIT_VOLUME: u64 = 64 * 64 * 64;
+
+const SELECTION_COLOR: [u8; 4] = [255, 200, 40, 255];
+const SELECTION_DOT_SIZE: u32 = 3;
+/// Dots drawn along each edge of the selection per voxel of its length
+const SELECTION_DOTS_PER_VOXEL: u32 = 2;
+
+#[derive(Resource, Debug, Default)]
+pub struct WorldEdit {
+    pub corners: [Option<IVec3>; 2],
+    pub clipboard: Option<Schematic>,
+}
+
+impl WorldEdit {
+    /// Both corners once set, or the one voxel while only one is
+    pub fn selection(&self) -> Option<Region> {
+        match self.corners {
+            [Some(a), Some(b)] => Some(Region::from_corners(a, b)),
+            [Some(corner), None] | [None, Some(corner)] => {
+                Some(Region::from_corners(corner, corner))
+            }
+            [None, None] => None,
+        }
+    }
+}
+
+/// World voxel under the crosshair, or the one the camera is in if nothing was picked.
+/// `transform` is the camera's, which sits behind the player in third person.
+fn target_voxel(origin: &FloatingOrigin, transform: &Transform, picked: &Picked) -> IVec3 {
+    let forward = transform.rotation * Vec3::NEG_Z;
+    let point = match picked.hit {
+        // Just past the hit, to land inside the voxel rather than on its face
+        Some(hit) => transform.translation + forward * (hit.distance + 0.01),
+        None => transform.translation,
+    };
+    origin.world_voxel(point)
+}
+
+/// `None` and a message if the region is too big to edit at once
+fn editable(region: Option<Region>, localization: &Localization) -> Option<Region> {
+    let Some(region) = region else {
+        println!("{}", localization.text("select-region-first"));
+        return None;
+    };
+    if region.volume() > MAX_EDIT_VOLUME {
+        println!(
+            "{}",
+            localization.format(
+                "selection-too-large",
+                &[
+                    ("voxels", region.volume().into()),
+                    ("limit", MAX_EDIT_VOLUME.into()),
+                ],
+            )
+        );
+        return None;
+    }
+    Some(region)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn world_edit(
+    keys: Res<ButtonInput<KeyCode>>,
+    hotbar: Res<Hotbar>,
+    picked: Res<Picked>,
+    origin: Res<FloatingOrigin>,
+    tasks: Res<TaskPools>,
+    localization: Res<Localization>,
+    mut edit: ResMut<WorldEdit>,
+    mut edits: VoxelEdits,
+    player: Single<(&Transform, Option<&SpringArm>), With<Player>>,
+) {
+    if !keys.pressed(EDIT_MODIFIER) {
+        return;
+    }
+    let (transform, spring_arm) = player.into_inner();
+    let camera = view_transform(transform, spring_arm);
+    let voxel = || target_voxel(&origin, &camera, &picked);
+
+    if keys.just_pressed(FIRST_CORNER_KEY) {
+        edit.corners[0] = Some(voxel());
+    }
+    if keys.just_pressed(SECOND_CORNER_KEY) {
+        edit.corners[1] = Some(voxel());
+    }
+    if keys.just_pressed(COPY_KEY) {
+        if let Some(region) = editable(edit.selection(), &localization) {
+            match Schematic::copy(&edits.chunks, region) {
+                Some(schematic) => {
+                    println!(
+                        "{}",
+                        localization.format("copied-voxels", &[("voxels", region.volume().into())])
+                    );
+                    edit.clipboard = Some(schematic);
+                }
+                None => println!("{}", localization.text("selection-not-loaded")),
+            }
+        }
+    }
+    if keys.just_pressed(ROTATE_KEY) {
+        if let Some(clipboard) = &mut edit.clipboard {
+            *clipboard = clipboard.rotated(1);
+        }
+    }
+    if keys.just_pressed(PASTE_KEY) {
+        if let Some(clipboard) = &edit.clipboard {
+            let pasted = clipboard.paste(&mut edits.chunks, voxel());
+            edits.commit(pasted);
+        }
+    }
+    if keys.just_pressed(FILL_KEY) {
+        if let Some(region) = editable(edit.selection(), &localization) {
+            let fill_voxel = hotbar.selected_voxel().unwrap_or(Voxel::Air);
+            let filled = fill(&mut edits.chunks, region, fill_voxel);
+            edits.commit(filled);
+        }
+    }
+    if keys.just_pressed(REPLACE_KEY) {
+        let replaced = edits.chunks.voxel(voxel());
+        if let (Some(region), Some(replaced)) =
+            (editable(edit.selection(), &localization), replaced)
+        {
+            let with = hotbar.selected_voxel().unwrap_or(Voxel::Air);
+            let replaced = replace(&mut edits.chunks, region, replaced, with);
+            edits.commit(replaced);
+        }
+    }
+
+    if keys.just_pressed(SAVE_SCHEMATIC_KEY) {
+        if let Some(clipboard) = &edit.clipboard {
+            let file = SchematicFile::from(clipboard);
+            let localization = localization.clone();
+            tasks
+                .spawn(TaskGroup::Io, async move {
+                    if let Err(e) = save_schematic(&file, SCHEMATIC_PATH) {
+                        eprintln!(
+                            "{}",
+                            localization.format(
+                                "save-failed",
+                                &[
+                                    ("path", SCHEMATIC_PATH.into()),
+                                    ("error", e.to_string().into()),
+                                ],
+                            )
+                        );
+                    }
+                })
+                .detach();
+        }
+    }
+    if keys.just_pressed(LOAD_SCHEMATIC_KEY) {
+        match load_schematic(SCHEMATIC_PATH) {
+            Ok(schematic) => edit.clipboard = Some(schematic),
+            Err(e) => eprintln!(
+                "{}",
+                localization.format(
+                    "load-failed",
+                    &[
+                        ("path", SCHEMATIC_PATH.into()),
+                        ("error", e.to_string().into()),
+                    ],
+                )
+            ),
+        }
+    }
+}
+
+pub fn save_schematic(file: &SchematicFile, path: impl AsRef<Path>) -> Result<(), Box<dyn Error>> {
+    fs::write(path, toml::to_string(file)?)?;
+    Ok(())
+}
+
+pub fn load_schematic(path: impl AsRef<Path>) -> Result<Schematic, Box<dyn Error>> {
+    let file: SchematicFile = toml::from_str(&fs::read_to_string(path)?)?;
+    Ok(Schematic::try_from(file)?)
+}
+
+/// Outlines the selection with dots along its edges, projected like the rendered frame
+fn draw_selection(
+    edit: Res<WorldEdit>,
+    origin: Res<FloatingOrigin>,
+    mut hud: ResMut<Hud>,
+    window: Single<&Window, With<PrimaryWindow>>,
+    player: Single<(&Transform, &CameraFov, Option<&SpringArm>), With<Player>>,
+) {
+    let Some(region) = edit.selection() else {
+        return;
+    };
+    let (transform, fov, spring_arm) = player.into_inner();
+    let (width, height) = (window.physical_width(), window.physical_height());
+    let camera = view_transform(transform, spring_arm);
+    let view_proj = CameraGpu::new(&camera, fov.degrees(), width as f32, height as f32).view_proj();
+
+    let min = origin.to_translation(region.min.as_vec3());
+    let max = origin.to_translation((region.max + 1).as_vec3());
+    let corner = |i: u32| Vec3::select(BVec3::new(i & 1 != 0, i & 2 != 0, i & 4 != 0), max, min);
+    // Corner pairs differing in one bit are edges
+    for (a, b) in (0..8u32)
+        .flat_map(|a| [1, 2, 4].map(|bit| (a, a | bit)))
+        .filter(|(a, b)| a != b)
+    {
+        let (start, end) = (corner(a), corner(b));
+        let dots = (start.distance(end) as u32 * SELECTION_DOTS_PER_VOXEL).max(1);
+        for dot in 0..=dots {
+            let clip = view_proj * start.lerp(end, dot as f32 / dots as f32).extend(1.0);
+            // Behind the camera
+            if clip.w <= 0.0 {
+                continue;
+            }
+            let ndc = clip.truncate() / clip.w;
+            if ndc.x.abs() > 1.0 || ndc.y.abs() > 1.0 {
+                continue;
+            }
+            hud.push(HudRect::centered(
+                ((ndc.x + 1.0) * 0.5 * width as f32) as i32,
+                ((ndc.y + 1.0) * 0.5 * height as f32) as i32,
+                SELECTION_DOT_SIZE,
+                SELECTION_DOT_SIZE,
+                SELECTION_COLOR,
+            ));
+        }
+    }
+}
@@ -0,0 +1,544 @@
+use std::{
+    fs,
+    time::{Duration, Instant, SystemTime},
+};
+
+use bevy_app::{Plugin, Update};
+use bevy_ecs::{
+    change_detection::DetectChangesMut,
+    query::With,
+    schedule::IntoSystemConfigs,
+    system::{Res, ResMut, Resource, Single},
+};
+use bevy_input::{keyboard::KeyCode, ButtonInput};
+use bevy_window::{PrimaryWindow, Window};
+use data::{
+    accessibility::{AccessibilitySettings, ColorFilter},
+    streaming::StreamingBudget,
+    view_distance::ViewDistance,
+};
+use renderer::{
+    hud::{Hud, HudRect},
+    settings::{Msaa, RendererSettings},
+};
+
+use crate::{
+    config::{Config, CONFIG_PATH, RESTART_KEYS},
+    hud_plugin::build_hud,
+    localization::{Localization, DEFAULT_LANGUAGE},
+    player_plugin::PlayerSettings,
+    task_plugin::{TaskGroup, TaskPools},
+};
+
+/// Loads the config file into [`RendererSettings`] and [`PlayerSettings`] and adds an in-game
+/// menu that edits them live, saving back to the file when it closes. Edits made to the file
+/// while the game runs are applied too.
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut bevy_app::App) {
+        let config = Config::load(CONFIG_PATH).unwrap_or_else(|e| {
+            eprintln!("Could not load {CONFIG_PATH}, using defaults: {e}");
+            Config::default()
+        });
+
+        let localization = load_language(config.language.as_deref(), &Localization::default());
+
+        app.insert_resource(localization)
+            .insert_resource(config.renderer)
+            .insert_resource(config.player)
+            .insert_resource(config.streaming)
+            .insert_resource(config.accessibility)
+            .insert_resource(ConfigWatcher {
+                modified: config_modified(),
+                checked: Instant::now(),
+            })
+            .init_resource::<SettingsMenu>()
+            .add_systems(
+                Update,
+                (
+                    reload_config.before(toggle_settings_menu),
+                    (
+                        toggle_settings_menu,
+                        navigate_settings_menu,
+                        draw_settings_menu.after(build_hud),
+                    )
+                        .chain(),
+                    cycle_debug_view,
+                ),
+            );
+    }
+}
+
+/// How often the config file is checked for edits
+const CONFIG_RELOAD_INTERVAL: Duration = Duration::from_secs(1);
+
+/// When the config file was last seen modified
+#[derive(Resource, Debug)]
+struct ConfigWatcher {
+    modified: Option<SystemTime>,
+    checked: Instant,
+}
+
+fn config_modified() -> Option<SystemTime> {
+    fs::metadata(CONFIG_PATH)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+/// Applies the config file once it was modified, leaving out the keys only read at startup.
+/// Saving from the menu modifies it as well, but then nothing differs.
+fn reload_config(
+    mut watcher: ResMut<ConfigWatcher>,
+    mut localization: ResMut<Localization>,
+    mut renderer: ResMut<RendererSettings>,
+    mut player: ResMut<PlayerSettings>,
+    mut streaming: ResMut<StreamingBudget>,
+    mut accessibility: ResMut<AccessibilitySettings>,
+) {
+    if watcher.checked.elapsed() < CONFIG_RELOAD_INTERVAL {
+        return;
+    }
+    watcher.checked = Instant::now();
+    let modified = config_modified();
+    // A deleted file would reset everything to the defaults
+    if modified.is_none() || modified == watcher.modified {
+        return;
+    }
+    watcher.modified = modified;
+
+    let loaded = match Config::load(CONFIG_PATH) {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            eprintln!(
+                "{}",
+                localization.format(
+                    "config-reload-failed",
+                    &[
+                        ("path", CONFIG_PATH.into()),
+                        ("error", e.to_string().into())
+                    ],
+                )
+            );
+            return;
+        }
+    };
+    let current = Config {
+        language: localization.language().map(str::to_owned),
+        renderer: renderer.clone(),
+        player: player.clone(),
+        streaming: *streaming,
+        accessibility: *accessibility,
+    };
+    let changed = match current.changed_keys(&loaded) {
+        Ok(changed) => changed,
+        Err(e) => {
+            eprintln!(
+                "{}",
+                localization.format(
+                    "config-compare-failed",
+                    &[
+                        ("path", CONFIG_PATH.into()),
+                        ("error", e.to_string().into())
+                    ],
+                )
+            );
+            return;
+        }
+    };
+    let (restart, live): (Vec<_>, Vec<_>) = changed
+        .iter()
+        .partition(|key| RESTART_KEYS.contains(&key.as_str()));
+    if !live.is_empty() {
+        println!(
+            "{}",
+            localization.format(
+                "config-reloaded",
+                &[("path", CONFIG_PATH.into()), ("keys", join(&live).into())],
+            )
+        );
+    }
+    if !restart.is_empty() {
+        println!(
+            "{}",
+            localization.format(
+                "config-restart-required",
+                &[("keys", join(&restart).into())]
+            )
+        );
+    }
+
+    if loaded.language.as_deref() != localization.language() {
+        *localization = load_language(loaded.language.as_deref(), &localization);
+    }
+
+    renderer.set_if_neq(RendererSettings {
+        render_path: renderer.render_path,
+        debug_view: renderer.debug_view,
+        ..loaded.renderer
+    });
+    player.set_if_neq(loaded.player);
+    streaming.set_if_neq(loaded.streaming);
+    accessibility.set_if_neq(loaded.accessibility);
+}
+
+/// The string tables of `language`, or `current` if they can't be loaded
+fn load_language(language: Option<&str>, current: &Localization) -> Localization {
+    Localization::load(language).unwrap_or_else(|e| {
+        eprintln!(
+            "{}",
+            current.format(
+                "language-load-failed",
+                &[
+                    ("language", language.unwrap_or(DEFAULT_LANGUAGE).into()),
+                    (
+                        "default",
+                        current.language().unwrap_or(DEFAULT_LANGUAGE).into()
+                    ),
+                    ("error", e.to_string().into()),
+                ],
+            )
+        );
+        current.clone()
+    })
+}
+
+fn join(keys: &[&String]) -> String {
+    keys.iter()
+        .map(|key| key.as_str())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+pub const MENU_KEY: KeyCode = KeyCode::F1;
+/// Steps through [`DebugView::ALL`](renderer::settings::DebugView::ALL)
+pub const DEBUG_VIEW_KEY: KeyCode = KeyCode::F4;
+
+/// One row of the settings menu
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettingsEntry {
+    RenderScale,
+    Vsync,
+    Fov,
+    MouseSensitivity,
+    Shadows,
+    ShadowRays,
+    AmbientOcclusion,
+    AdaptiveSampling,
+    ViewDistance,
+    Msaa,
+    Rumble,
+    CameraShake,
+    Photosensitive,
+    ColorFilter,
+    TextScale,
+}
+
+impl SettingsEntry {
+    /// Rows from top to bottom
+    pub const ALL: [Self; 15] = [
+        Self::RenderScale,
+        Self::Vsync,
+        Self::Fov,
+        Self::MouseSensitivity,
+        Self::Shadows,
+        Self::ShadowRays,
+        Self::AmbientOcclusion,
+        Self::AdaptiveSampling,
+        Self::ViewDistance,
+        Self::Msaa,
+        Self::Rumble,
+        Self::CameraShake,
+        Self::Photosensitive,
+        Self::ColorFilter,
+        Self::TextScale,
+    ];
+
+    /// Moves a value `steps` increments within its range; toggles flip on any step
+    pub fn adjust(
+        &self,
+        renderer: &mut RendererSettings,
+        player: &mut PlayerSettings,
+        accessibility: &mut AccessibilitySettings,
+        steps: i32,
+    ) {
+        let steps = steps as f32;
+        match self {
+            Self::RenderScale => {
+                renderer.render_scale = (renderer.render_scale + 0.25 * steps).clamp(
+                    RendererSettings::MIN_RENDER_SCALE,
+                    RendererSettings::MAX_RENDER_SCALE,
+                )
+            }
+            Self::Vsync => renderer.vsync = !renderer.vsync,
+            Self::Fov => {
+                player.fov_degrees = (player.fov_degrees + 5.0 * steps).clamp(
+                    PlayerSettings::MIN_FOV_DEGREES,
+                    PlayerSettings::MAX_FOV_DEGREES,
+                )
+            }
+            Self::MouseSensitivity => {
+                player.mouse_sensitivity = (player.mouse_sensitivity + 0.1 * steps).clamp(0.1, 5.0)
+            }
+            Self::Shadows => renderer.shadows = !renderer.shadows,
+            Self::ShadowRays => {
+                let rays = renderer.clamped_shadow_rays() as i32 + steps as i32;
+                renderer.shadow_rays =
+                    rays.clamp(1, RendererSettings::MAX_SHADOW_RAYS as i32) as u32;
+            }
+            Self::AmbientOcclusion => renderer.ambient_occlusion = !renderer.ambient_occlusion,
+            Self::AdaptiveSampling => renderer.adaptive_sampling = !renderer.adaptive_sampling,
+            Self::ViewDistance => {
+                let chunks = renderer.view_distance.chunks() as i32 + steps as i32;
+                renderer.view_distance = ViewDistance::new(chunks.max(0) as u32);
+            }
+            Self::Msaa => {
+                let index = Msaa::ALL.iter().position(|msaa| *msaa == renderer.msaa);
+                let index = index.unwrap_or(0) as i32 + steps as i32;
+                renderer.msaa = Msaa::ALL[index.clamp(0, Msaa::ALL.len() as i32 - 1) as usize];
+            }
+            Self::Rumble => player.rumble = !player.rumble,
+            Self::CameraShake => {
+                accessibility.camera_shake =
+                    (accessibility.camera_shake + 0.25 * steps).clamp(0.0, 1.0)
+            }
+            Self::Photosensitive => accessibility.photosensitive = !accessibility.photosensitive,
+            Self::ColorFilter => {
+                let index = ColorFilter::ALL
+                    .iter()
+                    .position(|filter| *filter == accessibility.color_filter);
+                let count = ColorFilter::ALL.len() as i32;
+                let index = (index.unwrap_or(0) as i32 + steps as i32).rem_euclid(count);
+                accessibility.color_filter = ColorFilter::ALL[index as usize];
+            }
+            Self::TextScale => {
+                let scale = accessibility.clamped_text_scale() as i32 + steps as i32;
+                accessibility.text_scale =
+                    scale.clamp(1, AccessibilitySettings::MAX_TEXT_SCALE as i32) as u32;
+            }
+        }
+    }
+
+    /// How full the row's bar is drawn, 0 to 1
+    pub fn fill(
+        &self,
+        renderer: &RendererSettings,
+        player: &PlayerSettings,
+        accessibility: &AccessibilitySettings,
+    ) -> f32 {
+        let fraction = |value: f32, min: f32, max: f32| (value - min) / (max - min);
+        match self {
+            Self::RenderScale => fraction(
+                renderer.render_scale,
+                RendererSettings::MIN_RENDER_SCALE,
+                RendererSettings::MAX_RENDER_SCALE,
+            ),
+            Self::Vsync => renderer.vsync as u8 as f32,
+            Self::Fov => fraction(
+                player.fov_degrees,
+                PlayerSettings::MIN_FOV_DEGREES,
+                PlayerSettings::MAX_FOV_DEGREES,
+            ),
+            Self::MouseSensitivity => fraction(player.mouse_sensitivity, 0.1, 5.0),
+            Self::Shadows => renderer.shadows as u8 as f32,
+            Self::ShadowRays => fraction(
+                renderer.clamped_shadow_rays() as f32,
+                1.0,
+                RendererSettings::MAX_SHADOW_RAYS as f32,
+            ),
+            Self::AmbientOcclusion => renderer.ambient_occlusion as u8 as f32,
+            Self::AdaptiveSampling => renderer.adaptive_sampling as u8 as f32,
+            Self::ViewDistance => fraction(
+                renderer.view_distance.chunks() as f32,
+                ViewDistance::MIN as f32,
+                ViewDistance::MAX as f32,
+            ),
+            Self::Msaa => fraction(
+                renderer.msaa.samples() as f32,
+                Msaa::Off.samples() as f32,
+                Msaa::X8.samples() as f32,
+            ),
+            Self::Rumble => player.rumble as u8 as f32,
+            Self::CameraShake => accessibility.camera_shake,
+            Self::Photosensitive => accessibility.photosensitive as u8 as f32,
+            Self::ColorFilter => fraction(
+                ColorFilter::ALL
+                    .iter()
+                    .position(|filter| *filter == accessibility.color_filter)
+                    .unwrap_or(0) as f32,
+                0.0,
+                ColorFilter::ALL.len() as f32 - 1.0,
+            ),
+            Self::TextScale => fraction(
+                accessibility.clamped_text_scale() as f32,
+                1.0,
+                AccessibilitySettings::MAX_TEXT_SCALE as f32,
+            ),
+        }
+        .clamp(0.0, 1.0)
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct SettingsMenu {
+    open: bool,
+    selected: usize,
+}
+
+impl SettingsMenu {
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn selected(&self) -> SettingsEntry {
+        SettingsEntry::ALL[self.selected]
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn toggle_settings_menu(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut menu: ResMut<SettingsMenu>,
+    renderer: Res<RendererSettings>,
+    player: Res<PlayerSettings>,
+    streaming: Res<StreamingBudget>,
+    accessibility: Res<AccessibilitySettings>,
+    localization: Res<Localization>,
+    tasks: Res<TaskPools>,
+) {
+    if !keys.just_pressed(MENU_KEY) {
+        return;
+    }
+
+    menu.open = !menu.open;
+    if !menu.open {
+        let config = Config {
+            language: localization.language().map(str::to_owned),
+            renderer: renderer.clone(),
+            player: player.clone(),
+            streaming: *streaming,
+            accessibility: *accessibility,
+        };
+        let localization = localization.clone();
+        tasks
+            .spawn(TaskGroup::Io, async move {
+                if let Err(e) = config.save(CONFIG_PATH) {
+                    eprintln!(
+                        "{}",
+                        localization.format(
+                            "save-failed",
+                            &[
+                                ("path", CONFIG_PATH.into()),
+                                ("error", e.to_string().into())
+                            ],
+                        )
+                    );
+                }
+            })
+            .detach();
+    }
+}
+
+fn navigate_settings_menu(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut menu: ResMut<SettingsMenu>,
+    mut renderer: ResMut<RendererSettings>,
+    mut player: ResMut<PlayerSettings>,
+    mut accessibility: ResMut<AccessibilitySettings>,
+) {
+    if !menu.open {
+        return;
+    }
+
+    let entry_count = SettingsEntry::ALL.len();
+    if keys.just_pressed(KeyCode::ArrowUp) {
+        menu.selected = (menu.selected + entry_count - 1) % entry_count;
+    }
+    if keys.just_pressed(KeyCode::ArrowDown) {
+        menu.selected = (menu.selected + 1) % entry_count;
+    }
+
+    let steps = [
+        (KeyCode::ArrowLeft, -1),
+        (KeyCode::ArrowRight, 1),
+        (KeyCode::Enter, 1),
+    ]
+    .iter()
+    .filter(|(key, _)| keys.just_pressed(*key))
+    .map(|(_, steps)| steps)
+    .sum::<i32>();
+    if steps != 0 {
+        menu.selected()
+            .adjust(&mut renderer, &mut player, &mut accessibility, steps);
+    }
+}
+
+const ROW_WIDTH: u32 = 240;
+const ROW_HEIGHT: u32 = 20;
+const ROW_GAP: u32 = 6;
+const ROW_BORDER: u32 = 2;
+const BACKGROUND_COLOR: [u8; 4] = [24, 24, 32, 255];
+const FILL_COLOR: [u8; 4] = [120, 170, 255, 255];
+const SELECTED_COLOR: [u8; 4] = [255, 255, 255, 255];
+
+/// Each setting is an unlabeled bar, in [`SettingsEntry::ALL`] order
+fn draw_settings_menu(
+    menu: Res<SettingsMenu>,
+    renderer: Res<RendererSettings>,
+    player: Res<PlayerSettings>,
+    accessibility: Res<AccessibilitySettings>,
+    mut hud: ResMut<Hud>,
+    window: Single<&Window, With<PrimaryWindow>>,
+) {
+    if !menu.open {
+        return;
+    }
+
+    let menu_height = SettingsEntry::ALL.len() as u32 * (ROW_HEIGHT + ROW_GAP) - ROW_GAP;
+    let left = (window.physical_width() as i32 - ROW_WIDTH as i32) / 2;
+    let top = (window.physical_height() as i32 - menu_height as i32) / 2;
+
+    for (row, entry) in SettingsEntry::ALL.iter().enumerate() {
+        let y = top + (row as u32 * (ROW_HEIGHT + ROW_GAP)) as i32;
+
+        if *entry == menu.selected() {
+            hud.push(HudRect::new(
+                left - ROW_BORDER as i32,
+                y - ROW_BORDER as i32,
+                ROW_WIDTH + 2 * ROW_BORDER,
+                ROW_HEIGHT + 2 * ROW_BORDER,
+                SELECTED_COLOR,
+            ));
+        }
+        hud.push(HudRect::new(
+            left,
+            y,
+            ROW_WIDTH,
+            ROW_HEIGHT,
+            BACKGROUND_COLOR,
+        ));
+
+        let fill_width =
+            (entry.fill(&renderer, &player, &accessibility) * ROW_WIDTH as f32).round() as u32;
+        if fill_width > 0 {
+            hud.push(HudRect::new(left, y, fill_width, ROW_HEIGHT, FILL_COLOR));
+        }
+    }
+}
+
+fn cycle_debug_view(
+    keys: Res<ButtonInput<KeyCode>>,
+    localization: Res<Localization>,
+    mut renderer: ResMut<RendererSettings>,
+) {
+    if keys.just_pressed(DEBUG_VIEW_KEY) {
+        renderer.debug_view = renderer.debug_view.next();
+        println!(
+            "{}",
+            localization.format(
+                "debug-view",
+                &[("view", format!("{:?}", renderer.debug_view).into())],
+            )
+        );
+    }
+}
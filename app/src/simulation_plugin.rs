@@ -0,0 +1,83 @@
+use bevy_app::{App, FixedFirst, FixedUpdate, Plugin};
+use bevy_ecs::{
+    change_detection::DetectChanges,
+    event::{Event, EventWriter},
+    system::{Res, ResMut},
+};
+use data::{
+    block_tick::{run_tick, BlockTicks, GameTick},
+    chunk_map::ChunkMap,
+    prelude::IVec3,
+    rng::Rng,
+    weather::{Sky, Weather},
+    worldgen::WorldSeed,
+};
+
+use crate::profiler;
+
+/// The world state that runs the same with or without a window: the [`WorldSeed`] and the
+/// [`Rng`] derived from it, the loaded chunks, the scheduled [`BlockTicks`] and the [`Weather`],
+/// run once per [`GameTick`]. Needs the [`TimePlugin`](crate::time_plugin::TimePlugin) for the
+/// ticks.
+pub struct SimulationPlugin;
+
+impl Plugin for SimulationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WorldSeed>()
+            .init_resource::<Rng>()
+            .init_resource::<ChunkMap>()
+            .init_resource::<BlockTicks>()
+            .init_resource::<Weather>()
+            .add_event::<VoxelChanged>()
+            .add_event::<WeatherChanged>()
+            .add_systems(FixedFirst, reseed_rng)
+            .add_systems(FixedUpdate, (run_block_ticks, update_weather));
+    }
+}
+
+/// Sent for every voxel changed in the [`ChunkMap`], so the chunks sharing it get remeshed
+#[derive(Event, Debug, Clone, Copy)]
+pub struct VoxelChanged {
+    pub position: IVec3,
+}
+
+/// Sent when the [`Weather`] turns to a new [`Sky`], e.g. to start or stop ambient sounds.
+/// The weather eases into the new sky over the following seconds.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct WeatherChanged {
+    pub from: Sky,
+    pub to: Sky,
+}
+
+/// Follows the seed, which may be set after the plugin is built or change with a new world
+fn reseed_rng(seed: Res<WorldSeed>, mut rng: ResMut<Rng>) {
+    if seed.is_changed() {
+        *rng = Rng::new(*seed);
+    }
+}
+
+fn update_weather(
+    rng: Res<Rng>,
+    tick: Res<GameTick>,
+    mut weather: ResMut<Weather>,
+    mut changed_writer: EventWriter<WeatherChanged>,
+) {
+    let from = weather.sky();
+    if let Some(to) = weather.tick(&rng, *tick) {
+        changed_writer.send(WeatherChanged { from, to });
+    }
+}
+
+fn run_block_ticks(
+    tick: Res<GameTick>,
+    mut chunks: ResMut<ChunkMap>,
+    mut ticks: ResMut<BlockTicks>,
+    mut changed_writer: EventWriter<VoxelChanged>,
+) {
+    let _span = profiler::span("run_block_ticks");
+    for scheduled in ticks.take_due(*tick, &chunks) {
+        for position in run_tick(&mut chunks, &mut ticks, *tick, scheduled) {
+            changed_writer.send(VoxelChanged { position });
+        }
+    }
+}
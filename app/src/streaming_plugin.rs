@@ -0,0 +1,420 @@
+use std::{
+    collections::{HashMap, HashSet},
+    time::{Duration, Instant},
+};
+
+use bevy_app::{App, Plugin, Startup, Update};
+use bevy_ecs::{
+    change_detection::DetectChanges,
+    event::EventReader,
+    query::With,
+    schedule::IntoSystemConfigs,
+    system::{Res, ResMut, Resource, Single},
+};
+use bevy_state::condition::in_state;
+use data::{
+    chunk_cache::ChunkCache,
+    chunk_heatmap::ChunkHeatmap,
+    chunk_map::{chunk_of, chunks_sharing, ChunkMap},
+    floating_origin::FloatingOrigin,
+    mesh::Mesh,
+    mesher::{mesh_faces, visible_faces},
+    prelude::{IVec3, Vec3},
+    streaming::{StreamingBudget, StreamingStats, StreamingView, WorkQueue},
+    transform::Transform,
+    voxel_block::VoxelBlockData,
+    worldgen::{generate_chunk, WorldSeed},
+};
+use renderer::settings::RendererSettings;
+
+use crate::{
+    loading_plugin::{AppState, LoadingProgress},
+    player_plugin::{move_player, Player},
+    profiler,
+    simulation_plugin::VoxelChanged,
+    task_plugin::{Task, TaskGroup, TaskPools},
+    world_plugin::rebase_origin,
+};
+
+/// Keeps the chunks within the view distance of the player loaded and meshed. Generation,
+/// meshing and handing meshes to the renderer each go through a [`WorkQueue`] and are limited
+/// by the [`StreamingBudget`], nearest chunks in view first. Unloaded chunks go to the
+/// [`ChunkCache`], which is checked before generating.
+pub struct StreamingPlugin;
+
+impl Plugin for StreamingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<StreamingBudget>()
+            .init_resource::<StreamingStats>()
+            .init_resource::<Streaming>()
+            .init_resource::<ChunkMeshes>()
+            .init_resource::<ChunkHeatmap>()
+            .init_resource::<LoadingProgress>()
+            .add_systems(Startup, expect_spawn_chunks)
+            .add_systems(
+                Update,
+                (
+                    apply_cache_budget,
+                    queue_chunks,
+                    queue_changed_chunks,
+                    finish_generation,
+                    start_generation,
+                    finish_meshing,
+                    start_meshing,
+                    build_chunk_meshes,
+                    update_streaming_stats,
+                    report_spawn_chunks.run_if(in_state(AppState::Loading)),
+                )
+                    .chain()
+                    .after(move_player)
+                    .after(rebase_origin),
+            );
+    }
+}
+
+/// Chunks are unloaded this many chunks beyond the view distance, so walking back and forth
+/// across a chunk border doesn't reload the same chunks
+const UNLOAD_MARGIN: u32 = 1;
+
+/// Chunks this many chunks around the player are generated and meshed before the game
+/// starts, so it doesn't start in a void
+const SPAWN_CHUNK_RADIUS: i32 = 1;
+
+/// Name of the spawn chunks in [`LoadingProgress`]
+const LOADING_WORK: &str = "spawn chunks";
+
+/// Meshes of the streamed chunks, in chunk-local voxel coordinates, ready for the renderer to
+/// place at [`FloatingOrigin::chunk_offset`]. Each chunk in `changed` needs its acceleration structure (re)built, or removed if it no
+/// longer has a mesh.
+#[derive(Resource, Debug, Default)]
+pub struct ChunkMeshes {
+    pub meshes: HashMap<IVec3, Mesh>,
+    pub changed: HashSet<IVec3>,
+}
+
+#[derive(Resource, Default)]
+struct Streaming {
+    /// Chunk the player was in when the queues were last refilled
+    center: Option<IVec3>,
+    view_distance: u32,
+    generation: WorkQueue,
+    /// Each task also times itself, for the [`ChunkHeatmap`]
+    generating: HashMap<IVec3, Task<(VoxelBlockData, Duration)>>,
+    meshing_queue: WorkQueue,
+    meshing: HashMap<IVec3, Task<Mesh>>,
+    /// Finished meshes waiting for the as-build budget; a remesh replaces a waiting one
+    as_builds: HashMap<IVec3, Mesh>,
+    as_build_queue: WorkQueue,
+    stats: StreamingStats,
+}
+
+impl Streaming {
+    fn in_range(&self, chunk: IVec3, margin: u32) -> bool {
+        self.center.is_some_and(|center| {
+            let radius = (self.view_distance + margin) as i32;
+            (chunk - center).length_squared() <= radius * radius
+        })
+    }
+
+    /// Drops every piece of pending work for a chunk. Running tasks are cancelled by dropping
+    /// them.
+    fn forget(&mut self, chunk: IVec3) {
+        self.generation.remove(chunk);
+        self.generating.remove(&chunk);
+        self.meshing_queue.remove(chunk);
+        self.meshing.remove(&chunk);
+        self.as_builds.remove(&chunk);
+        self.as_build_queue.remove(chunk);
+    }
+}
+
+fn view(origin: &FloatingOrigin, transform: &Transform) -> StreamingView {
+    StreamingView::new(
+        origin.to_world(transform.translation),
+        transform.rotation * Vec3::NEG_Z,
+    )
+}
+
+/// Refills the queues when the player enters another chunk or the view distance changes, and
+/// unloads chunks that fell out of range
+#[allow(clippy::too_many_arguments)]
+fn queue_chunks(
+    settings: Res<RendererSettings>,
+    mut streaming: ResMut<Streaming>,
+    mut chunks: ResMut<ChunkMap>,
+    mut chunk_meshes: ResMut<ChunkMeshes>,
+    mut heatmap: ResMut<ChunkHeatmap>,
+    mut cache: ResMut<ChunkCache>,
+    origin: Res<FloatingOrigin>,
+    player: Single<&Transform, With<Player>>,
+) {
+    let center = chunk_of(origin.world_voxel(player.translation));
+    let view_distance = settings.view_distance;
+    if streaming.center == Some(center) && streaming.view_distance == view_distance.chunks() {
+        return;
+    }
+    streaming.center = Some(center);
+    streaming.view_distance = view_distance.chunks();
+
+    let out_of_range: Vec<IVec3> = chunks
+        .chunks()
+        .map(|(chunk, _)| chunk)
+        .filter(|chunk| !streaming.in_range(*chunk, UNLOAD_MARGIN))
+        .collect();
+    for chunk in out_of_range {
+        if let Some(save) = chunks.save_chunk(chunk) {
+            cache.insert(chunk, save);
+        }
+        let (_, neighbors) = chunks.remove(chunk);
+        streaming.forget(chunk);
+        heatmap.remove(chunk);
+        if chunk_meshes.meshes.remove(&chunk).is_some() {
+            chunk_meshes.changed.insert(chunk);
+        }
+        for neighbor in neighbors {
+            streaming.meshing_queue.push(neighbor);
+        }
+    }
+    let pending: Vec<IVec3> = streaming
+        .generating
+        .keys()
+        .copied()
+        .filter(|chunk| !streaming.in_range(*chunk, UNLOAD_MARGIN))
+        .collect();
+    for chunk in pending {
+        streaming.forget(chunk);
+    }
+
+    for chunk in view_distance.chunks_around(center) {
+        if !chunks.contains(chunk) {
+            if !streaming.generating.contains_key(&chunk) {
+                streaming.generation.push(chunk);
+            }
+        } else if !chunk_meshes.meshes.contains_key(&chunk)
+            && !streaming.meshing.contains_key(&chunk)
+            && !streaming.as_builds.contains_key(&chunk)
+        {
+            // Loaded without going through the queues, e.g. by a teleport
+            streaming.meshing_queue.push(chunk);
+        }
+    }
+    streaming
+        .generation
+        .retain(|chunk| view_distance.contains(center, chunk));
+}
+
+/// Remeshes the chunks whose voxels were changed in place
+fn queue_changed_chunks(
+    mut changed_reader: EventReader<VoxelChanged>,
+    mut streaming: ResMut<Streaming>,
+    chunks: Res<ChunkMap>,
+) {
+    for changed in changed_reader.read() {
+        for chunk in chunks_sharing(changed.position) {
+            if chunks.contains(chunk) {
+                streaming.meshing_queue.push(chunk);
+            }
+        }
+    }
+}
+
+fn apply_cache_budget(budget: Res<StreamingBudget>, mut cache: ResMut<ChunkCache>) {
+    if budget.is_changed() {
+        cache.budget_bytes = budget.chunk_cache_bytes;
+    }
+}
+
+/// Loads queued chunks from the cache where it has them, and starts generating the rest.
+/// Both count against the generation budget.
+#[allow(clippy::too_many_arguments)]
+fn start_generation(
+    tasks: Res<TaskPools>,
+    seed: Res<WorldSeed>,
+    budget: Res<StreamingBudget>,
+    mut streaming: ResMut<Streaming>,
+    mut chunks: ResMut<ChunkMap>,
+    mut cache: ResMut<ChunkCache>,
+    origin: Res<FloatingOrigin>,
+    player: Single<&Transform, With<Player>>,
+) {
+    let _span = profiler::span("start_generation");
+    let free = budget
+        .max_generating
+        .saturating_sub(streaming.generating.len());
+    let count = budget.generation.min(free);
+    let started = streaming
+        .generation
+        .take(count, &view(&origin, &player), |_| true);
+    streaming.stats.generation_started = started.len();
+    for chunk in started {
+        if let Some(neighbors) = cache.load(&mut chunks, chunk) {
+            streaming.meshing_queue.push(chunk);
+            for neighbor in neighbors {
+                streaming.meshing_queue.push(neighbor);
+            }
+            continue;
+        }
+        let seed = *seed;
+        let task = tasks.spawn(TaskGroup::AsyncCompute, async move {
+            let start = Instant::now();
+            let data = generate_chunk(seed, chunk);
+            (data, start.elapsed())
+        });
+        streaming.generating.insert(chunk, Task::new(task));
+    }
+}
+
+fn finish_generation(
+    mut streaming: ResMut<Streaming>,
+    mut chunks: ResMut<ChunkMap>,
+    mut heatmap: ResMut<ChunkHeatmap>,
+) {
+    let _span = profiler::span("finish_generation");
+    let finished: Vec<IVec3> = streaming
+        .generating
+        .iter()
+        .filter(|(_, task)| task.is_finished())
+        .map(|(chunk, _)| *chunk)
+        .collect();
+    for chunk in finished {
+        let Some((data, duration)) = streaming
+            .generating
+            .remove(&chunk)
+            .and_then(|mut task| task.poll())
+        else {
+            continue;
+        };
+        // A teleport may have loaded it in the meantime
+        if chunks.contains(chunk) {
+            continue;
+        }
+        heatmap.record_generation(chunk, duration);
+        streaming.meshing_queue.push(chunk);
+        for neighbor in chunks.insert(chunk, data) {
+            streaming.meshing_queue.push(neighbor);
+        }
+    }
+}
+
+fn start_meshing(
+    tasks: Res<TaskPools>,
+    budget: Res<StreamingBudget>,
+    mut streaming: ResMut<Streaming>,
+    chunks: Res<ChunkMap>,
+    origin: Res<FloatingOrigin>,
+    player: Single<&Transform, With<Player>>,
+) {
+    let _span = profiler::span("start_meshing");
+    let streaming = streaming.as_mut();
+    // A chunk already being meshed waits for that task, since its result would be stale
+    let meshing = &streaming.meshing;
+    let started = streaming
+        .meshing_queue
+        .take(budget.meshing, &view(&origin, &player), |chunk| {
+            !meshing.contains_key(&chunk)
+        });
+    streaming.stats.meshing_started = started.len();
+    for chunk in started {
+        let Some(snapshot) = chunks.snapshot(chunk) else {
+            continue;
+        };
+        let task = tasks.spawn(TaskGroup::AsyncCompute, async move {
+            mesh_faces(&visible_faces(&snapshot.neighborhood()))
+        });
+        streaming.meshing.insert(chunk, Task::new(task));
+    }
+}
+
+fn finish_meshing(mut streaming: ResMut<Streaming>) {
+    let _span = profiler::span("finish_meshing");
+    let finished: Vec<IVec3> = streaming
+        .meshing
+        .iter()
+        .filter(|(_, task)| task.is_finished())
+        .map(|(chunk, _)| *chunk)
+        .collect();
+    for chunk in finished {
+        if let Some(mesh) = streaming
+            .meshing
+            .remove(&chunk)
+            .and_then(|mut task| task.poll())
+        {
+            streaming.as_builds.insert(chunk, mesh);
+            streaming.as_build_queue.push(chunk);
+        }
+    }
+}
+
+/// Hands finished meshes to the renderer, which builds an acceleration structure for each
+fn build_chunk_meshes(
+    budget: Res<StreamingBudget>,
+    mut streaming: ResMut<Streaming>,
+    mut chunk_meshes: ResMut<ChunkMeshes>,
+    mut heatmap: ResMut<ChunkHeatmap>,
+    origin: Res<FloatingOrigin>,
+    player: Single<&Transform, With<Player>>,
+) {
+    let _span = profiler::span("build_chunk_meshes");
+    let built = streaming
+        .as_build_queue
+        .take(budget.as_builds, &view(&origin, &player), |_| true);
+    streaming.stats.as_builds = built.len();
+    for chunk in built {
+        if let Some(mesh) = streaming.as_builds.remove(&chunk) {
+            heatmap.record_mesh(chunk, &mesh);
+            chunk_meshes.meshes.insert(chunk, mesh);
+            chunk_meshes.changed.insert(chunk);
+        }
+    }
+}
+
+/// Its total isn't known until the player's chunk is
+fn expect_spawn_chunks(mut progress: ResMut<LoadingProgress>) {
+    progress.expect(LOADING_WORK, 1);
+}
+
+fn report_spawn_chunks(
+    settings: Res<RendererSettings>,
+    streaming: Res<Streaming>,
+    chunk_meshes: Res<ChunkMeshes>,
+    mut progress: ResMut<LoadingProgress>,
+) {
+    let Some(center) = streaming.center else {
+        return;
+    };
+    let radius = SPAWN_CHUNK_RADIUS;
+    let spawn_chunks: Vec<IVec3> = (-radius..=radius)
+        .flat_map(|y| {
+            (-radius..=radius)
+                .flat_map(move |z| (-radius..=radius).map(move |x| IVec3::new(x, y, z)))
+        })
+        .map(|offset| center + offset)
+        .filter(|chunk| settings.view_distance.contains(center, *chunk))
+        .collect();
+    let meshed = spawn_chunks
+        .iter()
+        .filter(|chunk| chunk_meshes.meshes.contains_key(chunk))
+        .count();
+    progress.set(LOADING_WORK, meshed as u32, spawn_chunks.len() as u32);
+}
+
+fn update_streaming_stats(
+    streaming: Res<Streaming>,
+    chunks: Res<ChunkMap>,
+    cache: Res<ChunkCache>,
+    mut stats: ResMut<StreamingStats>,
+) {
+    *stats = StreamingStats {
+        loaded_chunks: chunks.len(),
+        queued_generation: streaming.generation.len(),
+        generating: streaming.generating.len(),
+        queued_meshing: streaming.meshing_queue.len(),
+        meshing: streaming.meshing.len(),
+        queued_as_builds: streaming.as_build_queue.len(),
+        cached_chunks: cache.len(),
+        cache_bytes: cache.bytes(),
+        cache_hits: cache.hits(),
+        cache_misses: cache.misses(),
+        ..streaming.stats
+    };
+}
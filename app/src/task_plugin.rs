@@ -0,0 +1,153 @@
+use std::future::Future;
+
+use bevy_app::Plugin;
+use bevy_ecs::{component::Component, system::Resource};
+use bevy_tasks::{
+    available_parallelism, block_on, poll_once, AsyncComputeTaskPool, ComputeTaskPool, IoTaskPool,
+    Scope, TaskPool, TaskPoolBuilder,
+};
+
+/// Sets up the global compute, IO and async compute thread pools and inserts [`TaskPools`].
+/// Must be added before anything spawns tasks or runs a multithreaded schedule, since the
+/// pools can only be initialized once.
+#[derive(Default)]
+pub struct TaskPlugin {
+    /// Total worker threads; defaults to the available parallelism
+    pub threads: Option<usize>,
+}
+
+impl TaskPlugin {
+    /// IO and async compute each get this share of the threads, within `1..=MAX_GROUP_THREADS`
+    const GROUP_SHARE: f32 = 0.25;
+    const MAX_GROUP_THREADS: usize = 4;
+
+    fn group_threads(total: usize) -> usize {
+        ((total as f32 * Self::GROUP_SHARE) as usize).clamp(1, Self::MAX_GROUP_THREADS)
+    }
+}
+
+impl Plugin for TaskPlugin {
+    fn build(&self, app: &mut bevy_app::App) {
+        let total = self.threads.unwrap_or_else(available_parallelism).max(1);
+        let io = Self::group_threads(total);
+        let async_compute = Self::group_threads(total);
+        // Compute always gets a thread even when the groups take up the whole budget
+        let compute = total.saturating_sub(io + async_compute).max(1);
+
+        let pool = |threads: usize, name: &str| {
+            TaskPoolBuilder::new()
+                .num_threads(threads)
+                .thread_name(name.to_owned())
+                .build()
+        };
+        ComputeTaskPool::get_or_init(|| pool(compute, "Compute"));
+        IoTaskPool::get_or_init(|| pool(io, "IO"));
+        AsyncComputeTaskPool::get_or_init(|| pool(async_compute, "Async Compute"));
+
+        app.init_resource::<TaskPools>();
+    }
+}
+
+/// Which pool work runs on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskGroup {
+    /// Work that must finish within the frame, e.g. parallel meshing in a scope
+    Compute,
+    /// Blocking file and network access, e.g. saving
+    Io,
+    /// CPU work that may span several frames, e.g. chunk generation
+    AsyncCompute,
+}
+
+/// Handle to the engine's task pools for systems
+#[derive(Resource, Debug, Default, Clone, Copy)]
+pub struct TaskPools;
+
+impl TaskPools {
+    pub fn pool(&self, group: TaskGroup) -> &'static TaskPool {
+        match group {
+            TaskGroup::Compute => ComputeTaskPool::get(),
+            TaskGroup::Io => IoTaskPool::get(),
+            TaskGroup::AsyncCompute => AsyncComputeTaskPool::get(),
+        }
+    }
+
+    /// Starts `future` on `group`. Keep the returned task, e.g. in a [`Task`] component, or
+    /// detach it; dropping it cancels the work.
+    pub fn spawn<T: Send + 'static>(
+        &self,
+        group: TaskGroup,
+        future: impl Future<Output = T> + Send + 'static,
+    ) -> bevy_tasks::Task<T> {
+        self.pool(group).spawn(future)
+    }
+
+    /// Runs the tasks spawned on the scope to completion on `group`, blocking the calling
+    /// system, and returns their results in spawn order
+    pub fn scope<'env, F, T>(&self, group: TaskGroup, f: F) -> Vec<T>
+    where
+        F: for<'scope> FnOnce(&'scope Scope<'scope, 'env, T>),
+        T: Send + 'static,
+    {
+        self.pool(group).scope(f)
+    }
+}
+
+/// A task whose result is picked up by polling from a system, one entity per task
+#[derive(Component)]
+pub struct Task<T: Send + 'static>(bevy_tasks::Task<T>);
+
+impl<T: Send + 'static> Task<T> {
+    pub fn new(task: bevy_tasks::Task<T>) -> Self {
+        Self(task)
+    }
+
+    /// The result once the task has finished. Must not be polled again after it returned
+    /// `Some`, so remove the component at that point.
+    pub fn poll(&mut self) -> Option<T> {
+        block_on(poll_once(&mut self.0))
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.0.is_finished()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_app::App;
+    use data::{
+        prelude::IVec3,
+        worldgen::{chunk_hash, generate_chunk, WorldSeed},
+    };
+
+    use super::*;
+
+    #[test]
+    fn scope_and_polled_tasks_complete() {
+        let mut app = App::new();
+        app.add_plugins(TaskPlugin { threads: Some(4) });
+        let tasks = *app.world().resource::<TaskPools>();
+
+        let chunks = [IVec3::ZERO, IVec3::NEG_Y, IVec3::new(-3, 0, 5)];
+        let hashes = tasks.scope(TaskGroup::Compute, |scope| {
+            for chunk in chunks {
+                scope.spawn(async move { chunk_hash(&generate_chunk(WorldSeed(0), chunk)) });
+            }
+        });
+        let expected: Vec<u64> = chunks
+            .iter()
+            .map(|chunk| chunk_hash(&generate_chunk(WorldSeed(0), *chunk)))
+            .collect();
+        assert_eq!(hashes, expected);
+
+        let mut task = Task::new(tasks.spawn(TaskGroup::AsyncCompute, async { 7 }));
+        let result = loop {
+            if let Some(result) = task.poll() {
+                break result;
+            }
+            std::thread::yield_now();
+        };
+        assert_eq!(result, 7);
+    }
+}
@@ -1,13 +1,75 @@
 use std::time::{Duration, Instant};
 
-use bevy_app::{Plugin, Update};
-use bevy_ecs::system::{ResMut, Resource};
+use bevy_app::{FixedFirst, FixedMain, Plugin, RunFixedMainLoop, RunFixedMainLoopSystem, Update};
+use bevy_ecs::{
+    schedule::IntoSystemConfigs,
+    system::{Query, ResMut, Resource},
+    world::World,
+};
+use data::{
+    block_tick::GameTick,
+    transform::{PreviousTransform, Transform},
+};
 
+/// Keeps the frame [`Time`] and runs the fixed schedules, [`FixedUpdate`](bevy_app::FixedUpdate)
+/// among them, once per [`GameTick`]
 pub struct TimePlugin;
 
 impl Plugin for TimePlugin {
     fn build(&self, app: &mut bevy_app::App) {
-        app.init_resource::<Time>().add_systems(Update, update_time);
+        app.init_resource::<Time>()
+            .init_resource::<GameTick>()
+            .init_resource::<FixedTimestep>()
+            .add_systems(Update, update_time)
+            .add_systems(
+                RunFixedMainLoop,
+                run_fixed_main.in_set(RunFixedMainLoopSystem::FixedMainLoop),
+            )
+            .add_systems(FixedFirst, (advance_game_tick, store_previous_transforms));
+    }
+}
+
+/// Ticks run in one frame at most. A frame that takes longer, e.g. a hitch while loading,
+/// drops the rest instead of making the next frames even slower catching up.
+const MAX_TICKS_PER_FRAME: u32 = 10;
+
+/// Frame time not yet used up by ticks
+#[derive(Resource, Default)]
+pub struct FixedTimestep {
+    accumulated: Duration,
+}
+
+impl FixedTimestep {
+    pub const TICK: Duration = Duration::from_nanos(1_000_000_000 / GameTick::PER_SECOND as u64);
+
+    /// How far the frame is into the next tick, from 0 right after one to almost 1 just
+    /// before the next
+    pub fn overstep_fraction(&self) -> f32 {
+        self.accumulated.as_secs_f32() / Self::TICK.as_secs_f32()
+    }
+
+    /// Where an entity moved by the fixed schedules is drawn this frame. Entities without a
+    /// [`PreviousTransform`] are drawn where they are.
+    pub fn interpolate(
+        &self,
+        transform: &Transform,
+        previous: Option<&PreviousTransform>,
+    ) -> Transform {
+        previous.map_or(*transform, |previous| {
+            previous.interpolate(transform, self.overstep_fraction())
+        })
+    }
+
+    /// Adds a frame's time and returns how many ticks are due
+    fn advance(&mut self, delta: Duration) -> u32 {
+        self.accumulated += delta;
+        let due = (self.accumulated.as_nanos() / Self::TICK.as_nanos()) as u32;
+        if due > MAX_TICKS_PER_FRAME {
+            self.accumulated = Duration::ZERO;
+            return MAX_TICKS_PER_FRAME;
+        }
+        self.accumulated -= Self::TICK * due;
+        due
     }
 }
 
@@ -16,6 +78,7 @@ pub struct Time {
     start: Instant,
     last: Instant,
     current: Instant,
+    paused: bool,
 }
 
 impl Default for Time {
@@ -24,6 +87,7 @@ impl Default for Time {
             start: Instant::now(),
             last: Instant::now(),
             current: Instant::now(),
+            paused: false,
         }
     }
 }
@@ -44,9 +108,46 @@ impl Time {
     pub fn elapsed_secs(&self) -> f32 {
         self.elapsed().as_secs_f32()
     }
+
+    /// While paused, [`Self::delta`] is zero and [`Self::elapsed`] stands still
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    pub const fn is_paused(&self) -> bool {
+        self.paused
+    }
 }
 
 fn update_time(mut time: ResMut<Time>) {
-    time.last = time.current;
-    time.current = Instant::now();
+    let now = Instant::now();
+    if time.paused {
+        // Shift the start so the paused span never counts towards elapsed time
+        let paused_for = now.duration_since(time.current);
+        time.start += paused_for;
+        time.last = now;
+    } else {
+        time.last = time.current;
+    }
+    time.current = now;
+}
+
+/// Runs before `update_time`, so the ticks catch up with the previous frame. Paused time has
+/// no delta, so the simulation pauses with it.
+fn run_fixed_main(world: &mut World) {
+    let delta = world.resource::<Time>().delta();
+    let due = world.resource_mut::<FixedTimestep>().advance(delta);
+    for _ in 0..due {
+        FixedMain::run_fixed_main(world);
+    }
+}
+
+fn advance_game_tick(mut tick: ResMut<GameTick>) {
+    *tick = tick.after(1);
+}
+
+fn store_previous_transforms(mut transforms: Query<(&Transform, &mut PreviousTransform)>) {
+    for (transform, mut previous) in &mut transforms {
+        previous.0 = *transform;
+    }
 }
@@ -7,13 +7,10 @@ use bevy_ecs::{
 };
 use bevy_input::{keyboard::KeyCode, ButtonInput};
 use bevy_window::{CursorGrabMode, PrimaryWindow, Window, WindowFocused, WindowResized};
-use glam::Vec2;
-use renderer::{
-    acceleration_structure_state::AccelerationStructureState, buffer_state::BufferState,
-    init_state::InitState, swapchain_state::SwapchainState,
-};
+use data::prelude::Vec2;
+use renderer::{init_state::InitState, swapchain_state::SwapchainState};
 
-use crate::render_plugin::CleanupEvent;
+use crate::render_plugin::{CleanupEvent, RenderPathState};
 
 pub struct WindowPlugin;
 
@@ -63,15 +60,14 @@ fn recreate_swapchain(
     mut resized_reader: EventReader<WindowResized>,
     init_state: Res<InitState>,
     mut swapchain_state: ResMut<SwapchainState>,
-    buffer_state: Res<BufferState<'static>>,
-    mut acceleration_structure_state: ResMut<AccelerationStructureState<'static>>,
+    mut path_state: RenderPathState,
 ) {
     for resize in resized_reader.read() {
-        swapchain_state
+        path_state
+            .frame_path()
             .recreate_swapchain(
                 &init_state,
-                &buffer_state,
-                &mut acceleration_structure_state,
+                &mut swapchain_state,
                 Vec2::new(resize.width, resize.height),
             )
             .unwrap();
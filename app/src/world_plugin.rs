@@ -0,0 +1,317 @@
+use bevy_app::{App, Plugin, Update};
+use bevy_ecs::{
+    entity::Entity,
+    event::{Event, EventReader, EventWriter},
+    query::{Changed, With, Without},
+    schedule::{common_conditions::not, IntoSystemConfigs},
+    system::{Commands, Query, Res, ResMut, Single, SystemParam},
+};
+use bevy_input::{keyboard::KeyCode, ButtonInput};
+use bevy_window::{PrimaryWindow, Window};
+use data::{
+    block_tick::{BlockTicks, GameTick},
+    chunk_cache::ChunkCache,
+    chunk_map::{chunk_of, ChunkMap},
+    edit_history::{EditHistory, VoxelEdit},
+    edit_prediction::{EditId, PredictedEdits},
+    floating_origin::{FloatingOrigin, WorldPosition},
+    prelude::{IVec3, Vec3},
+    transform::{PreviousTransform, Transform},
+    world_border::WorldBorder,
+    worldgen::{generate_chunk, WorldSeed},
+};
+use renderer::{
+    command_state::CommandState,
+    hud::{Hud, HudRect},
+};
+
+use crate::{
+    hud_plugin::{build_hud, ZOOM_MODIFIER},
+    photo_mode_plugin::photo_mode_active,
+    player_plugin::{move_player, Player},
+    simulation_plugin::VoxelChanged,
+};
+
+/// Owns the world border and the [`FloatingOrigin`], moves the player on [`Teleport`] and
+/// undoes [`VoxelEdits`]. The chunks themselves are owned by the
+/// [`SimulationPlugin`](crate::simulation_plugin::SimulationPlugin).
+pub struct WorldPlugin;
+
+impl Plugin for WorldPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ChunkCache>()
+            .init_resource::<WorldBorder>()
+            .init_resource::<FloatingOrigin>()
+            .init_resource::<EditHistory>()
+            .add_event::<Teleport>()
+            .add_event::<VoxelEdited>()
+            .add_systems(
+                Update,
+                (
+                    (
+                        teleport,
+                        // The photo mode camera may look at the world from outside
+                        keep_player_inside_border.run_if(not(photo_mode_active)),
+                        store_world_positions.run_if(|| cfg!(feature = "f64-world")),
+                        rebase_origin,
+                    )
+                        .chain()
+                        .after(move_player),
+                    draw_border_warning.after(build_hud),
+                    undo_edits,
+                ),
+            );
+    }
+}
+
+/// Moves the player to the world `position`, or the nearest free space above it, within the
+/// border
+#[derive(Event, Debug, Clone, Copy)]
+pub struct Teleport {
+    pub position: Vec3,
+}
+
+/// Sent for every voxel changed through [`VoxelEdits`], including undos and redos, with the
+/// voxel before and after. Unlike [`VoxelChanged`], block ticks don't send it.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct VoxelEdited(pub VoxelEdit);
+
+/// Held with [`UNDO_KEY`] or [`REDO_KEY`]
+pub const UNDO_MODIFIER: KeyCode = ZOOM_MODIFIER;
+pub const UNDO_KEY: KeyCode = KeyCode::KeyZ;
+pub const REDO_KEY: KeyCode = KeyCode::KeyY;
+
+/// Changes voxels on the player's behalf: every change is remeshed, wakes the block ticks
+/// around it and is recorded in the [`EditHistory`] to be undone. While connected to a
+/// server, changes are also [`PredictedEdits`] until the server answers.
+#[derive(SystemParam)]
+pub struct VoxelEdits<'w> {
+    pub chunks: ResMut<'w, ChunkMap>,
+    history: ResMut<'w, EditHistory>,
+    ticks: ResMut<'w, BlockTicks>,
+    tick: Res<'w, GameTick>,
+    predicted: Option<ResMut<'w, PredictedEdits>>,
+    changed_writer: EventWriter<'w, VoxelChanged>,
+    edited_writer: EventWriter<'w, VoxelEdited>,
+}
+
+impl VoxelEdits<'_> {
+    /// Records edits already applied to [`Self::chunks`] as one transaction
+    pub fn commit(&mut self, edits: Vec<VoxelEdit>) {
+        self.notify(&edits);
+        self.predict(&edits);
+        self.history.record(edits);
+    }
+
+    pub fn undo(&mut self) {
+        let edits = self.history.undo(&mut self.chunks);
+        self.notify(&edits);
+        self.predict(&edits);
+    }
+
+    pub fn redo(&mut self) {
+        let edits = self.history.redo(&mut self.chunks);
+        self.notify(&edits);
+        self.predict(&edits);
+    }
+
+    /// The server applied a prediction
+    pub fn acknowledge(&mut self, id: EditId) {
+        if let Some(predicted) = &mut self.predicted {
+            predicted.acknowledge(id);
+        }
+    }
+
+    /// The server turned a prediction down, so it's rolled back, here and in the history
+    pub fn reject(&mut self, id: EditId) {
+        let Some(predicted) = &mut self.predicted else {
+            return;
+        };
+        let Some((undone, rejected)) = predicted.reject(id, &mut self.chunks) else {
+            return;
+        };
+        self.history.roll_back(&rejected);
+        self.notify(&undone);
+    }
+
+    /// The server replaced `chunk`, so the predictions in it are applied again
+    pub fn reapply(&mut self, chunk: IVec3) {
+        let Some(predicted) = &mut self.predicted else {
+            return;
+        };
+        for position in predicted.reapply(chunk, &mut self.chunks) {
+            self.changed_writer.send(VoxelChanged { position });
+        }
+    }
+
+    fn predict(&mut self, edits: &[VoxelEdit]) {
+        if let Some(predicted) = &mut self.predicted {
+            predicted.predict(edits.to_vec());
+        }
+    }
+
+    fn notify(&mut self, edits: &[VoxelEdit]) {
+        for edit in edits {
+            self.ticks
+                .schedule_around(&self.chunks, *self.tick, edit.position);
+            self.changed_writer.send(VoxelChanged {
+                position: edit.position,
+            });
+            self.edited_writer.send(VoxelEdited(*edit));
+        }
+    }
+}
+
+/// Chunks around the destination generated before the player arrives
+const TELEPORT_LOAD_RADIUS: i32 = 1;
+/// Voxels the player occupies below their eyes, including the eye voxel
+const PLAYER_HEIGHT: u32 = 2;
+/// How far a teleport into terrain may be moved up to find room
+const MAX_TELEPORT_RISE: u32 = 64;
+
+/// The warning frame starts this many voxels from the border and is thickest at it
+const BORDER_WARNING_DISTANCE: f32 = 16.0;
+const BORDER_WARNING_THICKNESS: f32 = 24.0;
+const BORDER_WARNING_COLOR: [u8; 4] = [200, 40, 40, 255];
+
+#[allow(clippy::too_many_arguments)]
+pub fn teleport(
+    mut teleport_reader: EventReader<Teleport>,
+    seed: Res<WorldSeed>,
+    border: Res<WorldBorder>,
+    origin: Res<FloatingOrigin>,
+    mut chunks: ResMut<ChunkMap>,
+    mut cache: ResMut<ChunkCache>,
+    command_state: Option<ResMut<CommandState>>,
+    player: Single<(&mut Transform, Option<&mut PreviousTransform>), With<Player>>,
+) {
+    let Some(destination) = teleport_reader.read().last() else {
+        return;
+    };
+    let position = border.clamp(destination.position);
+    let cell = position.floor().as_ivec3();
+
+    // Load the destination now rather than letting the player arrive in unloaded terrain,
+    // from the cache where it can be so earlier edits there are kept
+    let center = chunk_of(cell);
+    let radius = TELEPORT_LOAD_RADIUS;
+    for y in -radius..=radius {
+        for z in -radius..=radius {
+            for x in -radius..=radius {
+                let chunk = center + IVec3::new(x, y, z);
+                if !chunks.contains(chunk) {
+                    cache.load(&mut chunks, chunk);
+                }
+            }
+        }
+    }
+    chunks.load_around(center, radius, |chunk| generate_chunk(*seed, chunk));
+    let position = match chunks.free_space_above(cell, PLAYER_HEIGHT, MAX_TELEPORT_RISE) {
+        Some(free) if free != cell => free.as_vec3() + 0.5,
+        _ => position,
+    };
+
+    let (mut transform, previous) = player.into_inner();
+    transform.translation = origin.to_translation(position);
+    // Arrive without being drawn sliding there
+    if let Some(mut previous) = previous {
+        previous.0 = *transform;
+    }
+    // Nothing seen from the old position is worth averaging in
+    if let Some(mut command_state) = command_state {
+        command_state.reset_accumulation();
+    }
+}
+
+fn keep_player_inside_border(
+    border: Res<WorldBorder>,
+    origin: Res<FloatingOrigin>,
+    player: Single<&mut Transform, With<Player>>,
+) {
+    let mut transform = player.into_inner();
+    let position = origin.to_world(transform.translation);
+    let clamped = border.clamp(position);
+    if clamped != position {
+        transform.translation = origin.to_translation(clamped);
+    }
+}
+
+/// Takes moves made to translations into the entities' [`WorldPosition`]s, giving one to
+/// entities that have none yet
+fn store_world_positions(
+    mut commands: Commands,
+    origin: Res<FloatingOrigin>,
+    new: Query<(Entity, &Transform), Without<WorldPosition>>,
+    mut moved: Query<(&Transform, &mut WorldPosition), Changed<Transform>>,
+) {
+    for (entity, transform) in &new {
+        commands
+            .entity(entity)
+            .insert(WorldPosition::new(&origin, transform.translation));
+    }
+    for (transform, mut position) in &mut moved {
+        position.follow(&origin, transform.translation);
+    }
+}
+
+/// Moves the origin under the player once they have wandered far from it, shifting every
+/// translation by the same whole chunks so nothing appears to move
+pub fn rebase_origin(
+    mut origin: ResMut<FloatingOrigin>,
+    player: Single<&Transform, With<Player>>,
+    mut transforms: Query<(&mut Transform, Option<&WorldPosition>)>,
+    mut previous_transforms: Query<&mut PreviousTransform>,
+) {
+    let Some(shift) = origin.rebase(player.translation) else {
+        return;
+    };
+    let shift = shift.as_vec3();
+    for (mut transform, position) in &mut transforms {
+        transform.translation = match position {
+            // Derived again rather than shifted, so far away entities lose nothing
+            Some(position) => origin.to_translation_f64(position.0),
+            None => transform.translation - shift,
+        };
+    }
+    for mut previous in &mut previous_transforms {
+        previous.0.translation -= shift;
+    }
+}
+
+fn undo_edits(keys: Res<ButtonInput<KeyCode>>, mut edits: VoxelEdits) {
+    if !keys.pressed(UNDO_MODIFIER) {
+        return;
+    }
+    if keys.just_pressed(UNDO_KEY) {
+        edits.undo();
+    } else if keys.just_pressed(REDO_KEY) {
+        edits.redo();
+    }
+}
+
+/// Frames the screen in red as the player nears the border
+fn draw_border_warning(
+    border: Res<WorldBorder>,
+    origin: Res<FloatingOrigin>,
+    mut hud: ResMut<Hud>,
+    window: Single<&Window, With<PrimaryWindow>>,
+    player: Single<&Transform, With<Player>>,
+) {
+    let distance = border.distance_to_edge(origin.to_world(player.translation));
+    if distance >= BORDER_WARNING_DISTANCE {
+        return;
+    }
+
+    let closeness = 1.0 - distance.max(0.0) / BORDER_WARNING_DISTANCE;
+    let thickness = (closeness * BORDER_WARNING_THICKNESS).ceil() as u32;
+    let (width, height) = (window.physical_width(), window.physical_height());
+    let far = |size: u32| size as i32 - thickness as i32;
+    for rect in [
+        HudRect::new(0, 0, width, thickness, BORDER_WARNING_COLOR),
+        HudRect::new(0, far(height), width, thickness, BORDER_WARNING_COLOR),
+        HudRect::new(0, 0, thickness, height, BORDER_WARNING_COLOR),
+        HudRect::new(far(width), 0, thickness, height, BORDER_WARNING_COLOR),
+    ] {
+        hud.push(rect);
+    }
+}
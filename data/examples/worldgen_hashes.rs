@@ -0,0 +1,28 @@
+//! Prints generated chunk hashes in the format of the worldgen test vectors.
+//!
+//! Usage: `cargo run -p data --example worldgen_hashes [seed...]`
+
+use data::worldgen::{chunk_hash, generate_chunk, WorldSeed};
+use glam::IVec3;
+
+/// Surface chunks, negative coordinates, one deep underground and one in the sky (-Y is up)
+const CHUNKS: [[i32; 3]; 5] = [[0, 0, 0], [0, -1, 0], [-3, 0, 5], [2, 4, -7], [0, -8, 0]];
+
+fn main() {
+    let seeds: Vec<u64> = std::env::args()
+        .skip(1)
+        .map(|seed| seed.parse().expect("seeds are unsigned integers"))
+        .collect();
+    let seeds = if seeds.is_empty() {
+        vec![0, 1, 0xdead_beef]
+    } else {
+        seeds
+    };
+
+    for seed in seeds {
+        for chunk in CHUNKS {
+            let hash = chunk_hash(&generate_chunk(WorldSeed(seed), IVec3::from_array(chunk)));
+            println!("({seed:#x}, {chunk:?}, {hash:#018x}),");
+        }
+    }
+}
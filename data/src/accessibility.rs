@@ -0,0 +1,132 @@
+use bevy_ecs::system::Resource;
+use glam::Mat3;
+use serde::{Deserialize, Serialize};
+
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AccessibilitySettings {
+    /// Multiplier on camera shake, 0 to 1
+    pub camera_shake: f32,
+    /// Slows how fast the whole screen may brighten or darken, e.g. auto exposure
+    pub photosensitive: bool,
+    pub color_filter: ColorFilter,
+    /// Multiplier on the size of HUD text, from 1 to [`Self::MAX_TEXT_SCALE`]
+    pub text_scale: u32,
+}
+
+impl Default for AccessibilitySettings {
+    fn default() -> Self {
+        Self {
+            camera_shake: 1.0,
+            photosensitive: false,
+            color_filter: ColorFilter::None,
+            text_scale: 1,
+        }
+    }
+}
+
+impl AccessibilitySettings {
+    pub const MAX_TEXT_SCALE: u32 = 3;
+    /// Most stops per second the exposure changes by in photosensitive mode
+    pub const PHOTOSENSITIVE_EXPOSURE_SPEED: f32 = 0.5;
+
+    pub fn clamped_text_scale(&self) -> u32 {
+        self.text_scale.clamp(1, Self::MAX_TEXT_SCALE)
+    }
+}
+
+/// Shifts colors a kind of color blindness can't tell apart into ones it can, applied to the
+/// output color after exposure
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColorFilter {
+    #[default]
+    None,
+    /// Red-blind
+    Protanopia,
+    /// Green-blind
+    Deuteranopia,
+    /// Blue-blind
+    Tritanopia,
+}
+
+impl ColorFilter {
+    pub const ALL: [Self; 4] = [
+        Self::None,
+        Self::Protanopia,
+        Self::Deuteranopia,
+        Self::Tritanopia,
+    ];
+
+    /// Daltonization: the difference between a color and how it is seen, from Machado et al.'s
+    /// simulation at full severity, is moved into the channels that are still seen
+    pub fn matrix(&self) -> Mat3 {
+        let rows = |rows: [[f32; 3]; 3]| Mat3::from_cols_array_2d(&rows).transpose();
+        let (simulation, shift) = match self {
+            Self::None => return Mat3::IDENTITY,
+            Self::Protanopia => (
+                rows([
+                    [0.152286, 1.052583, -0.204868],
+                    [0.114503, 0.786281, 0.099216],
+                    [-0.003882, -0.048116, 1.051998],
+                ]),
+                RED_GREEN_SHIFT,
+            ),
+            Self::Deuteranopia => (
+                rows([
+                    [0.367322, 0.860646, -0.227968],
+                    [0.280085, 0.672501, 0.047413],
+                    [-0.011820, 0.042940, 0.968881],
+                ]),
+                RED_GREEN_SHIFT,
+            ),
+            Self::Tritanopia => (
+                rows([
+                    [1.255528, -0.076749, -0.178779],
+                    [-0.078411, 0.930809, 0.147602],
+                    [0.004733, 0.691367, 0.303900],
+                ]),
+                BLUE_SHIFT,
+            ),
+        };
+        Mat3::IDENTITY + rows(shift) * (Mat3::IDENTITY - simulation)
+    }
+
+    /// [`Self::matrix`] laid out as a std140 `mat3`, one padded column per array
+    pub fn to_gpu(&self) -> [[f32; 4]; 3] {
+        let matrix = self.matrix();
+        [matrix.x_axis, matrix.y_axis, matrix.z_axis].map(|column| column.extend(0.0).into())
+    }
+}
+
+/// Lost red goes into green and blue
+const RED_GREEN_SHIFT: [[f32; 3]; 3] = [[0.0, 0.0, 0.0], [0.7, 1.0, 0.0], [0.7, 0.0, 1.0]];
+/// Lost blue goes into red and green
+const BLUE_SHIFT: [[f32; 3]; 3] = [[1.0, 0.0, 0.7], [0.0, 1.0, 0.7], [0.0, 0.0, 0.0]];
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+
+    use super::*;
+
+    #[test]
+    fn filters_keep_grays_and_separate_confused_colors() {
+        assert_eq!(ColorFilter::None.matrix(), Mat3::IDENTITY);
+        for filter in ColorFilter::ALL {
+            let gray = filter.matrix() * Vec3::splat(0.5);
+            assert!(gray.abs_diff_eq(Vec3::splat(0.5), 1e-4), "{filter:?}");
+        }
+
+        // Pure red and green look alike to the red-blind until filtered
+        let red_blind = Mat3::from_cols_array_2d(&[
+            [0.152286, 1.052583, -0.204868],
+            [0.114503, 0.786281, 0.099216],
+            [-0.003882, -0.048116, 1.051998],
+        ])
+        .transpose();
+        let seen_apart =
+            |matrix: Mat3| (red_blind * matrix * Vec3::X).distance(red_blind * matrix * Vec3::Y);
+        assert!(seen_apart(ColorFilter::Protanopia.matrix()) > seen_apart(Mat3::IDENTITY));
+    }
+}
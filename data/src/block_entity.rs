@@ -0,0 +1,116 @@
+use std::collections::HashMap;
+
+use glam::IVec3;
+use serde::{Deserialize, Serialize};
+
+use crate::{inventory::Inventory, Direction};
+
+/// Data a voxel needs beyond its id, kept sparsely per position by the
+/// [`ChunkMap`](crate::chunk_map::ChunkMap). Each variant wraps a type that gameplay code
+/// attaches and reads through [`BlockEntityData`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum BlockEntity {
+    Orientation(Orientation),
+    Container(Inventory),
+    Text(SignText),
+}
+
+/// Which way a voxel faces
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Orientation(pub Direction);
+
+/// Text written on a voxel
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignText(pub String);
+
+/// A type stored as one [`BlockEntity`] variant
+pub trait BlockEntityData: Into<BlockEntity> {
+    fn from_entity(entity: &BlockEntity) -> Option<&Self>;
+    fn from_entity_mut(entity: &mut BlockEntity) -> Option<&mut Self>;
+}
+
+macro_rules! block_entity_data {
+    ($($variant:ident($data:ty)),* $(,)?) => {
+        $(
+            impl From<$data> for BlockEntity {
+                fn from(data: $data) -> Self {
+                    Self::$variant(data)
+                }
+            }
+
+            impl BlockEntityData for $data {
+                fn from_entity(entity: &BlockEntity) -> Option<&Self> {
+                    match entity {
+                        BlockEntity::$variant(data) => Some(data),
+                        _ => None,
+                    }
+                }
+
+                fn from_entity_mut(entity: &mut BlockEntity) -> Option<&mut Self> {
+                    match entity {
+                        BlockEntity::$variant(data) => Some(data),
+                        _ => None,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+block_entity_data!(
+    Orientation(Orientation),
+    Container(Inventory),
+    Text(SignText),
+);
+
+/// The block entities of one chunk, by world position
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BlockEntities {
+    entities: HashMap<IVec3, BlockEntity>,
+}
+
+impl BlockEntities {
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    pub fn get(&self, position: IVec3) -> Option<&BlockEntity> {
+        self.entities.get(&position)
+    }
+
+    pub fn get_mut(&mut self, position: IVec3) -> Option<&mut BlockEntity> {
+        self.entities.get_mut(&position)
+    }
+
+    /// Returns the entity it replaced
+    pub fn insert(&mut self, position: IVec3, entity: BlockEntity) -> Option<BlockEntity> {
+        self.entities.insert(position, entity)
+    }
+
+    pub fn remove(&mut self, position: IVec3) -> Option<BlockEntity> {
+        self.entities.remove(&position)
+    }
+
+    /// Sorted by position, so saving the same chunk twice gives the same output
+    pub fn to_sorted(&self) -> Vec<(IVec3, BlockEntity)> {
+        let mut entities: Vec<_> = self
+            .entities
+            .iter()
+            .map(|(position, entity)| (*position, entity.clone()))
+            .collect();
+        entities.sort_by_key(|(position, _)| position.to_array());
+        entities
+    }
+}
+
+impl FromIterator<(IVec3, BlockEntity)> for BlockEntities {
+    fn from_iter<I: IntoIterator<Item = (IVec3, BlockEntity)>>(iter: I) -> Self {
+        Self {
+            entities: iter.into_iter().collect(),
+        }
+    }
+}
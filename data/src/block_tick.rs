@@ -0,0 +1,272 @@
+use std::collections::{BTreeMap, HashMap};
+
+use bevy_ecs::system::Resource;
+use glam::IVec3;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    chunk_map::{chunk_of, ChunkMap},
+    voxel::{Voxel, VoxelId},
+};
+
+/// Fixed-rate simulation steps since the world was created, [`Self::PER_SECOND`] of them a
+/// second while the game isn't paused. Block updates are scheduled in ticks, so they keep
+/// their timing across saves.
+#[derive(
+    Resource,
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+)]
+#[serde(transparent)]
+pub struct GameTick(pub u64);
+
+impl GameTick {
+    pub const PER_SECOND: u32 = 20;
+
+    pub const fn after(self, ticks: u64) -> Self {
+        Self(self.0 + ticks)
+    }
+}
+
+/// Ticks from a voxel being scheduled to its update; `None` for voxels that never update
+pub const fn tick_delay(voxel: Voxel) -> Option<u64> {
+    match voxel {
+        Voxel::Grass => Some(GRASS_SPREAD_TICKS),
+        Voxel::Water => Some(WATER_FLOW_TICKS),
+        _ => None,
+    }
+}
+
+const GRASS_SPREAD_TICKS: u64 = 5 * GameTick::PER_SECOND as u64;
+const WATER_FLOW_TICKS: u64 = 5;
+
+/// A voxel update waiting for its tick
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScheduledTick {
+    pub tick: GameTick,
+    pub position: IVec3,
+    /// Voxel the update was scheduled for. It is dropped if the voxel was replaced by another
+    /// before the tick came.
+    pub voxel: VoxelId,
+}
+
+/// Scheduled voxel updates, queued per chunk. Only loaded chunks are ticked; an unloaded
+/// chunk's updates wait and run as soon as it is loaded again.
+#[derive(Resource, Debug, Clone, Default, PartialEq)]
+pub struct BlockTicks {
+    chunks: HashMap<IVec3, ChunkTicks>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+struct ChunkTicks {
+    /// Positions by due tick, in the order they were scheduled
+    queue: BTreeMap<GameTick, Vec<IVec3>>,
+    /// At most one pending update per position
+    pending: HashMap<IVec3, (GameTick, VoxelId)>,
+}
+
+impl BlockTicks {
+    pub fn len(&self) -> usize {
+        self.chunks.values().map(|ticks| ticks.pending.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Queues an update of `voxel` at `position` for `tick`. Returns `false` and keeps the
+    /// existing one if the position already has an update pending.
+    pub fn schedule(&mut self, tick: GameTick, position: IVec3, voxel: Voxel) -> bool {
+        let ticks = self.chunks.entry(chunk_of(position)).or_default();
+        if ticks.pending.contains_key(&position) {
+            return false;
+        }
+        ticks.pending.insert(position, (tick, voxel as VoxelId));
+        ticks.queue.entry(tick).or_default().push(position);
+        true
+    }
+
+    /// Schedules every voxel in the 3x3x3 block around `position` that updates, after its
+    /// [`tick_delay`]. Called whenever a voxel changes so its neighbors can react.
+    pub fn schedule_around(&mut self, chunks: &ChunkMap, now: GameTick, position: IVec3) {
+        for y in -1..=1 {
+            for z in -1..=1 {
+                for x in -1..=1 {
+                    let neighbor = position + IVec3::new(x, y, z);
+                    let Some(voxel) = chunks.voxel(neighbor) else {
+                        continue;
+                    };
+                    if let Some(delay) = tick_delay(voxel) {
+                        self.schedule(now.after(delay), neighbor, voxel);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Removes and returns the updates due by `now` in chunks that are loaded, earliest first
+    pub fn take_due(&mut self, now: GameTick, chunks: &ChunkMap) -> Vec<ScheduledTick> {
+        let mut due = Vec::new();
+        for (chunk, ticks) in &mut self.chunks {
+            if !chunks.contains(*chunk) {
+                continue;
+            }
+            while let Some(entry) = ticks.queue.first_entry() {
+                if *entry.key() > now {
+                    break;
+                }
+                let (tick, positions) = entry.remove_entry();
+                for position in positions {
+                    if let Some((_, voxel)) = ticks.pending.remove(&position) {
+                        due.push(ScheduledTick {
+                            tick,
+                            position,
+                            voxel,
+                        });
+                    }
+                }
+            }
+        }
+        self.chunks.retain(|_, ticks| !ticks.pending.is_empty());
+        due.sort_by_key(|scheduled| scheduled.tick);
+        due
+    }
+}
+
+impl From<Vec<ScheduledTick>> for BlockTicks {
+    fn from(scheduled: Vec<ScheduledTick>) -> Self {
+        let mut ticks = Self::default();
+        for scheduled in scheduled {
+            if let Some(voxel) = Voxel::ALL.get(scheduled.voxel as usize) {
+                ticks.schedule(scheduled.tick, scheduled.position, *voxel);
+            }
+        }
+        ticks
+    }
+}
+
+impl From<&BlockTicks> for Vec<ScheduledTick> {
+    fn from(ticks: &BlockTicks) -> Self {
+        let mut scheduled: Vec<ScheduledTick> = ticks
+            .chunks
+            .values()
+            .flat_map(|ticks| {
+                ticks.queue.iter().flat_map(|(tick, positions)| {
+                    positions.iter().map(|position| ScheduledTick {
+                        tick: *tick,
+                        position: *position,
+                        voxel: ticks.pending[position].1,
+                    })
+                })
+            })
+            .collect();
+        scheduled.sort_by_key(|scheduled| (scheduled.tick, scheduled.position.to_array()));
+        scheduled
+    }
+}
+
+/// Runs a due update, returning the positions whose voxel it changed. Their neighbors are
+/// scheduled in turn, so spreading continues tick after tick.
+///
+/// - Grass turns the first dirt voxel next to it with air above into grass.
+/// - Water flows down into air.
+pub fn run_tick(
+    chunks: &mut ChunkMap,
+    ticks: &mut BlockTicks,
+    now: GameTick,
+    scheduled: ScheduledTick,
+) -> Vec<IVec3> {
+    let position = scheduled.position;
+    let Some(voxel) = chunks.voxel(position) else {
+        return Vec::new();
+    };
+    if voxel as VoxelId != scheduled.voxel {
+        return Vec::new();
+    }
+
+    let changes = match voxel {
+        Voxel::Grass => spread_grass(chunks, position)
+            .map(|dirt| (dirt, Voxel::Grass))
+            .into_iter()
+            .collect(),
+        // +Y is down
+        Voxel::Water if chunks.voxel(position + IVec3::Y) == Some(Voxel::Air) => {
+            vec![(position + IVec3::Y, Voxel::Water)]
+        }
+        _ => Vec::new(),
+    };
+    changes
+        .into_iter()
+        .filter_map(|(changed, voxel)| {
+            chunks.set_voxel(changed, voxel)?;
+            ticks.schedule_around(chunks, now, changed);
+            Some(changed)
+        })
+        .collect()
+}
+
+fn spread_grass(chunks: &ChunkMap, position: IVec3) -> Option<IVec3> {
+    (-1..=1)
+        .flat_map(|y| (-1..=1).flat_map(move |z| (-1..=1).map(move |x| IVec3::new(x, y, z))))
+        .map(|offset| position + offset)
+        .find(|neighbor| {
+            chunks.voxel(*neighbor) == Some(Voxel::Dirt)
+                && chunks.voxel(*neighbor - IVec3::Y) == Some(Voxel::Air)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::voxel_block::VoxelBlock;
+
+    use super::*;
+
+    #[test]
+    fn grass_spreads_tick_by_tick_and_survives_a_save() {
+        let mut chunks = ChunkMap::default();
+        chunks.load_around(IVec3::ZERO, 0, |_| {
+            Box::new([Voxel::Air; VoxelBlock::VOLUME as usize])
+        });
+        for x in 0..3 {
+            chunks.set_voxel(IVec3::new(x, 5, 0), Voxel::Dirt);
+        }
+        chunks.set_voxel(IVec3::new(0, 5, 0), Voxel::Grass);
+
+        let mut ticks = BlockTicks::default();
+        let mut now = GameTick(0);
+        ticks.schedule_around(&chunks, now, IVec3::new(0, 5, 0));
+        assert_eq!(ticks.len(), 1);
+        assert!(ticks.take_due(now, &chunks).is_empty());
+
+        now = now.after(GRASS_SPREAD_TICKS);
+        for scheduled in ticks.take_due(now, &chunks) {
+            run_tick(&mut chunks, &mut ticks, now, scheduled);
+        }
+        assert_eq!(chunks.voxel(IVec3::new(1, 5, 0)), Some(Voxel::Grass));
+        assert_eq!(chunks.voxel(IVec3::new(2, 5, 0)), Some(Voxel::Dirt));
+
+        // Pending updates come back from a save and still run
+        let saved: Vec<ScheduledTick> = (&ticks).into();
+        let mut ticks = BlockTicks::from(saved);
+        now = now.after(GRASS_SPREAD_TICKS);
+        for scheduled in ticks.take_due(now, &chunks) {
+            run_tick(&mut chunks, &mut ticks, now, scheduled);
+        }
+        assert_eq!(chunks.voxel(IVec3::new(2, 5, 0)), Some(Voxel::Grass));
+
+        // Unloaded chunks keep their updates until they are back
+        let unloaded = ChunkMap::default();
+        ticks.schedule(now, IVec3::new(2, 5, 0), Voxel::Grass);
+        assert!(ticks.take_due(now.after(1000), &unloaded).is_empty());
+        assert!(!ticks.is_empty());
+    }
+}
@@ -4,7 +4,7 @@ use bevy_ecs::component::Component;
 use bytemuck::{Pod, Zeroable};
 use glam::{Mat4, Vec3};
 
-use crate::{transform::Transform, IntoBytes};
+use crate::{accessibility::ColorFilter, transform::Transform, IntoBytes};
 
 #[derive(Component, Clone, Copy)]
 #[require(Transform, CameraFov)]
@@ -50,9 +50,33 @@ impl CameraFov {
 pub struct CameraGpu {
     pub proj_inverse: [[f32; 4]; 4],
     pub view_inverse: [[f32; 4]; 4],
+    /// Monotonic frame counter, used to seed stochastic sampling
+    pub frame: u32,
+    /// Frames averaged into the accumulation image since the view last changed
+    pub accumulated_frames: u32,
+    /// Lights in use at the start of the light buffer
+    pub light_count: u32,
+    /// Scale applied to the output color, see [`Exposure`](crate::exposure::Exposure)
+    pub exposure: f32,
+    /// Scale applied to the sun and the sky, see
+    /// [`Weather::sunlight`](crate::weather::Weather::sunlight). Local lights and emitters
+    /// aren't dimmed.
+    pub sunlight: f32,
+    /// Aligns `color_filter` to 16 bytes, as std140 lays it out
+    pub _padding: [f32; 3],
+    /// Applied to the output color after `exposure`, see
+    /// [`ColorFilter`](crate::accessibility::ColorFilter). Columns of a `mat3`.
+    pub color_filter: [[f32; 4]; 3],
 }
 
 impl CameraGpu {
+    /// Distance to the near plane. There is no far plane; the fog ends the view instead.
+    pub const NEAR: f32 = 0.1;
+
+    /// The projection is reverse-Z with an infinite far plane: the near plane maps to depth 1
+    /// and infinity to 0, which spreads float depth precision evenly enough that distant
+    /// chunks don't z-fight on the raster paths. Rays are generated through the inverse, so
+    /// the traced paths aren't affected.
     pub fn new(
         transform: &Transform,
         fov_degrees: f32,
@@ -65,11 +89,10 @@ impl CameraGpu {
             Vec3::Y,
         );
 
-        let proj = Mat4::perspective_rh(
+        let proj = Mat4::perspective_infinite_reverse_rh(
             fov_degrees.to_radians(),
-            window_width / window_height,
-            0.1,
-            100.0,
+            Self::aspect_ratio(window_width, window_height),
+            Self::NEAR,
         );
 
         let view_inverse = view.inverse().to_cols_array_2d();
@@ -78,8 +101,36 @@ impl CameraGpu {
         CameraGpu {
             view_inverse,
             proj_inverse,
+            frame: 0,
+            accumulated_frames: 0,
+            light_count: 0,
+            exposure: 1.0,
+            sunlight: 1.0,
+            _padding: [0.0; 3],
+            color_filter: ColorFilter::None.to_gpu(),
+        }
+    }
+
+    /// Width over height, or square for a minimized window so the projection stays invertible
+    pub fn aspect_ratio(window_width: f32, window_height: f32) -> f32 {
+        if window_width > 0.0 && window_height > 0.0 {
+            window_width / window_height
+        } else {
+            1.0
         }
     }
+
+    /// World to clip space, for the raster path
+    pub fn view_proj(&self) -> Mat4 {
+        (Mat4::from_cols_array_2d(&self.view_inverse)
+            * Mat4::from_cols_array_2d(&self.proj_inverse))
+        .inverse()
+    }
+
+    /// Whether both cameras see the scene from the same place
+    pub fn same_view(&self, other: &Self) -> bool {
+        self.view_inverse == other.view_inverse && self.proj_inverse == other.proj_inverse
+    }
 }
 
 impl IntoBytes for CameraGpu {
@@ -87,3 +138,33 @@ impl IntoBytes for CameraGpu {
         bytemuck::cast_slice(slice::from_ref(self))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zero_sized_window_keeps_the_projection_finite() {
+        assert_eq!(CameraGpu::aspect_ratio(1920.0, 1080.0), 1920.0 / 1080.0);
+        assert_eq!(CameraGpu::aspect_ratio(1920.0, 0.0), 1.0);
+
+        let camera = CameraGpu::new(&Transform::default(), 45.0, 0.0, 0.0);
+        assert!(camera
+            .proj_inverse
+            .iter()
+            .flatten()
+            .all(|value| value.is_finite()));
+    }
+
+    #[test]
+    fn depth_is_reversed_without_a_far_plane() {
+        let camera = CameraGpu::new(&Transform::default(), 70.0, 1920.0, 1080.0);
+        let depth = |distance: f32| camera.view_proj().project_point3(Vec3::Z * -distance).z;
+
+        assert!((depth(CameraGpu::NEAR) - 1.0).abs() < 1e-5);
+        // Further is smaller, and stays distinguishable kilometers away
+        assert!(depth(10.0) > depth(1000.0));
+        assert!(depth(4000.0) > depth(4001.0));
+        assert!(depth(1e6) > 0.0);
+    }
+}
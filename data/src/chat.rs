@@ -0,0 +1,136 @@
+use std::collections::VecDeque;
+
+use bevy_ecs::system::Resource;
+use serde::{Deserialize, Serialize};
+
+/// Longest message in characters; longer ones are cut off
+pub const MAX_CHAT_LEN: usize = 256;
+
+/// A line of chat, from a player or from the server itself
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChatMessage {
+    /// `None` for system messages, e.g. a player joining
+    pub sender: Option<String>,
+    pub text: String,
+}
+
+impl ChatMessage {
+    pub fn player(sender: impl Into<String>, text: impl Into<String>) -> Self {
+        Self {
+            sender: Some(sender.into()),
+            text: text.into(),
+        }
+    }
+
+    pub fn system(text: impl Into<String>) -> Self {
+        Self {
+            sender: None,
+            text: text.into(),
+        }
+    }
+
+    /// As shown in the chat log
+    pub fn line(&self) -> String {
+        match &self.sender {
+            Some(sender) => format!("<{sender}> {}", self.text),
+            None => format!("* {}", self.text),
+        }
+    }
+}
+
+/// A command typed into chat, e.g. `save` for `/save`
+pub fn chat_command(text: &str) -> Option<&str> {
+    text.strip_prefix('/').filter(|command| !command.is_empty())
+}
+
+/// `text` as it may be sent: without control characters or surrounding whitespace, at most
+/// [`MAX_CHAT_LEN`] characters, and `None` if nothing is left
+pub fn sanitize_chat(text: &str) -> Option<String> {
+    let text: String = text
+        .trim()
+        .chars()
+        .filter(|c| !c.is_control())
+        .take(MAX_CHAT_LEN)
+        .collect();
+    (!text.is_empty()).then_some(text)
+}
+
+/// The messages received so far, newest last, each with the seconds since it arrived
+#[derive(Resource, Debug, Default)]
+pub struct ChatLog {
+    messages: VecDeque<(ChatMessage, f32)>,
+}
+
+impl ChatLog {
+    /// Messages kept for scrolling back; older ones are dropped
+    pub const MAX_MESSAGES: usize = 100;
+
+    pub fn push(&mut self, message: ChatMessage) {
+        if self.messages.len() == Self::MAX_MESSAGES {
+            self.messages.pop_front();
+        }
+        self.messages.push_back((message, 0.0));
+    }
+
+    /// Ages the messages by `delta` seconds
+    pub fn update(&mut self, delta: f32) {
+        for (_, age) in &mut self.messages {
+            *age += delta;
+        }
+    }
+
+    /// Up to `count` of the newest messages younger than `max_age` seconds, oldest first
+    pub fn recent(&self, count: usize, max_age: f32) -> impl Iterator<Item = &ChatMessage> {
+        let young = self
+            .messages
+            .iter()
+            .rev()
+            .take(count)
+            .take_while(|(_, age)| *age < max_age)
+            .count();
+        self.messages
+            .iter()
+            .skip(self.messages.len() - young)
+            .map(|(message, _)| message)
+    }
+
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chat_is_sanitized_and_fades() {
+        assert_eq!(
+            sanitize_chat("  hi\u{7}there \n"),
+            Some("hithere".to_owned())
+        );
+        assert_eq!(sanitize_chat(" \t"), None);
+        assert_eq!(
+            sanitize_chat(&"a".repeat(MAX_CHAT_LEN + 10)).map(|text| text.len()),
+            Some(MAX_CHAT_LEN)
+        );
+        assert_eq!(chat_command("/kick bob"), Some("kick bob"));
+        assert_eq!(chat_command("/"), None);
+        assert_eq!(chat_command("hello"), None);
+
+        let mut log = ChatLog::default();
+        log.push(ChatMessage::system("bob joined"));
+        log.update(5.0);
+        log.push(ChatMessage::player("bob", "hello"));
+        log.push(ChatMessage::player("amy", "hi"));
+        let lines: Vec<String> = log.recent(10, 3.0).map(ChatMessage::line).collect();
+        assert_eq!(lines, ["<bob> hello", "<amy> hi"]);
+        let lines: Vec<String> = log.recent(1, 10.0).map(ChatMessage::line).collect();
+        assert_eq!(lines, ["<amy> hi"]);
+        assert_eq!(log.recent(10, 10.0).next().unwrap().line(), "* bob joined");
+    }
+}
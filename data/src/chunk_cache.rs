@@ -0,0 +1,174 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    mem,
+};
+
+use bevy_ecs::system::Resource;
+use glam::IVec3;
+
+use crate::{
+    block_entity::BlockEntity,
+    chunk_map::{ChunkMap, ChunkSave},
+    voxel_block::Rle,
+};
+
+/// Recently unloaded chunks, kept run-length encoded so coming back to an area loads them from
+/// memory instead of generating them again, edits included. Once the entries take more than
+/// `budget_bytes`, the ones unloaded longest ago are evicted.
+#[derive(Resource, Debug, Clone)]
+pub struct ChunkCache {
+    entries: HashMap<IVec3, CacheEntry>,
+    /// Chunks by when they were cached, oldest first
+    lru: BTreeMap<u64, IVec3>,
+    clock: u64,
+    bytes: usize,
+    pub budget_bytes: usize,
+    hits: u64,
+    misses: u64,
+}
+
+#[derive(Debug, Clone)]
+struct CacheEntry {
+    save: ChunkSave,
+    cached_at: u64,
+}
+
+impl Default for ChunkCache {
+    fn default() -> Self {
+        Self::new(64 << 20)
+    }
+}
+
+impl ChunkCache {
+    pub fn new(budget_bytes: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            lru: BTreeMap::new(),
+            clock: 0,
+            bytes: 0,
+            budget_bytes,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Approximate memory held by the cached chunks
+    pub const fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    /// Lookups that found their chunk, since the cache was created
+    pub const fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    /// Lookups that had to fall back to generating the chunk
+    pub const fn misses(&self) -> u64 {
+        self.misses
+    }
+
+    /// Caches a chunk that is being unloaded, replacing any older copy, then evicts down to
+    /// the budget
+    pub fn insert(&mut self, chunk: IVec3, save: ChunkSave) {
+        self.remove(chunk);
+        self.clock += 1;
+        self.bytes += save_bytes(&save);
+        self.lru.insert(self.clock, chunk);
+        self.entries.insert(
+            chunk,
+            CacheEntry {
+                save,
+                cached_at: self.clock,
+            },
+        );
+        while self.bytes > self.budget_bytes {
+            let Some((_, oldest)) = self.lru.pop_first() else {
+                break;
+            };
+            if let Some(entry) = self.entries.remove(&oldest) {
+                self.bytes -= save_bytes(&entry.save);
+            }
+        }
+    }
+
+    /// Removes and returns a cached chunk, counting a hit or a miss
+    pub fn take(&mut self, chunk: IVec3) -> Option<ChunkSave> {
+        let save = self.remove(chunk);
+        match save {
+            Some(_) => self.hits += 1,
+            None => self.misses += 1,
+        }
+        save
+    }
+
+    /// Loads a cached chunk into the map. Returns the loaded neighbors like
+    /// [`ChunkMap::insert`], or `None` on a miss. The cache encoded the chunk itself, so it
+    /// always decodes.
+    pub fn load(&mut self, chunks: &mut ChunkMap, chunk: IVec3) -> Option<Vec<IVec3>> {
+        let save = self.take(chunk)?;
+        chunks.load_chunk(chunk, save).ok()
+    }
+
+    fn remove(&mut self, chunk: IVec3) -> Option<ChunkSave> {
+        let entry = self.entries.remove(&chunk)?;
+        self.lru.remove(&entry.cached_at);
+        self.bytes -= save_bytes(&entry.save);
+        Some(entry.save)
+    }
+}
+
+fn save_bytes(save: &ChunkSave) -> usize {
+    mem::size_of::<ChunkSave>()
+        + save.voxels.len() * mem::size_of::<Rle>()
+        + save.block_entities.len() * mem::size_of::<(IVec3, BlockEntity)>()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{voxel::Voxel, voxel_block::VoxelBlock};
+
+    use super::*;
+
+    #[test]
+    fn least_recently_unloaded_chunks_are_evicted_first() {
+        let mut chunks = ChunkMap::default();
+        chunks.load_around(IVec3::ZERO, 1, |_| {
+            Box::new([Voxel::Air; VoxelBlock::VOLUME as usize])
+        });
+        chunks.set_voxel(IVec3::new(1, 2, 3), Voxel::Stone);
+        let save = chunks.save_chunk(IVec3::ZERO).unwrap();
+
+        // Room for two chunks
+        let mut cache = ChunkCache::new(save_bytes(&save) * 2);
+        cache.insert(IVec3::ZERO, save);
+        cache.insert(IVec3::X, chunks.save_chunk(IVec3::X).unwrap());
+        cache.insert(IVec3::Y, chunks.save_chunk(IVec3::Y).unwrap());
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.take(IVec3::ZERO), None);
+
+        // Unloading again makes a chunk the most recent
+        cache.insert(IVec3::X, chunks.save_chunk(IVec3::X).unwrap());
+        cache.insert(IVec3::Z, chunks.save_chunk(IVec3::Z).unwrap());
+        assert_eq!(cache.take(IVec3::Y), None);
+        assert!(cache.take(IVec3::X).is_some());
+        assert_eq!((cache.hits(), cache.misses()), (1, 2));
+
+        // Edits come back with the chunk
+        cache.insert(IVec3::ZERO, chunks.save_chunk(IVec3::ZERO).unwrap());
+        chunks.remove(IVec3::ZERO);
+        cache.load(&mut chunks, IVec3::ZERO).unwrap();
+        assert_eq!(chunks.voxel(IVec3::new(1, 2, 3)), Some(Voxel::Stone));
+        assert_eq!(
+            cache.bytes(),
+            save_bytes(&chunks.save_chunk(IVec3::Z).unwrap())
+        );
+    }
+}
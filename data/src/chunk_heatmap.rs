@@ -0,0 +1,181 @@
+use std::{collections::HashMap, mem, time::Duration};
+
+use bevy_ecs::system::Resource;
+use glam::{IVec2, IVec3};
+
+use crate::mesh::Mesh;
+
+/// Rough size of a BLAS per triangle before compaction. Drivers differ, but not by enough to
+/// move the hotspots.
+const BLAS_BYTES_PER_TRIANGLE: u64 = 64;
+
+/// What the chunk heatmap colors chunks by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeatMetric {
+    GenerationTime,
+    Triangles,
+    GpuMemory,
+}
+
+impl HeatMetric {
+    pub const ALL: [Self; 3] = [Self::GenerationTime, Self::Triangles, Self::GpuMemory];
+
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::GenerationTime => "generation time",
+            Self::Triangles => "triangles",
+            Self::GpuMemory => "gpu memory",
+        }
+    }
+
+    /// `value` of this metric in its unit
+    pub fn format(&self, value: f64) -> String {
+        match self {
+            Self::GenerationTime => format!("{value:.1} ms"),
+            Self::Triangles => format!("{value:.0}"),
+            Self::GpuMemory => format!("{:.1} KiB", value / 1024.0),
+        }
+    }
+}
+
+/// What a loaded chunk cost to make
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ChunkCost {
+    /// `None` when the chunk was loaded rather than generated
+    pub generation: Option<Duration>,
+    pub triangles: u32,
+    /// The mesh's vertex and index buffers plus an estimate of its BLAS. Identical meshes
+    /// share a BLAS in the renderer, so this is the most the chunk could cost.
+    pub gpu_bytes: u64,
+}
+
+impl ChunkCost {
+    /// Generation time in milliseconds, triangles, or bytes
+    pub fn value(&self, metric: HeatMetric) -> Option<f64> {
+        match metric {
+            HeatMetric::GenerationTime => self
+                .generation
+                .map(|duration| duration.as_secs_f64() * 1000.0),
+            HeatMetric::Triangles => Some(self.triangles as f64),
+            HeatMetric::GpuMemory => Some(self.gpu_bytes as f64),
+        }
+    }
+}
+
+/// Costs of the loaded chunks, for the debug heatmap that shows where a large world's
+/// generation time, triangles and GPU memory go
+#[derive(Resource, Debug, Default)]
+pub struct ChunkHeatmap {
+    /// `None` while the heatmap is hidden
+    pub metric: Option<HeatMetric>,
+    costs: HashMap<IVec3, ChunkCost>,
+}
+
+impl ChunkHeatmap {
+    /// Shows the next metric, or hides the heatmap after the last one
+    pub fn cycle(&mut self) {
+        self.metric = match self.metric {
+            None => Some(HeatMetric::ALL[0]),
+            Some(metric) => HeatMetric::ALL
+                .iter()
+                .position(|m| *m == metric)
+                .and_then(|index| HeatMetric::ALL.get(index + 1))
+                .copied(),
+        };
+    }
+
+    pub fn record_generation(&mut self, chunk: IVec3, duration: Duration) {
+        self.costs.entry(chunk).or_default().generation = Some(duration);
+    }
+
+    pub fn record_mesh(&mut self, chunk: IVec3, mesh: &Mesh) {
+        let cost = self.costs.entry(chunk).or_default();
+        cost.triangles = (mesh.indices.len() / 3) as u32;
+        cost.gpu_bytes = mem::size_of_val(mesh.positions.as_slice()) as u64
+            + mem::size_of_val(mesh.indices.as_slice()) as u64
+            + cost.triangles as u64 * BLAS_BYTES_PER_TRIANGLE;
+    }
+
+    pub fn remove(&mut self, chunk: IVec3) {
+        self.costs.remove(&chunk);
+    }
+
+    pub fn cost(&self, chunk: IVec3) -> Option<&ChunkCost> {
+        self.costs.get(&chunk)
+    }
+
+    /// `metric` of the costliest chunk in each column of chunks, by chunk x and z, since a
+    /// hotspot anywhere in a column should show from above
+    pub fn columns(&self, metric: HeatMetric) -> HashMap<IVec2, f64> {
+        let mut columns: HashMap<IVec2, f64> = HashMap::new();
+        for (chunk, cost) in &self.costs {
+            let Some(value) = cost.value(metric) else {
+                continue;
+            };
+            let column = columns.entry(IVec2::new(chunk.x, chunk.z)).or_default();
+            *column = column.max(value);
+        }
+        columns
+    }
+}
+
+/// Blue through green and yellow to red as `heat` goes from 0 to 1
+pub fn heat_color(heat: f32) -> [u8; 4] {
+    const STOPS: [[f32; 3]; 4] = [
+        [0.15, 0.25, 0.9],
+        [0.2, 0.8, 0.3],
+        [0.95, 0.85, 0.2],
+        [0.9, 0.15, 0.1],
+    ];
+    let at = heat.clamp(0.0, 1.0) * (STOPS.len() - 1) as f32;
+    let index = (at as usize).min(STOPS.len() - 2);
+    let t = at - index as f32;
+    let [r, g, b] = [0, 1, 2].map(|c| {
+        let value = STOPS[index][c] + (STOPS[index + 1][c] - STOPS[index][c]) * t;
+        (value * 255.0).round() as u8
+    });
+    [r, g, b, 255]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn columns_show_their_costliest_chunk() {
+        let mut heatmap = ChunkHeatmap::default();
+        heatmap.record_generation(IVec3::new(0, 0, 0), Duration::from_millis(2));
+        heatmap.record_generation(IVec3::new(0, -1, 0), Duration::from_millis(7));
+        heatmap.record_generation(IVec3::new(1, 0, 0), Duration::from_millis(3));
+        let quad = Mesh::new(vec![[0.0; 3]; 4], vec![0, 1, 2, 2, 3, 0]);
+        heatmap.record_mesh(IVec3::new(1, 0, 0), &quad);
+        // Loaded from the cache, so only its mesh is known
+        heatmap.record_mesh(IVec3::new(2, 0, 0), &quad);
+
+        let times = heatmap.columns(HeatMetric::GenerationTime);
+        assert_eq!(times.len(), 2);
+        assert_eq!(times[&IVec2::new(0, 0)], 7.0);
+        assert_eq!(times[&IVec2::new(1, 0)], 3.0);
+        let triangles = heatmap.columns(HeatMetric::Triangles);
+        assert_eq!(triangles[&IVec2::new(2, 0)], 2.0);
+        assert_eq!(
+            heatmap.cost(IVec3::new(1, 0, 0)).unwrap().gpu_bytes,
+            4 * 12 + 6 * 2 + 2 * BLAS_BYTES_PER_TRIANGLE
+        );
+
+        heatmap.remove(IVec3::new(0, -1, 0));
+        assert_eq!(
+            heatmap.columns(HeatMetric::GenerationTime)[&IVec2::new(0, 0)],
+            2.0
+        );
+
+        assert_eq!(heat_color(0.0), [38, 64, 230, 255]);
+        assert_eq!(heat_color(1.0), [230, 38, 26, 255]);
+        for _ in HeatMetric::ALL {
+            heatmap.cycle();
+        }
+        assert_eq!(heatmap.metric, Some(HeatMetric::GpuMemory));
+        heatmap.cycle();
+        assert_eq!(heatmap.metric, None);
+    }
+}
@@ -0,0 +1,475 @@
+use std::{collections::HashMap, mem};
+
+use bevy_ecs::system::Resource;
+use glam::{IVec3, UVec3, Vec3};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    block_entity::{BlockEntities, BlockEntity, BlockEntityData},
+    solid_mask::SolidMask,
+    voxel::Voxel,
+    voxel_block::{encode_rle, Rle, RleError, VoxelBlock, VoxelBlockData},
+    Direction,
+};
+
+/// Loaded chunks by chunk coordinate, along with the [`BlockEntity`]s of their voxels and
+/// the [`SolidMask`] collision and raycasts use
+#[derive(Resource, Debug, Default)]
+pub struct ChunkMap {
+    chunks: HashMap<IVec3, VoxelBlockData>,
+    /// Kept in step with `chunks`, which is why voxels are only changed through the map
+    solids: HashMap<IVec3, SolidMask>,
+    /// Only chunks that have any
+    block_entities: HashMap<IVec3, BlockEntities>,
+}
+
+/// Serialized form of a chunk: its voxels run-length encoded and its block entities
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChunkSave {
+    pub voxels: Vec<Rle>,
+    #[serde(default)]
+    pub block_entities: Vec<(IVec3, BlockEntity)>,
+}
+
+impl ChunkMap {
+    pub fn get(&self, chunk: IVec3) -> Option<&VoxelBlockData> {
+        self.chunks.get(&chunk)
+    }
+
+    /// Which of the chunk's voxels are opaque
+    pub fn solids(&self, chunk: IVec3) -> Option<&SolidMask> {
+        self.solids.get(&chunk)
+    }
+
+    pub fn contains(&self, chunk: IVec3) -> bool {
+        self.chunks.contains_key(&chunk)
+    }
+
+    pub fn len(&self) -> usize {
+        self.chunks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    pub fn chunks(&self) -> impl Iterator<Item = (IVec3, &VoxelBlockData)> {
+        self.chunks.iter().map(|(chunk, data)| (*chunk, data))
+    }
+
+    /// Adds or replaces a chunk, dropping the replaced one's block entities. Returns the
+    /// loaded neighbors, whose border faces may have changed and need remeshing.
+    pub fn insert(&mut self, chunk: IVec3, data: VoxelBlockData) -> Vec<IVec3> {
+        self.solids.insert(chunk, SolidMask::new(&data));
+        self.chunks.insert(chunk, data);
+        self.block_entities.remove(&chunk);
+        self.loaded_neighbors(chunk)
+    }
+
+    /// Unloads a chunk and its block entities. Returns its data, if it was loaded, and the
+    /// neighbors that now border an unloaded chunk.
+    pub fn remove(&mut self, chunk: IVec3) -> (Option<VoxelBlockData>, Vec<IVec3>) {
+        self.block_entities.remove(&chunk);
+        self.solids.remove(&chunk);
+        (self.chunks.remove(&chunk), self.loaded_neighbors(chunk))
+    }
+
+    /// The chunk's voxels and block entities, or `None` if it isn't loaded
+    pub fn save_chunk(&self, chunk: IVec3) -> Option<ChunkSave> {
+        Some(ChunkSave {
+            voxels: encode_rle(self.get(chunk)?.iter().copied()),
+            block_entities: self
+                .block_entities
+                .get(&chunk)
+                .map(BlockEntities::to_sorted)
+                .unwrap_or_default(),
+        })
+    }
+
+    /// Inserts a chunk saved with [`Self::save_chunk`], skipping block entities outside it.
+    /// Returns the loaded neighbors like [`Self::insert`].
+    pub fn load_chunk(&mut self, chunk: IVec3, save: ChunkSave) -> Result<Vec<IVec3>, RleError> {
+        let block = VoxelBlock::from_rle(save.voxels, UVec3::ZERO)?;
+        let neighbors = self.insert(chunk, block.into_data());
+        let entities: BlockEntities = save
+            .block_entities
+            .into_iter()
+            .filter(|(position, _)| chunk_of(*position) == chunk)
+            .collect();
+        if !entities.is_empty() {
+            self.block_entities.insert(chunk, entities);
+        }
+        Ok(neighbors)
+    }
+
+    /// The block entity at a world position if it holds a `T`
+    pub fn block_entity<T: BlockEntityData>(&self, position: IVec3) -> Option<&T> {
+        let entity = self
+            .block_entities
+            .get(&chunk_of(position))?
+            .get(position)?;
+        T::from_entity(entity)
+    }
+
+    pub fn block_entity_mut<T: BlockEntityData>(&mut self, position: IVec3) -> Option<&mut T> {
+        let entity = self
+            .block_entities
+            .get_mut(&chunk_of(position))?
+            .get_mut(position)?;
+        T::from_entity_mut(entity)
+    }
+
+    /// Attaches data to the voxel at a world position, replacing any it had. Returns `false`
+    /// without attaching anything if its chunk isn't loaded.
+    pub fn insert_block_entity(&mut self, position: IVec3, data: impl Into<BlockEntity>) -> bool {
+        let chunk = chunk_of(position);
+        if !self.contains(chunk) {
+            return false;
+        }
+        self.block_entities
+            .entry(chunk)
+            .or_default()
+            .insert(position, data.into());
+        true
+    }
+
+    pub fn remove_block_entity(&mut self, position: IVec3) -> Option<BlockEntity> {
+        let chunk = chunk_of(position);
+        let entities = self.block_entities.get_mut(&chunk)?;
+        let removed = entities.remove(position);
+        if entities.is_empty() {
+            self.block_entities.remove(&chunk);
+        }
+        removed
+    }
+
+    fn loaded_neighbors(&self, chunk: IVec3) -> Vec<IVec3> {
+        Direction::ALL
+            .iter()
+            .map(|direction| chunk + direction.offset())
+            .filter(|neighbor| self.contains(*neighbor))
+            .collect()
+    }
+
+    /// Voxel at a world position, or `None` if its chunk isn't loaded
+    pub fn voxel(&self, position: IVec3) -> Option<Voxel> {
+        let data = self.get(chunk_of(position))?;
+        Some(data[local_index(position)])
+    }
+
+    /// Whether the voxel at a world position is opaque, or `None` if its chunk isn't loaded
+    pub fn is_solid(&self, position: IVec3) -> Option<bool> {
+        Some(self.solids(chunk_of(position))?.get(local_index(position)))
+    }
+
+    /// Replaces the voxel at a world position, returning the old one, or `None` without
+    /// changing anything if its chunk isn't loaded. A different voxel drops the old one's
+    /// block entity. The chunks in [`chunks_sharing`] need remeshing after.
+    pub fn set_voxel(&mut self, position: IVec3, voxel: Voxel) -> Option<Voxel> {
+        let chunk = chunk_of(position);
+        let index = local_index(position);
+        let old = mem::replace(&mut self.chunks.get_mut(&chunk)?[index], voxel);
+        if old != voxel {
+            if let Some(solids) = self.solids.get_mut(&chunk) {
+                solids.set(index, voxel.is_opaque());
+            }
+            self.remove_block_entity(position);
+        }
+        Some(old)
+    }
+
+    /// Loads every missing chunk within `radius` chunks of `center` (a cube, not a sphere)
+    /// right away, e.g. the destination of a teleport. Returns the chunks that were loaded.
+    pub fn load_around(
+        &mut self,
+        center: IVec3,
+        radius: i32,
+        mut generate: impl FnMut(IVec3) -> VoxelBlockData,
+    ) -> Vec<IVec3> {
+        let mut loaded = Vec::new();
+        for y in -radius..=radius {
+            for z in -radius..=radius {
+                for x in -radius..=radius {
+                    let chunk = center + IVec3::new(x, y, z);
+                    if !self.contains(chunk) {
+                        let data = generate(chunk);
+                        self.solids.insert(chunk, SolidMask::new(&data));
+                        self.chunks.insert(chunk, data);
+                        loaded.push(chunk);
+                    }
+                }
+            }
+        }
+        loaded
+    }
+
+    /// First position at or above `position` (up is -Y) where a column `height` voxels tall,
+    /// extending downwards from it, is free of opaque voxels. Gives up after rising
+    /// `max_rise` voxels or reaching an unloaded chunk.
+    pub fn free_space_above(&self, position: IVec3, height: u32, max_rise: u32) -> Option<IVec3> {
+        for rise in 0..=max_rise as i32 {
+            let top = position - IVec3::Y * rise;
+            let mut free = true;
+            for below in 0..height as i32 {
+                free &= !self.is_solid(top + IVec3::Y * below)?;
+            }
+            if free {
+                return Some(top);
+            }
+        }
+        None
+    }
+
+    /// Distance along `direction` (normalized) from `start` to the first opaque voxel, stepping
+    /// voxel by voxel, or `None` if there is none within `max_distance`. `start` is relative to
+    /// the world voxel `offset`, like translations are to the floating origin, so rays stay
+    /// precise far out. Unloaded chunks don't block the ray.
+    pub fn raycast(
+        &self,
+        offset: IVec3,
+        start: Vec3,
+        direction: Vec3,
+        max_distance: f32,
+    ) -> Option<f32> {
+        let mut voxel = start.floor().as_ivec3();
+        let step = direction.signum().as_ivec3();
+        // Distance along the ray between voxel borders, and to the next border, per axis
+        let delta = direction.recip().abs();
+        let next_border = (voxel.as_vec3() + step.max(IVec3::ZERO).as_vec3() - start) / direction;
+        let mut next = Vec3::select(direction.cmpeq(Vec3::ZERO), Vec3::INFINITY, next_border);
+
+        // The chunk last stepped through, so that most steps skip the map lookup
+        let mut chunk: Option<(IVec3, Option<&SolidMask>)> = None;
+        let mut distance = 0.0;
+        while distance <= max_distance {
+            let position = voxel + offset;
+            let here = chunk_of(position);
+            if chunk.is_none_or(|(last, _)| last != here) {
+                chunk = Some((here, self.solids(here)));
+            }
+            let solids = chunk.and_then(|(_, solids)| solids);
+            if solids.is_some_and(|solids| solids.get(local_index(position))) {
+                return Some(distance);
+            }
+            let axis = if next.x < next.y && next.x < next.z {
+                0
+            } else if next.y < next.z {
+                1
+            } else {
+                2
+            };
+            distance = next[axis];
+            next[axis] += delta[axis];
+            voxel[axis] += step[axis];
+        }
+        None
+    }
+
+    /// The chunk and whichever of its six neighbors are loaded, or `None` if the chunk itself
+    /// isn't loaded
+    pub fn neighborhood(&self, chunk: IVec3) -> Option<ChunkNeighborhood<'_>> {
+        Some(ChunkNeighborhood {
+            center: self.get(chunk)?,
+            neighbors: Direction::ALL.map(|direction| self.get(chunk + direction.offset())),
+        })
+    }
+
+    /// Owned copy of [`Self::neighborhood`] that can be sent to the task pool
+    pub fn snapshot(&self, chunk: IVec3) -> Option<ChunkSnapshot> {
+        self.neighborhood(chunk)
+            .map(|neighborhood| neighborhood.to_snapshot())
+    }
+}
+
+/// A chunk together with its six face neighbors, so voxels just across a border can be read
+/// while meshing. Neighbors are indexed like [`Direction::ALL`]; `None` means not loaded.
+#[derive(Debug, Clone, Copy)]
+pub struct ChunkNeighborhood<'a> {
+    center: &'a VoxelBlockData,
+    neighbors: [Option<&'a VoxelBlockData>; 6],
+}
+
+impl<'a> ChunkNeighborhood<'a> {
+    pub const fn new(
+        center: &'a VoxelBlockData,
+        neighbors: [Option<&'a VoxelBlockData>; 6],
+    ) -> Self {
+        Self { center, neighbors }
+    }
+
+    pub const fn center(&self) -> &'a VoxelBlockData {
+        self.center
+    }
+
+    pub fn neighbor(&self, direction: Direction) -> Option<&'a VoxelBlockData> {
+        self.neighbors[direction as usize]
+    }
+
+    /// Voxel at `position` relative to the center chunk's origin, which may lie in a face
+    /// neighbor. `None` if that neighbor isn't loaded, or for positions diagonally across an
+    /// edge or corner, which face culling never needs.
+    pub fn get(&self, position: IVec3) -> Option<Voxel> {
+        let width = IVec3::splat(VoxelBlock::WIDTH as i32);
+        let chunk_offset = position.div_euclid(width);
+        let local = index(position.rem_euclid(width));
+        if chunk_offset == IVec3::ZERO {
+            return Some(self.center[local]);
+        }
+        let direction = Direction::ALL
+            .into_iter()
+            .find(|direction| direction.offset() == chunk_offset)?;
+        Some(self.neighbor(direction)?[local])
+    }
+
+    /// Copies the borrowed chunks
+    pub fn to_snapshot(&self) -> ChunkSnapshot {
+        ChunkSnapshot {
+            center: self.center.clone(),
+            neighbors: self.neighbors.map(|neighbor| neighbor.cloned()),
+        }
+    }
+}
+
+/// Owned [`ChunkNeighborhood`], for meshing off the main thread while the map keeps changing
+#[derive(Debug, Clone)]
+pub struct ChunkSnapshot {
+    center: VoxelBlockData,
+    neighbors: [Option<VoxelBlockData>; 6],
+}
+
+impl ChunkSnapshot {
+    pub fn neighborhood(&self) -> ChunkNeighborhood<'_> {
+        ChunkNeighborhood {
+            center: &self.center,
+            neighbors: self.neighbors.each_ref().map(Option::as_ref),
+        }
+    }
+}
+
+/// Chunk containing the world voxel position
+pub fn chunk_of(position: IVec3) -> IVec3 {
+    position.div_euclid(IVec3::splat(VoxelBlock::WIDTH as i32))
+}
+
+/// The chunk containing a world voxel position, plus the neighbors across any chunk face the
+/// voxel lies on, whose meshes also depend on it
+pub fn chunks_sharing(position: IVec3) -> Vec<IVec3> {
+    let chunk = chunk_of(position);
+    let mut chunks = vec![chunk];
+    for direction in Direction::ALL {
+        if chunk_of(position + direction.offset()) != chunk {
+            chunks.push(chunk + direction.offset());
+        }
+    }
+    chunks
+}
+
+/// Same order as [`VoxelBlock`]: x fastest, then z, then y
+fn index(position: IVec3) -> usize {
+    let width = VoxelBlock::WIDTH as usize;
+    position.x as usize + position.z as usize * width + position.y as usize * width * width
+}
+
+/// [`index`] of a world position within its chunk
+fn local_index(position: IVec3) -> usize {
+    index(position.rem_euclid(IVec3::splat(VoxelBlock::WIDTH as i32)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn free_space_is_found_above_solid_ground() {
+        let width = VoxelBlock::WIDTH as i32;
+        let mut chunks = ChunkMap::default();
+        // Stone below y = 4 (deeper is +Y), air above
+        chunks.load_around(IVec3::ZERO, 0, |_| {
+            let mut data = Box::new([Voxel::Air; VoxelBlock::VOLUME as usize]);
+            for (i, voxel) in data.iter_mut().enumerate() {
+                if i as i32 / (width * width) >= 4 {
+                    *voxel = Voxel::Stone;
+                }
+            }
+            data
+        });
+
+        let buried = IVec3::new(3, 10, 3);
+        assert_eq!(
+            chunks.free_space_above(buried, 2, 16),
+            Some(IVec3::new(3, 2, 3))
+        );
+        assert_eq!(
+            chunks.free_space_above(IVec3::new(3, 1, 3), 2, 0),
+            Some(IVec3::new(3, 1, 3))
+        );
+        // Not enough room before leaving the loaded chunk
+        assert_eq!(chunks.free_space_above(buried, 2, 4), None);
+        assert_eq!(chunks.free_space_above(IVec3::new(3, 10, 20), 2, 16), None);
+    }
+
+    #[test]
+    fn block_entities_follow_their_voxels() {
+        use crate::block_entity::{Orientation, SignText};
+
+        let mut chunks = ChunkMap::default();
+        chunks.load_around(IVec3::ZERO, 0, |_| {
+            Box::new([Voxel::Stone; VoxelBlock::VOLUME as usize])
+        });
+        let (sign, door) = (IVec3::new(1, 2, 3), IVec3::new(4, 5, 6));
+        assert!(chunks.insert_block_entity(sign, SignText("Spawn".to_string())));
+        assert!(chunks.insert_block_entity(door, Orientation(Direction::Left)));
+        assert!(!chunks.insert_block_entity(IVec3::splat(-1), Orientation(Direction::Up)));
+
+        assert_eq!(
+            chunks.block_entity::<SignText>(sign),
+            Some(&SignText("Spawn".to_string()))
+        );
+        assert_eq!(chunks.block_entity::<Orientation>(sign), None);
+        chunks.block_entity_mut::<Orientation>(door).unwrap().0 = Direction::Right;
+
+        // Saved with the chunk and back after a reload
+        let save = chunks.save_chunk(IVec3::ZERO).unwrap();
+        assert_eq!(save.block_entities.len(), 2);
+        chunks.remove(IVec3::ZERO);
+        assert_eq!(chunks.block_entity::<SignText>(sign), None);
+        chunks.load_chunk(IVec3::ZERO, save).unwrap();
+        assert_eq!(
+            chunks.block_entity::<Orientation>(door),
+            Some(&Orientation(Direction::Right))
+        );
+
+        // Only replacing the voxel with a different one drops its data
+        chunks.set_voxel(door, Voxel::Stone);
+        assert!(chunks.block_entity::<Orientation>(door).is_some());
+        chunks.set_voxel(door, Voxel::Air);
+        assert_eq!(chunks.block_entity::<Orientation>(door), None);
+    }
+
+    #[test]
+    fn solid_masks_follow_edits() {
+        let mut chunks = ChunkMap::default();
+        chunks.load_around(IVec3::ZERO, 0, |_| {
+            Box::new([Voxel::Air; VoxelBlock::VOLUME as usize])
+        });
+        assert!(chunks.solids(IVec3::ZERO).unwrap().is_empty());
+        assert_eq!(
+            chunks.raycast(IVec3::ZERO, Vec3::splat(0.5), Vec3::X, 8.0),
+            None
+        );
+
+        let wall = IVec3::new(5, 0, 0);
+        chunks.set_voxel(wall, Voxel::Stone);
+        assert_eq!(chunks.is_solid(wall), Some(true));
+        assert_eq!(chunks.is_solid(IVec3::new(-1, 0, 0)), None);
+        assert_eq!(
+            chunks.raycast(IVec3::ZERO, Vec3::splat(0.5), Vec3::X, 8.0),
+            Some(4.5)
+        );
+
+        // Water doesn't block
+        chunks.set_voxel(wall, Voxel::Water);
+        assert_eq!(chunks.is_solid(wall), Some(false));
+        assert!(chunks.solids(IVec3::ZERO).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,24 @@
+use bevy_ecs::component::Component;
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+
+use crate::{math::Aabb, transform::Transform};
+
+/// Box an entity takes up around its [`Transform`], unrotated
+#[derive(Component, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[require(Transform)]
+pub struct Collider {
+    pub half_extents: Vec3,
+}
+
+impl Collider {
+    pub const fn new(half_extents: Vec3) -> Self {
+        Self { half_extents }
+    }
+
+    /// The box around `translation`, scaled by `scale`
+    pub fn aabb(&self, translation: Vec3, scale: Vec3) -> Aabb {
+        let half_extents = self.half_extents * scale.abs();
+        Aabb::new(translation - half_extents, translation + half_extents)
+    }
+}
@@ -0,0 +1,167 @@
+use std::{collections::VecDeque, mem};
+
+use bevy_ecs::system::Resource;
+use glam::IVec3;
+
+use crate::{chunk_map::ChunkMap, voxel::Voxel};
+
+/// One voxel changed by an edit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VoxelEdit {
+    pub position: IVec3,
+    pub old: Voxel,
+    pub new: Voxel,
+}
+
+/// Undo and redo stacks of voxel edits, each entry a transaction that is undone as a whole,
+/// e.g. one fill. The oldest transactions are forgotten once the stacks hold more than
+/// `memory_cap` bytes of edits.
+#[derive(Resource, Debug, Clone)]
+pub struct EditHistory {
+    undo: VecDeque<Vec<VoxelEdit>>,
+    redo: Vec<Vec<VoxelEdit>>,
+    pub memory_cap: usize,
+}
+
+impl Default for EditHistory {
+    fn default() -> Self {
+        Self::new(16 << 20)
+    }
+}
+
+impl EditHistory {
+    pub fn new(memory_cap: usize) -> Self {
+        Self {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            memory_cap,
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Bytes of edits held by both stacks
+    pub fn memory(&self) -> usize {
+        let edits: usize = self.undo.iter().chain(&self.redo).map(Vec::len).sum();
+        edits * mem::size_of::<VoxelEdit>()
+    }
+
+    /// Records a transaction of edits that were just applied, which can't be redone past.
+    /// Empty transactions are ignored. One larger than the cap is still kept, on its own.
+    pub fn record(&mut self, edits: Vec<VoxelEdit>) {
+        if edits.is_empty() {
+            return;
+        }
+        self.redo.clear();
+        self.undo.push_back(edits);
+        while self.undo.len() > 1 && self.memory() > self.memory_cap {
+            self.undo.pop_front();
+        }
+    }
+
+    /// Reverts the latest transaction and returns its edits as undone, for remeshing. Voxels
+    /// in chunks that have since unloaded stay as they are.
+    pub fn undo(&mut self, chunks: &mut ChunkMap) -> Vec<VoxelEdit> {
+        let Some(edits) = self.undo.pop_back() else {
+            return Vec::new();
+        };
+        let undone = edits
+            .iter()
+            .rev()
+            .filter(|edit| chunks.set_voxel(edit.position, edit.old).is_some())
+            .map(|edit| VoxelEdit {
+                position: edit.position,
+                old: edit.new,
+                new: edit.old,
+            })
+            .collect();
+        self.redo.push(edits);
+        undone
+    }
+
+    /// Reapplies the latest undone transaction and returns its edits
+    pub fn redo(&mut self, chunks: &mut ChunkMap) -> Vec<VoxelEdit> {
+        let Some(edits) = self.redo.pop() else {
+            return Vec::new();
+        };
+        let redone = edits
+            .iter()
+            .copied()
+            .filter(|edit| chunks.set_voxel(edit.position, edit.new).is_some())
+            .collect();
+        self.undo.push_back(edits);
+        redone
+    }
+
+    /// Takes back a transaction that was rolled back, e.g. because a server turned it down.
+    /// A rolled back edit is forgotten; a rolled back undo is undoable again.
+    pub fn roll_back(&mut self, edits: &[VoxelEdit]) {
+        if let Some(index) = self.undo.iter().rposition(|undo| undo == edits) {
+            self.undo.remove(index);
+            return;
+        }
+        let undone_as = |redo: &Vec<VoxelEdit>| {
+            redo.iter()
+                .rev()
+                .map(|edit| VoxelEdit {
+                    position: edit.position,
+                    old: edit.new,
+                    new: edit.old,
+                })
+                .eq(edits.iter().copied())
+        };
+        if let Some(index) = self.redo.iter().rposition(undone_as) {
+            let redo = self.redo.remove(index);
+            self.undo.push_back(redo);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        schematic::{fill, Region},
+        voxel_block::VoxelBlock,
+    };
+
+    use super::*;
+
+    #[test]
+    fn transactions_undo_and_redo_as_a_whole() {
+        let mut chunks = ChunkMap::default();
+        chunks.load_around(IVec3::ZERO, 0, |_| {
+            Box::new([Voxel::Air; VoxelBlock::VOLUME as usize])
+        });
+        let mut history = EditHistory::default();
+        let region = Region::from_corners(IVec3::ZERO, IVec3::new(1, 0, 1));
+        history.record(fill(&mut chunks, region, Voxel::Stone));
+        history.record(fill(&mut chunks, region, Voxel::Dirt));
+
+        assert_eq!(history.undo(&mut chunks).len(), 4);
+        assert_eq!(chunks.voxel(IVec3::ONE.with_y(0)), Some(Voxel::Stone));
+        history.undo(&mut chunks);
+        assert_eq!(chunks.voxel(IVec3::ZERO), Some(Voxel::Air));
+        assert!(!history.can_undo());
+
+        history.redo(&mut chunks);
+        assert_eq!(chunks.voxel(IVec3::ZERO), Some(Voxel::Stone));
+        // A new edit can't be redone past
+        history.record(fill(&mut chunks, region, Voxel::Grass));
+        assert!(!history.can_redo());
+
+        // Over the cap the oldest go, but the newest always stays
+        let mut capped = EditHistory::new(mem::size_of::<VoxelEdit>() * 4);
+        capped.record(fill(&mut chunks, region, Voxel::Water));
+        capped.record(fill(&mut chunks, region, Voxel::Stone));
+        assert_eq!(capped.memory(), mem::size_of::<VoxelEdit>() * 4);
+        capped.undo(&mut chunks);
+        assert!(!capped.can_undo());
+        assert_eq!(chunks.voxel(IVec3::ZERO), Some(Voxel::Water));
+    }
+}
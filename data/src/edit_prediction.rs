@@ -0,0 +1,195 @@
+use std::collections::VecDeque;
+
+use bevy_ecs::system::Resource;
+use glam::IVec3;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    chunk_map::{chunk_of, ChunkMap},
+    edit_history::VoxelEdit,
+};
+
+/// Names a transaction of edits a client sent, so the server can accept or reject it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct EditId(pub u32);
+
+/// Why the server turned an edit down
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EditRejection {
+    /// Inside a protected region, and the client isn't an operator
+    Protected,
+    /// A voxel had already changed, e.g. another player got there first
+    Outdated,
+    /// A voxel's chunk isn't loaded on the server
+    Unloaded,
+}
+
+/// Edits applied on a client before the server confirmed them, oldest first. While they're
+/// pending, the voxels show the newest prediction for them, and each edit's `old` is the voxel
+/// to go back to if it's rejected: the server's, or the one an earlier prediction left.
+#[derive(Resource, Debug, Default)]
+pub struct PredictedEdits {
+    next_id: u32,
+    pending: VecDeque<(EditId, Vec<VoxelEdit>)>,
+    /// Predicted, but not handed to the transport yet
+    unsent: Vec<(EditId, Vec<VoxelEdit>)>,
+}
+
+impl PredictedEdits {
+    /// Tracks edits already applied to the chunks until the server answers
+    pub fn predict(&mut self, edits: Vec<VoxelEdit>) -> Option<EditId> {
+        if edits.is_empty() {
+            return None;
+        }
+        let id = EditId(self.next_id);
+        self.next_id = self.next_id.wrapping_add(1);
+        self.pending.push_back((id, edits.clone()));
+        self.unsent.push((id, edits));
+        Some(id)
+    }
+
+    /// The predictions to send to the server, oldest first
+    pub fn take_unsent(&mut self) -> Vec<(EditId, Vec<VoxelEdit>)> {
+        std::mem::take(&mut self.unsent)
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Whether a pending prediction changed the voxel at a world position
+    pub fn is_predicted(&self, position: IVec3) -> bool {
+        self.pending_edits().any(|edit| edit.position == position)
+    }
+
+    fn pending_edits(&self) -> impl Iterator<Item = &VoxelEdit> {
+        self.pending.iter().flat_map(|(_, edits)| edits)
+    }
+
+    /// The server applied the edits, so they're the server's voxels now. Returns them, or
+    /// `None` for an unknown id.
+    pub fn acknowledge(&mut self, id: EditId) -> Option<Vec<VoxelEdit>> {
+        let index = self
+            .pending
+            .iter()
+            .position(|(pending, _)| *pending == id)?;
+        self.pending.remove(index).map(|(_, edits)| edits)
+    }
+
+    /// The server turned the edits down, so they're undone and returned as undone, for
+    /// remeshing, along with the edits as they were predicted. A voxel a later prediction
+    /// changed again keeps that prediction, which goes back to this edit's `old` in turn if
+    /// it's rejected too.
+    pub fn reject(
+        &mut self,
+        id: EditId,
+        chunks: &mut ChunkMap,
+    ) -> Option<(Vec<VoxelEdit>, Vec<VoxelEdit>)> {
+        let index = self
+            .pending
+            .iter()
+            .position(|(pending, _)| *pending == id)?;
+        let (_, rejected) = self.pending.remove(index)?;
+        let mut undone = Vec::new();
+        for edit in rejected.iter().rev() {
+            let later = self
+                .pending
+                .iter_mut()
+                .skip(index)
+                .flat_map(|(_, edits)| edits)
+                .find(|later| later.position == edit.position);
+            match later {
+                Some(later) => later.old = edit.old,
+                None => {
+                    if chunks.set_voxel(edit.position, edit.old).is_some() {
+                        undone.push(VoxelEdit {
+                            position: edit.position,
+                            old: edit.new,
+                            new: edit.old,
+                        });
+                    }
+                }
+            }
+        }
+        self.unsent.retain(|(unsent, _)| *unsent != id);
+        Some((undone, rejected))
+    }
+
+    /// The server sent a chunk that replaced the predicted one, without the edits it hasn't
+    /// answered yet. Its voxels become what those edits go back to if they're rejected, and
+    /// the edits are applied over it again. Returns the positions that were put back.
+    pub fn reapply(&mut self, chunk: IVec3, chunks: &mut ChunkMap) -> Vec<IVec3> {
+        let mut reapplied: Vec<IVec3> = Vec::new();
+        for edit in self
+            .pending
+            .iter_mut()
+            .flat_map(|(_, edits)| edits)
+            .filter(|edit| chunk_of(edit.position) == chunk)
+        {
+            // Only the first prediction for a voxel goes back to the server's
+            if !reapplied.contains(&edit.position) {
+                if let Some(server) = chunks.voxel(edit.position) {
+                    edit.old = server;
+                }
+                reapplied.push(edit.position);
+            }
+            chunks.set_voxel(edit.position, edit.new);
+        }
+        reapplied
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{voxel::Voxel, voxel_block::VoxelBlock};
+
+    use super::*;
+
+    fn edit(chunks: &mut ChunkMap, position: IVec3, new: Voxel) -> Vec<VoxelEdit> {
+        let old = chunks.set_voxel(position, new).unwrap();
+        vec![VoxelEdit { position, old, new }]
+    }
+
+    #[test]
+    fn rejected_edits_roll_back_under_later_predictions() {
+        let mut chunks = ChunkMap::default();
+        chunks.load_around(IVec3::ZERO, 0, |_| {
+            Box::new([Voxel::Air; VoxelBlock::VOLUME as usize])
+        });
+        let mut predicted = PredictedEdits::default();
+        let (a, b) = (IVec3::ZERO, IVec3::X);
+
+        let stone = predicted
+            .predict(edit(&mut chunks, a, Voxel::Stone))
+            .unwrap();
+        let dirt = predicted
+            .predict(edit(&mut chunks, a, Voxel::Dirt))
+            .unwrap();
+        let grass = predicted
+            .predict(edit(&mut chunks, b, Voxel::Grass))
+            .unwrap();
+        assert_eq!(predicted.take_unsent().len(), 3);
+
+        // The later dirt still shows, but now goes back to air
+        let (undone, _) = predicted.reject(stone, &mut chunks).unwrap();
+        assert!(undone.is_empty());
+        assert_eq!(chunks.voxel(a), Some(Voxel::Dirt));
+        predicted.reject(dirt, &mut chunks).unwrap();
+        assert_eq!(chunks.voxel(a), Some(Voxel::Air));
+
+        // A resent chunk from before the server saw the grass gets it again, over water
+        chunks.set_voxel(b, Voxel::Water);
+        assert_eq!(predicted.reapply(IVec3::ZERO, &mut chunks), [b]);
+        assert_eq!(chunks.voxel(b), Some(Voxel::Grass));
+        let (undone, rejected) = predicted.reject(grass, &mut chunks).unwrap();
+        assert_eq!(undone[0].new, Voxel::Water);
+        assert_eq!(rejected[0].new, Voxel::Grass);
+        assert_eq!(chunks.voxel(b), Some(Voxel::Water));
+        assert!(predicted.is_empty());
+        assert!(predicted.acknowledge(grass).is_none());
+    }
+}
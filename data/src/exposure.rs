@@ -0,0 +1,169 @@
+use bevy_ecs::component::Component;
+
+/// How bright the camera renders the scene, in stops: every +1 doubles the light. The traced
+/// and marched paths scale their output by [`Self::multiplier`].
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Exposure {
+    pub ev: f32,
+    /// Adapts `ev` to the [`LuminanceHistogram`] of the rendered frames when set
+    pub auto: Option<AutoExposure>,
+}
+
+impl Default for Exposure {
+    fn default() -> Self {
+        Self {
+            ev: 0.0,
+            auto: None,
+        }
+    }
+}
+
+impl Exposure {
+    pub fn auto() -> Self {
+        Self {
+            auto: Some(AutoExposure::default()),
+            ..Self::default()
+        }
+    }
+
+    pub fn multiplier(&self) -> f32 {
+        self.ev.exp2()
+    }
+
+    /// Moves `ev` towards what the histogram asks for; does nothing without auto exposure
+    pub fn adapt(&mut self, histogram: &LuminanceHistogram, delta_secs: f32) {
+        if let Some(auto) = self.auto {
+            self.ev = auto.adapt(self.ev, histogram, delta_secs);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AutoExposure {
+    pub min_ev: f32,
+    pub max_ev: f32,
+    /// Added to the metered exposure, to bias the result brighter or darker
+    pub compensation: f32,
+    /// Most stops the exposure changes per second, so walking out of a cave fades instead of
+    /// flashing
+    pub speed: f32,
+    /// Fractions of the darkest and brightest pixels left out of metering, so a bright sky or
+    /// pitch black corner doesn't swing the exposure
+    pub ignore_dark: f32,
+    pub ignore_bright: f32,
+}
+
+impl Default for AutoExposure {
+    fn default() -> Self {
+        Self {
+            min_ev: -4.0,
+            max_ev: 4.0,
+            compensation: 0.0,
+            speed: 2.0,
+            ignore_dark: 0.1,
+            ignore_bright: 0.05,
+        }
+    }
+}
+
+impl AutoExposure {
+    /// Average displayed luminance the exposure aims for
+    pub const TARGET_LUMINANCE: f32 = 0.3;
+
+    /// `current_ev` moved towards the exposure that brings the metered average to
+    /// [`Self::TARGET_LUMINANCE`]. The histogram is of the output, which was already exposed
+    /// at `current_ev`, so the correction is relative to it.
+    pub fn adapt(&self, current_ev: f32, histogram: &LuminanceHistogram, delta_secs: f32) -> f32 {
+        let Some(average) = histogram.average_log2(self.ignore_dark, self.ignore_bright) else {
+            return current_ev;
+        };
+        let target = (current_ev + Self::TARGET_LUMINANCE.log2() - average + self.compensation)
+            .clamp(self.min_ev, self.max_ev);
+        let max_step = self.speed * delta_secs;
+        current_ev + (target - current_ev).clamp(-max_step, max_step)
+    }
+}
+
+/// Pixel counts of the output image by log2 luminance, read back from the GPU
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LuminanceHistogram {
+    pub bins: [u32; Self::BINS],
+}
+
+impl Default for LuminanceHistogram {
+    fn default() -> Self {
+        Self {
+            bins: [0; Self::BINS],
+        }
+    }
+}
+
+impl LuminanceHistogram {
+    /// Must match histogram.comp
+    pub const BINS: usize = 64;
+    /// log2 luminance of the first and last bin. Darker pixels, black included, land in the
+    /// first; the output is 8-bit, so nothing is brighter than the last.
+    pub const MIN_LOG2: f32 = -8.0;
+    pub const MAX_LOG2: f32 = 0.0;
+
+    pub fn total(&self) -> u64 {
+        self.bins.iter().map(|&count| count as u64).sum()
+    }
+
+    /// log2 luminance at the center of a bin
+    pub fn bin_log2(bin: usize) -> f32 {
+        let t = (bin as f32 + 0.5) / Self::BINS as f32;
+        Self::MIN_LOG2 + t * (Self::MAX_LOG2 - Self::MIN_LOG2)
+    }
+
+    /// Mean log2 luminance of the pixels between the `ignore_dark` and `ignore_bright`
+    /// fractions; `None` if the histogram is empty
+    pub fn average_log2(&self, ignore_dark: f32, ignore_bright: f32) -> Option<f32> {
+        let total = self.total() as f32;
+        let start = total * ignore_dark.clamp(0.0, 1.0);
+        let end = total * (1.0 - ignore_bright.clamp(0.0, 1.0));
+        let mut below = 0.0;
+        let (mut sum, mut counted) = (0.0, 0.0);
+        for (bin, &count) in self.bins.iter().enumerate() {
+            let count = count as f32;
+            // The part of this bin's pixels inside [start, end)
+            let used = (below + count).min(end) - below.max(start);
+            below += count;
+            if used > 0.0 {
+                sum += used * Self::bin_log2(bin);
+                counted += used;
+            }
+        }
+        (counted > 0.0).then(|| sum / counted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn auto_exposure_brightens_dark_frames_gradually() {
+        let mut histogram = LuminanceHistogram::default();
+        // Everything very dark, apart from a few bright pixels that get ignored
+        histogram.bins[4] = 95;
+        histogram.bins[63] = 5;
+        let average = histogram.average_log2(0.0, 0.05).unwrap();
+        assert_eq!(average, LuminanceHistogram::bin_log2(4));
+
+        let auto = AutoExposure::default();
+        let mut exposure = Exposure::auto();
+        exposure.adapt(&histogram, 0.5);
+        assert_eq!(exposure.ev, auto.speed * 0.5);
+        for _ in 0..100 {
+            exposure.adapt(&histogram, 0.5);
+        }
+        assert_eq!(exposure.ev, auto.max_ev);
+
+        // Nothing rendered yet, or manual exposure
+        assert_eq!(auto.adapt(1.0, &LuminanceHistogram::default(), 0.5), 1.0);
+        let mut manual = Exposure::default();
+        manual.adapt(&histogram, 0.5);
+        assert_eq!(manual.ev, 0.0);
+    }
+}
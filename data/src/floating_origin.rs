@@ -0,0 +1,152 @@
+use bevy_ecs::{component::Component, system::Resource};
+use glam::{DVec3, IVec3, Vec3};
+
+use crate::{chunk_map::chunk_of, voxel_block::VoxelBlock};
+
+/// World voxel position that entity translations are relative to. f32 translations lose
+/// precision far from zero, so once the camera drifts [`Self::rebase_distance`] away the
+/// origin moves under it and every translation is shifted back towards zero. The origin stays
+/// on chunk corners, so shifting is exact and chunk-local meshes keep their alignment.
+///
+/// Chunk coordinates, the world border and teleport destinations stay in world space.
+///
+/// With the `f64-world` feature entities keep their position in a [`WorldPosition`] as well,
+/// so only translations near the camera need to be precise and the origin follows the camera
+/// chunk by chunk, keeping translations camera-relative.
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct FloatingOrigin {
+    origin: IVec3,
+    pub rebase_distance: f32,
+}
+
+impl Default for FloatingOrigin {
+    fn default() -> Self {
+        Self {
+            origin: IVec3::ZERO,
+            rebase_distance: Self::DEFAULT_REBASE_DISTANCE,
+        }
+    }
+}
+
+impl FloatingOrigin {
+    /// With `f64-world`, just over the farthest a camera can be inside the origin's chunk, so
+    /// the origin moves whenever the camera changes chunks
+    const DEFAULT_REBASE_DISTANCE: f32 = if cfg!(feature = "f64-world") {
+        2.0 * VoxelBlock::WIDTH as f32
+    } else {
+        1024.0
+    };
+
+    pub const fn origin(&self) -> IVec3 {
+        self.origin
+    }
+
+    /// World voxel containing a translation, exact however far out the origin is
+    pub fn world_voxel(&self, translation: Vec3) -> IVec3 {
+        translation.floor().as_ivec3() + self.origin
+    }
+
+    /// World position of a translation. Loses precision far out, so prefer
+    /// [`Self::world_voxel`] for anything needing whole voxels.
+    pub fn to_world(&self, translation: Vec3) -> Vec3 {
+        translation + self.origin.as_vec3()
+    }
+
+    /// Translation of a world position
+    pub fn to_translation(&self, world: Vec3) -> Vec3 {
+        world - self.origin.as_vec3()
+    }
+
+    /// World position of a translation, as precise as the translation
+    pub fn to_world_f64(&self, translation: Vec3) -> DVec3 {
+        translation.as_dvec3() + self.origin.as_dvec3()
+    }
+
+    /// Translation of a world position, only losing precision far from the origin
+    pub fn to_translation_f64(&self, world: DVec3) -> Vec3 {
+        (world - self.origin.as_dvec3()).as_vec3()
+    }
+
+    /// Translation of a chunk's minimum corner, where its chunk-local mesh is placed
+    pub fn chunk_offset(&self, chunk: IVec3) -> Vec3 {
+        (chunk * VoxelBlock::WIDTH as i32 - self.origin).as_vec3()
+    }
+
+    /// Moves the origin to the corner of the chunk containing `camera` if the camera is
+    /// further than [`Self::rebase_distance`] from it. Returns the shift, which every
+    /// translation must then have subtracted.
+    pub fn rebase(&mut self, camera: Vec3) -> Option<IVec3> {
+        if camera.length() <= self.rebase_distance {
+            return None;
+        }
+        let shift = chunk_of(camera.floor().as_ivec3()) * VoxelBlock::WIDTH as i32;
+        self.origin += shift;
+        Some(shift)
+    }
+}
+
+/// An entity's world position in f64, with the `f64-world` feature. Translations far from
+/// the origin are imprecise, so when the origin moves they're derived from this again instead
+/// of being shifted, and only moves made to them are taken back into it.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Default)]
+pub struct WorldPosition(pub DVec3);
+
+impl WorldPosition {
+    pub fn new(origin: &FloatingOrigin, translation: Vec3) -> Self {
+        Self(origin.to_world_f64(translation))
+    }
+
+    /// Takes `translation` in if it was moved since being derived from this position
+    pub fn follow(&mut self, origin: &FloatingOrigin, translation: Vec3) {
+        if origin.to_translation_f64(self.0) != translation {
+            self.0 = origin.to_world_f64(translation);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rebasing_keeps_world_positions() {
+        let mut origin = FloatingOrigin {
+            rebase_distance: 100.0,
+            ..Default::default()
+        };
+        assert_eq!(origin.rebase(Vec3::new(50.0, 0.0, -50.0)), None);
+
+        let mut camera = Vec3::new(203.5, -7.25, -40.0);
+        let voxel = origin.world_voxel(camera);
+        let shift = origin.rebase(camera).unwrap();
+        assert_eq!(shift, IVec3::new(192, -16, -48));
+        camera -= shift.as_vec3();
+        assert_eq!(camera, Vec3::new(11.5, 8.75, 8.0));
+        assert_eq!(origin.world_voxel(camera), voxel);
+        assert_eq!(origin.chunk_offset(IVec3::new(12, -1, -3)), Vec3::ZERO);
+        assert_eq!(
+            origin.to_translation(Vec3::new(200.0, 0.0, 0.0)),
+            Vec3::new(8.0, 16.0, 48.0)
+        );
+    }
+
+    #[test]
+    fn world_positions_stay_exact_as_the_origin_moves() {
+        let mut origin = FloatingOrigin {
+            rebase_distance: 16.0,
+            ..Default::default()
+        };
+        let far = DVec3::new(1.0e6 + 0.125, 3.0, -2.0e6 - 0.5);
+        let mut position = WorldPosition(far);
+        for step in 1..=100 {
+            origin.rebase(Vec3::new(40.0, 0.0, 0.0));
+            let translation = origin.to_translation_f64(position.0);
+            position.follow(&origin, translation);
+            assert_eq!(position.0, far, "step {step}");
+        }
+
+        let translation = origin.to_translation_f64(position.0) + Vec3::X;
+        position.follow(&origin, translation);
+        assert!(position.0.abs_diff_eq(far + DVec3::X, 1.0));
+    }
+}
@@ -0,0 +1,167 @@
+use bevy_ecs::system::Resource;
+
+/// A rumble that ramps up over `attack_secs`, holds for `hold_secs` and fades out over
+/// `release_secs`. Strengths are 0 to 1 per motor: the strong one is usually the
+/// low-frequency motor, the weak one the high-frequency motor.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Haptic {
+    pub strong_motor: f32,
+    pub weak_motor: f32,
+    pub attack_secs: f32,
+    pub hold_secs: f32,
+    pub release_secs: f32,
+}
+
+impl Haptic {
+    /// A short, sharp tick
+    pub const BLOCK_BREAK: Self = Self {
+        strong_motor: 0.0,
+        weak_motor: 0.5,
+        attack_secs: 0.0,
+        hold_secs: 0.05,
+        release_secs: 0.05,
+    };
+    /// A heavy thud, scaled by how hard the landing was with [`Self::scaled`]
+    pub const LANDING: Self = Self {
+        strong_motor: 0.8,
+        weak_motor: 0.2,
+        attack_secs: 0.0,
+        hold_secs: 0.08,
+        release_secs: 0.25,
+    };
+    /// A jolt that lingers, scaled by the damage taken with [`Self::scaled`]
+    pub const DAMAGE: Self = Self {
+        strong_motor: 1.0,
+        weak_motor: 0.6,
+        attack_secs: 0.02,
+        hold_secs: 0.15,
+        release_secs: 0.4,
+    };
+
+    /// The same envelope with both motors scaled by `strength`, clamped to 0 to 1
+    pub fn scaled(self, strength: f32) -> Self {
+        let strength = strength.clamp(0.0, 1.0);
+        Self {
+            strong_motor: self.strong_motor * strength,
+            weak_motor: self.weak_motor * strength,
+            ..self
+        }
+    }
+
+    pub fn duration_secs(&self) -> f32 {
+        self.attack_secs + self.hold_secs + self.release_secs
+    }
+
+    /// How much of the full strength plays `age` seconds in, 0 to 1
+    pub fn envelope(&self, age: f32) -> f32 {
+        if age < 0.0 || age >= self.duration_secs() {
+            0.0
+        } else if age < self.attack_secs {
+            age / self.attack_secs
+        } else if age < self.attack_secs + self.hold_secs {
+            1.0
+        } else {
+            1.0 - (age - self.attack_secs - self.hold_secs) / self.release_secs
+        }
+    }
+}
+
+/// Strengths of both motors, 0 to 1
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct RumbleIntensity {
+    pub strong_motor: f32,
+    pub weak_motor: f32,
+}
+
+/// The [`Haptic`]s playing, mixed into one [`RumbleIntensity`] for the controller. Overlapping
+/// haptics add up, capped at full strength.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct Haptics {
+    /// With their age in seconds
+    playing: Vec<(Haptic, f32)>,
+}
+
+impl Haptics {
+    pub fn play(&mut self, haptic: Haptic) {
+        self.playing.push((haptic, 0.0));
+    }
+
+    /// Ages the haptics by `delta` seconds, dropping the finished ones
+    pub fn update(&mut self, delta: f32) {
+        for (_, age) in &mut self.playing {
+            *age += delta;
+        }
+        self.playing
+            .retain(|(haptic, age)| *age < haptic.duration_secs());
+    }
+
+    pub fn intensity(&self) -> RumbleIntensity {
+        let (strong, weak) =
+            self.playing
+                .iter()
+                .fold((0.0, 0.0), |(strong, weak), (haptic, age)| {
+                    let envelope = haptic.envelope(*age);
+                    (
+                        strong + haptic.strong_motor * envelope,
+                        weak + haptic.weak_motor * envelope,
+                    )
+                });
+        RumbleIntensity {
+            strong_motor: f32::min(strong, 1.0),
+            weak_motor: f32::min(weak, 1.0),
+        }
+    }
+
+    /// Seconds until everything playing has finished
+    pub fn remaining_secs(&self) -> f32 {
+        self.playing
+            .iter()
+            .map(|(haptic, age)| haptic.duration_secs() - age)
+            .fold(0.0, f32::max)
+    }
+
+    pub fn is_playing(&self) -> bool {
+        !self.playing.is_empty()
+    }
+
+    pub fn stop(&mut self) {
+        self.playing.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn haptics_follow_their_envelopes_and_add_up() {
+        let haptic = Haptic {
+            strong_motor: 0.8,
+            weak_motor: 0.0,
+            attack_secs: 0.1,
+            hold_secs: 0.2,
+            release_secs: 0.1,
+        };
+        assert_eq!(haptic.envelope(0.05), 0.5);
+        assert_eq!(haptic.envelope(0.2), 1.0);
+        assert!((haptic.envelope(0.35) - 0.5).abs() < 1e-5);
+        assert_eq!(haptic.envelope(0.4), 0.0);
+
+        let mut haptics = Haptics::default();
+        haptics.play(haptic);
+        haptics.play(haptic.scaled(0.5));
+        haptics.update(0.2);
+        assert_eq!(
+            haptics.intensity(),
+            RumbleIntensity {
+                strong_motor: 1.0,
+                weak_motor: 0.0,
+            }
+        );
+        assert!((haptics.remaining_secs() - 0.2).abs() < 1e-5);
+
+        haptics.update(0.2);
+        assert!(!haptics.is_playing());
+        assert_eq!(haptics.intensity(), RumbleIntensity::default());
+    }
+}
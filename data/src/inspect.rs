@@ -0,0 +1,262 @@
+use std::fmt;
+
+use glam::{EulerRot, Quat};
+use thiserror::Error;
+
+use crate::{
+    camera::CameraFov, instance::Instance, name::Name, transform::Transform, voxel::Voxel,
+};
+
+/// A single editable value exposed by [`Inspect`]. Vectors are flattened into one field per
+/// component so every field can be nudged by a scalar step.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldValue {
+    F32(f32),
+    Text(String),
+    /// Index into a fixed list of options, e.g. voxel types
+    Choice {
+        selected: usize,
+        options: &'static [&'static str],
+    },
+}
+
+impl fmt::Display for FieldValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::F32(value) => write!(f, "{value:.3}"),
+            Self::Text(text) => write!(f, "{text:?}"),
+            Self::Choice { selected, options } => f.write_str(options[*selected]),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    pub name: &'static str,
+    pub value: FieldValue,
+}
+
+impl Field {
+    pub const fn new(name: &'static str, value: FieldValue) -> Self {
+        Self { name, value }
+    }
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum InspectError {
+    #[error("{component} has no field {field}")]
+    UnknownField {
+        component: &'static str,
+        field: String,
+    },
+    #[error("wrong value type for {field}")]
+    WrongType { field: &'static str },
+}
+
+/// Minimal reflection for engine components, enough for a debug inspector to list and edit
+/// their fields without knowing the concrete types
+pub trait Inspect {
+    fn type_name(&self) -> &'static str;
+
+    fn fields(&self) -> Vec<Field>;
+
+    fn set_field(&mut self, name: &str, value: FieldValue) -> Result<(), InspectError>;
+
+    /// Adds `steps` increments to a field: numbers move by `step`, choices cycle
+    fn nudge_field(&mut self, name: &str, steps: i32, step: f32) -> Result<(), InspectError> {
+        let field = self
+            .fields()
+            .into_iter()
+            .find(|field| field.name == name)
+            .ok_or_else(|| InspectError::UnknownField {
+                component: self.type_name(),
+                field: name.to_owned(),
+            })?;
+        let value = match field.value {
+            FieldValue::F32(value) => FieldValue::F32(value + steps as f32 * step),
+            FieldValue::Choice { selected, options } => FieldValue::Choice {
+                selected: (selected as i32 + steps).rem_euclid(options.len() as i32) as usize,
+                options,
+            },
+            FieldValue::Text(_) => return Err(InspectError::WrongType { field: field.name }),
+        };
+        self.set_field(name, value)
+    }
+}
+
+fn unknown(component: &'static str, field: &str) -> InspectError {
+    InspectError::UnknownField {
+        component,
+        field: field.to_owned(),
+    }
+}
+
+fn expect_f32(field: &'static str, value: FieldValue) -> Result<f32, InspectError> {
+    match value {
+        FieldValue::F32(value) => Ok(value),
+        _ => Err(InspectError::WrongType { field }),
+    }
+}
+
+const TRANSFORM_FIELDS: [&str; 9] = [
+    "translation.x",
+    "translation.y",
+    "translation.z",
+    "rotation.yaw",
+    "rotation.pitch",
+    "rotation.roll",
+    "scale.x",
+    "scale.y",
+    "scale.z",
+];
+
+/// Rotation is shown as YXZ Euler angles in degrees
+impl Inspect for Transform {
+    fn type_name(&self) -> &'static str {
+        "Transform"
+    }
+
+    fn fields(&self) -> Vec<Field> {
+        let (yaw, pitch, roll) = self.rotation.to_euler(EulerRot::YXZ);
+        let values = [
+            self.translation.x,
+            self.translation.y,
+            self.translation.z,
+            yaw.to_degrees(),
+            pitch.to_degrees(),
+            roll.to_degrees(),
+            self.scale.x,
+            self.scale.y,
+            self.scale.z,
+        ];
+        TRANSFORM_FIELDS
+            .iter()
+            .zip(values)
+            .map(|(name, value)| Field::new(name, FieldValue::F32(value)))
+            .collect()
+    }
+
+    fn set_field(&mut self, name: &str, value: FieldValue) -> Result<(), InspectError> {
+        let index = TRANSFORM_FIELDS
+            .iter()
+            .position(|field| *field == name)
+            .ok_or_else(|| unknown(self.type_name(), name))?;
+        let value = expect_f32(TRANSFORM_FIELDS[index], value)?;
+
+        match index {
+            0..=2 => self.translation[index] = value,
+            3..=5 => {
+                let (yaw, pitch, roll) = self.rotation.to_euler(EulerRot::YXZ);
+                let mut angles = [yaw, pitch, roll];
+                angles[index - 3] = value.to_radians();
+                self.rotation = Quat::from_euler(EulerRot::YXZ, angles[0], angles[1], angles[2]);
+            }
+            _ => self.scale[index - 6] = value,
+        }
+        Ok(())
+    }
+}
+
+impl Inspect for Name {
+    fn type_name(&self) -> &'static str {
+        "Name"
+    }
+
+    fn fields(&self) -> Vec<Field> {
+        vec![Field::new(
+            "name",
+            FieldValue::Text(self.as_str().to_owned()),
+        )]
+    }
+
+    fn set_field(&mut self, name: &str, value: FieldValue) -> Result<(), InspectError> {
+        match (name, value) {
+            ("name", FieldValue::Text(text)) => {
+                self.set(text);
+                Ok(())
+            }
+            ("name", _) => Err(InspectError::WrongType { field: "name" }),
+            _ => Err(unknown(self.type_name(), name)),
+        }
+    }
+}
+
+const VOXEL_NAMES: [&str; Voxel::VOXEL_COUNT as usize] = [
+    "Air",
+    "Stone",
+    "Dirt",
+    "Grass",
+    "Water",
+    "Glowstone",
+    "Chest",
+    "Lamp",
+];
+
+/// The mesh is not editable since handles are only meaningful to the `Meshes` that made them
+impl Inspect for Instance {
+    fn type_name(&self) -> &'static str {
+        "Instance"
+    }
+
+    fn fields(&self) -> Vec<Field> {
+        vec![Field::new(
+            "material",
+            FieldValue::Choice {
+                selected: self.material as usize,
+                options: &VOXEL_NAMES,
+            },
+        )]
+    }
+
+    fn set_field(&mut self, name: &str, value: FieldValue) -> Result<(), InspectError> {
+        match (name, value) {
+            ("material", FieldValue::Choice { selected, .. }) => {
+                self.material = Voxel::ALL[selected];
+                Ok(())
+            }
+            ("material", _) => Err(InspectError::WrongType { field: "material" }),
+            _ => Err(unknown(self.type_name(), name)),
+        }
+    }
+}
+
+impl Inspect for CameraFov {
+    fn type_name(&self) -> &'static str {
+        "CameraFov"
+    }
+
+    fn fields(&self) -> Vec<Field> {
+        vec![Field::new("degrees", FieldValue::F32(self.degrees()))]
+    }
+
+    fn set_field(&mut self, name: &str, value: FieldValue) -> Result<(), InspectError> {
+        if name != "degrees" {
+            return Err(unknown(self.type_name(), name));
+        }
+        *self = CameraFov::from_degrees(expect_f32("degrees", value)?);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+
+    use super::*;
+
+    #[test]
+    fn transform_fields_round_trip() {
+        let mut transform = Transform::from_translation(Vec3::new(1.0, 2.0, 3.0));
+        transform.nudge_field("translation.y", 2, 0.5).unwrap();
+        transform
+            .set_field("rotation.yaw", FieldValue::F32(90.0))
+            .unwrap();
+
+        assert_eq!(transform.translation, Vec3::new(1.0, 3.0, 3.0));
+        let yaw = &transform.fields()[3];
+        assert!(matches!(yaw.value, FieldValue::F32(degrees) if (degrees - 90.0).abs() < 1e-3));
+        assert!(transform
+            .set_field("translation.w", FieldValue::F32(0.0))
+            .is_err());
+    }
+}
@@ -0,0 +1,57 @@
+use std::collections::BTreeMap;
+
+use bevy_ecs::{component::Component, entity::Entity};
+use glam::Mat4;
+
+use crate::{mesh::MeshHandle, transform::Transform, voxel::Voxel};
+
+/// A copy of a shared mesh placed by the entity's [`Transform`], e.g. grass or tree props
+#[derive(Component, Debug, Clone, Copy)]
+#[require(Transform)]
+pub struct Instance {
+    pub mesh: MeshHandle,
+    pub material: Voxel,
+}
+
+impl Instance {
+    pub const fn new(mesh: MeshHandle, material: Voxel) -> Self {
+        Self { mesh, material }
+    }
+}
+
+/// One instance of a batch, keeping its entity so GPU hits can be mapped back to it
+#[derive(Debug, Clone, Copy)]
+pub struct BatchedInstance {
+    pub entity: Entity,
+    pub model: Mat4,
+    pub material: Voxel,
+}
+
+/// All instances of one mesh, which share a single BLAS
+#[derive(Debug, Clone)]
+pub struct InstanceBatch {
+    pub mesh: MeshHandle,
+    pub instances: Vec<BatchedInstance>,
+}
+
+/// Groups instances by mesh, ordered by mesh handle
+pub fn batch_instances<'a>(
+    instances: impl IntoIterator<Item = (Entity, &'a Transform, &'a Instance)>,
+) -> Vec<InstanceBatch> {
+    let mut batches = BTreeMap::<MeshHandle, Vec<BatchedInstance>>::new();
+    for (entity, transform, instance) in instances {
+        batches
+            .entry(instance.mesh)
+            .or_default()
+            .push(BatchedInstance {
+                entity,
+                model: transform.to_mat4(),
+                material: instance.material,
+            });
+    }
+
+    batches
+        .into_iter()
+        .map(|(mesh, instances)| InstanceBatch { mesh, instances })
+        .collect()
+}
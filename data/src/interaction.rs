@@ -0,0 +1,38 @@
+use crate::voxel::Voxel;
+
+/// What right-clicking a voxel does, declared per voxel by [`Voxel::interaction`]. Gameplay
+/// systems carry it out; stateful voxels keep their state in a
+/// [`BlockEntity`](crate::block_entity::BlockEntity) or by turning into another voxel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interaction {
+    /// Shows the voxel's [`Inventory`](crate::inventory::Inventory) block entity, created
+    /// empty with `slots` slots on first use
+    OpenContainer { slots: usize },
+    /// Swaps the voxel for its lit or unlit counterpart
+    ToggleLight { to: Voxel },
+}
+
+impl Interaction {
+    pub const CHEST_SLOTS: usize = 27;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lights_toggle_back() {
+        for voxel in Voxel::ALL {
+            if let Some(Interaction::ToggleLight { to }) = voxel.interaction() {
+                assert_eq!(
+                    to.interaction(),
+                    Some(Interaction::ToggleLight { to: voxel })
+                );
+                assert_ne!(
+                    voxel.material().emission == [0.0; 3],
+                    to.material().emission == [0.0; 3]
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,226 @@
+use bevy_ecs::component::Component;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::item::{ItemId, ItemRegistry};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ItemStack {
+    pub item: ItemId,
+    pub count: u16,
+}
+
+impl ItemStack {
+    pub const fn new(item: ItemId, count: u16) -> Self {
+        Self { item, count }
+    }
+}
+
+/// Fixed-size list of item slots. The first [`Inventory::HOTBAR_SLOTS`] slots are the hotbar.
+#[derive(Component, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "SavedInventory", into = "SavedInventory")]
+pub struct Inventory {
+    slots: Vec<Option<ItemStack>>,
+}
+
+/// Serialized form of [`Inventory`]: only the occupied slots, since formats like TOML can't
+/// hold empty entries in an array
+#[derive(Serialize, Deserialize)]
+struct SavedInventory {
+    size: usize,
+    stacks: Vec<SavedSlot>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedSlot {
+    slot: usize,
+    item: ItemId,
+    count: u16,
+}
+
+impl From<Inventory> for SavedInventory {
+    fn from(inventory: Inventory) -> Self {
+        Self {
+            size: inventory.slots.len(),
+            stacks: inventory
+                .slots
+                .iter()
+                .enumerate()
+                .filter_map(|(slot, stack)| {
+                    stack.map(|stack| SavedSlot {
+                        slot,
+                        item: stack.item,
+                        count: stack.count,
+                    })
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<SavedInventory> for Inventory {
+    /// Stacks outside the inventory are dropped
+    fn from(saved: SavedInventory) -> Self {
+        let mut inventory = Self::new(saved.size);
+        for stack in saved
+            .stacks
+            .into_iter()
+            .filter(|stack| stack.slot < saved.size)
+        {
+            inventory.set(stack.slot, Some(ItemStack::new(stack.item, stack.count)));
+        }
+        inventory
+    }
+}
+
+impl Default for Inventory {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_SIZE)
+    }
+}
+
+impl Inventory {
+    pub const DEFAULT_SIZE: usize = 36;
+    pub const HOTBAR_SLOTS: usize = 9;
+
+    pub fn new(size: usize) -> Self {
+        Self {
+            slots: vec![None; size],
+        }
+    }
+
+    pub fn slots(&self) -> &[Option<ItemStack>] {
+        &self.slots
+    }
+
+    pub fn slot(&self, slot: usize) -> Option<ItemStack> {
+        self.slots.get(slot).copied().flatten()
+    }
+
+    pub fn hotbar(&self) -> &[Option<ItemStack>] {
+        &self.slots[..Self::HOTBAR_SLOTS.min(self.slots.len())]
+    }
+
+    /// Total count of `item` across all slots
+    pub fn count(&self, item: ItemId) -> u32 {
+        self.slots
+            .iter()
+            .flatten()
+            .filter(|stack| stack.item == item)
+            .map(|stack| stack.count as u32)
+            .sum()
+    }
+
+    /// Tops up existing stacks of `item` first, then fills empty slots.
+    /// Returns how many did not fit.
+    pub fn add(&mut self, item: ItemId, mut count: u16, registry: &ItemRegistry) -> u16 {
+        let max_stack = registry.max_stack(item);
+
+        for stack in self.slots.iter_mut().flatten() {
+            if count == 0 {
+                break;
+            }
+            if stack.item == item && stack.count < max_stack {
+                let moved = count.min(max_stack - stack.count);
+                stack.count += moved;
+                count -= moved;
+            }
+        }
+
+        for slot in self.slots.iter_mut().filter(|slot| slot.is_none()) {
+            if count == 0 {
+                break;
+            }
+            let moved = count.min(max_stack);
+            *slot = Some(ItemStack::new(item, moved));
+            count -= moved;
+        }
+
+        count
+    }
+
+    /// Takes up to `count` items out of `slot`, clearing it when emptied
+    pub fn take(&mut self, slot: usize, count: u16) -> Option<ItemStack> {
+        let stack = self.slots.get_mut(slot)?.as_mut()?;
+        let taken = ItemStack::new(stack.item, count.min(stack.count));
+        stack.count -= taken.count;
+        if stack.count == 0 {
+            self.slots[slot] = None;
+        }
+        (taken.count > 0).then_some(taken)
+    }
+
+    /// Does nothing for a slot past the end
+    pub fn set(&mut self, slot: usize, stack: Option<ItemStack>) {
+        if let Some(slot) = self.slots.get_mut(slot) {
+            *slot = stack.filter(|stack| stack.count > 0);
+        }
+    }
+
+    /// Slot count followed by `(item, count)` per slot, with count 0 for empty slots
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(4 + self.slots.len() * 4);
+        bytes.extend_from_slice(&(self.slots.len() as u32).to_le_bytes());
+        for slot in &self.slots {
+            let (item, count) = slot.map_or((0, 0), |stack| (stack.item.0, stack.count));
+            bytes.extend_from_slice(&item.to_le_bytes());
+            bytes.extend_from_slice(&count.to_le_bytes());
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, InventoryError> {
+        let (len, slots) = bytes
+            .split_first_chunk::<4>()
+            .ok_or(InventoryError::Truncated)?;
+        let len = u32::from_le_bytes(*len) as usize;
+        if slots.len() != len * 4 {
+            return Err(InventoryError::Truncated);
+        }
+
+        let slots = slots
+            .chunks_exact(4)
+            .map(|slot| {
+                let item = u16::from_le_bytes([slot[0], slot[1]]);
+                let count = u16::from_le_bytes([slot[2], slot[3]]);
+                (count > 0).then_some(ItemStack::new(ItemId(item), count))
+            })
+            .collect();
+        Ok(Self { slots })
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum InventoryError {
+    #[error("inventory data is truncated")]
+    Truncated,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{item::Item, voxel::Voxel};
+
+    #[test]
+    fn add_merges_stacks_then_spills() {
+        let mut registry = ItemRegistry::default();
+        let stone = registry.voxel_drop(Voxel::Stone).unwrap();
+        let torch = registry.register(Item::new("Torch").with_max_stack(4));
+
+        let mut inventory = Inventory::new(3);
+        assert_eq!(inventory.add(stone, 70, &registry), 0);
+        assert_eq!(inventory.slot(0), Some(ItemStack::new(stone, 64)));
+        assert_eq!(inventory.slot(1), Some(ItemStack::new(stone, 6)));
+
+        assert_eq!(inventory.add(torch, 6, &registry), 2);
+        assert_eq!(inventory.count(torch), 4);
+        // Past the end
+        inventory.set(3, Some(ItemStack::new(torch, 1)));
+        assert_eq!(inventory.count(torch), 4);
+
+        assert_eq!(
+            Inventory::from_bytes(&inventory.to_bytes()).unwrap(),
+            inventory
+        );
+    }
+}
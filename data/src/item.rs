@@ -0,0 +1,97 @@
+use bevy_ecs::{component::Component, system::Resource};
+use serde::{Deserialize, Serialize};
+
+use crate::{inventory::ItemStack, transform::Transform, voxel::Voxel};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ItemId(pub u16);
+
+/// Items lying in the world at the entity's [`Transform`], e.g. ones that didn't fit in an
+/// inventory
+#[derive(Component, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[require(Transform)]
+pub struct ItemDrop(pub ItemStack);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Item {
+    pub name: String,
+    pub max_stack: u16,
+    /// Voxel placed when this item is used on a block, if any
+    pub voxel: Option<Voxel>,
+}
+
+impl Item {
+    pub const DEFAULT_MAX_STACK: u16 = 64;
+
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            max_stack: Self::DEFAULT_MAX_STACK,
+            voxel: None,
+        }
+    }
+
+    pub fn with_max_stack(mut self, max_stack: u16) -> Self {
+        self.max_stack = max_stack.max(1);
+        self
+    }
+
+    pub fn with_voxel(mut self, voxel: Voxel) -> Self {
+        self.voxel = Some(voxel);
+        self
+    }
+}
+
+/// Every known item, and which item each voxel drops when broken
+#[derive(Resource, Debug, Clone)]
+pub struct ItemRegistry {
+    items: Vec<Item>,
+    voxel_items: [Option<ItemId>; Voxel::VOXEL_COUNT as usize],
+}
+
+impl Default for ItemRegistry {
+    /// One block item per voxel except air
+    fn default() -> Self {
+        let mut registry = Self {
+            items: Vec::new(),
+            voxel_items: [None; Voxel::VOXEL_COUNT as usize],
+        };
+        for voxel in Voxel::ALL.into_iter().filter(|voxel| *voxel != Voxel::Air) {
+            let item = registry.register(Item::new(format!("{voxel:?}")).with_voxel(voxel));
+            registry.set_voxel_drop(voxel, Some(item));
+        }
+        registry
+    }
+}
+
+impl ItemRegistry {
+    pub fn register(&mut self, item: Item) -> ItemId {
+        self.items.push(item);
+        ItemId(self.items.len() as u16 - 1)
+    }
+
+    pub fn get(&self, id: ItemId) -> Option<&Item> {
+        self.items.get(id.0 as usize)
+    }
+
+    pub fn max_stack(&self, id: ItemId) -> u16 {
+        self.get(id).map_or(1, |item| item.max_stack)
+    }
+
+    /// Item collected when `voxel` is broken
+    pub fn voxel_drop(&self, voxel: Voxel) -> Option<ItemId> {
+        self.voxel_items[voxel as usize]
+    }
+
+    pub fn set_voxel_drop(&mut self, voxel: Voxel, item: Option<ItemId>) {
+        self.voxel_items[voxel as usize] = item;
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (ItemId, &Item)> {
+        self.items
+            .iter()
+            .enumerate()
+            .map(|(index, item)| (ItemId(index as u16), item))
+    }
+}
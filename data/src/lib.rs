@@ -1,8 +1,54 @@
+use glam::IVec3;
+use serde::{Deserialize, Serialize};
+
+pub mod accessibility;
+pub mod block_entity;
+pub mod block_tick;
 pub mod camera;
+pub mod chat;
+pub mod chunk_cache;
+pub mod chunk_heatmap;
+pub mod chunk_map;
+pub mod collider;
+pub mod edit_history;
+pub mod edit_prediction;
+pub mod exposure;
+pub mod floating_origin;
+pub mod haptics;
+pub mod inspect;
+pub mod instance;
+pub mod interaction;
+pub mod inventory;
+pub mod item;
+pub mod light;
+pub mod material;
 pub mod math;
+pub mod mesh;
+pub mod mesh_attribute;
+pub mod mesh_shapes;
+pub mod mesher;
+pub mod minimap;
+pub mod name;
+pub mod notification;
+pub mod npc;
+pub mod particles;
+pub mod prelude;
+pub mod rng;
+pub mod schematic;
+pub mod skinning;
+pub mod solid_mask;
+pub mod spring_arm;
+pub mod streaming;
 pub mod transform;
+pub mod ui_layout;
+pub mod vertex;
+pub mod view_distance;
+pub mod vox;
 pub mod voxel;
 pub mod voxel_block;
+pub mod weather;
+pub mod world_border;
+pub mod worldgen;
 
 pub trait IntoBytes {
     fn to_bytes(&self) -> &[u8];
@@ -12,7 +58,7 @@ pub trait IntoBytesMut {
     fn to_bytes_mut(&mut self) -> &mut [u8];
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Direction {
     Left,
     Right,
@@ -21,3 +67,50 @@ pub enum Direction {
     Back,
     Forward,
 }
+
+impl Direction {
+    pub const ALL: [Self; 6] = [
+        Self::Left,
+        Self::Right,
+        Self::Down,
+        Self::Up,
+        Self::Back,
+        Self::Forward,
+    ];
+
+    /// Unit step in this direction. -Y is up and -Z is forward.
+    pub const fn offset(&self) -> IVec3 {
+        match self {
+            Self::Left => IVec3::NEG_X,
+            Self::Right => IVec3::X,
+            Self::Down => IVec3::Y,
+            Self::Up => IVec3::NEG_Y,
+            Self::Back => IVec3::Z,
+            Self::Forward => IVec3::NEG_Z,
+        }
+    }
+
+    pub const fn opposite(&self) -> Self {
+        match self {
+            Self::Left => Self::Right,
+            Self::Right => Self::Left,
+            Self::Down => Self::Up,
+            Self::Up => Self::Down,
+            Self::Back => Self::Forward,
+            Self::Forward => Self::Back,
+        }
+    }
+
+    /// Inverse of `direction as u8`
+    pub const fn from_index(index: u8) -> Option<Self> {
+        match index {
+            0 => Some(Self::Left),
+            1 => Some(Self::Right),
+            2 => Some(Self::Down),
+            3 => Some(Self::Up),
+            4 => Some(Self::Back),
+            5 => Some(Self::Forward),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,223 @@
+use bevy_ecs::component::Component;
+use bytemuck::{Pod, Zeroable};
+use glam::Vec3;
+
+use crate::{instance::Instance, material::Material, transform::Transform};
+
+/// Lights the GPU buffer has room for; the ones nearest the camera win
+pub const MAX_LIGHTS: usize = 64;
+/// How far an emissive instance's light reaches
+pub const EMISSIVE_LIGHT_RANGE: f32 = 8.0;
+
+/// Shines in every direction from the entity's [`Transform`], e.g. a torch
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+#[require(Transform)]
+pub struct PointLight {
+    pub color: [f32; 3],
+    pub intensity: f32,
+    /// Distance at which the light has faded out completely
+    pub range: f32,
+}
+
+impl Default for PointLight {
+    fn default() -> Self {
+        Self {
+            color: [1.0, 0.7, 0.4],
+            intensity: 1.0,
+            range: 8.0,
+        }
+    }
+}
+
+/// Shines along the entity's forward axis (-Z) in a cone, e.g. a lamp
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+#[require(Transform)]
+pub struct SpotLight {
+    pub color: [f32; 3],
+    pub intensity: f32,
+    pub range: f32,
+    /// Half-angle in radians inside which the light is at full strength
+    pub inner_angle: f32,
+    /// Half-angle in radians outside which the light doesn't reach
+    pub outer_angle: f32,
+}
+
+impl Default for SpotLight {
+    fn default() -> Self {
+        Self {
+            color: [1.0, 1.0, 0.9],
+            intensity: 1.5,
+            range: 16.0,
+            inner_angle: 20f32.to_radians(),
+            outer_angle: 30f32.to_radians(),
+        }
+    }
+}
+
+/// Matches `Light` in the hit shader
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+pub struct LightGpu {
+    pub position: [f32; 3],
+    pub range: f32,
+    pub color: [f32; 3],
+    pub intensity: f32,
+    pub direction: [f32; 3],
+    /// Cosines of the cone's half-angles; both -1 for point lights, which have no cone
+    pub cos_outer: f32,
+    pub cos_inner: f32,
+    /// Size of the emitting surface; shadow rays stop this far short so the emitter doesn't
+    /// shadow its own light
+    pub radius: f32,
+    _padding: [u32; 2],
+}
+
+impl LightGpu {
+    pub fn point(transform: &Transform, light: &PointLight) -> Self {
+        Self {
+            position: transform.translation.to_array(),
+            range: light.range,
+            color: light.color,
+            intensity: light.intensity,
+            direction: [0.0; 3],
+            cos_outer: -1.0,
+            cos_inner: -1.0,
+            radius: 0.0,
+            _padding: [0; 2],
+        }
+    }
+
+    pub fn spot(transform: &Transform, light: &SpotLight) -> Self {
+        Self {
+            position: transform.translation.to_array(),
+            range: light.range,
+            color: light.color,
+            intensity: light.intensity,
+            direction: (transform.rotation * Vec3::NEG_Z).to_array(),
+            cos_outer: light.outer_angle.cos(),
+            cos_inner: light.inner_angle.cos(),
+            radius: 0.0,
+            _padding: [0; 2],
+        }
+    }
+
+    /// A glowing instance lighting its surroundings like a point light from its center, or
+    /// `None` if its material doesn't emit. Instance meshes are assumed to be about a voxel
+    /// across before scaling.
+    pub fn emissive(transform: &Transform, material: &Material) -> Option<Self> {
+        let emission = Vec3::from_array(material.emission);
+        let intensity = emission.max_element();
+        (intensity > 0.0).then(|| Self {
+            position: transform.translation.to_array(),
+            range: EMISSIVE_LIGHT_RANGE,
+            color: (emission / intensity).to_array(),
+            intensity,
+            direction: [0.0; 3],
+            cos_outer: -1.0,
+            cos_inner: -1.0,
+            // Half the diagonal of a scaled unit cube
+            radius: transform.scale.max_element() * 0.87,
+            _padding: [0; 2],
+        })
+    }
+
+    fn distance_squared(&self, position: Vec3) -> f32 {
+        Vec3::from_array(self.position).distance_squared(position)
+    }
+}
+
+/// Every point and spot light plus emissive instances, keeping the [`MAX_LIGHTS`] nearest to
+/// `camera_position` when there are too many
+pub fn gather_lights<'a>(
+    camera_position: Vec3,
+    point_lights: impl IntoIterator<Item = (&'a Transform, &'a PointLight)>,
+    spot_lights: impl IntoIterator<Item = (&'a Transform, &'a SpotLight)>,
+    instances: impl IntoIterator<Item = (&'a Transform, &'a Instance)>,
+) -> Vec<LightGpu> {
+    let mut lights: Vec<_> = point_lights
+        .into_iter()
+        .map(|(transform, light)| LightGpu::point(transform, light))
+        .chain(
+            spot_lights
+                .into_iter()
+                .map(|(transform, light)| LightGpu::spot(transform, light)),
+        )
+        .chain(instances.into_iter().filter_map(|(transform, instance)| {
+            LightGpu::emissive(transform, &instance.material.material())
+        }))
+        .collect();
+    if lights.len() > MAX_LIGHTS {
+        lights.sort_by(|a, b| {
+            a.distance_squared(camera_position)
+                .total_cmp(&b.distance_squared(camera_position))
+        });
+        lights.truncate(MAX_LIGHTS);
+    }
+    lights
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Quat;
+
+    use super::*;
+    use crate::{
+        mesh::{Mesh, Meshes},
+        voxel::Voxel,
+    };
+
+    #[test]
+    fn spot_lights_point_along_forward() {
+        let transform = Transform {
+            rotation: Quat::from_rotation_y(std::f32::consts::FRAC_PI_2),
+            ..Default::default()
+        };
+        let light = LightGpu::spot(&transform, &SpotLight::default());
+        let direction = Vec3::from_array(light.direction);
+        assert!(direction.abs_diff_eq(Vec3::NEG_X, 1e-6));
+        assert!(light.cos_inner > light.cos_outer);
+    }
+
+    #[test]
+    fn only_emissive_instances_give_off_light() {
+        let mesh = Meshes::default().add(Mesh::default());
+        let transform = Transform::default();
+        let glowstone = Instance::new(mesh, Voxel::Glowstone);
+        let stone = Instance::new(mesh, Voxel::Stone);
+
+        let lights = gather_lights(
+            Vec3::ZERO,
+            [],
+            [],
+            [(&transform, &glowstone), (&transform, &stone)],
+        );
+
+        assert_eq!(lights.len(), 1);
+        assert!(lights[0].radius > 0.0);
+        assert_eq!(
+            Vec3::from_array(lights[0].color) * lights[0].intensity,
+            Vec3::from_array(Voxel::Glowstone.material().emission)
+        );
+    }
+
+    #[test]
+    fn nearest_lights_are_kept() {
+        let transforms: Vec<_> = (0..MAX_LIGHTS + 8)
+            .rev()
+            .map(|i| Transform::from_translation(Vec3::new(i as f32, 0.0, 0.0)))
+            .collect();
+        let point = PointLight::default();
+
+        let lights = gather_lights(
+            Vec3::ZERO,
+            transforms.iter().map(|transform| (transform, &point)),
+            [],
+            [],
+        );
+
+        assert_eq!(lights.len(), MAX_LIGHTS);
+        assert!(lights
+            .iter()
+            .all(|light| light.position[0] < MAX_LIGHTS as f32));
+    }
+}
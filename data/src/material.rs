@@ -0,0 +1,111 @@
+use bytemuck::{Pod, Zeroable};
+
+use crate::IntoBytes;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MaterialFlags(u32);
+
+impl MaterialFlags {
+    pub const NONE: Self = Self(0);
+    /// Surfaces such as mirrors and water that trace a reflection ray
+    pub const REFLECTIVE: Self = Self(1 << 0);
+
+    pub const fn bits(&self) -> u32 {
+        self.0
+    }
+
+    pub const fn contains(&self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub const fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Material {
+    pub color: [f32; 3],
+    /// 0.0 is a perfect mirror, 1.0 is fully diffuse
+    pub roughness: f32,
+    pub flags: MaterialFlags,
+    /// Light given off, already scaled by intensity; black for most materials
+    pub emission: [f32; 3],
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Self {
+            color: [1.0, 1.0, 1.0],
+            roughness: 1.0,
+            flags: MaterialFlags::NONE,
+            emission: [0.0; 3],
+        }
+    }
+}
+
+impl Material {
+    pub const fn from_color(color: [f32; 3]) -> Self {
+        Self {
+            color,
+            roughness: 1.0,
+            flags: MaterialFlags::NONE,
+            emission: [0.0; 3],
+        }
+    }
+
+    pub const fn with_roughness(mut self, roughness: f32) -> Self {
+        self.roughness = roughness;
+        self
+    }
+
+    pub const fn with_flags(mut self, flags: MaterialFlags) -> Self {
+        self.flags = self.flags.union(flags);
+        self
+    }
+
+    /// Glows with `color` at `intensity`, e.g. glowstone or lava
+    pub const fn with_emission(mut self, color: [f32; 3], intensity: f32) -> Self {
+        self.emission = [
+            color[0] * intensity,
+            color[1] * intensity,
+            color[2] * intensity,
+        ];
+        self
+    }
+
+    pub const fn is_reflective(&self) -> bool {
+        self.flags.contains(MaterialFlags::REFLECTIVE)
+    }
+
+    pub fn is_emissive(&self) -> bool {
+        self.emission.iter().any(|&channel| channel > 0.0)
+    }
+}
+
+/// Matches `struct Material` in the hit shaders (std430)
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+pub struct MaterialGpu {
+    pub color: [f32; 3],
+    pub roughness: f32,
+    pub emission: [f32; 3],
+    pub flags: u32,
+}
+
+impl MaterialGpu {
+    pub fn new(material: &Material) -> Self {
+        Self {
+            color: material.color,
+            roughness: material.roughness,
+            emission: material.emission,
+            flags: material.flags.bits(),
+        }
+    }
+}
+
+impl IntoBytes for [MaterialGpu] {
+    fn to_bytes(&self) -> &[u8] {
+        bytemuck::cast_slice(self)
+    }
+}
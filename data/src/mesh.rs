@@ -0,0 +1,546 @@
+use std::{
+    borrow::Cow,
+    collections::{hash_map::DefaultHasher, BTreeMap},
+    hash::{Hash, Hasher},
+    mem,
+};
+
+use bevy_ecs::system::Resource;
+use bytemuck::Pod;
+use glam::{Vec2, Vec3, Vec4};
+use thiserror::Error;
+
+use crate::{
+    mesh_attribute::{MeshAttribute, MeshAttributeError, MeshAttributeId},
+    vertex::{ChunkVertex, VertexAttribute, VertexFormat, VertexLayout},
+};
+
+/// Index of a mesh in [`Meshes`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MeshHandle(u32);
+
+impl MeshHandle {
+    pub const fn index(&self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// How a mesh's indices are grouped into primitives
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum PrimitiveTopology {
+    #[default]
+    TriangleList,
+    /// Only drawn, never ray traced
+    LineList,
+}
+
+/// Indexed geometry in object space
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Mesh {
+    pub positions: Vec<[f32; 3]>,
+    pub indices: Vec<u16>,
+    pub topology: PrimitiveTopology,
+    /// Values of every other attribute, one per position, as raw bytes
+    attributes: BTreeMap<MeshAttributeId, (MeshAttribute, Vec<u8>)>,
+}
+
+impl Mesh {
+    pub const ATTRIBUTE_POSITION: MeshAttribute = MeshAttribute::POSITION;
+    pub const ATTRIBUTE_NORMAL: MeshAttribute = MeshAttribute::NORMAL;
+    pub const ATTRIBUTE_COLOR: MeshAttribute = MeshAttribute::COLOR;
+    pub const ATTRIBUTE_UV: MeshAttribute = MeshAttribute::UV;
+    pub const ATTRIBUTE_TANGENT: MeshAttribute = MeshAttribute::TANGENT;
+    pub const ATTRIBUTE_JOINTS: MeshAttribute = MeshAttribute::JOINTS;
+    pub const ATTRIBUTE_WEIGHTS: MeshAttribute = MeshAttribute::WEIGHTS;
+
+    /// Tightly packed positions in one binding
+    pub const LAYOUT: VertexLayout = VertexLayout {
+        stride: VertexFormat::Float32x3.size(),
+        attributes: Cow::Borrowed(&[VertexAttribute {
+            location: 0,
+            format: VertexFormat::Float32x3,
+            offset: 0,
+        }]),
+    };
+
+    pub fn new(positions: Vec<[f32; 3]>, indices: Vec<u16>) -> Self {
+        Self {
+            positions,
+            indices,
+            topology: PrimitiveTopology::TriangleList,
+            attributes: BTreeMap::new(),
+        }
+    }
+
+    /// Sets one value of `attribute` per position. `T` must be the size of the attribute's
+    /// format, e.g. `[f32; 4]` for [`Self::ATTRIBUTE_COLOR`].
+    pub fn insert_attribute<T: Pod>(
+        &mut self,
+        attribute: MeshAttribute,
+        values: &[T],
+    ) -> Result<(), MeshAttributeError> {
+        if attribute.id == MeshAttribute::POSITION.id {
+            return Err(MeshAttributeError::Position);
+        }
+        if mem::size_of::<T>() != attribute.format.size() as usize {
+            return Err(MeshAttributeError::WrongSize {
+                name: attribute.name,
+                expected: attribute.format.size(),
+                actual: mem::size_of::<T>(),
+            });
+        }
+        if values.len() != self.positions.len() {
+            return Err(MeshAttributeError::WrongCount {
+                name: attribute.name,
+                expected: self.positions.len(),
+                actual: values.len(),
+            });
+        }
+        self.attributes.insert(
+            attribute.id,
+            (attribute, bytemuck::cast_slice(values).to_vec()),
+        );
+        Ok(())
+    }
+
+    pub fn remove_attribute(&mut self, attribute: MeshAttribute) -> bool {
+        self.attributes.remove(&attribute.id).is_some()
+    }
+
+    /// Raw bytes of an attribute other than the position
+    pub fn attribute(&self, attribute: MeshAttribute) -> Option<&[u8]> {
+        self.attributes
+            .get(&attribute.id)
+            .map(|(_, bytes)| bytes.as_slice())
+    }
+
+    /// Copies an attribute's values out as `T`, which must be the size of its format
+    pub fn attribute_values<T: Pod>(&self, attribute: MeshAttribute) -> Option<Vec<T>> {
+        self.attribute(attribute)
+            .filter(|_| mem::size_of::<T>() == attribute.format.size() as usize)
+            .map(|bytes| {
+                bytes
+                    .chunks_exact(mem::size_of::<T>())
+                    .map(bytemuck::pod_read_unaligned)
+                    .collect()
+            })
+    }
+
+    /// The position, then every other attribute the mesh has, ordered by id
+    pub fn attributes(&self) -> impl Iterator<Item = MeshAttribute> + '_ {
+        [MeshAttribute::POSITION]
+            .into_iter()
+            .chain(self.attributes.values().map(|(attribute, _)| *attribute))
+    }
+
+    /// The layout [`Self::pack_interleaved`] produces: the position at location 0, then every
+    /// other attribute the mesh has, ordered by id
+    pub fn vertex_layout(&self) -> VertexLayout {
+        if self.attributes.is_empty() {
+            return Self::LAYOUT;
+        }
+        MeshAttribute::vertex_layout(&self.attributes().collect::<Vec<_>>())
+    }
+
+    /// All attributes interleaved into one vertex buffer laid out as [`Self::vertex_layout`]
+    pub fn pack_interleaved(&self) -> Vec<u8> {
+        let layout = self.vertex_layout();
+        let mut bytes = vec![0; layout.stride as usize * self.positions.len()];
+        let sources = [bytemuck::cast_slice(&self.positions)].into_iter().chain(
+            self.attributes
+                .values()
+                .map(|(_, values)| values.as_slice()),
+        );
+        for (attribute, source) in layout.attributes.iter().zip(sources) {
+            let size = attribute.format.size() as usize;
+            for (vertex, value) in bytes
+                .chunks_exact_mut(layout.stride as usize)
+                .zip(source.chunks_exact(size))
+            {
+                vertex[attribute.offset as usize..][..size].copy_from_slice(value);
+            }
+        }
+        bytes
+    }
+
+    /// Checks that the indices form whole primitives and stay in bounds, and that every
+    /// attribute has one value per position
+    pub fn validate(&self) -> Result<(), MeshError> {
+        let vertex_count = self.positions.len();
+        if vertex_count > u16::MAX as usize + 1 {
+            return Err(MeshError::TooManyVertices(vertex_count));
+        }
+        let per_primitive = match self.topology {
+            PrimitiveTopology::TriangleList => 3,
+            PrimitiveTopology::LineList => 2,
+        };
+        if !self.indices.len().is_multiple_of(per_primitive) {
+            return Err(MeshError::PartialPrimitive {
+                topology: self.topology,
+                indices: self.indices.len(),
+            });
+        }
+        if let Some(&index) = self
+            .indices
+            .iter()
+            .find(|&&index| index as usize >= vertex_count)
+        {
+            return Err(MeshError::IndexOutOfBounds {
+                index,
+                vertex_count,
+            });
+        }
+        for (attribute, bytes) in self.attributes.values() {
+            let count = bytes.len() / attribute.format.size() as usize;
+            if count != vertex_count {
+                return Err(MeshError::AttributeCount {
+                    name: attribute.name,
+                    count,
+                    vertex_count,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Gives every index its own vertex, copying the attributes along, so no two triangles
+    /// share one
+    pub fn duplicate_vertices(&mut self) -> Result<(), MeshError> {
+        self.validate()?;
+        if self.indices.len() > u16::MAX as usize + 1 {
+            return Err(MeshError::TooManyVertices(self.indices.len()));
+        }
+        self.positions = self
+            .indices
+            .iter()
+            .map(|&index| self.positions[index as usize])
+            .collect();
+        for (attribute, bytes) in self.attributes.values_mut() {
+            let size = attribute.format.size() as usize;
+            *bytes = self
+                .indices
+                .iter()
+                .flat_map(|&index| &bytes[index as usize * size..][..size])
+                .copied()
+                .collect();
+        }
+        self.indices = (0..self.indices.len() as u16).collect();
+        Ok(())
+    }
+
+    /// Duplicates the vertices and gives each the normal of its triangle, for a faceted look
+    pub fn compute_flat_normals(&mut self) -> Result<(), MeshError> {
+        self.require_triangles()?;
+        self.duplicate_vertices()?;
+        let normals: Vec<[f32; 3]> = self
+            .positions
+            .chunks_exact(3)
+            .flat_map(|triangle| {
+                let [a, b, c] = [0, 1, 2].map(|i| Vec3::from(triangle[i]));
+                [(b - a).cross(c - a).normalize_or_zero().to_array(); 3]
+            })
+            .collect();
+        self.set_attribute(MeshAttribute::NORMAL, &normals)
+    }
+
+    /// Averages the normals of the triangles sharing each vertex, weighted by their area
+    pub fn compute_smooth_normals(&mut self) -> Result<(), MeshError> {
+        self.require_triangles()?;
+        self.validate()?;
+        let mut normals = vec![Vec3::ZERO; self.positions.len()];
+        for triangle in self.indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| Vec3::from(self.positions[triangle[i] as usize]));
+            // Twice the area, in the direction of the normal
+            let weighted = (b - a).cross(c - a);
+            for &index in triangle {
+                normals[index as usize] += weighted;
+            }
+        }
+        let normals: Vec<[f32; 3]> = normals
+            .into_iter()
+            .map(|normal| normal.normalize_or_zero().to_array())
+            .collect();
+        self.set_attribute(MeshAttribute::NORMAL, &normals)
+    }
+
+    /// Tangents along increasing U, in the style of MikkTSpace: per-triangle tangents from the
+    /// UV derivatives are accumulated per vertex, then made orthogonal to the normal. Needs
+    /// normals and UVs.
+    pub fn generate_tangents(&mut self) -> Result<(), MeshError> {
+        self.require_triangles()?;
+        self.validate()?;
+        let normals: Vec<[f32; 3]> = self
+            .attribute_values(MeshAttribute::NORMAL)
+            .ok_or(MeshError::MissingAttribute(MeshAttribute::NORMAL.name))?;
+        let uvs: Vec<[f32; 2]> = self
+            .attribute_values(MeshAttribute::UV)
+            .ok_or(MeshError::MissingAttribute(MeshAttribute::UV.name))?;
+
+        let mut tangents = vec![Vec3::ZERO; self.positions.len()];
+        let mut bitangents = vec![Vec3::ZERO; self.positions.len()];
+        for triangle in self.indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| Vec3::from(self.positions[triangle[i] as usize]));
+            let [ta, tb, tc] = [0, 1, 2].map(|i| Vec2::from(uvs[triangle[i] as usize]));
+            let (edge1, edge2) = (b - a, c - a);
+            let (duv1, duv2) = (tb - ta, tc - ta);
+            let determinant = duv1.perp_dot(duv2);
+            if determinant.abs() <= f32::EPSILON {
+                continue;
+            }
+            // Keeping the triangle's area as the weight, like the normals
+            let tangent = (edge1 * duv2.y - edge2 * duv1.y) * determinant.signum();
+            let bitangent = (edge2 * duv1.x - edge1 * duv2.x) * determinant.signum();
+            for &index in triangle {
+                tangents[index as usize] += tangent;
+                bitangents[index as usize] += bitangent;
+            }
+        }
+
+        let tangents: Vec<[f32; 4]> = tangents
+            .into_iter()
+            .zip(bitangents)
+            .zip(normals)
+            .map(|((tangent, bitangent), normal)| {
+                let normal = Vec3::from(normal);
+                let mut tangent = (tangent - normal * normal.dot(tangent)).normalize_or_zero();
+                if tangent == Vec3::ZERO {
+                    tangent = normal.any_orthonormal_vector();
+                }
+                let handedness = if normal.cross(tangent).dot(bitangent) < 0.0 {
+                    -1.0
+                } else {
+                    1.0
+                };
+                tangent.extend(handedness).to_array()
+            })
+            .collect();
+        debug_assert!(tangents.iter().all(|t| Vec4::from(*t).is_finite()));
+        self.set_attribute(MeshAttribute::TANGENT, &tangents)
+    }
+
+    fn require_triangles(&self) -> Result<(), MeshError> {
+        match self.topology {
+            PrimitiveTopology::TriangleList => Ok(()),
+            topology => Err(MeshError::Topology(topology)),
+        }
+    }
+
+    fn set_attribute<T: Pod>(
+        &mut self,
+        attribute: MeshAttribute,
+        values: &[T],
+    ) -> Result<(), MeshError> {
+        Ok(self.insert_attribute(attribute, values)?)
+    }
+
+    /// Hash of everything that ends up on the GPU, so identical meshes, like the chunks of a
+    /// solid or empty region, can share buffers. Positions are hashed by their bits; compare
+    /// the meshes themselves before sharing, since different meshes may collide.
+    pub fn content_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.topology.hash(&mut hasher);
+        bytemuck::cast_slice::<_, u8>(&self.positions).hash(&mut hasher);
+        self.indices.hash(&mut hasher);
+        for (id, (_, values)) in &self.attributes {
+            id.hash(&mut hasher);
+            values.hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+
+    /// Zero for line meshes
+    pub fn triangle_count(&self) -> usize {
+        match self.topology {
+            PrimitiveTopology::TriangleList => self.indices.len() / 3,
+            PrimitiveTopology::LineList => 0,
+        }
+    }
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum MeshError {
+    #[error("index {index} is out of bounds for {vertex_count} vertices")]
+    IndexOutOfBounds { index: u16, vertex_count: usize },
+    #[error("{indices} indices don't make whole {topology:?} primitives")]
+    PartialPrimitive {
+        topology: PrimitiveTopology,
+        indices: usize,
+    },
+    #[error("{name} has {count} values for {vertex_count} vertices")]
+    AttributeCount {
+        name: &'static str,
+        count: usize,
+        vertex_count: usize,
+    },
+    #[error("{0} vertices don't fit in 16-bit indices")]
+    TooManyVertices(usize),
+    #[error("the mesh has no {0}")]
+    MissingAttribute(&'static str),
+    #[error("only triangle meshes have normals and tangents, not {0:?}")]
+    Topology(PrimitiveTopology),
+    #[error(transparent)]
+    Attribute(#[from] MeshAttributeError),
+}
+
+/// Chunk geometry with [`ChunkVertex`] vertices, a third the size of a [`Mesh`]'s positions and
+/// carrying normals besides
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QuantizedMesh {
+    pub vertices: Vec<ChunkVertex>,
+    pub indices: Vec<u16>,
+}
+
+impl QuantizedMesh {
+    pub fn vertex_bytes(&self) -> usize {
+        self.vertices.len() * ChunkVertex::UINT_LAYOUT.stride as usize
+    }
+
+    /// Decodes the positions, e.g. for a BLAS, which needs float vertices
+    pub fn to_mesh(&self) -> Mesh {
+        Mesh::new(
+            self.vertices
+                .iter()
+                .map(|vertex| vertex.position().as_vec3().to_array())
+                .collect(),
+            self.indices.clone(),
+        )
+    }
+}
+
+/// Meshes that instances can reference. Meshes are never removed, so handles stay valid.
+#[derive(Resource, Debug, Default)]
+pub struct Meshes {
+    meshes: Vec<Mesh>,
+}
+
+impl Meshes {
+    pub fn add(&mut self, mesh: Mesh) -> MeshHandle {
+        self.meshes.push(mesh);
+        MeshHandle(self.meshes.len() as u32 - 1)
+    }
+
+    pub fn get(&self, handle: MeshHandle) -> Option<&Mesh> {
+        self.meshes.get(handle.index())
+    }
+
+    pub fn len(&self) -> usize {
+        self.meshes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.meshes.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Mesh> {
+        self.meshes.iter()
+    }
+
+    pub fn iter_with_handles(&self) -> impl Iterator<Item = (MeshHandle, &Mesh)> {
+        self.meshes
+            .iter()
+            .enumerate()
+            .map(|(index, mesh)| (MeshHandle(index as u32), mesh))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generated_normals_and_tangents_match_the_shapes() {
+        let mut sphere = Mesh::uv_sphere(2.0, 24, 12);
+        let analytic: Vec<[f32; 3]> = sphere.attribute_values(Mesh::ATTRIBUTE_NORMAL).unwrap();
+        sphere.compute_smooth_normals().unwrap();
+        let smooth: Vec<[f32; 3]> = sphere.attribute_values(Mesh::ATTRIBUTE_NORMAL).unwrap();
+        // The seam is duplicated, so its vertices only see one side, and the poles are spread
+        // over many vertices; everywhere else the average is close to the real normal
+        let close = analytic
+            .iter()
+            .zip(&smooth)
+            .filter(|(a, b)| Vec3::from(**a).dot(Vec3::from(**b)) > 0.99)
+            .count();
+        assert!(
+            close * 10 >= analytic.len() * 8,
+            "{close} of {}",
+            analytic.len()
+        );
+
+        let mut plane = Mesh::plane(Vec2::ONE);
+        plane.generate_tangents().unwrap();
+        let tangents: Vec<[f32; 4]> = plane.attribute_values(Mesh::ATTRIBUTE_TANGENT).unwrap();
+        // U increases along +X and V along +Z, which is cross(-Y, +X)
+        assert!(tangents.iter().all(|t| *t == [1.0, 0.0, 0.0, 1.0]));
+
+        let mut cube = Mesh::cube(1.0);
+        cube.compute_flat_normals().unwrap();
+        assert_eq!(cube.positions.len(), 36);
+        assert_eq!(cube.indices.len(), 36);
+        assert_eq!(cube.validate(), Ok(()));
+    }
+
+    #[test]
+    fn identical_meshes_hash_the_same() {
+        let cube = Mesh::cube(1.0);
+        assert_eq!(cube.content_hash(), Mesh::cube(1.0).content_hash());
+        assert_ne!(cube.content_hash(), Mesh::cube(2.0).content_hash());
+
+        let mut flat = cube.clone();
+        flat.compute_flat_normals().unwrap();
+        let mut lines = cube.clone();
+        lines.topology = PrimitiveTopology::LineList;
+        assert_ne!(cube.content_hash(), flat.content_hash());
+        assert_ne!(cube.content_hash(), lines.content_hash());
+    }
+
+    #[test]
+    fn packed_vertices_follow_the_layout() {
+        let mut mesh = Mesh::new(vec![[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]], vec![]);
+        assert_eq!(mesh.vertex_layout(), Mesh::LAYOUT);
+        assert_eq!(
+            mesh.pack_interleaved(),
+            bytemuck::cast_slice::<_, u8>(&mesh.positions)
+        );
+
+        mesh.insert_attribute(Mesh::ATTRIBUTE_UV, &[[0.0f32, 0.5], [1.0, 0.5]])
+            .unwrap();
+        mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, &[[0.0f32, -1.0, 0.0]; 2])
+            .unwrap();
+        let layout = mesh.vertex_layout();
+        // Ordered by id, so the normal comes before the UV
+        assert_eq!(layout.stride, 12 + 12 + 8);
+        assert_eq!(layout.attributes[1].format, VertexFormat::Float32x3);
+        assert_eq!(layout.attributes[2].offset, 24);
+        let packed: Vec<f32> = mesh
+            .pack_interleaved()
+            .chunks_exact(4)
+            .map(bytemuck::pod_read_unaligned)
+            .collect();
+        assert_eq!(
+            packed,
+            [1.0, 2.0, 3.0, 0.0, -1.0, 0.0, 0.0, 0.5, 4.0, 5.0, 6.0, 0.0, -1.0, 0.0, 1.0, 0.5]
+        );
+    }
+
+    #[test]
+    fn validate_reports_broken_meshes() {
+        let mut mesh = Mesh::new(vec![[0.0; 3]; 3], vec![0, 1, 3]);
+        assert_eq!(
+            mesh.validate(),
+            Err(MeshError::IndexOutOfBounds {
+                index: 3,
+                vertex_count: 3
+            })
+        );
+        mesh.indices = vec![0, 1];
+        assert!(matches!(
+            mesh.validate(),
+            Err(MeshError::PartialPrimitive { indices: 2, .. })
+        ));
+        mesh.indices = vec![0, 1, 2];
+        assert_eq!(
+            mesh.generate_tangents(),
+            Err(MeshError::MissingAttribute("Vertex_Normal"))
+        );
+    }
+}
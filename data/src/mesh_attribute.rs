@@ -0,0 +1,204 @@
+use std::collections::BTreeMap;
+
+use bevy_ecs::system::Resource;
+use thiserror::Error;
+
+use crate::vertex::{VertexFormat, VertexLayout};
+
+/// Unique across built-in and registered attributes, so a mesh can hold any combination
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MeshAttributeId(u32);
+
+impl MeshAttributeId {
+    pub const fn get(&self) -> u32 {
+        self.0
+    }
+}
+
+/// A named per-vertex value a [`Mesh`](crate::mesh::Mesh) can carry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MeshAttribute {
+    pub name: &'static str,
+    pub id: MeshAttributeId,
+    pub format: VertexFormat,
+}
+
+impl MeshAttribute {
+    pub const POSITION: Self = Self::builtin("Vertex_Position", 0, VertexFormat::Float32x3);
+    pub const NORMAL: Self = Self::builtin("Vertex_Normal", 1, VertexFormat::Float32x3);
+    pub const COLOR: Self = Self::builtin("Vertex_Color", 2, VertexFormat::Float32x4);
+    pub const UV: Self = Self::builtin("Vertex_Uv", 3, VertexFormat::Float32x2);
+    /// `w` is the bitangent's sign: `bitangent = w * cross(normal, tangent.xyz)`
+    pub const TANGENT: Self = Self::builtin("Vertex_Tangent", 4, VertexFormat::Float32x4);
+    /// Up to four joints of the [`Skeleton`](crate::skinning::Skeleton) moving the vertex
+    pub const JOINTS: Self = Self::builtin("Vertex_Joints", 5, VertexFormat::Uint8x4);
+    /// How much each of [`Self::JOINTS`] moves the vertex, summing to one
+    pub const WEIGHTS: Self = Self::builtin("Vertex_Weights", 6, VertexFormat::Float32x4);
+
+    pub const BUILTIN: [Self; 7] = [
+        Self::POSITION,
+        Self::NORMAL,
+        Self::COLOR,
+        Self::UV,
+        Self::TANGENT,
+        Self::JOINTS,
+        Self::WEIGHTS,
+    ];
+
+    /// Ids below this are reserved for built-in attributes
+    pub const FIRST_CUSTOM_ID: u32 = 16;
+
+    const fn builtin(name: &'static str, id: u32, format: VertexFormat) -> Self {
+        Self {
+            name,
+            id: MeshAttributeId(id),
+            format,
+        }
+    }
+
+    /// Interleaves `attributes` in order, at locations counting up from zero. Pipelines list
+    /// the attributes their vertex shader reads.
+    pub fn vertex_layout(attributes: &[Self]) -> VertexLayout {
+        VertexLayout::interleaved(attributes.iter().map(|attribute| attribute.format))
+    }
+}
+
+/// Every attribute meshes may use: the built-in ones plus those registered by name, e.g. baked
+/// ambient occlusion or light levels
+#[derive(Resource, Debug, Clone)]
+pub struct MeshAttributes {
+    by_name: BTreeMap<&'static str, MeshAttribute>,
+    next_id: u32,
+}
+
+impl Default for MeshAttributes {
+    fn default() -> Self {
+        Self {
+            by_name: MeshAttribute::BUILTIN
+                .into_iter()
+                .map(|attribute| (attribute.name, attribute))
+                .collect(),
+            next_id: MeshAttribute::FIRST_CUSTOM_ID,
+        }
+    }
+}
+
+impl MeshAttributes {
+    /// Registering the same name and format again returns the existing attribute
+    pub fn register(
+        &mut self,
+        name: &'static str,
+        format: VertexFormat,
+    ) -> Result<MeshAttribute, MeshAttributeError> {
+        if let Some(existing) = self.by_name.get(name) {
+            return if existing.format == format {
+                Ok(*existing)
+            } else {
+                Err(MeshAttributeError::NameTaken {
+                    name,
+                    format: existing.format,
+                })
+            };
+        }
+        let attribute = MeshAttribute {
+            name,
+            id: MeshAttributeId(self.next_id),
+            format,
+        };
+        self.next_id += 1;
+        self.by_name.insert(name, attribute);
+        Ok(attribute)
+    }
+
+    pub fn get(&self, name: &str) -> Option<MeshAttribute> {
+        self.by_name.get(name).copied()
+    }
+
+    /// Ordered by id
+    pub fn iter(&self) -> impl Iterator<Item = MeshAttribute> {
+        let mut attributes: Vec<_> = self.by_name.values().copied().collect();
+        attributes.sort_by_key(|attribute| attribute.id);
+        attributes.into_iter()
+    }
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum MeshAttributeError {
+    #[error("mesh attribute {name} is already registered as {format:?}")]
+    NameTaken {
+        name: &'static str,
+        format: VertexFormat,
+    },
+    #[error("{name} values are {expected} bytes, not {actual}")]
+    WrongSize {
+        name: &'static str,
+        expected: u32,
+        actual: usize,
+    },
+    #[error("{name} has {actual} values for {expected} vertices")]
+    WrongCount {
+        name: &'static str,
+        expected: usize,
+        actual: usize,
+    },
+    #[error("positions are set through `Mesh::positions`")]
+    Position,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::Mesh;
+
+    #[test]
+    fn color_uv_and_custom_attributes_coexist() {
+        let mut registry = MeshAttributes::default();
+        let ao = registry
+            .register("Voxel_AO", VertexFormat::Float32)
+            .unwrap();
+        let light = registry
+            .register("Voxel_Light", VertexFormat::Uint8x4)
+            .unwrap();
+        assert_eq!(registry.register("Voxel_AO", VertexFormat::Float32), Ok(ao));
+        assert!(registry
+            .register("Voxel_AO", VertexFormat::Float32x2)
+            .is_err());
+        assert!(registry
+            .register("Vertex_Color", VertexFormat::Float32)
+            .is_err());
+
+        let ids: Vec<_> = registry.iter().map(|attribute| attribute.id).collect();
+        let mut unique = ids.clone();
+        unique.dedup();
+        assert_eq!(ids, unique);
+
+        let mut mesh = Mesh::new(vec![[0.0; 3]; 3], vec![0, 1, 2]);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, &[[1.0f32; 4]; 3])
+            .unwrap();
+        mesh.insert_attribute(Mesh::ATTRIBUTE_UV, &[[0.5f32; 2]; 3])
+            .unwrap();
+        mesh.insert_attribute(ao, &[0.25f32; 3]).unwrap();
+        mesh.insert_attribute(light, &[[15u8, 0, 0, 0]; 3]).unwrap();
+        assert_eq!(
+            mesh.insert_attribute(ao, &[0.0f32; 2]),
+            Err(MeshAttributeError::WrongCount {
+                name: "Voxel_AO",
+                expected: 3,
+                actual: 2
+            })
+        );
+        assert_eq!(
+            mesh.attribute_values::<[f32; 2]>(Mesh::ATTRIBUTE_UV),
+            Some(vec![[0.5; 2]; 3])
+        );
+
+        let layout = MeshAttribute::vertex_layout(&[Mesh::ATTRIBUTE_POSITION, ao, light]);
+        assert_eq!(layout.stride, 12 + 4 + 4);
+        assert_eq!(layout.attributes[2].location, 2);
+        assert_eq!(layout.attributes[2].offset, 16);
+        assert_eq!(
+            MeshAttribute::vertex_layout(&[Mesh::ATTRIBUTE_POSITION]),
+            Mesh::LAYOUT
+        );
+    }
+}
@@ -0,0 +1,205 @@
+use std::f32::consts::{PI, TAU};
+
+use glam::{Vec2, Vec3};
+
+use crate::{
+    math::Aabb,
+    mesh::{Mesh, PrimitiveTopology},
+    mesh_attribute::MeshAttribute,
+    Direction,
+};
+
+/// Built-in shapes for examples, debug visuals and prototyping props without assets. All are
+/// centered on the origin, wound counter-clockwise seen from outside, with normals and UVs.
+impl Mesh {
+    /// A cube with `size` long edges and one UV square per side
+    pub fn cube(size: f32) -> Self {
+        let mut positions = Vec::with_capacity(24);
+        let mut normals = Vec::with_capacity(24);
+        let mut uvs = Vec::with_capacity(24);
+        let mut indices = Vec::with_capacity(36);
+        for direction in Direction::ALL {
+            let normal = direction.offset().as_vec3();
+            let axis = normal.abs().max_position();
+            let (mut u, mut v) = (Vec3::ZERO, Vec3::ZERO);
+            u[(axis + 1) % 3] = 1.0;
+            v[(axis + 2) % 3] = 1.0;
+            // u x v points along +axis
+            if normal[axis] < 0.0 {
+                (u, v) = (v, u);
+            }
+
+            let base = positions.len() as u16;
+            for corner in [Vec2::ZERO, Vec2::X, Vec2::ONE, Vec2::Y] {
+                let position = normal + (corner.x * 2.0 - 1.0) * u + (corner.y * 2.0 - 1.0) * v;
+                positions.push((position * size / 2.0).to_array());
+                normals.push(normal.to_array());
+                uvs.push(corner.to_array());
+            }
+            indices.extend([0, 1, 2, 0, 2, 3].map(|i| base + i));
+        }
+        Self::new(positions, indices)
+            .with_generated(MeshAttribute::NORMAL, &normals)
+            .with_generated(MeshAttribute::UV, &uvs)
+    }
+
+    /// A rectangle in the XZ plane facing up (-Y), `size` long along X and Z
+    pub fn plane(size: Vec2) -> Self {
+        let half = size / 2.0;
+        let corners = [Vec2::ZERO, Vec2::X, Vec2::ONE, Vec2::Y];
+        let positions = corners
+            .map(|corner| {
+                [
+                    (corner.x * 2.0 - 1.0) * half.x,
+                    0.0,
+                    (corner.y * 2.0 - 1.0) * half.y,
+                ]
+            })
+            .to_vec();
+        Self::new(positions, vec![0, 1, 2, 0, 2, 3])
+            .with_generated(MeshAttribute::NORMAL, &[Vec3::NEG_Y.to_array(); 4])
+            .with_generated(MeshAttribute::UV, &corners.map(|corner| corner.to_array()))
+    }
+
+    /// A sphere split into `sectors` around the Y axis and `stacks` from pole to pole. U wraps
+    /// around once; V runs from the top (-Y) to the bottom pole.
+    pub fn uv_sphere(radius: f32, sectors: u16, stacks: u16) -> Self {
+        let (sectors, stacks) = (sectors.max(3), stacks.max(2));
+        let rows = stacks as usize + 1;
+        let columns = sectors as usize + 1;
+        debug_assert!(
+            rows * columns <= u16::MAX as usize,
+            "too many sphere vertices"
+        );
+
+        let mut positions = Vec::with_capacity(rows * columns);
+        let mut normals = Vec::with_capacity(rows * columns);
+        let mut uvs = Vec::with_capacity(rows * columns);
+        for stack in 0..=stacks {
+            let v = stack as f32 / stacks as f32;
+            let (ring, height) = (v * PI).sin_cos();
+            for sector in 0..=sectors {
+                // The seam is duplicated so the UVs can wrap from 1 back to 0
+                let u = sector as f32 / sectors as f32;
+                let (sin, cos) = (u * TAU).sin_cos();
+                let normal = Vec3::new(ring * cos, -height, ring * sin);
+                positions.push((normal * radius).to_array());
+                normals.push(normal.to_array());
+                uvs.push([u, v]);
+            }
+        }
+
+        let mut indices = Vec::with_capacity(sectors as usize * stacks as usize * 6);
+        for stack in 0..stacks {
+            for sector in 0..sectors {
+                let top = stack * (sectors + 1) + sector;
+                let bottom = top + sectors + 1;
+                // The quads touching a pole collapse to one triangle
+                if stack != 0 {
+                    indices.extend([top, bottom, top + 1]);
+                }
+                if stack != stacks - 1 {
+                    indices.extend([top + 1, bottom, bottom + 1]);
+                }
+            }
+        }
+        Self::new(positions, indices)
+            .with_generated(MeshAttribute::NORMAL, &normals)
+            .with_generated(MeshAttribute::UV, &uvs)
+    }
+
+    /// The twelve edges of `aabb` as a line list, e.g. to outline a selection. Lines can't be
+    /// ray traced, so this is for drawing only.
+    pub fn wire_box(aabb: Aabb) -> Self {
+        let positions = (0..8)
+            .map(|corner: u16| {
+                let pick = |bit: u16, axis: usize| {
+                    if corner & bit == 0 {
+                        aabb.min[axis]
+                    } else {
+                        aabb.max[axis]
+                    }
+                };
+                [pick(1, 0), pick(2, 1), pick(4, 2)]
+            })
+            .collect();
+        // Corners one bit apart share an edge
+        let indices = (0..8u16)
+            .flat_map(|corner| {
+                [1, 2, 4]
+                    .into_iter()
+                    .filter(move |bit| corner & bit == 0)
+                    .flat_map(move |bit| [corner, corner | bit])
+            })
+            .collect();
+        let mut mesh = Self::new(positions, indices);
+        mesh.topology = PrimitiveTopology::LineList;
+        mesh
+    }
+
+    /// For attributes whose counts are right by construction
+    fn with_generated<T: bytemuck::Pod>(mut self, attribute: MeshAttribute, values: &[T]) -> Self {
+        self.insert_attribute(attribute, values)
+            .expect("generated attributes match the positions");
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every triangle faces the way its vertex normals point, and every normal is unit length
+    fn assert_outward(mesh: &Mesh) {
+        let normals: Vec<[f32; 3]> = mesh.attribute_values(MeshAttribute::NORMAL).unwrap();
+        assert_eq!(normals.len(), mesh.positions.len());
+        assert!(normals
+            .iter()
+            .all(|normal| (Vec3::from(*normal).length() - 1.0).abs() < 1e-5));
+        for triangle in mesh.indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| Vec3::from(mesh.positions[triangle[i] as usize]));
+            let normal: Vec3 = triangle
+                .iter()
+                .map(|&i| Vec3::from(normals[i as usize]))
+                .sum();
+            assert!((b - a).cross(c - a).dot(normal) > 0.0, "{triangle:?}");
+        }
+    }
+
+    #[test]
+    fn shapes_face_outward() {
+        let cube = Mesh::cube(2.0);
+        assert_eq!(cube.triangle_count(), 12);
+        assert!(cube
+            .positions
+            .iter()
+            .all(|position| position.iter().all(|v| v.abs() == 1.0)));
+        assert_outward(&cube);
+
+        let plane = Mesh::plane(Vec2::new(4.0, 2.0));
+        assert_eq!(plane.positions[2], [2.0, 0.0, 1.0]);
+        assert_outward(&plane);
+
+        let sphere = Mesh::uv_sphere(3.0, 16, 8);
+        // Two triangles per quad, minus one per quad touching a pole
+        assert_eq!(sphere.triangle_count(), 16 * 8 * 2 - 2 * 16);
+        assert!(sphere
+            .positions
+            .iter()
+            .all(|position| (Vec3::from(*position).length() - 3.0).abs() < 1e-5));
+        assert_outward(&sphere);
+    }
+
+    #[test]
+    fn wire_box_has_twelve_edges() {
+        let wire = Mesh::wire_box(Aabb::new(Vec3::ZERO, Vec3::new(1.0, 2.0, 3.0)));
+        assert_eq!(wire.topology, PrimitiveTopology::LineList);
+        assert_eq!(wire.indices.len(), 24);
+        assert_eq!(wire.triangle_count(), 0);
+        for edge in wire.indices.chunks_exact(2) {
+            let [a, b] = [0, 1].map(|i| Vec3::from(wire.positions[edge[i] as usize]));
+            // Each edge runs along exactly one axis
+            assert_eq!((b - a).cmpne(Vec3::ZERO).bitmask().count_ones(), 1);
+        }
+    }
+}
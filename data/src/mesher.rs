@@ -0,0 +1,298 @@
+use glam::{IVec3, Vec3};
+
+use crate::{
+    chunk_map::ChunkNeighborhood,
+    mesh::{Mesh, QuantizedMesh},
+    vertex::ChunkVertex,
+    voxel::Voxel,
+    voxel_block::VoxelBlock,
+    Direction,
+};
+
+/// One visible side of a voxel, in chunk-local voxel coordinates
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Face {
+    pub position: IVec3,
+    pub direction: Direction,
+    pub voxel: Voxel,
+}
+
+/// Sides of the center chunk's voxels that aren't hidden by the voxel next to them. Sides
+/// against an unloaded neighbor are kept so the world never has holes; remesh the chunk once
+/// that neighbor loads (see [`ChunkMap::insert`](crate::chunk_map::ChunkMap::insert)) to drop
+/// them. Each chunk only emits its own voxels' faces, so seams are never doubled.
+pub fn visible_faces(neighborhood: &ChunkNeighborhood) -> Vec<Face> {
+    let width = VoxelBlock::WIDTH as i32;
+    let mut faces = Vec::new();
+    for y in 0..width {
+        for z in 0..width {
+            for x in 0..width {
+                let position = IVec3::new(x, y, z);
+                let voxel = neighborhood.center()[(x + z * width + y * width * width) as usize];
+                if voxel == Voxel::Air {
+                    continue;
+                }
+                for direction in Direction::ALL {
+                    let hidden = neighborhood
+                        .get(position + direction.offset())
+                        .is_some_and(|next| next.is_opaque() || next == voxel);
+                    if !hidden {
+                        faces.push(Face {
+                            position,
+                            direction,
+                            voxel,
+                        });
+                    }
+                }
+            }
+        }
+    }
+    faces
+}
+
+/// Two triangles per face, wound counter-clockwise seen from outside the voxel
+pub fn mesh_faces(faces: &[Face]) -> Mesh {
+    let mut positions = Vec::with_capacity(faces.len() * 4);
+    let mut indices = Vec::with_capacity(faces.len() * 6);
+    for face in faces {
+        let base = positions.len() as u16;
+        positions
+            .extend(face_corners(face.position, face.direction).map(|corner| corner.to_array()));
+        indices.extend([0, 1, 2, 0, 2, 3].map(|i| base + i));
+    }
+    Mesh::new(positions, indices)
+}
+
+/// Same geometry as [`mesh_faces`] with [`ChunkVertex`] vertices, which also carry the normal
+pub fn mesh_faces_quantized(faces: &[Face]) -> QuantizedMesh {
+    let mut vertices = Vec::with_capacity(faces.len() * 4);
+    let mut indices = Vec::with_capacity(faces.len() * 6);
+    for face in faces {
+        let base = vertices.len() as u16;
+        vertices.extend(
+            face_corners(face.position, face.direction)
+                .map(|corner| ChunkVertex::new(corner.as_uvec3(), face.direction)),
+        );
+        indices.extend([0, 1, 2, 0, 2, 3].map(|i| base + i));
+    }
+    QuantizedMesh { vertices, indices }
+}
+
+/// FNV-1a over the vertex positions' bits and the indices, for golden tests of mesher output
+pub fn mesh_hash(mesh: &Mesh) -> u64 {
+    let positions = mesh.positions.iter().flatten().map(|v| v.to_bits() as u64);
+    let indices = mesh.indices.iter().map(|i| *i as u64);
+    positions
+        .chain(indices)
+        .fold(0xcbf2_9ce4_8422_2325, |hash, value| {
+            (hash ^ value).wrapping_mul(0x0000_0100_0000_01b3)
+        })
+}
+
+fn face_corners(position: IVec3, direction: Direction) -> [Vec3; 4] {
+    let offset = direction.offset();
+    let axis = offset.abs().max_position();
+    let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+
+    let mut base = position.as_vec3();
+    if offset[axis] > 0 {
+        base[axis] += 1.0;
+    }
+    let (mut du, mut dv) = (Vec3::ZERO, Vec3::ZERO);
+    du[u] = 1.0;
+    dv[v] = 1.0;
+
+    // du x dv points along +axis
+    let mut corners = [base, base + du, base + du + dv, base + dv];
+    if offset[axis] < 0 {
+        corners.reverse();
+    }
+    corners
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{chunk_map::ChunkMap, voxel_block::VoxelBlockData};
+
+    fn filled(voxel: Voxel) -> VoxelBlockData {
+        Box::new([voxel; VoxelBlock::VOLUME as usize])
+    }
+
+    #[test]
+    fn seams_between_loaded_chunks_have_no_faces() {
+        let mut chunks = ChunkMap::default();
+        chunks.insert(IVec3::ZERO, filled(Voxel::Stone));
+        let faces = visible_faces(&chunks.neighborhood(IVec3::ZERO).unwrap());
+        // A lone chunk is closed off on every side
+        assert_eq!(faces.len(), 6 * VoxelBlock::AREA as usize);
+
+        let remesh = chunks.insert(IVec3::X, filled(Voxel::Stone));
+        assert_eq!(remesh, vec![IVec3::ZERO]);
+        let faces = visible_faces(&chunks.neighborhood(IVec3::ZERO).unwrap());
+        assert_eq!(faces.len(), 5 * VoxelBlock::AREA as usize);
+        assert!(faces.iter().all(|face| face.direction != Direction::Right));
+
+        // The neighbor doesn't emit the shared side either
+        let faces = visible_faces(&chunks.snapshot(IVec3::X).unwrap().neighborhood());
+        assert!(faces.iter().all(|face| face.direction != Direction::Left));
+    }
+
+    #[test]
+    fn faces_against_transparent_neighbors_are_kept() {
+        let mut chunks = ChunkMap::default();
+        chunks.insert(IVec3::ZERO, filled(Voxel::Stone));
+        chunks.insert(IVec3::NEG_Y, filled(Voxel::Air));
+        chunks.insert(IVec3::Y, filled(Voxel::Water));
+
+        let faces = visible_faces(&chunks.neighborhood(IVec3::ZERO).unwrap());
+        let up = faces.iter().filter(|face| face.direction == Direction::Up);
+        let down = faces
+            .iter()
+            .filter(|face| face.direction == Direction::Down);
+        assert_eq!(up.count(), VoxelBlock::AREA as usize);
+        assert_eq!(down.count(), VoxelBlock::AREA as usize);
+    }
+
+    /// A chunk of air with `voxel` wherever `pattern` returns true, meshed on its own
+    fn mesh_pattern(voxel: Voxel, pattern: impl Fn(IVec3) -> bool) -> Mesh {
+        let width = VoxelBlock::WIDTH as i32;
+        let mut data = filled(Voxel::Air);
+        for (i, slot) in data.iter_mut().enumerate() {
+            let i = i as i32;
+            let position = IVec3::new(i % width, i / (width * width), i / width % width);
+            if pattern(position) {
+                *slot = voxel;
+            }
+        }
+        let neighborhood = ChunkNeighborhood::new(&data, [None; 6]);
+        mesh_faces(&visible_faces(&neighborhood))
+    }
+
+    #[test]
+    fn single_voxel_mesh_is_exact() {
+        let at = IVec3::new(1, 2, 3);
+        let mesh = mesh_pattern(Voxel::Stone, |position| position == at);
+
+        // One quad per side in `Direction::ALL` order, corners wound outwards
+        let corners: [[i32; 3]; 24] = [
+            // Left
+            [0, 0, 1],
+            [0, 1, 1],
+            [0, 1, 0],
+            [0, 0, 0],
+            // Right
+            [1, 0, 0],
+            [1, 1, 0],
+            [1, 1, 1],
+            [1, 0, 1],
+            // Down (+Y)
+            [0, 1, 0],
+            [0, 1, 1],
+            [1, 1, 1],
+            [1, 1, 0],
+            // Up (-Y)
+            [1, 0, 0],
+            [1, 0, 1],
+            [0, 0, 1],
+            [0, 0, 0],
+            // Back
+            [0, 0, 1],
+            [1, 0, 1],
+            [1, 1, 1],
+            [0, 1, 1],
+            // Forward
+            [0, 1, 0],
+            [1, 1, 0],
+            [1, 0, 0],
+            [0, 0, 0],
+        ];
+        let positions: Vec<[f32; 3]> = corners
+            .iter()
+            .map(|corner| (IVec3::from_array(*corner) + at).as_vec3().to_array())
+            .collect();
+        let indices: Vec<u16> = (0..6)
+            .flat_map(|face| [0, 1, 2, 0, 2, 3].map(|i| face * 4 + i))
+            .collect();
+        assert_eq!(mesh.positions, positions);
+        assert_eq!(mesh.indices, indices);
+    }
+
+    /// (pattern, faces, hash). Only update a hash when a change to the mesher's output is
+    /// intended; a failing assertion prints the new value.
+    #[test]
+    fn patterns_match_golden_hashes() {
+        let width = VoxelBlock::WIDTH as i32;
+        let l_shape = [
+            IVec3::new(0, 1, 0),
+            IVec3::new(1, 1, 0),
+            IVec3::new(0, 0, 0),
+        ];
+        let cases: [(&str, Mesh, usize, u64); 4] = [
+            (
+                "single voxel",
+                mesh_pattern(Voxel::Stone, |position| position == IVec3::ZERO),
+                6,
+                0x0bee9a5b12d5d9a5,
+            ),
+            (
+                // Two shared sides are hidden
+                "L-shape",
+                mesh_pattern(Voxel::Stone, |position| l_shape.contains(&position)),
+                3 * 6 - 2 * 2,
+                0x832197bb8d7b9b65,
+            ),
+            (
+                // No two voxels share a side, so nothing is hidden
+                "checkerboard",
+                mesh_pattern(Voxel::Stone, |position| {
+                    (position.x + position.y + position.z) % 2 == 0
+                }),
+                VoxelBlock::VOLUME as usize / 2 * 6,
+                0x3a5d98c998cea325,
+            ),
+            (
+                // Only the outer shell
+                "full chunk",
+                mesh_pattern(Voxel::Stone, |_| true),
+                6 * (width * width) as usize,
+                0x6d54bfea30115325,
+            ),
+        ];
+        for (name, mesh, faces, hash) in cases {
+            assert_eq!(mesh.positions.len(), faces * 4, "{name}");
+            assert_eq!(mesh.indices.len(), faces * 6, "{name}");
+            let actual = mesh_hash(&mesh);
+            assert_eq!(actual, hash, "{name}: mesh hash is {actual:#018x}");
+        }
+    }
+
+    #[test]
+    fn quantized_meshes_decode_to_the_same_geometry() {
+        let faces = visible_faces(&ChunkNeighborhood::new(&filled(Voxel::Stone), [None; 6]));
+        let mesh = mesh_faces(&faces);
+        let quantized = mesh_faces_quantized(&faces);
+
+        assert_eq!(quantized.to_mesh().positions, mesh.positions);
+        assert_eq!(quantized.indices, mesh.indices);
+        for (corners, face) in quantized.vertices.chunks_exact(4).zip(&faces) {
+            assert!(corners
+                .iter()
+                .all(|vertex| vertex.direction() == face.direction));
+        }
+        // A third of the float positions alone, which don't even carry normals
+        assert_eq!(
+            mesh.positions.len() * std::mem::size_of::<[f32; 3]>(),
+            3 * quantized.vertex_bytes()
+        );
+    }
+
+    #[test]
+    fn quads_face_outwards() {
+        for direction in Direction::ALL {
+            let [a, b, c, _] = face_corners(IVec3::ZERO, direction);
+            let normal = (b - a).cross(c - a).normalize();
+            assert_eq!(normal, direction.offset().as_vec3());
+        }
+    }
+}
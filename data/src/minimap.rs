@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+
+use bevy_ecs::system::Resource;
+use glam::{IVec2, IVec3};
+
+use crate::{
+    chunk_map::{chunk_of, ChunkMap},
+    voxel::Voxel,
+    voxel_block::VoxelBlock,
+};
+
+/// The top of one column of voxels as seen from above
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MapColumn {
+    /// World y of the topmost voxel that isn't air. Up is -Y, so lower is higher.
+    pub y: i32,
+    pub voxel: Voxel,
+}
+
+/// One [`MapColumn`] per column of a chunk-wide square, x fastest, then z
+type MapTile = Box<[Option<MapColumn>; VoxelBlock::AREA as usize]>;
+
+/// What the player has explored of the world from above, one [`MapColumn`] per column of
+/// every chunk that was ever loaded. Unlike the [`ChunkMap`] it keeps unloaded chunks, and
+/// only covers the loaded chunks it was surveyed against, so a column keeps the highest
+/// surface seen until a survey reaching as high finds it changed.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct Minimap {
+    /// By chunk x and z
+    tiles: HashMap<IVec2, MapTile>,
+}
+
+impl Minimap {
+    /// The column at world `x` and `z`, or `None` if it was never surveyed or only air was
+    /// loaded there
+    pub fn column(&self, x: i32, z: i32) -> Option<MapColumn> {
+        let width = VoxelBlock::WIDTH as i32;
+        let tile = self
+            .tiles
+            .get(&IVec2::new(x, z).div_euclid(IVec2::splat(width)))?;
+        tile[index(x.rem_euclid(width), z.rem_euclid(width))]
+    }
+
+    /// Surveys every column of the loaded `chunk`, e.g. after it was generated
+    pub fn survey_chunk(&mut self, chunks: &ChunkMap, chunk: IVec3) {
+        let width = VoxelBlock::WIDTH as i32;
+        for z in 0..width {
+            for x in 0..width {
+                let corner = chunk * width;
+                self.survey_column(chunks, IVec3::new(corner.x + x, corner.y, corner.z + z));
+            }
+        }
+    }
+
+    /// Surveys the column of the world voxel `position` through the loaded chunks stacked
+    /// above and below it, e.g. after the voxel changed
+    pub fn survey_column(&mut self, chunks: &ChunkMap, position: IVec3) {
+        let width = VoxelBlock::WIDTH as i32;
+        let chunk = chunk_of(position);
+        if !chunks.contains(chunk) {
+            return;
+        }
+        let stacked = |y: i32| chunks.contains(IVec3::new(chunk.x, y, chunk.z));
+        let top = (i32::MIN..chunk.y)
+            .rev()
+            .take_while(|y| stacked(*y))
+            .last()
+            .unwrap_or(chunk.y);
+        let bottom = (chunk.y + 1..).take_while(|y| stacked(*y)).last();
+        let surveyed = top * width..(bottom.unwrap_or(chunk.y) + 1) * width;
+
+        let found = surveyed.clone().find_map(|y| {
+            let voxel = chunks.voxel(IVec3::new(position.x, y, position.z))?;
+            (voxel != Voxel::Air).then_some(MapColumn { y, voxel })
+        });
+        let tile = self
+            .tiles
+            .entry(IVec2::new(chunk.x, chunk.z))
+            .or_insert_with(|| Box::new([None; VoxelBlock::AREA as usize]));
+        let slot = &mut tile[index(position.x.rem_euclid(width), position.z.rem_euclid(width))];
+        // A survey starting below the known surface can't tell whether it's still there
+        let covers_known = slot.is_none_or(|known| surveyed.start <= known.y);
+        match found {
+            Some(found) if covers_known || slot.is_some_and(|known| found.y < known.y) => {
+                *slot = Some(found);
+            }
+            None if slot.is_some_and(|known| surveyed.contains(&known.y)) => *slot = None,
+            _ => {}
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.tiles.clear();
+    }
+}
+
+fn index(x: i32, z: i32) -> usize {
+    (x + z * VoxelBlock::WIDTH as i32) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn columns_keep_the_highest_surface_seen() {
+        let width = VoxelBlock::WIDTH as i32;
+        let mut chunks = ChunkMap::default();
+        // Two chunks stacked, with grass at y = 4 in the upper one and stone below it
+        chunks.load_around(IVec3::ZERO, 0, |_| {
+            Box::new([Voxel::Stone; VoxelBlock::VOLUME as usize])
+        });
+        chunks.load_around(IVec3::new(0, -1, 0), 0, |_| {
+            let mut data = Box::new([Voxel::Air; VoxelBlock::VOLUME as usize]);
+            data[4 * width as usize * width as usize..].fill(Voxel::Grass);
+            data
+        });
+
+        let mut minimap = Minimap::default();
+        minimap.survey_chunk(&chunks, IVec3::ZERO);
+        let grass = Some(MapColumn {
+            y: 4 - width,
+            voxel: Voxel::Grass,
+        });
+        assert_eq!(minimap.column(3, 5), grass);
+        assert_eq!(minimap.column(-1, 5), None);
+
+        // Surveyed against the lower chunk alone, the grass above can't be seen
+        chunks.remove(IVec3::new(0, -1, 0));
+        minimap.survey_chunk(&chunks, IVec3::ZERO);
+        assert_eq!(minimap.column(3, 5), grass);
+
+        chunks.set_voxel(IVec3::new(3, 0, 5), Voxel::Air);
+        minimap.survey_column(&chunks, IVec3::new(3, 0, 5));
+        assert_eq!(minimap.column(3, 5), grass);
+
+        chunks.load_around(IVec3::new(0, -1, 0), 0, |_| {
+            let mut data = Box::new([Voxel::Air; VoxelBlock::VOLUME as usize]);
+            data[4 * width as usize * width as usize..].fill(Voxel::Grass);
+            data
+        });
+        chunks.set_voxel(IVec3::new(3, 4 - width, 5), Voxel::Air);
+        minimap.survey_column(&chunks, IVec3::new(3, 4 - width, 5));
+        assert_eq!(minimap.column(3, 5).map(|column| column.y), Some(5 - width));
+    }
+}
@@ -0,0 +1,29 @@
+use std::{borrow::Cow, fmt};
+
+use bevy_ecs::component::Component;
+use serde::{Deserialize, Serialize};
+
+/// Human-readable label for an entity, shown by debug tooling
+#[derive(Component, Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Name(Cow<'static, str>);
+
+impl Name {
+    pub fn new(name: impl Into<Cow<'static, str>>) -> Self {
+        Self(name.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn set(&mut self, name: impl Into<Cow<'static, str>>) {
+        self.0 = name.into();
+    }
+}
+
+impl fmt::Display for Name {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
@@ -0,0 +1,170 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+use bevy_ecs::system::Resource;
+
+/// Sent but not yet shown
+type Inbox = Arc<Mutex<Vec<(Severity, String)>>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    /// Seconds a notification stays up; worse news stays longer
+    pub const fn lifetime_secs(&self) -> f32 {
+        match self {
+            Self::Info => 4.0,
+            Self::Warning => 6.0,
+            Self::Error => 10.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notification {
+    pub severity: Severity,
+    pub message: String,
+    /// Seconds it has been shown for
+    pub age: f32,
+}
+
+impl Notification {
+    /// From 0 when it appears to 1 when it expires
+    pub fn progress(&self) -> f32 {
+        (self.age / self.severity.lifetime_secs()).clamp(0.0, 1.0)
+    }
+}
+
+/// Messages about engine events, e.g. a photo or the world being saved, shown to the player as
+/// toasts until they expire. Background tasks send theirs through a [`NotificationSender`],
+/// and they show up on the next [`Self::update`].
+#[derive(Resource, Debug, Default)]
+pub struct Notifications {
+    inbox: Inbox,
+    /// Oldest first
+    shown: VecDeque<Notification>,
+}
+
+impl Notifications {
+    /// Notifications shown at once; older ones are dropped early to make room
+    pub const MAX_SHOWN: usize = 5;
+
+    pub fn sender(&self) -> NotificationSender {
+        NotificationSender {
+            inbox: Some(self.inbox.clone()),
+        }
+    }
+
+    /// A sender to `notifications` if there are any, e.g. in a headless app, or one that only
+    /// logs
+    pub fn sender_or_log(notifications: Option<&Self>) -> NotificationSender {
+        notifications.map(Self::sender).unwrap_or_default()
+    }
+
+    /// Logs `message` and shows it from the next [`Self::update`]
+    pub fn push(&self, severity: Severity, message: impl Into<String>) {
+        self.sender().send(severity, message);
+    }
+
+    /// Shows what was sent since the last update, and ages and expires the rest by `delta`
+    /// seconds
+    pub fn update(&mut self, delta: f32) {
+        for notification in &mut self.shown {
+            notification.age += delta;
+        }
+        self.shown
+            .retain(|notification| notification.age < notification.severity.lifetime_secs());
+
+        let sent = std::mem::take(&mut *self.inbox.lock().unwrap());
+        self.shown
+            .extend(sent.into_iter().map(|(severity, message)| Notification {
+                severity,
+                message,
+                age: 0.0,
+            }));
+        while self.shown.len() > Self::MAX_SHOWN {
+            self.shown.pop_front();
+        }
+    }
+
+    /// Oldest first
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &Notification> {
+        self.shown.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.shown.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shown.is_empty()
+    }
+}
+
+/// Sends to [`Notifications`] from anywhere, including other threads
+#[derive(Debug, Clone, Default)]
+pub struct NotificationSender {
+    /// `None` for a sender that only logs
+    inbox: Option<Inbox>,
+}
+
+impl NotificationSender {
+    /// Logs `message` to stdout, or stderr for warnings and errors, and shows it as a toast
+    pub fn send(&self, severity: Severity, message: impl Into<String>) {
+        let message = message.into();
+        match severity {
+            Severity::Info => println!("{message}"),
+            Severity::Warning | Severity::Error => eprintln!("{message}"),
+        }
+        if let Some(inbox) = &self.inbox {
+            inbox.lock().unwrap().push((severity, message));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn notifications_expire_by_severity() {
+        let mut notifications = Notifications::default();
+        let sender = notifications.sender();
+        std::thread::spawn(move || sender.send(Severity::Error, "disk full"))
+            .join()
+            .unwrap();
+        notifications.push(Severity::Info, "saved");
+        assert!(notifications.is_empty());
+
+        notifications.update(0.0);
+        assert_eq!(notifications.len(), 2);
+        notifications.update(Severity::Info.lifetime_secs());
+        assert_eq!(
+            notifications
+                .iter()
+                .map(|notification| notification.message.as_str())
+                .collect::<Vec<_>>(),
+            ["disk full"]
+        );
+
+        for _ in 0..Notifications::MAX_SHOWN {
+            notifications.push(Severity::Warning, "chunk failed");
+        }
+        notifications.update(0.0);
+        assert_eq!(notifications.len(), Notifications::MAX_SHOWN);
+        assert!(notifications
+            .iter()
+            .all(|notification| notification.severity == Severity::Warning));
+
+        // Logging alone never shows anything
+        Notifications::sender_or_log(None).send(Severity::Info, "headless");
+        notifications.update(Severity::Error.lifetime_secs());
+        assert!(notifications.is_empty());
+    }
+}
@@ -0,0 +1,220 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap, VecDeque},
+};
+
+use bevy_ecs::{component::Component, entity::Entity};
+use glam::IVec3;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    block_tick::GameTick,
+    chunk_map::ChunkMap,
+    transform::{PreviousTransform, Transform},
+};
+
+/// A mob or villager walking the voxels on its own. Positions are standing voxels: the air
+/// voxel its feet are in, with another free one above for its head and an opaque one below.
+#[derive(Component, Debug, Clone)]
+#[require(Transform, PreviousTransform)]
+pub struct Npc {
+    pub behavior: Behavior,
+    /// Standing voxels left to walk through, the next one first
+    pub path: VecDeque<IVec3>,
+    /// Voxels walked per second
+    pub speed: f32,
+}
+
+impl Default for Npc {
+    fn default() -> Self {
+        Self {
+            behavior: Behavior::Idle { until: GameTick(0) },
+            path: VecDeque::new(),
+            speed: 2.0,
+        }
+    }
+}
+
+/// What an [`Npc`] is doing, reconsidered every tick
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Behavior {
+    /// Standing still until the tick
+    Idle { until: GameTick },
+    /// Walking to a standing voxel picked near where it was
+    Wander { goal: IVec3 },
+    /// Following the entity, repathing as it moves
+    Chase { target: Entity },
+}
+
+/// Makes an [`Npc`] chase the player once within `sight` voxels, and give up once further
+/// than `give_up`
+#[derive(Component, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[require(Npc)]
+#[serde(default)]
+pub struct Hostile {
+    pub sight: f32,
+    pub give_up: f32,
+}
+
+impl Default for Hostile {
+    fn default() -> Self {
+        Self {
+            sight: 16.0,
+            give_up: 24.0,
+        }
+    }
+}
+
+/// Whether an NPC can stand with its feet in the voxel at `position`. Unloaded voxels are never
+/// walkable, so NPCs stay in the loaded world.
+pub fn is_walkable(chunks: &ChunkMap, position: IVec3) -> bool {
+    let free = |position| chunks.is_solid(position) == Some(false);
+    // Up is -Y
+    free(position)
+        && free(position + IVec3::NEG_Y)
+        && chunks.is_solid(position + IVec3::Y) == Some(true)
+}
+
+/// The highest standing voxel in the column at `x` and `z` within `range` voxels of `y`, e.g.
+/// to spawn on or wander to
+pub fn standing_voxel(chunks: &ChunkMap, x: i32, z: i32, y: i32, range: i32) -> Option<IVec3> {
+    (y - range..=y + range)
+        .map(|y| IVec3::new(x, y, z))
+        .find(|position| is_walkable(chunks, *position))
+}
+
+/// Standing voxels an NPC can step to from `position`: one voxel along X or Z on the same
+/// level, one up with room to climb, or one down
+fn neighbors(chunks: &ChunkMap, position: IVec3) -> impl Iterator<Item = IVec3> + '_ {
+    let headroom = chunks.is_solid(position + 2 * IVec3::NEG_Y) == Some(false);
+    [IVec3::X, IVec3::NEG_X, IVec3::Z, IVec3::NEG_Z]
+        .into_iter()
+        .flat_map(move |step| {
+            let next = position + step;
+            [
+                Some(next),
+                headroom.then_some(next + IVec3::NEG_Y),
+                Some(next + IVec3::Y),
+            ]
+        })
+        .flatten()
+        .filter(|next| is_walkable(chunks, *next))
+}
+
+fn distance(from: IVec3, to: IVec3) -> u32 {
+    let d = (to - from).abs();
+    (d.x + d.y + d.z) as u32
+}
+
+/// Searches a walk from the standing voxel `from` to `to` with A*, weighted towards the goal
+/// so it settles for a longer walk rather than visiting every voxel around an obstacle. Gives up
+/// after visiting `max_visited` voxels, returning the walk to the one nearest the goal so
+/// far, so far away goals are still approached. The walk leaves out `from`, and is `None` if
+/// `from` isn't walkable or no step brings the NPC closer.
+pub fn find_path(
+    chunks: &ChunkMap,
+    from: IVec3,
+    to: IVec3,
+    max_visited: usize,
+) -> Option<VecDeque<IVec3>> {
+    /// How much the distance left outweighs the distance walked
+    const GREED: u32 = 2;
+
+    if !is_walkable(chunks, from) {
+        return None;
+    }
+    let mut came_from = HashMap::from([(from, (from, 0))]);
+    let mut open = BinaryHeap::from([Reverse((GREED * distance(from, to), from.to_array()))]);
+    let mut nearest = (distance(from, to), from);
+    let mut visited = 0;
+    while let Some(Reverse((_, position))) = open.pop() {
+        let position = IVec3::from_array(position);
+        if distance(position, to) < nearest.0 {
+            nearest = (distance(position, to), position);
+        }
+        if position == to || visited == max_visited {
+            break;
+        }
+        visited += 1;
+
+        let walked = came_from[&position].1 + 1;
+        for next in neighbors(chunks, position) {
+            if came_from
+                .get(&next)
+                .is_some_and(|(_, known)| *known <= walked)
+            {
+                continue;
+            }
+            came_from.insert(next, (position, walked));
+            open.push(Reverse((
+                walked + GREED * distance(next, to),
+                next.to_array(),
+            )));
+        }
+    }
+
+    let (_, mut position) = nearest;
+    if position == from {
+        return None;
+    }
+    let mut path = VecDeque::new();
+    while position != from {
+        path.push_front(position);
+        position = came_from[&position].0;
+    }
+    Some(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{voxel::Voxel, voxel_block::VoxelBlock};
+
+    use super::*;
+
+    #[test]
+    fn paths_climb_over_walls() {
+        let width = VoxelBlock::WIDTH as i32;
+        let mut chunks = ChunkMap::default();
+        chunks.load_around(IVec3::ZERO, 1, |_| {
+            Box::new([Voxel::Air; VoxelBlock::VOLUME as usize])
+        });
+        // A floor under y = 4, a one voxel step at x = 4 and a wall too high to climb at x = 8
+        for z in 0..width {
+            for x in 0..width {
+                chunks.set_voxel(IVec3::new(x, 5, z), Voxel::Stone);
+            }
+            chunks.set_voxel(IVec3::new(4, 4, z), Voxel::Stone);
+            if z != 12 {
+                chunks.set_voxel(IVec3::new(8, 4, z), Voxel::Stone);
+                chunks.set_voxel(IVec3::new(8, 3, z), Voxel::Stone);
+            }
+        }
+
+        let from = IVec3::new(1, 4, 2);
+        assert!(is_walkable(&chunks, from));
+        assert!(!is_walkable(&chunks, IVec3::new(1, 3, 2)));
+        assert_eq!(
+            standing_voxel(&chunks, 4, 2, 0, 8),
+            Some(IVec3::new(4, 3, 2))
+        );
+
+        let to = IVec3::new(10, 4, 2);
+        let path = find_path(&chunks, from, to, 4096).unwrap();
+        assert_eq!(path.back(), Some(&to));
+        // Through the gap in the wall, one step at a time
+        assert!(path.contains(&IVec3::new(8, 4, 12)));
+        let mut previous = from;
+        for position in &path {
+            assert!(is_walkable(&chunks, *position));
+            let step = (*position - previous).abs();
+            assert_eq!(step.x + step.z, 1);
+            assert!(step.y <= 1);
+            previous = *position;
+        }
+
+        // Out of budget, it walks towards the goal
+        let partial = find_path(&chunks, from, to, 4).unwrap();
+        assert!(distance(*partial.back().unwrap(), to) < distance(from, to));
+        assert_eq!(find_path(&chunks, IVec3::new(1, 2, 2), to, 4096), None);
+    }
+}
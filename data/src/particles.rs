@@ -0,0 +1,283 @@
+use std::cmp::Ordering;
+
+use bevy_ecs::system::Resource;
+use glam::{IVec3, Vec3};
+
+use crate::{chunk_map::ChunkMap, rng::RngStream};
+
+/// Voxels per second squared, towards +Y, which is down
+pub const GRAVITY: f32 = 20.0;
+/// Fraction of its speed a particle keeps when it bounces off a voxel
+const RESTITUTION: f32 = 0.3;
+/// Bounces slower than this, in voxels per second, leave the particle resting instead
+const REST_SPEED: f32 = 0.5;
+/// Fraction of its sideways speed a resting particle loses per second
+const GROUND_FRICTION: f32 = 8.0;
+
+/// A small cube of debris or dust, simulated on the CPU and collided against voxels as a point
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Particle {
+    /// Relative to the [`FloatingOrigin`](crate::floating_origin::FloatingOrigin), like entity
+    /// translations
+    pub position: Vec3,
+    /// Voxels per second
+    pub velocity: Vec3,
+    /// sRGB
+    pub color: [u8; 3],
+    /// Edge length in voxels when spawned; particles shrink to nothing over their lifetime
+    pub size: f32,
+    /// Seconds
+    pub age: f32,
+    pub lifetime: f32,
+    /// Multiplier on [`GRAVITY`], low for dust that drifts down
+    pub gravity: f32,
+    /// Fraction of its speed lost per second to the air
+    pub drag: f32,
+    /// Vanish on hitting a voxel instead of bouncing, like rain
+    pub expires_on_impact: bool,
+}
+
+impl Particle {
+    pub fn current_size(&self) -> f32 {
+        self.size * (1.0 - self.age / self.lifetime).clamp(0.0, 1.0)
+    }
+}
+
+/// A pool of at most `capacity` particles. Emitting into a full pool replaces the particle
+/// nearest the end of its lifetime, so bursts never allocate once the pool has grown.
+#[derive(Resource, Debug, Clone)]
+pub struct Particles {
+    particles: Vec<Particle>,
+    capacity: usize,
+    /// Spreads bursts; particles are cosmetic, so it isn't seeded from the world
+    random: RngStream,
+}
+
+impl Default for Particles {
+    fn default() -> Self {
+        Self::with_capacity(4096)
+    }
+}
+
+impl Particles {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            particles: Vec::with_capacity(capacity),
+            capacity,
+            random: RngStream::new(0x853c_49e6_748f_ea9b),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.particles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.particles.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Particle> {
+        self.particles.iter()
+    }
+
+    pub fn emit(&mut self, particle: Particle) {
+        if self.particles.len() < self.capacity {
+            self.particles.push(particle);
+            return;
+        }
+        let Some(oldest) = self.particles.iter_mut().max_by(|a, b| {
+            (a.age / a.lifetime)
+                .partial_cmp(&(b.age / b.lifetime))
+                .unwrap_or(Ordering::Equal)
+        }) else {
+            return;
+        };
+        *oldest = particle;
+    }
+
+    /// `count` copies of `particle`, scattered through the voxel whose minimum corner is at
+    /// `corner` and flung outwards at up to `speed`, with their lifetimes cut by up to half
+    pub fn burst(&mut self, corner: Vec3, count: usize, particle: Particle, speed: f32) {
+        for _ in 0..count {
+            let offset = Vec3::new(
+                self.random.next_f32(),
+                self.random.next_f32(),
+                self.random.next_f32(),
+            );
+            let direction = (offset - 0.5).normalize_or_zero();
+            let velocity = particle.velocity + direction * speed * self.random.next_f32();
+            let lifetime = particle.lifetime * (0.5 + self.random.next_f32() * 0.5);
+            self.emit(Particle {
+                position: corner + offset,
+                velocity,
+                lifetime,
+                ..particle
+            });
+        }
+    }
+
+    /// `count` copies of `particle` placed evenly at random in the box from `min` to `max`
+    pub fn scatter(&mut self, min: Vec3, max: Vec3, count: usize, particle: Particle) {
+        for _ in 0..count {
+            let along = Vec3::new(
+                self.random.next_f32(),
+                self.random.next_f32(),
+                self.random.next_f32(),
+            );
+            self.emit(Particle {
+                position: min + (max - min) * along,
+                ..particle
+            });
+        }
+    }
+
+    /// Ages, moves and collides every particle, dropping the expired ones. Voxels are looked
+    /// up relative to the floating `origin`, and unloaded chunks don't block.
+    pub fn step(&mut self, delta: f32, chunks: &ChunkMap, origin: IVec3) {
+        let solid = |position: Vec3| {
+            chunks
+                .is_solid(position.floor().as_ivec3() + origin)
+                .unwrap_or(false)
+        };
+        self.particles.retain_mut(|particle| {
+            particle.age += delta;
+            if particle.age >= particle.lifetime {
+                return false;
+            }
+            particle.velocity.y += GRAVITY * particle.gravity * delta;
+            particle.velocity *= (1.0 - particle.drag * delta).max(0.0);
+
+            // One axis at a time, so a particle sliding along a wall keeps its other motion
+            let mut grounded = false;
+            for axis in 0..3 {
+                let mut moved = particle.position;
+                moved[axis] += particle.velocity[axis] * delta;
+                if !solid(moved) {
+                    particle.position = moved;
+                    continue;
+                }
+                if particle.expires_on_impact {
+                    return false;
+                }
+                grounded |= axis == 1 && particle.velocity.y > 0.0;
+                particle.velocity[axis] *= -RESTITUTION;
+                if particle.velocity[axis].abs() < REST_SPEED {
+                    particle.velocity[axis] = 0.0;
+                }
+            }
+            if grounded {
+                let friction = (1.0 - GROUND_FRICTION * delta).max(0.0);
+                particle.velocity.x *= friction;
+                particle.velocity.z *= friction;
+            }
+            true
+        });
+    }
+
+    /// Moves every particle along with the floating origin, see
+    /// [`FloatingOrigin::rebase`](crate::floating_origin::FloatingOrigin::rebase)
+    pub fn shift(&mut self, shift: Vec3) {
+        for particle in &mut self.particles {
+            particle.position -= shift;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.particles.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{voxel::Voxel, voxel_block::VoxelBlock};
+
+    fn debris(position: Vec3) -> Particle {
+        Particle {
+            position,
+            velocity: Vec3::ZERO,
+            color: [128; 3],
+            size: 0.1,
+            age: 0.0,
+            lifetime: 2.0,
+            gravity: 1.0,
+            drag: 0.0,
+            expires_on_impact: false,
+        }
+    }
+
+    #[test]
+    fn particles_fall_onto_voxels_and_expire() {
+        let width = VoxelBlock::WIDTH as i32;
+        let mut chunks = ChunkMap::default();
+        // Stone from y = 8 down (deeper is +Y), air above
+        chunks.load_around(IVec3::ZERO, 0, |_| {
+            let mut data = Box::new([Voxel::Air; VoxelBlock::VOLUME as usize]);
+            for (i, voxel) in data.iter_mut().enumerate() {
+                if i as i32 / (width * width) >= 8 {
+                    *voxel = Voxel::Stone;
+                }
+            }
+            data
+        });
+
+        let mut particles = Particles::default();
+        particles.emit(debris(Vec3::new(4.5, 2.5, 4.5)));
+        for _ in 0..60 {
+            particles.step(1.0 / 60.0, &chunks, IVec3::ZERO);
+        }
+        let particle = particles.iter().next().unwrap();
+        assert!((7.5..8.0).contains(&particle.position.y), "{particle:?}");
+        assert_eq!(particle.position.x, 4.5);
+
+        // The origin moved a chunk up, so the particle rests on the same stone a chunk lower
+        let origin = IVec3::new(0, -width, 0);
+        particles.shift(origin.as_vec3());
+        particles.step(1.0 / 60.0, &chunks, origin);
+        let position = particles.iter().next().unwrap().position;
+        assert!((7.5..8.0).contains(&(position.y - width as f32)));
+
+        for _ in 0..60 {
+            particles.step(1.0 / 60.0, &chunks, origin);
+        }
+        assert!(particles.is_empty());
+
+        particles.scatter(
+            Vec3::new(0.0, 6.0, 0.0),
+            Vec3::new(16.0, 7.0, 16.0),
+            10,
+            Particle {
+                expires_on_impact: true,
+                ..debris(Vec3::ZERO)
+            },
+        );
+        assert_eq!(particles.len(), 10);
+        // Long enough to fall the two voxels, well short of the lifetime
+        for _ in 0..60 {
+            particles.step(1.0 / 60.0, &chunks, IVec3::ZERO);
+        }
+        assert!(particles.is_empty());
+    }
+
+    #[test]
+    fn full_pools_replace_the_oldest_particle() {
+        let mut particles = Particles::with_capacity(2);
+        particles.emit(Particle {
+            age: 1.5,
+            ..debris(Vec3::ZERO)
+        });
+        particles.emit(debris(Vec3::ONE));
+        particles.burst(Vec3::splat(8.0), 1, debris(Vec3::ZERO), 1.0);
+        assert_eq!(particles.len(), 2);
+        let positions: Vec<_> = particles.iter().map(|particle| particle.position).collect();
+        assert_eq!(positions[1], Vec3::ONE);
+        assert!(
+            positions[0].cmpge(Vec3::splat(8.0)).all()
+                && positions[0].cmplt(Vec3::splat(9.0)).all()
+        );
+    }
+}
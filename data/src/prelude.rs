@@ -0,0 +1,9 @@
+//! The math types the engine's crates share, so they don't depend on glam themselves and its
+//! precision can later be changed in one place
+
+pub use glam::{
+    BVec2, BVec3, BVec4, DVec3, EulerRot, IVec2, IVec3, IVec4, Mat2, Mat3, Mat4, Quat, UVec2,
+    UVec3, UVec4, Vec2, Vec3, Vec3A, Vec4,
+};
+
+pub use crate::{math::Aabb, transform::Transform, Direction};
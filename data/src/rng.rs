@@ -0,0 +1,123 @@
+use bevy_ecs::system::Resource;
+use glam::IVec3;
+
+use crate::{
+    block_tick::GameTick,
+    worldgen::{splitmix64, WorldSeed},
+};
+
+/// Hands out random number streams derived from the [`WorldSeed`], so everything random in
+/// the simulation happens the same way on every run of a world. Each user asks for its own
+/// stream by a label, and splits it further by chunk, tick or entity, so adding a roll in one
+/// system doesn't shift the rolls of another. Labels are hashed with FNV-1a, which unlike
+/// `std::hash` is stable across Rust versions and platforms.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Rng {
+    seed: u64,
+}
+
+impl Rng {
+    pub fn new(seed: WorldSeed) -> Self {
+        Self {
+            seed: splitmix64(seed.0),
+        }
+    }
+
+    /// The stream of the system or feature called `label`
+    pub fn stream(&self, label: &str) -> RngStream {
+        RngStream::new(self.seed ^ fnv1a(label.as_bytes()))
+    }
+
+    /// `label`'s stream for one chunk
+    pub fn chunk(&self, label: &str, chunk: IVec3) -> RngStream {
+        let [x, y, z] = chunk.to_array().map(|c| c as u32 as u64);
+        self.stream(label).split(x).split(y).split(z)
+    }
+
+    /// `label`'s stream for one tick
+    pub fn tick(&self, label: &str, tick: GameTick) -> RngStream {
+        self.stream(label).split(tick.0)
+    }
+}
+
+/// A splitmix64 generator. Cheap to create, so streams are usually made for one decision and
+/// dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RngStream {
+    state: u64,
+}
+
+impl RngStream {
+    pub const fn new(state: u64) -> Self {
+        Self { state }
+    }
+
+    /// An independent stream keyed by `key`, e.g. an entity's bits, leaving this one as it was
+    pub fn split(&self, key: u64) -> Self {
+        Self::new(splitmix64(self.state ^ splitmix64(key)))
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        splitmix64(self.state)
+    }
+
+    /// Uniform in `[0, 1)`
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// Uniform in `[min, max]`. Panics if `min > max`.
+    pub fn range(&mut self, min: u64, max: u64) -> u64 {
+        assert!(min <= max, "empty range {min}..={max}");
+        match (max - min).checked_add(1) {
+            Some(len) => min + self.next_u64() % len,
+            // Every u64
+            None => self.next_u64(),
+        }
+    }
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn streams_depend_only_on_seed_and_key() {
+        let rng = Rng::new(WorldSeed(7));
+        let rolls = |mut stream: RngStream| (0..4).map(|_| stream.next_u64()).collect::<Vec<_>>();
+        assert_eq!(
+            rolls(rng.tick("weather", GameTick(3))),
+            rolls(Rng::new(WorldSeed(7)).tick("weather", GameTick(3)))
+        );
+        assert_ne!(
+            rolls(rng.tick("weather", GameTick(3))),
+            rolls(rng.tick("weather", GameTick(4)))
+        );
+        assert_ne!(rolls(rng.stream("weather")), rolls(rng.stream("npc")));
+        assert_ne!(
+            rolls(rng.stream("npc")),
+            rolls(Rng::new(WorldSeed(8)).stream("npc"))
+        );
+        assert_ne!(
+            rolls(rng.chunk("trees", IVec3::new(1, 0, 0))),
+            rolls(rng.chunk("trees", IVec3::new(0, 1, 0)))
+        );
+
+        let mut stream = rng.stream("range");
+        for _ in 0..100 {
+            assert!((3..=5).contains(&stream.range(3, 5)));
+            assert!((0.0..1.0).contains(&stream.next_f32()));
+        }
+        assert_eq!(stream.range(9, 9), 9);
+        // The full range has no length that fits in a u64
+        let mut full = stream;
+        assert_eq!(full.range(0, u64::MAX), stream.next_u64());
+    }
+}
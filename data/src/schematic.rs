@@ -0,0 +1,268 @@
+use glam::{IVec3, UVec3};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    chunk_map::ChunkMap,
+    edit_history::VoxelEdit,
+    voxel::{Voxel, VoxelId},
+    voxel_block::{encode_rle, Rle},
+};
+
+/// Box of world voxel positions between two corners, both included
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Region {
+    pub min: IVec3,
+    pub max: IVec3,
+}
+
+impl Region {
+    pub fn from_corners(a: IVec3, b: IVec3) -> Self {
+        Self {
+            min: a.min(b),
+            max: a.max(b),
+        }
+    }
+
+    pub fn size(&self) -> UVec3 {
+        (self.max - self.min + 1).as_uvec3()
+    }
+
+    pub fn volume(&self) -> u64 {
+        self.size().as_u64vec3().element_product()
+    }
+
+    pub fn contains(&self, position: IVec3) -> bool {
+        position.cmpge(self.min).all() && position.cmple(self.max).all()
+    }
+
+    /// Every position in the same order as [`Schematic`] voxels: x fastest, then z, then y
+    pub fn positions(&self) -> impl Iterator<Item = IVec3> {
+        let Self { min, max } = *self;
+        (min.y..=max.y).flat_map(move |y| {
+            (min.z..=max.z).flat_map(move |z| (min.x..=max.x).map(move |x| IVec3::new(x, y, z)))
+        })
+    }
+}
+
+/// Voxels copied out of a [`Region`], to be pasted elsewhere or shared as a
+/// [`SchematicFile`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Schematic {
+    size: UVec3,
+    /// x fastest, then z, then y
+    voxels: Vec<Voxel>,
+}
+
+impl Schematic {
+    /// `None` if any of the region's chunks isn't loaded
+    pub fn copy(chunks: &ChunkMap, region: Region) -> Option<Self> {
+        Some(Self {
+            size: region.size(),
+            voxels: region
+                .positions()
+                .map(|position| chunks.voxel(position))
+                .collect::<Option<_>>()?,
+        })
+    }
+
+    pub const fn size(&self) -> UVec3 {
+        self.size
+    }
+
+    pub fn get(&self, position: UVec3) -> Voxel {
+        let index = position.x + position.z * self.size.x + position.y * self.size.x * self.size.z;
+        self.voxels[index as usize]
+    }
+
+    /// Cut down to the smallest box around its non-air voxels, or `None` if it's all air
+    pub fn trimmed(&self) -> Option<Self> {
+        let full = Region::from_corners(IVec3::ZERO, self.size.as_ivec3() - 1);
+        let (min, max) = full
+            .positions()
+            .filter(|position| self.get(position.as_uvec3()) != Voxel::Air)
+            .fold(None, |bounds: Option<(IVec3, IVec3)>, position| {
+                Some(bounds.map_or((position, position), |(min, max)| {
+                    (min.min(position), max.max(position))
+                }))
+            })?;
+        let region = Region { min, max };
+        Some(Self {
+            size: region.size(),
+            voxels: region
+                .positions()
+                .map(|position| self.get(position.as_uvec3()))
+                .collect(),
+        })
+    }
+
+    /// Turned a quarter turn about the vertical axis `quarter_turns` times, +X towards +Z
+    pub fn rotated(&self, quarter_turns: u8) -> Self {
+        let mut rotated = self.clone();
+        for _ in 0..quarter_turns % 4 {
+            let size = UVec3::new(rotated.size.z, rotated.size.y, rotated.size.x);
+            let voxels = Region::from_corners(IVec3::ZERO, size.as_ivec3() - 1)
+                .positions()
+                .map(|position| {
+                    let position = position.as_uvec3();
+                    rotated.get(UVec3::new(position.z, position.y, size.x - 1 - position.x))
+                })
+                .collect();
+            rotated = Self { size, voxels };
+        }
+        rotated
+    }
+
+    /// Writes every voxel, air included, with the minimum corner at `min`. Returns the
+    /// voxels that changed; those in unloaded chunks are skipped.
+    pub fn paste(&self, chunks: &mut ChunkMap, min: IVec3) -> Vec<VoxelEdit> {
+        let region = Region {
+            min,
+            max: min + self.size.as_ivec3() - 1,
+        };
+        region
+            .positions()
+            .zip(self.voxels.iter().copied())
+            .filter_map(|(position, new)| {
+                let old = chunks.set_voxel(position, new)?;
+                (old != new).then_some(VoxelEdit { position, old, new })
+            })
+            .collect()
+    }
+}
+
+/// Sets every loaded voxel in the region to `voxel`, returning the voxels that changed
+pub fn fill(chunks: &mut ChunkMap, region: Region, voxel: Voxel) -> Vec<VoxelEdit> {
+    replace_where(chunks, region, voxel, |old| old != voxel)
+}
+
+/// Turns every `from` voxel in the region into `to`, returning the voxels that changed
+pub fn replace(chunks: &mut ChunkMap, region: Region, from: Voxel, to: Voxel) -> Vec<VoxelEdit> {
+    if from == to {
+        return Vec::new();
+    }
+    replace_where(chunks, region, to, |old| old == from)
+}
+
+fn replace_where(
+    chunks: &mut ChunkMap,
+    region: Region,
+    new: Voxel,
+    filter: impl Fn(Voxel) -> bool,
+) -> Vec<VoxelEdit> {
+    region
+        .positions()
+        .filter_map(|position| {
+            let old = chunks.voxel(position).filter(|old| filter(*old))?;
+            chunks.set_voxel(position, new);
+            Some(VoxelEdit { position, old, new })
+        })
+        .collect()
+}
+
+/// Serialized form of a [`Schematic`], with the voxels run-length encoded
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SchematicFile {
+    pub version: u32,
+    pub size: UVec3,
+    pub voxels: Vec<Rle>,
+}
+
+impl SchematicFile {
+    pub const VERSION: u32 = 1;
+}
+
+impl From<&Schematic> for SchematicFile {
+    fn from(schematic: &Schematic) -> Self {
+        Self {
+            version: Self::VERSION,
+            size: schematic.size,
+            voxels: encode_rle(schematic.voxels.iter().copied()),
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum SchematicError {
+    #[error(
+        "unsupported schematic version {0}, expected {expected}",
+        expected = SchematicFile::VERSION
+    )]
+    UnsupportedVersion(u32),
+    #[error("invalid voxel ID: {0}")]
+    InvalidVoxelId(VoxelId),
+    #[error("schematic has {actual} voxels but its size holds {expected}")]
+    WrongVolume { expected: u64, actual: u64 },
+}
+
+impl TryFrom<SchematicFile> for Schematic {
+    type Error = SchematicError;
+
+    fn try_from(file: SchematicFile) -> Result<Self, Self::Error> {
+        if file.version != SchematicFile::VERSION {
+            return Err(SchematicError::UnsupportedVersion(file.version));
+        }
+        let expected = file.size.as_u64vec3().element_product();
+        let actual = file.voxels.iter().map(|(count, _)| *count as u64).sum();
+        if actual != expected {
+            return Err(SchematicError::WrongVolume { expected, actual });
+        }
+        let mut voxels = Vec::with_capacity(expected as usize);
+        for (count, id) in file.voxels {
+            let voxel = Voxel::ALL
+                .get(id as usize)
+                .ok_or(SchematicError::InvalidVoxelId(id))?;
+            voxels.extend((0..count).map(|_| *voxel));
+        }
+        Ok(Self {
+            size: file.size,
+            voxels,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::voxel_block::VoxelBlock;
+
+    use super::*;
+
+    #[test]
+    fn copied_builds_paste_rotated_and_round_trip_through_a_file() {
+        let mut chunks = ChunkMap::default();
+        chunks.load_around(IVec3::ZERO, 0, |_| {
+            Box::new([Voxel::Air; VoxelBlock::VOLUME as usize])
+        });
+        // A row of stone along x, with grass beside its far end
+        let region = Region::from_corners(IVec3::new(3, 0, 1), IVec3::new(1, 0, 0));
+        assert_eq!(region.size(), UVec3::new(3, 1, 2));
+        fill(&mut chunks, region, Voxel::Dirt);
+        assert_eq!(
+            replace(&mut chunks, region, Voxel::Dirt, Voxel::Stone).len(),
+            6
+        );
+        chunks.set_voxel(IVec3::new(1, 0, 1), Voxel::Air);
+        chunks.set_voxel(IVec3::new(2, 0, 1), Voxel::Air);
+        chunks.set_voxel(IVec3::new(3, 0, 1), Voxel::Grass);
+
+        let schematic = Schematic::copy(&chunks, region).unwrap();
+        let rotated = schematic.rotated(1);
+        assert_eq!(rotated.size(), UVec3::new(2, 1, 3));
+        assert_eq!(rotated.get(UVec3::new(0, 0, 2)), Voxel::Grass);
+        assert_eq!(schematic.rotated(4), schematic);
+
+        let changed = rotated.paste(&mut chunks, IVec3::new(8, 0, 8));
+        assert_eq!(changed.len(), 4);
+        assert_eq!(chunks.voxel(IVec3::new(8, 0, 10)), Some(Voxel::Grass));
+        assert_eq!(chunks.voxel(IVec3::new(9, 0, 8)), Some(Voxel::Stone));
+
+        let file = SchematicFile::from(&schematic);
+        assert_eq!(file.voxels.len(), 3);
+        assert_eq!(Schematic::try_from(file).unwrap(), schematic);
+        // Outside any loaded chunk
+        assert_eq!(
+            Schematic::copy(&chunks, Region::from_corners(IVec3::NEG_ONE, IVec3::ZERO)),
+            None
+        );
+    }
+}
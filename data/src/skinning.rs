@@ -0,0 +1,365 @@
+use std::sync::Arc;
+
+use bevy_ecs::component::Component;
+use glam::{Mat4, Quat, Vec3, Vec4};
+use thiserror::Error;
+
+use crate::{
+    mesh::{Mesh, MeshError},
+    mesh_attribute::MeshAttribute,
+    transform::Transform,
+};
+
+/// One bone of a [`Skeleton`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Joint {
+    /// Index of the joint this one hangs from, `None` for a root
+    pub parent: Option<usize>,
+    /// Relative to the parent, or to the mesh for a root, in the pose the mesh was modeled in
+    pub rest: Transform,
+}
+
+/// Joints a mesh is skinned to through its [`MeshAttribute::JOINTS`] and
+/// [`MeshAttribute::WEIGHTS`], ordered so every parent comes before its children
+#[derive(Debug, Clone, PartialEq)]
+pub struct Skeleton {
+    joints: Vec<Joint>,
+    /// Takes a vertex from the mesh into the space of each joint at rest
+    inverse_bind: Vec<Mat4>,
+}
+
+impl Skeleton {
+    /// Joint indices are a byte per vertex
+    pub const MAX_JOINTS: usize = u8::MAX as usize + 1;
+
+    pub fn new(joints: Vec<Joint>) -> Result<Self, SkinError> {
+        if joints.len() > Self::MAX_JOINTS {
+            return Err(SkinError::TooManyJoints(joints.len()));
+        }
+        if let Some((joint, parent)) =
+            joints
+                .iter()
+                .enumerate()
+                .find_map(|(joint, Joint { parent, .. })| {
+                    parent.filter(|p| *p >= joint).map(|p| (joint, p))
+                })
+        {
+            return Err(SkinError::ParentAfterChild { joint, parent });
+        }
+        let mut skeleton = Self {
+            joints,
+            inverse_bind: Vec::new(),
+        };
+        skeleton.inverse_bind = skeleton
+            .object_space(&skeleton.rest_pose())
+            .iter()
+            .map(Mat4::inverse)
+            .collect();
+        Ok(skeleton)
+    }
+
+    pub fn joints(&self) -> &[Joint] {
+        &self.joints
+    }
+
+    pub fn rest_pose(&self) -> Vec<Transform> {
+        self.joints.iter().map(|joint| joint.rest).collect()
+    }
+
+    /// Every joint of `pose`, given relative to the parents like [`Joint::rest`], relative to
+    /// the mesh instead. Joints missing from `pose` stay at rest.
+    fn object_space(&self, pose: &[Transform]) -> Vec<Mat4> {
+        let mut matrices: Vec<Mat4> = Vec::with_capacity(self.joints.len());
+        for (index, joint) in self.joints.iter().enumerate() {
+            let local = pose.get(index).unwrap_or(&joint.rest).to_mat4();
+            let matrix = match joint.parent {
+                Some(parent) => matrices[parent] * local,
+                None => local,
+            };
+            matrices.push(matrix);
+        }
+        matrices
+    }
+
+    /// Per joint, takes a vertex of the mesh from the rest pose to `pose`, see [`skin_mesh`]
+    pub fn joint_matrices(&self, pose: &[Transform]) -> Vec<Mat4> {
+        self.object_space(pose)
+            .iter()
+            .zip(&self.inverse_bind)
+            .map(|(object_space, inverse_bind)| *object_space * *inverse_bind)
+            .collect()
+    }
+}
+
+/// A joint's rotation at a point in an [`AnimationClip`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Keyframe {
+    /// Seconds from the start of the clip
+    pub time: f32,
+    /// Relative to the parent, replacing the rest rotation
+    pub rotation: Quat,
+}
+
+/// Keyframes of one joint, ordered by time
+#[derive(Debug, Clone, PartialEq)]
+pub struct Channel {
+    pub joint: usize,
+    pub keyframes: Vec<Keyframe>,
+}
+
+/// Looping rotations of some joints of a [`Skeleton`], e.g. legs swinging in a walk. Joints
+/// without a channel stay at rest.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationClip {
+    /// Seconds before the clip loops
+    pub duration: f32,
+    pub channels: Vec<Channel>,
+}
+
+impl AnimationClip {
+    /// The pose `time` seconds in, blending between the keyframes around it. The last
+    /// keyframe of a channel blends into its first one as the clip loops.
+    pub fn sample(&self, skeleton: &Skeleton, time: f32) -> Vec<Transform> {
+        let mut pose = skeleton.rest_pose();
+        let time = if self.duration > 0.0 {
+            time.rem_euclid(self.duration)
+        } else {
+            0.0
+        };
+        for channel in &self.channels {
+            let (Some(joint), Some(first), Some(last)) = (
+                pose.get_mut(channel.joint),
+                channel.keyframes.first(),
+                channel.keyframes.last(),
+            ) else {
+                continue;
+            };
+            let next = channel
+                .keyframes
+                .iter()
+                .position(|keyframe| keyframe.time > time);
+            let (from, to, to_time) = match next {
+                Some(0) => (last, first, first.time),
+                Some(next) => (
+                    &channel.keyframes[next - 1],
+                    &channel.keyframes[next],
+                    channel.keyframes[next].time,
+                ),
+                None => (last, first, first.time + self.duration),
+            };
+            // Wrapped around the loop when `from` is later in the clip than `time`
+            let from_time = if from.time > time {
+                from.time - self.duration
+            } else {
+                from.time
+            };
+            let span = to_time - from_time;
+            let t = if span > 0.0 {
+                ((time - from_time) / span).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            joint.rotation = from.rotation.slerp(to.rotation, t);
+        }
+        pose
+    }
+}
+
+/// Deforms the entity's [`Instance`](crate::instance::Instance) mesh by a [`Skeleton`] in
+/// `pose`, one transform per joint relative to its parent
+#[derive(Component, Debug, Clone)]
+pub struct Skin {
+    pub skeleton: Arc<Skeleton>,
+    pub pose: Vec<Transform>,
+}
+
+impl Skin {
+    /// At rest, so the mesh is drawn as modeled
+    pub fn new(skeleton: Arc<Skeleton>) -> Self {
+        let pose = skeleton.rest_pose();
+        Self { skeleton, pose }
+    }
+}
+
+/// Plays a clip on the entity's [`Skin`]
+#[derive(Component, Debug, Clone)]
+pub struct Animator {
+    pub clip: Arc<AnimationClip>,
+    /// Seconds into the clip
+    pub time: f32,
+    /// Multiplier on time, e.g. to walk faster
+    pub speed: f32,
+}
+
+impl Animator {
+    pub fn new(clip: Arc<AnimationClip>) -> Self {
+        Self {
+            clip,
+            time: 0.0,
+            speed: 1.0,
+        }
+    }
+
+    /// Advances the clip by `delta` seconds and poses `skin` at the new time
+    pub fn advance(&mut self, delta: f32, skin: &mut Skin) {
+        self.time =
+            (self.time + delta * self.speed).rem_euclid(self.clip.duration.max(f32::EPSILON));
+        skin.pose = self.clip.sample(&skin.skeleton, self.time);
+    }
+}
+
+/// A copy of `mesh` with every vertex moved by its [`MeshAttribute::JOINTS`] by
+/// [`MeshAttribute::WEIGHTS`], from [`Skeleton::joint_matrices`]. Normals and tangents are
+/// turned along, and the other attributes are kept as they are.
+pub fn skin_mesh(mesh: &Mesh, joint_matrices: &[Mat4]) -> Result<Mesh, SkinError> {
+    let joints = mesh
+        .attribute_values::<[u8; 4]>(MeshAttribute::JOINTS)
+        .ok_or(MeshError::MissingAttribute(MeshAttribute::JOINTS.name))?;
+    let weights = mesh
+        .attribute_values::<[f32; 4]>(MeshAttribute::WEIGHTS)
+        .ok_or(MeshError::MissingAttribute(MeshAttribute::WEIGHTS.name))?;
+    let skinning: Vec<Mat4> = joints
+        .iter()
+        .zip(&weights)
+        .map(|(joints, weights)| {
+            joints
+                .iter()
+                .zip(weights)
+                .filter(|(_, weight)| **weight != 0.0)
+                .try_fold(Mat4::ZERO, |sum, (&joint, &weight)| {
+                    let matrix =
+                        joint_matrices
+                            .get(joint as usize)
+                            .ok_or(SkinError::JointOutOfRange {
+                                joint,
+                                count: joint_matrices.len(),
+                            })?;
+                    Ok(sum + *matrix * weight)
+                })
+        })
+        .collect::<Result<_, SkinError>>()?;
+
+    let mut skinned = mesh.clone();
+    skinned.positions = mesh
+        .positions
+        .iter()
+        .zip(&skinning)
+        .map(|(position, matrix)| matrix.transform_point3(Vec3::from(*position)).to_array())
+        .collect();
+    if let Some(normals) = mesh.attribute_values::<[f32; 3]>(MeshAttribute::NORMAL) {
+        let normals: Vec<[f32; 3]> = normals
+            .iter()
+            .zip(&skinning)
+            .map(|(normal, matrix)| {
+                matrix
+                    .transform_vector3(Vec3::from(*normal))
+                    .normalize_or_zero()
+                    .to_array()
+            })
+            .collect();
+        skinned
+            .insert_attribute(MeshAttribute::NORMAL, &normals)
+            .map_err(MeshError::from)?;
+    }
+    if let Some(tangents) = mesh.attribute_values::<[f32; 4]>(MeshAttribute::TANGENT) {
+        let tangents: Vec<[f32; 4]> = tangents
+            .iter()
+            .zip(&skinning)
+            .map(|(tangent, matrix)| {
+                let tangent = Vec4::from(*tangent);
+                matrix
+                    .transform_vector3(tangent.truncate())
+                    .normalize_or_zero()
+                    .extend(tangent.w)
+                    .to_array()
+            })
+            .collect();
+        skinned
+            .insert_attribute(MeshAttribute::TANGENT, &tangents)
+            .map_err(MeshError::from)?;
+    }
+    Ok(skinned)
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum SkinError {
+    #[error("{0} joints don't fit in 8-bit joint indices")]
+    TooManyJoints(usize),
+    #[error("joint {joint} comes before its parent {parent}")]
+    ParentAfterChild { joint: usize, parent: usize },
+    #[error("a vertex is skinned to joint {joint} of {count}")]
+    JointOutOfRange { joint: u8, count: usize },
+    #[error(transparent)]
+    Mesh(#[from] MeshError),
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use super::*;
+
+    #[test]
+    fn vertices_follow_their_joints() {
+        // A root at the origin and an elbow one voxel down +X from it
+        let skeleton = Skeleton::new(vec![
+            Joint {
+                parent: None,
+                rest: Transform::default(),
+            },
+            Joint {
+                parent: Some(0),
+                rest: Transform::from_xyz(1.0, 0.0, 0.0),
+            },
+        ])
+        .unwrap();
+        let mut mesh = Mesh::new(vec![[0.5, 0.0, 0.0], [2.0, 0.0, 0.0]], Vec::new());
+        mesh.insert_attribute(Mesh::ATTRIBUTE_JOINTS, &[[0u8, 0, 0, 0], [1, 0, 0, 0]])
+            .unwrap();
+        mesh.insert_attribute(
+            Mesh::ATTRIBUTE_WEIGHTS,
+            &[[1.0f32, 0.0, 0.0, 0.0], [1.0, 0.0, 0.0, 0.0]],
+        )
+        .unwrap();
+
+        let at_rest = skin_mesh(&mesh, &skeleton.joint_matrices(&skeleton.rest_pose())).unwrap();
+        assert_eq!(at_rest.positions, mesh.positions);
+
+        // Bending the elbow a quarter turn a quarter of the way through the clip
+        let clip = AnimationClip {
+            duration: 2.0,
+            channels: vec![Channel {
+                joint: 1,
+                keyframes: vec![
+                    Keyframe {
+                        time: 0.0,
+                        rotation: Quat::IDENTITY,
+                    },
+                    Keyframe {
+                        time: 1.0,
+                        rotation: Quat::from_rotation_z(FRAC_PI_2),
+                    },
+                ],
+            }],
+        };
+        let bent = skin_mesh(
+            &mesh,
+            &skeleton.joint_matrices(&clip.sample(&skeleton, 1.0)),
+        )
+        .unwrap();
+        assert_eq!(bent.positions[0], mesh.positions[0]);
+        assert!(Vec3::from(bent.positions[1]).abs_diff_eq(Vec3::new(1.0, 1.0, 0.0), 1e-5));
+        // Looping back to rest through the second half
+        assert!(clip.sample(&skeleton, 2.0)[1]
+            .rotation
+            .abs_diff_eq(Quat::IDENTITY, 1e-5));
+        assert!(clip.sample(&skeleton, 1.5)[1]
+            .rotation
+            .abs_diff_eq(Quat::from_rotation_z(FRAC_PI_2 * 0.5), 1e-5));
+
+        assert_eq!(
+            skin_mesh(&mesh, &[Mat4::IDENTITY]),
+            Err(SkinError::JointOutOfRange { joint: 1, count: 1 })
+        );
+    }
+}
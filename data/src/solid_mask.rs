@@ -0,0 +1,56 @@
+use crate::voxel_block::{VoxelBlock, VoxelBlockData};
+
+const WORDS: usize = VoxelBlock::VOLUME as usize / u64::BITS as usize;
+
+/// One bit per voxel of a chunk, set for the opaque ones, in the same order as
+/// [`VoxelBlock`]. Collision and raycasts test these bits rather than reading voxels, and
+/// chunks with no solid voxels can be told apart without looking at any.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SolidMask {
+    words: [u64; WORDS],
+    solid: u32,
+}
+
+impl SolidMask {
+    pub fn new(data: &VoxelBlockData) -> Self {
+        let mut words = [0; WORDS];
+        for (word, voxels) in words.iter_mut().zip(data.chunks_exact(u64::BITS as usize)) {
+            *word = voxels
+                .iter()
+                .enumerate()
+                .filter(|(_, voxel)| voxel.is_opaque())
+                .fold(0, |word, (bit, _)| word | 1 << bit);
+        }
+        Self {
+            words,
+            solid: words.iter().map(|word| word.count_ones()).sum(),
+        }
+    }
+
+    /// Whether the voxel at `index` in the chunk is opaque
+    pub fn get(&self, index: usize) -> bool {
+        self.words[index / u64::BITS as usize] >> (index % u64::BITS as usize) & 1 == 1
+    }
+
+    pub fn set(&mut self, index: usize, solid: bool) {
+        if self.get(index) == solid {
+            return;
+        }
+        self.words[index / u64::BITS as usize] ^= 1 << (index % u64::BITS as usize);
+        if solid {
+            self.solid += 1;
+        } else {
+            self.solid -= 1;
+        }
+    }
+
+    /// No opaque voxels at all, e.g. a chunk of sky
+    pub const fn is_empty(&self) -> bool {
+        self.solid == 0
+    }
+
+    /// Opaque throughout, e.g. a chunk deep underground
+    pub const fn is_full(&self) -> bool {
+        self.solid == VoxelBlock::VOLUME
+    }
+}
@@ -0,0 +1,111 @@
+use bevy_ecs::component::Component;
+use glam::{IVec3, Vec3};
+use serde::{Deserialize, Serialize};
+
+use crate::{chunk_map::ChunkMap, transform::Transform};
+
+/// Places the camera behind its entity for a third-person view. The arm reaches from the
+/// entity to `shoulder_offset` plus `length` behind it, and shortens whenever terrain is in
+/// the way so the camera never ends up inside a wall.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpringArm {
+    /// How far behind the entity the camera sits when nothing is in the way
+    pub length: f32,
+    /// Offset of the arm's end in the entity's local space, e.g. +X to look over the right
+    /// shoulder
+    pub shoulder_offset: Vec3,
+    /// How quickly the arm extends back out once terrain stops blocking it, per second.
+    /// Pulling in is immediate.
+    pub smoothing: f32,
+    /// Distance kept from the blocking surface, so the near plane doesn't clip into it
+    pub margin: f32,
+    /// Current reach along the arm, from 0 at the entity to 1 fully extended
+    #[serde(skip)]
+    extension: f32,
+}
+
+impl Default for SpringArm {
+    fn default() -> Self {
+        Self {
+            length: 4.0,
+            shoulder_offset: Vec3::new(0.75, 0.0, 0.0),
+            smoothing: 6.0,
+            margin: 0.2,
+            extension: 1.0,
+        }
+    }
+}
+
+impl SpringArm {
+    /// From the entity to the camera when fully extended, in world space
+    fn arm(&self, pivot: &Transform) -> Vec3 {
+        pivot.rotation * (self.shoulder_offset + Vec3::Z * self.length)
+    }
+
+    /// Casts the arm from `pivot` through the chunks and moves its end towards where the
+    /// camera fits. `origin` is the floating origin's world voxel.
+    pub fn update(&mut self, chunks: &ChunkMap, origin: IVec3, pivot: &Transform, delta_secs: f32) {
+        let arm = self.arm(pivot);
+        let reach = arm.length();
+        if reach <= f32::EPSILON {
+            self.extension = 1.0;
+            return;
+        }
+        let target = chunks
+            .raycast(origin, pivot.translation, arm / reach, reach)
+            .map_or(1.0, |hit| ((hit - self.margin) / reach).clamp(0.0, 1.0));
+        self.extension = if target < self.extension {
+            target
+        } else {
+            let t = 1.0 - (-self.smoothing * delta_secs).exp();
+            self.extension + (target - self.extension) * t
+        };
+    }
+
+    /// Where the camera is, looking the same way as `pivot`
+    pub fn camera_transform(&self, pivot: &Transform) -> Transform {
+        pivot.with_translation(pivot.translation + self.arm(pivot) * self.extension)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{voxel::Voxel, voxel_block::VoxelBlock};
+
+    use super::*;
+
+    #[test]
+    fn arm_pulls_in_at_walls_and_eases_back_out() {
+        let mut chunks = ChunkMap::default();
+        chunks.load_around(IVec3::ZERO, 1, |_| {
+            Box::new([Voxel::Air; VoxelBlock::VOLUME as usize])
+        });
+        let pivot = Transform::from_xyz(0.5, 0.5, 0.5);
+        let mut arm = SpringArm {
+            shoulder_offset: Vec3::ZERO,
+            ..Default::default()
+        };
+        arm.update(&chunks, IVec3::ZERO, &pivot, 0.1);
+        assert_eq!(
+            arm.camera_transform(&pivot).translation,
+            Vec3::new(0.5, 0.5, 4.5)
+        );
+
+        // A wall two voxels behind, its near face 1.5 from the pivot
+        chunks.set_voxel(IVec3::new(0, 0, 2), Voxel::Stone);
+        arm.update(&chunks, IVec3::ZERO, &pivot, 0.1);
+        let camera = arm.camera_transform(&pivot).translation;
+        assert!((camera.z - 1.8).abs() < 1e-4);
+
+        // The same wall seen from a shifted floating origin
+        let shifted = Transform::from_xyz(0.5, 0.5, 0.5 - 16.0);
+        arm.update(&chunks, IVec3::new(0, 0, 16), &shifted, 0.1);
+        assert!((arm.camera_transform(&shifted).translation.z - (1.8 - 16.0)).abs() < 1e-4);
+
+        chunks.set_voxel(IVec3::new(0, 0, 2), Voxel::Air);
+        arm.update(&chunks, IVec3::ZERO, &pivot, 0.1);
+        let eased = arm.camera_transform(&pivot).translation.z;
+        assert!(eased > 1.8 && eased < 4.5);
+    }
+}
@@ -0,0 +1,199 @@
+use std::collections::HashSet;
+
+use bevy_ecs::system::Resource;
+use glam::{IVec3, Vec3};
+use serde::{Deserialize, Serialize};
+
+use crate::voxel_block::VoxelBlock;
+
+/// How much streaming work may start each frame, so a teleport or a large view distance is
+/// worked off over several frames instead of flooding the task pool
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StreamingBudget {
+    /// Generation tasks started per frame
+    pub generation: usize,
+    /// Generation tasks running at once
+    pub max_generating: usize,
+    /// Meshing tasks started per frame
+    pub meshing: usize,
+    /// Chunk meshes handed to the renderer per frame, each of which costs an acceleration
+    /// structure build
+    pub as_builds: usize,
+    /// Memory kept for recently unloaded chunks, see
+    /// [`ChunkCache`](crate::chunk_cache::ChunkCache)
+    pub chunk_cache_bytes: usize,
+}
+
+impl Default for StreamingBudget {
+    fn default() -> Self {
+        Self {
+            generation: 8,
+            max_generating: 32,
+            meshing: 8,
+            as_builds: 4,
+            chunk_cache_bytes: 64 << 20,
+        }
+    }
+}
+
+/// Counts of the streaming work waiting, running and done, updated every frame
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct StreamingStats {
+    pub loaded_chunks: usize,
+    pub queued_generation: usize,
+    pub generating: usize,
+    pub queued_meshing: usize,
+    pub meshing: usize,
+    pub queued_as_builds: usize,
+    pub cached_chunks: usize,
+    pub cache_bytes: usize,
+    /// Chunks loaded from the cache, and ones that had to be generated, since startup
+    pub cache_hits: u64,
+    pub cache_misses: u64,
+    /// Started or finished during the last frame
+    pub generation_started: usize,
+    pub meshing_started: usize,
+    pub as_builds: usize,
+}
+
+impl StreamingStats {
+    /// Whether anything is still waiting or running
+    pub const fn is_busy(&self) -> bool {
+        self.queued_generation
+            + self.generating
+            + self.queued_meshing
+            + self.meshing
+            + self.queued_as_builds
+            > 0
+    }
+}
+
+/// Where streaming work is prioritized from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StreamingView {
+    /// In chunks rather than voxels
+    position: Vec3,
+    forward: Vec3,
+}
+
+impl StreamingView {
+    /// A chunk straight behind the viewer waits as long as one this many times further away
+    /// in front of it
+    const BEHIND_PENALTY: f32 = 3.0;
+
+    /// `position` in world units; `forward` needn't be normalized
+    pub fn new(position: Vec3, forward: Vec3) -> Self {
+        Self {
+            position: position / VoxelBlock::WIDTH as f32,
+            forward: forward.normalize_or_zero(),
+        }
+    }
+
+    /// Lower is sooner: distance to the chunk's center, stretched for chunks away from the
+    /// view direction so the ones on screen fill in first
+    pub fn priority(&self, chunk: IVec3) -> f32 {
+        let offset = chunk.as_vec3() + 0.5 - self.position;
+        let distance = offset.length();
+        if distance <= f32::EPSILON {
+            return 0.0;
+        }
+        let facing = offset.dot(self.forward) / distance;
+        distance * (1.0 + (Self::BEHIND_PENALTY - 1.0) * (1.0 - facing) / 2.0)
+    }
+}
+
+/// Chunks waiting for one stage of streaming, taken in [`StreamingView::priority`] order.
+/// Priorities are recomputed on every take, since the view moves between frames.
+#[derive(Debug, Clone, Default)]
+pub struct WorkQueue {
+    chunks: HashSet<IVec3>,
+}
+
+impl WorkQueue {
+    /// Returns whether the chunk wasn't already queued
+    pub fn push(&mut self, chunk: IVec3) -> bool {
+        self.chunks.insert(chunk)
+    }
+
+    pub fn remove(&mut self, chunk: IVec3) -> bool {
+        self.chunks.remove(&chunk)
+    }
+
+    pub fn contains(&self, chunk: IVec3) -> bool {
+        self.chunks.contains(&chunk)
+    }
+
+    pub fn len(&self) -> usize {
+        self.chunks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    pub fn retain(&mut self, mut keep: impl FnMut(IVec3) -> bool) {
+        self.chunks.retain(|chunk| keep(*chunk));
+    }
+
+    /// Removes and returns up to `count` chunks accepted by `ready`, most urgent first.
+    /// Chunks `ready` rejects stay queued.
+    pub fn take(
+        &mut self,
+        count: usize,
+        view: &StreamingView,
+        ready: impl Fn(IVec3) -> bool,
+    ) -> Vec<IVec3> {
+        if count == 0 {
+            return Vec::new();
+        }
+        let mut candidates: Vec<(f32, IVec3)> = self
+            .chunks
+            .iter()
+            .filter(|chunk| ready(**chunk))
+            .map(|chunk| (view.priority(*chunk), *chunk))
+            .collect();
+        if candidates.len() > count {
+            candidates.select_nth_unstable_by(count, |a, b| a.0.total_cmp(&b.0));
+            candidates.truncate(count);
+        }
+        candidates.sort_by(|a, b| a.0.total_cmp(&b.0));
+        for (_, chunk) in &candidates {
+            self.chunks.remove(chunk);
+        }
+        candidates.into_iter().map(|(_, chunk)| chunk).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunks_in_view_are_taken_first() {
+        // Looking down -Z from the middle of chunk 0
+        let view = StreamingView::new(Vec3::splat(VoxelBlock::WIDTH as f32 / 2.0), Vec3::NEG_Z);
+        let mut queue = WorkQueue::default();
+        for chunk in [
+            IVec3::new(0, 0, 3),
+            IVec3::new(0, 0, -2),
+            IVec3::new(2, 0, 0),
+            IVec3::ZERO,
+            IVec3::new(0, 0, -5),
+        ] {
+            queue.push(chunk);
+        }
+
+        // Two ahead beats two to the side, which beats three behind
+        assert_eq!(
+            queue.take(3, &view, |_| true),
+            [IVec3::ZERO, IVec3::new(0, 0, -2), IVec3::new(2, 0, 0)]
+        );
+        // Rejected chunks stay queued
+        assert_eq!(
+            queue.take(5, &view, |chunk| chunk.z > 0),
+            [IVec3::new(0, 0, 3)]
+        );
+        assert_eq!(queue.len(), 1);
+    }
+}
@@ -5,10 +5,12 @@ use std::slice;
 use bevy_ecs::component::Component;
 use bytemuck::{Pod, Zeroable};
 use glam::{Mat4, Quat, Vec3};
+use serde::{Deserialize, Serialize};
 
 use crate::IntoBytes;
 
-#[derive(Component, Clone, Copy)]
+#[derive(Component, Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Transform {
     pub translation: Vec3,
     pub rotation: Quat,
@@ -64,6 +66,30 @@ impl Transform {
         self.scale = scale;
         self
     }
+
+    /// Blends towards `to`, `t` being 0 at `self` and 1 at `to`. Rotations take the shortest
+    /// arc.
+    pub fn lerp(&self, to: &Self, t: f32) -> Self {
+        Self {
+            translation: self.translation.lerp(to.translation, t),
+            rotation: self.rotation.slerp(to.rotation, t),
+            scale: self.scale.lerp(to.scale, t),
+        }
+    }
+}
+
+/// The [`Transform`] an entity had when the current fixed tick started. Entities moved in
+/// `FixedUpdate` carry one so they can be drawn between ticks instead of jumping at the tick
+/// rate. Anything moving the entity outside of a tick, like a teleport, should set it as well
+/// so the jump isn't drawn as movement.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq)]
+pub struct PreviousTransform(pub Transform);
+
+impl PreviousTransform {
+    /// Where the entity is drawn, `overstep` of the way from the last tick to `current`
+    pub fn interpolate(&self, current: &Transform, overstep: f32) -> Transform {
+        self.0.lerp(current, overstep)
+    }
 }
 
 impl TransformGpu {
@@ -79,3 +105,27 @@ impl IntoBytes for TransformGpu {
         bytemuck::cast_slice(slice::from_ref(self))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interpolation_blends_between_ticks() {
+        let previous = PreviousTransform(Transform::from_xyz(0.0, 0.0, 0.0));
+        let current = Transform::from_xyz(2.0, 0.0, -4.0).with_rotation(Quat::from_rotation_y(1.0));
+
+        assert_eq!(previous.interpolate(&current, 0.0), previous.0);
+        let halfway = previous.interpolate(&current, 0.5);
+        assert!(halfway
+            .translation
+            .abs_diff_eq(Vec3::new(1.0, 0.0, -2.0), 1e-6));
+        assert!(halfway
+            .rotation
+            .abs_diff_eq(Quat::from_rotation_y(0.5), 1e-6));
+        assert!(previous
+            .interpolate(&current, 1.0)
+            .to_mat4()
+            .abs_diff_eq(current.to_mat4(), 1e-6));
+    }
+}
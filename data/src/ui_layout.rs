@@ -0,0 +1,128 @@
+use bevy_ecs::system::Resource;
+use glam::{IVec2, UVec2};
+
+/// The point of the window an element is placed from, and the same point of the element
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl Anchor {
+    /// Halves across and down the window: 0 at the left or top, 2 at the right or bottom
+    const fn halves(&self) -> IVec2 {
+        match self {
+            Self::TopLeft => IVec2::new(0, 0),
+            Self::Top => IVec2::new(1, 0),
+            Self::TopRight => IVec2::new(2, 0),
+            Self::Left => IVec2::new(0, 1),
+            Self::Center => IVec2::new(1, 1),
+            Self::Right => IVec2::new(2, 1),
+            Self::BottomLeft => IVec2::new(0, 2),
+            Self::Bottom => IVec2::new(1, 2),
+            Self::BottomRight => IVec2::new(2, 2),
+        }
+    }
+}
+
+/// The window the HUD is laid out in, updated as it's resized or moved to another display.
+/// HUD sizes and margins are in UI units, which are physical pixels at a scale factor of 1 and
+/// grow with the display's scale factor, so the HUD keeps its size on high-DPI displays.
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct UiLayout {
+    /// In physical pixels
+    pub window_size: UVec2,
+    /// Physical pixels per UI unit
+    pub scale: f32,
+}
+
+impl Default for UiLayout {
+    fn default() -> Self {
+        Self {
+            window_size: UVec2::ZERO,
+            scale: 1.0,
+        }
+    }
+}
+
+impl UiLayout {
+    pub const fn new(window_size: UVec2, scale: f32) -> Self {
+        Self { window_size, scale }
+    }
+
+    /// Pixels in `units`, never rounded down to nothing. Also the integer scale to draw the
+    /// built-in font at for a text scale of `units`.
+    pub fn px(&self, units: u32) -> u32 {
+        if units == 0 {
+            return 0;
+        }
+        ((units as f32 * self.scale).round() as u32).max(1)
+    }
+
+    /// Pixels in `units` across and down
+    pub fn px_vec(&self, units: IVec2) -> IVec2 {
+        (units.as_vec2() * self.scale).round().as_ivec2()
+    }
+
+    /// Pixels in fractional `units`, for positions that are rounded later
+    pub fn px_f32(&self, units: f32) -> f32 {
+        units * self.scale
+    }
+
+    /// Top left corner of an element `size` pixels large at `anchor`, moved `margin` units
+    /// from it towards the middle of the window. Centered axes ignore their margin.
+    pub fn place(&self, anchor: Anchor, size: UVec2, margin: IVec2) -> IVec2 {
+        let halves = anchor.halves();
+        let free = self.window_size.as_ivec2() - size.as_ivec2();
+        let inwards = IVec2::ONE - halves;
+        free * halves / 2 + inwards * self.px_vec(margin)
+    }
+
+    /// Center of the window
+    pub fn center(&self) -> IVec2 {
+        self.window_size.as_ivec2() / 2
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn anchored_elements_follow_the_window_and_scale() {
+        let size = UVec2::new(100, 20);
+        let margin = IVec2::new(12, 8);
+        let layout = UiLayout::new(UVec2::new(1280, 720), 1.0);
+        assert_eq!(
+            layout.place(Anchor::TopLeft, size, margin),
+            IVec2::new(12, 8)
+        );
+        assert_eq!(
+            layout.place(Anchor::BottomRight, size, margin),
+            IVec2::new(1280 - 100 - 12, 720 - 20 - 8)
+        );
+        assert_eq!(
+            layout.place(Anchor::Bottom, size, margin),
+            IVec2::new(590, 692)
+        );
+
+        let resized = UiLayout::new(UVec2::new(2560, 1440), 2.0);
+        assert_eq!(
+            resized.place(Anchor::TopRight, size, margin),
+            IVec2::new(2560 - 100 - 24, 16)
+        );
+        assert_eq!(
+            resized.place(Anchor::Center, size, margin),
+            IVec2::new(1230, 710)
+        );
+        assert_eq!(UiLayout::new(UVec2::ZERO, 1.25).px(2), 3);
+        assert_eq!(UiLayout::new(UVec2::ZERO, 0.25).px(1), 1);
+    }
+}
@@ -0,0 +1,165 @@
+use std::borrow::Cow;
+
+use bytemuck::{Pod, Zeroable};
+use glam::{UVec3, Vec3};
+
+use crate::{voxel_block::VoxelBlock, Direction};
+
+/// Format of one vertex attribute, named after the matching `vk::Format`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VertexFormat {
+    /// `R32_SFLOAT`
+    Float32,
+    /// `R32G32_SFLOAT`
+    Float32x2,
+    /// `R32G32B32_SFLOAT`
+    Float32x3,
+    /// `R32G32B32A32_SFLOAT`
+    Float32x4,
+    /// `R8G8B8A8_UNORM`; read as `vec4` in 0..=1
+    Unorm8x4,
+    /// `R8G8B8A8_UINT`; read as `uvec4`
+    Uint8x4,
+}
+
+impl VertexFormat {
+    /// Bytes per vertex
+    pub const fn size(&self) -> u32 {
+        match self {
+            Self::Float32 | Self::Unorm8x4 | Self::Uint8x4 => 4,
+            Self::Float32x2 => 8,
+            Self::Float32x3 => 12,
+            Self::Float32x4 => 16,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VertexAttribute {
+    pub location: u32,
+    pub format: VertexFormat,
+    pub offset: u32,
+}
+
+/// How one interleaved vertex buffer binding is laid out
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VertexLayout {
+    pub stride: u32,
+    pub attributes: Cow<'static, [VertexAttribute]>,
+}
+
+impl VertexLayout {
+    /// `formats` interleaved in order, at locations counting up from zero
+    pub fn interleaved(formats: impl IntoIterator<Item = VertexFormat>) -> Self {
+        let mut stride = 0;
+        let attributes = formats
+            .into_iter()
+            .enumerate()
+            .map(|(location, format)| {
+                let attribute = VertexAttribute {
+                    location: location as u32,
+                    format,
+                    offset: stride,
+                };
+                stride += format.size();
+                attribute
+            })
+            .collect::<Vec<_>>();
+        Self {
+            stride,
+            attributes: Cow::Owned(attributes),
+        }
+    }
+}
+
+/// A chunk mesh vertex in four bytes: the corner's chunk-local position in x, y and z
+/// (`0..=VoxelBlock::WIDTH`) and the index of its face's [`Direction`] in w, which stands in
+/// for the normal. Decoded by `shaders/chunk_vertex.glsl`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Pod, Zeroable)]
+pub struct ChunkVertex(pub [u8; 4]);
+
+impl ChunkVertex {
+    /// Read as a `uvec4`; the exact encoding
+    pub const UINT_LAYOUT: VertexLayout = VertexLayout {
+        stride: 4,
+        attributes: Cow::Borrowed(&[VertexAttribute {
+            location: 0,
+            format: VertexFormat::Uint8x4,
+            offset: 0,
+        }]),
+    };
+
+    /// Read as a normalized `vec4`, for pipelines that can't take integer inputs. Multiply
+    /// by [`Self::UNORM_SCALE`] to get the encoding back.
+    pub const UNORM_LAYOUT: VertexLayout = VertexLayout {
+        stride: 4,
+        attributes: Cow::Borrowed(&[VertexAttribute {
+            location: 0,
+            format: VertexFormat::Unorm8x4,
+            offset: 0,
+        }]),
+    };
+
+    /// Matches `CHUNK_UNORM_SCALE` in the shader
+    pub const UNORM_SCALE: f32 = u8::MAX as f32;
+
+    /// Normal per face index, like `CHUNK_FACE_NORMALS` in the shader. -Y is up.
+    pub const FACE_NORMALS: [[f32; 3]; 6] = [
+        [-1.0, 0.0, 0.0],
+        [1.0, 0.0, 0.0],
+        [0.0, 1.0, 0.0],
+        [0.0, -1.0, 0.0],
+        [0.0, 0.0, 1.0],
+        [0.0, 0.0, -1.0],
+    ];
+
+    /// `position` must lie within the chunk, counting its far corners
+    pub fn new(position: UVec3, direction: Direction) -> Self {
+        debug_assert!(
+            position.max_element() <= VoxelBlock::WIDTH as u32,
+            "{position} is outside the chunk"
+        );
+        Self([
+            position.x as u8,
+            position.y as u8,
+            position.z as u8,
+            direction as u8,
+        ])
+    }
+
+    pub fn position(&self) -> UVec3 {
+        UVec3::new(self.0[0] as u32, self.0[1] as u32, self.0[2] as u32)
+    }
+
+    pub fn direction(&self) -> Direction {
+        Direction::from_index(self.0[3]).expect("face index is a direction")
+    }
+
+    pub fn normal(&self) -> Vec3 {
+        Vec3::from_array(Self::FACE_NORMALS[self.0[3] as usize])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunk_vertices_round_trip() {
+        for direction in Direction::ALL {
+            assert_eq!(
+                ChunkVertex::FACE_NORMALS[direction as usize],
+                direction.offset().as_vec3().to_array()
+            );
+            let far_corner = UVec3::splat(VoxelBlock::WIDTH as u32);
+            let vertex = ChunkVertex::new(far_corner, direction);
+            assert_eq!(vertex.position(), far_corner);
+            assert_eq!(vertex.direction(), direction);
+        }
+        assert_eq!(
+            ChunkVertex::UINT_LAYOUT.stride,
+            ChunkVertex::UINT_LAYOUT.attributes[0].format.size()
+        );
+    }
+}
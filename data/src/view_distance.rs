@@ -0,0 +1,95 @@
+use glam::IVec3;
+use serde::{Deserialize, Serialize};
+
+use crate::voxel_block::VoxelBlock;
+
+/// Radius in chunks around the viewer that is kept loaded. Rendering fades into the sky
+/// before this distance so chunks streaming in at the edge are not seen popping in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "u32", into = "u32")]
+pub struct ViewDistance(u32);
+
+impl From<u32> for ViewDistance {
+    fn from(chunks: u32) -> Self {
+        Self::new(chunks)
+    }
+}
+
+impl From<ViewDistance> for u32 {
+    fn from(view_distance: ViewDistance) -> Self {
+        view_distance.0
+    }
+}
+
+impl Default for ViewDistance {
+    fn default() -> Self {
+        Self(8)
+    }
+}
+
+impl ViewDistance {
+    pub const MIN: u32 = 2;
+    pub const MAX: u32 = 32;
+
+    /// Fog starts at this fraction of the view distance and is opaque at the end
+    const FOG_START: f32 = 0.75;
+
+    pub fn new(chunks: u32) -> Self {
+        Self(chunks.clamp(Self::MIN, Self::MAX))
+    }
+
+    pub const fn chunks(&self) -> u32 {
+        self.0
+    }
+
+    /// Radius in world units
+    pub fn distance(&self) -> f32 {
+        (self.0 * VoxelBlock::WIDTH as u32) as f32
+    }
+
+    /// Distances at which fog starts and becomes opaque, in world units
+    pub fn fog_range(&self) -> (f32, f32) {
+        let end = self.distance();
+        (end * Self::FOG_START, end)
+    }
+
+    /// Whether the chunk at `chunk` is within streaming range of the chunk at `center`
+    pub fn contains(&self, center: IVec3, chunk: IVec3) -> bool {
+        let radius = self.0 as i32;
+        (chunk - center).length_squared() <= radius * radius
+    }
+
+    /// Chunk coordinates in range of `center`, nearest first so streaming fills in outwards
+    pub fn chunks_around(&self, center: IVec3) -> Vec<IVec3> {
+        let radius = self.0 as i32;
+        let mut chunks: Vec<_> = (-radius..=radius)
+            .flat_map(|y| {
+                (-radius..=radius)
+                    .flat_map(move |z| (-radius..=radius).map(move |x| IVec3::new(x, y, z)))
+            })
+            .filter(|offset| offset.length_squared() <= radius * radius)
+            .map(|offset| center + offset)
+            .collect();
+        chunks.sort_by_key(|chunk| (*chunk - center).length_squared());
+        chunks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunks_around_match_contains() {
+        let view_distance = ViewDistance::new(3);
+        let center = IVec3::new(5, -2, 7);
+        let chunks = view_distance.chunks_around(center);
+
+        assert_eq!(chunks[0], center);
+        assert!(chunks
+            .iter()
+            .all(|chunk| view_distance.contains(center, *chunk)));
+        assert!(!view_distance.contains(center, center + IVec3::new(4, 0, 0)));
+        assert_eq!(ViewDistance::new(0).chunks(), ViewDistance::MIN);
+    }
+}
@@ -0,0 +1,117 @@
+use glam::UVec3;
+
+use crate::{schematic::Schematic, voxel::Voxel};
+
+/// Version of the MagicaVoxel format written
+pub const VOX_VERSION: u32 = 150;
+
+/// Largest model a `.vox` file holds along each axis
+pub const MAX_VOX_SIZE: u32 = 256;
+
+/// Encodes `schematic` as a MagicaVoxel `.vox` file, or `None` if it's too big for one. Each
+/// voxel type gets the palette entry of its [`VoxelId`](crate::voxel::VoxelId), colored like
+/// its material; air is left out.
+///
+/// MagicaVoxel has Z up where the world has -Y up, so the model is turned to stand upright.
+pub fn encode_vox(schematic: &Schematic) -> Option<Vec<u8>> {
+    let size = schematic.size();
+    if size.max_element() > MAX_VOX_SIZE {
+        return None;
+    }
+
+    let mut voxels = Vec::new();
+    for y in 0..size.y {
+        for z in 0..size.z {
+            for x in 0..size.x {
+                let voxel = schematic.get(UVec3::new(x, y, z));
+                if voxel != Voxel::Air {
+                    // Every coordinate is below 256, checked above
+                    voxels.extend([x as u8, z as u8, (size.y - 1 - y) as u8, voxel as u8]);
+                }
+            }
+        }
+    }
+
+    let mut size_chunk = Vec::new();
+    for extent in [size.x, size.z, size.y] {
+        size_chunk.extend(extent.to_le_bytes());
+    }
+    let mut xyzi = (voxels.len() as u32 / 4).to_le_bytes().to_vec();
+    xyzi.extend(voxels);
+    // Entry `i` colors index `i + 1`, which is why the voxel IDs can be used as indices
+    let mut palette = Vec::with_capacity(256 * 4);
+    for index in 1..=256 {
+        let rgba = match Voxel::ALL.get(index) {
+            Some(voxel) => {
+                let [r, g, b] = voxel.material().color.map(|c| (c * 255.0).round() as u8);
+                [r, g, b, 255]
+            }
+            None => [0, 0, 0, 255],
+        };
+        palette.extend(rgba);
+    }
+
+    let mut children = Vec::new();
+    write_chunk(&mut children, b"SIZE", &size_chunk, &[]);
+    write_chunk(&mut children, b"XYZI", &xyzi, &[]);
+    write_chunk(&mut children, b"RGBA", &palette, &[]);
+
+    let mut file = b"VOX ".to_vec();
+    file.extend(VOX_VERSION.to_le_bytes());
+    write_chunk(&mut file, b"MAIN", &[], &children);
+    Some(file)
+}
+
+fn write_chunk(out: &mut Vec<u8>, id: &[u8; 4], content: &[u8], children: &[u8]) {
+    out.extend(id);
+    out.extend((content.len() as u32).to_le_bytes());
+    out.extend((children.len() as u32).to_le_bytes());
+    out.extend(content);
+    out.extend(children);
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::IVec3;
+
+    use crate::{
+        chunk_map::ChunkMap,
+        schematic::{fill, Region},
+        voxel_block::VoxelBlock,
+    };
+
+    use super::*;
+
+    #[test]
+    fn trimmed_models_stand_upright_in_vox_files() {
+        let mut chunks = ChunkMap::default();
+        chunks.load_around(IVec3::ZERO, 0, |_| {
+            Box::new([Voxel::Air; VoxelBlock::VOLUME as usize])
+        });
+        // A stone pillar two voxels tall with grass on top, which is towards -Y
+        fill(
+            &mut chunks,
+            Region::from_corners(IVec3::new(4, 5, 4), IVec3::new(4, 6, 4)),
+            Voxel::Stone,
+        );
+        chunks.set_voxel(IVec3::new(4, 4, 4), Voxel::Grass);
+        let block = Region::from_corners(IVec3::ZERO, IVec3::splat(VoxelBlock::WIDTH as i32 - 1));
+        let model = Schematic::copy(&chunks, block).unwrap().trimmed().unwrap();
+        assert_eq!(model.size(), UVec3::new(1, 3, 1));
+
+        let vox = encode_vox(&model).unwrap();
+        assert_eq!(&vox[..4], b"VOX ");
+        let read_u32 = |at: usize| u32::from_le_bytes(vox[at..at + 4].try_into().unwrap());
+        assert_eq!(read_u32(4), VOX_VERSION);
+        // SIZE follows the MAIN header, with Z as the height
+        assert_eq!(&vox[20..24], b"SIZE");
+        assert_eq!([read_u32(32), read_u32(36), read_u32(40)], [1, 1, 3]);
+        assert_eq!(&vox[44..48], b"XYZI");
+        assert_eq!(read_u32(56), 3);
+        let grass = [0, 0, 2, Voxel::Grass as u8];
+        assert!(vox[60..72].chunks(4).any(|voxel| voxel == grass));
+
+        let empty = Region::from_corners(IVec3::ZERO, IVec3::ONE);
+        assert!(Schematic::copy(&chunks, empty).unwrap().trimmed().is_none());
+    }
+}
@@ -1,22 +1,73 @@
 use std::fmt::Debug;
 
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    interaction::Interaction,
+    material::{Material, MaterialFlags},
+};
+
 pub type VoxelId = u8;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[repr(u8)]
 pub enum Voxel {
     Air = 0,
     Stone,
     Dirt,
     Grass,
+    Water,
+    Glowstone,
+    Chest,
+    /// Glowstone switched off
+    Lamp,
 }
 
 impl Voxel {
-    pub const VOXEL_COUNT: u8 = 4;
-    pub const ALL: [Self; Self::VOXEL_COUNT as usize] =
-        [Self::Air, Self::Stone, Self::Dirt, Self::Grass];
+    pub const VOXEL_COUNT: u8 = 8;
+    pub const ALL: [Self; Self::VOXEL_COUNT as usize] = [
+        Self::Air,
+        Self::Stone,
+        Self::Dirt,
+        Self::Grass,
+        Self::Water,
+        Self::Glowstone,
+        Self::Chest,
+        Self::Lamp,
+    ];
 
     pub const fn is_opaque(&self) -> bool {
-        !matches!(self, Self::Air)
+        !matches!(self, Self::Air | Self::Water)
+    }
+
+    pub const fn material(&self) -> Material {
+        match self {
+            Self::Air => Material::from_color([0.0, 0.0, 0.0]),
+            Self::Stone => Material::from_color([0.5, 0.5, 0.5]),
+            Self::Dirt => Material::from_color([0.45, 0.3, 0.2]),
+            Self::Grass => Material::from_color([0.3, 0.6, 0.2]),
+            Self::Water => Material::from_color([0.2, 0.4, 0.7])
+                .with_roughness(0.05)
+                .with_flags(MaterialFlags::REFLECTIVE),
+            Self::Glowstone => {
+                Material::from_color([1.0, 0.85, 0.5]).with_emission([1.0, 0.8, 0.45], 3.0)
+            }
+            Self::Chest => Material::from_color([0.55, 0.35, 0.15]),
+            Self::Lamp => Material::from_color([0.6, 0.55, 0.4]),
+        }
+    }
+
+    /// What right-clicking the voxel does, if anything
+    pub const fn interaction(&self) -> Option<Interaction> {
+        match self {
+            Self::Chest => Some(Interaction::OpenContainer {
+                slots: Interaction::CHEST_SLOTS,
+            }),
+            Self::Glowstone => Some(Interaction::ToggleLight { to: Self::Lamp }),
+            Self::Lamp => Some(Interaction::ToggleLight {
+                to: Self::Glowstone,
+            }),
+            _ => None,
+        }
     }
 }
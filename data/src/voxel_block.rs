@@ -27,6 +27,10 @@ impl VoxelBlock {
         }
     }
 
+    pub fn into_data(self) -> VoxelBlockData {
+        self.data
+    }
+
     pub fn get(&self, pos: U8Vec3) -> &Voxel {
         let index = Self::to_index(pos);
         &self.data[index]
@@ -48,22 +52,7 @@ impl VoxelBlock {
     }
 
     pub fn to_rle(&self) -> Vec<Rle> {
-        let mut rle = Vec::new();
-
-        let mut prev_voxel = self.data[0];
-        let mut count = 1;
-
-        for &voxel in self.data.iter().skip(1) {
-            if prev_voxel == voxel {
-                count += 1;
-            } else {
-                rle.push((count, voxel as VoxelId));
-                count = 0;
-            }
-            prev_voxel = voxel;
-        }
-        rle.push((count, prev_voxel as VoxelId));
-        rle
+        encode_rle(self.data.iter().copied())
     }
 
     pub fn from_rle<I>(rle: I, coords: UVec3) -> Result<Self, RleError>
@@ -86,6 +75,18 @@ impl VoxelBlock {
 
 pub type Rle = (VoxelCount, VoxelId);
 
+/// Runs of equal voxels, in order
+pub fn encode_rle(voxels: impl IntoIterator<Item = Voxel>) -> Vec<Rle> {
+    let mut rle: Vec<Rle> = Vec::new();
+    for voxel in voxels {
+        match rle.last_mut() {
+            Some((count, id)) if *id == voxel as VoxelId => *count += 1,
+            _ => rle.push((1, voxel as VoxelId)),
+        }
+    }
+    rle
+}
+
 pub type VoxelCount = u32;
 
 #[derive(Error, Debug)]
@@ -0,0 +1,195 @@
+use bevy_ecs::system::Resource;
+use serde::{Deserialize, Serialize};
+
+use crate::{block_tick::GameTick, rng::Rng};
+
+/// What the sky is doing, see [`Weather`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Sky {
+    #[default]
+    Clear,
+    Overcast,
+    /// Overcast and raining, or snowing at altitude, see [`Precipitation::at`]
+    Precipitating,
+}
+
+impl Sky {
+    /// Cloud cover and precipitation the weather eases towards under this sky
+    const fn targets(self) -> (f32, f32) {
+        match self {
+            Self::Clear => (0.0, 0.0),
+            Self::Overcast => (1.0, 0.0),
+            Self::Precipitating => (1.0, 1.0),
+        }
+    }
+}
+
+/// Relative chance of each [`Sky`] whenever the weather changes. The terrain has no biomes
+/// yet, so one table covers the whole world.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WeatherOdds {
+    pub clear: f32,
+    pub overcast: f32,
+    pub precipitating: f32,
+}
+
+impl Default for WeatherOdds {
+    fn default() -> Self {
+        Self {
+            clear: 0.6,
+            overcast: 0.25,
+            precipitating: 0.15,
+        }
+    }
+}
+
+impl WeatherOdds {
+    /// The sky at `roll` in `[0, 1)` along the odds, or clear if they are all zero
+    fn pick(&self, roll: f32) -> Sky {
+        let weights = [
+            (Sky::Clear, self.clear),
+            (Sky::Overcast, self.overcast),
+            (Sky::Precipitating, self.precipitating),
+        ]
+        .map(|(sky, weight)| (sky, weight.max(0.0)));
+        let total: f32 = weights.iter().map(|(_, weight)| weight).sum();
+        let mut remaining = roll * total;
+        for (sky, weight) in weights {
+            if remaining < weight {
+                return sky;
+            }
+            remaining -= weight;
+        }
+        Sky::Clear
+    }
+}
+
+/// What falls while [`Sky::Precipitating`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Precipitation {
+    Rain,
+    Snow,
+}
+
+impl Precipitation {
+    /// World y above which rain falls as snow. Up is -Y, so this is ten voxels above the sea.
+    pub const SNOW_LINE_Y: f32 = -10.0;
+
+    pub fn at(world_y: f32) -> Self {
+        if world_y < Self::SNOW_LINE_Y {
+            Self::Snow
+        } else {
+            Self::Rain
+        }
+    }
+}
+
+/// The world's weather, changing at times drawn from the world's [`Rng`] so every run of a world
+/// sees the same weather on the same ticks. Cloud cover and precipitation ease towards the
+/// current [`Sky`] rather than switching at once.
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct Weather {
+    sky: Sky,
+    cloud_cover: f32,
+    precipitation: f32,
+    /// Changes so far, which with the seed decides the next one
+    changes: u64,
+    next_change: GameTick,
+    pub odds: WeatherOdds,
+}
+
+impl Default for Weather {
+    fn default() -> Self {
+        Self {
+            sky: Sky::Clear,
+            cloud_cover: 0.0,
+            precipitation: 0.0,
+            changes: 0,
+            next_change: GameTick(0),
+            odds: WeatherOdds::default(),
+        }
+    }
+}
+
+impl Weather {
+    /// Shortest and longest spells of one sky
+    pub const MIN_SPELL_TICKS: u64 = 60 * GameTick::PER_SECOND as u64;
+    pub const MAX_SPELL_TICKS: u64 = 5 * 60 * GameTick::PER_SECOND as u64;
+    /// Fraction of the way to the sky's targets covered per tick
+    const EASING: f32 = 0.01;
+    /// Sunlight left under full cloud cover
+    const OVERCAST_SUNLIGHT: f32 = 0.4;
+
+    pub const fn sky(&self) -> Sky {
+        self.sky
+    }
+
+    /// From 0 for a clear sky to 1 for a fully clouded one
+    pub const fn cloud_cover(&self) -> f32 {
+        self.cloud_cover
+    }
+
+    /// From 0 for none to 1 for a downpour
+    pub const fn precipitation(&self) -> f32 {
+        self.precipitation
+    }
+
+    /// Multiplier on the sun and sky light, dimmed by the clouds
+    pub fn sunlight(&self) -> f32 {
+        1.0 - (1.0 - Self::OVERCAST_SUNLIGHT) * self.cloud_cover
+    }
+
+    /// Advances the weather to `tick`, returning the new sky if it changed
+    pub fn tick(&mut self, rng: &Rng, tick: GameTick) -> Option<Sky> {
+        let previous = self.sky;
+        while tick >= self.next_change {
+            let roll = rng.stream("weather").split(self.changes).next_u64();
+            let spell = Self::MIN_SPELL_TICKS
+                + (roll >> 32) % (Self::MAX_SPELL_TICKS - Self::MIN_SPELL_TICKS + 1);
+            // The first spell of a world is always clear
+            if self.changes > 0 {
+                self.sky = self
+                    .odds
+                    .pick((roll as u32 >> 8) as f32 / (1u32 << 24) as f32);
+            }
+            self.changes += 1;
+            self.next_change = self.next_change.after(spell);
+        }
+
+        let (cloud_cover, precipitation) = self.sky.targets();
+        self.cloud_cover += (cloud_cover - self.cloud_cover) * Self::EASING;
+        self.precipitation += (precipitation - self.precipitation) * Self::EASING;
+        (self.sky != previous).then_some(self.sky)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::worldgen::WorldSeed;
+
+    #[test]
+    fn weather_follows_the_seed() {
+        let run = |seed| {
+            let mut weather = Weather::default();
+            (0..Weather::MAX_SPELL_TICKS * 20)
+                .filter_map(|tick| weather.tick(&Rng::new(WorldSeed(seed)), GameTick(tick)))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(run(7), run(7));
+        assert_ne!(run(7), run(8));
+        assert!(run(7).contains(&Sky::Precipitating));
+
+        let mut weather = Weather {
+            sky: Sky::Overcast,
+            next_change: GameTick(u64::MAX),
+            ..Weather::default()
+        };
+        for tick in 0..1000 {
+            weather.tick(&Rng::new(WorldSeed(7)), GameTick(tick));
+        }
+        assert!((weather.sunlight() - Weather::OVERCAST_SUNLIGHT).abs() < 1e-3);
+        assert_eq!(weather.precipitation(), 0.0);
+    }
+}
@@ -0,0 +1,60 @@
+use bevy_ecs::system::Resource;
+use glam::{Vec2, Vec3};
+use serde::{Deserialize, Serialize};
+
+/// Square limit on how far the world extends horizontally (X and Z) from `center`. Height is
+/// unlimited.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WorldBorder {
+    pub center: Vec2,
+    pub half_size: f32,
+}
+
+impl Default for WorldBorder {
+    fn default() -> Self {
+        Self {
+            center: Vec2::ZERO,
+            half_size: 4096.0,
+        }
+    }
+}
+
+impl WorldBorder {
+    pub const fn new(center: Vec2, half_size: f32) -> Self {
+        Self { center, half_size }
+    }
+
+    /// Moves `position` horizontally onto the border if it is beyond it
+    pub fn clamp(&self, position: Vec3) -> Vec3 {
+        let min = self.center - self.half_size;
+        let max = self.center + self.half_size;
+        Vec3::new(
+            position.x.clamp(min.x, max.x),
+            position.y,
+            position.z.clamp(min.y, max.y),
+        )
+    }
+
+    /// Horizontal distance from `position` to the nearest edge; negative outside the border
+    pub fn distance_to_edge(&self, position: Vec3) -> f32 {
+        let offset = (Vec2::new(position.x, position.z) - self.center).abs();
+        self.half_size - offset.max_element()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn positions_outside_are_pulled_back() {
+        let border = WorldBorder::new(Vec2::new(10.0, 0.0), 5.0);
+        let outside = Vec3::new(20.0, -3.0, -7.0);
+        assert_eq!(border.distance_to_edge(outside), -5.0);
+
+        let clamped = border.clamp(outside);
+        assert_eq!(clamped, Vec3::new(15.0, -3.0, -5.0));
+        assert_eq!(border.distance_to_edge(clamped), 0.0);
+        assert_eq!(border.distance_to_edge(Vec3::new(11.0, 0.0, 1.0)), 4.0);
+    }
+}
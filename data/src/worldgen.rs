@@ -0,0 +1,151 @@
+use bevy_ecs::system::Resource;
+use glam::IVec3;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    voxel::Voxel,
+    voxel_block::{VoxelBlock, VoxelBlockData},
+};
+
+/// Everything terrain generation depends on besides the chunk coordinate
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub struct WorldSeed(pub u64);
+
+/// World y of the sea surface. -Y is up, so water fills columns whose surface lies below it,
+/// i.e. at a larger y.
+pub const SEA_LEVEL_Y: i32 = 0;
+
+const SURFACE_Y: f32 = 0.0;
+/// Height variation of the terrain, as (wavelength in voxels, amplitude in voxels)
+const OCTAVES: [(f32, f32); 3] = [(64.0, 12.0), (24.0, 4.0), (8.0, 1.5)];
+const DIRT_DEPTH: i32 = 3;
+
+/// Generates the voxels of the chunk at `chunk` (in chunk coordinates, so the chunk covers
+/// world voxels `chunk * WIDTH` up to `(chunk + 1) * WIDTH`).
+///
+/// This is a pure function of its arguments: changing its output for an existing seed changes
+/// existing worlds, which the test vectors below guard against.
+pub fn generate_chunk(seed: WorldSeed, chunk: IVec3) -> VoxelBlockData {
+    let width = VoxelBlock::WIDTH as i32;
+    let origin = chunk * width;
+
+    let surfaces: Vec<i32> = (0..width)
+        .flat_map(|z| (0..width).map(move |x| (x, z)))
+        .map(|(x, z)| surface_y(seed, origin.x + x, origin.z + z))
+        .collect();
+
+    // Same order as VoxelBlock's indexing: x fastest, then z, then y
+    let voxels: Vec<Voxel> = (0..width)
+        .flat_map(|y| (0..width).flat_map(move |z| (0..width).map(move |x| (x, y, z))))
+        .map(|(x, y, z)| {
+            let surface = surfaces[(x + z * width) as usize];
+            voxel_at(surface, origin.y + y)
+        })
+        .collect();
+
+    voxels
+        .try_into()
+        .expect("generated chunk has VoxelBlock::VOLUME voxels")
+}
+
+/// World y of the topmost solid voxel of the column at `(x, z)`
+pub fn surface_y(seed: WorldSeed, x: i32, z: i32) -> i32 {
+    let height: f32 = OCTAVES
+        .iter()
+        .enumerate()
+        .map(|(octave, (wavelength, amplitude))| {
+            let octave_seed = seed.0.wrapping_add(octave as u64);
+            (value_noise(octave_seed, x as f32 / wavelength, z as f32 / wavelength) * 2.0 - 1.0)
+                * amplitude
+        })
+        .sum();
+    // Up is -Y, so higher terrain has a smaller surface y
+    (SURFACE_Y - height).floor() as i32
+}
+
+fn voxel_at(surface: i32, y: i32) -> Voxel {
+    let depth = y - surface;
+    if depth < 0 {
+        if y >= SEA_LEVEL_Y {
+            Voxel::Water
+        } else {
+            Voxel::Air
+        }
+    } else if depth == 0 && surface < SEA_LEVEL_Y {
+        Voxel::Grass
+    } else if depth <= DIRT_DEPTH {
+        Voxel::Dirt
+    } else {
+        Voxel::Stone
+    }
+}
+
+/// Smoothly interpolated lattice noise in `[0, 1)`. Only uses integer hashing and basic float
+/// arithmetic so results are identical across platforms.
+fn value_noise(seed: u64, x: f32, z: f32) -> f32 {
+    let (x0, z0) = (x.floor(), z.floor());
+    let (tx, tz) = (smoothstep(x - x0), smoothstep(z - z0));
+    let (x0, z0) = (x0 as i32, z0 as i32);
+
+    let corner = |dx: i32, dz: i32| lattice(seed, x0 + dx, z0 + dz);
+    let top = corner(0, 0) + (corner(1, 0) - corner(0, 0)) * tx;
+    let bottom = corner(0, 1) + (corner(1, 1) - corner(0, 1)) * tx;
+    top + (bottom - top) * tz
+}
+
+fn smoothstep(t: f32) -> f32 {
+    t * t * (3.0 - 2.0 * t)
+}
+
+fn lattice(seed: u64, x: i32, z: i32) -> f32 {
+    let key =
+        seed ^ ((x as u32 as u64) << 32 | z as u32 as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    (splitmix64(key) >> 40) as f32 / (1u64 << 24) as f32
+}
+
+pub(crate) fn splitmix64(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+/// FNV-1a over the voxel IDs. Stable across Rust versions and platforms, unlike `std::hash`.
+pub fn chunk_hash(data: &VoxelBlockData) -> u64 {
+    data.iter().fold(0xcbf2_9ce4_8422_2325, |hash, voxel| {
+        (hash ^ *voxel as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// (seed, chunk, hash). Only update these when a change to existing worlds is intended;
+    /// `cargo run -p data --example worldgen_hashes` prints the current values.
+    const VECTORS: [(u64, [i32; 3], u64); 15] = [
+        (0x0, [0, 0, 0], 0x13411b19e5157325),
+        (0x0, [0, -1, 0], 0x91e316c65aa61fe8),
+        (0x0, [-3, 0, 5], 0xe6a9d661dfa4eb08),
+        (0x0, [2, 4, -7], 0x13411b19e5157325),
+        (0x0, [0, -8, 0], 0xb93a0c83ce3b6325),
+        (0x1, [0, 0, 0], 0xe3c3c4359eefac13),
+        (0x1, [0, -1, 0], 0x1ba217f35aef115c),
+        (0x1, [-3, 0, 5], 0xcf82f1bae22dc131),
+        (0x1, [2, 4, -7], 0x13411b19e5157325),
+        (0x1, [0, -8, 0], 0xb93a0c83ce3b6325),
+        (0xdeadbeef, [0, 0, 0], 0x5d453f74a12d637b),
+        (0xdeadbeef, [0, -1, 0], 0xb93a0c83ce3b6325),
+        (0xdeadbeef, [-3, 0, 5], 0x4f580c8efb68c903),
+        (0xdeadbeef, [2, 4, -7], 0x13411b19e5157325),
+        (0xdeadbeef, [0, -8, 0], 0xb93a0c83ce3b6325),
+    ];
+
+    #[test]
+    fn generation_matches_vectors() {
+        for (seed, chunk, hash) in VECTORS {
+            let data = generate_chunk(WorldSeed(seed), IVec3::from_array(chunk));
+            assert_eq!(chunk_hash(&data), hash, "seed {seed}, chunk {chunk:?}");
+        }
+    }
+}
@@ -0,0 +1,64 @@
+//! Derives for the `ecs` crate, which re-exports them
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, spanned::Spanned, Data, DeriveInput, Error, Fields};
+
+/// Implements `SystemParam` for a struct whose fields are all system params. It is available
+/// whenever every field is, so one argument can stand in for several that are always used
+/// together:
+///
+/// ```ignore
+/// #[derive(SystemParam, Debug)]
+/// struct ChunkAccess {
+///     map: ResMut<ChunkMap>,
+///     registry: Res<VoxelRegistry>,
+/// }
+/// ```
+#[proc_macro_derive(SystemParam)]
+pub fn derive_system_param(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let name = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+
+    let Data::Struct(data) = &input.data else {
+        return Error::new(input.span(), "SystemParam can only be derived for structs")
+            .to_compile_error()
+            .into();
+    };
+    let get = |ty: &syn::Type| quote! { <#ty as ::ecs::SystemParam>::get_from_world(world)? };
+    let access = data.fields.iter().map(|field| {
+        let ty = &field.ty;
+        quote! { <#ty as ::ecs::SystemParam>::access(access); }
+    });
+    let construct = match &data.fields {
+        Fields::Named(fields) => {
+            let fields = fields.named.iter().map(|field| {
+                let ident = &field.ident;
+                let get = get(&field.ty);
+                quote! { #ident: #get }
+            });
+            quote! { Self { #(#fields),* } }
+        }
+        Fields::Unnamed(fields) => {
+            let fields = fields.unnamed.iter().map(|field| get(&field.ty));
+            quote! { Self(#(#fields),*) }
+        }
+        Fields::Unit => quote! { Self },
+    };
+
+    quote! {
+        impl #impl_generics ::ecs::SystemParam for #name #type_generics #where_clause {
+            #[allow(unused_variables)]
+            fn get_from_world(world: &::ecs::World) -> ::core::option::Option<Self> {
+                ::core::option::Option::Some(#construct)
+            }
+
+            #[allow(unused_variables)]
+            fn access(access: &mut ::ecs::Access) {
+                #(#access)*
+            }
+        }
+    }
+    .into()
+}
@@ -0,0 +1,172 @@
+use std::{
+    any::{self, TypeId},
+    collections::{BTreeMap, BTreeSet},
+    fmt::{self, Display, Formatter},
+};
+
+use crate::{Schedule, SystemConfig};
+
+/// The resources a system reads and writes, declared with [`SystemConfig::params`]
+#[derive(Debug, Clone, Default)]
+pub struct Access {
+    reads: BTreeMap<TypeId, &'static str>,
+    writes: BTreeMap<TypeId, &'static str>,
+}
+
+impl Access {
+    pub fn read<T: 'static>(&mut self) {
+        self.reads.insert(TypeId::of::<T>(), any::type_name::<T>());
+    }
+
+    pub fn write<T: 'static>(&mut self) {
+        self.writes.insert(TypeId::of::<T>(), any::type_name::<T>());
+    }
+
+    /// Names of the types one of the two writes while the other uses them
+    pub fn conflicts(&self, other: &Access) -> BTreeSet<&'static str> {
+        let written = |writes: &BTreeMap<TypeId, &'static str>, other: &Access| {
+            writes
+                .iter()
+                .filter(|(ty, _)| other.reads.contains_key(ty) || other.writes.contains_key(ty))
+                .map(|(_, name)| *name)
+                .collect::<Vec<_>>()
+        };
+        written(&self.writes, other)
+            .into_iter()
+            .chain(written(&other.writes, self))
+            .collect()
+    }
+}
+
+/// Two systems of a schedule that use the same type, one of them mutably, with nothing
+/// ordering them. Their order is only decided by how they were inserted, so an unrelated
+/// insertion can change what each of them sees.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ambiguity {
+    pub schedule: Schedule,
+    pub systems: [&'static str; 2],
+    pub conflicts: BTreeSet<&'static str>,
+}
+
+impl Display for Ambiguity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let [first, second] = self.systems;
+        let conflicts: Vec<_> = self.conflicts.iter().copied().collect();
+        write!(
+            f,
+            "{:?}: {first} and {second} both use {} with one of them writing, \
+             order them with after or before",
+            self.schedule,
+            conflicts.join(", ")
+        )
+    }
+}
+
+/// See [`crate::World::ambiguities`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AmbiguityReport(pub Vec<Ambiguity>);
+
+impl Display for AmbiguityReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.0.len() {
+            0 => write!(f, "No ambiguous systems")?,
+            1 => write!(f, "1 pair of ambiguous systems")?,
+            len => write!(f, "{len} pairs of ambiguous systems")?,
+        }
+        for ambiguity in &self.0 {
+            write!(f, "\n  {ambiguity}")?;
+        }
+        Ok(())
+    }
+}
+
+/// For each system, the systems that have to run after it, from their `after` and `before`
+/// constraints
+pub(crate) fn successors(systems: &[&SystemConfig]) -> Vec<BTreeSet<usize>> {
+    let named = |name: &'static str| {
+        systems
+            .iter()
+            .enumerate()
+            .filter(move |(_, system)| system.name == name)
+            .map(|(index, _)| index)
+    };
+    let mut successors = vec![BTreeSet::new(); systems.len()];
+    for (index, system) in systems.iter().enumerate() {
+        for before in system.after.iter().flat_map(|name| named(name)) {
+            successors[before].insert(index);
+        }
+        for after in system.before.iter().flat_map(|name| named(name)) {
+            successors[index].insert(after);
+        }
+    }
+    successors
+}
+
+/// Indices of `systems` in an order that satisfies their constraints, keeping the inserted
+/// order where there are none. Fails with the names of the systems in a cycle.
+pub(crate) fn sort(systems: &[&SystemConfig]) -> Result<Vec<usize>, Vec<&'static str>> {
+    let successors = successors(systems);
+    let mut predecessors = vec![0; systems.len()];
+    for next in successors.iter().flatten() {
+        predecessors[*next] += 1;
+    }
+    let mut ready: BTreeSet<_> = (0..systems.len())
+        .filter(|index| predecessors[*index] == 0)
+        .collect();
+    let mut order = Vec::with_capacity(systems.len());
+    while let Some(index) = ready.pop_first() {
+        order.push(index);
+        for next in &successors[index] {
+            predecessors[*next] -= 1;
+            if predecessors[*next] == 0 {
+                ready.insert(*next);
+            }
+        }
+    }
+    if order.len() == systems.len() {
+        Ok(order)
+    } else {
+        Err((0..systems.len())
+            .filter(|index| predecessors[*index] > 0)
+            .map(|index| systems[index].name)
+            .collect())
+    }
+}
+
+/// Conflicting pairs of `systems` where neither is ordered before the other, even through
+/// systems in between
+pub(crate) fn ambiguities(schedule: Schedule, systems: &[&SystemConfig]) -> Vec<Ambiguity> {
+    let successors = successors(systems);
+    let reachable: Vec<BTreeSet<usize>> = (0..systems.len())
+        .map(|start| {
+            let mut reached = BTreeSet::new();
+            let mut stack = vec![start];
+            while let Some(index) = stack.pop() {
+                for next in &successors[index] {
+                    if reached.insert(*next) {
+                        stack.push(*next);
+                    }
+                }
+            }
+            reached
+        })
+        .collect();
+
+    let mut ambiguities = Vec::new();
+    for (first, first_system) in systems.iter().enumerate() {
+        for (second, second_system) in systems.iter().enumerate().skip(first + 1) {
+            if reachable[first].contains(&second) || reachable[second].contains(&first) {
+                continue;
+            }
+            let conflicts = first_system.access.conflicts(&second_system.access);
+            if !conflicts.is_empty() {
+                ambiguities.push(Ambiguity {
+                    schedule,
+                    systems: [first_system.name, second_system.name],
+                    conflicts,
+                });
+            }
+        }
+    }
+    ambiguities
+}
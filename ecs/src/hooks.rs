@@ -0,0 +1,78 @@
+use std::{
+    any::TypeId,
+    fmt::{self, Debug, Formatter},
+    sync::Arc,
+};
+
+use ahash::HashMap;
+
+use crate::{EntityId, World};
+
+/// Called with the entity whose component was added or is about to be removed. The component
+/// can be read through [`World::get_entity_commands`] in both cases.
+pub type Hook = Arc<dyn Fn(&mut World, EntityId)>;
+
+/// Hooks of every component type, see [`World::on_add`] and [`World::on_remove`]
+#[derive(Default)]
+pub(crate) struct ComponentHooks {
+    pub(crate) on_add: HashMap<TypeId, Vec<Hook>>,
+    pub(crate) on_remove: HashMap<TypeId, Vec<Hook>>,
+}
+
+impl Debug for ComponentHooks {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let count =
+            |hooks: &HashMap<TypeId, Vec<Hook>>| hooks.values().map(Vec::len).sum::<usize>();
+        f.debug_struct("ComponentHooks")
+            .field("on_add", &count(&self.on_add))
+            .field("on_remove", &count(&self.on_remove))
+            .finish()
+    }
+}
+
+impl World {
+    /// Runs `hook` whenever a `C` is added to an entity, including by [`World::spawn`] and by
+    /// replacing one it already had
+    pub fn on_add<C: 'static>(&mut self, hook: impl Fn(&mut World, EntityId) + 'static) {
+        self.hooks
+            .on_add
+            .entry(TypeId::of::<C>())
+            .or_default()
+            .push(Arc::new(hook));
+    }
+
+    /// Runs `hook` whenever a `C` is about to leave an entity, including when the entity is
+    /// removed and when the component is replaced
+    pub fn on_remove<C: 'static>(&mut self, hook: impl Fn(&mut World, EntityId) + 'static) {
+        self.hooks
+            .on_remove
+            .entry(TypeId::of::<C>())
+            .or_default()
+            .push(Arc::new(hook));
+    }
+
+    /// Cloned out, so the hooks can change the world, including registering more hooks
+    pub(crate) fn hooks_for(
+        &self,
+        hooks: fn(&ComponentHooks) -> &HashMap<TypeId, Vec<Hook>>,
+        types: &[TypeId],
+    ) -> Vec<Hook> {
+        types
+            .iter()
+            .filter_map(|ty| hooks(&self.hooks).get(ty))
+            .flatten()
+            .cloned()
+            .collect()
+    }
+
+    pub(crate) fn run_hooks(
+        &mut self,
+        hooks: fn(&ComponentHooks) -> &HashMap<TypeId, Vec<Hook>>,
+        entity: EntityId,
+        types: &[TypeId],
+    ) {
+        for hook in self.hooks_for(hooks, types) {
+            hook(self, entity);
+        }
+    }
+}
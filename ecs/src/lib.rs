@@ -1,22 +1,38 @@
 // Inspired by Bevy's ECS (MIT/Apache-2.0)
 // Though this is a very naive first attempt
 
+// Lets the derives refer to `::ecs` from inside this crate too
+extern crate self as ecs;
+
+mod ambiguity;
+mod hooks;
+mod query;
+
 use ahash::{HashMap, HashSet};
+pub use ambiguity::{Access, Ambiguity, AmbiguityReport};
+pub use ecs_macros::SystemParam;
+pub use hooks::Hook;
+pub use query::{Allow, Disabled, QueryFilter, With, Without};
 
 use std::{
-    any::{Any, TypeId},
-    fmt::{self, Debug, Formatter},
+    any::{self, Any, TypeId},
+    collections::BTreeMap,
+    fmt::{self, Debug, Display, Formatter},
     hash::Hash,
-    ops::Deref,
-    sync::{Arc, Mutex},
+    sync::{Arc, Mutex, MutexGuard},
 };
 
 #[derive(Debug, Default)]
 pub struct World {
     entities: HashMap<EntityId, HashMap<TypeId, Box<dyn Component>>>,
-    systems: HashMap<Schedule, HashMap<TypeId, Arc<Mutex<System>>>>,
+    systems: HashMap<Schedule, Vec<Arc<Mutex<SystemConfig>>>>,
     resources: HashMap<TypeId, Box<dyn Any>>,
+    /// Type names of the resources, which `Any` doesn't keep
+    resource_names: HashMap<TypeId, &'static str>,
     entity_id_generator: IdGenerator,
+    /// Whether the next schedule to run prints [`World::ambiguities`] first
+    report_ambiguities: bool,
+    hooks: hooks::ComponentHooks,
 }
 
 impl World {
@@ -24,42 +40,143 @@ impl World {
         Self::default()
     }
 
+    /// A world whose entity ids are drawn from `seed`, e.g. the world seed
+    pub fn with_seed(seed: u64) -> Self {
+        Self {
+            entity_id_generator: IdGenerator::with_seed(seed),
+            ..Self::default()
+        }
+    }
+
     pub fn run_schedule(&mut self, schedule: Schedule) {
+        if self.report_ambiguities {
+            self.report_ambiguities = false;
+            eprintln!("{}", self.ambiguities());
+        }
         if let Some(systems) = self.systems.get(&schedule) {
-            let systems: Vec<_> = systems.values().cloned().collect();
+            let systems = systems.clone();
             for system in systems {
                 let mut system = system.lock().unwrap();
-                system.call(self);
+                system.system.call(self);
             }
         }
     }
 
-    pub fn spawn(&mut self, components: Vec<Box<dyn Component>>) {
-        self.entities.insert(
-            EntityId(self.entity_id_generator.generate()),
-            components
-                .into_iter()
-                .map(|c| ((*c).type_id(), c))
-                .collect(),
-        );
+    pub fn spawn(&mut self, components: Vec<Box<dyn Component>>) -> EntityId {
+        let entity = EntityId(self.entity_id_generator.generate());
+        let components: HashMap<_, _> = components
+            .into_iter()
+            .map(|c| (c.as_ref().as_any().type_id(), c))
+            .collect();
+        let types: Vec<_> = components.keys().copied().collect();
+        self.entities.insert(entity, components);
+        self.run_hooks(|hooks| &hooks.on_add, entity, &types);
+        entity
+    }
+
+    /// Spawns an entity for each item of `batch`, reserving room for all of them up front.
+    /// Add hooks run once every entity is spawned.
+    ///
+    /// # Panics
+    /// If the items don't all have the same component types in the same order
+    pub fn spawn_batch<I>(&mut self, batch: I) -> Vec<EntityId>
+    where
+        I: IntoIterator<Item = Vec<Box<dyn Component>>>,
+    {
+        let batch = batch.into_iter();
+        let (len, _) = batch.size_hint();
+        self.entities.reserve(len);
+        self.entity_id_generator.reserve(len);
+        let mut spawned = Vec::with_capacity(len);
+        let mut layout: Option<Vec<TypeId>> = None;
+        for components in batch {
+            let types = components.iter().map(|c| c.as_ref().as_any().type_id());
+            let layout = match &layout {
+                Some(layout) => {
+                    assert!(
+                        types.eq(layout.iter().copied()),
+                        "spawn_batch items have different components"
+                    );
+                    layout
+                }
+                None => layout.insert(types.collect()),
+            };
+            let mut entity_components =
+                HashMap::with_capacity_and_hasher(layout.len(), Default::default());
+            entity_components.extend(layout.iter().copied().zip(components));
+            let entity = EntityId(self.entity_id_generator.generate());
+            self.entities.insert(entity, entity_components);
+            spawned.push(entity);
+        }
+
+        let hooks = layout
+            .map(|layout| self.hooks_for(|hooks| &hooks.on_add, &layout))
+            .unwrap_or_default();
+        for entity in &spawned {
+            for hook in &hooks {
+                hook(self, *entity);
+            }
+        }
+        spawned
     }
 
     pub fn insert_resource<R: 'static + Resource>(&mut self, resource: R) {
-        self.resources.insert(
-            TypeId::of::<R>(),
-            Box::new(Arc::new(Mutex::new(Box::new(resource)))),
-        );
+        self.resources
+            .insert(TypeId::of::<R>(), Box::new(Arc::new(Mutex::new(resource))));
+        self.resource_names
+            .insert(TypeId::of::<R>(), any::type_name::<R>());
     }
 
-    pub fn insert_systems(&mut self, schedule: Schedule, systems: Vec<System>) {
-        let systems = systems
+    /// Replaces the systems of `schedule`, which run in the order given except where
+    /// [`SystemConfig::after`] and [`SystemConfig::before`] say otherwise
+    ///
+    /// # Panics
+    /// If the constraints form a cycle
+    pub fn insert_systems(&mut self, schedule: Schedule, systems: Vec<impl Into<SystemConfig>>) {
+        let systems: Vec<SystemConfig> = systems.into_iter().map(Into::into).collect();
+        let order = ambiguity::sort(&systems.iter().collect::<Vec<_>>()).unwrap_or_else(|cycle| {
+            panic!(
+                "systems in {schedule:?} are ordered in a cycle: {}",
+                cycle.join(", ")
+            )
+        });
+        let mut systems: Vec<_> = systems.into_iter().map(Some).collect();
+        let systems = order
             .into_iter()
-            .map(|sys| (sys.type_id(), Arc::new(Mutex::new(sys))))
+            .map(|index| Arc::new(Mutex::new(systems[index].take().unwrap())))
             .collect();
         self.systems.insert(schedule, systems);
     }
 
-    pub fn get_entity_commands(&mut self, entity: EntityId) -> Option<EntityCommands> {
+    /// Opts in to printing [`Self::ambiguities`] to stderr when the first schedule runs
+    pub fn report_ambiguities(&mut self) {
+        self.report_ambiguities = true;
+    }
+
+    /// Pairs of systems in the same schedule that use a resource, at least one of them
+    /// mutably, without an order between them. Only access declared with
+    /// [`SystemConfig::params`]
+    /// counts.
+    pub fn ambiguities(&self) -> AmbiguityReport {
+        let mut schedules: Vec<_> = self.systems.iter().collect();
+        schedules.sort_unstable_by_key(|(schedule, _)| **schedule);
+        AmbiguityReport(
+            schedules
+                .into_iter()
+                .flat_map(|(schedule, systems)| {
+                    let systems: Vec<_> = systems
+                        .iter()
+                        .map(|system| system.lock().unwrap())
+                        .collect();
+                    let systems: Vec<&SystemConfig> =
+                        systems.iter().map(|system| &**system).collect();
+                    ambiguity::ambiguities(*schedule, &systems)
+                })
+                .collect(),
+        )
+    }
+
+    pub fn get_entity_commands(&mut self, entity: EntityId) -> Option<EntityCommands<'_>> {
         if self.entities.contains_key(&entity) {
             Some(EntityCommands {
                 entity,
@@ -73,6 +190,74 @@ impl World {
     pub fn get<P: SystemParam>(&self) -> Option<P> {
         P::get_from_world(self)
     }
+
+    /// Counts of what the world holds, for debugging
+    pub fn stats(&self) -> WorldStats {
+        let mut components = BTreeMap::new();
+        for component in self.entities.values().flat_map(HashMap::values) {
+            // Through the box, which is a `Component` itself
+            *components
+                .entry(component.as_ref().type_name())
+                .or_default() += 1;
+        }
+        let mut resources: Vec<_> = self.resource_names.values().copied().collect();
+        resources.sort_unstable();
+        WorldStats {
+            entities: self.entities.len(),
+            components,
+            resources,
+            systems: self
+                .systems
+                .iter()
+                .map(|(schedule, systems)| (*schedule, systems.len()))
+                .collect(),
+        }
+    }
+
+    /// [`Self::stats`] followed by every entity and its components, one per line
+    pub fn dump(&self) -> String {
+        let mut dump = self.stats().to_string();
+        let mut entities: Vec<_> = self.entities.iter().collect();
+        entities.sort_unstable_by_key(|(entity, _)| entity.0);
+        for (entity, components) in entities {
+            dump += &format!("\n{entity:?}");
+            let mut components: Vec<_> = components.values().collect();
+            components.sort_unstable_by_key(|component| component.as_ref().type_name());
+            for component in components {
+                dump += &format!("\n  {component:?}");
+            }
+        }
+        dump
+    }
+}
+
+/// See [`World::stats`]. Types are listed by their full path, in order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WorldStats {
+    pub entities: usize,
+    /// Entities having each component type
+    pub components: BTreeMap<&'static str, usize>,
+    pub resources: Vec<&'static str>,
+    /// Systems in each schedule that has any
+    pub systems: BTreeMap<Schedule, usize>,
+}
+
+impl Display for WorldStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} entities", self.entities)?;
+        for (name, count) in &self.components {
+            writeln!(f, "  {count:>6} {name}")?;
+        }
+        writeln!(f, "{} resources", self.resources.len())?;
+        for name in &self.resources {
+            writeln!(f, "  {name}")?;
+        }
+        write!(f, "{} systems", self.systems.values().sum::<usize>())?;
+        for (schedule, count) in &self.systems {
+            write!(f, "\n  {count:>6} {schedule:?}")?;
+        }
+        Ok(())
+    }
 }
 
 pub struct EntityCommands<'w> {
@@ -81,12 +266,27 @@ pub struct EntityCommands<'w> {
 }
 
 impl EntityCommands<'_> {
+    /// Replaces components of the same types, running their remove hooks first
     pub fn insert(&mut self, components: Vec<Box<dyn Component>>) {
+        let components: Vec<_> = components
+            .into_iter()
+            .map(|c| (c.as_ref().as_any().type_id(), c))
+            .collect();
+        let types: Vec<_> = components.iter().map(|(ty, _)| *ty).collect();
+        let replaced: Vec<_> = types
+            .iter()
+            .copied()
+            .filter(|ty| self.world.entities[&self.entity].contains_key(ty))
+            .collect();
         self.world
-            .entities
-            .get_mut(&self.entity)
-            .unwrap()
-            .extend(components.into_iter().map(|c| ((*c).type_id(), c)));
+            .run_hooks(|hooks| &hooks.on_remove, self.entity, &replaced);
+        // A hook may have removed the entity
+        let Some(entity) = self.world.entities.get_mut(&self.entity) else {
+            return;
+        };
+        entity.extend(components);
+        self.world
+            .run_hooks(|hooks| &hooks.on_add, self.entity, &types);
     }
 
     pub fn get<C: Component + 'static>(&self) -> Option<&C> {
@@ -94,21 +294,54 @@ impl EntityCommands<'_> {
             .entities
             .get(&self.entity)?
             .get(&TypeId::of::<C>())?
+            .as_ref()
             .as_any()
             .downcast_ref::<C>()
     }
 
+    /// Removes the entity, after running the remove hooks of its components
     pub fn remove(&mut self) {
+        let types: Vec<_> = self.world.entities[&self.entity].keys().copied().collect();
+        self.world
+            .run_hooks(|hooks| &hooks.on_remove, self.entity, &types);
         self.world.entities.remove(&self.entity);
     }
+
+    /// See [`Disabled`]
+    pub fn disable(&mut self) {
+        self.insert(vec![Box::new(Disabled)]);
+    }
+
+    pub fn enable(&mut self) {
+        self.remove_component::<Disabled>();
+    }
+
+    /// Whether the entity had a `C` to remove
+    pub fn remove_component<C: Component + 'static>(&mut self) -> bool {
+        let ty = TypeId::of::<C>();
+        if !self.world.entities[&self.entity].contains_key(&ty) {
+            return false;
+        }
+        self.world
+            .run_hooks(|hooks| &hooks.on_remove, self.entity, &[ty]);
+        if let Some(entity) = self.world.entities.get_mut(&self.entity) {
+            entity.remove(&ty);
+        }
+        true
+    }
 }
 
 pub trait Component: Debug + Send + Sync {
     fn as_any(&self) -> &dyn Any;
     fn as_any_mut(&mut self) -> &mut dyn Any;
+    fn type_name(&self) -> &'static str;
 }
 
 impl<T: Debug + Send + Sync + 'static> Component for T {
+    fn type_name(&self) -> &'static str {
+        any::type_name::<T>()
+    }
+
     fn as_any(&self) -> &dyn Any {
         self
     }
@@ -120,7 +353,7 @@ impl<T: Debug + Send + Sync + 'static> Component for T {
 
 impl PartialEq for dyn Component {
     fn eq(&self, other: &Self) -> bool {
-        self.type_id() == other.type_id()
+        self.as_any().type_id() == other.as_any().type_id()
     }
 }
 
@@ -129,9 +362,12 @@ impl Eq for dyn Component {}
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct EntityId(u32);
 
+/// Hands out unique, random looking ids from a seeded splitmix64 sequence, so the same spawns
+/// get the same ids on every run
 #[derive(Debug, Default)]
 pub struct IdGenerator {
     lookup_table: HashSet<u32>,
+    state: u64,
 }
 
 impl IdGenerator {
@@ -139,19 +375,34 @@ impl IdGenerator {
         Self::default()
     }
 
-    pub fn generate(&mut self) -> u32 {
-        fn generate_id() -> u32 {
-            rand::random_range(0..=u32::MAX)
+    pub fn with_seed(seed: u64) -> Self {
+        Self {
+            lookup_table: HashSet::default(),
+            state: seed,
         }
+    }
+
+    pub fn reserve(&mut self, additional: usize) {
+        self.lookup_table.reserve(additional);
+    }
 
-        let mut id = generate_id();
+    pub fn generate(&mut self) -> u32 {
+        let mut id = self.next_id();
         while self.lookup_table.contains(&id) {
-            id = generate_id();
+            id = self.next_id();
         }
 
         self.lookup_table.insert(id);
         id
     }
+
+    fn next_id(&mut self) -> u32 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut x = self.state;
+        x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        (x ^ (x >> 31)) as u32
+    }
 }
 
 pub struct System(pub Box<dyn FnMut(&mut World)>);
@@ -171,19 +422,125 @@ impl Debug for System {
     }
 }
 
+/// A [`System`] with a name, the params it uses and constraints on its order, for
+/// [`World::insert_systems`] to sort by and [`World::ambiguities`] to check
+pub struct SystemConfig {
+    name: &'static str,
+    system: System,
+    access: Access,
+    after: Vec<&'static str>,
+    before: Vec<&'static str>,
+}
+
+impl SystemConfig {
+    /// Named after the path of `run`, e.g. `game::physics::step` for a function
+    pub fn new<F: FnMut(&mut World) + 'static>(run: F) -> Self {
+        Self {
+            name: any::type_name::<F>(),
+            ..Self::from(System(Box::new(run)))
+        }
+    }
+
+    pub fn named(mut self, name: &'static str) -> Self {
+        self.name = name;
+        self
+    }
+
+    /// Declares the params the system gets from the world, for [`World::ambiguities`]
+    pub fn params<P: SystemParam>(mut self) -> Self {
+        P::access(&mut self.access);
+        self
+    }
+
+    /// Runs after every system of the schedule with this name
+    pub fn after(mut self, name: &'static str) -> Self {
+        self.after.push(name);
+        self
+    }
+
+    /// Runs before every system of the schedule with this name
+    pub fn before(mut self, name: &'static str) -> Self {
+        self.before.push(name);
+        self
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+/// Unordered and without declared params, so it's never reported as ambiguous
+impl From<System> for SystemConfig {
+    fn from(system: System) -> Self {
+        Self {
+            name: any::type_name::<System>(),
+            system,
+            access: Access::default(),
+            after: Vec::new(),
+            before: Vec::new(),
+        }
+    }
+}
+
+impl Debug for SystemConfig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SystemConfig")
+            .field("name", &self.name)
+            .field("access", &self.access)
+            .field("after", &self.after)
+            .field("before", &self.before)
+            .finish_non_exhaustive()
+    }
+}
+
 pub trait SystemParam: Debug {
     fn get_from_world(world: &World) -> Option<Self>
     where
         Self: Sized;
+
+    /// Adds what the param reads and writes
+    fn access(_access: &mut Access)
+    where
+        Self: Sized,
+    {
+    }
 }
 
+/// Every param of the tuple, or `None` if any is missing
+macro_rules! impl_system_param_tuple {
+    ($($param:ident),*) => {
+        impl<$($param: SystemParam),*> SystemParam for ($($param,)*) {
+            fn get_from_world(world: &World) -> Option<Self> {
+                Some(($($param::get_from_world(world)?,)*))
+            }
+
+            fn access(access: &mut Access) {
+                $($param::access(access);)*
+            }
+        }
+    };
+}
+
+impl_system_param_tuple!(P0);
+impl_system_param_tuple!(P0, P1);
+impl_system_param_tuple!(P0, P1, P2);
+impl_system_param_tuple!(P0, P1, P2, P3);
+impl_system_param_tuple!(P0, P1, P2, P3, P4);
+impl_system_param_tuple!(P0, P1, P2, P3, P4, P5);
+impl_system_param_tuple!(P0, P1, P2, P3, P4, P5, P6);
+impl_system_param_tuple!(P0, P1, P2, P3, P4, P5, P6, P7);
+impl_system_param_tuple!(P0, P1, P2, P3, P4, P5, P6, P7, P8);
+impl_system_param_tuple!(P0, P1, P2, P3, P4, P5, P6, P7, P8, P9);
+impl_system_param_tuple!(P0, P1, P2, P3, P4, P5, P6, P7, P8, P9, P10);
+impl_system_param_tuple!(P0, P1, P2, P3, P4, P5, P6, P7, P8, P9, P10, P11);
+
+/// Shares the resource with [`ResMut`], so reading it takes the same lock
 #[derive(Debug, Clone)]
-pub struct Res<R: Resource>(Arc<R>);
+pub struct Res<R: Resource>(Arc<Mutex<R>>);
 
-impl<R: Resource> Deref for Res<R> {
-    type Target = R;
-    fn deref(&self) -> &Self::Target {
-        self.0.as_ref()
+impl<R: Resource> Res<R> {
+    pub fn get(&self) -> MutexGuard<'_, R> {
+        self.0.lock().unwrap()
     }
 }
 
@@ -197,10 +554,14 @@ impl<R: Resource + 'static> SystemParam for Res<R> {
         world
             .resources
             .get(&TypeId::of::<R>())?
-            .downcast_ref::<Arc<R>>()
+            .downcast_ref::<Arc<Mutex<R>>>()
             .cloned()
             .map(Res)
     }
+
+    fn access(access: &mut Access) {
+        access.read::<R>();
+    }
 }
 
 impl<R: Resource + 'static> SystemParam for ResMut<R> {
@@ -212,9 +573,13 @@ impl<R: Resource + 'static> SystemParam for ResMut<R> {
             .cloned()
             .map(ResMut)
     }
+
+    fn access(access: &mut Access) {
+        access.write::<R>();
+    }
 }
 
-#[derive(Debug, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Schedule {
     Initialize,
     PreStartup,
@@ -237,6 +602,219 @@ mod tests {
         world.run_schedule(Schedule::Startup);
     }
 
+    #[test]
+    fn stats_count_entities_components_and_systems() {
+        let mut world = World::new();
+        world.insert_systems(
+            Schedule::Update,
+            vec![System(Box::new(system)), System(Box::new(|_| {}))],
+        );
+        world.insert_resource(Person { name: "Anthony" });
+        world.spawn(vec![Box::new(Person { name: "Ada" }), Box::new(1u32)]);
+        world.spawn(vec![Box::new(Person { name: "Grace" })]);
+
+        let stats = world.stats();
+        assert_eq!(stats.entities, 2);
+        assert_eq!(stats.components[any::type_name::<Person>()], 2);
+        assert_eq!(stats.components["u32"], 1);
+        assert_eq!(stats.resources, [any::type_name::<Person>()]);
+        assert_eq!(stats.systems[&Schedule::Update], 2);
+
+        let dump = world.dump();
+        assert!(dump.starts_with("2 entities"));
+        assert!(dump.contains("Person { name: \"Grace\" }"));
+    }
+
+    #[derive(SystemParam, Debug)]
+    struct Population {
+        person: Res<Person>,
+        count: ResMut<Count>,
+    }
+
+    /// Uses neither the world nor the access it's handed
+    #[derive(SystemParam, Debug)]
+    struct Nothing;
+
+    #[derive(Debug)]
+    struct Count(u32);
+
+    impl Resource for Count {}
+
+    #[test]
+    fn derived_and_tuple_params_need_every_field() {
+        let mut world = World::new();
+        assert!(world.get::<Nothing>().is_some());
+        world.insert_resource(Person { name: "Anthony" });
+        assert!(world.get::<Population>().is_none());
+        assert!(world.get::<(Res<Person>, ResMut<Count>)>().is_none());
+
+        world.insert_resource(Count(0));
+        let population = world.get::<Population>().unwrap();
+        population.count.0.lock().unwrap().0 += 1;
+        assert_eq!(population.person.get().name, "Anthony");
+
+        let (_, count) = world.get::<(Res<Person>, Res<Count>)>().unwrap();
+        assert_eq!(count.get().0, 1);
+    }
+
+    fn add_one(world: &mut World) {
+        world.get::<ResMut<Count>>().unwrap().0.lock().unwrap().0 += 1;
+    }
+
+    fn double(world: &mut World) {
+        world.get::<ResMut<Count>>().unwrap().0.lock().unwrap().0 *= 2;
+    }
+
+    #[test]
+    fn unordered_conflicting_systems_are_ambiguous() {
+        let mut world = World::new();
+        world.insert_resource(Count(1));
+        world.insert_systems(
+            Schedule::Update,
+            vec![
+                SystemConfig::new(add_one).params::<ResMut<Count>>(),
+                SystemConfig::new(double).params::<ResMut<Count>>(),
+                SystemConfig::new(|_| {})
+                    .named("reader")
+                    .params::<Population>(),
+            ],
+        );
+        let report = world.ambiguities();
+        assert_eq!(report.0.len(), 3);
+        assert_eq!(
+            report.0[0].systems,
+            [
+                any::type_name_of_val(&add_one),
+                any::type_name_of_val(&double)
+            ]
+        );
+        assert_eq!(
+            report.0[0].conflicts.iter().copied().collect::<Vec<_>>(),
+            [any::type_name::<Count>()]
+        );
+
+        // Ordered through the reader, and listed out of order
+        world.insert_systems(
+            Schedule::Update,
+            vec![
+                SystemConfig::new(add_one)
+                    .params::<ResMut<Count>>()
+                    .after("reader"),
+                SystemConfig::new(double)
+                    .params::<ResMut<Count>>()
+                    .before("reader"),
+                SystemConfig::new(|_| {})
+                    .named("reader")
+                    .params::<Population>(),
+            ],
+        );
+        assert_eq!(world.ambiguities(), AmbiguityReport::default());
+        world.run_schedule(Schedule::Update);
+        assert_eq!(world.get::<Res<Count>>().unwrap().get().0, 3);
+    }
+
+    #[derive(Debug)]
+    struct Mesh(&'static str);
+
+    #[test]
+    fn hooks_run_when_components_come_and_go() {
+        let mut world = World::new();
+        world.insert_resource(Count(0));
+        // Stands in for the renderer counting its GPU meshes
+        world.on_add::<Mesh>(|world, entity| {
+            let commands = world.get_entity_commands(entity).unwrap();
+            assert!(commands.get::<Mesh>().is_some());
+            world.get::<ResMut<Count>>().unwrap().0.lock().unwrap().0 += 1;
+        });
+        world.on_remove::<Mesh>(|world, entity| {
+            let commands = world.get_entity_commands(entity).unwrap();
+            assert!(commands.get::<Mesh>().is_some());
+            world.get::<ResMut<Count>>().unwrap().0.lock().unwrap().0 -= 1;
+        });
+        let count = |world: &World| world.get::<Res<Count>>().unwrap().get().0;
+
+        let cube = world.spawn(vec![Box::new(Mesh("cube"))]);
+        let sphere = world.spawn(vec![Box::new(1u32)]);
+        assert_eq!(count(&world), 1);
+
+        let mut commands = world.get_entity_commands(sphere).unwrap();
+        commands.insert(vec![Box::new(Mesh("sphere"))]);
+        assert_eq!(count(&world), 2);
+        let mut commands = world.get_entity_commands(sphere).unwrap();
+        commands.insert(vec![Box::new(Mesh("sphere"))]);
+        assert_eq!(count(&world), 2);
+        let mut commands = world.get_entity_commands(sphere).unwrap();
+        assert!(commands.remove_component::<Mesh>());
+        assert!(!commands.remove_component::<Mesh>());
+        assert_eq!(count(&world), 1);
+
+        world.get_entity_commands(cube).unwrap().remove();
+        assert_eq!(count(&world), 0);
+    }
+
+    #[test]
+    fn disabled_entities_keep_their_components_out_of_queries() {
+        let mut world = World::new();
+        world.insert_resource(Count(0));
+        world.on_remove::<Mesh>(|world, _| {
+            world.get::<ResMut<Count>>().unwrap().0.lock().unwrap().0 += 1;
+        });
+        let near = world.spawn(vec![Box::new(Mesh("near")), Box::new(1u32)]);
+        let far = world.spawn(vec![Box::new(Mesh("far"))]);
+        let names = |meshes: Vec<(EntityId, &Mesh)>| {
+            let mut names: Vec<_> = meshes.into_iter().map(|(_, mesh)| mesh.0).collect();
+            names.sort_unstable();
+            names
+        };
+
+        world.get_entity_commands(far).unwrap().disable();
+        assert_eq!(names(world.query::<Mesh>().collect()), ["near"]);
+        assert_eq!(
+            names(world.query_filtered::<Mesh, Allow<Disabled>>().collect()),
+            ["far", "near"]
+        );
+        assert_eq!(
+            names(
+                world
+                    .query_filtered::<Mesh, (Without<u32>, Allow<Disabled>)>()
+                    .collect()
+            ),
+            ["far"]
+        );
+        assert_eq!(
+            world.query_filtered::<Mesh, With<u32>>().next().unwrap().0,
+            near
+        );
+        assert_eq!(world.get::<Res<Count>>().unwrap().get().0, 0);
+
+        world.get_entity_commands(far).unwrap().enable();
+        assert_eq!(names(world.query::<Mesh>().collect()), ["far", "near"]);
+    }
+
+    #[derive(Debug)]
+    struct Particle(u32);
+
+    #[test]
+    fn spawn_batch_spawns_every_item_before_running_hooks() {
+        let mut world = World::new();
+        world.insert_resource(Count(0));
+        world.on_add::<Particle>(|world, _| {
+            let particles = world.query::<Particle>().count() as u32;
+            let count = world.get::<ResMut<Count>>().unwrap();
+            count.0.lock().unwrap().0 += particles;
+        });
+
+        let spawned = world.spawn_batch(
+            (0..1000).map(|i| vec![Box::new(Particle(i)) as Box<dyn Component>, Box::new(i)]),
+        );
+        assert_eq!(spawned.len(), 1000);
+        assert_eq!(world.stats().components[any::type_name::<Particle>()], 1000);
+        assert_eq!(world.get::<Res<Count>>().unwrap().get().0, 1000 * 1000);
+        let particle = world.get_entity_commands(spawned[7]).unwrap();
+        assert_eq!(particle.get::<Particle>().unwrap().0, 7);
+        assert_eq!(*particle.get::<u32>().unwrap(), 7);
+    }
+
     fn system(world: &mut World) {
         if let Some(person) = world.get::<Res<Person>>() {
             println!("person: {:?}", person);
@@ -0,0 +1,110 @@
+use std::{any::TypeId, marker::PhantomData};
+
+use ahash::HashMap;
+
+use crate::{Component, EntityId, World};
+
+/// Leaves an entity out of queries while keeping its components, e.g. for a chunk that is out
+/// of range but cached. Queries see it again once the marker is removed, and no remove hooks
+/// run in between. See [`Allow`] to query disabled entities too.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Disabled;
+
+/// Marker components every query leaves out unless its filter [`Allow`]s them
+const DEFAULT_FILTERS: [fn() -> TypeId; 1] = [TypeId::of::<Disabled>];
+
+type EntityComponents = HashMap<TypeId, Box<dyn Component>>;
+
+/// Narrows down the entities a query visits, on top of the [`DEFAULT_FILTERS`]
+pub trait QueryFilter {
+    fn matches(components: &EntityComponents) -> bool;
+
+    /// Whether entities having the default filter `ty` are visited anyway
+    fn allows(_ty: TypeId) -> bool {
+        false
+    }
+}
+
+/// Only the default filters
+impl QueryFilter for () {
+    fn matches(_components: &EntityComponents) -> bool {
+        true
+    }
+}
+
+pub struct With<C>(PhantomData<C>);
+
+impl<C: 'static> QueryFilter for With<C> {
+    fn matches(components: &EntityComponents) -> bool {
+        components.contains_key(&TypeId::of::<C>())
+    }
+}
+
+pub struct Without<C>(PhantomData<C>);
+
+impl<C: 'static> QueryFilter for Without<C> {
+    fn matches(components: &EntityComponents) -> bool {
+        !components.contains_key(&TypeId::of::<C>())
+    }
+}
+
+/// Lifts the default filter on `C`, e.g. `Allow<Disabled>` to visit disabled entities too
+pub struct Allow<C>(PhantomData<C>);
+
+impl<C: 'static> QueryFilter for Allow<C> {
+    fn matches(_components: &EntityComponents) -> bool {
+        true
+    }
+
+    fn allows(ty: TypeId) -> bool {
+        ty == TypeId::of::<C>()
+    }
+}
+
+/// Every filter of the tuple has to match
+macro_rules! impl_query_filter_tuple {
+    ($($filter:ident),*) => {
+        impl<$($filter: QueryFilter),*> QueryFilter for ($($filter,)*) {
+            fn matches(components: &EntityComponents) -> bool {
+                $($filter::matches(components))&&*
+            }
+
+            fn allows(ty: TypeId) -> bool {
+                $($filter::allows(ty))||*
+            }
+        }
+    };
+}
+
+impl_query_filter_tuple!(F0);
+impl_query_filter_tuple!(F0, F1);
+impl_query_filter_tuple!(F0, F1, F2);
+impl_query_filter_tuple!(F0, F1, F2, F3);
+
+impl World {
+    /// Every entity having a `C`, except disabled ones
+    pub fn query<C: Component + 'static>(&self) -> impl Iterator<Item = (EntityId, &C)> {
+        self.query_filtered::<C, ()>()
+    }
+
+    /// Every entity having a `C` that passes `F` and the default filters `F` doesn't allow
+    pub fn query_filtered<C: Component + 'static, F: QueryFilter>(
+        &self,
+    ) -> impl Iterator<Item = (EntityId, &C)> {
+        self.entities.iter().filter_map(|(entity, components)| {
+            let filtered = DEFAULT_FILTERS.iter().any(|ty| {
+                let ty = ty();
+                components.contains_key(&ty) && !F::allows(ty)
+            });
+            if filtered || !F::matches(components) {
+                return None;
+            }
+            let component = components
+                .get(&TypeId::of::<C>())?
+                .as_ref()
+                .as_any()
+                .downcast_ref::<C>()?;
+            Some((*entity, component))
+        })
+    }
+}
@@ -0,0 +1,92 @@
+# Text shown to the player. Other languages go next to this file as <language>.ftl, e.g.
+# de.ftl, and are picked with `language` in vx.toml; any message they leave out is taken
+# from here.
+
+## Files
+
+saved = Saved { $path }
+save-failed = Could not save { $path }: { $error }
+load-failed = Could not load { $path }: { $error }
+loaded = Loaded { $path }
+world-save-failed = Could not save the world: { $error }
+unknown-saved-component = Skipping unknown saved component { $name }
+save-migrated = Upgraded { $path } from version { $from }: { $description }
+
+## Settings
+
+config-reload-failed = Could not reload { $path }, keeping the current settings: { $error }
+config-compare-failed = Could not compare { $path } to the current settings: { $error }
+config-reloaded = Reloaded { $path }: { $keys }
+config-restart-required = Restart to apply { $keys }
+language-load-failed = Could not load the { $language } strings, using { $default }: { $error }
+debug-view = Debug view: { $view }
+
+## Game
+
+loaded-in = Loaded in { $seconds }s
+goodbye = Goodbye!
+pipeline-cache-read-failed = Could not read the pipeline cache: { $error }
+capturing-frame = Capturing frame { $frame }
+frame-dump-started = Dumping frames to { $target }
+frame-dump-finished = Dumped { $frames } frames, dropped { $dropped }
+frame-dump-failed = Frame dump stopped: { $error }
+
+## World editing
+
+select-region-first = Select a region first
+selection-too-large = Selection of { $voxels } voxels is over the limit of { $limit }
+copied-voxels = Copied { $voxels ->
+        [one] { $voxels } voxel
+       *[other] { $voxels } voxels
+    }
+selection-not-loaded = Can't copy a selection that isn't fully loaded
+model-editor-entered = Model editor: left click breaks, right click places, Enter saves, F12 previews and F11 leaves
+model-empty = Nothing to save, the model has no voxels
+model-saved = Saved the model as { $path } and { $vox }, and copied it for pasting
+
+## Mods
+
+mods-loaded = Loaded { $count ->
+        [one] 1 mod
+       *[other] { $count } mods
+    }: { $mods }
+mod-load-failed = Could not load the mod { $mod }: { $error }
+mod-api-mismatch = Skipped the mod { $mod }: it was built for mod API { $found }, this game has { $expected }
+mods-dir-unreadable = Could not read mods from { $path }: { $error }
+
+## Prefabs
+
+prefab-load-failed = Could not load the prefab { $prefab }: { $error }
+prefab-spawn-failed = Could not spawn the prefab { $prefab }: { $error }
+
+## Multiplayer
+
+protocol-version-mismatch = Can't connect: this game speaks protocol { $local } and the other side { $remote }, update whichever is older
+handshake-failed = Could not connect: { $error }
+player-joined = { $player } joined
+player-left = { $player } left
+not-operator = Only operators can run commands
+command-from-chat = { $player } ran /{ $command }
+command-ran = Ran /{ $command }
+commands-need-server = Commands can only be run on a server
+edit-rejected-protected = This area is protected
+edit-rejected-outdated = Someone else changed that first
+edit-rejected-unloaded = The server hasn't loaded that area
+
+## Server console
+
+console-greeting = Type help for a list of commands
+console-help =
+    save              write the world to disk
+    stop              save and shut down
+    kick <player>     disconnect a player
+    info              show the world's seed, tick and loaded chunks
+    help              show this list
+unknown-command = Unknown command "{ $command }", type help for a list
+command-usage = Usage: { $usage }
+stopping = Stopping
+player-not-connected = No player named "{ $player }" is connected
+server-info = Seed { $seed }, tick { $tick }, up for { $uptime }s
+server-counts = { $chunks } chunks loaded, { $block_ticks } block ticks pending, { $entities } entities
+spawn-chunks-generated = Generated { $chunks } spawn chunks
+world-save-load-failed = Could not load { $path }, starting from tick 0: { $error }
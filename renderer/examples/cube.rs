@@ -0,0 +1,136 @@
+//! Ray traces a single spinning cube in a window through the high level [`Renderer`].
+//!
+//! ```sh
+//! cargo run -p renderer --example cube
+//! ```
+
+use std::{error::Error, time::Instant};
+
+use bevy_ecs::entity::Entity;
+use data::{
+    instance::{BatchedInstance, InstanceBatch},
+    mesh::{Mesh, MeshHandle},
+    prelude::{EulerRot, Quat, Vec2},
+    transform::Transform,
+    voxel::Voxel,
+};
+use renderer::{renderer::Renderer, settings::RendererSettings};
+use winit::{
+    application::ApplicationHandler,
+    event::WindowEvent,
+    event_loop::{ActiveEventLoop, EventLoop},
+    raw_window_handle::{HasDisplayHandle, HasWindowHandle},
+    window::{Window, WindowId},
+};
+
+struct Scene {
+    window: Window,
+    renderer: Renderer,
+    cube: MeshHandle,
+}
+
+struct CubeExample {
+    scene: Option<Scene>,
+    start: Instant,
+    error: Option<Box<dyn Error>>,
+}
+
+fn window_size(window: &Window) -> Vec2 {
+    let size = window.inner_size();
+    Vec2::new(size.width as f32, size.height as f32)
+}
+
+impl CubeExample {
+    fn create_scene(event_loop: &ActiveEventLoop) -> Result<Scene, Box<dyn Error>> {
+        let window = event_loop.create_window(Window::default_attributes().with_title("cube"))?;
+        let handles = (
+            window.display_handle()?.as_raw(),
+            window.window_handle()?.as_raw(),
+        );
+        let mut renderer =
+            Renderer::new(handles, window_size(&window), RendererSettings::default())?;
+        let cube = renderer.add_mesh(Mesh::cube(1.0))?;
+        Ok(Scene {
+            window,
+            renderer,
+            cube,
+        })
+    }
+
+    fn draw(&mut self) -> Result<(), Box<dyn Error>> {
+        let Some(scene) = &mut self.scene else {
+            return Ok(());
+        };
+        let angle = self.start.elapsed().as_secs_f32();
+        let model = Transform::from_xyz(0.0, 0.0, -1.0).with_rotation(Quat::from_euler(
+            EulerRot::YXZ,
+            angle,
+            angle * 0.5,
+            0.0,
+        ));
+        scene.renderer.set_instances(&[InstanceBatch {
+            mesh: scene.cube,
+            instances: vec![BatchedInstance {
+                entity: Entity::PLACEHOLDER,
+                model: model.to_mat4(),
+                material: Voxel::Stone,
+            }],
+        }])?;
+        let camera = Transform::from_xyz(0.0, 0.0, 2.0);
+        scene
+            .renderer
+            .draw_frame(&camera, 70.0, window_size(&scene.window), &[])?;
+        scene.window.request_redraw();
+        Ok(())
+    }
+}
+
+impl ApplicationHandler for CubeExample {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        if self.scene.is_some() {
+            return;
+        }
+        match Self::create_scene(event_loop) {
+            Ok(scene) => {
+                scene.window.request_redraw();
+                self.scene = Some(scene);
+            }
+            Err(e) => {
+                self.error = Some(e);
+                event_loop.exit();
+            }
+        }
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, _: WindowId, event: WindowEvent) {
+        match event {
+            WindowEvent::CloseRequested => {
+                if let Some(scene) = self.scene.take() {
+                    scene.renderer.cleanup();
+                }
+                event_loop.exit();
+            }
+            WindowEvent::RedrawRequested => {
+                if let Err(e) = self.draw() {
+                    self.error = Some(e);
+                    event_loop.exit();
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let event_loop = EventLoop::new()?;
+    let mut app = CubeExample {
+        scene: None,
+        start: Instant::now(),
+        error: None,
+    };
+    event_loop.run_app(&mut app)?;
+    match app.error {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,85 @@
+//! Renders the renderer's built-in triangle scene and saves it as `triangle.png` without
+//! showing anything. Vulkan still needs a surface to create the device against, so the
+//! window exists but stays hidden.
+//!
+//! ```sh
+//! cargo run -p renderer --example triangle_png
+//! ```
+
+use std::{error::Error, fs};
+
+use data::{prelude::Vec2, transform::Transform};
+use renderer::{renderer::Renderer, settings::RendererSettings};
+use winit::{
+    application::ApplicationHandler,
+    event::WindowEvent,
+    event_loop::{ActiveEventLoop, EventLoop},
+    raw_window_handle::{HasDisplayHandle, HasWindowHandle},
+    window::{Window, WindowId},
+};
+
+const SIZE: Vec2 = Vec2::new(640.0, 480.0);
+/// Frames to accumulate before capturing; the capture is read back a few frames later
+const ACCUMULATED_FRAMES: u32 = 64;
+const MAX_FRAMES: u32 = ACCUMULATED_FRAMES + 16;
+
+#[derive(Default)]
+struct TrianglePng {
+    error: Option<Box<dyn Error>>,
+}
+
+impl ApplicationHandler for TrianglePng {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        self.error = render(event_loop).err();
+        event_loop.exit();
+    }
+
+    fn window_event(&mut self, _: &ActiveEventLoop, _: WindowId, _: WindowEvent) {}
+}
+
+fn render(event_loop: &ActiveEventLoop) -> Result<(), Box<dyn Error>> {
+    let window = event_loop.create_window(
+        Window::default_attributes()
+            .with_visible(false)
+            .with_inner_size(winit::dpi::PhysicalSize::new(SIZE.x, SIZE.y)),
+    )?;
+    let handles = (
+        window.display_handle()?.as_raw(),
+        window.window_handle()?.as_raw(),
+    );
+    let mut renderer = Renderer::new(handles, SIZE, RendererSettings::default())?;
+    if !renderer.render_path().uses_acceleration_structures() {
+        renderer.cleanup();
+        return Err("captures need a ray tracing capable device".into());
+    }
+
+    // The triangle faces +Z, half a unit out from the origin
+    let camera = Transform::from_xyz(0.0, 0.0, 2.0);
+    let mut capture = None;
+    for frame in 0..MAX_FRAMES {
+        if frame == ACCUMULATED_FRAMES {
+            renderer.request_capture();
+        }
+        renderer.draw_frame(&camera, 60.0, SIZE, &[])?;
+        capture = renderer.take_capture();
+        if capture.is_some() {
+            break;
+        }
+    }
+    renderer.cleanup();
+
+    let capture = capture.ok_or("the capture was never read back")?;
+    fs::write("triangle.png", capture.to_png())?;
+    println!("Saved triangle.png ({}x{})", capture.width, capture.height);
+    Ok(())
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let event_loop = EventLoop::new()?;
+    let mut app = TrianglePng::default();
+    event_loop.run_app(&mut app)?;
+    match app.error {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
@@ -1,28 +1,222 @@
-use std::{error::Error, mem, slice};
+use std::{collections::HashMap, error::Error, mem, slice};
 
 use ash::{khr::acceleration_structure, prelude::VkResult, vk};
-use bevy_ecs::system::Resource;
-use data::camera::CameraGpu;
+use bevy_ecs::{entity::Entity, system::Resource};
+use bytemuck::{Pod, Zeroable};
+use data::{
+    camera::CameraGpu,
+    instance::InstanceBatch,
+    material::MaterialGpu,
+    mesh::{Mesh, MeshHandle, Meshes, PrimitiveTopology},
+    prelude::Mat4,
+    voxel::Voxel,
+};
 
 use crate::{
-    buffer::Buffer, buffer_state::BufferState, init_state::InitState,
-    pipeline_state::PipelineState, swapchain_state::SwapchainState, INDICES, MAX_FRAMES_IN_FLIGHT,
-    VERTICES,
+    buffer::Buffer,
+    buffer_state::BufferState,
+    init_state::InitState,
+    pipeline_state::PipelineState,
+    reflection::PipelineReflection,
+    swapchain_state::{FrameDescriptorVersions, SwapchainState},
+    INDICES, MAX_FRAMES_IN_FLIGHT, VERTICES,
 };
 
+/// Per-instance data read by the hit shader through `gl_InstanceCustomIndexEXT`
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+struct InstanceGpu {
+    model: [[f32; 4]; 4],
+    position_address: vk::DeviceAddress,
+    index_address: vk::DeviceAddress,
+    material: u32,
+    /// In floats, since the position is the first of the interleaved attributes
+    vertex_stride: u32,
+    _padding: [u32; 2],
+}
+
+impl InstanceGpu {
+    /// `material` indexes the material buffer, which holds every voxel's in [`Voxel::ALL`] order
+    fn new(model: Mat4, geometry: &MeshGeometry, material: Voxel) -> Self {
+        Self {
+            model: model.to_cols_array_2d(),
+            position_address: geometry.position_address,
+            index_address: geometry.index_address,
+            material: material as u32,
+            vertex_stride: geometry.vertex_stride / mem::size_of::<f32>() as u32,
+            _padding: [0; 2],
+        }
+    }
+}
+
+/// Device addresses of a mesh's interleaved vertices and `u16` indices
+#[derive(Debug, Clone, Copy)]
+struct MeshGeometry {
+    position_address: vk::DeviceAddress,
+    index_address: vk::DeviceAddress,
+    /// In bytes, from [`Mesh::vertex_layout`]
+    vertex_stride: u32,
+}
+
+/// How [`AccelerationStructureState::update_instances`] brings the TLAS up to date
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TlasBuild {
+    Rebuild,
+    /// Updates the existing TLAS in place, which only works with the same instances
+    Refit,
+}
+
+fn tlas_shape(batches: &[InstanceBatch]) -> Vec<(MeshHandle, usize)> {
+    batches
+        .iter()
+        .map(|batch| (batch.mesh, batch.instances.len()))
+        .collect()
+}
+
+fn next_tlas_build(
+    previous: &[(MeshHandle, usize)],
+    shape: &[(MeshHandle, usize)],
+    refits: u32,
+) -> TlasBuild {
+    if previous == shape && refits < AccelerationStructureState::MAX_REFITS {
+        TlasBuild::Refit
+    } else {
+        TlasBuild::Rebuild
+    }
+}
+
+/// Everything derived from one set of instance batches
+struct InstanceUpload {
+    tlas_instances: Vec<vk::AccelerationStructureInstanceKHR>,
+    instances: Vec<InstanceGpu>,
+    /// Entity per instance custom index; `None` for the scene
+    entities: Vec<Option<Entity>>,
+}
+
+/// A prop mesh uploaded for instancing, with the BLAS all of its instances share. Meshes with
+/// identical contents share one as well.
+struct MeshBlas<'a> {
+    /// First mesh this was built for
+    source: MeshHandle,
+    vertex_buffer: Buffer<'a>,
+    index_buffer: Buffer<'a>,
+    geometry: MeshGeometry,
+    blas: vk::AccelerationStructureKHR,
+    blas_buffer: Buffer<'a>,
+}
+
+/// A skinned instance's own copies of its mesh, skinned on the CPU. Only the vertices change
+/// between frames, so they are written in place and the BLAS is refit rather than rebuilt.
+///
+/// Frames in flight trace whichever copy the TLAS was last built from, so a new pose goes into
+/// another copy, one per frame in flight, and the next TLAS build switches to it.
+struct SkinnedBlas<'a> {
+    index_buffer: Buffer<'a>,
+    vertex_count: u32,
+    index_count: u32,
+    copies: Vec<SkinnedCopy<'a>>,
+    /// Index into `copies` of the latest pose
+    live: usize,
+    /// Index into `copies` of the one the TLAS was last built from
+    traced: usize,
+}
+
+/// One pose of a [`SkinnedBlas`], sharing its index buffer
+struct SkinnedCopy<'a> {
+    /// Host visible and mapped
+    vertex_buffer: Buffer<'a>,
+    geometry: MeshGeometry,
+    blas: vk::AccelerationStructureKHR,
+    blas_buffer: Buffer<'a>,
+}
+
+impl SkinnedBlas<'_> {
+    /// Whether `mesh` can be refit into this BLAS, which needs the same triangles
+    fn fits(&self, mesh: &Mesh) -> bool {
+        self.vertex_count == mesh.positions.len() as u32
+            && self.index_count == mesh.indices.len() as u32
+            && self.copies[self.live].geometry.vertex_stride == mesh.vertex_layout().stride
+    }
+
+    /// The copy after the traced one. Once the current frame's fence has signaled, no frame
+    /// still in flight can be tracing it: a frame traces the copy the TLAS had when it was
+    /// recorded, and this one was last traced before the previous TLAS build.
+    fn next_copy(&self) -> usize {
+        (self.traced + 1) % self.copies.len()
+    }
+}
+
 #[derive(Resource)]
 pub struct AccelerationStructureState<'a> {
     loader: acceleration_structure::Device,
     fence: vk::Fence,
     blas: vk::AccelerationStructureKHR,
     blas_buffer: Buffer<'a>,
+    scene_geometry: MeshGeometry,
+    mesh_blases: Vec<MeshBlas<'a>>,
+    /// Index into `mesh_blases` for every synced mesh handle
+    mesh_blas_indices: Vec<usize>,
+    /// `mesh_blases` by [`Mesh::content_hash`] of their source
+    blas_by_hash: HashMap<u64, usize>,
+    /// Used instead of the shared BLAS of their mesh for these entities' instances
+    skinned_blases: HashMap<Entity, SkinnedBlas<'a>>,
+    instance_buffer: Buffer<'a>,
+    instance_entities: Vec<Option<Entity>>,
     tlas: vk::AccelerationStructureKHR,
     tlas_buffer: Buffer<'a>,
+    /// Mesh and instance count of every batch the TLAS was last built from
+    tlas_shape: Vec<(MeshHandle, usize)>,
+    /// Since the last full build
+    refits: u32,
     descriptor_pool: vk::DescriptorPool,
     descriptor_sets: Vec<vk::DescriptorSet>,
+    /// What the descriptor sets' layout holds, as reflected from the pipeline's shaders
+    descriptor_bindings: PipelineReflection,
+    descriptor_versions: FrameDescriptorVersions,
 }
 
 impl<'a> AccelerationStructureState<'a> {
+    /// Scene instance plus props
+    pub const MAX_INSTANCES: usize = 4096;
+
+    /// Refits in a row before the TLAS is rebuilt, since its bounds loosen with every refit
+    /// as instances move away from where they were when it was built
+    pub const MAX_REFITS: u32 = 120;
+    /// Material of the test scene instance, read from the instance buffer like any prop's
+    const SCENE_MATERIAL: Voxel = Voxel::Stone;
+
+    /// Building the TLAS so it can be refit costs a little trace performance
+    const TLAS_FLAGS: vk::BuildAccelerationStructureFlagsKHR =
+        vk::BuildAccelerationStructureFlagsKHR::from_raw(
+            vk::BuildAccelerationStructureFlagsKHR::PREFER_FAST_TRACE.as_raw()
+                | vk::BuildAccelerationStructureFlagsKHR::ALLOW_UPDATE.as_raw(),
+        );
+
+    /// Skinned vertices and indices are read by BLAS builds through their addresses
+    const SKINNED_BUFFER_USAGE: vk::BufferUsageFlags = vk::BufferUsageFlags::from_raw(
+        vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS.as_raw()
+            | vk::BufferUsageFlags::ACCELERATION_STRUCTURE_BUILD_INPUT_READ_ONLY_KHR.as_raw(),
+    );
+    /// Skinned BLASes are refit every frame, so they favor building over tracing
+    const SKINNED_BLAS_FLAGS: vk::BuildAccelerationStructureFlagsKHR =
+        vk::BuildAccelerationStructureFlagsKHR::from_raw(
+            vk::BuildAccelerationStructureFlagsKHR::PREFER_FAST_BUILD.as_raw()
+                | vk::BuildAccelerationStructureFlagsKHR::ALLOW_UPDATE.as_raw(),
+        );
+
+    /// Entity owning the instance with this custom index, as of the last TLAS rebuild
+    pub fn instance_entity(&self, custom_index: u32) -> Option<Entity> {
+        self.instance_entities
+            .get(custom_index as usize)
+            .copied()
+            .flatten()
+    }
+
+    /// Rebuilt by [`Self::update_instances`], so don't hold on to it across frames
+    pub const fn tlas(&self) -> vk::AccelerationStructureKHR {
+        self.tlas
+    }
+
     pub const fn descriptor_pool(&self) -> vk::DescriptorPool {
         self.descriptor_pool
     }
@@ -45,22 +239,58 @@ impl<'a> AccelerationStructureState<'a> {
                 .device()
                 .create_fence(&vk::FenceCreateInfo::default(), None)?;
 
+            let scene_geometry = MeshGeometry {
+                position_address: Self::buffer_address(
+                    pipeline_state,
+                    buffer_state.vertex_buffer(),
+                ),
+                index_address: Self::buffer_address(pipeline_state, buffer_state.index_buffer()),
+                vertex_stride: Mesh::LAYOUT.stride,
+            };
+
             let (blas, blas_buffer) = Self::create_blas(
                 &acceleration_structure_loader,
                 fence,
                 init_state,
                 pipeline_state,
-                buffer_state,
+                scene_geometry,
+                VERTICES.len() as u32,
+                INDICES.len() as u32,
+            )?;
+
+            let mut instance_buffer = Buffer::create(
+                init_state.instance(),
+                init_state.device(),
+                init_state.physical_device(),
+                (Self::MAX_INSTANCES * mem::size_of::<InstanceGpu>()) as u64,
+                vk::BufferUsageFlags::STORAGE_BUFFER,
+                vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+            )?;
+            instance_buffer.map_memory(init_state.device(), 0, vk::MemoryMapFlags::empty())?;
+
+            let upload = Self::build_instances(
+                &acceleration_structure_loader,
+                pipeline_state,
+                blas,
+                scene_geometry,
+                &[],
+                &[],
+                &HashMap::new(),
+                &[],
             )?;
+            instance_buffer.write(bytemuck::cast_slice(&upload.instances));
+
             let (tlas, tlas_buffer) = Self::create_tlas(
                 &acceleration_structure_loader,
                 fence,
                 init_state,
                 pipeline_state,
-                blas,
+                &upload.tlas_instances,
             )?;
 
-            let descriptor_pool = Self::create_descriptor_pool(init_state.device())?;
+            let descriptor_bindings = pipeline_state.reflection().clone();
+            let descriptor_pool =
+                Self::create_descriptor_pool(init_state.device(), &descriptor_bindings)?;
             let descriptor_sets = Self::create_descriptor_sets(
                 init_state.device(),
                 descriptor_pool,
@@ -72,16 +302,30 @@ impl<'a> AccelerationStructureState<'a> {
                 fence,
                 blas,
                 blas_buffer,
+                scene_geometry,
+                mesh_blases: Vec::new(),
+                mesh_blas_indices: Vec::new(),
+                blas_by_hash: HashMap::new(),
+                skinned_blases: HashMap::new(),
+                instance_buffer,
+                instance_entities: upload.entities,
                 tlas,
                 tlas_buffer,
+                tlas_shape: Vec::new(),
+                refits: 0,
                 descriptor_pool,
                 descriptor_sets,
+                descriptor_bindings,
+                descriptor_versions: FrameDescriptorVersions::default(),
             };
-            state.update_descriptor_sets(
-                init_state.device(),
-                buffer_state.uniform_buffers(),
-                swapchain_state.output_image_views(),
-            );
+            for frame in 0..MAX_FRAMES_IN_FLIGHT {
+                state.refresh_descriptor_set(
+                    init_state.device(),
+                    buffer_state,
+                    swapchain_state,
+                    frame,
+                );
+            }
 
             Ok(state)
         }
@@ -96,12 +340,15 @@ impl<'a> AccelerationStructureState<'a> {
     //     unimplemented!()
     // }
 
+    #[profiling::function]
     unsafe fn create_blas(
         loader: &acceleration_structure::Device,
         fence: vk::Fence,
         init_state: &InitState,
         pipeline_state: &PipelineState,
-        buffer_state: &BufferState,
+        geometry: MeshGeometry,
+        vertex_count: u32,
+        index_count: u32,
     ) -> Result<(vk::AccelerationStructureKHR, Buffer<'a>), Box<dyn Error>> {
         let buffer_usage_flags =
             vk::BufferUsageFlags::ACCELERATION_STRUCTURE_BUILD_INPUT_READ_ONLY_KHR
@@ -118,49 +365,35 @@ impl<'a> AccelerationStructureState<'a> {
             vk::MemoryPropertyFlags::DEVICE_LOCAL,
         )?;
 
-        let vertex_address = pipeline_state
-            .buffer_device_address_loader()
-            .get_buffer_device_address(
-                &vk::BufferDeviceAddressInfo::default()
-                    .buffer(buffer_state.vertex_buffer().handle()),
-            );
-
-        let index_address = pipeline_state
-            .buffer_device_address_loader()
-            .get_buffer_device_address(
-                &vk::BufferDeviceAddressInfo::default()
-                    .buffer(buffer_state.index_buffer().handle()),
-            );
-
         let transform_matrix_address = pipeline_state
             .buffer_device_address_loader()
             .get_buffer_device_address(
                 &vk::BufferDeviceAddressInfo::default().buffer(transform_matrix_buffer.handle()),
             );
 
-        let geometry = vk::AccelerationStructureGeometryKHR::default()
+        let blas_geometry = vk::AccelerationStructureGeometryKHR::default()
             .geometry_type(vk::GeometryTypeKHR::TRIANGLES)
             .flags(vk::GeometryFlagsKHR::OPAQUE)
             .geometry(vk::AccelerationStructureGeometryDataKHR {
                 triangles: vk::AccelerationStructureGeometryTrianglesDataKHR::default()
                     .vertex_format(vk::Format::R32G32B32_SFLOAT)
                     .vertex_data(vk::DeviceOrHostAddressConstKHR {
-                        device_address: vertex_address,
+                        device_address: geometry.position_address,
                     })
-                    .vertex_stride(mem::size_of::<[f32; 3]>() as vk::DeviceSize)
-                    .max_vertex(VERTICES.len() as u32 - 1)
+                    .vertex_stride(geometry.vertex_stride as vk::DeviceSize)
+                    .max_vertex(vertex_count.saturating_sub(1))
                     .index_type(vk::IndexType::UINT16)
                     .index_data(vk::DeviceOrHostAddressConstKHR {
-                        device_address: index_address,
+                        device_address: geometry.index_address,
                     })
                     .transform_data(vk::DeviceOrHostAddressConstKHR {
                         device_address: transform_matrix_address,
                     }),
             });
 
-        let geometries = &[geometry];
+        let geometries = &[blas_geometry];
 
-        let primitive_count = INDICES.len() as u32 / 3;
+        let primitive_count = index_count / 3;
 
         let mut build_info = vk::AccelerationStructureBuildGeometryInfoKHR::default()
             .ty(vk::AccelerationStructureTypeKHR::BOTTOM_LEVEL)
@@ -232,7 +465,7 @@ impl<'a> AccelerationStructureState<'a> {
             command_buffer,
             &[build_info],
             &[&[vk::AccelerationStructureBuildRangeInfoKHR::default()
-                .primitive_count(INDICES.len() as u32 / 3)
+                .primitive_count(primitive_count)
                 .primitive_offset(0)
                 .first_vertex(0)
                 .transform_offset(0)]],
@@ -262,78 +495,675 @@ impl<'a> AccelerationStructureState<'a> {
         Ok((acceleration_structure, buffer))
     }
 
-    unsafe fn create_tlas(
+    /// BLASes actually built, fewer than the synced meshes when some of them are identical
+    pub fn mesh_blas_count(&self) -> usize {
+        self.mesh_blases.len()
+    }
+
+    /// Uploads every mesh added to `meshes` since the last call and builds its BLAS, unless
+    /// an identical mesh already has one, e.g. the chunks of solid stone underground
+    #[profiling::function]
+    pub fn sync_meshes(
+        &mut self,
+        init_state: &InitState,
+        pipeline_state: &PipelineState,
+        meshes: &Meshes,
+    ) -> Result<(), Box<dyn Error>> {
+        for (handle, mesh) in meshes
+            .iter_with_handles()
+            .skip(self.mesh_blas_indices.len())
+        {
+            if mesh.topology != PrimitiveTopology::TriangleList {
+                return Err("Only triangle meshes can be ray traced".into());
+            }
+            let hash = mesh.content_hash();
+            let shared = self
+                .blas_by_hash
+                .get(&hash)
+                .copied()
+                .filter(|&shared| meshes.get(self.mesh_blases[shared].source) == Some(mesh));
+            let blas_index = match shared {
+                Some(shared) => shared,
+                None => {
+                    let mesh_blas =
+                        self.create_mesh_blas(init_state, pipeline_state, mesh, handle)?;
+                    self.mesh_blases.push(mesh_blas);
+                    // A colliding mesh keeps the first one's entry and just doesn't share
+                    self.blas_by_hash
+                        .entry(hash)
+                        .or_insert(self.mesh_blases.len() - 1);
+                    self.mesh_blases.len() - 1
+                }
+            };
+            self.mesh_blas_indices.push(blas_index);
+        }
+        Ok(())
+    }
+
+    /// Refits the TLAS to the scene plus one entry per prop instance when only transforms or
+    /// materials changed, and rebuilds it when instances were added or removed, or after
+    /// [`Self::MAX_REFITS`] refits in a row. All instances of a batch reference the same
+    /// BLAS; only their transform and material differ.
+    #[profiling::function]
+    pub fn update_instances(
+        &mut self,
+        init_state: &InitState,
+        pipeline_state: &PipelineState,
+        batches: &[InstanceBatch],
+    ) -> Result<(), Box<dyn Error>> {
+        unsafe {
+            let upload = Self::build_instances(
+                &self.loader,
+                pipeline_state,
+                self.blas,
+                self.scene_geometry,
+                &self.mesh_blases,
+                &self.mesh_blas_indices,
+                &self.skinned_blases,
+                batches,
+            )?;
+
+            for skinned in self.skinned_blases.values_mut() {
+                skinned.traced = skinned.live;
+            }
+
+            // Frames in flight may still be tracing against the old TLAS and instance data
+            init_state.wait_idle()?;
+
+            self.instance_buffer
+                .write(bytemuck::cast_slice(&upload.instances));
+            self.instance_entities = upload.entities;
+
+            let shape = tlas_shape(batches);
+            let build = next_tlas_build(&self.tlas_shape, &shape, self.refits);
+            self.tlas_shape = shape;
+            if build == TlasBuild::Refit {
+                self.refits += 1;
+                return Self::build_tlas(
+                    &self.loader,
+                    self.fence,
+                    init_state,
+                    pipeline_state,
+                    &upload.tlas_instances,
+                    self.tlas,
+                    TlasBuild::Refit,
+                );
+            }
+            self.refits = 0;
+
+            let (tlas, tlas_buffer) = Self::create_tlas(
+                &self.loader,
+                self.fence,
+                init_state,
+                pipeline_state,
+                &upload.tlas_instances,
+            )?;
+            self.loader.destroy_acceleration_structure(self.tlas, None);
+            self.tlas_buffer.cleanup(init_state.device());
+            self.tlas = tlas;
+            self.tlas_buffer = tlas_buffer;
+
+            for &descriptor_set in &self.descriptor_sets {
+                init_state.device().update_descriptor_sets(
+                    &[vk::WriteDescriptorSet::default()
+                        .dst_set(descriptor_set)
+                        .dst_binding(0)
+                        .dst_array_element(0)
+                        .descriptor_type(vk::DescriptorType::ACCELERATION_STRUCTURE_KHR)
+                        .descriptor_count(1)
+                        .push_next(
+                            &mut vk::WriteDescriptorSetAccelerationStructureKHR::default()
+                                .acceleration_structures(&[self.tlas]),
+                        )],
+                    &[],
+                );
+            }
+            Ok(())
+        }
+    }
+
+    fn create_mesh_blas(
+        &self,
+        init_state: &InitState,
+        pipeline_state: &PipelineState,
+        mesh: &Mesh,
+        source: MeshHandle,
+    ) -> Result<MeshBlas<'a>, Box<dyn Error>> {
+        unsafe {
+            let usage = vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS
+                | vk::BufferUsageFlags::ACCELERATION_STRUCTURE_BUILD_INPUT_READ_ONLY_KHR;
+
+            let vertex_buffer = Buffer::create_from_bytes_with_staging(
+                init_state.instance(),
+                init_state.device(),
+                init_state.physical_device(),
+                init_state.queues().command_fence().unwrap(),
+                init_state.queues().transfer(),
+                &mesh.pack_interleaved(),
+                usage,
+            )?;
+            let index_buffer = Buffer::create_from_bytes_with_staging(
+                init_state.instance(),
+                init_state.device(),
+                init_state.physical_device(),
+                init_state.queues().command_fence().unwrap(),
+                init_state.queues().transfer(),
+                bytemuck::cast_slice(&mesh.indices),
+                usage,
+            )?;
+
+            let geometry = MeshGeometry {
+                position_address: Self::buffer_address(pipeline_state, &vertex_buffer),
+                index_address: Self::buffer_address(pipeline_state, &index_buffer),
+                vertex_stride: mesh.vertex_layout().stride,
+            };
+
+            let (blas, blas_buffer) = Self::create_blas(
+                &self.loader,
+                self.fence,
+                init_state,
+                pipeline_state,
+                geometry,
+                mesh.positions.len() as u32,
+                mesh.indices.len() as u32,
+            )?;
+
+            Ok(MeshBlas {
+                source,
+                vertex_buffer,
+                index_buffer,
+                geometry,
+                blas,
+                blas_buffer,
+            })
+        }
+    }
+
+    /// Gives every entity in `skins` its own BLAS of its skinned mesh, refit in place when
+    /// only its vertices moved, and drops those of entities no longer skinned. Their
+    /// instances use it from the next [`Self::update_instances`], which has to follow so the
+    /// TLAS picks up the refit bounds. Only call once the current frame's fence has signaled,
+    /// see [`CommandState::wait_for_frame`](crate::command_state::CommandState::wait_for_frame).
+    #[profiling::function]
+    pub fn update_skins(
+        &mut self,
+        init_state: &InitState,
+        pipeline_state: &PipelineState,
+        skins: &[(Entity, Mesh)],
+    ) -> Result<(), Box<dyn Error>> {
+        if skins.is_empty() && self.skinned_blases.is_empty() {
+            return Ok(());
+        }
+        // Dropped BLASes may still be traced
+        if self.skinned_blases.iter().any(|(entity, skinned)| {
+            !skins
+                .iter()
+                .any(|(e, mesh)| e == entity && skinned.fits(mesh))
+        }) {
+            init_state.wait_idle()?;
+        }
+
+        let mut replaced = false;
+        self.skinned_blases.retain(|entity, skinned| {
+            let kept = skins
+                .iter()
+                .any(|(skinned_entity, mesh)| skinned_entity == entity && skinned.fits(mesh));
+            if !kept {
+                unsafe { Self::destroy_skinned_blas(&self.loader, init_state, skinned) };
+                replaced = true;
+            }
+            kept
+        });
+        for (entity, mesh) in skins {
+            if mesh.topology != PrimitiveTopology::TriangleList {
+                return Err("Only triangle meshes can be ray traced".into());
+            }
+            match self.skinned_blases.get_mut(entity) {
+                Some(skinned) => unsafe {
+                    let copy = skinned.next_copy();
+                    skinned.copies[copy]
+                        .vertex_buffer
+                        .write(&mesh.pack_interleaved());
+                    Self::build_skinned_blas(
+                        &self.loader,
+                        self.fence,
+                        init_state,
+                        pipeline_state,
+                        skinned,
+                        copy,
+                        vk::BuildAccelerationStructureModeKHR::UPDATE,
+                    )?;
+                    skinned.live = copy;
+                },
+                None => {
+                    let skinned = self.create_skinned_blas(init_state, pipeline_state, mesh)?;
+                    self.skinned_blases.insert(*entity, skinned);
+                    replaced = true;
+                }
+            }
+        }
+        if replaced {
+            // Instances now reference other BLASes, which a refit can't change
+            self.tlas_shape.clear();
+        }
+        Ok(())
+    }
+
+    fn create_skinned_blas(
+        &self,
+        init_state: &InitState,
+        pipeline_state: &PipelineState,
+        mesh: &Mesh,
+    ) -> Result<SkinnedBlas<'a>, Box<dyn Error>> {
+        unsafe {
+            let index_buffer = Buffer::create_from_bytes_with_staging(
+                init_state.instance(),
+                init_state.device(),
+                init_state.physical_device(),
+                init_state.queues().command_fence().unwrap(),
+                init_state.queues().transfer(),
+                bytemuck::cast_slice(&mesh.indices),
+                Self::SKINNED_BUFFER_USAGE,
+            )?;
+            let vertex_count = mesh.positions.len() as u32;
+            let index_count = mesh.indices.len() as u32;
+            let vertices = mesh.pack_interleaved();
+            let copies = (0..MAX_FRAMES_IN_FLIGHT)
+                .map(|_| {
+                    self.create_skinned_copy(
+                        init_state,
+                        pipeline_state,
+                        &vertices,
+                        &index_buffer,
+                        mesh.vertex_layout().stride,
+                        vertex_count,
+                        index_count,
+                    )
+                })
+                .collect::<Result<_, _>>()?;
+
+            let skinned = SkinnedBlas {
+                index_buffer,
+                vertex_count,
+                index_count,
+                copies,
+                live: 0,
+                traced: 0,
+            };
+            for copy in 0..skinned.copies.len() {
+                Self::build_skinned_blas(
+                    &self.loader,
+                    self.fence,
+                    init_state,
+                    pipeline_state,
+                    &skinned,
+                    copy,
+                    vk::BuildAccelerationStructureModeKHR::BUILD,
+                )?;
+            }
+            Ok(skinned)
+        }
+    }
+
+    /// Vertices and an unbuilt BLAS sized for them
+    #[allow(clippy::too_many_arguments)]
+    unsafe fn create_skinned_copy(
+        &self,
+        init_state: &InitState,
+        pipeline_state: &PipelineState,
+        vertices: &[u8],
+        index_buffer: &Buffer,
+        vertex_stride: u32,
+        vertex_count: u32,
+        index_count: u32,
+    ) -> Result<SkinnedCopy<'a>, Box<dyn Error>> {
+        let mut vertex_buffer = Buffer::create(
+            init_state.instance(),
+            init_state.device(),
+            init_state.physical_device(),
+            vertices.len().max(1) as u64,
+            Self::SKINNED_BUFFER_USAGE,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+        )?;
+        vertex_buffer.map_memory(init_state.device(), 0, vk::MemoryMapFlags::empty())?;
+        vertex_buffer.write(vertices);
+        let geometry = MeshGeometry {
+            position_address: Self::buffer_address(pipeline_state, &vertex_buffer),
+            index_address: Self::buffer_address(pipeline_state, index_buffer),
+            vertex_stride,
+        };
+
+        let geometries = [Self::skinned_geometry(geometry, vertex_count)];
+        let mut size_info = vk::AccelerationStructureBuildSizesInfoKHR::default();
+        self.loader.get_acceleration_structure_build_sizes(
+            vk::AccelerationStructureBuildTypeKHR::DEVICE,
+            &vk::AccelerationStructureBuildGeometryInfoKHR::default()
+                .ty(vk::AccelerationStructureTypeKHR::BOTTOM_LEVEL)
+                .flags(Self::SKINNED_BLAS_FLAGS)
+                .geometries(&geometries),
+            &[index_count / 3],
+            &mut size_info,
+        );
+        let blas_buffer = Buffer::create(
+            init_state.instance(),
+            init_state.device(),
+            init_state.physical_device(),
+            size_info.acceleration_structure_size,
+            vk::BufferUsageFlags::ACCELERATION_STRUCTURE_STORAGE_KHR
+                | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        )?;
+        let blas = self.loader.create_acceleration_structure(
+            &vk::AccelerationStructureCreateInfoKHR::default()
+                .buffer(blas_buffer.handle())
+                .size(size_info.acceleration_structure_size)
+                .ty(vk::AccelerationStructureTypeKHR::BOTTOM_LEVEL),
+            None,
+        )?;
+        Ok(SkinnedCopy {
+            vertex_buffer,
+            geometry,
+            blas,
+            blas_buffer,
+        })
+    }
+
+    /// Untransformed, unlike the shared BLASes, since update builds have to describe the
+    /// geometry exactly as the first build did
+    fn skinned_geometry(
+        geometry: MeshGeometry,
+        vertex_count: u32,
+    ) -> vk::AccelerationStructureGeometryKHR<'static> {
+        vk::AccelerationStructureGeometryKHR::default()
+            .geometry_type(vk::GeometryTypeKHR::TRIANGLES)
+            .flags(vk::GeometryFlagsKHR::OPAQUE)
+            .geometry(vk::AccelerationStructureGeometryDataKHR {
+                triangles: vk::AccelerationStructureGeometryTrianglesDataKHR::default()
+                    .vertex_format(vk::Format::R32G32B32_SFLOAT)
+                    .vertex_data(vk::DeviceOrHostAddressConstKHR {
+                        device_address: geometry.position_address,
+                    })
+                    .vertex_stride(geometry.vertex_stride as vk::DeviceSize)
+                    .max_vertex(vertex_count.saturating_sub(1))
+                    .index_type(vk::IndexType::UINT16)
+                    .index_data(vk::DeviceOrHostAddressConstKHR {
+                        device_address: geometry.index_address,
+                    }),
+            })
+    }
+
+    /// Builds one copy of the skinned BLAS from its current vertices, or refits it in place
+    /// with [`vk::BuildAccelerationStructureModeKHR::UPDATE`]
+    unsafe fn build_skinned_blas(
         loader: &acceleration_structure::Device,
         fence: vk::Fence,
         init_state: &InitState,
         pipeline_state: &PipelineState,
+        skinned: &SkinnedBlas,
+        copy: usize,
+        mode: vk::BuildAccelerationStructureModeKHR,
+    ) -> Result<(), Box<dyn Error>> {
+        let copy = &skinned.copies[copy];
+        let geometries = [Self::skinned_geometry(copy.geometry, skinned.vertex_count)];
+        let build_info = vk::AccelerationStructureBuildGeometryInfoKHR::default()
+            .ty(vk::AccelerationStructureTypeKHR::BOTTOM_LEVEL)
+            .flags(Self::SKINNED_BLAS_FLAGS)
+            .mode(mode)
+            .geometries(&geometries);
+        let primitive_count = skinned.index_count / 3;
+
+        let mut size_info = vk::AccelerationStructureBuildSizesInfoKHR::default();
+        loader.get_acceleration_structure_build_sizes(
+            vk::AccelerationStructureBuildTypeKHR::DEVICE,
+            &build_info,
+            &[primitive_count],
+            &mut size_info,
+        );
+        let scratch_size = if mode == vk::BuildAccelerationStructureModeKHR::UPDATE {
+            size_info.update_scratch_size
+        } else {
+            size_info.build_scratch_size
+        };
+        let mut scratch_buffer = Buffer::create(
+            init_state.instance(),
+            init_state.device(),
+            init_state.physical_device(),
+            scratch_size.max(1),
+            vk::BufferUsageFlags::STORAGE_BUFFER | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        )?;
+        let scratch_address = Self::buffer_address(pipeline_state, &scratch_buffer);
+
+        let command_buffer = init_state.device().allocate_command_buffers(
+            &vk::CommandBufferAllocateInfo::default()
+                .command_pool(init_state.queues().transfer().command_pool().unwrap())
+                .level(vk::CommandBufferLevel::PRIMARY)
+                .command_buffer_count(1),
+        )?[0];
+        init_state.device().begin_command_buffer(
+            command_buffer,
+            &vk::CommandBufferBeginInfo::default()
+                .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT),
+        )?;
+
+        let mut build_info = build_info
+            .dst_acceleration_structure(copy.blas)
+            .scratch_data(vk::DeviceOrHostAddressKHR {
+                device_address: scratch_address,
+            });
+        if mode == vk::BuildAccelerationStructureModeKHR::UPDATE {
+            build_info = build_info.src_acceleration_structure(copy.blas);
+        }
+        loader.cmd_build_acceleration_structures(
+            command_buffer,
+            &[build_info],
+            &[&[vk::AccelerationStructureBuildRangeInfoKHR::default()
+                .primitive_count(primitive_count)]],
+        );
+
+        init_state.device().end_command_buffer(command_buffer)?;
+        init_state.device().reset_fences(&[fence])?;
+        init_state.device().queue_submit(
+            init_state.queues().transfer().primary_handle().unwrap(),
+            &[vk::SubmitInfo::default().command_buffers(&[command_buffer])],
+            fence,
+        )?;
+        init_state
+            .device()
+            .wait_for_fences(&[fence], true, u64::MAX)?;
+
+        scratch_buffer.cleanup(init_state.device());
+        init_state.device().free_command_buffers(
+            init_state.queues().transfer().command_pool().unwrap(),
+            &[command_buffer],
+        );
+        Ok(())
+    }
+
+    unsafe fn destroy_skinned_blas(
+        loader: &acceleration_structure::Device,
+        init_state: &InitState,
+        skinned: &mut SkinnedBlas,
+    ) {
+        for copy in &mut skinned.copies {
+            loader.destroy_acceleration_structure(copy.blas, None);
+            copy.blas_buffer.cleanup(init_state.device());
+            copy.vertex_buffer.cleanup(init_state.device());
+        }
+        skinned.index_buffer.cleanup(init_state.device());
+    }
+
+    /// TLAS entries and the matching instance buffer contents. Entry 0 is always the scene.
+    #[allow(clippy::too_many_arguments)]
+    unsafe fn build_instances(
+        loader: &acceleration_structure::Device,
+        pipeline_state: &PipelineState,
+        scene_blas: vk::AccelerationStructureKHR,
+        scene_geometry: MeshGeometry,
+        mesh_blases: &[MeshBlas],
+        mesh_blas_indices: &[usize],
+        skinned_blases: &HashMap<Entity, SkinnedBlas>,
+        batches: &[InstanceBatch],
+    ) -> Result<InstanceUpload, Box<dyn Error>> {
+        let shader_binding_table = pipeline_state.shader_binding_table();
+        let scene_hit_offset = shader_binding_table
+            .instance_hit_offset(PipelineState::SCENE_HIT_BLOCK)
+            .ok_or(vk::Result::ERROR_UNKNOWN)?;
+        let prop_hit_offset = shader_binding_table
+            .instance_hit_offset(PipelineState::PROP_HIT_BLOCK)
+            .ok_or(vk::Result::ERROR_UNKNOWN)?;
+
+        let instance_count = 1 + batches
+            .iter()
+            .map(|batch| batch.instances.len())
+            .sum::<usize>();
+        if instance_count > Self::MAX_INSTANCES {
+            return Err(Box::new(std::io::Error::other(
+                "Too many instances for the instance buffer",
+            )));
+        }
+
+        let mut tlas_instances = Vec::with_capacity(instance_count);
+        let mut instances = Vec::with_capacity(instance_count);
+        let mut entities = Vec::with_capacity(instance_count);
+
+        tlas_instances.push(Self::tlas_instance(
+            loader,
+            scene_blas,
+            Mat4::IDENTITY,
+            0,
+            scene_hit_offset,
+        ));
+        instances.push(InstanceGpu::new(
+            Mat4::IDENTITY,
+            &scene_geometry,
+            Self::SCENE_MATERIAL,
+        ));
+        entities.push(None);
+
+        for batch in batches {
+            let mesh_blas = mesh_blas_indices
+                .get(batch.mesh.index())
+                .map(|&index| &mesh_blases[index])
+                .ok_or("Instance references a mesh that has not been uploaded")?;
+            let blas_address = loader.get_acceleration_structure_device_address(
+                &vk::AccelerationStructureDeviceAddressInfoKHR::default()
+                    .acceleration_structure(mesh_blas.blas),
+            );
+
+            for instance in &batch.instances {
+                let (blas_address, geometry) = match skinned_blases.get(&instance.entity) {
+                    Some(skinned) => {
+                        let copy = &skinned.copies[skinned.live];
+                        (
+                            loader.get_acceleration_structure_device_address(
+                                &vk::AccelerationStructureDeviceAddressInfoKHR::default()
+                                    .acceleration_structure(copy.blas),
+                            ),
+                            &copy.geometry,
+                        )
+                    }
+                    None => (blas_address, &mesh_blas.geometry),
+                };
+                tlas_instances.push(Self::tlas_instance_from_address(
+                    blas_address,
+                    instance.model,
+                    instances.len() as u32,
+                    prop_hit_offset,
+                ));
+                instances.push(InstanceGpu::new(
+                    instance.model,
+                    geometry,
+                    instance.material,
+                ));
+                entities.push(Some(instance.entity));
+            }
+        }
+
+        Ok(InstanceUpload {
+            tlas_instances,
+            instances,
+            entities,
+        })
+    }
+
+    unsafe fn tlas_instance(
+        loader: &acceleration_structure::Device,
         blas: vk::AccelerationStructureKHR,
-    ) -> Result<(vk::AccelerationStructureKHR, Buffer<'a>), Box<dyn Error>> {
-        let instance = vk::AccelerationStructureInstanceKHR {
+        model: Mat4,
+        custom_index: u32,
+        hit_offset: u32,
+    ) -> vk::AccelerationStructureInstanceKHR {
+        let blas_address = loader.get_acceleration_structure_device_address(
+            &vk::AccelerationStructureDeviceAddressInfoKHR::default().acceleration_structure(blas),
+        );
+        Self::tlas_instance_from_address(blas_address, model, custom_index, hit_offset)
+    }
+
+    fn tlas_instance_from_address(
+        blas_address: vk::DeviceAddress,
+        model: Mat4,
+        custom_index: u32,
+        hit_offset: u32,
+    ) -> vk::AccelerationStructureInstanceKHR {
+        // Row-major 3x4: the first three rows of the model matrix
+        let rows = model.transpose().to_cols_array();
+        let mut matrix = [0.0; 12];
+        matrix.copy_from_slice(&rows[..12]);
+
+        vk::AccelerationStructureInstanceKHR {
             acceleration_structure_reference: vk::AccelerationStructureReferenceKHR {
-                device_handle: loader.get_acceleration_structure_device_address(
-                    &vk::AccelerationStructureDeviceAddressInfoKHR::default()
-                        .acceleration_structure(blas),
-                ),
-            },
-            transform: vk::TransformMatrixKHR {
-                #[rustfmt::skip]
-                matrix: [
-                    1.0, 0.0, 0.0, 0.0,
-                    0.0, 1.0, 0.0, 0.0,
-                    0.0, 0.0, 1.0, 0.0,
-                ],
+                device_handle: blas_address,
             },
-            instance_custom_index_and_mask: vk::Packed24_8::new(0, 0xFF),
+            transform: vk::TransformMatrixKHR { matrix },
+            instance_custom_index_and_mask: vk::Packed24_8::new(custom_index, 0xFF),
             instance_shader_binding_table_record_offset_and_flags: vk::Packed24_8::new(
-                0,
-                // vk::GeometryInstanceFlagsKHR::default().as_raw() as u8,
+                hit_offset,
                 vk::GeometryInstanceFlagsKHR::TRIANGLE_FACING_CULL_DISABLE.as_raw() as u8,
             ),
-        };
-
-        let bytes = slice::from_raw_parts(
-            (&instance as *const _) as *const u8,
-            mem::size_of_val(&instance),
-        );
+        }
+    }
 
-        let mut instances_buffer = Buffer::create_from_bytes_with_staging(
-            init_state.instance(),
-            init_state.device(),
-            init_state.physical_device(),
-            init_state.queues().command_fence().unwrap(),
-            init_state.queues().transfer(),
-            bytes,
-            vk::BufferUsageFlags::ACCELERATION_STRUCTURE_BUILD_INPUT_READ_ONLY_KHR
-                | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS,
-        )?;
+    unsafe fn buffer_address(pipeline_state: &PipelineState, buffer: &Buffer) -> vk::DeviceAddress {
+        pipeline_state
+            .buffer_device_address_loader()
+            .get_buffer_device_address(
+                &vk::BufferDeviceAddressInfo::default().buffer(buffer.handle()),
+            )
+    }
 
-        let geometries = [vk::AccelerationStructureGeometryKHR::default()
+    fn tlas_geometry(
+        instances_address: vk::DeviceAddress,
+    ) -> vk::AccelerationStructureGeometryKHR<'static> {
+        vk::AccelerationStructureGeometryKHR::default()
             .geometry_type(vk::GeometryTypeKHR::INSTANCES)
             .flags(vk::GeometryFlagsKHR::OPAQUE)
             .geometry(vk::AccelerationStructureGeometryDataKHR {
                 instances: vk::AccelerationStructureGeometryInstancesDataKHR::default().data(
                     vk::DeviceOrHostAddressConstKHR {
-                        device_address: pipeline_state
-                            .buffer_device_address_loader()
-                            .get_buffer_device_address(
-                                &vk::BufferDeviceAddressInfo::default()
-                                    .buffer(instances_buffer.handle()),
-                            ),
+                        device_address: instances_address,
                     },
                 ),
-            })];
-
-        let build_info = vk::AccelerationStructureBuildGeometryInfoKHR::default()
-            .ty(vk::AccelerationStructureTypeKHR::TOP_LEVEL)
-            .flags(vk::BuildAccelerationStructureFlagsKHR::PREFER_FAST_TRACE)
-            .geometries(&geometries);
+            })
+    }
 
+    #[profiling::function]
+    unsafe fn create_tlas(
+        loader: &acceleration_structure::Device,
+        fence: vk::Fence,
+        init_state: &InitState,
+        pipeline_state: &PipelineState,
+        instances: &[vk::AccelerationStructureInstanceKHR],
+    ) -> Result<(vk::AccelerationStructureKHR, Buffer<'a>), Box<dyn Error>> {
+        let geometries = [Self::tlas_geometry(0)];
         let mut size_info = vk::AccelerationStructureBuildSizesInfoKHR::default();
         loader.get_acceleration_structure_build_sizes(
             vk::AccelerationStructureBuildTypeKHR::DEVICE,
-            &build_info,
-            &[1], // One instance (the cube BLAS)
+            &vk::AccelerationStructureBuildGeometryInfoKHR::default()
+                .ty(vk::AccelerationStructureTypeKHR::TOP_LEVEL)
+                .flags(Self::TLAS_FLAGS)
+                .geometries(&geometries),
+            &[instances.len() as u32],
             &mut size_info,
         );
 
@@ -355,19 +1185,79 @@ impl<'a> AccelerationStructureState<'a> {
             None,
         )?;
 
+        Self::build_tlas(
+            loader,
+            fence,
+            init_state,
+            pipeline_state,
+            instances,
+            tlas,
+            TlasBuild::Rebuild,
+        )?;
+        Ok((tlas, tlas_buffer))
+    }
+
+    /// Builds `tlas` from `instances`, or updates it in place when refitting, which needs the
+    /// same instances and BLASes as its last build
+    unsafe fn build_tlas(
+        loader: &acceleration_structure::Device,
+        fence: vk::Fence,
+        init_state: &InitState,
+        pipeline_state: &PipelineState,
+        instances: &[vk::AccelerationStructureInstanceKHR],
+        tlas: vk::AccelerationStructureKHR,
+        build: TlasBuild,
+    ) -> Result<(), Box<dyn Error>> {
+        let bytes =
+            slice::from_raw_parts(instances.as_ptr() as *const u8, mem::size_of_val(instances));
+
+        let mut instances_buffer = Buffer::create_from_bytes_with_staging(
+            init_state.instance(),
+            init_state.device(),
+            init_state.physical_device(),
+            init_state.queues().command_fence().unwrap(),
+            init_state.queues().transfer(),
+            bytes,
+            vk::BufferUsageFlags::ACCELERATION_STRUCTURE_BUILD_INPUT_READ_ONLY_KHR
+                | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS,
+        )?;
+
+        let geometries = [Self::tlas_geometry(Self::buffer_address(
+            pipeline_state,
+            &instances_buffer,
+        ))];
+
+        let mode = match build {
+            TlasBuild::Rebuild => vk::BuildAccelerationStructureModeKHR::BUILD,
+            TlasBuild::Refit => vk::BuildAccelerationStructureModeKHR::UPDATE,
+        };
+        let build_info = vk::AccelerationStructureBuildGeometryInfoKHR::default()
+            .ty(vk::AccelerationStructureTypeKHR::TOP_LEVEL)
+            .flags(Self::TLAS_FLAGS)
+            .mode(mode)
+            .geometries(&geometries);
+
+        let mut size_info = vk::AccelerationStructureBuildSizesInfoKHR::default();
+        loader.get_acceleration_structure_build_sizes(
+            vk::AccelerationStructureBuildTypeKHR::DEVICE,
+            &build_info,
+            &[instances.len() as u32],
+            &mut size_info,
+        );
+        let scratch_size = match build {
+            TlasBuild::Rebuild => size_info.build_scratch_size,
+            TlasBuild::Refit => size_info.update_scratch_size,
+        };
+
         let mut scratch_buffer = Buffer::create(
             init_state.instance(),
             init_state.device(),
             init_state.physical_device(),
-            size_info.build_scratch_size,
+            scratch_size.max(1),
             vk::BufferUsageFlags::STORAGE_BUFFER | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS,
             vk::MemoryPropertyFlags::DEVICE_LOCAL,
         )?;
-        let scratch_address = pipeline_state
-            .buffer_device_address_loader()
-            .get_buffer_device_address(
-                &vk::BufferDeviceAddressInfo::default().buffer(scratch_buffer.handle()),
-            );
+        let scratch_address = Self::buffer_address(pipeline_state, &scratch_buffer);
 
         let command_buffer = init_state.device().allocate_command_buffers(
             &vk::CommandBufferAllocateInfo::default()
@@ -382,17 +1272,21 @@ impl<'a> AccelerationStructureState<'a> {
                 .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT),
         )?;
 
-        let build_info =
+        let mut build_info =
             build_info
                 .dst_acceleration_structure(tlas)
                 .scratch_data(vk::DeviceOrHostAddressKHR {
                     device_address: scratch_address,
                 });
+        if build == TlasBuild::Refit {
+            build_info = build_info.src_acceleration_structure(tlas);
+        }
 
         loader.cmd_build_acceleration_structures(
             command_buffer,
             &[build_info],
-            &[&[vk::AccelerationStructureBuildRangeInfoKHR::default().primitive_count(1)]],
+            &[&[vk::AccelerationStructureBuildRangeInfoKHR::default()
+                .primitive_count(instances.len() as u32)]],
         );
 
         init_state.device().end_command_buffer(command_buffer)?;
@@ -416,24 +1310,20 @@ impl<'a> AccelerationStructureState<'a> {
             &[command_buffer],
         );
 
-        Ok((tlas, tlas_buffer))
+        Ok(())
     }
 
-    unsafe fn create_descriptor_pool(device: &ash::Device) -> VkResult<vk::DescriptorPool> {
+    unsafe fn create_descriptor_pool(
+        device: &ash::Device,
+        reflection: &PipelineReflection,
+    ) -> VkResult<vk::DescriptorPool> {
         device.create_descriptor_pool(
             &vk::DescriptorPoolCreateInfo::default()
                 .flags(vk::DescriptorPoolCreateFlags::FREE_DESCRIPTOR_SET)
-                .pool_sizes(&[
-                    vk::DescriptorPoolSize::default()
-                        .descriptor_count(MAX_FRAMES_IN_FLIGHT as u32)
-                        .ty(vk::DescriptorType::ACCELERATION_STRUCTURE_KHR),
-                    vk::DescriptorPoolSize::default()
-                        .descriptor_count(MAX_FRAMES_IN_FLIGHT as u32)
-                        .ty(vk::DescriptorType::STORAGE_IMAGE),
-                    vk::DescriptorPoolSize::default()
-                        .descriptor_count(MAX_FRAMES_IN_FLIGHT as u32)
-                        .ty(vk::DescriptorType::UNIFORM_BUFFER),
-                ])
+                .pool_sizes(
+                    &reflection
+                        .pool_sizes(PipelineState::DESCRIPTOR_SET, MAX_FRAMES_IN_FLIGHT as u32),
+                )
                 .max_sets(MAX_FRAMES_IN_FLIGHT as u32),
             None,
         )
@@ -451,49 +1341,154 @@ impl<'a> AccelerationStructureState<'a> {
         )
     }
 
-    pub fn update_descriptor_sets(
+    /// Points the frame's descriptor set at the swapchain's current images if they were
+    /// recreated since it was written. Only call once the frame's fence has signaled, so the
+    /// set isn't in use.
+    pub fn refresh_descriptor_set(
         &mut self,
         device: &ash::Device,
-        uniform_buffers: &[Buffer],
-        output_image_views: &[vk::ImageView],
+        buffer_state: &BufferState,
+        swapchain_state: &SwapchainState,
+        current_frame: u8,
     ) {
+        if !self
+            .descriptor_versions
+            .update(current_frame, swapchain_state.generation())
+        {
+            return;
+        }
+        let frame = current_frame as usize;
+        let descriptor_set = self.descriptor_sets[frame];
         unsafe {
-            for (frame, &descriptor_set) in self.descriptor_sets.iter().enumerate() {
-                device.update_descriptor_sets(
-                    &[
-                        vk::WriteDescriptorSet::default()
-                            .dst_set(descriptor_set)
-                            .dst_binding(0)
-                            .dst_array_element(0)
-                            .descriptor_type(vk::DescriptorType::ACCELERATION_STRUCTURE_KHR)
-                            .descriptor_count(1)
-                            .push_next(
-                                &mut vk::WriteDescriptorSetAccelerationStructureKHR::default()
-                                    .acceleration_structures(&[self.tlas]),
-                            ),
-                        vk::WriteDescriptorSet::default()
-                            .dst_set(descriptor_set)
-                            .dst_binding(1)
-                            .dst_array_element(0)
-                            .descriptor_type(vk::DescriptorType::STORAGE_IMAGE)
-                            .descriptor_count(1)
-                            .image_info(&[vk::DescriptorImageInfo::default()
-                                .image_view(output_image_views[frame])
-                                .image_layout(vk::ImageLayout::GENERAL)]),
-                        vk::WriteDescriptorSet::default()
-                            .dst_set(descriptor_set)
-                            .dst_binding(2)
-                            .dst_array_element(0)
-                            .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
-                            .descriptor_count(1)
-                            .buffer_info(&[vk::DescriptorBufferInfo::default()
-                                .buffer(uniform_buffers[frame].handle())
-                                .offset(0)
-                                .range(mem::size_of::<CameraGpu>() as u64)]),
-                    ],
-                    &[],
-                );
-            }
+            device.update_descriptor_sets(
+                &[
+                    vk::WriteDescriptorSet::default()
+                        .dst_set(descriptor_set)
+                        .dst_binding(0)
+                        .dst_array_element(0)
+                        .descriptor_type(vk::DescriptorType::ACCELERATION_STRUCTURE_KHR)
+                        .descriptor_count(1)
+                        .push_next(
+                            &mut vk::WriteDescriptorSetAccelerationStructureKHR::default()
+                                .acceleration_structures(&[self.tlas]),
+                        ),
+                    vk::WriteDescriptorSet::default()
+                        .dst_set(descriptor_set)
+                        .dst_binding(1)
+                        .dst_array_element(0)
+                        .descriptor_type(vk::DescriptorType::STORAGE_IMAGE)
+                        .descriptor_count(1)
+                        .image_info(&[vk::DescriptorImageInfo::default()
+                            .image_view(swapchain_state.output_image_views()[frame])
+                            .image_layout(vk::ImageLayout::GENERAL)]),
+                    vk::WriteDescriptorSet::default()
+                        .dst_set(descriptor_set)
+                        .dst_binding(2)
+                        .dst_array_element(0)
+                        .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC)
+                        .descriptor_count(1)
+                        // Offset by the frame's slot when bound
+                        .buffer_info(&[vk::DescriptorBufferInfo::default()
+                            .buffer(buffer_state.uniforms().buffer().handle())
+                            .offset(0)
+                            .range(mem::size_of::<CameraGpu>() as u64)]),
+                    vk::WriteDescriptorSet::default()
+                        .dst_set(descriptor_set)
+                        .dst_binding(3)
+                        .dst_array_element(0)
+                        .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+                        .descriptor_count(1)
+                        .buffer_info(&[vk::DescriptorBufferInfo::default()
+                            .buffer(buffer_state.material_buffer().handle())
+                            .offset(0)
+                            .range((mem::size_of::<MaterialGpu>() * Voxel::ALL.len()) as u64)]),
+                    vk::WriteDescriptorSet::default()
+                        .dst_set(descriptor_set)
+                        .dst_binding(4)
+                        .dst_array_element(0)
+                        .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+                        .descriptor_count(1)
+                        .buffer_info(&[vk::DescriptorBufferInfo::default()
+                            .buffer(buffer_state.vertex_buffer().handle())
+                            .offset(0)
+                            .range(vk::WHOLE_SIZE)]),
+                    vk::WriteDescriptorSet::default()
+                        .dst_set(descriptor_set)
+                        .dst_binding(5)
+                        .dst_array_element(0)
+                        .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+                        .descriptor_count(1)
+                        .buffer_info(&[vk::DescriptorBufferInfo::default()
+                            .buffer(buffer_state.index_buffer().handle())
+                            .offset(0)
+                            .range(vk::WHOLE_SIZE)]),
+                    vk::WriteDescriptorSet::default()
+                        .dst_set(descriptor_set)
+                        .dst_binding(6)
+                        .dst_array_element(0)
+                        .descriptor_type(vk::DescriptorType::STORAGE_IMAGE)
+                        .descriptor_count(1)
+                        .image_info(&[vk::DescriptorImageInfo::default()
+                            .image_view(swapchain_state.accumulation_image_view())
+                            .image_layout(vk::ImageLayout::GENERAL)]),
+                    vk::WriteDescriptorSet::default()
+                        .dst_set(descriptor_set)
+                        .dst_binding(7)
+                        .dst_array_element(0)
+                        .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+                        .descriptor_count(1)
+                        .buffer_info(&[vk::DescriptorBufferInfo::default()
+                            .buffer(self.instance_buffer.handle())
+                            .offset(0)
+                            .range(vk::WHOLE_SIZE)]),
+                    vk::WriteDescriptorSet::default()
+                        .dst_set(descriptor_set)
+                        .dst_binding(8)
+                        .dst_array_element(0)
+                        .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+                        .descriptor_count(1)
+                        .buffer_info(&[vk::DescriptorBufferInfo::default()
+                            .buffer(buffer_state.pick_buffers()[frame].handle())
+                            .offset(0)
+                            .range(vk::WHOLE_SIZE)]),
+                    vk::WriteDescriptorSet::default()
+                        .dst_set(descriptor_set)
+                        .dst_binding(9)
+                        .dst_array_element(0)
+                        .descriptor_type(vk::DescriptorType::STORAGE_IMAGE)
+                        .descriptor_count(1)
+                        .image_info(&[vk::DescriptorImageInfo::default()
+                            .image_view(buffer_state.blue_noise().view())
+                            .image_layout(vk::ImageLayout::GENERAL)]),
+                    vk::WriteDescriptorSet::default()
+                        .dst_set(descriptor_set)
+                        .dst_binding(10)
+                        .dst_array_element(0)
+                        .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+                        .descriptor_count(1)
+                        .buffer_info(&[vk::DescriptorBufferInfo::default()
+                            .buffer(buffer_state.light_buffers()[frame].handle())
+                            .offset(0)
+                            .range(vk::WHOLE_SIZE)]),
+                    vk::WriteDescriptorSet::default()
+                        .dst_set(descriptor_set)
+                        .dst_binding(11)
+                        .dst_array_element(0)
+                        .descriptor_type(vk::DescriptorType::STORAGE_IMAGE)
+                        .descriptor_count(1)
+                        .image_info(&[vk::DescriptorImageInfo::default()
+                            .image_view(swapchain_state.variance_image_view())
+                            .image_layout(vk::ImageLayout::GENERAL)]),
+                ]
+                .into_iter()
+                // Bindings the shaders no longer use aren't in the layout
+                .filter(|write| {
+                    self.descriptor_bindings
+                        .contains(PipelineState::DESCRIPTOR_SET, write.dst_binding)
+                })
+                .collect::<Vec<_>>(),
+                &[],
+            );
         }
     }
 
@@ -506,6 +1501,18 @@ impl<'a> AccelerationStructureState<'a> {
             self.loader.destroy_acceleration_structure(self.blas, None);
             self.loader.destroy_acceleration_structure(self.tlas, None);
 
+            for skinned in self.skinned_blases.values_mut() {
+                Self::destroy_skinned_blas(&self.loader, init_state, skinned);
+            }
+            for mesh_blas in &mut self.mesh_blases {
+                self.loader
+                    .destroy_acceleration_structure(mesh_blas.blas, None);
+                mesh_blas.blas_buffer.cleanup(init_state.device());
+                mesh_blas.vertex_buffer.cleanup(init_state.device());
+                mesh_blas.index_buffer.cleanup(init_state.device());
+            }
+            self.instance_buffer.cleanup(init_state.device());
+
             init_state
                 .device()
                 .free_descriptor_sets(self.descriptor_pool, &self.descriptor_sets)
@@ -516,3 +1523,51 @@ impl<'a> AccelerationStructureState<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use data::material::MaterialFlags;
+
+    use super::*;
+    use crate::buffer_state::material_table;
+
+    #[test]
+    fn tlas_is_refit_until_instances_change_or_refits_run_out() {
+        let mut meshes = Meshes::default();
+        let (grass, tree) = (meshes.add(Mesh::default()), meshes.add(Mesh::default()));
+        let shape = [(grass, 12), (tree, 3)];
+
+        assert_eq!(next_tlas_build(&[], &[], 0), TlasBuild::Refit);
+        assert_eq!(next_tlas_build(&shape, &shape, 5), TlasBuild::Refit);
+        assert_eq!(
+            next_tlas_build(&shape, &[(grass, 12), (tree, 4)], 0),
+            TlasBuild::Rebuild
+        );
+        assert_eq!(
+            next_tlas_build(&shape, &[(grass, 12), (grass, 3)], 0),
+            TlasBuild::Rebuild
+        );
+        assert_eq!(
+            next_tlas_build(&shape, &shape, AccelerationStructureState::MAX_REFITS),
+            TlasBuild::Rebuild
+        );
+    }
+
+    #[test]
+    fn instances_carry_their_own_material() {
+        let geometry = MeshGeometry {
+            position_address: 0,
+            index_address: 0,
+            vertex_stride: 32,
+        };
+        let materials = material_table();
+        let reflective = |instance: InstanceGpu| {
+            materials[instance.material as usize].flags & MaterialFlags::REFLECTIVE.bits() != 0
+        };
+        let water = InstanceGpu::new(Mat4::IDENTITY, &geometry, Voxel::Water);
+        assert!(reflective(water));
+        let stone = InstanceGpu::new(Mat4::IDENTITY, &geometry, Voxel::Stone);
+        assert!(!reflective(stone));
+        assert_eq!(water.vertex_stride, 8);
+    }
+}
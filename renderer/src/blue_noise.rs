@@ -0,0 +1,386 @@
+use std::thread;
+
+use ash::{prelude::VkResult, vk};
+use thiserror::Error;
+
+use crate::{buffer::Buffer, init_state::InitState, tracking::tracker};
+
+/// Tileable blue noise with an independent pattern in each of its four channels, so a shader
+/// can draw up to four decorrelated samples per pixel from one texel
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlueNoise {
+    size: u32,
+    texels: Vec<[u8; 4]>,
+}
+
+#[derive(Error, Debug)]
+pub enum BlueNoiseError {
+    #[error("expected {expected} bytes of RGBA8 data for a {size}x{size} texture, got {actual}")]
+    WrongLength {
+        size: u32,
+        expected: usize,
+        actual: usize,
+    },
+}
+
+impl BlueNoise {
+    pub const DEFAULT_SIZE: u32 = 64;
+    pub const FORMAT: vk::Format = vk::Format::R8G8B8A8_UNORM;
+
+    /// Width of the Gaussian void-and-cluster measures density with
+    const SIGMA: f32 = 1.9;
+    /// Fraction of pixels set in the initial binary pattern
+    const INITIAL_DENSITY: f32 = 0.1;
+
+    /// Runs void-and-cluster once per channel, each on its own thread. Takes a moment for large
+    /// sizes, so prefer loading baked data through [`Self::from_rgba8`] when startup time matters.
+    pub fn generate(size: u32, seed: u64) -> Self {
+        let channels: [Vec<u8>; 4] = thread::scope(|scope| {
+            let handles: [_; 4] = std::array::from_fn(|channel| {
+                scope.spawn(move || void_and_cluster(size, seed.wrapping_add(channel as u64)))
+            });
+            handles.map(|handle| handle.join().unwrap())
+        });
+        let texels = (0..(size * size) as usize)
+            .map(|i| channels.each_ref().map(|channel| channel[i]))
+            .collect();
+        Self { size, texels }
+    }
+
+    /// Square RGBA8 data, row by row
+    pub fn from_rgba8(size: u32, bytes: &[u8]) -> Result<Self, BlueNoiseError> {
+        let expected = (size * size * 4) as usize;
+        if bytes.len() != expected {
+            return Err(BlueNoiseError::WrongLength {
+                size,
+                expected,
+                actual: bytes.len(),
+            });
+        }
+        let texels = bytes
+            .chunks_exact(4)
+            .map(|texel| [texel[0], texel[1], texel[2], texel[3]])
+            .collect();
+        Ok(Self { size, texels })
+    }
+
+    pub const fn size(&self) -> u32 {
+        self.size
+    }
+
+    pub fn as_rgba8(&self) -> &[u8] {
+        bytemuck::cast_slice(&self.texels)
+    }
+}
+
+impl Default for BlueNoise {
+    fn default() -> Self {
+        Self::generate(Self::DEFAULT_SIZE, 0)
+    }
+}
+
+/// Ranks every pixel of a `size`² torus so that each prefix of the ranking is evenly spread,
+/// then maps ranks to `0..=255`
+fn void_and_cluster(size: u32, seed: u64) -> Vec<u8> {
+    let size = size as usize;
+    let pixel_count = size * size;
+    let kernel = gaussian_kernel(size);
+
+    let mut pattern = vec![false; pixel_count];
+    let mut energy = vec![0.0f32; pixel_count];
+    let splat = |energy: &mut [f32], pixel: usize, sign: f32| {
+        let (px, py) = (pixel % size, pixel / size);
+        for y in 0..size {
+            let dy = (y + size - py) % size;
+            for x in 0..size {
+                let dx = (x + size - px) % size;
+                energy[x + y * size] += sign * kernel[dx + dy * size];
+            }
+        }
+    };
+    let tightest_cluster = |pattern: &[bool], energy: &[f32]| {
+        (0..pixel_count)
+            .filter(|&i| pattern[i])
+            .max_by(|&a, &b| energy[a].total_cmp(&energy[b]))
+    };
+    let largest_void = |pattern: &[bool], energy: &[f32]| {
+        (0..pixel_count)
+            .filter(|&i| !pattern[i])
+            .min_by(|&a, &b| energy[a].total_cmp(&energy[b]))
+    };
+
+    // Random initial points, then relaxed by moving the tightest cluster into the largest void
+    let initial_count = ((pixel_count as f32 * BlueNoise::INITIAL_DENSITY) as usize).max(1);
+    let mut state = seed;
+    let mut placed = 0;
+    while placed < initial_count {
+        let pixel = (splitmix64(&mut state) % pixel_count as u64) as usize;
+        if !pattern[pixel] {
+            pattern[pixel] = true;
+            splat(&mut energy, pixel, 1.0);
+            placed += 1;
+        }
+    }
+    for _ in 0..pixel_count {
+        let cluster = tightest_cluster(&pattern, &energy).unwrap();
+        pattern[cluster] = false;
+        splat(&mut energy, cluster, -1.0);
+
+        let void = largest_void(&pattern, &energy).unwrap();
+        pattern[void] = true;
+        splat(&mut energy, void, 1.0);
+        if void == cluster {
+            break;
+        }
+    }
+
+    let mut ranks = vec![0usize; pixel_count];
+
+    // Initial points get the lowest ranks, tightest clusters last
+    let mut removal_pattern = pattern.clone();
+    let mut removal_energy = energy.clone();
+    for rank in (0..initial_count).rev() {
+        let cluster = tightest_cluster(&removal_pattern, &removal_energy).unwrap();
+        removal_pattern[cluster] = false;
+        splat(&mut removal_energy, cluster, -1.0);
+        ranks[cluster] = rank;
+    }
+
+    // The rest fill the largest remaining void one at a time
+    for rank in initial_count..pixel_count {
+        let void = largest_void(&pattern, &energy).unwrap();
+        pattern[void] = true;
+        splat(&mut energy, void, 1.0);
+        ranks[void] = rank;
+    }
+
+    ranks
+        .into_iter()
+        .map(|rank| (rank * 256 / pixel_count) as u8)
+        .collect()
+}
+
+/// Gaussian falloff by toroidal offset, indexed by `dx + dy * size`
+fn gaussian_kernel(size: usize) -> Vec<f32> {
+    let wrapped = |d: usize| d.min(size - d) as f32;
+    (0..size * size)
+        .map(|i| {
+            let (dx, dy) = (wrapped(i % size), wrapped(i / size));
+            (-(dx * dx + dy * dy) / (2.0 * BlueNoise::SIGMA * BlueNoise::SIGMA)).exp()
+        })
+        .collect()
+}
+
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut x = *state;
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+/// [`BlueNoise`] uploaded to a device-local storage image in `GENERAL` layout, read by the ray
+/// tracing shaders with `imageLoad`
+pub struct BlueNoiseTexture {
+    image: vk::Image,
+    memory: vk::DeviceMemory,
+    view: vk::ImageView,
+    size: u32,
+}
+
+impl BlueNoiseTexture {
+    pub const fn view(&self) -> vk::ImageView {
+        self.view
+    }
+
+    pub const fn size(&self) -> u32 {
+        self.size
+    }
+
+    pub fn new(init_state: &InitState, noise: &BlueNoise) -> VkResult<Self> {
+        unsafe {
+            let device = init_state.device();
+            let extent = vk::Extent3D {
+                width: noise.size(),
+                height: noise.size(),
+                depth: 1,
+            };
+
+            let image = device.create_image(
+                &vk::ImageCreateInfo::default()
+                    .image_type(vk::ImageType::TYPE_2D)
+                    .format(BlueNoise::FORMAT)
+                    .extent(extent)
+                    .mip_levels(1)
+                    .array_layers(1)
+                    .samples(vk::SampleCountFlags::TYPE_1)
+                    .tiling(vk::ImageTiling::OPTIMAL)
+                    .usage(vk::ImageUsageFlags::STORAGE | vk::ImageUsageFlags::TRANSFER_DST),
+                None,
+            )?;
+
+            let memory_requirements = device.get_image_memory_requirements(image);
+            let (memory_type_index, _) = Buffer::find_memory_type(
+                init_state.instance(),
+                init_state.physical_device(),
+                memory_requirements.memory_type_bits,
+                vk::MemoryPropertyFlags::DEVICE_LOCAL,
+            )?;
+            let memory = device.allocate_memory(
+                &vk::MemoryAllocateInfo::default()
+                    .allocation_size(memory_requirements.size)
+                    .memory_type_index(memory_type_index),
+                None,
+            )?;
+            device.bind_image_memory(image, memory, 0)?;
+            tracker().track_image(image, memory, memory_requirements.size, "blue noise");
+
+            Self::upload(init_state, image, extent, noise.as_rgba8())?;
+
+            let view = device.create_image_view(
+                &vk::ImageViewCreateInfo::default()
+                    .image(image)
+                    .view_type(vk::ImageViewType::TYPE_2D)
+                    .format(BlueNoise::FORMAT)
+                    .subresource_range(Self::subresource_range()),
+                None,
+            )?;
+
+            Ok(Self {
+                image,
+                memory,
+                view,
+                size: noise.size(),
+            })
+        }
+    }
+
+    unsafe fn upload(
+        init_state: &InitState,
+        image: vk::Image,
+        extent: vk::Extent3D,
+        bytes: &[u8],
+    ) -> VkResult<()> {
+        let device = init_state.device();
+        let queue = init_state.queues().graphics();
+
+        let mut staging = Buffer::create(
+            init_state.instance(),
+            device,
+            init_state.physical_device(),
+            bytes.len() as vk::DeviceSize,
+            vk::BufferUsageFlags::TRANSFER_SRC,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+        )?;
+        staging.map_memory(device, 0, vk::MemoryMapFlags::empty())?;
+        staging.write(bytes);
+        staging.unmap_memory(device)?;
+
+        let command_buffer =
+            Buffer::begin_single_time_commands(device, queue.command_pool().unwrap())?;
+        let barrier = |old_layout, new_layout, src_access_mask, dst_access_mask| {
+            vk::ImageMemoryBarrier::default()
+                .old_layout(old_layout)
+                .new_layout(new_layout)
+                .src_access_mask(src_access_mask)
+                .dst_access_mask(dst_access_mask)
+                .image(image)
+                .subresource_range(Self::subresource_range())
+        };
+
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::TOP_OF_PIPE,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &[barrier(
+                vk::ImageLayout::UNDEFINED,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                vk::AccessFlags::NONE,
+                vk::AccessFlags::TRANSFER_WRITE,
+            )],
+        );
+        device.cmd_copy_buffer_to_image(
+            command_buffer,
+            staging.handle(),
+            image,
+            vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            &[vk::BufferImageCopy::default()
+                .image_subresource(
+                    vk::ImageSubresourceLayers::default()
+                        .aspect_mask(vk::ImageAspectFlags::COLOR)
+                        .layer_count(1),
+                )
+                .image_extent(extent)],
+        );
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::TRANSFER,
+            // One-off, and valid whether or not the ray tracing stage exists
+            vk::PipelineStageFlags::ALL_COMMANDS,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &[barrier(
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                vk::ImageLayout::GENERAL,
+                vk::AccessFlags::TRANSFER_WRITE,
+                vk::AccessFlags::SHADER_READ,
+            )],
+        );
+
+        Buffer::end_single_time_commands(
+            device,
+            command_buffer,
+            init_state.queues().command_fence().unwrap(),
+            queue,
+        )?;
+        staging.cleanup(device);
+        Ok(())
+    }
+
+    const fn subresource_range() -> vk::ImageSubresourceRange {
+        vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer: 0,
+            layer_count: 1,
+        }
+    }
+
+    pub fn cleanup(&self, init_state: &InitState) {
+        unsafe {
+            let device = init_state.device();
+            device.destroy_image_view(self.view, None);
+            device.destroy_image(self.image, None);
+            device.free_memory(self.memory, None);
+        }
+        tracker().untrack_image(self.image, self.memory);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_channel_uses_each_level_equally() {
+        let size = 32;
+        let noise = BlueNoise::generate(size, 7);
+        let per_level = (size * size / 256) as usize;
+
+        for channel in 0..4 {
+            let mut histogram = [0usize; 256];
+            for texel in noise.as_rgba8().chunks_exact(4) {
+                histogram[texel[channel] as usize] += 1;
+            }
+            assert!(histogram.iter().all(|&count| count == per_level));
+        }
+
+        let reloaded = BlueNoise::from_rgba8(size, noise.as_rgba8()).unwrap();
+        assert_eq!(reloaded, noise);
+    }
+}
@@ -0,0 +1,293 @@
+use std::{ffi::c_void, fmt, mem};
+
+use ash::{amd, nv, prelude::VkResult, vk};
+use thiserror::Error;
+
+use crate::{buffer::Buffer, init_state::InitState, MAX_FRAMES_IN_FLIGHT};
+
+/// How far the GPU got with a pass
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reached {
+    Started,
+    Finished,
+}
+
+/// The last marker the GPU reached in a frame in flight
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Breadcrumb {
+    pub frame: u8,
+    pub reached: Reached,
+    /// `None` for the start of the frame, before its first pass
+    pub pass: Option<&'static str>,
+}
+
+impl fmt::Display for Breadcrumb {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let reached = match self.reached {
+            Reached::Started => "started",
+            Reached::Finished => "finished",
+        };
+        match self.pass {
+            Some(pass) => write!(f, "frame {} {reached} {pass}", self.frame),
+            None => write!(f, "frame {} {reached}", self.frame),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+#[error("device lost, last reached: {}", list(.breadcrumbs))]
+pub struct DeviceLost {
+    pub breadcrumbs: Vec<Breadcrumb>,
+}
+
+fn list(breadcrumbs: &[Breadcrumb]) -> String {
+    if breadcrumbs.is_empty() {
+        return "nothing".to_owned();
+    }
+    breadcrumbs
+        .iter()
+        .map(Breadcrumb::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+enum Markers {
+    /// `VK_NV_device_diagnostic_checkpoints`: the driver keeps the last checkpoint each
+    /// pipeline stage reached
+    Checkpoints(nv::device_diagnostic_checkpoints::Device),
+    /// `VK_AMD_buffer_marker`: markers are written into a host-visible buffer as the stages
+    /// complete, two per frame in flight for the last pass started and finished
+    BufferMarker {
+        device: amd::buffer_marker::Device,
+        buffer: Buffer<'static>,
+    },
+}
+
+/// Markers written between the render graph's passes, so that when the device is lost the
+/// last pass the GPU reached can be reported instead of nothing. Only with the
+/// `gpu-breadcrumbs` feature, on devices with one of the vendor extensions for it.
+pub struct Breadcrumbs {
+    markers: Markers,
+    /// Pass names by marker; marker 0 is the start of a frame
+    names: Vec<&'static str>,
+}
+
+impl Breadcrumbs {
+    /// Markers for a frame in flight are offset by this, so the frames can be told apart. The
+    /// first frame is offset once too, since a null checkpoint marker may not be kept.
+    const FRAME_STRIDE: u32 = 1 << 16;
+    const BUFFER_SIZE: u64 = 2 * MAX_FRAMES_IN_FLIGHT as u64 * mem::size_of::<u32>() as u64;
+
+    /// `None` without the feature or the extensions
+    pub fn new(init_state: &InitState) -> VkResult<Option<Self>> {
+        if !cfg!(feature = "gpu-breadcrumbs") {
+            return Ok(None);
+        }
+        let capabilities = init_state.capabilities();
+        let markers = if capabilities.diagnostic_checkpoints {
+            Markers::Checkpoints(nv::device_diagnostic_checkpoints::Device::new(
+                init_state.instance(),
+                init_state.device(),
+            ))
+        } else if capabilities.buffer_marker {
+            let device = init_state.device();
+            let mut buffer = Buffer::create(
+                init_state.instance(),
+                device,
+                init_state.physical_device(),
+                Self::BUFFER_SIZE,
+                vk::BufferUsageFlags::TRANSFER_DST,
+                vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+            )?;
+            buffer.map_memory(device, 0, vk::MemoryMapFlags::empty())?;
+            buffer.write(&[0; Self::BUFFER_SIZE as usize]);
+            Markers::BufferMarker {
+                device: amd::buffer_marker::Device::new(init_state.instance(), device),
+                buffer,
+            }
+        } else {
+            return Ok(None);
+        };
+        Ok(Some(Self {
+            markers,
+            names: Vec::new(),
+        }))
+    }
+
+    fn marker(&mut self, current_frame: u8, pass: Option<&'static str>) -> u32 {
+        let index = match pass {
+            None => 0,
+            Some(name) => match self.names.iter().position(|n| *n == name) {
+                Some(index) => index + 1,
+                None => {
+                    self.names.push(name);
+                    self.names.len()
+                }
+            },
+        };
+        (current_frame as u32 + 1) * Self::FRAME_STRIDE + index as u32
+    }
+
+    fn breadcrumb(&self, marker: u32, reached: Reached) -> Breadcrumb {
+        let index = (marker % Self::FRAME_STRIDE) as usize;
+        Breadcrumb {
+            frame: (marker / Self::FRAME_STRIDE).saturating_sub(1) as u8,
+            reached,
+            pass: index
+                .checked_sub(1)
+                .and_then(|index| self.names.get(index).copied()),
+        }
+    }
+
+    /// Marks the start of the frame, before its first pass
+    pub fn begin(&mut self, command_buffer: vk::CommandBuffer, current_frame: u8) {
+        let marker = self.marker(current_frame, None);
+        self.write(command_buffer, current_frame, marker, Reached::Started);
+    }
+
+    /// Marks `pass` as started once the GPU gets to it
+    pub fn start_pass(
+        &mut self,
+        command_buffer: vk::CommandBuffer,
+        current_frame: u8,
+        pass: &'static str,
+    ) {
+        let marker = self.marker(current_frame, Some(pass));
+        self.write(command_buffer, current_frame, marker, Reached::Started);
+    }
+
+    /// Marks `pass` as finished once the GPU has completed it
+    pub fn end_pass(
+        &mut self,
+        command_buffer: vk::CommandBuffer,
+        current_frame: u8,
+        pass: &'static str,
+    ) {
+        let marker = self.marker(current_frame, Some(pass));
+        self.write(command_buffer, current_frame, marker, Reached::Finished);
+    }
+
+    fn write(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        current_frame: u8,
+        marker: u32,
+        reached: Reached,
+    ) {
+        unsafe {
+            match &self.markers {
+                // Only the bottom of the pipe is read back, where a checkpoint means everything
+                // before it completed, so passes need no checkpoint of their own to start
+                Markers::Checkpoints(checkpoints) => {
+                    let frame_start = marker.is_multiple_of(Self::FRAME_STRIDE);
+                    if reached == Reached::Finished || frame_start {
+                        checkpoints
+                            .cmd_set_checkpoint(command_buffer, marker as usize as *const c_void);
+                    }
+                }
+                Markers::BufferMarker {
+                    device: buffer_marker,
+                    buffer,
+                } => {
+                    let (stage, slot) = match reached {
+                        Reached::Started => (vk::PipelineStageFlags::TOP_OF_PIPE, 0),
+                        Reached::Finished => (vk::PipelineStageFlags::BOTTOM_OF_PIPE, 1),
+                    };
+                    let offset = (2 * current_frame as u64 + slot) * mem::size_of::<u32>() as u64;
+                    buffer_marker.cmd_write_buffer_marker(
+                        command_buffer,
+                        stage,
+                        buffer.handle(),
+                        offset,
+                        marker,
+                    );
+                }
+            }
+        }
+    }
+
+    /// The last markers the GPU reached, to be read once the device is lost
+    pub fn last_reached(&self, init_state: &InitState) -> Vec<Breadcrumb> {
+        match &self.markers {
+            Markers::Checkpoints(checkpoints) => unsafe {
+                let Some(queue) = init_state.queues().graphics().primary_handle() else {
+                    return Vec::new();
+                };
+                let mut data = vec![
+                    vk::CheckpointDataNV::default();
+                    checkpoints.get_queue_checkpoint_data_len(queue)
+                ];
+                checkpoints.get_queue_checkpoint_data(queue, &mut data);
+                data.iter()
+                    .filter(|checkpoint| {
+                        checkpoint
+                            .stage
+                            .contains(vk::PipelineStageFlags::BOTTOM_OF_PIPE)
+                    })
+                    .map(|checkpoint| {
+                        let marker = checkpoint.p_checkpoint_marker as usize as u32;
+                        let reached = if marker.is_multiple_of(Self::FRAME_STRIDE) {
+                            Reached::Started
+                        } else {
+                            Reached::Finished
+                        };
+                        self.breadcrumb(marker, reached)
+                    })
+                    .collect()
+            },
+            Markers::BufferMarker { buffer, .. } => {
+                let Some(mapped) = buffer.mapped().as_deref() else {
+                    return Vec::new();
+                };
+                mapped
+                    .chunks_exact(mem::size_of::<u32>())
+                    .map(bytemuck::pod_read_unaligned::<u32>)
+                    .enumerate()
+                    // Zero until the slot's first marker is written
+                    .filter(|(_, marker)| *marker != 0)
+                    .map(|(slot, marker)| {
+                        let reached = if slot % 2 == 0 {
+                            Reached::Started
+                        } else {
+                            Reached::Finished
+                        };
+                        self.breadcrumb(marker, reached)
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    pub fn cleanup(&mut self, device: &ash::Device) {
+        if let Markers::BufferMarker { buffer, .. } = &mut self.markers {
+            buffer.cleanup(device);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn device_lost_lists_the_last_passes_reached() {
+        let error = DeviceLost {
+            breadcrumbs: vec![
+                Breadcrumb {
+                    frame: 0,
+                    reached: Reached::Finished,
+                    pass: Some("trace"),
+                },
+                Breadcrumb {
+                    frame: 1,
+                    reached: Reached::Started,
+                    pass: None,
+                },
+            ],
+        };
+        assert_eq!(
+            error.to_string(),
+            "device lost, last reached: frame 0 finished trace, frame 1 started"
+        );
+    }
+}
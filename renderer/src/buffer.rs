@@ -1,8 +1,49 @@
-use std::{ptr, slice};
+use std::{mem, panic::Location, ptr, slice};
 
 use ash::{prelude::VkResult, vk};
+use data::{mesh::Mesh, vertex::VertexFormat};
 
-use crate::init_state::Queue;
+use crate::{
+    init_state::Queue,
+    tracking::{tracker, ObjectKind},
+};
+
+/// One indexed draw per mesh, for meshes packed back to back into shared vertex and index
+/// buffers in the same order. `first_instance` is the mesh's position in the list, so shaders
+/// can look up per-draw data with `gl_InstanceIndex`.
+pub fn indexed_indirect_commands<'m>(
+    meshes: impl IntoIterator<Item = &'m Mesh>,
+) -> Vec<vk::DrawIndexedIndirectCommand> {
+    let mut first_index = 0;
+    let mut vertex_offset = 0;
+    meshes
+        .into_iter()
+        .enumerate()
+        .map(|(index, mesh)| {
+            let command = vk::DrawIndexedIndirectCommand {
+                index_count: mesh.indices.len() as u32,
+                instance_count: 1,
+                first_index,
+                vertex_offset,
+                first_instance: index as u32,
+            };
+            first_index += mesh.indices.len() as u32;
+            vertex_offset += mesh.positions.len() as i32;
+            command
+        })
+        .collect()
+}
+
+pub const fn vk_vertex_format(format: VertexFormat) -> vk::Format {
+    match format {
+        VertexFormat::Float32 => vk::Format::R32_SFLOAT,
+        VertexFormat::Float32x2 => vk::Format::R32G32_SFLOAT,
+        VertexFormat::Float32x3 => vk::Format::R32G32B32_SFLOAT,
+        VertexFormat::Float32x4 => vk::Format::R32G32B32A32_SFLOAT,
+        VertexFormat::Unorm8x4 => vk::Format::R8G8B8A8_UNORM,
+        VertexFormat::Uint8x4 => vk::Format::R8G8B8A8_UINT,
+    }
+}
 
 pub struct Buffer<'a> {
     size: u64,
@@ -28,6 +69,7 @@ impl<'a> Buffer<'a> {
         &mut self.mapped
     }
 
+    #[track_caller]
     pub fn create(
         instance: &ash::Instance,
         device: &ash::Device,
@@ -36,6 +78,8 @@ impl<'a> Buffer<'a> {
         usage: vk::BufferUsageFlags,
         properties: vk::MemoryPropertyFlags,
     ) -> VkResult<Self> {
+        // Tracked under the file that created it
+        let tag = Location::caller().file();
         unsafe {
             let handle = device.create_buffer(
                 &vk::BufferCreateInfo::default()
@@ -69,6 +113,10 @@ impl<'a> Buffer<'a> {
 
             device.bind_buffer_memory(handle, memory, 0)?;
 
+            let mut tracker = tracker();
+            tracker.track_memory(memory, memory_requirements.size, tag);
+            tracker.track_object(ObjectKind::Buffer, handle, memory, tag);
+
             Ok(Self {
                 size,
                 handle,
@@ -78,6 +126,7 @@ impl<'a> Buffer<'a> {
         }
     }
 
+    #[track_caller]
     pub fn create_from_bytes_with_staging(
         instance: &ash::Instance,
         device: &ash::Device,
@@ -125,6 +174,50 @@ impl<'a> Buffer<'a> {
         }
     }
 
+    /// Device-local buffer of draw commands for `cmd_draw_indexed_indirect`. Also a storage
+    /// buffer, so GPU culling can rewrite the instance counts.
+    #[track_caller]
+    pub fn create_indirect(
+        instance: &ash::Instance,
+        device: &ash::Device,
+        physical_device: vk::PhysicalDevice,
+        command_fence: vk::Fence,
+        transfer_queue: &Queue,
+        commands: &[vk::DrawIndexedIndirectCommand],
+    ) -> VkResult<Self> {
+        // SAFETY: the command is four u32s and an i32 with no padding
+        let bytes = unsafe {
+            slice::from_raw_parts(commands.as_ptr().cast::<u8>(), mem::size_of_val(commands))
+        };
+        Self::create_from_bytes_with_staging(
+            instance,
+            device,
+            physical_device,
+            command_fence,
+            transfer_queue,
+            bytes,
+            vk::BufferUsageFlags::INDIRECT_BUFFER | vk::BufferUsageFlags::STORAGE_BUFFER,
+        )
+    }
+
+    /// Issues `draw_count` commands from this indirect buffer in one call
+    pub fn cmd_draw_indexed_indirect(
+        &self,
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        draw_count: u32,
+    ) {
+        unsafe {
+            device.cmd_draw_indexed_indirect(
+                command_buffer,
+                self.handle,
+                0,
+                draw_count,
+                mem::size_of::<vk::DrawIndexedIndirectCommand>() as u32,
+            );
+        }
+    }
+
     unsafe fn copy_handles(
         device: &ash::Device,
         command_fence: vk::Fence,
@@ -145,7 +238,7 @@ impl<'a> Buffer<'a> {
         Ok(())
     }
 
-    pub unsafe fn begin_single_time_commands(
+    pub(crate) unsafe fn begin_single_time_commands(
         device: &ash::Device,
         command_pool: vk::CommandPool,
     ) -> VkResult<vk::CommandBuffer> {
@@ -165,7 +258,7 @@ impl<'a> Buffer<'a> {
         Ok(command_buffer)
     }
 
-    pub unsafe fn end_single_time_commands(
+    pub(crate) unsafe fn end_single_time_commands(
         device: &ash::Device,
         command_buffer: vk::CommandBuffer,
         command_fence: vk::Fence,
@@ -249,8 +342,36 @@ impl<'a> Buffer<'a> {
             if self.mapped.is_some() {
                 device.unmap_memory(self.memory);
             }
-            device.free_memory(self.memory, None);
             device.destroy_buffer(self.handle, None);
+            device.free_memory(self.memory, None);
         }
+        let mut tracker = tracker();
+        tracker.untrack_object(ObjectKind::Buffer, self.handle);
+        tracker.untrack_memory(self.memory);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commands_follow_packed_meshes() {
+        let triangle = Mesh::new(vec![[0.0; 3]; 3], vec![0, 1, 2]);
+        let quad = Mesh::new(vec![[0.0; 3]; 4], vec![0, 1, 2, 0, 2, 3]);
+        let commands = indexed_indirect_commands([&triangle, &quad, &triangle]);
+
+        let fields: Vec<_> = commands
+            .iter()
+            .map(|c| {
+                (
+                    c.index_count,
+                    c.first_index,
+                    c.vertex_offset,
+                    c.first_instance,
+                )
+            })
+            .collect();
+        assert_eq!(fields, [(3, 0, 0, 0), (6, 3, 3, 1), (3, 9, 7, 2)]);
     }
 }
@@ -1,11 +1,22 @@
-use std::error::Error;
+use std::{error::Error, mem};
 
 use ash::{prelude::VkResult, vk};
 use bevy_ecs::system::Resource;
+use data::{
+    light::{LightGpu, MAX_LIGHTS},
+    material::MaterialGpu,
+    voxel::Voxel,
+    IntoBytes,
+};
 
 use crate::{
+    blue_noise::{BlueNoise, BlueNoiseTexture},
     buffer::Buffer,
+    capabilities::RenderPath,
+    hud::HUD_BUFFER_SIZE,
     init_state::{InitState, Queue},
+    picking::PickGpu,
+    uniform_ring::UniformRing,
     INDICES, MAX_FRAMES_IN_FLIGHT, UNIFORM_BUFFER_SIZE, VERTICES,
 };
 
@@ -13,7 +24,14 @@ use crate::{
 pub struct BufferState<'a> {
     vertex_buffer: Buffer<'a>,
     index_buffer: Buffer<'a>,
-    uniform_buffers: Vec<Buffer<'a>>,
+    uniforms: UniformRing<'a>,
+    material_buffer: Buffer<'a>,
+    pick_buffers: Vec<Buffer<'a>>,
+    hud_buffers: Vec<Buffer<'a>>,
+    light_buffers: Vec<Buffer<'a>>,
+    /// Per frame in flight, created on the first capture and resized with the render extent
+    capture_buffers: Vec<Option<(Buffer<'a>, vk::Extent2D)>>,
+    blue_noise: BlueNoiseTexture,
 }
 
 impl<'a> BufferState<'a> {
@@ -25,22 +43,91 @@ impl<'a> BufferState<'a> {
         &self.index_buffer
     }
 
-    pub fn uniform_buffers(&self) -> &[Buffer<'a>] {
-        &self.uniform_buffers
+    /// The camera block for every frame in flight
+    pub fn uniforms(&self) -> &UniformRing<'a> {
+        &self.uniforms
+    }
+
+    pub fn uniforms_mut(&mut self) -> &mut UniformRing<'a> {
+        &mut self.uniforms
+    }
+
+    /// Materials indexed by `VoxelId`, read by the hit shaders
+    pub fn material_buffer(&self) -> &Buffer<'a> {
+        &self.material_buffer
+    }
+
+    /// Per frame in flight, written by the raygen shader when picking is enabled
+    pub fn pick_buffers(&self) -> &[Buffer<'a>] {
+        &self.pick_buffers
+    }
+
+    pub(crate) fn pick_buffers_mut(&mut self) -> &mut [Buffer<'a>] {
+        &mut self.pick_buffers
+    }
+
+    /// Per frame in flight staging for the HUD rects copied onto the swapchain image
+    pub fn hud_buffers(&self) -> &[Buffer<'a>] {
+        &self.hud_buffers
+    }
+
+    pub(crate) fn hud_buffers_mut(&mut self) -> &mut [Buffer<'a>] {
+        &mut self.hud_buffers
+    }
+
+    /// Sampled by the stochastic effects instead of white noise
+    /// Per frame in flight, room for [`MAX_LIGHTS`] lights read by the hit shader
+    pub fn light_buffers(&self) -> &[Buffer<'a>] {
+        &self.light_buffers
     }
 
-    pub fn uniform_buffers_mut(&mut self) -> &mut [Buffer<'a>] {
-        &mut self.uniform_buffers
+    pub(crate) fn light_buffers_mut(&mut self) -> &mut [Buffer<'a>] {
+        &mut self.light_buffers
+    }
+
+    /// The frame's host-visible copy target for its output image, sized for `extent` RGBA8
+    /// pixels. Only call once the frame's fence has signaled, since it may be recreated.
+    pub(crate) fn capture_buffer(
+        &mut self,
+        init_state: &InitState,
+        current_frame: u8,
+        extent: vk::Extent2D,
+    ) -> VkResult<&mut Buffer<'a>> {
+        let slot = &mut self.capture_buffers[current_frame as usize];
+        if slot.as_ref().is_some_and(|(_, size)| *size != extent) {
+            if let Some((mut buffer, _)) = slot.take() {
+                buffer.cleanup(init_state.device());
+            }
+        }
+        if slot.is_none() {
+            let mut buffer = Buffer::create(
+                init_state.instance(),
+                init_state.device(),
+                init_state.physical_device(),
+                extent.width as u64 * extent.height as u64 * 4,
+                vk::BufferUsageFlags::TRANSFER_DST,
+                vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+            )?;
+            buffer.map_memory(init_state.device(), 0, vk::MemoryMapFlags::empty())?;
+            *slot = Some((buffer, extent));
+        }
+        Ok(slot.as_mut().map(|(buffer, _)| buffer).unwrap())
     }
 
-    pub fn new(init_state: &InitState) -> Result<Self, Box<dyn Error>> {
+    pub fn blue_noise(&self) -> &BlueNoiseTexture {
+        &self.blue_noise
+    }
+
+    pub fn new(init_state: &InitState, blue_noise: &BlueNoise) -> Result<Self, Box<dyn Error>> {
         unsafe {
+            let geometry_usage = Self::geometry_usage(init_state.render_path());
             let vertex_buffer = Self::create_vertex_buffer(
                 init_state.instance(),
                 init_state.device(),
                 init_state.physical_device(),
                 init_state.queues().command_fence().unwrap(),
                 init_state.queues().transfer(),
+                geometry_usage,
             )?;
 
             let index_buffer = Self::create_index_buffer(
@@ -49,29 +136,80 @@ impl<'a> BufferState<'a> {
                 init_state.physical_device(),
                 init_state.queues().command_fence().unwrap(),
                 init_state.queues().transfer(),
+                geometry_usage,
+            )?;
+
+            let uniforms = UniformRing::new(
+                init_state.instance(),
+                init_state.device(),
+                init_state.physical_device(),
+                UNIFORM_BUFFER_SIZE as u64,
+                MAX_FRAMES_IN_FLIGHT,
+            )?;
+
+            let material_buffer = Self::create_material_buffer(
+                init_state.instance(),
+                init_state.device(),
+                init_state.physical_device(),
+                init_state.queues().command_fence().unwrap(),
+                init_state.queues().transfer(),
+            )?;
+
+            let pick_buffers = Self::create_pick_buffers(
+                init_state.instance(),
+                init_state.device(),
+                init_state.physical_device(),
+                MAX_FRAMES_IN_FLIGHT,
+            )?;
+
+            let hud_buffers = Self::create_hud_buffers(
+                init_state.instance(),
+                init_state.device(),
+                init_state.physical_device(),
+                MAX_FRAMES_IN_FLIGHT,
             )?;
 
-            let uniform_buffers = Self::create_uniform_buffers(
+            let light_buffers = Self::create_light_buffers(
                 init_state.instance(),
                 init_state.device(),
                 init_state.physical_device(),
                 MAX_FRAMES_IN_FLIGHT,
             )?;
 
+            let blue_noise = BlueNoiseTexture::new(init_state, blue_noise)?;
+
             Ok(Self {
                 vertex_buffer,
                 index_buffer,
-                uniform_buffers,
+                uniforms,
+                material_buffer,
+                pick_buffers,
+                hud_buffers,
+                light_buffers,
+                capture_buffers: (0..MAX_FRAMES_IN_FLIGHT).map(|_| None).collect(),
+                blue_noise,
             })
         }
     }
 
+    /// Extra usage for the scene geometry; acceleration structure builds read it by address,
+    /// which the raster path can't enable
+    fn geometry_usage(render_path: RenderPath) -> vk::BufferUsageFlags {
+        if render_path.uses_acceleration_structures() {
+            vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS
+                | vk::BufferUsageFlags::ACCELERATION_STRUCTURE_BUILD_INPUT_READ_ONLY_KHR
+        } else {
+            vk::BufferUsageFlags::empty()
+        }
+    }
+
     unsafe fn create_vertex_buffer(
         instance: &ash::Instance,
         device: &ash::Device,
         physical_device: vk::PhysicalDevice,
         command_fence: vk::Fence,
         transfer_queue: &Queue,
+        geometry_usage: vk::BufferUsageFlags,
     ) -> VkResult<Buffer<'a>> {
         let positions = VERTICES.map(|v| v.pos);
         Buffer::create_from_bytes_with_staging(
@@ -84,8 +222,7 @@ impl<'a> BufferState<'a> {
             vk::BufferUsageFlags::VERTEX_BUFFER
                 | vk::BufferUsageFlags::STORAGE_BUFFER
                 | vk::BufferUsageFlags::TRANSFER_DST
-                | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS
-                | vk::BufferUsageFlags::ACCELERATION_STRUCTURE_BUILD_INPUT_READ_ONLY_KHR,
+                | geometry_usage,
         )
     }
 
@@ -95,6 +232,7 @@ impl<'a> BufferState<'a> {
         physical_device: vk::PhysicalDevice,
         command_fence: vk::Fence,
         transfer_queue: &Queue,
+        geometry_usage: vk::BufferUsageFlags,
     ) -> VkResult<Buffer<'a>> {
         Buffer::create_from_bytes_with_staging(
             instance,
@@ -106,42 +244,118 @@ impl<'a> BufferState<'a> {
             vk::BufferUsageFlags::INDEX_BUFFER
                 | vk::BufferUsageFlags::STORAGE_BUFFER
                 | vk::BufferUsageFlags::TRANSFER_DST
-                | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS
-                | vk::BufferUsageFlags::ACCELERATION_STRUCTURE_BUILD_INPUT_READ_ONLY_KHR,
+                | geometry_usage,
+        )
+    }
+
+    unsafe fn create_material_buffer(
+        instance: &ash::Instance,
+        device: &ash::Device,
+        physical_device: vk::PhysicalDevice,
+        command_fence: vk::Fence,
+        transfer_queue: &Queue,
+    ) -> VkResult<Buffer<'a>> {
+        let materials = material_table();
+        Buffer::create_from_bytes_with_staging(
+            instance,
+            device,
+            physical_device,
+            command_fence,
+            transfer_queue,
+            materials.to_bytes(),
+            vk::BufferUsageFlags::STORAGE_BUFFER | vk::BufferUsageFlags::TRANSFER_DST,
         )
     }
 
-    unsafe fn create_uniform_buffers(
+    unsafe fn create_pick_buffers(
         instance: &ash::Instance,
         device: &ash::Device,
         physical_device: vk::PhysicalDevice,
         frames: u8,
     ) -> VkResult<Vec<Buffer<'a>>> {
-        let buffer_size = UNIFORM_BUFFER_SIZE;
-
-        let mut buffers = Vec::with_capacity(frames as usize);
+        (0..frames)
+            .map(|_| {
+                let mut buffer = Buffer::create(
+                    instance,
+                    device,
+                    physical_device,
+                    mem::size_of::<PickGpu>() as u64,
+                    vk::BufferUsageFlags::STORAGE_BUFFER,
+                    vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+                )?;
+                buffer.map_memory(device, 0, vk::MemoryMapFlags::empty())?;
+                buffer.write(bytemuck::bytes_of(&PickGpu::NO_HIT));
+                Ok(buffer)
+            })
+            .collect()
+    }
 
-        for _ in 0..frames as usize {
-            let mut buffer = Buffer::create(
-                instance,
-                device,
-                physical_device,
-                buffer_size as u64,
-                vk::BufferUsageFlags::UNIFORM_BUFFER | vk::BufferUsageFlags::TRANSFER_DST,
-                vk::MemoryPropertyFlags::HOST_VISIBLE | { vk::MemoryPropertyFlags::HOST_COHERENT },
-            )?;
-            buffer.map_memory(device, 0, vk::MemoryMapFlags::empty())?;
-            buffers.push(buffer);
-        }
+    unsafe fn create_hud_buffers(
+        instance: &ash::Instance,
+        device: &ash::Device,
+        physical_device: vk::PhysicalDevice,
+        frames: u8,
+    ) -> VkResult<Vec<Buffer<'a>>> {
+        (0..frames)
+            .map(|_| {
+                let mut buffer = Buffer::create(
+                    instance,
+                    device,
+                    physical_device,
+                    HUD_BUFFER_SIZE as u64,
+                    vk::BufferUsageFlags::TRANSFER_SRC,
+                    vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+                )?;
+                buffer.map_memory(device, 0, vk::MemoryMapFlags::empty())?;
+                Ok(buffer)
+            })
+            .collect()
+    }
 
-        Ok(buffers)
+    unsafe fn create_light_buffers(
+        instance: &ash::Instance,
+        device: &ash::Device,
+        physical_device: vk::PhysicalDevice,
+        frames: u8,
+    ) -> VkResult<Vec<Buffer<'a>>> {
+        (0..frames)
+            .map(|_| {
+                let mut buffer = Buffer::create(
+                    instance,
+                    device,
+                    physical_device,
+                    (MAX_LIGHTS * mem::size_of::<LightGpu>()) as u64,
+                    vk::BufferUsageFlags::STORAGE_BUFFER,
+                    vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+                )?;
+                buffer.map_memory(device, 0, vk::MemoryMapFlags::empty())?;
+                Ok(buffer)
+            })
+            .collect()
     }
 
     pub fn cleanup(&mut self, init_state: &InitState) {
         self.vertex_buffer.cleanup(init_state.device());
         self.index_buffer.cleanup(init_state.device());
-        for uniform_buffer in &mut self.uniform_buffers {
-            uniform_buffer.cleanup(init_state.device());
+        self.uniforms.cleanup(init_state.device());
+        self.material_buffer.cleanup(init_state.device());
+        for pick_buffer in &mut self.pick_buffers {
+            pick_buffer.cleanup(init_state.device());
         }
+        for hud_buffer in &mut self.hud_buffers {
+            hud_buffer.cleanup(init_state.device());
+        }
+        for light_buffer in &mut self.light_buffers {
+            light_buffer.cleanup(init_state.device());
+        }
+        for (capture_buffer, _) in self.capture_buffers.iter_mut().flatten() {
+            capture_buffer.cleanup(init_state.device());
+        }
+        self.blue_noise.cleanup(init_state);
     }
 }
+
+/// Contents of the material buffer: every voxel's material, indexed by its ID
+pub(crate) fn material_table() -> [MaterialGpu; Voxel::VOXEL_COUNT as usize] {
+    Voxel::ALL.map(|voxel| MaterialGpu::new(&voxel.material()))
+}
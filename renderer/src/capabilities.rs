@@ -0,0 +1,282 @@
+use std::ffi::CStr;
+
+use ash::{amd, khr, nv, prelude::VkResult, vk};
+use bevy_ecs::system::Resource;
+use serde::{Deserialize, Serialize};
+
+/// How frames are rendered, from least to most capable
+#[derive(
+    Resource, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub enum RenderPath {
+    /// Dynamic-rendering rasterizer for devices without ray tracing
+    Raster,
+    /// Compute-shader voxel ray marcher for devices with no ray tracing hardware at all
+    Compute,
+    /// Rasterized primary visibility, with shadows and AO from ray queries in the fragment
+    /// shader. Cheaper than full ray tracing on mid-tier hardware.
+    Hybrid,
+    RayTracing,
+}
+
+impl RenderPath {
+    /// Most capable first
+    pub const ALL: [Self; 4] = [Self::RayTracing, Self::Hybrid, Self::Compute, Self::Raster];
+
+    /// Whether frames go through the ray tracing pipeline's acceleration structures. The hybrid
+    /// path queries the same TLAS, which is still built alongside the ray tracing pipeline.
+    pub const fn uses_acceleration_structures(&self) -> bool {
+        matches!(self, Self::Hybrid | Self::RayTracing)
+    }
+
+    /// Whether the crate's `rt` and `raster` features allow picking the path
+    pub const fn is_enabled(&self) -> bool {
+        match self {
+            Self::Raster => cfg!(feature = "raster"),
+            Self::Compute => true,
+            Self::Hybrid => cfg!(all(feature = "rt", feature = "raster")),
+            Self::RayTracing => cfg!(feature = "rt"),
+        }
+    }
+}
+
+/// Optional device features, queried per physical device so the renderer can pick a
+/// [`RenderPath`] instead of requiring everything up front
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct AdapterCapabilities {
+    pub api_version: u32,
+    /// `VK_KHR_ray_tracing_pipeline`, including its `VK_KHR_deferred_host_operations` dependency
+    pub ray_tracing_pipeline: bool,
+    pub acceleration_structure: bool,
+    pub ray_query: bool,
+    pub descriptor_indexing: bool,
+    pub buffer_device_address: bool,
+    /// 16-bit storage and uniform buffer access, used for the index buffers the hit shaders read
+    pub storage_16bit: bool,
+    pub shader_int64: bool,
+    pub dynamic_rendering: bool,
+    pub multi_draw_indirect: bool,
+    pub sampler_anisotropy: bool,
+    /// `VK_NV_device_diagnostic_checkpoints`, for the `gpu-breadcrumbs` feature
+    pub diagnostic_checkpoints: bool,
+    /// `VK_AMD_buffer_marker`, for the `gpu-breadcrumbs` feature where there are no checkpoints
+    pub buffer_marker: bool,
+    /// `VK_KHR_present_wait` with the `VK_KHR_present_id` it relies on, for timing presents as
+    /// they reach the display
+    pub present_wait: bool,
+}
+
+impl AdapterCapabilities {
+    /// Dynamic rendering is core from here on
+    const MIN_API_VERSION: u32 = vk::API_VERSION_1_3;
+
+    pub fn query(instance: &ash::Instance, physical_device: vk::PhysicalDevice) -> VkResult<Self> {
+        unsafe {
+            let extensions: Vec<_> = instance
+                .enumerate_device_extension_properties(physical_device)?
+                .iter()
+                .filter_map(|extension| extension.extension_name_as_c_str().ok())
+                .map(CStr::to_owned)
+                .collect();
+            let has_extension = |name: &CStr| extensions.iter().any(|e| e.as_c_str() == name);
+
+            let mut vulkan11_features = vk::PhysicalDeviceVulkan11Features::default();
+            let mut vulkan12_features = vk::PhysicalDeviceVulkan12Features::default();
+            let mut vulkan13_features = vk::PhysicalDeviceVulkan13Features::default();
+            let mut ray_tracing_pipeline_features =
+                vk::PhysicalDeviceRayTracingPipelineFeaturesKHR::default();
+            let mut acceleration_structure_features =
+                vk::PhysicalDeviceAccelerationStructureFeaturesKHR::default();
+            let mut ray_query_features = vk::PhysicalDeviceRayQueryFeaturesKHR::default();
+            let mut present_id_features = vk::PhysicalDevicePresentIdFeaturesKHR::default();
+            let mut present_wait_features = vk::PhysicalDevicePresentWaitFeaturesKHR::default();
+            let mut features = vk::PhysicalDeviceFeatures2::default()
+                .push_next(&mut vulkan11_features)
+                .push_next(&mut vulkan12_features)
+                .push_next(&mut vulkan13_features)
+                .push_next(&mut ray_tracing_pipeline_features)
+                .push_next(&mut acceleration_structure_features)
+                .push_next(&mut ray_query_features)
+                .push_next(&mut present_id_features)
+                .push_next(&mut present_wait_features);
+            instance.get_physical_device_features2(physical_device, &mut features);
+            let core = features.features;
+
+            let api_version = instance
+                .get_physical_device_properties(physical_device)
+                .api_version;
+
+            Ok(Self {
+                api_version,
+                ray_tracing_pipeline: has_extension(khr::ray_tracing_pipeline::NAME)
+                    && has_extension(khr::deferred_host_operations::NAME)
+                    && ray_tracing_pipeline_features.ray_tracing_pipeline != 0,
+                acceleration_structure: has_extension(khr::acceleration_structure::NAME)
+                    && acceleration_structure_features.acceleration_structure != 0,
+                ray_query: has_extension(khr::ray_query::NAME) && ray_query_features.ray_query != 0,
+                descriptor_indexing: vulkan12_features.descriptor_indexing != 0,
+                // Addresses are fetched through the KHR entry points
+                buffer_device_address: has_extension(khr::buffer_device_address::NAME)
+                    && vulkan12_features.buffer_device_address != 0,
+                storage_16bit: vulkan11_features.storage_buffer16_bit_access != 0
+                    && vulkan11_features.uniform_and_storage_buffer16_bit_access != 0,
+                shader_int64: core.shader_int64 != 0,
+                dynamic_rendering: vulkan13_features.dynamic_rendering != 0,
+                multi_draw_indirect: core.multi_draw_indirect != 0,
+                sampler_anisotropy: core.sampler_anisotropy != 0,
+                diagnostic_checkpoints: has_extension(nv::device_diagnostic_checkpoints::NAME),
+                buffer_marker: has_extension(amd::buffer_marker::NAME),
+                present_wait: has_extension(khr::present_id::NAME)
+                    && has_extension(khr::present_wait::NAME)
+                    && present_id_features.present_id != 0
+                    && present_wait_features.present_wait != 0,
+            })
+        }
+    }
+
+    /// The most capable enabled path the device can run that is no more capable than
+    /// `preferred`, or `None` if it can't even rasterize
+    pub fn render_path(&self, preferred: Option<RenderPath>) -> Option<RenderPath> {
+        RenderPath::ALL
+            .into_iter()
+            .filter(RenderPath::is_enabled)
+            .filter(|path| preferred.is_none_or(|preferred| *path <= preferred))
+            .find(|path| self.supports(*path))
+    }
+
+    pub fn supports(&self, path: RenderPath) -> bool {
+        match path {
+            RenderPath::Raster | RenderPath::Compute => self.supports_raster(),
+            RenderPath::Hybrid => {
+                self.supports_raster() && self.supports_ray_tracing() && self.ray_query
+            }
+            RenderPath::RayTracing => self.supports_raster() && self.supports_ray_tracing(),
+        }
+    }
+
+    /// Everything both paths rely on: dynamic rendering, textures and indirect chunk draws
+    fn supports_raster(&self) -> bool {
+        self.api_version >= Self::MIN_API_VERSION
+            && self.dynamic_rendering
+            && self.multi_draw_indirect
+            && self.sampler_anisotropy
+    }
+
+    fn supports_ray_tracing(&self) -> bool {
+        self.missing_for_ray_tracing().is_empty()
+    }
+
+    /// Names of the features keeping the device off [`RenderPath::RayTracing`], for logging
+    pub fn missing_for_ray_tracing(&self) -> Vec<&'static str> {
+        [
+            (self.ray_tracing_pipeline, "ray tracing pipeline"),
+            (self.acceleration_structure, "acceleration structures"),
+            (self.buffer_device_address, "buffer device address"),
+            (self.storage_16bit, "16-bit storage"),
+            (self.shader_int64, "64-bit shader integers"),
+        ]
+        .into_iter()
+        .filter(|(supported, _)| !supported)
+        .map(|(_, name)| name)
+        .collect()
+    }
+
+    /// Extensions to enable on the logical device: only the ones the device has and `path`
+    /// uses
+    pub fn device_extensions(&self, path: RenderPath) -> Vec<&'static CStr> {
+        let mut extensions = vec![khr::swapchain::NAME];
+        if path.uses_acceleration_structures() {
+            extensions.extend([
+                khr::ray_tracing_pipeline::NAME,
+                khr::acceleration_structure::NAME,
+                khr::deferred_host_operations::NAME,
+                khr::buffer_device_address::NAME,
+            ]);
+        }
+        if self.ray_query {
+            extensions.push(khr::ray_query::NAME);
+        }
+        if cfg!(feature = "gpu-breadcrumbs") {
+            if self.diagnostic_checkpoints {
+                extensions.push(nv::device_diagnostic_checkpoints::NAME);
+            } else if self.buffer_marker {
+                extensions.push(amd::buffer_marker::NAME);
+            }
+        }
+        if self.present_wait {
+            extensions.extend([khr::present_id::NAME, khr::present_wait::NAME]);
+        }
+        #[cfg(any(target_os = "macos", target_os = "ios"))]
+        extensions.push(khr::portability_subset::NAME);
+        extensions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FULL: AdapterCapabilities = AdapterCapabilities {
+        api_version: vk::API_VERSION_1_3,
+        ray_tracing_pipeline: true,
+        acceleration_structure: true,
+        ray_query: true,
+        descriptor_indexing: true,
+        buffer_device_address: true,
+        storage_16bit: true,
+        shader_int64: true,
+        dynamic_rendering: true,
+        multi_draw_indirect: true,
+        sampler_anisotropy: true,
+        diagnostic_checkpoints: false,
+        buffer_marker: false,
+        present_wait: false,
+    };
+
+    #[test]
+    #[cfg(all(feature = "rt", feature = "raster"))]
+    fn missing_features_fall_back_to_raster_or_nothing() {
+        assert_eq!(FULL.render_path(None), Some(RenderPath::RayTracing));
+
+        let no_ray_tracing = AdapterCapabilities {
+            ray_tracing_pipeline: false,
+            ..FULL
+        };
+        assert_eq!(no_ray_tracing.render_path(None), Some(RenderPath::Compute));
+        assert_eq!(
+            no_ray_tracing.missing_for_ray_tracing(),
+            ["ray tracing pipeline"]
+        );
+        assert!(!no_ray_tracing
+            .device_extensions(RenderPath::Compute)
+            .contains(&khr::ray_tracing_pipeline::NAME));
+
+        let old_api = AdapterCapabilities {
+            api_version: vk::API_VERSION_1_2,
+            ..FULL
+        };
+        assert_eq!(old_api.render_path(None), None);
+    }
+
+    #[test]
+    #[cfg(all(feature = "rt", feature = "raster"))]
+    fn preference_caps_the_path() {
+        assert_eq!(
+            FULL.render_path(Some(RenderPath::Hybrid)),
+            Some(RenderPath::Hybrid)
+        );
+
+        let no_ray_query = AdapterCapabilities {
+            ray_query: false,
+            ..FULL
+        };
+        assert_eq!(
+            no_ray_query.render_path(Some(RenderPath::Hybrid)),
+            Some(RenderPath::Compute)
+        );
+        assert_eq!(
+            FULL.render_path(Some(RenderPath::Raster)),
+            Some(RenderPath::Raster)
+        );
+    }
+}
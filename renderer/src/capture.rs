@@ -0,0 +1,156 @@
+use ash::vk;
+
+use crate::render_graph::{BufferState as GraphBufferState, ImageHandle, ImageState, RenderGraph};
+
+/// A frame's output image read back to the CPU, before the HUD is drawn over it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Capture {
+    pub width: u32,
+    pub height: u32,
+    /// RGBA8, row by row from the top
+    pub pixels: Vec<u8>,
+}
+
+impl Capture {
+    const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+    /// Largest stored (uncompressed) deflate block
+    const MAX_STORED_BLOCK: usize = u16::MAX as usize;
+
+    /// Encodes the capture as an RGBA PNG. The image data is stored without compression, which
+    /// keeps the encoder tiny at the cost of file size.
+    pub fn to_png(&self) -> Vec<u8> {
+        let row_size = self.width as usize * 4;
+        let mut scanlines = Vec::with_capacity((row_size + 1) * self.height as usize);
+        for row in self.pixels.chunks_exact(row_size) {
+            // Filter type: none
+            scanlines.push(0);
+            scanlines.extend_from_slice(row);
+        }
+
+        // zlib header: deflate with a 32K window, no preset dictionary, fastest
+        let mut zlib = vec![0x78, 0x01];
+        let mut blocks = scanlines.chunks(Self::MAX_STORED_BLOCK).peekable();
+        if blocks.peek().is_none() {
+            zlib.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+        }
+        while let Some(block) = blocks.next() {
+            let len = block.len() as u16;
+            zlib.push(blocks.peek().is_none() as u8);
+            zlib.extend_from_slice(&len.to_le_bytes());
+            zlib.extend_from_slice(&(!len).to_le_bytes());
+            zlib.extend_from_slice(block);
+        }
+        zlib.extend_from_slice(&adler32(&scanlines).to_be_bytes());
+
+        let mut header = Vec::with_capacity(13);
+        header.extend_from_slice(&self.width.to_be_bytes());
+        header.extend_from_slice(&self.height.to_be_bytes());
+        // 8 bits per channel, RGBA, deflate, adaptive filtering, not interlaced
+        header.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+        let mut png = Self::PNG_SIGNATURE.to_vec();
+        write_chunk(&mut png, b"IHDR", &header);
+        write_chunk(&mut png, b"IDAT", &zlib);
+        write_chunk(&mut png, b"IEND", &[]);
+        png
+    }
+}
+
+fn write_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0u32, |crc, &byte| {
+        (0..8).fold(crc ^ byte as u32, |crc, _| {
+            if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            }
+        })
+    })
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    const MODULUS: u32 = 65521;
+    let (a, b) = bytes.iter().fold((1u32, 0u32), |(a, b), &byte| {
+        let a = (a + byte as u32) % MODULUS;
+        (a, (b + a) % MODULUS)
+    });
+    (b << 16) | a
+}
+
+/// Copies `output` into `buffer`, which must be host visible and hold `extent` RGBA8 pixels.
+/// The copy is finished once the frame's fence signals.
+pub fn add_capture_pass<'a>(
+    graph: &mut RenderGraph<'a>,
+    device: &'a ash::Device,
+    output: ImageHandle,
+    buffer: vk::Buffer,
+    extent: vk::Extent2D,
+) {
+    let host_read = GraphBufferState::new(vk::PipelineStageFlags::HOST, vk::AccessFlags::HOST_READ);
+    let capture = graph.import_buffer(buffer, host_read, Some(host_read));
+    graph.add_pass(
+        "capture",
+        |pass| {
+            pass.image(output, ImageState::TRANSFER_SRC).buffer(
+                capture,
+                GraphBufferState::new(
+                    vk::PipelineStageFlags::TRANSFER,
+                    vk::AccessFlags::TRANSFER_WRITE,
+                ),
+            );
+        },
+        move |command_buffer, resources| unsafe {
+            device.cmd_copy_image_to_buffer(
+                command_buffer,
+                resources.image(output),
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                buffer,
+                &[vk::BufferImageCopy::default()
+                    .image_subresource(
+                        vk::ImageSubresourceLayers::default()
+                            .aspect_mask(vk::ImageAspectFlags::COLOR)
+                            .layer_count(1),
+                    )
+                    .image_extent(vk::Extent3D {
+                        width: extent.width,
+                        height: extent.height,
+                        depth: 1,
+                    })],
+            );
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn png_has_valid_chunks_and_checksums() {
+        assert_eq!(crc32(b"IEND"), 0xae42_6082);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+
+        let capture = Capture {
+            width: 2,
+            height: 1,
+            pixels: vec![255, 0, 0, 255, 0, 0, 255, 255],
+        };
+        let png = capture.to_png();
+        assert_eq!(png[..8], Capture::PNG_SIGNATURE);
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(&png[16..24], &[0, 0, 0, 2, 0, 0, 0, 1]);
+        assert_eq!(
+            &png[png.len() - 12..],
+            &[0, 0, 0, 0, b'I', b'E', b'N', b'D', 0xae, 0x42, 0x60, 0x82]
+        );
+    }
+}
@@ -1,20 +1,142 @@
-use std::error::Error;
+use std::{error::Error, mem};
 
 use ash::{prelude::VkResult, vk};
 use bevy_ecs::system::Resource;
-use data::{camera::CameraGpu, IntoBytes};
+use data::{
+    camera::CameraGpu,
+    exposure::LuminanceHistogram,
+    light::{LightGpu, MAX_LIGHTS},
+    IntoBytes,
+};
 
-use glam::Vec2;
+use data::prelude::Vec2;
 
 use crate::{
-    acceleration_structure_state::AccelerationStructureState, buffer_state::BufferState,
-    init_state::InitState, pipeline_state::PipelineState, swapchain_state::SwapchainState,
+    acceleration_structure_state::AccelerationStructureState,
+    breadcrumbs::{Breadcrumbs, DeviceLost},
+    buffer_state::BufferState,
+    capture::{add_capture_pass, Capture},
+    compute_state::{ComputeState, MarchPushConstants},
+    histogram::HistogramPass,
+    hud::{stage_hud, Hud},
+    init_state::InitState,
+    pass_timer::{PassTimer, PassTiming},
+    picking::{PickGpu, PickHit},
+    pipeline_state::PipelineState,
+    present_wait::PresentWaiter,
+    raster_state::{RasterPushConstants, RasterState, RasterTarget},
+    render_graph::{
+        BufferState as GraphBufferState, ImageHandle, ImageState, PassMarkers, RenderGraph,
+    },
+    settings::RendererSettings,
+    swapchain_state::SwapchainState,
+    transient_images::TransientImages,
+    PushConstants,
 };
 
+/// The state a frame is recorded with, depending on the device's
+/// [`RenderPath`](crate::capabilities::RenderPath)
+pub enum FramePath<'s, 'a> {
+    RayTracing {
+        pipeline_state: &'s PipelineState<'a>,
+        acceleration_structure_state: &'s mut AccelerationStructureState<'a>,
+    },
+    /// Rasterizes through a [`RasterShading::RayQuery`](crate::raster_state::RasterShading)
+    /// pipeline that queries the ray tracing path's TLAS
+    Hybrid {
+        acceleration_structure_state: &'s mut AccelerationStructureState<'a>,
+        raster_state: &'s mut RasterState,
+    },
+    Raster(&'s mut RasterState),
+    /// Marches the voxel grid in a compute shader instead of tracing the TLAS
+    Compute(&'s mut ComputeState<'a>),
+    /// Only the HUD over a cleared screen, e.g. a loading screen drawn while the path's own
+    /// state is still being created
+    Splash,
+}
+
+impl FramePath<'_, '_> {
+    /// Recreates the swapchain and whatever of this path depends on its formats. Descriptor
+    /// sets pointing at its images are refreshed per frame by
+    /// [`refresh_descriptor_sets`](Self::refresh_descriptor_sets) instead.
+    pub fn recreate_swapchain(
+        &mut self,
+        init_state: &InitState,
+        swapchain_state: &mut SwapchainState,
+        window_size: Vec2,
+    ) -> Result<(), Box<dyn Error>> {
+        swapchain_state.recreate_swapchain(init_state, window_size)?;
+        match self {
+            Self::Hybrid { raster_state, .. } | Self::Raster(raster_state) => {
+                raster_state.recreate_pipeline(init_state, swapchain_state)?
+            }
+            Self::RayTracing { .. } | Self::Compute(_) | Self::Splash => (),
+        }
+        Ok(())
+    }
+
+    /// Brings the frame's descriptor sets up to date with the swapchain's images and the
+    /// current TLAS. Only call once the frame's fence has signaled, so none of its sets are in
+    /// use.
+    pub fn refresh_descriptor_sets(
+        &mut self,
+        device: &ash::Device,
+        buffer_state: &BufferState,
+        swapchain_state: &SwapchainState,
+        current_frame: u8,
+    ) {
+        match self {
+            Self::RayTracing {
+                acceleration_structure_state,
+                ..
+            } => acceleration_structure_state.refresh_descriptor_set(
+                device,
+                buffer_state,
+                swapchain_state,
+                current_frame,
+            ),
+            Self::Hybrid {
+                acceleration_structure_state,
+                raster_state,
+            } => {
+                raster_state.write_tlas(device, current_frame, acceleration_structure_state.tlas())
+            }
+            Self::Raster(_) | Self::Splash => (),
+            Self::Compute(compute_state) => compute_state.refresh_descriptor_set(
+                device,
+                buffer_state,
+                swapchain_state,
+                current_frame,
+            ),
+        }
+    }
+}
+
 #[derive(Resource)]
 pub struct CommandState {
     command_buffers: Vec<vk::CommandBuffer>,
     sync_objects: SyncObjects,
+    /// One pool per frame in flight, reused once the frame's fence has signaled
+    transient_images: Vec<TransientImages>,
+    accumulation: Accumulation,
+    last_pick: Option<PickHit>,
+    capture_requested: bool,
+    /// Extent of the capture recorded into each frame in flight, read once its fence signals
+    pending_captures: Vec<Option<vk::Extent2D>>,
+    last_capture: Option<Capture>,
+    /// Created the first time auto exposure asks for a histogram
+    histogram_pass: Option<HistogramPass<'static>>,
+    histogram_requested: bool,
+    /// Whether each frame in flight recorded a histogram, read once its fence signals
+    pending_histograms: Vec<bool>,
+    last_histogram: Option<LuminanceHistogram>,
+    /// Created the first time pass timings are asked for; `None` after that if the device
+    /// can't time them
+    pass_timer: Option<PassTimer>,
+    pass_timing_requested: bool,
+    pass_timer_created: bool,
+    last_pass_timings: Option<Vec<PassTiming>>,
+    breadcrumbs: Option<Breadcrumbs>,
 }
 
 impl CommandState {
@@ -27,32 +149,196 @@ impl CommandState {
 
             let sync_objects = SyncObjects::new(init_state.device())?;
 
+            let transient_images = (0..MAX_FRAMES_IN_FLIGHT)
+                .map(|_| TransientImages::new(init_state))
+                .collect();
+
             Ok(Self {
                 command_buffers,
                 sync_objects,
+                transient_images,
+                accumulation: Accumulation::default(),
+                last_pick: None,
+                capture_requested: false,
+                pending_captures: vec![None; MAX_FRAMES_IN_FLIGHT as usize],
+                last_capture: None,
+                histogram_pass: None,
+                histogram_requested: false,
+                pending_histograms: vec![false; MAX_FRAMES_IN_FLIGHT as usize],
+                last_histogram: None,
+                pass_timer: None,
+                pass_timing_requested: false,
+                pass_timer_created: false,
+                last_pass_timings: None,
+                breadcrumbs: Breadcrumbs::new(init_state)?,
             })
         }
     }
 
+    /// Discards accumulated samples, e.g. after a teleport or scene change
+    pub fn reset_accumulation(&mut self) {
+        self.accumulation.frames = 0;
+    }
+
+    /// Latest crosshair hit read back from the GPU. Lags rendering by the number of frames in
+    /// flight, so the instance may have been removed since.
+    pub const fn last_pick(&self) -> Option<PickHit> {
+        self.last_pick
+    }
+
+    /// Copies the next frame's output image back to the CPU, for [`Self::take_capture`] to
+    /// return a few frames later. Only the ray tracing and compute paths render to an output
+    /// image; on the others the request is dropped.
+    pub fn request_capture(&mut self) {
+        self.capture_requested = true;
+    }
+
+    /// The finished capture, if one has been read back since the last call
+    pub fn take_capture(&mut self) -> Option<Capture> {
+        self.last_capture.take()
+    }
+
+    /// Whether frames bin their output image by luminance, for auto exposure. Like captures,
+    /// only the ray tracing and compute paths have an output image to bin.
+    pub fn set_luminance_histogram(&mut self, enabled: bool) {
+        self.histogram_requested = enabled;
+    }
+
+    /// The latest histogram read back since the last call. Lags rendering by the number of
+    /// frames in flight.
+    pub fn take_luminance_histogram(&mut self) -> Option<LuminanceHistogram> {
+        self.last_histogram.take()
+    }
+
+    /// Whether frames time their render graph passes on the GPU, for diagnostics
+    pub fn set_pass_timing(&mut self, enabled: bool) {
+        self.pass_timing_requested = enabled;
+    }
+
+    /// The GPU time of each pass of the latest frame read back since the last call, in the
+    /// order they ran. Lags rendering by the number of frames in flight.
+    pub fn take_pass_timings(&mut self) -> Option<Vec<PassTiming>> {
+        self.last_pass_timings.take()
+    }
+
+    /// Fences to wait on before recording into `current_frame`'s slot: its own, plus those of
+    /// the most recent frames when fewer than [`MAX_FRAMES_IN_FLIGHT`] may be in flight
+    fn frame_fences(&self, current_frame: u8, frames_in_flight: u32) -> Vec<vk::Fence> {
+        let slots = MAX_FRAMES_IN_FLIGHT as u32;
+        (0..=slots - frames_in_flight)
+            .map(|age| (current_frame as u32 + slots - age) % slots)
+            .map(|slot| self.sync_objects.in_flight_fences[slot as usize])
+            .collect()
+    }
+
+    /// Blocks until the GPU is done with the frames [`Self::draw_frame`] waits on before
+    /// recording into `current_frame`'s slot, so what only those frames read can be rewritten
+    pub fn wait_for_frame(
+        &self,
+        init_state: &InitState,
+        current_frame: u8,
+        frames_in_flight: u32,
+    ) -> Result<(), Box<dyn Error>> {
+        unsafe {
+            init_state
+                .device()
+                .wait_for_fences(
+                    &self.frame_fences(current_frame, frames_in_flight),
+                    true,
+                    u64::MAX,
+                )
+                .map_err(|e| self.device_error(init_state, e))
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[profiling::function]
     pub fn draw_frame(
         &mut self,
         init_state: &InitState,
         swapchain_state: &mut SwapchainState,
-        pipeline_state: &PipelineState,
+        mut path: FramePath,
         buffer_state: &mut BufferState,
-        acceleration_structure_state: &mut AccelerationStructureState,
+        settings: &RendererSettings,
+        hud: &Hud,
         window_size: Vec2,
         camera_gpu: CameraGpu,
+        lights: &[LightGpu],
         current_frame: u8,
-    ) -> VkResult<()> {
+    ) -> Result<(), Box<dyn Error>> {
+        // Minimized: there is nothing to present to, and acquiring would only report the
+        // swapchain out of date again
+        if window_size.x <= 0.0 || window_size.y <= 0.0 {
+            return Ok(());
+        }
         unsafe {
-            self.update_uniform_buffers(buffer_state, camera_gpu, current_frame)?;
+            let lights = &lights[..lights.len().min(MAX_LIGHTS)];
+            let mut camera_gpu =
+                self.accumulation
+                    .advance(camera_gpu, lights, *swapchain_state.render_extent());
+            camera_gpu.light_count = lights.len() as u32;
 
-            init_state.device().wait_for_fences(
-                &[self.sync_objects.in_flight_fences[current_frame as usize]],
-                true,
-                u64::MAX,
+            self.wait_for_frame(
+                init_state,
+                current_frame,
+                settings.clamped_frames_in_flight(),
             )?;
+            // The frame's slot and descriptor sets are no longer read by the GPU
+            self.update_uniform_buffers(buffer_state, camera_gpu, current_frame)?;
+            buffer_state.light_buffers_mut()[current_frame as usize]
+                .write(bytemuck::cast_slice(lights));
+            path.refresh_descriptor_sets(
+                init_state.device(),
+                buffer_state,
+                swapchain_state,
+                current_frame,
+            );
+
+            if settings.picking {
+                self.read_pick(buffer_state, current_frame);
+            }
+            if let Some(extent) = self.pending_captures[current_frame as usize].take() {
+                self.read_capture(init_state, buffer_state, extent, current_frame)?;
+            }
+            if mem::take(&mut self.pending_histograms[current_frame as usize]) {
+                if let Some(histogram_pass) = &mut self.histogram_pass {
+                    self.last_histogram = histogram_pass.read(current_frame);
+                }
+            }
+            if let Some(pass_timer) = &mut self.pass_timer {
+                if let Some(timings) = pass_timer.read(init_state.device(), current_frame) {
+                    self.last_pass_timings = Some(timings);
+                }
+            }
+            if self.pass_timing_requested && !self.pass_timer_created {
+                self.pass_timer = PassTimer::new(init_state)?;
+                self.pass_timer_created = true;
+            }
+            let histogram = matches!(path, FramePath::RayTracing { .. } | FramePath::Compute(_))
+                && self.histogram_requested;
+            if histogram && self.histogram_pass.is_none() {
+                self.histogram_pass = Some(HistogramPass::new(init_state)?);
+            }
+            if let Some(histogram_pass) = self.histogram_pass.as_mut().filter(|_| histogram) {
+                histogram_pass.refresh_descriptor_set(
+                    init_state.device(),
+                    swapchain_state,
+                    current_frame,
+                );
+            }
+
+            let hud_copies = match buffer_state.hud_buffers_mut()[current_frame as usize]
+                .mapped_mut()
+                .as_deref_mut()
+            {
+                Some(staging) => stage_hud(
+                    hud,
+                    staging,
+                    *swapchain_state.extent(),
+                    swapchain_state.image_format(),
+                ),
+                None => Vec::new(),
+            };
 
             let (image_index, _suboptimal) = match swapchain_state.loader().acquire_next_image(
                 swapchain_state.swapchain(),
@@ -63,15 +349,10 @@ impl CommandState {
                 Ok(i) => i,
                 Err(vk::Result::SUBOPTIMAL_KHR) => return Ok(()),
                 Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => {
-                    swapchain_state.recreate_swapchain(
-                        init_state,
-                        buffer_state,
-                        acceleration_structure_state,
-                        window_size,
-                    )?;
+                    path.recreate_swapchain(init_state, swapchain_state, window_size)?;
                     return Ok(());
                 }
-                Err(e) => return Err(e),
+                Err(e) => return Err(self.device_error(init_state, e)),
             };
 
             init_state
@@ -82,237 +363,646 @@ impl CommandState {
                 self.command_buffers[current_frame as usize],
                 vk::CommandBufferResetFlags::empty(),
             )?;
-            self.record_command_buffer(
-                init_state,
-                swapchain_state,
-                pipeline_state,
-                acceleration_structure_state,
-                self.command_buffers[current_frame as usize],
-                image_index,
-                current_frame,
-            )?;
+            let command_buffer = self.command_buffers[current_frame as usize];
+            let capture_extent = *swapchain_state.render_extent();
+            let capture = match &path {
+                FramePath::RayTracing { .. } | FramePath::Compute(_) if self.capture_requested => {
+                    let buffer =
+                        buffer_state.capture_buffer(init_state, current_frame, capture_extent)?;
+                    Some(buffer.handle())
+                }
+                _ => None,
+            };
+            self.capture_requested = false;
+            // Taken for the recording, which borrows the rest of `self`
+            let mut pass_timer = self.pass_timer.take();
+            let mut breadcrumbs = self.breadcrumbs.take();
+            let timing = self.pass_timing_requested;
+            let recorded = match &path {
+                FramePath::RayTracing {
+                    pipeline_state,
+                    acceleration_structure_state,
+                } => self.record_command_buffer(
+                    init_state,
+                    swapchain_state,
+                    pipeline_state,
+                    buffer_state,
+                    acceleration_structure_state,
+                    settings,
+                    &hud_copies,
+                    capture,
+                    histogram,
+                    command_buffer,
+                    image_index,
+                    current_frame,
+                    PassMarkers {
+                        timer: pass_timer.as_mut().filter(|_| timing),
+                        breadcrumbs: breadcrumbs.as_mut(),
+                        current_frame,
+                    },
+                ),
+                FramePath::Hybrid { raster_state, .. } | FramePath::Raster(raster_state) => self
+                    .record_raster_command_buffer(
+                        init_state,
+                        swapchain_state,
+                        raster_state,
+                        buffer_state,
+                        settings,
+                        &camera_gpu,
+                        &hud_copies,
+                        command_buffer,
+                        image_index,
+                        current_frame,
+                        PassMarkers {
+                            timer: pass_timer.as_mut().filter(|_| timing),
+                            breadcrumbs: breadcrumbs.as_mut(),
+                            current_frame,
+                        },
+                    ),
+                FramePath::Compute(compute_state) => self.record_compute_command_buffer(
+                    init_state,
+                    swapchain_state,
+                    compute_state,
+                    buffer_state,
+                    settings,
+                    &hud_copies,
+                    capture,
+                    histogram,
+                    command_buffer,
+                    image_index,
+                    current_frame,
+                    PassMarkers {
+                        timer: pass_timer.as_mut().filter(|_| timing),
+                        breadcrumbs: breadcrumbs.as_mut(),
+                        current_frame,
+                    },
+                ),
+                FramePath::Splash => self.record_splash_command_buffer(
+                    init_state,
+                    swapchain_state,
+                    buffer_state,
+                    &hud_copies,
+                    command_buffer,
+                    image_index,
+                    current_frame,
+                    PassMarkers {
+                        timer: pass_timer.as_mut().filter(|_| timing),
+                        breadcrumbs: breadcrumbs.as_mut(),
+                        current_frame,
+                    },
+                ),
+            };
+            self.pass_timer = pass_timer;
+            self.breadcrumbs = breadcrumbs;
+            recorded?;
+            if capture.is_some() {
+                self.pending_captures[current_frame as usize] = Some(capture_extent);
+            }
+            self.pending_histograms[current_frame as usize] = histogram;
 
             let wait_semaphores =
                 &[self.sync_objects.image_available_semaphores[current_frame as usize]];
             let signal_semaphores =
                 &[self.sync_objects.render_finished_semaphores[current_frame as usize]];
 
-            init_state.device().queue_submit(
-                init_state.queues().graphics().primary_handle().unwrap(),
-                &[vk::SubmitInfo::default()
-                    .wait_semaphores(wait_semaphores)
-                    .wait_dst_stage_mask(&[vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT])
-                    .command_buffers(&[self.command_buffers[current_frame as usize]])
-                    .signal_semaphores(signal_semaphores)],
-                self.sync_objects.in_flight_fences[current_frame as usize],
-            )?;
+            init_state
+                .device()
+                .queue_submit(
+                    init_state.queues().graphics().primary_handle().unwrap(),
+                    &[vk::SubmitInfo::default()
+                        .wait_semaphores(wait_semaphores)
+                        .wait_dst_stage_mask(&[vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT])
+                        .command_buffers(&[self.command_buffers[current_frame as usize]])
+                        .signal_semaphores(signal_semaphores)],
+                    self.sync_objects.in_flight_fences[current_frame as usize],
+                )
+                .map_err(|e| self.device_error(init_state, e))?;
 
-            match swapchain_state.loader().queue_present(
+            let swapchains = [swapchain_state.swapchain()];
+            let image_indices = [image_index];
+            let present_id = swapchain_state
+                .present_waiter_mut()
+                .map(PresentWaiter::next_id);
+            let present_ids = present_id.as_slice();
+            let mut present_id_info = vk::PresentIdKHR::default().present_ids(present_ids);
+            let mut present_info = vk::PresentInfoKHR::default()
+                .wait_semaphores(signal_semaphores)
+                .swapchains(&swapchains)
+                .image_indices(&image_indices);
+            if present_id.is_some() {
+                present_info = present_info.push_next(&mut present_id_info);
+            }
+            let presented = swapchain_state.loader().queue_present(
                 init_state.queues().present().primary_handle().unwrap(),
-                &vk::PresentInfoKHR::default()
-                    .wait_semaphores(signal_semaphores)
-                    .swapchains(&[swapchain_state.swapchain()])
-                    .image_indices(&[image_index]),
-            ) {
+                &present_info,
+            );
+            if let (Ok(_), Some(waiter), Some(id)) =
+                (presented, swapchain_state.present_waiter(), present_id)
+            {
+                waiter.wait_for(swapchains[0], id);
+            }
+            match presented {
                 Ok(_) => (),
                 Err(vk::Result::ERROR_OUT_OF_DATE_KHR) | Err(vk::Result::SUBOPTIMAL_KHR) => {
-                    swapchain_state.recreate_swapchain(
-                        init_state,
-                        buffer_state,
-                        acceleration_structure_state,
-                        window_size,
-                    )?;
+                    path.recreate_swapchain(init_state, swapchain_state, window_size)?;
                 }
-                Err(e) => return Err(e),
+                Err(e) => return Err(self.device_error(init_state, e)),
             };
             Ok(())
         }
     }
 
+    /// `e`, or with the `gpu-breadcrumbs` feature the passes the GPU last reached if `e` is a
+    /// lost device
+    fn device_error(&self, init_state: &InitState, e: vk::Result) -> Box<dyn Error> {
+        match &self.breadcrumbs {
+            Some(breadcrumbs) if e == vk::Result::ERROR_DEVICE_LOST => DeviceLost {
+                breadcrumbs: breadcrumbs.last_reached(init_state),
+            }
+            .into(),
+            _ => e.into(),
+        }
+    }
+
     unsafe fn update_uniform_buffers(
         &mut self,
         buffer_state: &mut BufferState,
         camera_gpu: CameraGpu,
         current_frame: u8,
     ) -> VkResult<()> {
-        buffer_state.uniform_buffers_mut()[current_frame as usize].write(camera_gpu.to_bytes());
-        // let mapped = buffer_state.uniform_buffers()[current_frame as usize]
-        //     .mapped()
-        //     .as_ref()
-        //     .unwrap();
-        // let camera_data: &CameraGpu = bytemuck::from_bytes(mapped);
-        // println!("GPU View Inverse:\n{:?}", camera_data.view_inverse);
-        // println!("GPU Proj Inverse:\n{:?}", camera_data.proj_inverse);
+        buffer_state
+            .uniforms_mut()
+            .write(current_frame, camera_gpu.to_bytes());
         Ok(())
     }
 
+    /// The frame's fence has signaled, so its pick buffer holds a finished result
+    fn read_pick(&mut self, buffer_state: &mut BufferState, current_frame: u8) {
+        let pick_buffer = &mut buffer_state.pick_buffers_mut()[current_frame as usize];
+        let Some(mapped) = pick_buffer.mapped() else {
+            return;
+        };
+        let pick: PickGpu = bytemuck::pod_read_unaligned(&mapped[..mem::size_of::<PickGpu>()]);
+        self.last_pick = pick.hit();
+        pick_buffer.write(bytemuck::bytes_of(&PickGpu::NO_HIT));
+    }
+
+    /// The frame's fence has signaled, so its capture buffer holds the copied output image
+    fn read_capture(
+        &mut self,
+        init_state: &InitState,
+        buffer_state: &mut BufferState,
+        extent: vk::Extent2D,
+        current_frame: u8,
+    ) -> VkResult<()> {
+        let buffer = buffer_state.capture_buffer(init_state, current_frame, extent)?;
+        if let Some(mapped) = buffer.mapped() {
+            let size = extent.width as usize * extent.height as usize * 4;
+            self.last_capture = Some(Capture {
+                width: extent.width,
+                height: extent.height,
+                pixels: mapped[..size].to_vec(),
+            });
+        }
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[profiling::function]
     unsafe fn record_command_buffer(
         &mut self,
         init_state: &InitState,
         swapchain_state: &SwapchainState,
         pipeline_state: &PipelineState,
+        buffer_state: &BufferState,
         acceleration_structure_state: &AccelerationStructureState,
+        settings: &RendererSettings,
+        hud_copies: &[vk::BufferImageCopy],
+        capture: Option<vk::Buffer>,
+        histogram: bool,
         command_buffer: vk::CommandBuffer,
         image_index: u32,
         current_frame: u8,
+        markers: PassMarkers,
     ) -> VkResult<()> {
-        init_state
-            .device()
-            .begin_command_buffer(command_buffer, &vk::CommandBufferBeginInfo::default())?;
+        let device = init_state.device();
+        device.begin_command_buffer(command_buffer, &vk::CommandBufferBeginInfo::default())?;
 
-        // Transition swapchain image from PRESENT_SRC_KHR to TRANSFER_DST_OPTIMAL
-        init_state.device().cmd_pipeline_barrier(
-            command_buffer,
-            vk::PipelineStageFlags::TOP_OF_PIPE,
-            vk::PipelineStageFlags::TRANSFER,
-            vk::DependencyFlags::empty(),
-            &[],
-            &[],
-            &[vk::ImageMemoryBarrier::default()
-                .old_layout(vk::ImageLayout::PRESENT_SRC_KHR)
-                .new_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
-                .src_access_mask(vk::AccessFlags::NONE)
-                .dst_access_mask(vk::AccessFlags::TRANSFER_WRITE)
-                .image(swapchain_state.images()[image_index as usize])
-                .subresource_range(
-                    vk::ImageSubresourceRange::default()
-                        .aspect_mask(vk::ImageAspectFlags::COLOR)
-                        .base_mip_level(0)
-                        .level_count(1)
-                        .base_array_layer(0)
-                        .layer_count(1),
-                )],
+        let render_extent = *swapchain_state.render_extent();
+        let output_image = swapchain_state.output_images()[current_frame as usize];
+        let swapchain_image = swapchain_state.images()[image_index as usize];
+        let pick_buffer = buffer_state.pick_buffers()[current_frame as usize].handle();
+        let ray_tracing = vk::PipelineStageFlags::RAY_TRACING_SHADER_KHR;
+
+        let mut graph = RenderGraph::new();
+        let output = graph.import_image(
+            output_image,
+            vk::ImageAspectFlags::COLOR,
+            ImageState::storage_write(ray_tracing),
+            Some(ImageState::storage_write(ray_tracing)),
+        );
+        // Shared by all frames in flight, so the previous frame's writes must be waited on
+        let accumulation = graph.import_image(
+            swapchain_state.accumulation_image(),
+            vk::ImageAspectFlags::COLOR,
+            ImageState::storage_read_write(ray_tracing),
+            None,
+        );
+        let variance = graph.import_image(
+            swapchain_state.variance_image(),
+            vk::ImageAspectFlags::COLOR,
+            ImageState::storage_read_write(ray_tracing),
+            None,
+        );
+        let swapchain = graph.import_image(
+            swapchain_image,
+            vk::ImageAspectFlags::COLOR,
+            ImageState::new(
+                vk::ImageLayout::PRESENT_SRC_KHR,
+                vk::PipelineStageFlags::TOP_OF_PIPE,
+                vk::AccessFlags::NONE,
+            ),
+            Some(ImageState::PRESENT),
+        );
+        let pick = graph.import_buffer(
+            pick_buffer,
+            GraphBufferState::new(vk::PipelineStageFlags::HOST, vk::AccessFlags::HOST_WRITE),
+            Some(GraphBufferState::new(
+                vk::PipelineStageFlags::HOST,
+                vk::AccessFlags::HOST_READ,
+            )),
         );
 
-        // Ray tracing (output_image already in GENERAL from descriptor setup)
-        init_state.device().cmd_bind_pipeline(
-            command_buffer,
-            vk::PipelineBindPoint::RAY_TRACING_KHR,
-            pipeline_state.pipeline(),
+        graph.add_pass(
+            "trace",
+            |pass| {
+                pass.image(output, ImageState::storage_write(ray_tracing))
+                    .image(accumulation, ImageState::storage_read_write(ray_tracing))
+                    .image(variance, ImageState::storage_read_write(ray_tracing))
+                    .buffer(
+                        pick,
+                        GraphBufferState::new(ray_tracing, vk::AccessFlags::SHADER_WRITE),
+                    );
+            },
+            |command_buffer, _| {
+                device.cmd_bind_pipeline(
+                    command_buffer,
+                    vk::PipelineBindPoint::RAY_TRACING_KHR,
+                    pipeline_state.pipeline(),
+                );
+
+                device.cmd_bind_descriptor_sets(
+                    command_buffer,
+                    vk::PipelineBindPoint::RAY_TRACING_KHR,
+                    pipeline_state.pipeline_layout(),
+                    0,
+                    &[acceleration_structure_state.descriptor_sets()[current_frame as usize]],
+                    &[buffer_state.uniforms().offset(current_frame)],
+                );
+
+                // Only as much as the shaders declare, which may be none at all
+                let push_constants = pipeline_state.reflection().push_constant_ranges();
+                if let Some(range) = push_constants.first() {
+                    let push_constants =
+                        PushConstants::new(settings, pipeline_state.max_recursion_depth());
+                    device.cmd_push_constants(
+                        command_buffer,
+                        pipeline_state.pipeline_layout(),
+                        range.stage_flags,
+                        0,
+                        &bytemuck::bytes_of(&push_constants)[..range.size as usize],
+                    );
+                }
+
+                let shader_binding_table = pipeline_state.shader_binding_table();
+                pipeline_state.ray_tracing_loader().cmd_trace_rays(
+                    command_buffer,
+                    &shader_binding_table.raygen_region,
+                    &shader_binding_table.miss_region,
+                    &shader_binding_table.hit_region,
+                    &shader_binding_table.callable_region,
+                    render_extent.width,
+                    render_extent.height,
+                    1,
+                );
+            },
         );
 
-        init_state.device().cmd_bind_descriptor_sets(
-            command_buffer,
-            vk::PipelineBindPoint::RAY_TRACING_KHR,
-            pipeline_state.pipeline_layout(),
-            0,
-            &[acceleration_structure_state.descriptor_sets()[current_frame as usize]],
-            &[],
+        if let Some(histogram_pass) = self.histogram_pass.as_ref().filter(|_| histogram) {
+            histogram_pass.add_pass(&mut graph, device, output, render_extent, current_frame);
+        }
+        if let Some(capture) = capture {
+            add_capture_pass(&mut graph, device, output, capture, render_extent);
+        }
+        add_blit_and_hud_passes(
+            &mut graph,
+            device,
+            swapchain_state,
+            buffer_state,
+            output,
+            swapchain,
+            hud_copies,
+            image_index,
+            current_frame,
         );
 
-        pipeline_state.ray_tracing_loader().cmd_trace_rays(
+        graph.execute(
+            device,
+            init_state.debug_labels(),
+            &mut self.transient_images[current_frame as usize],
             command_buffer,
-            &pipeline_state.shader_binding_table().raygen_region,
-            &pipeline_state.shader_binding_table().miss_region,
-            &pipeline_state.shader_binding_table().hit_region,
-            &vk::StridedDeviceAddressRegionKHR::default(),
-            swapchain_state.extent().width,
-            swapchain_state.extent().height,
-            1,
+            markers,
+        )?;
+
+        device.end_command_buffer(command_buffer)?;
+        Ok(())
+    }
+
+    /// Marches the frame's output image in a compute shader, then presents it like the ray
+    /// tracing path
+    #[allow(clippy::too_many_arguments)]
+    #[profiling::function]
+    unsafe fn record_compute_command_buffer(
+        &mut self,
+        init_state: &InitState,
+        swapchain_state: &SwapchainState,
+        compute_state: &ComputeState,
+        buffer_state: &BufferState,
+        settings: &RendererSettings,
+        hud_copies: &[vk::BufferImageCopy],
+        capture: Option<vk::Buffer>,
+        histogram: bool,
+        command_buffer: vk::CommandBuffer,
+        image_index: u32,
+        current_frame: u8,
+        markers: PassMarkers,
+    ) -> VkResult<()> {
+        let device = init_state.device();
+        device.begin_command_buffer(command_buffer, &vk::CommandBufferBeginInfo::default())?;
+
+        let compute = vk::PipelineStageFlags::COMPUTE_SHADER;
+
+        let mut graph = RenderGraph::new();
+        let output = graph.import_image(
+            swapchain_state.output_images()[current_frame as usize],
+            vk::ImageAspectFlags::COLOR,
+            ImageState::storage_write(compute),
+            Some(ImageState::storage_write(compute)),
+        );
+        let swapchain = graph.import_image(
+            swapchain_state.images()[image_index as usize],
+            vk::ImageAspectFlags::COLOR,
+            ImageState::new(
+                vk::ImageLayout::PRESENT_SRC_KHR,
+                vk::PipelineStageFlags::TOP_OF_PIPE,
+                vk::AccessFlags::NONE,
+            ),
+            Some(ImageState::PRESENT),
+        );
+
+        compute_state.add_pass(
+            &mut graph,
+            device,
+            buffer_state,
+            output,
+            *swapchain_state.render_extent(),
+            MarchPushConstants::new(
+                compute_state.grid(),
+                compute_state.render_origin(),
+                settings,
+            ),
+            current_frame,
+        );
+        if let Some(histogram_pass) = self.histogram_pass.as_ref().filter(|_| histogram) {
+            histogram_pass.add_pass(
+                &mut graph,
+                device,
+                output,
+                *swapchain_state.render_extent(),
+                current_frame,
+            );
+        }
+        if let Some(capture) = capture {
+            add_capture_pass(
+                &mut graph,
+                device,
+                output,
+                capture,
+                *swapchain_state.render_extent(),
+            );
+        }
+        add_blit_and_hud_passes(
+            &mut graph,
+            device,
+            swapchain_state,
+            buffer_state,
+            output,
+            swapchain,
+            hud_copies,
+            image_index,
+            current_frame,
         );
 
-        // Transition output_image to TRANSFER_SRC_OPTIMAL
-        init_state.device().cmd_pipeline_barrier(
+        graph.execute(
+            device,
+            init_state.debug_labels(),
+            &mut self.transient_images[current_frame as usize],
             command_buffer,
-            vk::PipelineStageFlags::RAY_TRACING_SHADER_KHR,
-            vk::PipelineStageFlags::TRANSFER,
-            vk::DependencyFlags::empty(),
-            &[],
-            &[],
-            &[vk::ImageMemoryBarrier::default()
-                .old_layout(vk::ImageLayout::GENERAL)
-                .new_layout(vk::ImageLayout::TRANSFER_SRC_OPTIMAL)
-                .src_access_mask(vk::AccessFlags::SHADER_WRITE)
-                .dst_access_mask(vk::AccessFlags::TRANSFER_READ)
-                .image(swapchain_state.output_images()[current_frame as usize])
-                .subresource_range(
-                    vk::ImageSubresourceRange::default()
-                        .aspect_mask(vk::ImageAspectFlags::COLOR)
-                        .base_mip_level(0)
-                        .level_count(1)
-                        .base_array_layer(0)
-                        .layer_count(1),
-                )],
+            markers,
+        )?;
+
+        device.end_command_buffer(command_buffer)?;
+        Ok(())
+    }
+
+    /// Draws the scene straight into the swapchain image, through the multisampled color
+    /// image when MSAA is on. Shared by the raster and hybrid paths.
+    #[allow(clippy::too_many_arguments)]
+    #[profiling::function]
+    unsafe fn record_raster_command_buffer(
+        &mut self,
+        init_state: &InitState,
+        swapchain_state: &SwapchainState,
+        raster_state: &RasterState,
+        buffer_state: &BufferState,
+        settings: &RendererSettings,
+        camera_gpu: &CameraGpu,
+        hud_copies: &[vk::BufferImageCopy],
+        command_buffer: vk::CommandBuffer,
+        image_index: u32,
+        current_frame: u8,
+        markers: PassMarkers,
+    ) -> VkResult<()> {
+        let device = init_state.device();
+        device.begin_command_buffer(command_buffer, &vk::CommandBufferBeginInfo::default())?;
+
+        let swapchain_image = swapchain_state.images()[image_index as usize];
+        let swapchain_view = swapchain_state.image_views()[image_index as usize];
+        let hud_buffer = buffer_state.hud_buffers()[current_frame as usize].handle();
+
+        let mut graph = RenderGraph::new();
+        let swapchain = graph.import_image(
+            swapchain_image,
+            vk::ImageAspectFlags::COLOR,
+            ImageState::new(
+                vk::ImageLayout::PRESENT_SRC_KHR,
+                vk::PipelineStageFlags::TOP_OF_PIPE,
+                vk::AccessFlags::NONE,
+            ),
+            Some(ImageState::PRESENT),
+        );
+        // Shared by all frames in flight and cleared every frame, so only the previous frame's
+        // writes have to be waited on
+        let depth = graph.import_image(
+            swapchain_state.depth_image(),
+            swapchain_state.depth_aspect_mask(),
+            ImageState {
+                layout: vk::ImageLayout::UNDEFINED,
+                ..ImageState::DEPTH_ATTACHMENT
+            },
+            None,
+        );
+        let multisampled = swapchain_state
+            .msaa_color_image()
+            .zip(swapchain_state.msaa_color_image_view())
+            .map(|(image, view)| {
+                let handle = graph.import_image(
+                    image,
+                    vk::ImageAspectFlags::COLOR,
+                    ImageState {
+                        layout: vk::ImageLayout::UNDEFINED,
+                        ..ImageState::COLOR_ATTACHMENT
+                    },
+                    None,
+                );
+                (handle, view)
+            });
+
+        let (color, color_view) = multisampled.unwrap_or((swapchain, swapchain_view));
+        let target = RasterTarget {
+            color,
+            color_view,
+            depth,
+            depth_view: swapchain_state.depth_image_view(),
+            resolve: multisampled.map(|_| (swapchain, swapchain_view)),
+            extent: *swapchain_state.extent(),
+        };
+        raster_state.add_pass(
+            &mut graph,
+            device,
+            buffer_state,
+            target,
+            RasterPushConstants::new(camera_gpu, RASTER_SCENE_COLOR, settings),
+            current_frame,
         );
 
-        // Blit from output_image to swapchain image
-        init_state.device().cmd_blit_image(
+        if !hud_copies.is_empty() {
+            graph.add_pass(
+                "hud",
+                |pass| {
+                    pass.image(swapchain, ImageState::TRANSFER_DST);
+                },
+                |command_buffer, _| {
+                    device.cmd_copy_buffer_to_image(
+                        command_buffer,
+                        hud_buffer,
+                        swapchain_image,
+                        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                        hud_copies,
+                    );
+                },
+            );
+        }
+
+        graph.execute(
+            device,
+            init_state.debug_labels(),
+            &mut self.transient_images[current_frame as usize],
             command_buffer,
-            swapchain_state.output_images()[current_frame as usize],
-            vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
-            swapchain_state.images()[image_index as usize],
-            vk::ImageLayout::TRANSFER_DST_OPTIMAL,
-            &[vk::ImageBlit::default()
-                .src_subresource(
-                    vk::ImageSubresourceLayers::default()
-                        .aspect_mask(vk::ImageAspectFlags::COLOR)
-                        .layer_count(1),
-                )
-                .src_offsets([
-                    vk::Offset3D { x: 0, y: 0, z: 0 },
-                    vk::Offset3D {
-                        x: swapchain_state.extent().width as i32,
-                        y: swapchain_state.extent().height as i32,
-                        z: 1,
+            markers,
+        )?;
+
+        device.end_command_buffer(command_buffer)?;
+        Ok(())
+    }
+
+    /// Clears the swapchain image to [`SPLASH_COLOR`] and copies the HUD over it
+    #[allow(clippy::too_many_arguments)]
+    unsafe fn record_splash_command_buffer(
+        &mut self,
+        init_state: &InitState,
+        swapchain_state: &SwapchainState,
+        buffer_state: &BufferState,
+        hud_copies: &[vk::BufferImageCopy],
+        command_buffer: vk::CommandBuffer,
+        image_index: u32,
+        current_frame: u8,
+        markers: PassMarkers,
+    ) -> VkResult<()> {
+        let device = init_state.device();
+        device.begin_command_buffer(command_buffer, &vk::CommandBufferBeginInfo::default())?;
+
+        let swapchain_image = swapchain_state.images()[image_index as usize];
+        let hud_buffer = buffer_state.hud_buffers()[current_frame as usize].handle();
+
+        let mut graph = RenderGraph::new();
+        let swapchain = graph.import_image(
+            swapchain_image,
+            vk::ImageAspectFlags::COLOR,
+            ImageState::new(
+                vk::ImageLayout::UNDEFINED,
+                vk::PipelineStageFlags::TOP_OF_PIPE,
+                vk::AccessFlags::NONE,
+            ),
+            Some(ImageState::PRESENT),
+        );
+        graph.add_pass(
+            "clear",
+            |pass| {
+                pass.image(swapchain, ImageState::TRANSFER_DST);
+            },
+            |command_buffer, _| {
+                device.cmd_clear_color_image(
+                    command_buffer,
+                    swapchain_image,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    &vk::ClearColorValue {
+                        float32: SPLASH_COLOR,
                     },
-                ])
-                .dst_subresource(
-                    vk::ImageSubresourceLayers::default()
+                    &[vk::ImageSubresourceRange::default()
                         .aspect_mask(vk::ImageAspectFlags::COLOR)
-                        .layer_count(1),
-                )
-                .dst_offsets([
-                    vk::Offset3D { x: 0, y: 0, z: 0 },
-                    vk::Offset3D {
-                        x: swapchain_state.extent().width as i32,
-                        y: swapchain_state.extent().height as i32,
-                        z: 1,
-                    },
-                ])],
-            vk::Filter::NEAREST,
+                        .level_count(1)
+                        .layer_count(1)],
+                );
+            },
         );
+        if !hud_copies.is_empty() {
+            graph.add_pass(
+                "hud",
+                |pass| {
+                    pass.image(swapchain, ImageState::TRANSFER_DST);
+                },
+                |command_buffer, _| {
+                    device.cmd_copy_buffer_to_image(
+                        command_buffer,
+                        hud_buffer,
+                        swapchain_image,
+                        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                        hud_copies,
+                    );
+                },
+            );
+        }
 
-        // Transition swapchain to PRESENT_SRC_KHR and output_image back to GENERAL
-        init_state.device().cmd_pipeline_barrier(
+        graph.execute(
+            device,
+            init_state.debug_labels(),
+            &mut self.transient_images[current_frame as usize],
             command_buffer,
-            vk::PipelineStageFlags::TRANSFER,
-            vk::PipelineStageFlags::RAY_TRACING_SHADER_KHR,
-            vk::DependencyFlags::empty(),
-            &[],
-            &[],
-            &[
-                vk::ImageMemoryBarrier::default()
-                    .old_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
-                    .new_layout(vk::ImageLayout::PRESENT_SRC_KHR)
-                    .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
-                    .dst_access_mask(vk::AccessFlags::NONE)
-                    .image(swapchain_state.images()[image_index as usize])
-                    .subresource_range(
-                        vk::ImageSubresourceRange::default()
-                            .aspect_mask(vk::ImageAspectFlags::COLOR)
-                            .base_mip_level(0)
-                            .level_count(1)
-                            .base_array_layer(0)
-                            .layer_count(1),
-                    ),
-                vk::ImageMemoryBarrier::default()
-                    .old_layout(vk::ImageLayout::TRANSFER_SRC_OPTIMAL)
-                    .new_layout(vk::ImageLayout::GENERAL)
-                    .src_access_mask(vk::AccessFlags::TRANSFER_READ)
-                    .dst_access_mask(vk::AccessFlags::SHADER_WRITE)
-                    .image(swapchain_state.output_images()[current_frame as usize])
-                    .subresource_range(
-                        vk::ImageSubresourceRange::default()
-                            .aspect_mask(vk::ImageAspectFlags::COLOR)
-                            .base_mip_level(0)
-                            .level_count(1)
-                            .base_array_layer(0)
-                            .layer_count(1),
-                    ),
-            ],
-        );
+            markers,
+        )?;
 
-        init_state.device().end_command_buffer(command_buffer)?;
+        device.end_command_buffer(command_buffer)?;
         Ok(())
     }
 
@@ -328,7 +1018,19 @@ impl CommandState {
         )
     }
 
-    pub fn cleanup(&self, init_state: &InitState) {
+    pub fn cleanup(&mut self, init_state: &InitState) {
+        for transient_images in &mut self.transient_images {
+            transient_images.cleanup(init_state.device());
+        }
+        if let Some(breadcrumbs) = &mut self.breadcrumbs {
+            breadcrumbs.cleanup(init_state.device());
+        }
+        if let Some(pass_timer) = &mut self.pass_timer {
+            pass_timer.cleanup(init_state.device());
+        }
+        if let Some(histogram_pass) = &mut self.histogram_pass {
+            histogram_pass.cleanup(init_state);
+        }
         unsafe {
             for i in 0..MAX_FRAMES_IN_FLIGHT as usize {
                 init_state
@@ -345,8 +1047,137 @@ impl CommandState {
     }
 }
 
+/// Scales the frame's output image onto the swapchain image and copies the HUD over it
+#[allow(clippy::too_many_arguments)]
+fn add_blit_and_hud_passes<'g>(
+    graph: &mut RenderGraph<'g>,
+    device: &'g ash::Device,
+    swapchain_state: &SwapchainState,
+    buffer_state: &BufferState,
+    output: ImageHandle,
+    swapchain: ImageHandle,
+    hud_copies: &'g [vk::BufferImageCopy],
+    image_index: u32,
+    current_frame: u8,
+) {
+    let extent = *swapchain_state.extent();
+    let render_extent = *swapchain_state.render_extent();
+    let output_image = swapchain_state.output_images()[current_frame as usize];
+    let swapchain_image = swapchain_state.images()[image_index as usize];
+    let hud_buffer = buffer_state.hud_buffers()[current_frame as usize].handle();
+
+    graph.add_pass(
+        "blit",
+        |pass| {
+            pass.image(output, ImageState::TRANSFER_SRC)
+                .image(swapchain, ImageState::TRANSFER_DST);
+        },
+        move |command_buffer, _| unsafe {
+            let subresource = vk::ImageSubresourceLayers::default()
+                .aspect_mask(vk::ImageAspectFlags::COLOR)
+                .layer_count(1);
+            let offsets = |extent: vk::Extent2D| {
+                [
+                    vk::Offset3D { x: 0, y: 0, z: 0 },
+                    vk::Offset3D {
+                        x: extent.width as i32,
+                        y: extent.height as i32,
+                        z: 1,
+                    },
+                ]
+            };
+            let filter = if render_extent == extent {
+                vk::Filter::NEAREST
+            } else {
+                vk::Filter::LINEAR
+            };
+
+            device.cmd_blit_image(
+                command_buffer,
+                output_image,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                swapchain_image,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                &[vk::ImageBlit::default()
+                    .src_subresource(subresource)
+                    .src_offsets(offsets(render_extent))
+                    .dst_subresource(subresource)
+                    .dst_offsets(offsets(extent))],
+                filter,
+            );
+        },
+    );
+
+    if !hud_copies.is_empty() {
+        graph.add_pass(
+            "hud",
+            |pass| {
+                pass.image(swapchain, ImageState::TRANSFER_DST);
+            },
+            move |command_buffer, _| unsafe {
+                device.cmd_copy_buffer_to_image(
+                    command_buffer,
+                    hud_buffer,
+                    swapchain_image,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    hud_copies,
+                );
+            },
+        );
+    }
+}
+
 const MAX_FRAMES_IN_FLIGHT: u8 = 2;
 
+/// The raster shaders don't read materials yet, so the scene is drawn in one base color
+const RASTER_SCENE_COLOR: [f32; 4] = [0.8, 0.8, 0.8, 1.0];
+
+/// Background of [`FramePath::Splash`] frames
+pub const SPLASH_COLOR: [f32; 4] = [0.02, 0.02, 0.03, 1.0];
+
+/// Tracks how many frames have been rendered from an unchanged view and lighting
+#[derive(Default)]
+struct Accumulation {
+    frame: u32,
+    frames: u32,
+    previous_camera: Option<CameraGpu>,
+    previous_lights: Vec<LightGpu>,
+    extent: vk::Extent2D,
+}
+
+impl Accumulation {
+    fn advance(
+        &mut self,
+        mut camera_gpu: CameraGpu,
+        lights: &[LightGpu],
+        extent: vk::Extent2D,
+    ) -> CameraGpu {
+        let view_changed = self
+            .previous_camera
+            .is_none_or(|previous| !previous.same_view(&camera_gpu));
+        // Moved or toggled lights would otherwise leave old lighting smeared into the average
+        let lights_changed = lights != self.previous_lights;
+        let sunlight_changed = self
+            .previous_camera
+            .is_some_and(|previous| previous.sunlight != camera_gpu.sunlight);
+        if view_changed || lights_changed || sunlight_changed || extent != self.extent {
+            self.frames = 0;
+        }
+        if lights_changed {
+            self.previous_lights = lights.to_vec();
+        }
+
+        camera_gpu.frame = self.frame;
+        camera_gpu.accumulated_frames = self.frames;
+
+        self.frame = self.frame.wrapping_add(1);
+        self.frames = self.frames.saturating_add(1);
+        self.previous_camera = Some(camera_gpu);
+        self.extent = extent;
+        camera_gpu
+    }
+}
+
 struct SyncObjects {
     image_available_semaphores: Vec<vk::Semaphore>,
     render_finished_semaphores: Vec<vk::Semaphore>,
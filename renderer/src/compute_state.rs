@@ -0,0 +1,450 @@
+use std::{error::Error, mem, path::Path};
+
+use ash::{prelude::VkResult, vk};
+use bevy_ecs::system::Resource;
+use bytemuck::{Pod, Zeroable};
+use data::{
+    camera::CameraGpu,
+    material::MaterialGpu,
+    prelude::{IVec3, UVec3},
+    voxel::{Voxel, VoxelId},
+    voxel_block::{VoxelBlock, VoxelBlockData},
+};
+
+use crate::{
+    buffer::Buffer,
+    buffer_state::BufferState,
+    init_state::InitState,
+    pipeline_state::PipelineState,
+    render_graph::{ImageHandle, ImageState, RenderGraph},
+    settings::RendererSettings,
+    swapchain_state::{FrameDescriptorVersions, SwapchainState},
+    MAX_FRAMES_IN_FLIGHT,
+};
+
+/// Matches the `push_constant` block of march.comp
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+pub struct MarchPushConstants {
+    grid_origin: [i32; 4],
+    grid_size: [u32; 4],
+    fog_start: f32,
+    fog_end: f32,
+    shadows: u32,
+    _padding: u32,
+}
+
+impl MarchPushConstants {
+    /// Places the grid relative to `render_origin`, the world position the camera's
+    /// translation is relative to
+    pub fn new(grid: &VoxelGrid, render_origin: IVec3, settings: &RendererSettings) -> Self {
+        let (fog_start, fog_end) = settings.view_distance.fog_range();
+        Self {
+            grid_origin: (grid.origin - render_origin).extend(0).to_array(),
+            grid_size: grid.size.extend(0).to_array(),
+            fog_start,
+            fog_end,
+            shadows: settings.shadows as u32,
+            _padding: 0,
+        }
+    }
+}
+
+/// A dense box of voxels in world space, one [`VoxelId`] per voxel in the same order as
+/// [`VoxelBlock`]'s indexing: x fastest, then z, then y
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VoxelGrid {
+    origin: IVec3,
+    size: UVec3,
+    voxels: Vec<u32>,
+}
+
+impl VoxelGrid {
+    /// All air
+    pub fn new(origin: IVec3, size: UVec3) -> Self {
+        Self {
+            origin,
+            size,
+            voxels: vec![Voxel::Air as u32; size.element_product() as usize],
+        }
+    }
+
+    /// World position of the minimum corner
+    pub const fn origin(&self) -> IVec3 {
+        self.origin
+    }
+
+    pub const fn size(&self) -> UVec3 {
+        self.size
+    }
+
+    pub fn voxels(&self) -> &[u32] {
+        &self.voxels
+    }
+
+    fn index(&self, position: IVec3) -> Option<usize> {
+        let local = position - self.origin;
+        if local.cmplt(IVec3::ZERO).any() || local.as_uvec3().cmpge(self.size).any() {
+            return None;
+        }
+        let local = local.as_uvec3();
+        Some((local.x + local.z * self.size.x + local.y * self.size.x * self.size.z) as usize)
+    }
+
+    pub fn get(&self, position: IVec3) -> Option<VoxelId> {
+        self.index(position)
+            .map(|index| self.voxels[index] as VoxelId)
+    }
+
+    /// Copies the part of the chunk at `chunk` (in chunk coordinates) that overlaps the grid
+    pub fn insert_chunk(&mut self, chunk: IVec3, data: &VoxelBlockData) {
+        let width = VoxelBlock::WIDTH as i32;
+        let chunk_origin = chunk * width;
+        for (i, voxel) in data.iter().enumerate() {
+            let i = i as i32;
+            let offset = IVec3::new(i % width, i / (width * width), i / width % width);
+            if let Some(index) = self.index(chunk_origin + offset) {
+                self.voxels[index] = *voxel as u32;
+            }
+        }
+    }
+}
+
+/// Ray marches a [`VoxelGrid`] in a compute shader, for devices with no ray tracing hardware.
+/// Writes the same output image as the ray tracing pipeline, which is then blitted to the
+/// swapchain the same way.
+#[derive(Resource)]
+pub struct ComputeState<'a> {
+    descriptor_set_layout: vk::DescriptorSetLayout,
+    descriptor_pool: vk::DescriptorPool,
+    /// One per frame in flight, each writing that frame's output image
+    descriptor_sets: Vec<vk::DescriptorSet>,
+    descriptor_versions: FrameDescriptorVersions,
+    pipeline_layout: vk::PipelineLayout,
+    pipeline: vk::Pipeline,
+    grid: VoxelGrid,
+    grid_buffer: Buffer<'a>,
+    /// World position the camera's translation is relative to
+    render_origin: IVec3,
+}
+
+impl<'a> ComputeState<'a> {
+    /// Matches `local_size_x` and `local_size_y` of march.comp
+    const WORKGROUP_SIZE: u32 = 8;
+    /// Per binding of march.comp: the output image, camera, materials and voxel grid
+    const DESCRIPTOR_TYPES: [vk::DescriptorType; 4] = [
+        vk::DescriptorType::STORAGE_IMAGE,
+        vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC,
+        vk::DescriptorType::STORAGE_BUFFER,
+        vk::DescriptorType::STORAGE_BUFFER,
+    ];
+
+    pub const fn pipeline_layout(&self) -> vk::PipelineLayout {
+        self.pipeline_layout
+    }
+
+    pub const fn pipeline(&self) -> vk::Pipeline {
+        self.pipeline
+    }
+
+    pub const fn grid(&self) -> &VoxelGrid {
+        &self.grid
+    }
+
+    pub const fn render_origin(&self) -> IVec3 {
+        self.render_origin
+    }
+
+    /// Follows a floating origin, which the grid is placed relative to when marched
+    pub fn set_render_origin(&mut self, render_origin: IVec3) {
+        self.render_origin = render_origin;
+    }
+
+    pub fn new(
+        init_state: &InitState,
+        swapchain_state: &SwapchainState,
+        buffer_state: &BufferState,
+        grid: VoxelGrid,
+    ) -> Result<Self, Box<dyn Error>> {
+        unsafe {
+            let device = init_state.device();
+            let descriptor_set_layout = Self::create_descriptor_set_layout(device)?;
+            let descriptor_pool = Self::create_descriptor_pool(device)?;
+            let descriptor_sets = device.allocate_descriptor_sets(
+                &vk::DescriptorSetAllocateInfo::default()
+                    .descriptor_pool(descriptor_pool)
+                    .set_layouts(&[descriptor_set_layout; MAX_FRAMES_IN_FLIGHT as usize]),
+            )?;
+            let (pipeline_layout, pipeline) =
+                Self::create_pipeline(device, init_state.pipeline_cache(), descriptor_set_layout)?;
+
+            let grid_buffer = Buffer::create_from_bytes_with_staging(
+                init_state.instance(),
+                device,
+                init_state.physical_device(),
+                init_state.queues().command_fence().unwrap(),
+                init_state.queues().transfer(),
+                bytemuck::cast_slice(grid.voxels()),
+                vk::BufferUsageFlags::STORAGE_BUFFER,
+            )?;
+
+            let mut compute_state = Self {
+                descriptor_set_layout,
+                descriptor_pool,
+                descriptor_sets,
+                descriptor_versions: FrameDescriptorVersions::default(),
+                pipeline_layout,
+                pipeline,
+                grid,
+                grid_buffer,
+                render_origin: IVec3::ZERO,
+            };
+            for frame in 0..MAX_FRAMES_IN_FLIGHT {
+                compute_state.refresh_descriptor_set(device, buffer_state, swapchain_state, frame);
+            }
+            Ok(compute_state)
+        }
+    }
+
+    /// Points the frame's descriptor set at the swapchain's current output image if it was
+    /// recreated since the set was written. Only call once the frame's fence has signaled.
+    pub fn refresh_descriptor_set(
+        &mut self,
+        device: &ash::Device,
+        buffer_state: &BufferState,
+        swapchain_state: &SwapchainState,
+        current_frame: u8,
+    ) {
+        if !self
+            .descriptor_versions
+            .update(current_frame, swapchain_state.generation())
+        {
+            return;
+        }
+        let frame = current_frame as usize;
+        let descriptor_set = self.descriptor_sets[frame];
+        unsafe {
+            device.update_descriptor_sets(
+                &[
+                    vk::WriteDescriptorSet::default()
+                        .dst_set(descriptor_set)
+                        .dst_binding(0)
+                        .descriptor_type(vk::DescriptorType::STORAGE_IMAGE)
+                        .descriptor_count(1)
+                        .image_info(&[vk::DescriptorImageInfo::default()
+                            .image_view(swapchain_state.output_image_views()[frame])
+                            .image_layout(vk::ImageLayout::GENERAL)]),
+                    vk::WriteDescriptorSet::default()
+                        .dst_set(descriptor_set)
+                        .dst_binding(1)
+                        .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC)
+                        .descriptor_count(1)
+                        // Offset by the frame's slot when bound
+                        .buffer_info(&[vk::DescriptorBufferInfo::default()
+                            .buffer(buffer_state.uniforms().buffer().handle())
+                            .offset(0)
+                            .range(mem::size_of::<CameraGpu>() as u64)]),
+                    vk::WriteDescriptorSet::default()
+                        .dst_set(descriptor_set)
+                        .dst_binding(2)
+                        .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+                        .descriptor_count(1)
+                        .buffer_info(&[vk::DescriptorBufferInfo::default()
+                            .buffer(buffer_state.material_buffer().handle())
+                            .offset(0)
+                            .range((mem::size_of::<MaterialGpu>() * Voxel::ALL.len()) as u64)]),
+                    vk::WriteDescriptorSet::default()
+                        .dst_set(descriptor_set)
+                        .dst_binding(3)
+                        .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+                        .descriptor_count(1)
+                        .buffer_info(&[vk::DescriptorBufferInfo::default()
+                            .buffer(self.grid_buffer.handle())
+                            .offset(0)
+                            .range(vk::WHOLE_SIZE)]),
+                ],
+                &[],
+            );
+        }
+    }
+
+    /// Adds a pass marching a ray per pixel of `extent` into `output`, the frame's output image
+    #[allow(clippy::too_many_arguments)]
+    pub fn add_pass<'g>(
+        &'g self,
+        graph: &mut RenderGraph<'g>,
+        device: &'g ash::Device,
+        buffer_state: &'g BufferState,
+        output: ImageHandle,
+        extent: vk::Extent2D,
+        push_constants: MarchPushConstants,
+        current_frame: u8,
+    ) {
+        let compute = vk::PipelineStageFlags::COMPUTE_SHADER;
+        graph.add_pass(
+            "march",
+            |pass| {
+                pass.image(output, ImageState::storage_write(compute));
+            },
+            move |command_buffer, _| unsafe {
+                device.cmd_bind_pipeline(
+                    command_buffer,
+                    vk::PipelineBindPoint::COMPUTE,
+                    self.pipeline,
+                );
+                device.cmd_bind_descriptor_sets(
+                    command_buffer,
+                    vk::PipelineBindPoint::COMPUTE,
+                    self.pipeline_layout,
+                    0,
+                    &[self.descriptor_sets[current_frame as usize]],
+                    &[buffer_state.uniforms().offset(current_frame)],
+                );
+                device.cmd_push_constants(
+                    command_buffer,
+                    self.pipeline_layout,
+                    vk::ShaderStageFlags::COMPUTE,
+                    0,
+                    bytemuck::bytes_of(&push_constants),
+                );
+                device.cmd_dispatch(
+                    command_buffer,
+                    extent.width.div_ceil(Self::WORKGROUP_SIZE),
+                    extent.height.div_ceil(Self::WORKGROUP_SIZE),
+                    1,
+                );
+            },
+        );
+    }
+
+    unsafe fn create_descriptor_set_layout(
+        device: &ash::Device,
+    ) -> VkResult<vk::DescriptorSetLayout> {
+        let bindings: Vec<_> = (0..)
+            .zip(Self::DESCRIPTOR_TYPES)
+            .map(|(binding, descriptor_type)| {
+                vk::DescriptorSetLayoutBinding::default()
+                    .binding(binding)
+                    .descriptor_type(descriptor_type)
+                    .descriptor_count(1)
+                    .stage_flags(vk::ShaderStageFlags::COMPUTE)
+            })
+            .collect();
+        device.create_descriptor_set_layout(
+            &vk::DescriptorSetLayoutCreateInfo::default().bindings(&bindings),
+            None,
+        )
+    }
+
+    unsafe fn create_descriptor_pool(device: &ash::Device) -> VkResult<vk::DescriptorPool> {
+        device.create_descriptor_pool(
+            &vk::DescriptorPoolCreateInfo::default()
+                .pool_sizes(&[
+                    vk::DescriptorPoolSize::default()
+                        .descriptor_count(MAX_FRAMES_IN_FLIGHT as u32)
+                        .ty(vk::DescriptorType::STORAGE_IMAGE),
+                    vk::DescriptorPoolSize::default()
+                        .descriptor_count(MAX_FRAMES_IN_FLIGHT as u32)
+                        .ty(vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC),
+                    vk::DescriptorPoolSize::default()
+                        .descriptor_count(2 * MAX_FRAMES_IN_FLIGHT as u32)
+                        .ty(vk::DescriptorType::STORAGE_BUFFER),
+                ])
+                .max_sets(MAX_FRAMES_IN_FLIGHT as u32),
+            None,
+        )
+    }
+
+    unsafe fn create_pipeline(
+        device: &ash::Device,
+        pipeline_cache: vk::PipelineCache,
+        descriptor_set_layout: vk::DescriptorSetLayout,
+    ) -> Result<(vk::PipelineLayout, vk::Pipeline), Box<dyn Error>> {
+        let shader = PipelineState::read_shader_code(Path::new("./bin/march.comp.spv"))?;
+        let module = PipelineState::create_shader_module(device, &shader)?;
+
+        let pipeline_layout = device.create_pipeline_layout(
+            &vk::PipelineLayoutCreateInfo::default()
+                .set_layouts(&[descriptor_set_layout])
+                .push_constant_ranges(&[vk::PushConstantRange::default()
+                    .stage_flags(vk::ShaderStageFlags::COMPUTE)
+                    .offset(0)
+                    .size(mem::size_of::<MarchPushConstants>() as u32)]),
+            None,
+        )?;
+
+        let pipelines = device
+            .create_compute_pipelines(
+                pipeline_cache,
+                &[vk::ComputePipelineCreateInfo::default()
+                    .stage(
+                        vk::PipelineShaderStageCreateInfo::default()
+                            .stage(vk::ShaderStageFlags::COMPUTE)
+                            .module(module)
+                            .name(c"main"),
+                    )
+                    .layout(pipeline_layout)],
+                None,
+            )
+            .map_err(|(_, e)| e)?;
+
+        device.destroy_shader_module(module, None);
+        Ok((pipeline_layout, pipelines[0]))
+    }
+
+    pub fn cleanup(&mut self, init_state: &InitState) {
+        unsafe {
+            let device = init_state.device();
+            self.grid_buffer.cleanup(device);
+            device.destroy_pipeline(self.pipeline, None);
+            device.destroy_pipeline_layout(self.pipeline_layout, None);
+            // Destroying the pool frees its sets
+            device.destroy_descriptor_pool(self.descriptor_pool, None);
+            device.destroy_descriptor_set_layout(self.descriptor_set_layout, None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reflection::{PipelineReflection, ShaderReflection};
+
+    #[test]
+    fn chunks_land_at_their_world_position() {
+        let width = VoxelBlock::WIDTH as i32;
+        let mut data: VoxelBlockData = Box::new([Voxel::Air; VoxelBlock::VOLUME as usize]);
+        // x = 1, y = 2, z = 3 within the chunk
+        data[1 + 3 * width as usize + 2 * (width * width) as usize] = Voxel::Stone;
+
+        let mut grid = VoxelGrid::new(IVec3::new(-width, 0, 0), UVec3::new(2, 1, 1) * width as u32);
+        grid.insert_chunk(IVec3::new(-1, 0, 0), &data);
+        // Out of bounds, so ignored
+        grid.insert_chunk(IVec3::new(5, 0, 0), &data);
+
+        assert_eq!(
+            grid.get(IVec3::new(1 - width, 2, 3)),
+            Some(Voxel::Stone as VoxelId)
+        );
+        assert_eq!(grid.get(IVec3::new(1, 2, 3)), Some(Voxel::Air as VoxelId));
+        assert_eq!(grid.get(IVec3::new(width, 0, 0)), None);
+        assert_eq!(grid.voxels().iter().filter(|voxel| **voxel != 0).count(), 1);
+    }
+
+    #[test]
+    fn march_shader_matches_the_pipeline_layout() {
+        let code = PipelineState::read_shader_code(Path::new("../bin/march.comp.spv")).unwrap();
+        let march = ShaderReflection::new(&code).unwrap();
+        assert_eq!(march.stage, vk::ShaderStageFlags::COMPUTE);
+        let reflection = PipelineReflection::new([&march])
+            .unwrap()
+            .with_dynamic_offset(0, 1);
+        let layout: Vec<_> = reflection
+            .bindings(0)
+            .map(|binding| (binding.binding, binding.descriptor_type))
+            .collect();
+        let expected: Vec<_> = (0..).zip(ComputeState::DESCRIPTOR_TYPES).collect();
+        assert_eq!(layout, expected);
+        assert!(march.push_constant_size as usize <= mem::size_of::<MarchPushConstants>());
+    }
+}
@@ -0,0 +1,294 @@
+use std::{error::Error, mem, path::Path};
+
+use ash::{prelude::VkResult, vk};
+use data::exposure::LuminanceHistogram;
+
+use crate::{
+    buffer::Buffer,
+    init_state::InitState,
+    pipeline_state::PipelineState,
+    render_graph::{BufferState as GraphBufferState, ImageHandle, ImageState, RenderGraph},
+    swapchain_state::{FrameDescriptorVersions, SwapchainState},
+    MAX_FRAMES_IN_FLIGHT,
+};
+
+/// Bins the frame's output image by luminance in a compute shader, into a host-visible buffer
+/// per frame in flight that is read once the frame's fence signals. Feeds auto exposure.
+pub struct HistogramPass<'a> {
+    descriptor_set_layout: vk::DescriptorSetLayout,
+    descriptor_pool: vk::DescriptorPool,
+    /// One per frame in flight, each reading that frame's output image
+    descriptor_sets: Vec<vk::DescriptorSet>,
+    descriptor_versions: FrameDescriptorVersions,
+    pipeline_layout: vk::PipelineLayout,
+    pipeline: vk::Pipeline,
+    buffers: Vec<Buffer<'a>>,
+}
+
+impl HistogramPass<'_> {
+    /// Matches `local_size_x` and `local_size_y` of histogram.comp
+    const WORKGROUP_SIZE: u32 = 16;
+    const BUFFER_SIZE: usize = LuminanceHistogram::BINS * mem::size_of::<u32>();
+
+    pub fn new(init_state: &InitState) -> Result<Self, Box<dyn Error>> {
+        unsafe {
+            let device = init_state.device();
+            let descriptor_set_layout = Self::create_descriptor_set_layout(device)?;
+            let descriptor_pool = Self::create_descriptor_pool(device)?;
+            let descriptor_sets = device.allocate_descriptor_sets(
+                &vk::DescriptorSetAllocateInfo::default()
+                    .descriptor_pool(descriptor_pool)
+                    .set_layouts(&[descriptor_set_layout; MAX_FRAMES_IN_FLIGHT as usize]),
+            )?;
+            let (pipeline_layout, pipeline) =
+                Self::create_pipeline(device, init_state.pipeline_cache(), descriptor_set_layout)?;
+
+            let buffers = (0..MAX_FRAMES_IN_FLIGHT)
+                .map(|_| {
+                    let mut buffer = Buffer::create(
+                        init_state.instance(),
+                        device,
+                        init_state.physical_device(),
+                        Self::BUFFER_SIZE as u64,
+                        vk::BufferUsageFlags::STORAGE_BUFFER,
+                        vk::MemoryPropertyFlags::HOST_VISIBLE
+                            | vk::MemoryPropertyFlags::HOST_COHERENT,
+                    )?;
+                    buffer.map_memory(device, 0, vk::MemoryMapFlags::empty())?;
+                    buffer.write(&[0; Self::BUFFER_SIZE]);
+                    Ok(buffer)
+                })
+                .collect::<VkResult<_>>()?;
+
+            Ok(Self {
+                descriptor_set_layout,
+                descriptor_pool,
+                descriptor_sets,
+                descriptor_versions: FrameDescriptorVersions::default(),
+                pipeline_layout,
+                pipeline,
+                buffers,
+            })
+        }
+    }
+
+    /// Points the frame's descriptor set at the swapchain's current output image if it was
+    /// recreated since the set was written. Only call once the frame's fence has signaled.
+    pub fn refresh_descriptor_set(
+        &mut self,
+        device: &ash::Device,
+        swapchain_state: &SwapchainState,
+        current_frame: u8,
+    ) {
+        if !self
+            .descriptor_versions
+            .update(current_frame, swapchain_state.generation())
+        {
+            return;
+        }
+        let frame = current_frame as usize;
+        unsafe {
+            device.update_descriptor_sets(
+                &[
+                    vk::WriteDescriptorSet::default()
+                        .dst_set(self.descriptor_sets[frame])
+                        .dst_binding(0)
+                        .descriptor_type(vk::DescriptorType::STORAGE_IMAGE)
+                        .descriptor_count(1)
+                        .image_info(&[vk::DescriptorImageInfo::default()
+                            .image_view(swapchain_state.output_image_views()[frame])
+                            .image_layout(vk::ImageLayout::GENERAL)]),
+                    vk::WriteDescriptorSet::default()
+                        .dst_set(self.descriptor_sets[frame])
+                        .dst_binding(1)
+                        .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+                        .descriptor_count(1)
+                        .buffer_info(&[vk::DescriptorBufferInfo::default()
+                            .buffer(self.buffers[frame].handle())
+                            .offset(0)
+                            .range(vk::WHOLE_SIZE)]),
+                ],
+                &[],
+            );
+        }
+    }
+
+    /// The histogram recorded into the frame, zeroing the buffer for the next one. The frame's
+    /// fence must have signaled.
+    pub fn read(&mut self, current_frame: u8) -> Option<LuminanceHistogram> {
+        let buffer = &mut self.buffers[current_frame as usize];
+        let mapped = buffer.mapped().as_deref()?;
+        let mut histogram = LuminanceHistogram::default();
+        for (bin, bytes) in histogram
+            .bins
+            .iter_mut()
+            .zip(mapped[..Self::BUFFER_SIZE].chunks_exact(mem::size_of::<u32>()))
+        {
+            *bin = bytemuck::pod_read_unaligned(bytes);
+        }
+        buffer.write(&[0; Self::BUFFER_SIZE]);
+        Some(histogram)
+    }
+
+    /// Adds a pass binning `output`, the frame's output image, once it has been rendered
+    pub fn add_pass<'g>(
+        &'g self,
+        graph: &mut RenderGraph<'g>,
+        device: &'g ash::Device,
+        output: ImageHandle,
+        extent: vk::Extent2D,
+        current_frame: u8,
+    ) {
+        let compute = vk::PipelineStageFlags::COMPUTE_SHADER;
+        let histogram = graph.import_buffer(
+            self.buffers[current_frame as usize].handle(),
+            GraphBufferState::new(vk::PipelineStageFlags::HOST, vk::AccessFlags::HOST_WRITE),
+            Some(GraphBufferState::new(
+                vk::PipelineStageFlags::HOST,
+                vk::AccessFlags::HOST_READ,
+            )),
+        );
+        graph.add_pass(
+            "histogram",
+            |pass| {
+                pass.image(output, ImageState::storage_read(compute))
+                    .buffer(
+                        histogram,
+                        GraphBufferState::new(
+                            compute,
+                            vk::AccessFlags::SHADER_READ | vk::AccessFlags::SHADER_WRITE,
+                        ),
+                    );
+            },
+            move |command_buffer, _| unsafe {
+                device.cmd_bind_pipeline(
+                    command_buffer,
+                    vk::PipelineBindPoint::COMPUTE,
+                    self.pipeline,
+                );
+                device.cmd_bind_descriptor_sets(
+                    command_buffer,
+                    vk::PipelineBindPoint::COMPUTE,
+                    self.pipeline_layout,
+                    0,
+                    &[self.descriptor_sets[current_frame as usize]],
+                    &[],
+                );
+                device.cmd_dispatch(
+                    command_buffer,
+                    extent.width.div_ceil(Self::WORKGROUP_SIZE),
+                    extent.height.div_ceil(Self::WORKGROUP_SIZE),
+                    1,
+                );
+            },
+        );
+    }
+
+    unsafe fn create_descriptor_set_layout(
+        device: &ash::Device,
+    ) -> VkResult<vk::DescriptorSetLayout> {
+        let binding = |binding: u32, descriptor_type: vk::DescriptorType| {
+            vk::DescriptorSetLayoutBinding::default()
+                .binding(binding)
+                .descriptor_type(descriptor_type)
+                .descriptor_count(1)
+                .stage_flags(vk::ShaderStageFlags::COMPUTE)
+        };
+        device.create_descriptor_set_layout(
+            &vk::DescriptorSetLayoutCreateInfo::default().bindings(&[
+                binding(0, vk::DescriptorType::STORAGE_IMAGE),
+                binding(1, vk::DescriptorType::STORAGE_BUFFER),
+            ]),
+            None,
+        )
+    }
+
+    unsafe fn create_descriptor_pool(device: &ash::Device) -> VkResult<vk::DescriptorPool> {
+        device.create_descriptor_pool(
+            &vk::DescriptorPoolCreateInfo::default()
+                .pool_sizes(&[
+                    vk::DescriptorPoolSize::default()
+                        .descriptor_count(MAX_FRAMES_IN_FLIGHT as u32)
+                        .ty(vk::DescriptorType::STORAGE_IMAGE),
+                    vk::DescriptorPoolSize::default()
+                        .descriptor_count(MAX_FRAMES_IN_FLIGHT as u32)
+                        .ty(vk::DescriptorType::STORAGE_BUFFER),
+                ])
+                .max_sets(MAX_FRAMES_IN_FLIGHT as u32),
+            None,
+        )
+    }
+
+    unsafe fn create_pipeline(
+        device: &ash::Device,
+        pipeline_cache: vk::PipelineCache,
+        descriptor_set_layout: vk::DescriptorSetLayout,
+    ) -> Result<(vk::PipelineLayout, vk::Pipeline), Box<dyn Error>> {
+        let shader = PipelineState::read_shader_code(Path::new("./bin/histogram.comp.spv"))?;
+        let module = PipelineState::create_shader_module(device, &shader)?;
+
+        let pipeline_layout = device.create_pipeline_layout(
+            &vk::PipelineLayoutCreateInfo::default().set_layouts(&[descriptor_set_layout]),
+            None,
+        )?;
+
+        let pipelines = device
+            .create_compute_pipelines(
+                pipeline_cache,
+                &[vk::ComputePipelineCreateInfo::default()
+                    .stage(
+                        vk::PipelineShaderStageCreateInfo::default()
+                            .stage(vk::ShaderStageFlags::COMPUTE)
+                            .module(module)
+                            .name(c"main"),
+                    )
+                    .layout(pipeline_layout)],
+                None,
+            )
+            .map_err(|(_, e)| e)?;
+
+        device.destroy_shader_module(module, None);
+        Ok((pipeline_layout, pipelines[0]))
+    }
+
+    pub fn cleanup(&mut self, init_state: &InitState) {
+        unsafe {
+            let device = init_state.device();
+            for buffer in &mut self.buffers {
+                buffer.cleanup(device);
+            }
+            device.destroy_pipeline(self.pipeline, None);
+            device.destroy_pipeline_layout(self.pipeline_layout, None);
+            // Destroying the pool frees its sets
+            device.destroy_descriptor_pool(self.descriptor_pool, None);
+            device.destroy_descriptor_set_layout(self.descriptor_set_layout, None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reflection::ShaderReflection;
+
+    #[test]
+    fn compiled_shader_reads_the_output_into_the_histogram_buffer() {
+        let code = PipelineState::read_shader_code(Path::new("../bin/histogram.comp.spv")).unwrap();
+        let histogram = ShaderReflection::new(&code).unwrap();
+        assert_eq!(histogram.stage, vk::ShaderStageFlags::COMPUTE);
+        let bindings: Vec<_> = histogram
+            .bindings
+            .iter()
+            .map(|binding| (binding.set, binding.binding, binding.descriptor_type))
+            .collect();
+        assert_eq!(
+            bindings,
+            [
+                (0, 0, vk::DescriptorType::STORAGE_IMAGE),
+                (0, 1, vk::DescriptorType::STORAGE_BUFFER),
+            ]
+        );
+        // The pipeline layout has no push constant range
+        assert_eq!(histogram.push_constant_size, 0);
+    }
+}
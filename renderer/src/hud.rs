@@ -0,0 +1,177 @@
+use ash::vk;
+use bevy_ecs::system::Resource;
+
+use crate::hud_font::{glyph, GLYPH_ADVANCE, GLYPH_HEIGHT, GLYPH_WIDTH};
+
+/// Screen-space overlay copied onto the swapchain image after the traced frame is blitted.
+/// Rebuilt by the app every frame.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct Hud {
+    rects: Vec<HudRect>,
+}
+
+impl Hud {
+    pub fn clear(&mut self) {
+        self.rects.clear();
+    }
+
+    /// Later rects are drawn over earlier ones
+    pub fn push(&mut self, rect: HudRect) {
+        self.rects.push(rect);
+    }
+
+    pub fn rects(&self) -> &[HudRect] {
+        &self.rects
+    }
+
+    /// Draws one line of `text` in the built-in 5x7 font with its top left corner at `(x, y)`,
+    /// each font pixel `scale` pixels wide. Each row of a glyph is drawn as runs of rects.
+    pub fn push_text(&mut self, x: i32, y: i32, scale: u32, text: &str, color: [u8; 4]) {
+        for (i, c) in text.chars().enumerate() {
+            let left = x + (i as u32 * GLYPH_ADVANCE * scale) as i32;
+            for (row, bits) in glyph(c).into_iter().enumerate() {
+                let top = y + (row as u32 * scale) as i32;
+                let mut column = 0;
+                while column < GLYPH_WIDTH {
+                    let lit = |column: u32| bits >> (GLYPH_WIDTH - 1 - column) & 1 == 1;
+                    if !lit(column) {
+                        column += 1;
+                        continue;
+                    }
+                    let start = column;
+                    while column < GLYPH_WIDTH && lit(column) {
+                        column += 1;
+                    }
+                    self.push(HudRect::new(
+                        left + (start * scale) as i32,
+                        top,
+                        (column - start) * scale,
+                        scale,
+                        color,
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Width and height in pixels of `text` drawn by [`Self::push_text`]
+    pub fn text_size(text: &str, scale: u32) -> (u32, u32) {
+        let chars = text.chars().count() as u32;
+        (
+            (chars * GLYPH_ADVANCE).saturating_sub(1) * scale,
+            GLYPH_HEIGHT * scale,
+        )
+    }
+}
+
+/// An opaque rectangle in pixels, with the origin at the top left of the window
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HudRect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    /// sRGB RGBA8
+    pub color: [u8; 4],
+}
+
+impl HudRect {
+    pub const fn new(x: i32, y: i32, width: u32, height: u32, color: [u8; 4]) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+            color,
+        }
+    }
+
+    /// Centered on `(center_x, center_y)`
+    pub const fn centered(
+        center_x: i32,
+        center_y: i32,
+        width: u32,
+        height: u32,
+        color: [u8; 4],
+    ) -> Self {
+        Self::new(
+            center_x - width as i32 / 2,
+            center_y - height as i32 / 2,
+            width,
+            height,
+            color,
+        )
+    }
+
+    /// The part of the rect inside `extent` as an offset and size
+    fn clip(&self, extent: vk::Extent2D) -> Option<(vk::Offset2D, vk::Extent2D)> {
+        let x0 = self.x.max(0);
+        let y0 = self.y.max(0);
+        let x1 = (self.x + self.width as i32).min(extent.width as i32);
+        let y1 = (self.y + self.height as i32).min(extent.height as i32);
+        (x1 > x0 && y1 > y0).then_some((
+            vk::Offset2D { x: x0, y: y0 },
+            vk::Extent2D {
+                width: (x1 - x0) as u32,
+                height: (y1 - y0) as u32,
+            },
+        ))
+    }
+}
+
+/// Size of each frame's HUD staging buffer, enough for the full map at its largest
+pub(crate) const HUD_BUFFER_SIZE: usize = 1 << 23;
+
+/// Fills `staging` with the texels of every visible rect and returns the copies that place
+/// them on the swapchain image. Rects that no longer fit in the buffer are dropped.
+pub(crate) fn stage_hud(
+    hud: &Hud,
+    staging: &mut [u8],
+    extent: vk::Extent2D,
+    format: vk::Format,
+) -> Vec<vk::BufferImageCopy> {
+    let bgra = matches!(
+        format,
+        vk::Format::B8G8R8A8_UNORM | vk::Format::B8G8R8A8_SRGB
+    );
+
+    let mut offset = 0;
+    let mut copies = Vec::with_capacity(hud.rects().len());
+    for rect in hud.rects() {
+        let Some((image_offset, image_extent)) = rect.clip(extent) else {
+            continue;
+        };
+        let size = (image_extent.width * image_extent.height * 4) as usize;
+        if offset + size > staging.len() {
+            break;
+        }
+
+        let [r, g, b, a] = rect.color;
+        let texel = if bgra { [b, g, r, a] } else { [r, g, b, a] };
+        for chunk in staging[offset..offset + size].chunks_exact_mut(4) {
+            chunk.copy_from_slice(&texel);
+        }
+
+        copies.push(
+            vk::BufferImageCopy::default()
+                .buffer_offset(offset as vk::DeviceSize)
+                .image_subresource(
+                    vk::ImageSubresourceLayers::default()
+                        .aspect_mask(vk::ImageAspectFlags::COLOR)
+                        .layer_count(1),
+                )
+                .image_offset(vk::Offset3D {
+                    x: image_offset.x,
+                    y: image_offset.y,
+                    z: 0,
+                })
+                .image_extent(vk::Extent3D {
+                    width: image_extent.width,
+                    height: image_extent.height,
+                    depth: 1,
+                }),
+        );
+        offset += size;
+    }
+    copies
+}
@@ -2,7 +2,7 @@ use std::{
     borrow::Cow,
     collections::HashSet,
     error::Error,
-    ffi::{c_void, CStr, CString},
+    ffi::{CStr, CString},
     os::raw,
 };
 
@@ -14,6 +14,15 @@ use ash::{
 };
 use bevy_ecs::system::Resource;
 use raw_window_handle::{RawDisplayHandle, RawWindowHandle};
+use thiserror::Error;
+
+use crate::capabilities::{AdapterCapabilities, RenderPath};
+
+#[derive(Debug, Error)]
+pub enum InitError {
+    #[error("no Vulkan 1.3 device with dynamic rendering, multi-draw indirect and anisotropic filtering can present to this window")]
+    NoSuitableAdapter,
+}
 
 #[derive(Resource)]
 pub struct InitState {
@@ -21,11 +30,16 @@ pub struct InitState {
     instance: ash::Instance,
     debug_utils_loader: debug_utils::Instance,
     debug_messenger: vk::DebugUtilsMessengerEXT,
+    /// `None` when the instance doesn't offer `VK_EXT_debug_utils`
+    debug_labels: Option<debug_utils::Device>,
     surface: vk::SurfaceKHR,
     surface_loader: surface::Instance,
     physical_device: vk::PhysicalDevice,
+    capabilities: AdapterCapabilities,
+    render_path: RenderPath,
     device: ash::Device,
     queues: Queues,
+    pipeline_cache: vk::PipelineCache,
 }
 
 impl InitState {
@@ -33,16 +47,12 @@ impl InitState {
     const ENGINE_VERSION: u32 = 0;
     const API_VERSION: u32 = vk::make_api_version(1, 4, 0, 0);
 
-    const LAYER_NAMES: &[&CStr] = &[c"VK_LAYER_KHRONOS_validation"];
-    const DEVICE_EXTENSION_NAMES: &[&CStr] = &[
-        khr::swapchain::NAME,
-        khr::ray_tracing_pipeline::NAME,
-        khr::acceleration_structure::NAME,
-        khr::deferred_host_operations::NAME,
-        khr::buffer_device_address::NAME,
-        #[cfg(any(target_os = "macos", target_os = "ios"))]
-        ash::khr::portability_subset::NAME,
-    ];
+    /// Only with the `validation` feature, which also installs the debug messenger
+    const LAYER_NAMES: &[&CStr] = if cfg!(feature = "validation") {
+        &[c"VK_LAYER_KHRONOS_validation"]
+    } else {
+        &[]
+    };
 
     pub fn instance(&self) -> &ash::Instance {
         &self.instance
@@ -68,30 +78,94 @@ impl InitState {
         &self.queues
     }
 
+    /// Shared by every pipeline the renderer creates, so pipelines compiled on an earlier run
+    /// can be reused, see [`Self::pipeline_cache_data`]
+    pub const fn pipeline_cache(&self) -> vk::PipelineCache {
+        self.pipeline_cache
+    }
+
+    /// Contents of the pipeline cache, to be passed back to [`Self::new`] on the next run
+    pub fn pipeline_cache_data(&self) -> VkResult<Vec<u8>> {
+        unsafe { self.device.get_pipeline_cache_data(self.pipeline_cache) }
+    }
+
+    /// Names command buffer regions, e.g. render graph passes, for frame debuggers like
+    /// RenderDoc and Nsight
+    pub const fn debug_labels(&self) -> Option<&debug_utils::Device> {
+        self.debug_labels.as_ref()
+    }
+
+    pub const fn capabilities(&self) -> &AdapterCapabilities {
+        &self.capabilities
+    }
+
+    /// The most capable path the picked device supports
+    pub const fn render_path(&self) -> RenderPath {
+        self.render_path
+    }
+
+    /// Uses the most capable render path the best device supports, capped at `preferred_path`.
+    /// `pipeline_cache_data` comes from [`Self::pipeline_cache_data`] on an earlier run; the
+    /// driver ignores it if it was written by another device or driver version.
     pub fn new(
         app_name: &'static str,
         app_version: u32,
         display_handle: RawDisplayHandle,
         window_handle: RawWindowHandle,
+        preferred_path: Option<RenderPath>,
+        pipeline_cache_data: &[u8],
     ) -> Result<Self, Box<dyn Error>> {
         unsafe {
             let entry = ash::Entry::load()?;
-            let instance = Self::create_instance(&entry, app_name, app_version, display_handle)?;
+            let debug_utils_available = entry
+                .enumerate_instance_extension_properties(None)?
+                .iter()
+                .any(|extension| extension.extension_name_as_c_str() == Ok(debug_utils::NAME));
+            let instance = Self::create_instance(
+                &entry,
+                app_name,
+                app_version,
+                display_handle,
+                debug_utils_available,
+            )?;
 
             let debug_utils_loader = debug_utils::Instance::new(&entry, &instance);
-            let debug_messenger = Self::create_debug_messenger(&debug_utils_loader)?;
+            let debug_messenger = if cfg!(feature = "validation") {
+                Self::create_debug_messenger(&debug_utils_loader)?
+            } else {
+                vk::DebugUtilsMessengerEXT::null()
+            };
 
             let surface_loader = surface::Instance::new(&entry, &instance);
             let surface = Self::create_surface(&entry, &instance, display_handle, window_handle)?;
 
-            println!("Before physical device");
-            let (physical_device, mut queues) =
+            let (physical_device, mut queues, capabilities) =
                 Self::pick_physical_device(&instance, &surface_loader, surface)?;
-            println!("After physical device");
+            let render_path = capabilities
+                .render_path(preferred_path)
+                .ok_or(InitError::NoSuitableAdapter)?;
+            if !capabilities.supports(RenderPath::RayTracing) {
+                println!(
+                    "Ray tracing unavailable (missing {}), using the {render_path:?} path",
+                    capabilities.missing_for_ray_tracing().join(", ")
+                );
+            }
 
-            let device = Self::create_logical_device(&instance, physical_device, &queues)?;
+            let device = Self::create_logical_device(
+                &instance,
+                physical_device,
+                &queues,
+                &capabilities,
+                render_path,
+            )?;
+            let debug_labels =
+                debug_utils_available.then(|| debug_utils::Device::new(&instance, &device));
             Self::initialize_queues(&device, &mut queues)?;
             queues.initialize_fence(&device)?;
+            let pipeline_cache = device.create_pipeline_cache(
+                &vk::PipelineCacheCreateInfo::default().initial_data(pipeline_cache_data),
+                None,
+            )?;
             println!("Queue indices: {:?}", queues.indices());
 
             Ok(Self {
@@ -99,11 +173,15 @@ impl InitState {
                 instance,
                 debug_utils_loader,
                 debug_messenger,
+                debug_labels,
                 surface_loader,
                 surface,
                 physical_device,
+                capabilities,
+                render_path,
                 device,
                 queues,
+                pipeline_cache,
             })
         }
     }
@@ -113,15 +191,19 @@ impl InitState {
         Ok(())
     }
 
+    /// Enables `VK_EXT_debug_utils` for validation or, if it's available, debug labels
     unsafe fn create_instance(
         entry: &ash::Entry,
         app_name: &str,
         app_version: u32,
         display_handle: RawDisplayHandle,
+        debug_utils_available: bool,
     ) -> Result<ash::Instance, Box<dyn Error>> {
         let mut extension_names =
             ash_window::enumerate_required_extensions(display_handle)?.to_vec();
-        extension_names.push(debug_utils::NAME.as_ptr());
+        if cfg!(feature = "validation") || debug_utils_available {
+            extension_names.push(debug_utils::NAME.as_ptr());
+        }
         #[cfg(any(target_os = "macos", target_os = "ios"))]
         {
             extension_names.push(ash::khr::portability_enumeration::NAME.as_ptr());
@@ -183,125 +265,150 @@ impl InitState {
         ash_window::create_surface(entry, instance, display_handle, window_handle, None)
     }
 
+    /// Prefers the device with the most capable [`RenderPath`], then discrete over integrated
+    /// GPUs
     unsafe fn pick_physical_device(
         instance: &ash::Instance,
         surface_loader: &surface::Instance,
         surface: vk::SurfaceKHR,
-    ) -> Result<(vk::PhysicalDevice, Queues), Box<dyn Error>> {
-        instance
-            .enumerate_physical_devices()?
-            .iter()
-            .find_map(|&physical_device| {
-                let indices =
-                    Self::device_is_suitable(physical_device, instance, surface_loader, surface)
-                        .ok()?;
-                indices.map(|indices| (physical_device, indices))
-            })
-            .ok_or(Box::new(vk::Result::ERROR_UNKNOWN))
-    }
-
-    unsafe fn check_device_extension_support(
-        instance: &ash::Instance,
-        physical_device: vk::PhysicalDevice,
-    ) -> VkResult<HashSet<String>> {
-        let available_extensions =
-            instance.enumerate_device_extension_properties(physical_device)?;
-        let required_extensions: HashSet<_> = Self::DEVICE_EXTENSION_NAMES
-            .iter()
-            .map(|e| e.to_string_lossy().into_owned())
-            .collect();
-
-        let mut missing_extensions = required_extensions.clone();
-        for ext in available_extensions.iter() {
-            if let Ok(ext_name) = ext.extension_name_as_c_str() {
-                missing_extensions.remove(&ext_name.to_string_lossy().into_owned());
-            }
+    ) -> Result<(vk::PhysicalDevice, Queues, AdapterCapabilities), Box<dyn Error>> {
+        let mut candidates = Vec::new();
+        for physical_device in instance.enumerate_physical_devices()? {
+            let Some((queues, capabilities)) =
+                Self::device_is_suitable(physical_device, instance, surface_loader, surface)?
+            else {
+                continue;
+            };
+            let Some(render_path) = capabilities.render_path(None) else {
+                continue;
+            };
+            let properties = instance.get_physical_device_properties(physical_device);
+            let discrete = properties.device_type == vk::PhysicalDeviceType::DISCRETE_GPU;
+            candidates.push((
+                (render_path, discrete),
+                physical_device,
+                queues,
+                capabilities,
+            ));
         }
 
-        println!("Required extensions: {required_extensions:?}");
-        println!("Missing extensions: {missing_extensions:?}");
-        Ok(missing_extensions)
+        candidates
+            .into_iter()
+            .max_by_key(|(rank, ..)| *rank)
+            .map(|(_, physical_device, queues, capabilities)| {
+                (physical_device, queues, capabilities)
+            })
+            .ok_or_else(|| InitError::NoSuitableAdapter.into())
     }
 
-    /// Returns `Some(Queue)` if the device is suitable
+    /// Returns the device's queues and optional features if it can present to `surface`
     unsafe fn device_is_suitable(
         physical_device: vk::PhysicalDevice,
         instance: &ash::Instance,
         surface_loader: &surface::Instance,
         surface: vk::SurfaceKHR,
-    ) -> VkResult<Option<Queues>> {
+    ) -> VkResult<Option<(Queues, AdapterCapabilities)>> {
         let queues =
             Queues::new_with_family_indices(instance, physical_device, surface_loader, surface)?;
-        let missing_extensions = Self::check_device_extension_support(instance, physical_device)?;
-        let extensions_supported = missing_extensions.is_empty();
+        let capabilities = AdapterCapabilities::query(instance, physical_device)?;
 
-        let swapchain_adequate = {
-            let swapchain_support =
-                SwapchainSupportDetails::new(physical_device, surface_loader, surface)?;
-            !swapchain_support.formats.is_empty() && !swapchain_support.present_modes.is_empty()
-        };
-        let supported_features = instance.get_physical_device_features(physical_device);
+        let has_swapchain = instance
+            .enumerate_device_extension_properties(physical_device)?
+            .iter()
+            .any(|extension| extension.extension_name_as_c_str() == Ok(khr::swapchain::NAME));
+        if !has_swapchain {
+            return Ok(None);
+        }
 
-        if extensions_supported && swapchain_adequate && supported_features.sampler_anisotropy != 0
-        {
-            Ok(Some(queues))
-        } else {
-            Ok(None)
+        let swapchain_support =
+            SwapchainSupportDetails::new(physical_device, surface_loader, surface)?;
+        if swapchain_support.formats.is_empty() || swapchain_support.present_modes.is_empty() {
+            return Ok(None);
         }
+
+        Ok(Some((queues, capabilities)))
     }
 
     unsafe fn create_logical_device(
         instance: &ash::Instance,
         physical_device: vk::PhysicalDevice,
         queues: &Queues,
+        capabilities: &AdapterCapabilities,
+        render_path: RenderPath,
     ) -> VkResult<ash::Device> {
+        let ray_tracing = render_path.uses_acceleration_structures();
+
         let mut vulkan11_features = vk::PhysicalDeviceVulkan11Features::default()
-            .storage_buffer16_bit_access(true)
-            .uniform_and_storage_buffer16_bit_access(true);
+            .storage_buffer16_bit_access(capabilities.storage_16bit)
+            .uniform_and_storage_buffer16_bit_access(capabilities.storage_16bit);
+
+        let mut vulkan12_features = vk::PhysicalDeviceVulkan12Features::default()
+            .buffer_device_address(capabilities.buffer_device_address)
+            .descriptor_indexing(capabilities.descriptor_indexing);
+
+        // The raster fallback renders without render passes or framebuffers
+        let mut vulkan13_features =
+            vk::PhysicalDeviceVulkan13Features::default().dynamic_rendering(true);
+
+        let device_extension_names = capabilities
+            .device_extensions(render_path)
+            .iter()
+            .map(|name| name.as_ptr())
+            .collect::<Vec<_>>();
+        // Unique queue family indices
+        let queue_create_infos = queues
+            .indices()
+            .iter()
+            .collect::<HashSet<_>>()
+            .iter()
+            .map(|&&index| {
+                vk::DeviceQueueCreateInfo::default()
+                    .queue_family_index(index)
+                    .queue_priorities(&[1.0])
+            })
+            .collect::<Vec<_>>();
+        let enabled_features = vk::PhysicalDeviceFeatures::default()
+            .sampler_anisotropy(true)
+            // Lets one indirect call draw every chunk mesh
+            .multi_draw_indirect(true)
+            // Instance buffer device addresses are read as `uint64_t`
+            .shader_int64(capabilities.shader_int64);
 
-        let mut buffer_device_address_features =
-            vk::PhysicalDeviceBufferDeviceAddressFeatures::default().buffer_device_address(true); // Already present, keep this
         let mut ray_tracing_pipeline_features =
             vk::PhysicalDeviceRayTracingPipelineFeaturesKHR::default().ray_tracing_pipeline(true);
         let mut acceleration_structure_features =
             vk::PhysicalDeviceAccelerationStructureFeaturesKHR::default()
                 .acceleration_structure(true);
+        let mut ray_query_features =
+            vk::PhysicalDeviceRayQueryFeaturesKHR::default().ray_query(true);
+        let mut present_id_features =
+            vk::PhysicalDevicePresentIdFeaturesKHR::default().present_id(true);
+        let mut present_wait_features =
+            vk::PhysicalDevicePresentWaitFeaturesKHR::default().present_wait(true);
+
+        // Extension feature structs may only be chained when their extension is enabled
+        let mut create_info = vk::DeviceCreateInfo::default()
+            .queue_create_infos(&queue_create_infos)
+            .enabled_extension_names(&device_extension_names)
+            .enabled_features(&enabled_features)
+            .push_next(&mut vulkan11_features)
+            .push_next(&mut vulkan12_features)
+            .push_next(&mut vulkan13_features);
+        if ray_tracing {
+            create_info = create_info
+                .push_next(&mut ray_tracing_pipeline_features)
+                .push_next(&mut acceleration_structure_features);
+        }
+        if capabilities.ray_query {
+            create_info = create_info.push_next(&mut ray_query_features);
+        }
+        if capabilities.present_wait {
+            create_info = create_info
+                .push_next(&mut present_id_features)
+                .push_next(&mut present_wait_features);
+        }
 
-        // Chain the feature structs
-        vulkan11_features.p_next = &mut buffer_device_address_features as *mut _ as *mut c_void;
-        buffer_device_address_features.p_next =
-            &mut ray_tracing_pipeline_features as *mut _ as *mut c_void;
-        ray_tracing_pipeline_features.p_next =
-            &mut acceleration_structure_features as *mut _ as *mut c_void;
-
-        let device = instance.create_device(
-            physical_device,
-            &vk::DeviceCreateInfo::default()
-                .queue_create_infos(
-                    // Unique queue family indices
-                    &queues
-                        .indices()
-                        .iter()
-                        .collect::<HashSet<_>>()
-                        .iter()
-                        .map(|&&index| {
-                            vk::DeviceQueueCreateInfo::default()
-                                .queue_family_index(index)
-                                .queue_priorities(&[1.0])
-                        })
-                        .collect::<Vec<_>>(),
-                )
-                .enabled_extension_names(
-                    // Raw pointer extension names
-                    &Self::DEVICE_EXTENSION_NAMES
-                        .iter()
-                        .map(|x| x.as_ptr())
-                        .collect::<Vec<_>>(),
-                )
-                .push_next(&mut vulkan11_features)
-                .enabled_features(&vk::PhysicalDeviceFeatures::default().sampler_anisotropy(true)),
-            None,
-        )?;
+        let device = instance.create_device(physical_device, &create_info, None)?;
         Ok(device)
     }
 
@@ -356,10 +463,14 @@ impl Drop for InitState {
                     .destroy_command_pool(command_pool.unwrap(), None);
             }
 
+            self.device
+                .destroy_pipeline_cache(self.pipeline_cache, None);
             self.device.destroy_device(None);
             self.surface_loader.destroy_surface(self.surface, None);
-            self.debug_utils_loader
-                .destroy_debug_utils_messenger(self.debug_messenger, None);
+            if self.debug_messenger != vk::DebugUtilsMessengerEXT::null() {
+                self.debug_utils_loader
+                    .destroy_debug_utils_messenger(self.debug_messenger, None);
+            }
             self.instance.destroy_instance(None);
         }
     }
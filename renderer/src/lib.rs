@@ -3,17 +3,40 @@ use std::mem;
 use bevy_ecs::system::Resource;
 use bytemuck::{Pod, Zeroable};
 use data::camera::CameraGpu;
-
-mod buffer;
+use settings::RendererSettings;
 
 pub mod acceleration_structure_state;
+pub mod blue_noise;
+pub mod breadcrumbs;
+pub mod buffer;
 pub mod buffer_state;
+pub mod capabilities;
+pub mod capture;
 pub mod command_state;
+pub mod compute_state;
+pub mod histogram;
+pub mod hud;
+pub mod hud_font;
 pub mod init_state;
+pub mod pass_timer;
+pub mod picking;
 pub mod pipeline_state;
+pub mod present_wait;
+
+pub mod raster_state;
+pub mod reflection;
+pub mod render_graph;
+pub mod renderer;
+pub mod settings;
+pub mod shader_binding_table;
+pub mod specialization;
 pub mod swapchain_state;
+pub mod texture;
+pub mod tracking;
+pub mod transient_images;
+pub mod uniform_ring;
 
-const MAX_FRAMES_IN_FLIGHT: u8 = 2;
+pub const MAX_FRAMES_IN_FLIGHT: u8 = 2;
 
 const UNIFORM_BUFFER_SIZE: usize = mem::size_of::<CameraGpu>();
 
@@ -141,6 +164,50 @@ struct Vertex {
     pub color: [f32; 3],
 }
 
+/// Matches the `push_constant` block shared by the ray tracing shaders
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+struct PushConstants {
+    reflection_roughness_cutoff: f32,
+    max_reflection_depth: u32,
+    global_illumination: u32,
+    picking: u32,
+    shadows: u32,
+    ambient_occlusion: u32,
+    fog_start: f32,
+    fog_end: f32,
+    adaptive_sampling: u32,
+    adaptive_sampling_threshold: f32,
+    show_sampling_mask: u32,
+    debug_view: u32,
+}
+
+impl PushConstants {
+    /// Secondary rays are disabled when the device cannot recurse past the primary hit
+    fn new(settings: &RendererSettings, max_recursion_depth: u32) -> Self {
+        let secondary_rays = max_recursion_depth > 1;
+        let (fog_start, fog_end) = settings.view_distance.fog_range();
+        Self {
+            reflection_roughness_cutoff: settings.reflection_quality.roughness_cutoff(),
+            max_reflection_depth: if secondary_rays {
+                settings.reflection_quality.max_bounces()
+            } else {
+                0
+            },
+            global_illumination: (secondary_rays && settings.global_illumination) as u32,
+            picking: settings.picking as u32,
+            shadows: (secondary_rays && settings.shadows) as u32,
+            ambient_occlusion: (secondary_rays && settings.ambient_occlusion) as u32,
+            fog_start,
+            fog_end,
+            adaptive_sampling: settings.adaptive_sampling as u32,
+            adaptive_sampling_threshold: settings.adaptive_sampling_threshold.max(0.0),
+            show_sampling_mask: settings.show_sampling_mask as u32,
+            debug_view: settings.debug_view as u32,
+        }
+    }
+}
+
 #[derive(Resource, Default)]
 pub struct CurrentFrame(pub u8);
 
@@ -0,0 +1,153 @@
+use std::time::Duration;
+
+use ash::{prelude::VkResult, vk};
+
+use crate::{init_state::InitState, MAX_FRAMES_IN_FLIGHT};
+
+/// GPU time one render graph pass took
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PassTiming {
+    pub name: &'static str,
+    pub duration: Duration,
+}
+
+/// Times the passes of each frame with timestamp queries: one at the start of the frame and one
+/// after every pass, in a query pool per frame in flight that is read once the frame's fence
+/// signals
+pub struct PassTimer {
+    query_pools: Vec<vk::QueryPool>,
+    /// Passes recorded into each frame in flight, in order
+    recorded: Vec<Vec<&'static str>>,
+    nanos_per_tick: f64,
+    /// Bits of a timestamp the queue actually writes
+    valid_mask: u64,
+}
+
+impl PassTimer {
+    /// Passes past this are left untimed
+    pub const MAX_PASSES: usize = 32;
+    const QUERY_COUNT: u32 = Self::MAX_PASSES as u32 + 1;
+
+    /// `None` if the graphics queue can't write timestamps
+    pub fn new(init_state: &InitState) -> VkResult<Option<Self>> {
+        unsafe {
+            let instance = init_state.instance();
+            let physical_device = init_state.physical_device();
+            let limits = instance
+                .get_physical_device_properties(physical_device)
+                .limits;
+            let valid_bits = instance
+                .get_physical_device_queue_family_properties(physical_device)
+                .get(init_state.queues().graphics().family_index() as usize)
+                .map_or(0, |family| family.timestamp_valid_bits);
+            if valid_bits == 0 || limits.timestamp_period <= 0.0 {
+                return Ok(None);
+            }
+
+            let device = init_state.device();
+            let query_pools = (0..MAX_FRAMES_IN_FLIGHT)
+                .map(|_| {
+                    device.create_query_pool(
+                        &vk::QueryPoolCreateInfo::default()
+                            .query_type(vk::QueryType::TIMESTAMP)
+                            .query_count(Self::QUERY_COUNT),
+                        None,
+                    )
+                })
+                .collect::<VkResult<_>>()?;
+
+            Ok(Some(Self {
+                query_pools,
+                recorded: vec![Vec::new(); MAX_FRAMES_IN_FLIGHT as usize],
+                nanos_per_tick: limits.timestamp_period as f64,
+                valid_mask: u64::MAX >> (64 - valid_bits.min(64)),
+            }))
+        }
+    }
+
+    /// Resets the frame's queries and writes the starting timestamp. Must be recorded outside
+    /// of a render pass, before the frame's first pass.
+    pub fn begin(
+        &mut self,
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        current_frame: u8,
+    ) {
+        let pool = self.query_pools[current_frame as usize];
+        self.recorded[current_frame as usize].clear();
+        unsafe {
+            device.cmd_reset_query_pool(command_buffer, pool, 0, Self::QUERY_COUNT);
+            device.cmd_write_timestamp(
+                command_buffer,
+                vk::PipelineStageFlags::TOP_OF_PIPE,
+                pool,
+                0,
+            );
+        }
+    }
+
+    /// Writes the timestamp ending the pass called `name`
+    pub fn end_pass(
+        &mut self,
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        current_frame: u8,
+        name: &'static str,
+    ) {
+        let recorded = &mut self.recorded[current_frame as usize];
+        if recorded.len() >= Self::MAX_PASSES {
+            return;
+        }
+        recorded.push(name);
+        unsafe {
+            device.cmd_write_timestamp(
+                command_buffer,
+                vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+                self.query_pools[current_frame as usize],
+                recorded.len() as u32,
+            );
+        }
+    }
+
+    /// How long each pass recorded into the frame took, in order; `None` if nothing was
+    /// recorded or the results aren't available. The frame's fence must have signaled.
+    pub fn read(&mut self, device: &ash::Device, current_frame: u8) -> Option<Vec<PassTiming>> {
+        let recorded = std::mem::take(&mut self.recorded[current_frame as usize]);
+        if recorded.is_empty() {
+            return None;
+        }
+        let mut timestamps = vec![0u64; recorded.len() + 1];
+        unsafe {
+            device
+                .get_query_pool_results(
+                    self.query_pools[current_frame as usize],
+                    0,
+                    &mut timestamps,
+                    vk::QueryResultFlags::TYPE_64,
+                )
+                .ok()?;
+        }
+        Some(
+            recorded
+                .into_iter()
+                .zip(timestamps.windows(2))
+                .map(|(name, pair)| {
+                    let ticks = (pair[1] & self.valid_mask).wrapping_sub(pair[0] & self.valid_mask)
+                        & self.valid_mask;
+                    PassTiming {
+                        name,
+                        duration: Duration::from_nanos((ticks as f64 * self.nanos_per_tick) as u64),
+                    }
+                })
+                .collect(),
+        )
+    }
+
+    pub fn cleanup(&mut self, device: &ash::Device) {
+        unsafe {
+            for pool in self.query_pools.drain(..) {
+                device.destroy_query_pool(pool, None);
+            }
+        }
+    }
+}
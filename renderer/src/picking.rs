@@ -0,0 +1,38 @@
+use bytemuck::{Pod, Zeroable};
+
+/// Layout of the buffer the raygen shader writes the crosshair hit into
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+pub(crate) struct PickGpu {
+    instance: u32,
+    primitive: u32,
+    distance: f32,
+    _padding: u32,
+}
+
+impl PickGpu {
+    /// Written by the miss shader, and by the host when clearing a result it has consumed
+    pub(crate) const NO_HIT: Self = Self {
+        instance: u32::MAX,
+        primitive: u32::MAX,
+        distance: 0.0,
+        _padding: 0,
+    };
+
+    pub(crate) fn hit(&self) -> Option<PickHit> {
+        (self.instance != u32::MAX).then_some(PickHit {
+            instance: self.instance,
+            primitive: self.primitive,
+            distance: self.distance,
+        })
+    }
+}
+
+/// What the center of the screen hit a few frames ago
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PickHit {
+    /// Instance custom index; see `AccelerationStructureState::instance_entity`
+    pub instance: u32,
+    pub primitive: u32,
+    pub distance: f32,
+}
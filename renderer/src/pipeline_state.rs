@@ -2,6 +2,7 @@ use std::{
     error::Error,
     fs::File,
     io::{self, Read},
+    mem,
     path::Path,
 };
 
@@ -11,20 +12,84 @@ use ash::{
     vk,
 };
 use bevy_ecs::system::Resource;
+use data::voxel_block::VoxelBlock;
+
+use crate::{
+    init_state::InitState,
+    reflection::{PipelineReflection, ShaderReflection},
+    settings::RendererSettings,
+    shader_binding_table::{SbtBuilder, ShaderBindingTable, ShaderRecord},
+    specialization::SpecializationConstants,
+    PushConstants,
+};
+
+/// Values the ray tracing shaders are specialized with. Unlike push constants these let the
+/// compiler unroll loops and drop dead branches, at the cost of a pipeline rebuild on change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShaderConstants {
+    /// Recursion depth the pipeline allows, limited by the device
+    pub max_recursion_depth: u32,
+    pub shadow_rays: u32,
+    /// Edge length of a voxel chunk
+    pub brick_size: u32,
+}
+
+impl ShaderConstants {
+    const MAX_RECURSION_DEPTH_ID: u32 = 0;
+    const SHADOW_RAYS_ID: u32 = 1;
+    const BRICK_SIZE_ID: u32 = 2;
+
+    pub fn new(settings: &RendererSettings, device_max_recursion_depth: u32) -> Self {
+        Self {
+            max_recursion_depth: RendererSettings::MAX_RAY_RECURSION_DEPTH
+                .min(device_max_recursion_depth),
+            shadow_rays: settings.clamped_shadow_rays(),
+            brick_size: VoxelBlock::WIDTH as u32,
+        }
+    }
 
-use crate::{buffer::Buffer, init_state::InitState};
+    pub fn specialization(&self) -> SpecializationConstants {
+        SpecializationConstants::new()
+            .with_u32(Self::MAX_RECURSION_DEPTH_ID, self.max_recursion_depth)
+            .with_u32(Self::SHADOW_RAYS_ID, self.shadow_rays)
+            .with_u32(Self::BRICK_SIZE_ID, self.brick_size)
+    }
+}
 
 #[derive(Resource)]
 pub struct PipelineState<'a> {
     ray_tracing_loader: ray_tracing_pipeline::Device,
     buffer_device_address_loader: buffer_device_address::Device,
     descriptor_set_layout: vk::DescriptorSetLayout,
+    reflection: PipelineReflection,
     pipeline_layout: vk::PipelineLayout,
     pipeline: vk::Pipeline,
+    shader_constants: ShaderConstants,
     shader_binding_table: ShaderBindingTable<'a>,
 }
 
 impl<'a> PipelineState<'a> {
+    const SHADER_PATHS: [&'static str; 3] = [
+        "./bin/raygen.rgen.spv",
+        "./bin/miss.rmiss.spv",
+        "./bin/closesthit.rchit.spv",
+    ];
+    pub const DESCRIPTOR_SET: u32 = 0;
+    /// The camera uniforms, bound at the frame's slot of the uniform ring
+    const CAMERA_BINDING: u32 = 2;
+
+    const RAYGEN_GROUP: u32 = 0;
+    const MISS_GROUP: u32 = 1;
+    const HIT_GROUP: u32 = 2;
+    const GROUP_COUNT: u32 = 3;
+
+    /// SBT hit blocks added through [`SbtBuilder::add_instance`]
+    pub const SCENE_HIT_BLOCK: usize = 0;
+    pub const PROP_HIT_BLOCK: usize = 1;
+
+    /// Hit record material index telling the shader to use the instance's material instead
+    const MATERIAL_FROM_INSTANCE: u32 = u32::MAX;
+
     pub const fn ray_tracing_loader(&self) -> &ray_tracing_pipeline::Device {
         &self.ray_tracing_loader
     }
@@ -37,6 +102,10 @@ impl<'a> PipelineState<'a> {
         self.descriptor_set_layout
     }
 
+    pub const fn reflection(&self) -> &PipelineReflection {
+        &self.reflection
+    }
+
     pub const fn pipeline_layout(&self) -> vk::PipelineLayout {
         self.pipeline_layout
     }
@@ -45,7 +114,16 @@ impl<'a> PipelineState<'a> {
         self.pipeline
     }
 
-    pub const fn shader_binding_table(&self) -> &ShaderBindingTable {
+    /// Recursion depth the pipeline was created with, limited by the device
+    pub const fn max_recursion_depth(&self) -> u32 {
+        self.shader_constants.max_recursion_depth
+    }
+
+    pub const fn shader_constants(&self) -> &ShaderConstants {
+        &self.shader_constants
+    }
+
+    pub const fn shader_binding_table(&self) -> &ShaderBindingTable<'_> {
         &self.shader_binding_table
     }
 
@@ -53,27 +131,40 @@ impl<'a> PipelineState<'a> {
         &mut self.shader_binding_table
     }
 
-    pub fn new(init_state: &InitState) -> Result<Self, Box<dyn Error>> {
+    pub fn new(
+        init_state: &InitState,
+        settings: &RendererSettings,
+    ) -> Result<Self, Box<dyn Error>> {
         unsafe {
             let ray_tracing_loader =
                 ray_tracing_pipeline::Device::new(init_state.instance(), init_state.device());
             let buffer_device_address_loader =
                 buffer_device_address::Device::new(init_state.instance(), init_state.device());
 
-            let descriptor_set_layout = Self::create_descriptor_set_layout(init_state.device())?;
+            let rt_properties =
+                Self::ray_tracing_properties(init_state.instance(), init_state.physical_device());
+
+            let reflection = Self::reflect_shaders()?;
+            let descriptor_set_layout =
+                Self::create_descriptor_set_layout(init_state.device(), &reflection)?;
+
+            let shader_constants =
+                ShaderConstants::new(settings, rt_properties.max_ray_recursion_depth);
 
             let (pipeline_layout, pipeline) = Self::create_pipeline(
                 init_state.device(),
+                init_state.pipeline_cache(),
                 &ray_tracing_loader,
                 descriptor_set_layout,
+                &reflection,
+                &shader_constants,
             )?;
 
             let shader_binding_table = Self::create_shader_binding_table(
-                init_state.instance(),
-                init_state.device(),
-                init_state.physical_device(),
+                init_state,
                 &buffer_device_address_loader,
                 &ray_tracing_loader,
+                &rt_properties,
                 pipeline,
             )?;
 
@@ -81,39 +172,109 @@ impl<'a> PipelineState<'a> {
                 ray_tracing_loader,
                 buffer_device_address_loader,
                 descriptor_set_layout,
+                reflection,
                 pipeline_layout,
                 pipeline,
+                shader_constants,
                 shader_binding_table,
             })
         }
     }
 
+    /// Rebuilds the pipeline and its shader binding table when `settings` changes a
+    /// specialization constant, returning whether it did. The table's layout is unchanged, so
+    /// instance SBT offsets in the TLAS stay valid.
+    pub fn apply_settings(
+        &mut self,
+        init_state: &InitState,
+        settings: &RendererSettings,
+    ) -> Result<bool, Box<dyn Error>> {
+        unsafe {
+            let rt_properties =
+                Self::ray_tracing_properties(init_state.instance(), init_state.physical_device());
+            let shader_constants =
+                ShaderConstants::new(settings, rt_properties.max_ray_recursion_depth);
+            if shader_constants == self.shader_constants {
+                return Ok(false);
+            }
+
+            let device = init_state.device();
+            device.device_wait_idle()?;
+            self.shader_binding_table.cleanup(device);
+            device.destroy_pipeline(self.pipeline, None);
+            device.destroy_pipeline_layout(self.pipeline_layout, None);
+
+            (self.pipeline_layout, self.pipeline) = Self::create_pipeline(
+                device,
+                init_state.pipeline_cache(),
+                &self.ray_tracing_loader,
+                self.descriptor_set_layout,
+                &self.reflection,
+                &shader_constants,
+            )?;
+            self.shader_binding_table = Self::create_shader_binding_table(
+                init_state,
+                &self.buffer_device_address_loader,
+                &self.ray_tracing_loader,
+                &rt_properties,
+                self.pipeline,
+            )?;
+            self.shader_constants = shader_constants;
+            Ok(true)
+        }
+    }
+
+    unsafe fn ray_tracing_properties(
+        instance: &ash::Instance,
+        physical_device: vk::PhysicalDevice,
+    ) -> vk::PhysicalDeviceRayTracingPipelinePropertiesKHR<'static> {
+        let mut rt_properties = vk::PhysicalDeviceRayTracingPipelinePropertiesKHR::default();
+        instance.get_physical_device_properties2(
+            physical_device,
+            &mut vk::PhysicalDeviceProperties2::default().push_next(&mut rt_properties),
+        );
+        rt_properties
+    }
+
+    /// The shaders' interface, which the descriptor set layout and push constant range are
+    /// built from so they can't drift from what the shaders declare
+    fn reflect_shaders() -> Result<PipelineReflection, Box<dyn Error>> {
+        let shaders = Self::SHADER_PATHS
+            .iter()
+            .map(|path| {
+                Ok(ShaderReflection::new(&Self::read_shader_code(Path::new(
+                    path,
+                ))?)?)
+            })
+            .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+        let reflection = PipelineReflection::new(&shaders)?
+            .with_dynamic_offset(Self::DESCRIPTOR_SET, Self::CAMERA_BINDING);
+        let push_constants = reflection
+            .push_constant_ranges()
+            .first()
+            .map_or(0, |range| range.size);
+        if push_constants > mem::size_of::<PushConstants>() as u32 {
+            return Err(format!(
+                "shaders declare {push_constants} bytes of push constants but {} are pushed",
+                mem::size_of::<PushConstants>()
+            )
+            .into());
+        }
+        Ok(reflection)
+    }
+
     unsafe fn create_descriptor_set_layout(
         device: &ash::Device,
+        reflection: &PipelineReflection,
     ) -> VkResult<vk::DescriptorSetLayout> {
         device.create_descriptor_set_layout(
-            &vk::DescriptorSetLayoutCreateInfo::default().bindings(&[
-                vk::DescriptorSetLayoutBinding::default()
-                    .binding(0)
-                    .descriptor_type(vk::DescriptorType::ACCELERATION_STRUCTURE_KHR)
-                    .descriptor_count(1)
-                    .stage_flags(vk::ShaderStageFlags::RAYGEN_KHR),
-                vk::DescriptorSetLayoutBinding::default()
-                    .binding(1)
-                    .descriptor_type(vk::DescriptorType::STORAGE_IMAGE)
-                    .descriptor_count(1)
-                    .stage_flags(vk::ShaderStageFlags::RAYGEN_KHR),
-                vk::DescriptorSetLayoutBinding::default()
-                    .binding(2)
-                    .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
-                    .descriptor_count(1)
-                    .stage_flags(vk::ShaderStageFlags::RAYGEN_KHR),
-            ]),
+            &vk::DescriptorSetLayoutCreateInfo::default()
+                .bindings(&reflection.set_layout_bindings(Self::DESCRIPTOR_SET)),
             None,
         )
     }
 
-    fn read_shader_code(path: &Path) -> io::Result<Vec<u32>> {
+    pub(crate) fn read_shader_code(path: &Path) -> io::Result<Vec<u32>> {
         let mut file = File::open(path)?;
         let mut buffer = Vec::new();
         file.read_to_end(&mut buffer)?;
@@ -140,7 +301,7 @@ impl<'a> PipelineState<'a> {
         Ok(code)
     }
 
-    unsafe fn create_shader_module(
+    pub(crate) unsafe fn create_shader_module(
         device: &ash::Device,
         code: &[u32],
     ) -> VkResult<vk::ShaderModule> {
@@ -149,40 +310,52 @@ impl<'a> PipelineState<'a> {
 
     unsafe fn create_pipeline(
         device: &ash::Device,
+        pipeline_cache: vk::PipelineCache,
         ray_tracing_loader: &ray_tracing_pipeline::Device,
         descriptor_set_layout: vk::DescriptorSetLayout,
+        reflection: &PipelineReflection,
+        shader_constants: &ShaderConstants,
     ) -> Result<(vk::PipelineLayout, vk::Pipeline), Box<dyn Error>> {
-        let raygen_shader = Self::read_shader_code(Path::new("./bin/raygen.rgen.spv"))?;
-        let miss_shader = Self::read_shader_code(Path::new("./bin/miss.rmiss.spv"))?;
-        let closest_hit_shader = Self::read_shader_code(Path::new("./bin/closesthit.rchit.spv"))?;
+        let [raygen_path, miss_path, closest_hit_path] = Self::SHADER_PATHS;
+        let raygen_shader = Self::read_shader_code(Path::new(raygen_path))?;
+        let miss_shader = Self::read_shader_code(Path::new(miss_path))?;
+        let closest_hit_shader = Self::read_shader_code(Path::new(closest_hit_path))?;
 
         let raygen_module = Self::create_shader_module(device, &raygen_shader)?;
         let miss_module = Self::create_shader_module(device, &miss_shader)?;
         let closest_hit_module = Self::create_shader_module(device, &closest_hit_shader)?;
 
         let pipeline_layout = device.create_pipeline_layout(
-            &vk::PipelineLayoutCreateInfo::default().set_layouts(&[descriptor_set_layout]),
+            &vk::PipelineLayoutCreateInfo::default()
+                .set_layouts(&[descriptor_set_layout])
+                .push_constant_ranges(&reflection.push_constant_ranges()),
             None,
         )?;
 
+        let specialization = shader_constants.specialization();
+        let specialization_info = specialization.info();
+
         let pipelines = ray_tracing_loader
             .create_ray_tracing_pipelines(
                 vk::DeferredOperationKHR::null(),
-                vk::PipelineCache::null(),
+                pipeline_cache,
                 &[vk::RayTracingPipelineCreateInfoKHR::default()
                     .stages(&[
                         vk::PipelineShaderStageCreateInfo::default()
                             .stage(vk::ShaderStageFlags::RAYGEN_KHR)
                             .module(raygen_module)
-                            .name(c"main"),
+                            .name(c"main")
+                            .specialization_info(&specialization_info),
                         vk::PipelineShaderStageCreateInfo::default()
                             .stage(vk::ShaderStageFlags::MISS_KHR)
                             .module(miss_module)
-                            .name(c"main"),
+                            .name(c"main")
+                            .specialization_info(&specialization_info),
                         vk::PipelineShaderStageCreateInfo::default()
                             .stage(vk::ShaderStageFlags::CLOSEST_HIT_KHR)
                             .module(closest_hit_module)
-                            .name(c"main"),
+                            .name(c"main")
+                            .specialization_info(&specialization_info),
                     ])
                     .groups(&[
                         vk::RayTracingShaderGroupCreateInfoKHR::default()
@@ -204,7 +377,7 @@ impl<'a> PipelineState<'a> {
                             .any_hit_shader(vk::SHADER_UNUSED_KHR)
                             .intersection_shader(vk::SHADER_UNUSED_KHR),
                     ])
-                    .max_pipeline_ray_recursion_depth(1)
+                    .max_pipeline_ray_recursion_depth(shader_constants.max_recursion_depth)
                     .layout(pipeline_layout)],
                 None,
             )
@@ -216,94 +389,39 @@ impl<'a> PipelineState<'a> {
         Ok((pipeline_layout, pipelines[0]))
     }
 
-    unsafe fn create_shader_binding_table(
-        instance: &ash::Instance,
-        device: &ash::Device,
-        physical_device: vk::PhysicalDevice,
+    fn create_shader_binding_table(
+        init_state: &InitState,
         bda_loader: &buffer_device_address::Device,
         rt_loader: &ray_tracing_pipeline::Device,
+        rt_properties: &vk::PhysicalDeviceRayTracingPipelinePropertiesKHR,
         pipeline: vk::Pipeline,
     ) -> Result<ShaderBindingTable<'a>, Box<dyn Error>> {
-        let mut rt_properties = vk::PhysicalDeviceRayTracingPipelinePropertiesKHR::default();
-        instance.get_physical_device_properties2(
-            physical_device,
-            &mut vk::PhysicalDeviceProperties2::default().push_next(&mut rt_properties),
-        );
-
-        let handle_size = rt_properties.shader_group_handle_size as vk::DeviceSize;
-        let group_count = 3;
-
-        let group_alignment = rt_properties
-            .shader_group_handle_alignment
-            .max(rt_properties.shader_group_base_alignment)
-            .max(64) as vk::DeviceSize;
-
-        let total_size = group_alignment * group_count;
-
-        if handle_size == 0 || total_size == 0 {
-            return Err(Box::new(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                "Shader group handle size is 0, properties query failed",
-            )));
+        let mut builder = SbtBuilder::new(ShaderRecord::new(Self::RAYGEN_GROUP))
+            .with_miss(ShaderRecord::new(Self::MISS_GROUP));
+
+        // The scene and every prop instance each have a block, but all of them take their
+        // material from the instance buffer through `gl_InstanceCustomIndexEXT`, so water and
+        // other reflective materials can be any instance's
+        for _ in [Self::SCENE_HIT_BLOCK, Self::PROP_HIT_BLOCK] {
+            builder.add_instance(&[ShaderRecord::new(Self::HIT_GROUP)
+                .with_data(bytemuck::bytes_of(&Self::MATERIAL_FROM_INSTANCE))]);
         }
 
-        let mut buffer = Buffer::create(
-            instance,
-            device,
-            physical_device,
-            total_size,
-            vk::BufferUsageFlags::SHADER_BINDING_TABLE_KHR
-                | vk::BufferUsageFlags::TRANSFER_DST
-                | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS,
-            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
-        )?;
-
-        buffer.map_memory(device, 0, vk::MemoryMapFlags::empty())?;
-
-        let handles = rt_loader.get_ray_tracing_shader_group_handles(
+        builder.build(
+            init_state.instance(),
+            init_state.device(),
+            init_state.physical_device(),
+            bda_loader,
+            rt_loader,
+            rt_properties,
             pipeline,
-            0,
-            group_count as u32,
-            (handle_size * group_count) as usize,
-        )?;
-        let mapped = buffer.mapped_mut().as_mut().unwrap();
-        mapped[0..handle_size as usize].copy_from_slice(&handles[0..handle_size as usize]); // Raygen at 0
-        mapped[group_alignment as usize..(group_alignment + handle_size) as usize]
-            .copy_from_slice(&handles[handle_size as usize..(handle_size * 2) as usize]); // Miss at 64
-        mapped[(group_alignment * 2) as usize..(group_alignment * 2 + handle_size) as usize]
-            .copy_from_slice(&handles[(handle_size * 2) as usize..]); // Hit at 128
-        buffer.unmap_memory(device)?;
-
-        let buffer_address = bda_loader.get_buffer_device_address(
-            &vk::BufferDeviceAddressInfo::default().buffer(buffer.handle()),
-        );
-
-        let aligned_buffer_address =
-            (buffer_address + group_alignment - 1) & !(group_alignment - 1);
-
-        let region_size = handle_size;
-        Ok(ShaderBindingTable {
-            buffer,
-            raygen_region: vk::StridedDeviceAddressRegionKHR::default()
-                .device_address(aligned_buffer_address)
-                .stride(region_size)
-                .size(region_size),
-            miss_region: vk::StridedDeviceAddressRegionKHR::default()
-                .device_address(aligned_buffer_address + group_alignment)
-                .stride(region_size)
-                .size(region_size),
-            hit_region: vk::StridedDeviceAddressRegionKHR::default()
-                .device_address(aligned_buffer_address + group_alignment * 2)
-                .stride(region_size)
-                .size(region_size),
-        })
+            Self::GROUP_COUNT,
+        )
     }
 
     pub fn cleanup(&mut self, init_state: &InitState) {
         unsafe {
-            self.shader_binding_table
-                .buffer
-                .cleanup(init_state.device());
+            self.shader_binding_table.cleanup(init_state.device());
 
             init_state.device().destroy_pipeline(self.pipeline, None);
             init_state
@@ -315,10 +433,3 @@ impl<'a> PipelineState<'a> {
         }
     }
 }
-
-pub struct ShaderBindingTable<'a> {
-    buffer: Buffer<'a>,
-    pub raygen_region: vk::StridedDeviceAddressRegionKHR,
-    pub miss_region: vk::StridedDeviceAddressRegionKHR,
-    pub hit_region: vk::StridedDeviceAddressRegionKHR,
-}
@@ -0,0 +1,119 @@
+use std::{
+    mem,
+    sync::{
+        mpsc::{self, Sender},
+        Arc, Condvar, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use ash::{khr::present_wait, vk};
+
+use crate::init_state::InitState;
+
+/// Timestamps presents as they reach the display. `vkWaitForPresentKHR` blocks, so a thread of
+/// its own waits on each present ID the renderer hands it, and the main thread collects the
+/// times without ever stalling on the presentation engine.
+pub struct PresentWaiter {
+    requests: Option<Sender<(vk::SwapchainKHR, u64)>>,
+    shared: Arc<(Mutex<Shared>, Condvar)>,
+    thread: Option<JoinHandle<()>>,
+    last_id: u64,
+}
+
+#[derive(Default)]
+struct Shared {
+    /// IDs and times of the presents displayed
+    presented: Vec<(u64, Instant)>,
+    /// Presents handed to the thread and not yet waited for
+    waiting: usize,
+}
+
+impl PresentWaiter {
+    /// A present not displayed by then is given up on, so a hidden window can't keep
+    /// [`Self::drain`] blocked
+    const TIMEOUT: Duration = Duration::from_secs(1);
+
+    /// `None` if the device wasn't created with `VK_KHR_present_wait`
+    pub fn new(init_state: &InitState) -> Option<Self> {
+        if !init_state.capabilities().present_wait {
+            return None;
+        }
+        let loader = present_wait::Device::new(init_state.instance(), init_state.device());
+        let (requests, received) = mpsc::channel::<(vk::SwapchainKHR, u64)>();
+        let shared = Arc::new((Mutex::new(Shared::default()), Condvar::new()));
+        let thread_shared = Arc::clone(&shared);
+        let thread = thread::Builder::new()
+            .name("present wait".to_owned())
+            .spawn(move || {
+                let (lock, drained) = &*thread_shared;
+                for (swapchain, id) in received {
+                    let result = unsafe {
+                        loader.wait_for_present(swapchain, id, Self::TIMEOUT.as_nanos() as u64)
+                    };
+                    let mut shared = lock.lock().unwrap();
+                    if result.is_ok() {
+                        shared.presented.push((id, Instant::now()));
+                    }
+                    shared.waiting -= 1;
+                    drained.notify_all();
+                }
+            })
+            .ok()?;
+        Some(Self {
+            requests: Some(requests),
+            shared,
+            thread: Some(thread),
+            last_id: 0,
+        })
+    }
+
+    /// ID to chain onto the next present with [`vk::PresentIdKHR`]. IDs only ever increase
+    /// on a swapchain, so they keep counting across recreations.
+    pub fn next_id(&mut self) -> u64 {
+        self.last_id += 1;
+        self.last_id
+    }
+
+    /// ID of the last present queued
+    pub const fn last_id(&self) -> u64 {
+        self.last_id
+    }
+
+    /// Starts waiting for the present with `id`, once it's been queued
+    pub fn wait_for(&self, swapchain: vk::SwapchainKHR, id: u64) {
+        let Some(requests) = &self.requests else {
+            return;
+        };
+        self.shared.0.lock().unwrap().waiting += 1;
+        if requests.send((swapchain, id)).is_err() {
+            self.shared.0.lock().unwrap().waiting -= 1;
+        }
+    }
+
+    /// Blocks until nothing is being waited for. Call before destroying a swapchain, which the
+    /// thread mustn't wait on any more.
+    pub fn drain(&self) {
+        let (lock, drained) = &*self.shared;
+        let _idle = drained
+            .wait_while(lock.lock().unwrap(), |shared| shared.waiting > 0)
+            .unwrap();
+    }
+
+    /// IDs and times of the presents that reached the display since the last call, oldest
+    /// first
+    pub fn take_presented(&self) -> Vec<(u64, Instant)> {
+        mem::take(&mut self.shared.0.lock().unwrap().presented)
+    }
+}
+
+impl Drop for PresentWaiter {
+    fn drop(&mut self) {
+        // Ends the thread's loop once it's done with what it was sent
+        self.requests = None;
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
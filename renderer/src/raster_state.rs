@@ -0,0 +1,559 @@
+use std::{error::Error, mem, path::Path};
+
+use ash::{prelude::VkResult, vk};
+use bevy_ecs::system::Resource;
+use bytemuck::{Pod, Zeroable};
+use data::{camera::CameraGpu, mesh::Mesh};
+
+use crate::{
+    buffer_state::BufferState,
+    init_state::InitState,
+    pipeline_state::PipelineState,
+    reflection::{vertex_input_descriptions, ShaderReflection},
+    render_graph::{ImageHandle, ImageState, RenderGraph},
+    settings::RendererSettings,
+    swapchain_state::SwapchainState,
+    INDICES, MAX_FRAMES_IN_FLIGHT,
+};
+
+/// Matches the `push_constant` block of the raster shaders; the flat shader only declares the
+/// leading members
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+pub struct RasterPushConstants {
+    view_proj: [[f32; 4]; 4],
+    color: [f32; 4],
+    camera_position: [f32; 4],
+    shadows: u32,
+    ambient_occlusion: u32,
+    sunlight: f32,
+    _padding: u32,
+}
+
+impl RasterPushConstants {
+    pub fn new(camera_gpu: &CameraGpu, color: [f32; 4], settings: &RendererSettings) -> Self {
+        Self {
+            view_proj: camera_gpu.view_proj().to_cols_array_2d(),
+            color,
+            camera_position: camera_gpu.view_inverse[3],
+            shadows: settings.shadows as u32,
+            ambient_occlusion: settings.ambient_occlusion as u32,
+            sunlight: camera_gpu.sunlight,
+            _padding: 0,
+        }
+    }
+}
+
+/// How the raster fragment shader lights the scene
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RasterShading {
+    /// One flat color, for devices without any ray tracing support
+    Flat,
+    /// Shadow and AO rays from `rayQueryEXT` against the TLAS, for the hybrid path
+    RayQuery,
+}
+
+/// Images a raster pass draws into. The depth image is cleared, so its previous contents and
+/// layout don't matter.
+#[derive(Debug, Clone, Copy)]
+pub struct RasterTarget {
+    /// The multisampled color image when `resolve` is set
+    pub color: ImageHandle,
+    pub color_view: vk::ImageView,
+    pub depth: ImageHandle,
+    pub depth_view: vk::ImageView,
+    /// Single-sampled image `color` is averaged into, e.g. the swapchain image
+    pub resolve: Option<(ImageHandle, vk::ImageView)>,
+    pub extent: vk::Extent2D,
+}
+
+/// Rasterizes the scene for devices without ray tracing. Uses dynamic rendering, so there is no
+/// render pass, and no framebuffers to rebuild when the swapchain is recreated: the pipeline
+/// only depends on the attachment formats, and viewport and scissor are set per frame.
+#[derive(Resource)]
+pub struct RasterState {
+    shading: RasterShading,
+    color_format: vk::Format,
+    depth_format: vk::Format,
+    samples: vk::SampleCountFlags,
+    /// TLAS binding for [`RasterShading::RayQuery`]; `None` when flat shaded
+    descriptor_set_layout: Option<vk::DescriptorSetLayout>,
+    descriptor_pool: vk::DescriptorPool,
+    /// One per frame in flight, so a frame's set can be rewritten while others are pending
+    descriptor_sets: Vec<vk::DescriptorSet>,
+    pipeline_layout: vk::PipelineLayout,
+    pipeline: vk::Pipeline,
+}
+
+impl RasterState {
+    const PUSH_CONSTANT_STAGES: vk::ShaderStageFlags = vk::ShaderStageFlags::from_raw(
+        vk::ShaderStageFlags::VERTEX.as_raw() | vk::ShaderStageFlags::FRAGMENT.as_raw(),
+    );
+
+    const CLEAR_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
+    /// Infinitely far, since depth is reversed; nearer fragments have greater depth
+    const CLEAR_DEPTH: f32 = 0.0;
+
+    pub const fn shading(&self) -> RasterShading {
+        self.shading
+    }
+
+    pub const fn color_format(&self) -> vk::Format {
+        self.color_format
+    }
+
+    pub const fn depth_format(&self) -> vk::Format {
+        self.depth_format
+    }
+
+    pub const fn samples(&self) -> vk::SampleCountFlags {
+        self.samples
+    }
+
+    pub const fn pipeline_layout(&self) -> vk::PipelineLayout {
+        self.pipeline_layout
+    }
+
+    pub const fn pipeline(&self) -> vk::Pipeline {
+        self.pipeline
+    }
+
+    /// Renders into the swapchain's images: its color and depth formats and sample count
+    pub fn new(
+        init_state: &InitState,
+        swapchain_state: &SwapchainState,
+        shading: RasterShading,
+    ) -> Result<Self, Box<dyn Error>> {
+        unsafe {
+            let color_format = swapchain_state.image_format();
+            let depth_format = swapchain_state.depth_format();
+            let samples = swapchain_state.msaa_samples();
+
+            let (descriptor_set_layout, descriptor_pool, descriptor_sets) = match shading {
+                RasterShading::Flat => (None, vk::DescriptorPool::null(), Vec::new()),
+                RasterShading::RayQuery => {
+                    let layout = Self::create_descriptor_set_layout(init_state.device())?;
+                    let pool = Self::create_descriptor_pool(init_state.device())?;
+                    let sets = init_state.device().allocate_descriptor_sets(
+                        &vk::DescriptorSetAllocateInfo::default()
+                            .descriptor_pool(pool)
+                            .set_layouts(&[layout; MAX_FRAMES_IN_FLIGHT as usize]),
+                    )?;
+                    (Some(layout), pool, sets)
+                }
+            };
+
+            let (pipeline_layout, pipeline) = Self::create_pipeline(
+                init_state.device(),
+                init_state.pipeline_cache(),
+                shading,
+                descriptor_set_layout,
+                color_format,
+                depth_format,
+                samples,
+            )?;
+
+            Ok(Self {
+                shading,
+                color_format,
+                depth_format,
+                samples,
+                descriptor_set_layout,
+                descriptor_pool,
+                descriptor_sets,
+                pipeline_layout,
+                pipeline,
+            })
+        }
+    }
+
+    /// Points the frame's descriptor set at `tlas`. Only call once the frame's fence has
+    /// signaled, so the set isn't in use. Does nothing when flat shaded.
+    pub fn write_tlas(
+        &self,
+        device: &ash::Device,
+        current_frame: u8,
+        tlas: vk::AccelerationStructureKHR,
+    ) {
+        let Some(&descriptor_set) = self.descriptor_sets.get(current_frame as usize) else {
+            return;
+        };
+        unsafe {
+            device.update_descriptor_sets(
+                &[vk::WriteDescriptorSet::default()
+                    .dst_set(descriptor_set)
+                    .dst_binding(0)
+                    .descriptor_type(vk::DescriptorType::ACCELERATION_STRUCTURE_KHR)
+                    .descriptor_count(1)
+                    .push_next(
+                        &mut vk::WriteDescriptorSetAccelerationStructureKHR::default()
+                            .acceleration_structures(&[tlas]),
+                    )],
+                &[],
+            );
+        }
+    }
+
+    /// Call after recreating the swapchain. Only rebuilds the pipeline when the formats or
+    /// sample count changed; a resize keeps it.
+    pub fn recreate_pipeline(
+        &mut self,
+        init_state: &InitState,
+        swapchain_state: &SwapchainState,
+    ) -> Result<(), Box<dyn Error>> {
+        let color_format = swapchain_state.image_format();
+        let depth_format = swapchain_state.depth_format();
+        let samples = swapchain_state.msaa_samples();
+        if (color_format, depth_format, samples)
+            == (self.color_format, self.depth_format, self.samples)
+        {
+            return Ok(());
+        }
+        unsafe {
+            init_state.device().device_wait_idle()?;
+            self.destroy_pipeline(init_state.device());
+            (self.pipeline_layout, self.pipeline) = Self::create_pipeline(
+                init_state.device(),
+                init_state.pipeline_cache(),
+                self.shading,
+                self.descriptor_set_layout,
+                color_format,
+                depth_format,
+                samples,
+            )?;
+        }
+        self.color_format = color_format;
+        self.depth_format = depth_format;
+        self.samples = samples;
+        Ok(())
+    }
+
+    /// Adds a pass clearing `target` and drawing the scene into it
+    pub fn add_pass<'a>(
+        &'a self,
+        graph: &mut RenderGraph<'a>,
+        device: &'a ash::Device,
+        buffer_state: &'a BufferState,
+        target: RasterTarget,
+        push_constants: RasterPushConstants,
+        current_frame: u8,
+    ) {
+        graph.add_pass(
+            "raster",
+            |pass| {
+                pass.image(target.color, ImageState::COLOR_ATTACHMENT)
+                    .image(target.depth, ImageState::DEPTH_ATTACHMENT);
+                if let Some((resolve, _)) = target.resolve {
+                    pass.image(resolve, ImageState::COLOR_ATTACHMENT);
+                }
+            },
+            move |command_buffer, _| unsafe {
+                self.record(
+                    device,
+                    command_buffer,
+                    buffer_state,
+                    &target,
+                    &push_constants,
+                    current_frame,
+                );
+            },
+        );
+    }
+
+    /// The target images must be in their attachment layouts
+    unsafe fn record(
+        &self,
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        buffer_state: &BufferState,
+        target: &RasterTarget,
+        push_constants: &RasterPushConstants,
+        current_frame: u8,
+    ) {
+        let extent = target.extent;
+        let render_area = vk::Rect2D {
+            offset: vk::Offset2D { x: 0, y: 0 },
+            extent,
+        };
+        let mut color_attachment = vk::RenderingAttachmentInfo::default()
+            .image_view(target.color_view)
+            .image_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+            .load_op(vk::AttachmentLoadOp::CLEAR)
+            .store_op(vk::AttachmentStoreOp::STORE)
+            .clear_value(vk::ClearValue {
+                color: vk::ClearColorValue {
+                    float32: Self::CLEAR_COLOR,
+                },
+            });
+        if let Some((_, resolve_view)) = target.resolve {
+            // Only the resolved samples are kept
+            color_attachment = color_attachment
+                .store_op(vk::AttachmentStoreOp::DONT_CARE)
+                .resolve_mode(vk::ResolveModeFlags::AVERAGE)
+                .resolve_image_view(resolve_view)
+                .resolve_image_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL);
+        }
+        let color_attachments = [color_attachment];
+        // Only needed within the pass
+        let depth_attachment = vk::RenderingAttachmentInfo::default()
+            .image_view(target.depth_view)
+            .image_layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL)
+            .load_op(vk::AttachmentLoadOp::CLEAR)
+            .store_op(vk::AttachmentStoreOp::DONT_CARE)
+            .clear_value(vk::ClearValue {
+                depth_stencil: vk::ClearDepthStencilValue {
+                    depth: Self::CLEAR_DEPTH,
+                    stencil: 0,
+                },
+            });
+
+        device.cmd_begin_rendering(
+            command_buffer,
+            &vk::RenderingInfo::default()
+                .render_area(render_area)
+                .layer_count(1)
+                .color_attachments(&color_attachments)
+                .depth_attachment(&depth_attachment),
+        );
+
+        device.cmd_bind_pipeline(
+            command_buffer,
+            vk::PipelineBindPoint::GRAPHICS,
+            self.pipeline,
+        );
+        if let Some(&descriptor_set) = self.descriptor_sets.get(current_frame as usize) {
+            device.cmd_bind_descriptor_sets(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                self.pipeline_layout,
+                0,
+                &[descriptor_set],
+                &[],
+            );
+        }
+        device.cmd_set_viewport(
+            command_buffer,
+            0,
+            &[vk::Viewport {
+                x: 0.0,
+                y: 0.0,
+                width: extent.width as f32,
+                height: extent.height as f32,
+                min_depth: 0.0,
+                max_depth: 1.0,
+            }],
+        );
+        device.cmd_set_scissor(command_buffer, 0, &[render_area]);
+        device.cmd_push_constants(
+            command_buffer,
+            self.pipeline_layout,
+            Self::PUSH_CONSTANT_STAGES,
+            0,
+            bytemuck::bytes_of(push_constants),
+        );
+        device.cmd_bind_vertex_buffers(
+            command_buffer,
+            0,
+            &[buffer_state.vertex_buffer().handle()],
+            &[0],
+        );
+        device.cmd_bind_index_buffer(
+            command_buffer,
+            buffer_state.index_buffer().handle(),
+            0,
+            vk::IndexType::UINT16,
+        );
+        device.cmd_draw_indexed(command_buffer, INDICES.len() as u32, 1, 0, 0, 0);
+
+        device.cmd_end_rendering(command_buffer);
+    }
+
+    unsafe fn create_descriptor_set_layout(
+        device: &ash::Device,
+    ) -> VkResult<vk::DescriptorSetLayout> {
+        device.create_descriptor_set_layout(
+            &vk::DescriptorSetLayoutCreateInfo::default().bindings(&[
+                vk::DescriptorSetLayoutBinding::default()
+                    .binding(0)
+                    .descriptor_type(vk::DescriptorType::ACCELERATION_STRUCTURE_KHR)
+                    .descriptor_count(1)
+                    .stage_flags(vk::ShaderStageFlags::FRAGMENT),
+            ]),
+            None,
+        )
+    }
+
+    unsafe fn create_descriptor_pool(device: &ash::Device) -> VkResult<vk::DescriptorPool> {
+        device.create_descriptor_pool(
+            &vk::DescriptorPoolCreateInfo::default()
+                .pool_sizes(&[vk::DescriptorPoolSize::default()
+                    .descriptor_count(MAX_FRAMES_IN_FLIGHT as u32)
+                    .ty(vk::DescriptorType::ACCELERATION_STRUCTURE_KHR)])
+                .max_sets(MAX_FRAMES_IN_FLIGHT as u32),
+            None,
+        )
+    }
+
+    unsafe fn create_pipeline(
+        device: &ash::Device,
+        pipeline_cache: vk::PipelineCache,
+        shading: RasterShading,
+        descriptor_set_layout: Option<vk::DescriptorSetLayout>,
+        color_format: vk::Format,
+        depth_format: vk::Format,
+        samples: vk::SampleCountFlags,
+    ) -> Result<(vk::PipelineLayout, vk::Pipeline), Box<dyn Error>> {
+        let fragment_path = match shading {
+            RasterShading::Flat => "./bin/raster.frag.spv",
+            RasterShading::RayQuery => "./bin/hybrid.frag.spv",
+        };
+        let vertex_shader = PipelineState::read_shader_code(Path::new("./bin/raster.vert.spv"))?;
+        let fragment_shader = PipelineState::read_shader_code(Path::new(fragment_path))?;
+
+        let vertex_module = PipelineState::create_shader_module(device, &vertex_shader)?;
+        let fragment_module = PipelineState::create_shader_module(device, &fragment_shader)?;
+
+        let set_layouts: Vec<_> = descriptor_set_layout.into_iter().collect();
+        let pipeline_layout = device.create_pipeline_layout(
+            &vk::PipelineLayoutCreateInfo::default()
+                .set_layouts(&set_layouts)
+                .push_constant_ranges(&[vk::PushConstantRange::default()
+                    .stage_flags(Self::PUSH_CONSTANT_STAGES)
+                    .offset(0)
+                    .size(mem::size_of::<RasterPushConstants>() as u32)]),
+            None,
+        )?;
+
+        // Only the attributes the vertex shader reads, which must be in the mesh layout
+        let (vertex_binding, vertex_attributes) =
+            vertex_input_descriptions(&ShaderReflection::new(&vertex_shader)?, &Mesh::LAYOUT, 0)?;
+
+        let color_formats = [color_format];
+        let mut rendering_info = vk::PipelineRenderingCreateInfo::default()
+            .color_attachment_formats(&color_formats)
+            .depth_attachment_format(depth_format);
+
+        let pipelines = device
+            .create_graphics_pipelines(
+                pipeline_cache,
+                &[vk::GraphicsPipelineCreateInfo::default()
+                    .push_next(&mut rendering_info)
+                    .stages(&[
+                        vk::PipelineShaderStageCreateInfo::default()
+                            .stage(vk::ShaderStageFlags::VERTEX)
+                            .module(vertex_module)
+                            .name(c"main"),
+                        vk::PipelineShaderStageCreateInfo::default()
+                            .stage(vk::ShaderStageFlags::FRAGMENT)
+                            .module(fragment_module)
+                            .name(c"main"),
+                    ])
+                    .vertex_input_state(
+                        &vk::PipelineVertexInputStateCreateInfo::default()
+                            .vertex_binding_descriptions(&[vertex_binding])
+                            .vertex_attribute_descriptions(&vertex_attributes),
+                    )
+                    .input_assembly_state(
+                        &vk::PipelineInputAssemblyStateCreateInfo::default()
+                            .topology(vk::PrimitiveTopology::TRIANGLE_LIST),
+                    )
+                    .viewport_state(
+                        &vk::PipelineViewportStateCreateInfo::default()
+                            .viewport_count(1)
+                            .scissor_count(1),
+                    )
+                    .rasterization_state(
+                        &vk::PipelineRasterizationStateCreateInfo::default()
+                            .polygon_mode(vk::PolygonMode::FILL)
+                            .cull_mode(vk::CullModeFlags::NONE)
+                            .front_face(vk::FrontFace::COUNTER_CLOCKWISE)
+                            .line_width(1.0),
+                    )
+                    .multisample_state(
+                        &vk::PipelineMultisampleStateCreateInfo::default()
+                            .rasterization_samples(samples),
+                    )
+                    .depth_stencil_state(
+                        &vk::PipelineDepthStencilStateCreateInfo::default()
+                            .depth_test_enable(true)
+                            .depth_write_enable(true)
+                            .depth_compare_op(vk::CompareOp::GREATER),
+                    )
+                    .color_blend_state(
+                        &vk::PipelineColorBlendStateCreateInfo::default()
+                            .attachments(&[vk::PipelineColorBlendAttachmentState::default()
+                                .color_write_mask(vk::ColorComponentFlags::RGBA)]),
+                    )
+                    .dynamic_state(
+                        &vk::PipelineDynamicStateCreateInfo::default().dynamic_states(&[
+                            vk::DynamicState::VIEWPORT,
+                            vk::DynamicState::SCISSOR,
+                        ]),
+                    )
+                    .layout(pipeline_layout)],
+                None,
+            )
+            .map_err(|(_, e)| e)?;
+
+        device.destroy_shader_module(vertex_module, None);
+        device.destroy_shader_module(fragment_module, None);
+        Ok((pipeline_layout, pipelines[0]))
+    }
+
+    unsafe fn destroy_pipeline(&self, device: &ash::Device) {
+        device.destroy_pipeline(self.pipeline, None);
+        device.destroy_pipeline_layout(self.pipeline_layout, None);
+    }
+
+    pub fn cleanup(&self, init_state: &InitState) {
+        unsafe {
+            self.destroy_pipeline(init_state.device());
+            if let Some(descriptor_set_layout) = self.descriptor_set_layout {
+                // Destroying the pool frees its sets
+                init_state
+                    .device()
+                    .destroy_descriptor_pool(self.descriptor_pool, None);
+                init_state
+                    .device()
+                    .destroy_descriptor_set_layout(descriptor_set_layout, None);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reflect(name: &str) -> ShaderReflection {
+        let code = PipelineState::read_shader_code(&Path::new("../bin").join(name)).unwrap();
+        ShaderReflection::new(&code).unwrap()
+    }
+
+    #[test]
+    fn compiled_shaders_fit_the_pipeline_layout() {
+        let vertex = reflect("raster.vert.spv");
+        assert_eq!(vertex.stage, vk::ShaderStageFlags::VERTEX);
+        let (binding, attributes) = vertex_input_descriptions(&vertex, &Mesh::LAYOUT, 0).unwrap();
+        assert_eq!(binding.stride, Mesh::LAYOUT.stride);
+        assert_eq!(attributes.len(), 1);
+
+        let flat = reflect("raster.frag.spv");
+        assert_eq!(flat.stage, vk::ShaderStageFlags::FRAGMENT);
+        assert!(flat.bindings.is_empty());
+        // The ray query shader reads the TLAS through the one binding the set layout has
+        let hybrid = reflect("hybrid.frag.spv");
+        let bindings: Vec<_> = hybrid
+            .bindings
+            .iter()
+            .map(|binding| (binding.set, binding.binding, binding.descriptor_type))
+            .collect();
+        assert_eq!(
+            bindings,
+            [(0, 0, vk::DescriptorType::ACCELERATION_STRUCTURE_KHR)]
+        );
+        for shader in [&vertex, &flat, &hybrid] {
+            assert!(shader.push_constant_size as usize <= mem::size_of::<RasterPushConstants>());
+        }
+    }
+}
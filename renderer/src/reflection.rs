@@ -0,0 +1,722 @@
+use std::collections::{BTreeMap, HashMap};
+
+use ash::vk;
+use data::vertex::VertexLayout;
+use thiserror::Error;
+
+use crate::buffer::vk_vertex_format;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ReflectionError {
+    #[error("not a SPIR-V module")]
+    NotSpirv,
+    #[error("instruction at word {0} runs past the end of the module")]
+    Truncated(usize),
+    #[error("module has no entry point")]
+    NoEntryPoint,
+    #[error("unsupported descriptor type for set {set} binding {binding}")]
+    UnsupportedDescriptor { set: u32, binding: u32 },
+    #[error("set {set} binding {binding} is a {first:?} in one stage and a {second:?} in another")]
+    ConflictingDescriptor {
+        set: u32,
+        binding: u32,
+        first: vk::DescriptorType,
+        second: vk::DescriptorType,
+    },
+    #[error("vertex input at location {0} isn't in the vertex layout")]
+    MissingVertexAttribute(u32),
+    #[error("vertex input at location {location} is a {shader:?} but the layout has {layout:?}")]
+    VertexFormatMismatch {
+        location: u32,
+        shader: vk::Format,
+        layout: vk::Format,
+    },
+}
+
+/// A descriptor a shader declares
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DescriptorBinding {
+    pub set: u32,
+    pub binding: u32,
+    pub descriptor_type: vk::DescriptorType,
+    pub count: u32,
+    pub stages: vk::ShaderStageFlags,
+}
+
+/// A vertex shader input. The format is what the shader reads, e.g. `R32G32B32A32_SFLOAT` for
+/// a `vec4`, which normalized or narrower attributes also convert to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VertexInput {
+    pub location: u32,
+    pub format: vk::Format,
+}
+
+/// The interface of one shader module: its stage, descriptors, push constants and, for
+/// vertex shaders, inputs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShaderReflection {
+    pub stage: vk::ShaderStageFlags,
+    pub bindings: Vec<DescriptorBinding>,
+    /// Bytes of the push constant block, 0 without one
+    pub push_constant_size: u32,
+    pub inputs: Vec<VertexInput>,
+}
+
+const MAGIC: u32 = 0x0723_0203;
+const HEADER_WORDS: usize = 5;
+
+mod op {
+    pub const ENTRY_POINT: u32 = 15;
+    pub const TYPE_BOOL: u32 = 20;
+    pub const TYPE_INT: u32 = 21;
+    pub const TYPE_FLOAT: u32 = 22;
+    pub const TYPE_VECTOR: u32 = 23;
+    pub const TYPE_MATRIX: u32 = 24;
+    pub const TYPE_IMAGE: u32 = 25;
+    pub const TYPE_SAMPLER: u32 = 26;
+    pub const TYPE_SAMPLED_IMAGE: u32 = 27;
+    pub const TYPE_ARRAY: u32 = 28;
+    pub const TYPE_RUNTIME_ARRAY: u32 = 29;
+    pub const TYPE_STRUCT: u32 = 30;
+    pub const TYPE_POINTER: u32 = 32;
+    pub const CONSTANT: u32 = 43;
+    pub const VARIABLE: u32 = 59;
+    pub const DECORATE: u32 = 71;
+    pub const MEMBER_DECORATE: u32 = 72;
+    pub const TYPE_ACCELERATION_STRUCTURE: u32 = 5341;
+}
+
+mod decoration {
+    pub const BLOCK: u32 = 2;
+    pub const BUFFER_BLOCK: u32 = 3;
+    pub const ARRAY_STRIDE: u32 = 6;
+    pub const MATRIX_STRIDE: u32 = 7;
+    pub const BUILT_IN: u32 = 11;
+    pub const LOCATION: u32 = 30;
+    pub const BINDING: u32 = 33;
+    pub const DESCRIPTOR_SET: u32 = 34;
+    pub const OFFSET: u32 = 35;
+}
+
+mod storage_class {
+    pub const UNIFORM_CONSTANT: u32 = 0;
+    pub const INPUT: u32 = 1;
+    pub const UNIFORM: u32 = 2;
+    pub const PUSH_CONSTANT: u32 = 9;
+    pub const STORAGE_BUFFER: u32 = 12;
+    pub const PHYSICAL_STORAGE_BUFFER: u32 = 5349;
+}
+
+/// `OpTypeImage`'s dimensionality for texel buffers and input attachments
+const DIM_BUFFER: u32 = 5;
+const DIM_SUBPASS_DATA: u32 = 6;
+/// `OpTypeImage`'s sampled operand for images used without a sampler
+const SAMPLED_STORAGE: u32 = 2;
+
+#[derive(Debug, Clone)]
+enum Type {
+    Bool,
+    Int { width: u32, signed: bool },
+    Float { width: u32 },
+    Vector { component: u32, count: u32 },
+    Matrix { column: u32, count: u32 },
+    Image { dim: u32, sampled: u32 },
+    Sampler,
+    SampledImage,
+    Array { element: u32, length: u32 },
+    RuntimeArray { element: u32 },
+    Struct { members: Vec<u32> },
+    Pointer { storage_class: u32, pointee: u32 },
+    AccelerationStructure,
+}
+
+fn execution_model_stage(model: u32) -> vk::ShaderStageFlags {
+    match model {
+        0 => vk::ShaderStageFlags::VERTEX,
+        1 => vk::ShaderStageFlags::TESSELLATION_CONTROL,
+        2 => vk::ShaderStageFlags::TESSELLATION_EVALUATION,
+        3 => vk::ShaderStageFlags::GEOMETRY,
+        4 => vk::ShaderStageFlags::FRAGMENT,
+        5 => vk::ShaderStageFlags::COMPUTE,
+        5313 => vk::ShaderStageFlags::RAYGEN_KHR,
+        5314 => vk::ShaderStageFlags::INTERSECTION_KHR,
+        5315 => vk::ShaderStageFlags::ANY_HIT_KHR,
+        5316 => vk::ShaderStageFlags::CLOSEST_HIT_KHR,
+        5317 => vk::ShaderStageFlags::MISS_KHR,
+        5318 => vk::ShaderStageFlags::CALLABLE_KHR,
+        5364 => vk::ShaderStageFlags::TASK_EXT,
+        5365 => vk::ShaderStageFlags::MESH_EXT,
+        _ => vk::ShaderStageFlags::empty(),
+    }
+}
+
+/// Everything the reflection needs from a module, indexed by result id
+#[derive(Default)]
+struct Module {
+    stage: vk::ShaderStageFlags,
+    types: HashMap<u32, Type>,
+    constants: HashMap<u32, u32>,
+    /// Result type and storage class of each global variable, in declaration order
+    variables: Vec<(u32, u32, u32)>,
+    decorations: HashMap<(u32, u32), u32>,
+    member_decorations: HashMap<(u32, u32, u32), u32>,
+}
+
+impl Module {
+    fn parse(code: &[u32]) -> Result<Self, ReflectionError> {
+        if code.len() < HEADER_WORDS || code[0] != MAGIC {
+            return Err(ReflectionError::NotSpirv);
+        }
+        let mut module = Self::default();
+        let mut entry_point = false;
+        let mut position = HEADER_WORDS;
+        while position < code.len() {
+            let word_count = (code[position] >> 16) as usize;
+            let opcode = code[position] & 0xffff;
+            if word_count == 0 || position + word_count > code.len() {
+                return Err(ReflectionError::Truncated(position));
+            }
+            let operands = &code[position + 1..position + word_count];
+            let operand = |index: usize| operands.get(index).copied().unwrap_or_default();
+            match opcode {
+                // Each module is expected to hold one stage's entry point
+                op::ENTRY_POINT if !entry_point => {
+                    module.stage = execution_model_stage(operand(0));
+                    entry_point = true;
+                }
+                op::TYPE_BOOL => {
+                    module.types.insert(operand(0), Type::Bool);
+                }
+                op::TYPE_INT => {
+                    module.types.insert(
+                        operand(0),
+                        Type::Int {
+                            width: operand(1),
+                            signed: operand(2) != 0,
+                        },
+                    );
+                }
+                op::TYPE_FLOAT => {
+                    module
+                        .types
+                        .insert(operand(0), Type::Float { width: operand(1) });
+                }
+                op::TYPE_VECTOR => {
+                    module.types.insert(
+                        operand(0),
+                        Type::Vector {
+                            component: operand(1),
+                            count: operand(2),
+                        },
+                    );
+                }
+                op::TYPE_MATRIX => {
+                    module.types.insert(
+                        operand(0),
+                        Type::Matrix {
+                            column: operand(1),
+                            count: operand(2),
+                        },
+                    );
+                }
+                op::TYPE_IMAGE => {
+                    module.types.insert(
+                        operand(0),
+                        Type::Image {
+                            dim: operand(2),
+                            sampled: operand(6),
+                        },
+                    );
+                }
+                op::TYPE_SAMPLER => {
+                    module.types.insert(operand(0), Type::Sampler);
+                }
+                op::TYPE_SAMPLED_IMAGE => {
+                    module.types.insert(operand(0), Type::SampledImage);
+                }
+                op::TYPE_ARRAY => {
+                    module.types.insert(
+                        operand(0),
+                        Type::Array {
+                            element: operand(1),
+                            length: operand(2),
+                        },
+                    );
+                }
+                op::TYPE_RUNTIME_ARRAY => {
+                    module.types.insert(
+                        operand(0),
+                        Type::RuntimeArray {
+                            element: operand(1),
+                        },
+                    );
+                }
+                op::TYPE_STRUCT => {
+                    module.types.insert(
+                        operand(0),
+                        Type::Struct {
+                            members: operands[1..].to_vec(),
+                        },
+                    );
+                }
+                op::TYPE_POINTER => {
+                    module.types.insert(
+                        operand(0),
+                        Type::Pointer {
+                            storage_class: operand(1),
+                            pointee: operand(2),
+                        },
+                    );
+                }
+                op::TYPE_ACCELERATION_STRUCTURE => {
+                    module.types.insert(operand(0), Type::AccelerationStructure);
+                }
+                // Only the low word matters for array lengths
+                op::CONSTANT => {
+                    module.constants.insert(operand(1), operand(2));
+                }
+                op::VARIABLE => {
+                    module.variables.push((operand(0), operand(1), operand(2)));
+                }
+                op::DECORATE => {
+                    module
+                        .decorations
+                        .insert((operand(0), operand(1)), operand(2));
+                }
+                op::MEMBER_DECORATE => {
+                    module
+                        .member_decorations
+                        .insert((operand(0), operand(1), operand(2)), operand(3));
+                }
+                _ => (),
+            }
+            position += word_count;
+        }
+        if !entry_point {
+            return Err(ReflectionError::NoEntryPoint);
+        }
+        Ok(module)
+    }
+
+    fn decoration(&self, id: u32, decoration: u32) -> Option<u32> {
+        self.decorations.get(&(id, decoration)).copied()
+    }
+
+    /// Bytes `id` takes in a buffer laid out by its offset and stride decorations
+    fn size(&self, id: u32) -> u32 {
+        match self.types.get(&id) {
+            Some(Type::Bool) => 4,
+            Some(Type::Int { width, .. } | Type::Float { width }) => width / 8,
+            Some(Type::Vector { component, count }) => self.size(*component) * count,
+            // Without a stride, e.g. in a member that has one of its own
+            Some(Type::Matrix { column, count }) => self.size(*column) * count,
+            Some(Type::Array { element, length }) => {
+                let length = self.constants.get(length).copied().unwrap_or_default();
+                let stride = self
+                    .decoration(id, decoration::ARRAY_STRIDE)
+                    .unwrap_or_else(|| self.size(*element));
+                stride * length
+            }
+            Some(Type::Struct { members }) => members
+                .iter()
+                .enumerate()
+                .map(|(index, member)| {
+                    let offset = self
+                        .member_decorations
+                        .get(&(id, index as u32, decoration::OFFSET))
+                        .copied()
+                        .unwrap_or_default();
+                    let size = match (
+                        self.types.get(member),
+                        self.member_decorations
+                            .get(&(id, index as u32, decoration::MATRIX_STRIDE)),
+                    ) {
+                        (Some(Type::Matrix { count, .. }), Some(stride)) => stride * count,
+                        _ => self.size(*member),
+                    };
+                    offset + size
+                })
+                .max()
+                .unwrap_or_default(),
+            Some(Type::Pointer {
+                storage_class: storage_class::PHYSICAL_STORAGE_BUFFER,
+                ..
+            }) => 8,
+            _ => 0,
+        }
+    }
+
+    /// The descriptor a resource variable of type `pointee` binds, with its array length
+    fn descriptor(&self, pointee: u32, storage_class: u32) -> Option<(vk::DescriptorType, u32)> {
+        let (element, count) = match self.types.get(&pointee)? {
+            Type::Array { element, length } => {
+                (*element, self.constants.get(length).copied().unwrap_or(1))
+            }
+            // Sized when the descriptor set is allocated
+            Type::RuntimeArray { element } => (*element, 0),
+            _ => (pointee, 1),
+        };
+        let descriptor_type = match self.types.get(&element)? {
+            Type::AccelerationStructure => vk::DescriptorType::ACCELERATION_STRUCTURE_KHR,
+            Type::Sampler => vk::DescriptorType::SAMPLER,
+            Type::SampledImage => vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+            Type::Image { dim, sampled } => match (*dim, *sampled == SAMPLED_STORAGE) {
+                (DIM_BUFFER, true) => vk::DescriptorType::STORAGE_TEXEL_BUFFER,
+                (DIM_BUFFER, false) => vk::DescriptorType::UNIFORM_TEXEL_BUFFER,
+                (DIM_SUBPASS_DATA, _) => vk::DescriptorType::INPUT_ATTACHMENT,
+                (_, true) => vk::DescriptorType::STORAGE_IMAGE,
+                (_, false) => vk::DescriptorType::SAMPLED_IMAGE,
+            },
+            Type::Struct { .. } => match storage_class {
+                storage_class::STORAGE_BUFFER => vk::DescriptorType::STORAGE_BUFFER,
+                // Older modules declare storage buffers as uniform buffer blocks
+                storage_class::UNIFORM
+                    if self.decoration(element, decoration::BUFFER_BLOCK).is_some() =>
+                {
+                    vk::DescriptorType::STORAGE_BUFFER
+                }
+                storage_class::UNIFORM if self.decoration(element, decoration::BLOCK).is_some() => {
+                    vk::DescriptorType::UNIFORM_BUFFER
+                }
+                _ => return None,
+            },
+            _ => return None,
+        };
+        Some((descriptor_type, count))
+    }
+
+    /// The format a vertex input of type `id` is read as
+    fn input_format(&self, id: u32) -> Option<vk::Format> {
+        let (component, count) = match self.types.get(&id)? {
+            Type::Vector { component, count } => (*component, *count),
+            _ => (id, 1),
+        };
+        let formats = match self.types.get(&component)? {
+            Type::Float { width: 32 } => [
+                vk::Format::R32_SFLOAT,
+                vk::Format::R32G32_SFLOAT,
+                vk::Format::R32G32B32_SFLOAT,
+                vk::Format::R32G32B32A32_SFLOAT,
+            ],
+            Type::Int {
+                width: 32,
+                signed: true,
+            } => [
+                vk::Format::R32_SINT,
+                vk::Format::R32G32_SINT,
+                vk::Format::R32G32B32_SINT,
+                vk::Format::R32G32B32A32_SINT,
+            ],
+            Type::Int {
+                width: 32,
+                signed: false,
+            } => [
+                vk::Format::R32_UINT,
+                vk::Format::R32G32_UINT,
+                vk::Format::R32G32B32_UINT,
+                vk::Format::R32G32B32A32_UINT,
+            ],
+            _ => return None,
+        };
+        formats.get(count.checked_sub(1)? as usize).copied()
+    }
+}
+
+impl ShaderReflection {
+    pub fn new(code: &[u32]) -> Result<Self, ReflectionError> {
+        let module = Module::parse(code)?;
+        let mut bindings = Vec::new();
+        let mut push_constant_size = 0;
+        let mut inputs = Vec::new();
+        for &(result_type, id, storage_class) in &module.variables {
+            let Some(Type::Pointer { pointee, .. }) = module.types.get(&result_type) else {
+                continue;
+            };
+            match storage_class {
+                storage_class::UNIFORM_CONSTANT
+                | storage_class::UNIFORM
+                | storage_class::STORAGE_BUFFER => {
+                    let (Some(set), Some(binding)) = (
+                        module.decoration(id, decoration::DESCRIPTOR_SET),
+                        module.decoration(id, decoration::BINDING),
+                    ) else {
+                        continue;
+                    };
+                    let (descriptor_type, count) = module
+                        .descriptor(*pointee, storage_class)
+                        .ok_or(ReflectionError::UnsupportedDescriptor { set, binding })?;
+                    bindings.push(DescriptorBinding {
+                        set,
+                        binding,
+                        descriptor_type,
+                        count,
+                        stages: module.stage,
+                    });
+                }
+                storage_class::PUSH_CONSTANT => {
+                    push_constant_size = push_constant_size.max(module.size(*pointee));
+                }
+                storage_class::INPUT if module.stage == vk::ShaderStageFlags::VERTEX => {
+                    // Built-ins such as `gl_VertexIndex` don't come from a vertex buffer
+                    if module.decoration(id, decoration::BUILT_IN).is_some() {
+                        continue;
+                    }
+                    let (Some(location), Some(format)) = (
+                        module.decoration(id, decoration::LOCATION),
+                        module.input_format(*pointee),
+                    ) else {
+                        continue;
+                    };
+                    inputs.push(VertexInput { location, format });
+                }
+                _ => (),
+            }
+        }
+        bindings.sort_by_key(|binding| (binding.set, binding.binding));
+        inputs.sort_by_key(|input| input.location);
+        Ok(Self {
+            stage: module.stage,
+            bindings,
+            push_constant_size,
+            inputs,
+        })
+    }
+}
+
+/// The interface of a pipeline's stages together: each descriptor with every stage using it,
+/// and one push constant range covering every stage's block
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PipelineReflection {
+    bindings: BTreeMap<(u32, u32), DescriptorBinding>,
+    push_constant_size: u32,
+    push_constant_stages: vk::ShaderStageFlags,
+}
+
+impl PipelineReflection {
+    pub fn new<'a>(
+        shaders: impl IntoIterator<Item = &'a ShaderReflection>,
+    ) -> Result<Self, ReflectionError> {
+        let mut reflection = Self::default();
+        for shader in shaders {
+            for binding in &shader.bindings {
+                let key = (binding.set, binding.binding);
+                match reflection.bindings.get_mut(&key) {
+                    Some(existing) if existing.descriptor_type != binding.descriptor_type => {
+                        return Err(ReflectionError::ConflictingDescriptor {
+                            set: binding.set,
+                            binding: binding.binding,
+                            first: existing.descriptor_type,
+                            second: binding.descriptor_type,
+                        });
+                    }
+                    Some(existing) => {
+                        existing.stages |= binding.stages;
+                        existing.count = existing.count.max(binding.count);
+                    }
+                    None => {
+                        reflection.bindings.insert(key, *binding);
+                    }
+                }
+            }
+            if shader.push_constant_size > 0 {
+                reflection.push_constant_size =
+                    reflection.push_constant_size.max(shader.push_constant_size);
+                reflection.push_constant_stages |= shader.stage;
+            }
+        }
+        Ok(reflection)
+    }
+
+    /// Binds the uniform buffer at `set` and `binding` with a dynamic offset, which SPIR-V
+    /// can't express
+    pub fn with_dynamic_offset(mut self, set: u32, binding: u32) -> Self {
+        if let Some(binding) = self.bindings.get_mut(&(set, binding)) {
+            if binding.descriptor_type == vk::DescriptorType::UNIFORM_BUFFER {
+                binding.descriptor_type = vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC;
+            }
+        }
+        self
+    }
+
+    pub fn bindings(&self, set: u32) -> impl Iterator<Item = &DescriptorBinding> {
+        self.bindings
+            .range((set, 0)..=(set, u32::MAX))
+            .map(|(_, binding)| binding)
+    }
+
+    /// Whether a stage uses `binding` of `set`. Writing a descriptor the layout doesn't have is
+    /// invalid, so writes of descriptors the shaders no longer use are skipped with this.
+    pub fn contains(&self, set: u32, binding: u32) -> bool {
+        self.bindings.contains_key(&(set, binding))
+    }
+
+    pub fn set_layout_bindings(&self, set: u32) -> Vec<vk::DescriptorSetLayoutBinding<'static>> {
+        self.bindings(set)
+            .map(|binding| {
+                vk::DescriptorSetLayoutBinding::default()
+                    .binding(binding.binding)
+                    .descriptor_type(binding.descriptor_type)
+                    .descriptor_count(binding.count)
+                    .stage_flags(binding.stages)
+            })
+            .collect()
+    }
+
+    /// Enough descriptors for `sets` copies of `set`
+    pub fn pool_sizes(&self, set: u32, sets: u32) -> Vec<vk::DescriptorPoolSize> {
+        let mut counts: Vec<(vk::DescriptorType, u32)> = Vec::new();
+        for binding in self.bindings(set) {
+            match counts
+                .iter_mut()
+                .find(|(ty, _)| *ty == binding.descriptor_type)
+            {
+                Some((_, count)) => *count += binding.count,
+                None => counts.push((binding.descriptor_type, binding.count)),
+            }
+        }
+        counts
+            .into_iter()
+            .map(|(ty, count)| {
+                vk::DescriptorPoolSize::default()
+                    .ty(ty)
+                    .descriptor_count(count * sets)
+            })
+            .collect()
+    }
+
+    pub fn push_constant_ranges(&self) -> Vec<vk::PushConstantRange> {
+        if self.push_constant_size == 0 {
+            return Vec::new();
+        }
+        vec![vk::PushConstantRange::default()
+            .stage_flags(self.push_constant_stages)
+            .offset(0)
+            .size(self.push_constant_size)]
+    }
+}
+
+/// The attributes of `layout` that `vertex_shader` reads, from vertex buffer `binding`. Fails
+/// if the shader reads a location the layout doesn't have or in a different kind of format.
+pub fn vertex_input_descriptions(
+    vertex_shader: &ShaderReflection,
+    layout: &VertexLayout,
+    binding: u32,
+) -> Result<
+    (
+        vk::VertexInputBindingDescription,
+        Vec<vk::VertexInputAttributeDescription>,
+    ),
+    ReflectionError,
+> {
+    let attributes = vertex_shader
+        .inputs
+        .iter()
+        .map(|input| {
+            let attribute = layout
+                .attributes
+                .iter()
+                .find(|attribute| attribute.location == input.location)
+                .ok_or(ReflectionError::MissingVertexAttribute(input.location))?;
+            let format = vk_vertex_format(attribute.format);
+            if numeric_kind(format) != numeric_kind(input.format) {
+                return Err(ReflectionError::VertexFormatMismatch {
+                    location: input.location,
+                    shader: input.format,
+                    layout: format,
+                });
+            }
+            Ok(vk::VertexInputAttributeDescription {
+                location: attribute.location,
+                binding,
+                format,
+                offset: attribute.offset,
+            })
+        })
+        .collect::<Result<_, _>>()?;
+    Ok((
+        vk::VertexInputBindingDescription {
+            binding,
+            stride: layout.stride,
+            input_rate: vk::VertexInputRate::VERTEX,
+        },
+        attributes,
+    ))
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum NumericKind {
+    Float,
+    Sint,
+    Uint,
+}
+
+/// Floats, normalized formats included, can only feed float inputs, and integers inputs of
+/// the same signedness
+fn numeric_kind(format: vk::Format) -> NumericKind {
+    match format {
+        vk::Format::R8G8B8A8_UINT
+        | vk::Format::R32_UINT
+        | vk::Format::R32G32_UINT
+        | vk::Format::R32G32B32_UINT
+        | vk::Format::R32G32B32A32_UINT => NumericKind::Uint,
+        vk::Format::R8G8B8A8_SINT
+        | vk::Format::R32_SINT
+        | vk::Format::R32G32_SINT
+        | vk::Format::R32G32B32_SINT
+        | vk::Format::R32G32B32A32_SINT => NumericKind::Sint,
+        _ => NumericKind::Float,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::pipeline_state::PipelineState;
+
+    #[test]
+    fn bindings_come_from_the_compiled_shaders() {
+        let read =
+            |name: &str| PipelineState::read_shader_code(&Path::new("../bin").join(name)).unwrap();
+        let code = read("raygen.rgen.spv");
+        let raygen = ShaderReflection::new(&code).unwrap();
+        assert_eq!(raygen.stage, vk::ShaderStageFlags::RAYGEN_KHR);
+        let miss = ShaderReflection::new(&read("miss.rmiss.spv")).unwrap();
+        let closest_hit = ShaderReflection::new(&read("closesthit.rchit.spv")).unwrap();
+
+        let reflection = PipelineReflection::new([&raygen, &miss, &closest_hit])
+            .unwrap()
+            .with_dynamic_offset(0, 2);
+        let types: Vec<_> = reflection
+            .bindings(0)
+            .map(|binding| (binding.binding, binding.descriptor_type))
+            .collect();
+        assert_eq!(
+            types,
+            [
+                (0, vk::DescriptorType::ACCELERATION_STRUCTURE_KHR),
+                (1, vk::DescriptorType::STORAGE_IMAGE),
+                (2, vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC),
+                (3, vk::DescriptorType::STORAGE_BUFFER),
+                (6, vk::DescriptorType::STORAGE_IMAGE),
+                (7, vk::DescriptorType::STORAGE_BUFFER),
+                (8, vk::DescriptorType::STORAGE_BUFFER),
+                (9, vk::DescriptorType::STORAGE_IMAGE),
+                (10, vk::DescriptorType::STORAGE_BUFFER),
+                (11, vk::DescriptorType::STORAGE_IMAGE),
+            ]
+        );
+        assert!(!reflection.contains(0, 4));
+        // The block every stage shares is exactly what the renderer pushes
+        assert_eq!(
+            reflection.push_constant_ranges()[0].size as usize,
+            std::mem::size_of::<crate::PushConstants>()
+        );
+
+        assert_eq!(
+            ShaderReflection::new(&code[..3]),
+            Err(ReflectionError::NotSpirv)
+        );
+    }
+}
@@ -0,0 +1,850 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    ffi::CString,
+};
+
+use ash::{ext::debug_utils, prelude::VkResult, vk};
+
+use crate::{
+    breadcrumbs::Breadcrumbs,
+    pass_timer::PassTimer,
+    transient_images::{TransientImageDesc, TransientImages},
+};
+
+/// How a pass (or the outside world) uses an image: the layout it must be in, and the
+/// stage/access pair that has to be synchronized against
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageState {
+    pub layout: vk::ImageLayout,
+    pub stage: vk::PipelineStageFlags,
+    pub access: vk::AccessFlags,
+}
+
+impl ImageState {
+    pub const fn new(
+        layout: vk::ImageLayout,
+        stage: vk::PipelineStageFlags,
+        access: vk::AccessFlags,
+    ) -> Self {
+        Self {
+            layout,
+            stage,
+            access,
+        }
+    }
+
+    /// Contents are discarded on the first transition
+    pub const UNDEFINED: Self = Self::new(
+        vk::ImageLayout::UNDEFINED,
+        vk::PipelineStageFlags::TOP_OF_PIPE,
+        vk::AccessFlags::NONE,
+    );
+
+    pub const PRESENT: Self = Self::new(
+        vk::ImageLayout::PRESENT_SRC_KHR,
+        vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+        vk::AccessFlags::NONE,
+    );
+
+    pub const TRANSFER_SRC: Self = Self::new(
+        vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+        vk::PipelineStageFlags::TRANSFER,
+        vk::AccessFlags::TRANSFER_READ,
+    );
+
+    pub const TRANSFER_DST: Self = Self::new(
+        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+        vk::PipelineStageFlags::TRANSFER,
+        vk::AccessFlags::TRANSFER_WRITE,
+    );
+
+    pub const COLOR_ATTACHMENT: Self = Self::new(
+        vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+        vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+        vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+    );
+
+    pub const DEPTH_ATTACHMENT: Self = Self::new(
+        vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+        vk::PipelineStageFlags::from_raw(
+            vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS.as_raw()
+                | vk::PipelineStageFlags::LATE_FRAGMENT_TESTS.as_raw(),
+        ),
+        vk::AccessFlags::from_raw(
+            vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ.as_raw()
+                | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE.as_raw(),
+        ),
+    );
+
+    pub const fn storage_read(stage: vk::PipelineStageFlags) -> Self {
+        Self::new(
+            vk::ImageLayout::GENERAL,
+            stage,
+            vk::AccessFlags::SHADER_READ,
+        )
+    }
+
+    pub const fn storage_write(stage: vk::PipelineStageFlags) -> Self {
+        Self::new(
+            vk::ImageLayout::GENERAL,
+            stage,
+            vk::AccessFlags::SHADER_WRITE,
+        )
+    }
+
+    pub const fn storage_read_write(stage: vk::PipelineStageFlags) -> Self {
+        Self::new(
+            vk::ImageLayout::GENERAL,
+            stage,
+            vk::AccessFlags::from_raw(
+                vk::AccessFlags::SHADER_READ.as_raw() | vk::AccessFlags::SHADER_WRITE.as_raw(),
+            ),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BufferState {
+    pub stage: vk::PipelineStageFlags,
+    pub access: vk::AccessFlags,
+}
+
+impl BufferState {
+    pub const fn new(stage: vk::PipelineStageFlags, access: vk::AccessFlags) -> Self {
+        Self { stage, access }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ImageHandle(usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BufferHandle(usize);
+
+/// Either kind of handle, for ordering passes by what they share
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Resource {
+    Image(ImageHandle),
+    Buffer(BufferHandle),
+}
+
+struct GraphImage {
+    /// Null for transient images until the graph is executed
+    image: vk::Image,
+    view: vk::ImageView,
+    aspect_mask: vk::ImageAspectFlags,
+    initial: ImageState,
+    final_state: Option<ImageState>,
+    transient: Option<TransientImageDesc>,
+}
+
+struct GraphBuffer {
+    buffer: vk::Buffer,
+    initial: BufferState,
+    final_state: Option<BufferState>,
+}
+
+type RecordFn<'a> = Box<dyn FnOnce(vk::CommandBuffer, &PassResources) + 'a>;
+
+struct Pass<'a> {
+    name: &'static str,
+    images: Vec<(ImageHandle, ImageState)>,
+    buffers: Vec<(BufferHandle, BufferState)>,
+    record: RecordFn<'a>,
+}
+
+/// Declares the images and buffers a pass touches
+#[derive(Default)]
+pub struct PassBuilder {
+    images: Vec<(ImageHandle, ImageState)>,
+    buffers: Vec<(BufferHandle, BufferState)>,
+}
+
+impl PassBuilder {
+    pub fn image(&mut self, image: ImageHandle, state: ImageState) -> &mut Self {
+        self.images.push((image, state));
+        self
+    }
+
+    pub fn buffer(&mut self, buffer: BufferHandle, state: BufferState) -> &mut Self {
+        self.buffers.push((buffer, state));
+        self
+    }
+}
+
+/// The graph's images as they are when passes are recorded, including the transient images
+/// created for the frame
+pub struct PassResources {
+    images: Vec<(vk::Image, vk::ImageView)>,
+}
+
+impl PassResources {
+    pub fn image(&self, handle: ImageHandle) -> vk::Image {
+        self.images[handle.0].0
+    }
+
+    /// Null for imported images, which come with their own views
+    pub fn view(&self, handle: ImageHandle) -> vk::ImageView {
+        self.images[handle.0].1
+    }
+}
+
+/// What is written around each pass besides debug labels, into `current_frame`'s slots
+#[derive(Default)]
+pub struct PassMarkers<'a> {
+    pub timer: Option<&'a mut PassTimer>,
+    pub breadcrumbs: Option<&'a mut Breadcrumbs>,
+    pub current_frame: u8,
+}
+
+/// Barriers recorded before a pass runs
+#[derive(Debug, Default)]
+pub struct PassBarriers {
+    pub src_stage: vk::PipelineStageFlags,
+    pub dst_stage: vk::PipelineStageFlags,
+    pub image_barriers: Vec<vk::ImageMemoryBarrier<'static>>,
+    pub buffer_barriers: Vec<vk::BufferMemoryBarrier<'static>>,
+}
+
+impl PassBarriers {
+    pub fn is_empty(&self) -> bool {
+        self.image_barriers.is_empty() && self.buffer_barriers.is_empty()
+    }
+
+    unsafe fn record(&self, device: &ash::Device, command_buffer: vk::CommandBuffer) {
+        if self.is_empty() {
+            return;
+        }
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            self.src_stage,
+            self.dst_stage,
+            vk::DependencyFlags::empty(),
+            &[],
+            &self.buffer_barriers,
+            &self.image_barriers,
+        );
+    }
+}
+
+/// Execution order and the barriers derived for it
+#[derive(Debug, Default)]
+pub struct CompiledGraph {
+    pub order: Vec<usize>,
+    /// `barriers[i]` precedes the pass `order[i]`
+    pub barriers: Vec<PassBarriers>,
+    /// Transitions to each resource's final state after the last pass
+    pub final_barriers: PassBarriers,
+    /// Memory slot of each transient image, by image handle. Images sharing a slot are never
+    /// used by overlapping passes, so they can alias the same memory.
+    pub memory_slots: Vec<Option<usize>>,
+}
+
+/// A single-frame graph of passes. Passes declare what they read and write, and the graph
+/// orders them so each runs after the passes producing what it reads, then derives every layout
+/// transition and memory barrier between them.
+#[derive(Default)]
+pub struct RenderGraph<'a> {
+    images: Vec<GraphImage>,
+    buffers: Vec<GraphBuffer>,
+    passes: Vec<Pass<'a>>,
+}
+
+impl<'a> RenderGraph<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers an image owned outside the graph. `final_state` is the state it is left in
+    /// after the frame, or `None` to leave it wherever the last pass put it.
+    pub fn import_image(
+        &mut self,
+        image: vk::Image,
+        aspect_mask: vk::ImageAspectFlags,
+        initial: ImageState,
+        final_state: Option<ImageState>,
+    ) -> ImageHandle {
+        self.images.push(GraphImage {
+            image,
+            view: vk::ImageView::null(),
+            aspect_mask,
+            initial,
+            final_state,
+            transient: None,
+        });
+        ImageHandle(self.images.len() - 1)
+    }
+
+    /// Registers an image that only lives for this frame. It starts out undefined and its
+    /// contents are dropped after its last pass, so it may share memory with other transient
+    /// images; passes get its handles from [`PassResources`].
+    pub fn create_image(&mut self, desc: TransientImageDesc) -> ImageHandle {
+        self.images.push(GraphImage {
+            image: vk::Image::null(),
+            view: vk::ImageView::null(),
+            aspect_mask: desc.aspect_mask,
+            initial: ImageState::UNDEFINED,
+            final_state: None,
+            transient: Some(desc),
+        });
+        ImageHandle(self.images.len() - 1)
+    }
+
+    /// Registers a buffer owned outside the graph. `final_state` makes the last pass's writes
+    /// available to it, e.g. `HOST_READ` for readback.
+    pub fn import_buffer(
+        &mut self,
+        buffer: vk::Buffer,
+        initial: BufferState,
+        final_state: Option<BufferState>,
+    ) -> BufferHandle {
+        self.buffers.push(GraphBuffer {
+            buffer,
+            initial,
+            final_state,
+        });
+        BufferHandle(self.buffers.len() - 1)
+    }
+
+    pub fn add_pass(
+        &mut self,
+        name: &'static str,
+        setup: impl FnOnce(&mut PassBuilder),
+        record: impl FnOnce(vk::CommandBuffer, &PassResources) + 'a,
+    ) {
+        let mut builder = PassBuilder::default();
+        setup(&mut builder);
+        self.passes.push(Pass {
+            name,
+            images: builder.images,
+            buffers: builder.buffers,
+            record: Box::new(record),
+        });
+    }
+
+    pub fn pass_names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.passes.iter().map(|pass| pass.name)
+    }
+
+    pub fn compile(&self) -> CompiledGraph {
+        let order = self.execution_order();
+        let memory_slots = self.memory_slots(&order);
+
+        let mut image_states: Vec<_> = self.images.iter().map(|image| image.initial).collect();
+        let mut buffer_states: Vec<_> = self.buffers.iter().map(|buffer| buffer.initial).collect();
+        let slot_count = memory_slots
+            .iter()
+            .flatten()
+            .max()
+            .map_or(0, |slot| slot + 1);
+        let mut slot_occupants: Vec<Option<ImageHandle>> = vec![None; slot_count];
+
+        let barriers = order
+            .iter()
+            .map(|&pass_index| {
+                let pass = &self.passes[pass_index];
+                let mut barriers = PassBarriers::default();
+                for &(handle, _) in &pass.images {
+                    let Some(slot) = memory_slots[handle.0] else {
+                        continue;
+                    };
+                    if slot_occupants[slot] == Some(handle) {
+                        continue;
+                    }
+                    // First use of an aliasing image: its memory was last accessed through the
+                    // previous occupant, so the transition out of UNDEFINED waits on that
+                    if let Some(previous) = slot_occupants[slot].replace(handle) {
+                        image_states[handle.0] = ImageState {
+                            layout: vk::ImageLayout::UNDEFINED,
+                            ..image_states[previous.0]
+                        };
+                    }
+                }
+                for &(handle, state) in &pass.images {
+                    self.transition_image(&mut barriers, &mut image_states, handle, state);
+                }
+                for &(handle, state) in &pass.buffers {
+                    self.transition_buffer(&mut barriers, &mut buffer_states, handle, state);
+                }
+                barriers
+            })
+            .collect();
+
+        let mut final_barriers = PassBarriers::default();
+        for (index, image) in self.images.iter().enumerate() {
+            if let Some(final_state) = image.final_state {
+                self.transition_image(
+                    &mut final_barriers,
+                    &mut image_states,
+                    ImageHandle(index),
+                    final_state,
+                );
+            }
+        }
+        for (index, buffer) in self.buffers.iter().enumerate() {
+            if let Some(final_state) = buffer.final_state {
+                self.transition_buffer(
+                    &mut final_barriers,
+                    &mut buffer_states,
+                    BufferHandle(index),
+                    final_state,
+                );
+            }
+        }
+
+        CompiledGraph {
+            order,
+            barriers,
+            final_barriers,
+            memory_slots,
+        }
+    }
+
+    /// Records every pass with its barriers into `command_buffer`. Transient images come from
+    /// `transient_images`, which the GPU must be done with, e.g. the frame's own pool after its
+    /// fence has signaled. With `debug_labels`, each pass is labeled with its name so frame
+    /// captures show the graph's structure, and `markers` are written around each pass.
+    pub fn execute(
+        mut self,
+        device: &ash::Device,
+        debug_labels: Option<&debug_utils::Device>,
+        transient_images: &mut TransientImages,
+        command_buffer: vk::CommandBuffer,
+        markers: PassMarkers,
+    ) -> VkResult<()> {
+        let memory_slots = self.memory_slots(&self.execution_order());
+        let transients: Vec<_> = self
+            .images
+            .iter()
+            .zip(&memory_slots)
+            .enumerate()
+            .filter_map(|(index, (image, slot))| Some((index, (image.transient?, (*slot)?))))
+            .collect();
+        let requests: Vec<_> = transients.iter().map(|(_, request)| *request).collect();
+        let realized = transient_images.realize(device, &requests)?;
+        for ((index, _), transient) in transients.iter().zip(realized) {
+            self.images[*index].image = transient.image;
+            self.images[*index].view = transient.view;
+        }
+
+        let compiled = self.compile();
+        let resources = PassResources {
+            images: self
+                .images
+                .iter()
+                .map(|image| (image.image, image.view))
+                .collect(),
+        };
+        let mut passes: Vec<_> = self.passes.into_iter().map(Some).collect();
+
+        unsafe {
+            let PassMarkers {
+                mut timer,
+                mut breadcrumbs,
+                current_frame,
+            } = markers;
+            if let Some(timer) = &mut timer {
+                timer.begin(device, command_buffer, current_frame);
+            }
+            if let Some(breadcrumbs) = &mut breadcrumbs {
+                breadcrumbs.begin(command_buffer, current_frame);
+            }
+            for (&pass_index, barriers) in compiled.order.iter().zip(&compiled.barriers) {
+                let pass = passes[pass_index].take().unwrap();
+                if let Some(debug_labels) = debug_labels {
+                    let name = CString::new(pass.name).unwrap_or_default();
+                    debug_labels.cmd_begin_debug_utils_label(
+                        command_buffer,
+                        &vk::DebugUtilsLabelEXT::default().label_name(&name),
+                    );
+                }
+                if let Some(breadcrumbs) = &mut breadcrumbs {
+                    breadcrumbs.start_pass(command_buffer, current_frame, pass.name);
+                }
+                barriers.record(device, command_buffer);
+                (pass.record)(command_buffer, &resources);
+                if let Some(timer) = &mut timer {
+                    timer.end_pass(device, command_buffer, current_frame, pass.name);
+                }
+                if let Some(breadcrumbs) = &mut breadcrumbs {
+                    breadcrumbs.end_pass(command_buffer, current_frame, pass.name);
+                }
+                if let Some(debug_labels) = debug_labels {
+                    debug_labels.cmd_end_debug_utils_label(command_buffer);
+                }
+            }
+            compiled.final_barriers.record(device, command_buffer);
+        }
+        Ok(())
+    }
+
+    /// Memory slot of each transient image, from the first and last position in `order` using
+    /// it
+    fn memory_slots(&self, order: &[usize]) -> Vec<Option<usize>> {
+        let mut lifetimes = vec![None; self.images.len()];
+        for (position, &pass_index) in order.iter().enumerate() {
+            for &(handle, _) in &self.passes[pass_index].images {
+                if self.images[handle.0].transient.is_some() {
+                    let lifetime = lifetimes[handle.0].get_or_insert((position, position));
+                    lifetime.1 = position;
+                }
+            }
+        }
+        assign_memory_slots(&lifetimes)
+    }
+
+    /// Orders passes from producer to consumer, preferring declaration order between
+    /// independent ones. A pass reading a resource reads what the last writer declared before
+    /// it wrote, or what the first writer writes if it's declared before all of them, and runs
+    /// after that writer. Writers of a resource keep their declaration order, and each one
+    /// waits for the readers of what the previous one wrote.
+    fn execution_order(&self) -> Vec<usize> {
+        let dependencies = self.dependencies();
+
+        let mut order = Vec::with_capacity(self.passes.len());
+        let mut scheduled = vec![false; self.passes.len()];
+        while order.len() < self.passes.len() {
+            let next = (0..self.passes.len())
+                .find(|&pass| {
+                    !scheduled[pass] && dependencies[pass].iter().all(|&dep| scheduled[dep])
+                })
+                .expect("render graph dependencies form a cycle");
+            scheduled[next] = true;
+            order.push(next);
+        }
+        order
+    }
+
+    /// The passes each pass has to run after
+    fn dependencies(&self) -> Vec<BTreeSet<usize>> {
+        // Writers and readers of each resource, in declaration order. A pass that both reads
+        // and writes a resource counts as a writer.
+        let mut uses = BTreeMap::<Resource, (Vec<usize>, Vec<usize>)>::new();
+        for (index, pass) in self.passes.iter().enumerate() {
+            let images = pass
+                .images
+                .iter()
+                .map(|(handle, state)| (Resource::Image(*handle), state.access));
+            let buffers = pass
+                .buffers
+                .iter()
+                .map(|(handle, state)| (Resource::Buffer(*handle), state.access));
+            for (resource, access) in images.chain(buffers) {
+                let (writers, readers) = uses.entry(resource).or_default();
+                if is_write(access) {
+                    readers.retain(|&reader| reader != index);
+                    if writers.last() != Some(&index) {
+                        writers.push(index);
+                    }
+                } else if writers.last() != Some(&index) && readers.last() != Some(&index) {
+                    readers.push(index);
+                }
+            }
+        }
+
+        let mut dependencies = vec![BTreeSet::new(); self.passes.len()];
+        for (writers, readers) in uses.values() {
+            for pair in writers.windows(2) {
+                dependencies[pair[1]].insert(pair[0]);
+            }
+            if writers.is_empty() {
+                continue;
+            }
+            for &reader in readers {
+                let producer = writers
+                    .iter()
+                    .rposition(|&writer| writer < reader)
+                    .unwrap_or(0);
+                dependencies[reader].insert(writers[producer]);
+                if let Some(&next) = writers.get(producer + 1) {
+                    dependencies[next].insert(reader);
+                }
+            }
+        }
+        dependencies
+    }
+
+    fn transition_image(
+        &self,
+        barriers: &mut PassBarriers,
+        states: &mut [ImageState],
+        handle: ImageHandle,
+        next: ImageState,
+    ) {
+        let previous = states[handle.0];
+        let needs_barrier =
+            previous.layout != next.layout || is_write(previous.access) || is_write(next.access);
+
+        if needs_barrier {
+            let image = &self.images[handle.0];
+            barriers.src_stage |= previous.stage;
+            barriers.dst_stage |= next.stage;
+            barriers.image_barriers.push(
+                vk::ImageMemoryBarrier::default()
+                    .old_layout(previous.layout)
+                    .new_layout(next.layout)
+                    .src_access_mask(previous.access)
+                    .dst_access_mask(next.access)
+                    .image(image.image)
+                    .subresource_range(
+                        vk::ImageSubresourceRange::default()
+                            .aspect_mask(image.aspect_mask)
+                            .base_mip_level(0)
+                            .level_count(vk::REMAINING_MIP_LEVELS)
+                            .base_array_layer(0)
+                            .layer_count(vk::REMAINING_ARRAY_LAYERS),
+                    ),
+            );
+            states[handle.0] = next;
+        } else {
+            // Read after read: widen the state so a later write waits on both readers
+            states[handle.0].stage |= next.stage;
+            states[handle.0].access |= next.access;
+        }
+    }
+
+    fn transition_buffer(
+        &self,
+        barriers: &mut PassBarriers,
+        states: &mut [BufferState],
+        handle: BufferHandle,
+        next: BufferState,
+    ) {
+        let previous = states[handle.0];
+        if is_write(previous.access) || is_write(next.access) {
+            barriers.src_stage |= previous.stage;
+            barriers.dst_stage |= next.stage;
+            barriers.buffer_barriers.push(
+                vk::BufferMemoryBarrier::default()
+                    .src_access_mask(previous.access)
+                    .dst_access_mask(next.access)
+                    .buffer(self.buffers[handle.0].buffer)
+                    .offset(0)
+                    .size(vk::WHOLE_SIZE),
+            );
+            states[handle.0] = next;
+        } else {
+            states[handle.0].stage |= next.stage;
+            states[handle.0].access |= next.access;
+        }
+    }
+}
+
+/// Greedy interval partitioning: in order of first use, each image takes the first slot whose
+/// last image is done before it starts. Unused images get no slot.
+fn assign_memory_slots(lifetimes: &[Option<(usize, usize)>]) -> Vec<Option<usize>> {
+    let mut by_first_use: Vec<_> = lifetimes
+        .iter()
+        .enumerate()
+        .filter_map(|(image, lifetime)| Some((image, (*lifetime)?)))
+        .collect();
+    by_first_use.sort_by_key(|(_, (first, _))| *first);
+
+    let mut slot_ends: Vec<usize> = Vec::new();
+    let mut slots = vec![None; lifetimes.len()];
+    for (image, (first, last)) in by_first_use {
+        let slot = match slot_ends.iter().position(|&end| end < first) {
+            Some(slot) => slot,
+            None => {
+                slot_ends.push(last);
+                slot_ends.len() - 1
+            }
+        };
+        slot_ends[slot] = last;
+        slots[image] = Some(slot);
+    }
+    slots
+}
+
+fn is_write(access: vk::AccessFlags) -> bool {
+    access.intersects(
+        vk::AccessFlags::SHADER_WRITE
+            | vk::AccessFlags::TRANSFER_WRITE
+            | vk::AccessFlags::COLOR_ATTACHMENT_WRITE
+            | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE
+            | vk::AccessFlags::HOST_WRITE
+            | vk::AccessFlags::MEMORY_WRITE
+            | vk::AccessFlags::ACCELERATION_STRUCTURE_WRITE_KHR,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use ash::vk::Handle;
+
+    use super::*;
+
+    const RT: vk::PipelineStageFlags = vk::PipelineStageFlags::RAY_TRACING_SHADER_KHR;
+
+    #[test]
+    fn trace_then_blit() {
+        let mut graph = RenderGraph::new();
+        let output = graph.import_image(
+            vk::Image::from_raw(1),
+            vk::ImageAspectFlags::COLOR,
+            ImageState::storage_write(RT),
+            Some(ImageState::storage_write(RT)),
+        );
+        let swapchain = graph.import_image(
+            vk::Image::from_raw(2),
+            vk::ImageAspectFlags::COLOR,
+            ImageState::PRESENT,
+            Some(ImageState::PRESENT),
+        );
+
+        graph.add_pass(
+            "blit",
+            |pass| {
+                pass.image(output, ImageState::TRANSFER_SRC)
+                    .image(swapchain, ImageState::TRANSFER_DST);
+            },
+            |_, _| (),
+        );
+        graph.add_pass(
+            "trace",
+            |pass| {
+                pass.image(output, ImageState::storage_write(RT));
+            },
+            |_, _| (),
+        );
+
+        // Declared first, but blit reads what trace writes
+        let compiled = graph.compile();
+        assert_eq!(compiled.order, [1, 0]);
+
+        // trace: output written again in GENERAL (write -> write)
+        assert_eq!(compiled.barriers[0].image_barriers.len(), 1);
+        assert_eq!(
+            compiled.barriers[0].image_barriers[0].new_layout,
+            vk::ImageLayout::GENERAL
+        );
+        // blit: output GENERAL -> TRANSFER_SRC, swapchain PRESENT -> TRANSFER_DST
+        assert_eq!(compiled.barriers[1].image_barriers.len(), 2);
+        // output back to GENERAL, swapchain back to PRESENT
+        assert_eq!(compiled.final_barriers.image_barriers.len(), 2);
+    }
+
+    #[test]
+    fn writers_wait_for_readers_of_what_they_overwrite() {
+        let mut graph = RenderGraph::new();
+        let image = graph.import_image(
+            vk::Image::from_raw(1),
+            vk::ImageAspectFlags::COLOR,
+            ImageState::storage_write(RT),
+            None,
+        );
+        let passes = [
+            ("early read", ImageState::storage_read(RT)),
+            ("first write", ImageState::storage_write(RT)),
+            ("second write", ImageState::storage_write(RT)),
+            ("late read", ImageState::storage_read(RT)),
+        ];
+        for (name, state) in passes {
+            graph.add_pass(
+                name,
+                |pass| {
+                    pass.image(image, state);
+                },
+                |_, _| (),
+            );
+        }
+
+        // The early read sees what the first write writes, so the second write waits for it
+        assert_eq!(graph.compile().order, [1, 0, 2, 3]);
+    }
+
+    #[test]
+    fn reads_do_not_synchronize_with_each_other() {
+        let mut graph = RenderGraph::new();
+        let image = graph.import_image(
+            vk::Image::from_raw(1),
+            vk::ImageAspectFlags::COLOR,
+            ImageState::storage_read(RT),
+            None,
+        );
+        for name in ["a", "b"] {
+            graph.add_pass(
+                name,
+                |pass| {
+                    pass.image(image, ImageState::storage_read(RT));
+                },
+                |_, _| (),
+            );
+        }
+
+        let compiled = graph.compile();
+        assert!(compiled.barriers.iter().all(PassBarriers::is_empty));
+        assert!(compiled.final_barriers.is_empty());
+    }
+
+    #[test]
+    fn transient_images_alias_when_their_passes_do_not_overlap() {
+        let compute = vk::PipelineStageFlags::COMPUTE_SHADER;
+        let desc = TransientImageDesc::new(
+            vk::Format::R16G16B16A16_SFLOAT,
+            vk::Extent2D {
+                width: 64,
+                height: 64,
+            },
+            vk::ImageUsageFlags::STORAGE,
+            vk::ImageAspectFlags::COLOR,
+        );
+
+        let mut graph = RenderGraph::new();
+        let ao = graph.create_image(desc);
+        let denoised = graph.create_image(desc);
+        let tonemapped = graph.create_image(desc);
+        let unused = graph.create_image(desc);
+        graph.add_pass(
+            "ao",
+            |pass| {
+                pass.image(ao, ImageState::storage_write(compute));
+            },
+            |_, _| (),
+        );
+        graph.add_pass(
+            "denoise",
+            |pass| {
+                pass.image(ao, ImageState::storage_read(compute))
+                    .image(denoised, ImageState::storage_write(compute));
+            },
+            |_, _| (),
+        );
+        graph.add_pass(
+            "tonemap",
+            |pass| {
+                pass.image(denoised, ImageState::storage_read(compute))
+                    .image(tonemapped, ImageState::TRANSFER_DST);
+            },
+            |_, _| (),
+        );
+
+        let compiled = graph.compile();
+        // ao is done before tonemapped is first written
+        assert_eq!(
+            compiled.memory_slots,
+            [Some(0), Some(1), Some(0), None],
+            "{unused:?} has no pass"
+        );
+
+        // tonemapped's first transition waits on ao's last read of the shared memory
+        let tonemap_barrier = &compiled.barriers[2];
+        let barrier = tonemap_barrier
+            .image_barriers
+            .iter()
+            .find(|barrier| barrier.new_layout == vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+            .unwrap();
+        assert_eq!(barrier.old_layout, vk::ImageLayout::UNDEFINED);
+        assert_eq!(barrier.src_access_mask, vk::AccessFlags::SHADER_READ);
+        assert!(tonemap_barrier.src_stage.contains(compute));
+    }
+
+    #[test]
+    fn slots_are_reused_first_fit() {
+        let lifetimes = [Some((0, 2)), Some((1, 1)), Some((2, 3)), Some((3, 4)), None];
+        assert_eq!(
+            assign_memory_slots(&lifetimes),
+            [Some(0), Some(1), Some(1), Some(0), None]
+        );
+    }
+}
@@ -0,0 +1,303 @@
+use std::error::Error;
+
+use bevy_ecs::entity::Entity;
+
+use data::{
+    camera::CameraGpu,
+    instance::InstanceBatch,
+    light::LightGpu,
+    mesh::{Mesh, MeshHandle, Meshes},
+    prelude::{IVec3, UVec3, Vec2},
+    transform::Transform,
+};
+use raw_window_handle::{RawDisplayHandle, RawWindowHandle};
+
+use crate::{
+    acceleration_structure_state::AccelerationStructureState,
+    blue_noise::BlueNoise,
+    buffer_state::BufferState,
+    capabilities::RenderPath,
+    capture::Capture,
+    command_state::{CommandState, FramePath},
+    compute_state::{ComputeState, VoxelGrid},
+    hud::Hud,
+    init_state::InitState,
+    pipeline_state::PipelineState,
+    raster_state::{RasterShading, RasterState},
+    settings::RendererSettings,
+    swapchain_state::SwapchainState,
+    CurrentFrame,
+};
+
+/// Everything needed to draw into one window, for using the renderer outside the vx app.
+/// The app keeps the same states as separate resources instead.
+///
+/// ```no_run
+/// # fn run(display: raw_window_handle::RawDisplayHandle, window: raw_window_handle::RawWindowHandle) -> Result<(), Box<dyn std::error::Error>> {
+/// use data::{mesh::Mesh, transform::Transform};
+/// use data::prelude::Vec2;
+/// use renderer::{renderer::Renderer, settings::RendererSettings};
+///
+/// let size = Vec2::new(1280.0, 720.0);
+/// let mut renderer = Renderer::new((display, window), size, RendererSettings::default())?;
+/// let _cube = renderer.add_mesh(Mesh::cube(1.0))?;
+/// renderer.draw_frame(&Transform::from_xyz(0.0, 0.0, 3.0), 70.0, size, &[])?;
+/// renderer.cleanup();
+/// # Ok(())
+/// # }
+/// ```
+pub struct Renderer {
+    settings: RendererSettings,
+    init_state: InitState,
+    swapchain_state: SwapchainState,
+    buffer_state: BufferState<'static>,
+    command_state: CommandState,
+    path: PathStates,
+    meshes: Meshes,
+    hud: Hud,
+    current_frame: CurrentFrame,
+}
+
+/// The states of whichever [`RenderPath`] the device supports
+struct PathStates {
+    pipeline_state: Option<PipelineState<'static>>,
+    acceleration_structure_state: Option<AccelerationStructureState<'static>>,
+    raster_state: Option<RasterState>,
+    compute_state: Option<ComputeState<'static>>,
+}
+
+impl PathStates {
+    fn frame_path(&mut self) -> FramePath<'_, 'static> {
+        if let Some(compute_state) = &mut self.compute_state {
+            return FramePath::Compute(compute_state);
+        }
+        match (
+            &self.pipeline_state,
+            &mut self.acceleration_structure_state,
+            &mut self.raster_state,
+        ) {
+            (Some(pipeline_state), Some(acceleration_structure_state), None) => {
+                FramePath::RayTracing {
+                    pipeline_state,
+                    acceleration_structure_state,
+                }
+            }
+            (_, Some(acceleration_structure_state), Some(raster_state)) => FramePath::Hybrid {
+                acceleration_structure_state,
+                raster_state,
+            },
+            (_, None, Some(raster_state)) => FramePath::Raster(raster_state),
+            _ => unreachable!("Renderer::new creates the states of one path"),
+        }
+    }
+}
+
+impl Renderer {
+    /// Picks the most capable render path the device supports, capped at
+    /// `settings.render_path`. The compute path marches an empty voxel grid.
+    pub fn new(
+        (display_handle, window_handle): (RawDisplayHandle, RawWindowHandle),
+        window_size: Vec2,
+        settings: RendererSettings,
+    ) -> Result<Self, Box<dyn Error>> {
+        let init_state = InitState::new(
+            "vx renderer",
+            1,
+            display_handle,
+            window_handle,
+            settings.render_path,
+            &[],
+        )?;
+        let swapchain_state = SwapchainState::new(&init_state, window_size, &settings)?;
+        let buffer_state = BufferState::new(&init_state, &BlueNoise::default())?;
+        let command_state = CommandState::new(&init_state)?;
+
+        let render_path = init_state.render_path();
+        let mut path = PathStates {
+            pipeline_state: None,
+            acceleration_structure_state: None,
+            raster_state: None,
+            compute_state: None,
+        };
+        if render_path.uses_acceleration_structures() {
+            let pipeline_state = PipelineState::new(&init_state, &settings)?;
+            path.acceleration_structure_state = Some(AccelerationStructureState::new(
+                &init_state,
+                &swapchain_state,
+                &pipeline_state,
+                &buffer_state,
+            )?);
+            path.pipeline_state = Some(pipeline_state);
+        }
+        let raster_shading = match render_path {
+            RenderPath::RayTracing | RenderPath::Compute => None,
+            RenderPath::Hybrid => Some(RasterShading::RayQuery),
+            RenderPath::Raster => Some(RasterShading::Flat),
+        };
+        if let Some(shading) = raster_shading {
+            path.raster_state = Some(RasterState::new(&init_state, &swapchain_state, shading)?);
+        }
+        if render_path == RenderPath::Compute {
+            path.compute_state = Some(ComputeState::new(
+                &init_state,
+                &swapchain_state,
+                &buffer_state,
+                VoxelGrid::new(IVec3::ZERO, UVec3::ONE),
+            )?);
+        }
+
+        Ok(Self {
+            settings,
+            init_state,
+            swapchain_state,
+            buffer_state,
+            command_state,
+            path,
+            meshes: Meshes::default(),
+            hud: Hud::default(),
+            current_frame: CurrentFrame::default(),
+        })
+    }
+
+    pub const fn render_path(&self) -> RenderPath {
+        self.init_state.render_path()
+    }
+
+    pub const fn settings(&self) -> &RendererSettings {
+        &self.settings
+    }
+
+    /// Rebuilds whatever the changed settings are baked into, like the app does when its
+    /// settings resource changes
+    pub fn set_settings(
+        &mut self,
+        settings: RendererSettings,
+        window_size: Vec2,
+    ) -> Result<(), Box<dyn Error>> {
+        self.settings = settings;
+        if let Some(pipeline_state) = &mut self.path.pipeline_state {
+            pipeline_state.apply_settings(&self.init_state, &self.settings)?;
+        }
+        if self.swapchain_state.apply_settings(&self.settings) {
+            self.path.frame_path().recreate_swapchain(
+                &self.init_state,
+                &mut self.swapchain_state,
+                window_size,
+            )?;
+        }
+        self.command_state.reset_accumulation();
+        Ok(())
+    }
+
+    /// Uploads the mesh for instancing with [`Self::set_instances`]. Only the paths that trace
+    /// acceleration structures draw instances.
+    pub fn add_mesh(&mut self, mesh: Mesh) -> Result<MeshHandle, Box<dyn Error>> {
+        let handle = self.meshes.add(mesh);
+        if let (Some(pipeline_state), Some(acceleration_structure_state)) = (
+            &self.path.pipeline_state,
+            &mut self.path.acceleration_structure_state,
+        ) {
+            acceleration_structure_state.sync_meshes(
+                &self.init_state,
+                pipeline_state,
+                &self.meshes,
+            )?;
+        }
+        Ok(handle)
+    }
+
+    /// Replaces every instance; see [`batch_instances`](data::instance::batch_instances). Also
+    /// needed after [`Self::set_skins`], so the TLAS fits the skinned meshes.
+    pub fn set_instances(&mut self, batches: &[InstanceBatch]) -> Result<(), Box<dyn Error>> {
+        if let (Some(pipeline_state), Some(acceleration_structure_state)) = (
+            &self.path.pipeline_state,
+            &mut self.path.acceleration_structure_state,
+        ) {
+            acceleration_structure_state.update_instances(
+                &self.init_state,
+                pipeline_state,
+                batches,
+            )?;
+            self.command_state.reset_accumulation();
+        }
+        Ok(())
+    }
+
+    /// Skins the instances of these entities with their own copy of the mesh from now on,
+    /// replacing the skins set before. Call again whenever a pose changes.
+    pub fn set_skins(&mut self, skins: &[(Entity, Mesh)]) -> Result<(), Box<dyn Error>> {
+        if let (Some(pipeline_state), Some(acceleration_structure_state)) = (
+            &self.path.pipeline_state,
+            &mut self.path.acceleration_structure_state,
+        ) {
+            self.command_state.wait_for_frame(
+                &self.init_state,
+                self.current_frame.0,
+                self.settings.clamped_frames_in_flight(),
+            )?;
+            acceleration_structure_state.update_skins(&self.init_state, pipeline_state, skins)?;
+            self.command_state.reset_accumulation();
+        }
+        Ok(())
+    }
+
+    /// Drawn over the next frames until cleared
+    pub fn hud_mut(&mut self) -> &mut Hud {
+        &mut self.hud
+    }
+
+    /// Skips the frame while `window_size` is empty, e.g. when minimized, and recreates the
+    /// swapchain when it no longer matches the window
+    pub fn draw_frame(
+        &mut self,
+        camera: &Transform,
+        fov_degrees: f32,
+        window_size: Vec2,
+        lights: &[LightGpu],
+    ) -> Result<(), Box<dyn Error>> {
+        self.command_state.draw_frame(
+            &self.init_state,
+            &mut self.swapchain_state,
+            self.path.frame_path(),
+            &mut self.buffer_state,
+            &self.settings,
+            &self.hud,
+            window_size,
+            CameraGpu::new(camera, fov_degrees, window_size.x, window_size.y),
+            lights,
+            self.current_frame.0,
+        )?;
+        self.current_frame.0 = self.current_frame.next();
+        Ok(())
+    }
+
+    /// Captures the next ray traced or compute frame; read it back with
+    /// [`Self::take_capture`] once a few more frames have been drawn
+    pub fn request_capture(&mut self) {
+        self.command_state.request_capture();
+    }
+
+    pub fn take_capture(&mut self) -> Option<Capture> {
+        self.command_state.take_capture()
+    }
+
+    /// Waits for the GPU and destroys everything but the device, which goes when dropped
+    pub fn cleanup(mut self) {
+        self.init_state.wait_idle().unwrap();
+        self.command_state.cleanup(&self.init_state);
+        if let Some(acceleration_structure_state) = &mut self.path.acceleration_structure_state {
+            acceleration_structure_state.cleanup(&self.init_state);
+        }
+        self.buffer_state.cleanup(&self.init_state);
+        if let Some(pipeline_state) = &mut self.path.pipeline_state {
+            pipeline_state.cleanup(&self.init_state);
+        }
+        if let Some(raster_state) = &self.path.raster_state {
+            raster_state.cleanup(&self.init_state);
+        }
+        if let Some(compute_state) = &mut self.path.compute_state {
+            compute_state.cleanup(&self.init_state);
+        }
+        self.swapchain_state.cleanup(&self.init_state);
+    }
+}
@@ -0,0 +1,192 @@
+use bevy_ecs::system::Resource;
+use data::view_distance::ViewDistance;
+use serde::{Deserialize, Serialize};
+
+use crate::capabilities::RenderPath;
+
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RendererSettings {
+    pub reflection_quality: ReflectionQuality,
+    /// One-bounce diffuse path tracing, converging over several still frames
+    pub global_illumination: bool,
+    /// Write the instance and primitive under the crosshair to a readback buffer
+    pub picking: bool,
+    /// Trace rays at this fraction of the swapchain resolution and scale up on blit
+    pub render_scale: f32,
+    /// Present with FIFO instead of the lowest-latency mode the surface supports
+    pub vsync: bool,
+    /// Images to ask the swapchain for, within what the surface allows; `None` asks for one
+    /// more than its minimum. More images smooth out uneven frames at the cost of latency.
+    pub swapchain_images: Option<u32>,
+    /// Frames the CPU may record ahead of the GPU. One waits for the previous frame before
+    /// recording the next, trading throughput for input latency.
+    pub frames_in_flight: u32,
+    /// Trace shadow rays towards the sun from every primary hit
+    pub shadows: bool,
+    /// Shadow rays per primary hit, spread over the sun's disc for soft edges. Baked into the
+    /// pipeline, so changing it rebuilds it.
+    pub shadow_rays: u32,
+    /// Trace one short occlusion ray per primary hit, converging over still frames
+    pub ambient_occlusion: bool,
+    /// Stop tracing pixels whose accumulated samples have converged, so a still camera gets
+    /// cheaper frames the longer it holds still. Only matters while accumulating, i.e. with
+    /// global illumination or ambient occlusion.
+    pub adaptive_sampling: bool,
+    /// A pixel has converged once the standard error of its mean luminance falls below this
+    /// fraction of the mean
+    pub adaptive_sampling_threshold: f32,
+    /// Tint converged pixels green and traced ones red instead of shading them
+    pub show_sampling_mask: bool,
+    /// Replaces the shaded image on the ray traced paths. Not saved, so a restart always
+    /// shows the shaded image.
+    #[serde(skip)]
+    pub debug_view: DebugView,
+    /// Chunk streaming radius; primary rays end in fog at this distance
+    pub view_distance: ViewDistance,
+    /// Samples per pixel on the raster path, resolved into the swapchain image
+    pub msaa: Msaa,
+    /// Most capable path to use if the device supports it; `None` picks the best one. Only
+    /// read at startup, since it decides which device extensions are enabled.
+    pub render_path: Option<RenderPath>,
+}
+
+impl Default for RendererSettings {
+    fn default() -> Self {
+        Self {
+            reflection_quality: ReflectionQuality::default(),
+            global_illumination: false,
+            picking: false,
+            render_scale: 1.0,
+            vsync: true,
+            swapchain_images: None,
+            frames_in_flight: Self::MAX_FRAMES_IN_FLIGHT,
+            shadows: true,
+            shadow_rays: 1,
+            ambient_occlusion: false,
+            adaptive_sampling: false,
+            adaptive_sampling_threshold: 0.02,
+            show_sampling_mask: false,
+            debug_view: DebugView::None,
+            view_distance: ViewDistance::default(),
+            msaa: Msaa::default(),
+            render_path: None,
+        }
+    }
+}
+
+impl RendererSettings {
+    pub const MIN_RENDER_SCALE: f32 = 0.25;
+    pub const MAX_RENDER_SCALE: f32 = 2.0;
+
+    /// Ray recursion depth the pipeline is created with, so every setting can be toggled live.
+    /// Secondary rays (reflection, GI, shadow, AO) are only traced from primary hits.
+    pub const MAX_RAY_RECURSION_DEPTH: u32 = 2;
+
+    pub const MAX_SHADOW_RAYS: u32 = 8;
+
+    /// Per-frame resources exist for this many frames
+    pub const MAX_FRAMES_IN_FLIGHT: u32 = crate::MAX_FRAMES_IN_FLIGHT as u32;
+
+    pub fn clamped_render_scale(&self) -> f32 {
+        self.render_scale
+            .clamp(Self::MIN_RENDER_SCALE, Self::MAX_RENDER_SCALE)
+    }
+
+    pub fn clamped_shadow_rays(&self) -> u32 {
+        self.shadow_rays.clamp(1, Self::MAX_SHADOW_RAYS)
+    }
+
+    pub fn clamped_frames_in_flight(&self) -> u32 {
+        self.frames_in_flight.clamp(1, Self::MAX_FRAMES_IN_FLIGHT)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ReflectionQuality {
+    Off,
+    /// Only near-perfect mirrors trace a reflection ray
+    #[default]
+    Low,
+    High,
+}
+
+impl ReflectionQuality {
+    /// Number of reflection bounces traced after the primary ray
+    pub const fn max_bounces(&self) -> u32 {
+        match self {
+            Self::Off => 0,
+            Self::Low | Self::High => 1,
+        }
+    }
+
+    /// Materials rougher than this fall back to the environment color
+    pub const fn roughness_cutoff(&self) -> f32 {
+        match self {
+            Self::Off => 0.0,
+            Self::Low => 0.1,
+            Self::High => 0.5,
+        }
+    }
+}
+
+/// What the hit shader outputs instead of the shaded color. The discriminants are what the
+/// shaders switch on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum DebugView {
+    #[default]
+    None = 0,
+    /// World-space normals mapped to 0..1
+    Normals = 1,
+    /// Ray length as a heat map, hot up close and cold at the fog distance
+    Depth = 2,
+    /// A color per instance; the scene is instance 0
+    InstanceId = 3,
+    /// White surfaces darkened by ambient occlusion only, accumulated like the shaded image
+    AmbientOcclusion = 4,
+    /// Sun, shadows and local lights on white surfaces, without materials or reflections
+    Lighting = 5,
+    /// The shaded image with chunk borders drawn over it
+    ChunkBoundaries = 6,
+}
+
+impl DebugView {
+    pub const ALL: [Self; 7] = [
+        Self::None,
+        Self::Normals,
+        Self::Depth,
+        Self::InstanceId,
+        Self::AmbientOcclusion,
+        Self::Lighting,
+        Self::ChunkBoundaries,
+    ];
+
+    /// The view after this one, wrapping around to [`Self::None`]
+    pub fn next(&self) -> Self {
+        Self::ALL[(*self as usize + 1) % Self::ALL.len()]
+    }
+}
+
+/// Requested multisampling; the device may support fewer samples, see
+/// [`SwapchainState::msaa_samples`](crate::swapchain_state::SwapchainState::msaa_samples)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, Serialize, Deserialize)]
+pub enum Msaa {
+    #[default]
+    Off,
+    X2,
+    X4,
+    X8,
+}
+
+impl Msaa {
+    pub const ALL: [Self; 4] = [Self::Off, Self::X2, Self::X4, Self::X8];
+
+    pub const fn samples(&self) -> u32 {
+        match self {
+            Self::Off => 1,
+            Self::X2 => 2,
+            Self::X4 => 4,
+            Self::X8 => 8,
+        }
+    }
+}
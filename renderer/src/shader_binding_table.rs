@@ -0,0 +1,420 @@
+use std::error::Error;
+
+use ash::{
+    khr::{buffer_device_address, ray_tracing_pipeline},
+    vk,
+};
+
+use crate::buffer::Buffer;
+
+/// A shader group handle followed by inline data readable through `shaderRecordEXT`
+#[derive(Debug, Clone, Default)]
+pub struct ShaderRecord {
+    pub group: u32,
+    pub data: Vec<u8>,
+}
+
+impl ShaderRecord {
+    pub const fn new(group: u32) -> Self {
+        Self {
+            group,
+            data: Vec::new(),
+        }
+    }
+
+    pub fn with_data(mut self, data: &[u8]) -> Self {
+        self.data = data.to_vec();
+        self
+    }
+}
+
+/// Records for every region of the table, in the order they are indexed by `traceRayEXT`
+/// (miss index, SBT record offset/stride) and `executeCallableEXT` (callable index)
+#[derive(Debug, Clone, Default)]
+pub struct ShaderRecords {
+    pub raygen: ShaderRecord,
+    pub miss: Vec<ShaderRecord>,
+    pub hit: Vec<ShaderRecord>,
+    pub callable: Vec<ShaderRecord>,
+}
+
+impl ShaderRecords {
+    fn regions(&self) -> [&[ShaderRecord]; 4] {
+        [
+            std::slice::from_ref(&self.raygen),
+            &self.miss,
+            &self.hit,
+            &self.callable,
+        ]
+    }
+
+    fn max_group(&self) -> Option<u32> {
+        self.regions()
+            .iter()
+            .flat_map(|records| records.iter())
+            .map(|record| record.group)
+            .max()
+    }
+}
+
+/// Byte offsets and strides of each region within the table buffer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SbtRegionLayout {
+    pub offset: vk::DeviceSize,
+    pub stride: vk::DeviceSize,
+    pub size: vk::DeviceSize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SbtLayout {
+    pub raygen: SbtRegionLayout,
+    pub miss: SbtRegionLayout,
+    pub hit: SbtRegionLayout,
+    pub callable: SbtRegionLayout,
+    pub total_size: vk::DeviceSize,
+}
+
+impl SbtLayout {
+    pub fn new(
+        records: &ShaderRecords,
+        handle_size: vk::DeviceSize,
+        handle_alignment: vk::DeviceSize,
+        base_alignment: vk::DeviceSize,
+    ) -> Self {
+        let mut offset = 0;
+        let [raygen, miss, hit, callable] = records.regions().map(|records| {
+            let region = Self::region(
+                records,
+                offset,
+                handle_size,
+                handle_alignment,
+                base_alignment,
+            );
+            offset += region.size;
+            region
+        });
+
+        Self {
+            // Raygen size must equal its stride
+            raygen: SbtRegionLayout {
+                size: raygen.stride,
+                ..raygen
+            },
+            miss,
+            hit,
+            callable,
+            total_size: offset,
+        }
+    }
+
+    fn region(
+        records: &[ShaderRecord],
+        offset: vk::DeviceSize,
+        handle_size: vk::DeviceSize,
+        handle_alignment: vk::DeviceSize,
+        base_alignment: vk::DeviceSize,
+    ) -> SbtRegionLayout {
+        if records.is_empty() {
+            return SbtRegionLayout {
+                offset,
+                ..Default::default()
+            };
+        }
+
+        let max_data_size = records
+            .iter()
+            .map(|record| record.data.len() as vk::DeviceSize)
+            .max()
+            .unwrap_or(0);
+        let stride = align_up(handle_size + max_data_size, handle_alignment);
+        let size = align_up(stride * records.len() as vk::DeviceSize, base_alignment);
+
+        SbtRegionLayout {
+            offset,
+            stride,
+            size,
+        }
+    }
+}
+
+/// Lays out hit records so that each TLAS instance owns one record per BLAS geometry.
+///
+/// The offset returned by [`SbtBuilder::add_instance`] must be written into the instance's
+/// `instance_shader_binding_table_record_offset`, and shaders must trace with an SBT record
+/// stride of 1 so `geometry index` selects the record within the instance's block.
+#[derive(Debug, Clone, Default)]
+pub struct SbtBuilder {
+    records: ShaderRecords,
+    instance_hit_offsets: Vec<u32>,
+}
+
+impl SbtBuilder {
+    /// Largest value that fits in the 24-bit instance SBT record offset
+    const MAX_RECORD_OFFSET: u32 = (1 << 24) - 1;
+
+    pub fn new(raygen: ShaderRecord) -> Self {
+        Self {
+            records: ShaderRecords {
+                raygen,
+                ..Default::default()
+            },
+            instance_hit_offsets: Vec::new(),
+        }
+    }
+
+    pub fn with_miss(mut self, miss: ShaderRecord) -> Self {
+        self.records.miss.push(miss);
+        self
+    }
+
+    pub fn with_callable(mut self, callable: ShaderRecord) -> Self {
+        self.records.callable.push(callable);
+        self
+    }
+
+    /// Appends one hit record per geometry of an instance and returns the instance's
+    /// SBT record offset
+    pub fn add_instance(&mut self, geometries: &[ShaderRecord]) -> u32 {
+        let offset = self.records.hit.len() as u32;
+        assert!(
+            offset <= Self::MAX_RECORD_OFFSET,
+            "SBT record offset does not fit in 24 bits"
+        );
+        self.records.hit.extend_from_slice(geometries);
+        self.instance_hit_offsets.push(offset);
+        offset
+    }
+
+    pub const fn records(&self) -> &ShaderRecords {
+        &self.records
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn build<'a>(
+        self,
+        instance: &ash::Instance,
+        device: &ash::Device,
+        physical_device: vk::PhysicalDevice,
+        bda_loader: &buffer_device_address::Device,
+        rt_loader: &ray_tracing_pipeline::Device,
+        rt_properties: &vk::PhysicalDeviceRayTracingPipelinePropertiesKHR,
+        pipeline: vk::Pipeline,
+        group_count: u32,
+    ) -> Result<ShaderBindingTable<'a>, Box<dyn Error>> {
+        let mut table = ShaderBindingTable::new(
+            instance,
+            device,
+            physical_device,
+            bda_loader,
+            rt_loader,
+            rt_properties,
+            pipeline,
+            group_count,
+            &self.records,
+        )?;
+        table.instance_hit_offsets = self.instance_hit_offsets;
+        Ok(table)
+    }
+}
+
+pub struct ShaderBindingTable<'a> {
+    buffer: Buffer<'a>,
+    layout: SbtLayout,
+    instance_hit_offsets: Vec<u32>,
+    pub raygen_region: vk::StridedDeviceAddressRegionKHR,
+    pub miss_region: vk::StridedDeviceAddressRegionKHR,
+    pub hit_region: vk::StridedDeviceAddressRegionKHR,
+    pub callable_region: vk::StridedDeviceAddressRegionKHR,
+}
+
+impl<'a> ShaderBindingTable<'a> {
+    pub const fn layout(&self) -> &SbtLayout {
+        &self.layout
+    }
+
+    /// Hit record offset for the `instance`-th instance added through [`SbtBuilder`]
+    pub fn instance_hit_offset(&self, instance: usize) -> Option<u32> {
+        self.instance_hit_offsets.get(instance).copied()
+    }
+
+    /// `group_count` is the number of shader groups the pipeline was created with
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        instance: &ash::Instance,
+        device: &ash::Device,
+        physical_device: vk::PhysicalDevice,
+        bda_loader: &buffer_device_address::Device,
+        rt_loader: &ray_tracing_pipeline::Device,
+        rt_properties: &vk::PhysicalDeviceRayTracingPipelinePropertiesKHR,
+        pipeline: vk::Pipeline,
+        group_count: u32,
+        records: &ShaderRecords,
+    ) -> Result<Self, Box<dyn Error>> {
+        unsafe {
+            let handle_size = rt_properties.shader_group_handle_size as vk::DeviceSize;
+            let handle_alignment = rt_properties.shader_group_handle_alignment as vk::DeviceSize;
+            let base_alignment = rt_properties.shader_group_base_alignment as vk::DeviceSize;
+
+            if handle_size == 0 || base_alignment == 0 {
+                return Err(Box::new(std::io::Error::other(
+                    "Shader group handle size is 0, properties query failed",
+                )));
+            }
+
+            if records
+                .max_group()
+                .is_some_and(|group| group >= group_count)
+            {
+                return Err(Box::new(std::io::Error::other(
+                    "Shader record references a group the pipeline does not have",
+                )));
+            }
+
+            let layout = SbtLayout::new(records, handle_size, handle_alignment, base_alignment);
+
+            let max_stride = [layout.miss, layout.hit, layout.callable]
+                .iter()
+                .map(|region| region.stride)
+                .max()
+                .unwrap_or(0);
+            if max_stride > rt_properties.max_shader_group_stride as vk::DeviceSize {
+                return Err(Box::new(std::io::Error::other(
+                    "Shader record data exceeds the device's maximum shader group stride",
+                )));
+            }
+
+            // Over-allocate so the table start can be moved up to the base alignment
+            let mut buffer = Buffer::create(
+                instance,
+                device,
+                physical_device,
+                layout.total_size + base_alignment,
+                vk::BufferUsageFlags::SHADER_BINDING_TABLE_KHR
+                    | vk::BufferUsageFlags::TRANSFER_DST
+                    | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS,
+                vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+            )?;
+
+            let buffer_address = bda_loader.get_buffer_device_address(
+                &vk::BufferDeviceAddressInfo::default().buffer(buffer.handle()),
+            );
+            let aligned_buffer_address = align_up(buffer_address, base_alignment);
+            let table_offset = (aligned_buffer_address - buffer_address) as usize;
+
+            let handles = rt_loader.get_ray_tracing_shader_group_handles(
+                pipeline,
+                0,
+                group_count,
+                (handle_size * group_count as vk::DeviceSize) as usize,
+            )?;
+
+            buffer.map_memory(device, 0, vk::MemoryMapFlags::empty())?;
+            let mapped = buffer.mapped_mut().as_mut().unwrap();
+            let handle_size = handle_size as usize;
+
+            for (region, records) in [layout.raygen, layout.miss, layout.hit, layout.callable]
+                .iter()
+                .zip(records.regions())
+            {
+                for (i, record) in records.iter().enumerate() {
+                    let start = table_offset + (region.offset + region.stride * i as u64) as usize;
+                    let handle_start = record.group as usize * handle_size;
+
+                    mapped[start..start + handle_size]
+                        .copy_from_slice(&handles[handle_start..handle_start + handle_size]);
+                    mapped[start + handle_size..start + handle_size + record.data.len()]
+                        .copy_from_slice(&record.data);
+                }
+            }
+            buffer.unmap_memory(device)?;
+
+            let region = |layout: SbtRegionLayout| {
+                if layout.size == 0 {
+                    vk::StridedDeviceAddressRegionKHR::default()
+                } else {
+                    vk::StridedDeviceAddressRegionKHR::default()
+                        .device_address(aligned_buffer_address + layout.offset)
+                        .stride(layout.stride)
+                        .size(layout.size)
+                }
+            };
+
+            Ok(Self {
+                buffer,
+                layout,
+                instance_hit_offsets: Vec::new(),
+                raygen_region: region(layout.raygen),
+                miss_region: region(layout.miss),
+                hit_region: region(layout.hit),
+                callable_region: region(layout.callable),
+            })
+        }
+    }
+
+    pub fn cleanup(&mut self, device: &ash::Device) {
+        self.buffer.cleanup(device);
+    }
+}
+
+const fn align_up(value: vk::DeviceSize, alignment: vk::DeviceSize) -> vk::DeviceSize {
+    (value + alignment - 1) & !(alignment - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn regions_are_aligned_around_inline_data() {
+        assert_eq!(align_up(0, 64), 0);
+        assert_eq!(align_up(1, 64), 64);
+        assert_eq!(align_up(64, 64), 64);
+        assert_eq!(align_up(72, 32), 96);
+
+        let records = ShaderRecords {
+            raygen: ShaderRecord::new(0),
+            miss: vec![
+                ShaderRecord::new(1),
+                ShaderRecord::new(2).with_data(&[0; 4]),
+            ],
+            hit: vec![
+                ShaderRecord::new(3),
+                ShaderRecord::new(3).with_data(&[0; 40]),
+                ShaderRecord::new(4),
+            ],
+            callable: Vec::new(),
+        };
+        // A 32-byte handle aligned to 32 bytes, with regions aligned to 64
+        let layout = SbtLayout::new(&records, 32, 32, 64);
+
+        // Raygen's size is its stride, but the next region still starts base aligned
+        let raygen = SbtRegionLayout {
+            offset: 0,
+            stride: 32,
+            size: 32,
+        };
+        assert_eq!(layout.raygen, raygen);
+        // 32 + 4 bytes rounds up to a 64-byte stride
+        let miss = SbtRegionLayout {
+            offset: 64,
+            stride: 64,
+            size: 128,
+        };
+        assert_eq!(layout.miss, miss);
+        // Every record gets the largest record's 32 + 40 bytes, rounded up to 96; three of
+        // them round up to 320
+        let hit = SbtRegionLayout {
+            offset: 192,
+            stride: 96,
+            size: 320,
+        };
+        assert_eq!(layout.hit, hit);
+        let callable = SbtRegionLayout {
+            offset: 512,
+            stride: 0,
+            size: 0,
+        };
+        assert_eq!(layout.callable, callable);
+        assert_eq!(layout.total_size, 512);
+    }
+}
@@ -0,0 +1,79 @@
+use std::mem;
+
+use ash::vk;
+
+/// Values baked into a shader stage at pipeline creation through `layout(constant_id = N)`.
+/// Entries for IDs a shader does not declare are ignored, so one set can serve every stage.
+#[derive(Debug, Clone, Default)]
+pub struct SpecializationConstants {
+    entries: Vec<vk::SpecializationMapEntry>,
+    data: Vec<u8>,
+}
+
+impl SpecializationConstants {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_u32(self, constant_id: u32, value: u32) -> Self {
+        self.with_bytes(constant_id, &value.to_ne_bytes())
+    }
+
+    pub fn with_f32(self, constant_id: u32, value: f32) -> Self {
+        self.with_bytes(constant_id, &value.to_ne_bytes())
+    }
+
+    /// GLSL `bool` constants are 32-bit `VkBool32`s
+    pub fn with_bool(self, constant_id: u32, value: bool) -> Self {
+        self.with_u32(constant_id, value as vk::Bool32)
+    }
+
+    fn with_bytes(mut self, constant_id: u32, bytes: &[u8]) -> Self {
+        debug_assert!(
+            self.entries
+                .iter()
+                .all(|entry| entry.constant_id != constant_id),
+            "constant {constant_id} specialized twice"
+        );
+        self.entries.push(vk::SpecializationMapEntry {
+            constant_id,
+            offset: self.data.len() as u32,
+            size: bytes.len(),
+        });
+        self.data.extend_from_slice(bytes);
+        self
+    }
+
+    /// Borrowed by `PipelineShaderStageCreateInfo::specialization_info`
+    pub fn info(&self) -> vk::SpecializationInfo<'_> {
+        vk::SpecializationInfo::default()
+            .map_entries(&self.entries)
+            .data(&self.data)
+    }
+}
+
+const _: () = assert!(mem::size_of::<vk::Bool32>() == 4);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_index_packed_data() {
+        let constants = SpecializationConstants::new()
+            .with_u32(0, 3)
+            .with_bool(2, true)
+            .with_f32(5, 0.5);
+        let info = constants.info();
+
+        assert_eq!(info.map_entry_count, 3);
+        assert_eq!(info.data_size, 12);
+        let offsets: Vec<_> = constants
+            .entries
+            .iter()
+            .map(|entry| (entry.constant_id, entry.offset))
+            .collect();
+        assert_eq!(offsets, [(0, 0), (2, 4), (5, 8)]);
+        assert_eq!(&constants.data[8..], &0.5f32.to_ne_bytes());
+    }
+}
@@ -6,13 +6,14 @@ use ash::{
     vk,
 };
 use bevy_ecs::system::Resource;
-use glam::Vec2;
+use data::prelude::Vec2;
 
 use crate::{
-    acceleration_structure_state::AccelerationStructureState,
     buffer::Buffer,
-    buffer_state::BufferState,
     init_state::{InitState, Queue, Queues, SwapchainSupportDetails},
+    present_wait::PresentWaiter,
+    settings::{Msaa, RendererSettings},
+    tracking::tracker,
     MAX_FRAMES_IN_FLIGHT,
 };
 
@@ -21,6 +22,15 @@ pub struct SwapchainState {
     loader: swapchain::Device,
     image_format: vk::Format,
     extent: vk::Extent2D,
+    /// Size of the traced output and accumulation images, `extent` times the render scale
+    render_extent: vk::Extent2D,
+    render_scale: f32,
+    vsync: bool,
+    /// Requested rather than actual image count; see [`RendererSettings::swapchain_images`]
+    desired_images: Option<u32>,
+    msaa: Msaa,
+    /// `msaa` limited to what the device supports for both color and depth attachments
+    msaa_samples: vk::SampleCountFlags,
 
     swapchain: vk::SwapchainKHR,
     images: Vec<vk::Image>,
@@ -29,13 +39,116 @@ pub struct SwapchainState {
     output_images: Vec<vk::Image>,
     output_image_memories: Vec<vk::DeviceMemory>,
     output_image_views: Vec<vk::ImageView>,
+
+    accumulation_image: vk::Image,
+    accumulation_image_memory: vk::DeviceMemory,
+    accumulation_image_view: vk::ImageView,
+
+    /// Per-pixel luminance moments and sample counts next to the accumulation image, which
+    /// raygen reads to skip converged pixels
+    variance_image: vk::Image,
+    variance_image_memory: vk::DeviceMemory,
+    variance_image_view: vk::ImageView,
+
+    /// Depth attachment for the raster path, at the swapchain extent and `msaa_samples`
+    depth_format: vk::Format,
+    depth_image: vk::Image,
+    depth_image_memory: vk::DeviceMemory,
+    depth_image_view: vk::ImageView,
+
+    /// Raster color attachment resolved into the swapchain image, if multisampling
+    msaa_color_image: Option<AttachmentImage>,
+
+    /// Bumped every time the images above are recreated
+    generation: u64,
+
+    /// `None` without `VK_KHR_present_wait`
+    present_waiter: Option<PresentWaiter>,
+}
+
+/// The [`SwapchainState::generation`] each frame's descriptor set was last written for.
+/// Recreating the swapchain while frames are in flight mustn't touch sets those frames are
+/// reading, so each set is only rewritten once its own frame's fence has signaled.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FrameDescriptorVersions([Option<u64>; MAX_FRAMES_IN_FLIGHT as usize]);
+
+impl FrameDescriptorVersions {
+    /// Records `frame`'s set as written for `generation`, returning whether it was stale and
+    /// needs rewriting
+    pub fn update(&mut self, frame: u8, generation: u64) -> bool {
+        let version = &mut self.0[frame as usize];
+        let stale = *version != Some(generation);
+        *version = Some(generation);
+        stale
+    }
+}
+
+/// An image only used as a render attachment
+struct AttachmentImage {
+    image: vk::Image,
+    memory: vk::DeviceMemory,
+    view: vk::ImageView,
 }
 
 impl SwapchainState {
+    const ACCUMULATION_FORMAT: vk::Format = vk::Format::R32G32B32A32_SFLOAT;
+    /// In order of preference
+    const DEPTH_FORMATS: [vk::Format; 3] = [
+        vk::Format::D32_SFLOAT,
+        vk::Format::D32_SFLOAT_S8_UINT,
+        vk::Format::D24_UNORM_S8_UINT,
+    ];
+
+    pub const fn image_format(&self) -> vk::Format {
+        self.image_format
+    }
+
     pub const fn extent(&self) -> &vk::Extent2D {
         &self.extent
     }
 
+    pub const fn render_extent(&self) -> &vk::Extent2D {
+        &self.render_extent
+    }
+
+    pub const fn vsync(&self) -> bool {
+        self.vsync
+    }
+
+    /// Samples per pixel of the raster attachments
+    pub const fn msaa_samples(&self) -> vk::SampleCountFlags {
+        self.msaa_samples
+    }
+
+    /// The multisampled color attachment, or `None` when rendering to the swapchain image directly
+    pub fn msaa_color_image(&self) -> Option<vk::Image> {
+        self.msaa_color_image.as_ref().map(|msaa| msaa.image)
+    }
+
+    pub fn msaa_color_image_view(&self) -> Option<vk::ImageView> {
+        self.msaa_color_image.as_ref().map(|msaa| msaa.view)
+    }
+
+    /// Changes whenever the swapchain is recreated, and with it every image above
+    pub const fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Takes the present mode, image count, render scale and multisampling from `settings`, returning
+    /// whether the swapchain has to be recreated for them to apply
+    pub fn apply_settings(&mut self, settings: &RendererSettings) -> bool {
+        let render_scale = settings.clamped_render_scale();
+        let changed = self.vsync != settings.vsync
+            || self.desired_images != settings.swapchain_images
+            || self.render_scale != render_scale
+            || self.msaa != settings.msaa;
+        self.vsync = settings.vsync;
+        self.desired_images = settings.swapchain_images;
+        self.render_scale = render_scale;
+        self.msaa = settings.msaa;
+        changed
+    }
+
     pub const fn output_images(&self) -> &Vec<vk::Image> {
         &self.output_images
     }
@@ -44,6 +157,39 @@ impl SwapchainState {
         &self.output_image_views
     }
 
+    pub const fn accumulation_image(&self) -> vk::Image {
+        self.accumulation_image
+    }
+
+    pub const fn accumulation_image_view(&self) -> vk::ImageView {
+        self.accumulation_image_view
+    }
+
+    pub const fn variance_image(&self) -> vk::Image {
+        self.variance_image
+    }
+
+    pub const fn variance_image_view(&self) -> vk::ImageView {
+        self.variance_image_view
+    }
+
+    pub const fn depth_format(&self) -> vk::Format {
+        self.depth_format
+    }
+
+    pub const fn depth_image(&self) -> vk::Image {
+        self.depth_image
+    }
+
+    pub const fn depth_image_view(&self) -> vk::ImageView {
+        self.depth_image_view
+    }
+
+    /// Aspects to transition the depth image with; formats with stencil need both
+    pub fn depth_aspect_mask(&self) -> vk::ImageAspectFlags {
+        Self::depth_aspect(self.depth_format)
+    }
+
     pub const fn swapchain(&self) -> vk::SwapchainKHR {
         self.swapchain
     }
@@ -60,10 +206,23 @@ impl SwapchainState {
         &self.loader
     }
 
-    pub fn new(init_state: &InitState, window_size: Vec2) -> Result<Self, Box<dyn Error>> {
+    pub const fn present_waiter(&self) -> Option<&PresentWaiter> {
+        self.present_waiter.as_ref()
+    }
+
+    pub fn present_waiter_mut(&mut self) -> Option<&mut PresentWaiter> {
+        self.present_waiter.as_mut()
+    }
+
+    pub fn new(
+        init_state: &InitState,
+        window_size: Vec2,
+        settings: &RendererSettings,
+    ) -> Result<Self, Box<dyn Error>> {
         unsafe {
             let loader = swapchain::Device::new(init_state.instance(), init_state.device());
 
+            let render_scale = settings.clamped_render_scale();
             let (swapchain, image_format, extent, images) = Self::create_swapchain(
                 init_state.device(),
                 init_state.physical_device(),
@@ -72,7 +231,10 @@ impl SwapchainState {
                 init_state.queues(),
                 &loader,
                 window_size,
+                settings.vsync,
+                settings.swapchain_images,
             )?;
+            let render_extent = Self::scale_extent(extent, render_scale);
 
             let image_views = Self::create_image_views(init_state.device(), image_format, &images)?;
 
@@ -82,16 +244,77 @@ impl SwapchainState {
                 init_state.physical_device(),
                 init_state.queues().command_fence().unwrap(),
                 init_state.queues().graphics(),
-                extent,
+                render_extent,
             )?;
 
             let output_image_views =
                 Self::create_image_views(init_state.device(), image_format, &output_images)?;
 
+            let (accumulation_image, accumulation_image_memory) = Self::create_storage_image(
+                init_state.instance(),
+                init_state.device(),
+                init_state.physical_device(),
+                init_state.queues().command_fence().unwrap(),
+                init_state.queues().graphics(),
+                render_extent,
+                Self::ACCUMULATION_FORMAT,
+            )?;
+            let accumulation_image_view = Self::create_image_view(
+                init_state.device(),
+                Self::ACCUMULATION_FORMAT,
+                accumulation_image,
+            )?;
+
+            let (variance_image, variance_image_memory) = Self::create_storage_image(
+                init_state.instance(),
+                init_state.device(),
+                init_state.physical_device(),
+                init_state.queues().command_fence().unwrap(),
+                init_state.queues().graphics(),
+                render_extent,
+                Self::ACCUMULATION_FORMAT,
+            )?;
+            let variance_image_view = Self::create_image_view(
+                init_state.device(),
+                Self::ACCUMULATION_FORMAT,
+                variance_image,
+            )?;
+
+            let depth_format =
+                Self::choose_depth_format(init_state.instance(), init_state.physical_device())
+                    .ok_or("no supported depth format")?;
+            let msaa_samples = Self::choose_msaa_samples(
+                init_state.instance(),
+                init_state.physical_device(),
+                settings.msaa,
+            );
+            let AttachmentImage {
+                image: depth_image,
+                memory: depth_image_memory,
+                view: depth_image_view,
+            } = Self::create_attachment_image(
+                init_state.instance(),
+                init_state.device(),
+                init_state.physical_device(),
+                extent,
+                depth_format,
+                msaa_samples,
+                vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT,
+                Self::depth_aspect(depth_format),
+            )?;
+            let msaa_color_image =
+                Self::create_msaa_color_image(init_state, extent, image_format, msaa_samples)?;
+
             Ok(Self {
                 loader,
                 image_format,
                 extent,
+                render_extent,
+                render_scale,
+                vsync: settings.vsync,
+                desired_images: settings.swapchain_images,
+                msaa: settings.msaa,
+                msaa_samples,
 
                 swapchain,
                 images,
@@ -100,15 +323,35 @@ impl SwapchainState {
                 output_images,
                 output_image_memories,
                 output_image_views,
+
+                accumulation_image,
+                accumulation_image_memory,
+                accumulation_image_view,
+
+                variance_image,
+                variance_image_memory,
+                variance_image_view,
+
+                depth_format,
+                depth_image,
+                depth_image_memory,
+                depth_image_view,
+
+                msaa_color_image,
+
+                generation: 0,
+
+                present_waiter: PresentWaiter::new(init_state),
             })
         }
     }
 
+    /// Descriptor sets pointing at the old images are left alone, since frames in flight may
+    /// still be using them; each is rewritten when its frame is next recorded, see
+    /// [`FrameDescriptorVersions`]
     pub fn recreate_swapchain(
         &mut self,
         init_state: &InitState,
-        buffer_state: &BufferState,
-        acceleration_structure_state: &mut AccelerationStructureState,
         window_size: Vec2,
     ) -> VkResult<()> {
         unsafe {
@@ -126,7 +369,10 @@ impl SwapchainState {
                 init_state.queues(),
                 &self.loader,
                 window_size,
+                self.vsync,
+                self.desired_images,
             )?;
+            self.render_extent = Self::scale_extent(self.extent, self.render_scale);
 
             self.image_views =
                 Self::create_image_views(init_state.device(), self.image_format, &self.images)?;
@@ -137,18 +383,71 @@ impl SwapchainState {
                 init_state.physical_device(),
                 init_state.queues().command_fence().unwrap(),
                 init_state.queues().graphics(),
-                self.extent,
+                self.render_extent,
             )?;
             self.output_image_views = Self::create_image_views(
                 init_state.device(),
                 self.image_format,
                 self.output_images(),
             )?;
-            acceleration_structure_state.update_descriptor_sets(
+
+            (self.accumulation_image, self.accumulation_image_memory) = Self::create_storage_image(
+                init_state.instance(),
+                init_state.device(),
+                init_state.physical_device(),
+                init_state.queues().command_fence().unwrap(),
+                init_state.queues().graphics(),
+                self.render_extent,
+                Self::ACCUMULATION_FORMAT,
+            )?;
+            self.accumulation_image_view = Self::create_image_view(
+                init_state.device(),
+                Self::ACCUMULATION_FORMAT,
+                self.accumulation_image,
+            )?;
+
+            (self.variance_image, self.variance_image_memory) = Self::create_storage_image(
+                init_state.instance(),
                 init_state.device(),
-                buffer_state.uniform_buffers(),
-                self.output_image_views(),
+                init_state.physical_device(),
+                init_state.queues().command_fence().unwrap(),
+                init_state.queues().graphics(),
+                self.render_extent,
+                Self::ACCUMULATION_FORMAT,
+            )?;
+            self.variance_image_view = Self::create_image_view(
+                init_state.device(),
+                Self::ACCUMULATION_FORMAT,
+                self.variance_image,
+            )?;
+
+            self.msaa_samples = Self::choose_msaa_samples(
+                init_state.instance(),
+                init_state.physical_device(),
+                self.msaa,
             );
+            let depth = Self::create_attachment_image(
+                init_state.instance(),
+                init_state.device(),
+                init_state.physical_device(),
+                self.extent,
+                self.depth_format,
+                self.msaa_samples,
+                vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT,
+                Self::depth_aspect(self.depth_format),
+            )?;
+            (
+                self.depth_image,
+                self.depth_image_memory,
+                self.depth_image_view,
+            ) = (depth.image, depth.memory, depth.view);
+            self.msaa_color_image = Self::create_msaa_color_image(
+                init_state,
+                self.extent,
+                self.image_format,
+                self.msaa_samples,
+            )?;
+            self.generation += 1;
 
             Ok(())
         }
@@ -161,10 +460,83 @@ impl SwapchainState {
         })
     }
 
-    fn choose_present_mode(present_modes: &[vk::PresentModeKHR]) -> Option<&vk::PresentModeKHR> {
-        present_modes
+    /// FIFO is always supported, so it doubles as the fallback when vsync is off
+    fn choose_present_mode(
+        present_modes: &[vk::PresentModeKHR],
+        vsync: bool,
+    ) -> vk::PresentModeKHR {
+        let preferred: &[vk::PresentModeKHR] = if vsync {
+            &[vk::PresentModeKHR::FIFO]
+        } else {
+            &[vk::PresentModeKHR::MAILBOX, vk::PresentModeKHR::IMMEDIATE]
+        };
+        preferred
             .iter()
-            .find(|p| **p == vk::PresentModeKHR::MAILBOX || **p == vk::PresentModeKHR::FIFO)
+            .find(|mode| present_modes.contains(mode))
+            .copied()
+            .unwrap_or(vk::PresentModeKHR::FIFO)
+    }
+
+    fn choose_depth_format(
+        instance: &ash::Instance,
+        physical_device: vk::PhysicalDevice,
+    ) -> Option<vk::Format> {
+        Self::DEPTH_FORMATS.into_iter().find(|&format| unsafe {
+            instance
+                .get_physical_device_format_properties(physical_device, format)
+                .optimal_tiling_features
+                .contains(vk::FormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT)
+        })
+    }
+
+    /// The most samples up to `msaa` that color and depth attachments both support
+    fn choose_msaa_samples(
+        instance: &ash::Instance,
+        physical_device: vk::PhysicalDevice,
+        msaa: Msaa,
+    ) -> vk::SampleCountFlags {
+        let limits = unsafe {
+            instance
+                .get_physical_device_properties(physical_device)
+                .limits
+        };
+        let supported =
+            limits.framebuffer_color_sample_counts & limits.framebuffer_depth_sample_counts;
+        Msaa::ALL
+            .iter()
+            .rev()
+            .filter(|candidate| **candidate <= msaa)
+            .map(|candidate| vk::SampleCountFlags::from_raw(candidate.samples()))
+            .find(|&samples| supported.contains(samples))
+            .unwrap_or(vk::SampleCountFlags::TYPE_1)
+    }
+
+    fn depth_aspect(format: vk::Format) -> vk::ImageAspectFlags {
+        match format {
+            vk::Format::D32_SFLOAT_S8_UINT | vk::Format::D24_UNORM_S8_UINT => {
+                vk::ImageAspectFlags::DEPTH | vk::ImageAspectFlags::STENCIL
+            }
+            _ => vk::ImageAspectFlags::DEPTH,
+        }
+    }
+
+    /// A `max_image_count` of zero means there is no maximum
+    fn choose_image_count(capabilities: &vk::SurfaceCapabilitiesKHR, desired: Option<u32>) -> u32 {
+        let count = desired
+            .unwrap_or(capabilities.min_image_count + 1)
+            .max(capabilities.min_image_count);
+        if capabilities.max_image_count > 0 {
+            count.min(capabilities.max_image_count)
+        } else {
+            count
+        }
+    }
+
+    fn scale_extent(extent: vk::Extent2D, scale: f32) -> vk::Extent2D {
+        vk::Extent2D {
+            width: ((extent.width as f32 * scale).round() as u32).max(1),
+            height: ((extent.height as f32 * scale).round() as u32).max(1),
+        }
     }
 
     fn choose_extent(capabilities: &vk::SurfaceCapabilitiesKHR, window_size: Vec2) -> vk::Extent2D {
@@ -184,6 +556,7 @@ impl SwapchainState {
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     unsafe fn create_swapchain(
         device: &ash::Device,
         physical_device: vk::PhysicalDevice,
@@ -192,6 +565,8 @@ impl SwapchainState {
         queues: &Queues,
         swapchain_loader: &swapchain::Device,
         window_size: Vec2,
+        vsync: bool,
+        desired_images: Option<u32>,
     ) -> VkResult<(vk::SwapchainKHR, vk::Format, vk::Extent2D, Vec<vk::Image>)> {
         let SwapchainSupportDetails {
             capabilities,
@@ -202,15 +577,11 @@ impl SwapchainState {
         let surface_format =
             Self::choose_surface_format(&formats).ok_or(vk::Result::ERROR_UNKNOWN)?;
 
-        let present_mode =
-            Self::choose_present_mode(&present_modes).ok_or(vk::Result::ERROR_UNKNOWN)?;
+        let present_mode = Self::choose_present_mode(&present_modes, vsync);
 
         let extent = Self::choose_extent(&capabilities, window_size);
 
-        let mut image_count = capabilities.min_image_count + 1;
-        if capabilities.min_image_count > 0 && image_count > capabilities.max_image_count {
-            image_count = capabilities.max_image_count;
-        }
+        let image_count = Self::choose_image_count(&capabilities, desired_images);
 
         let unique_indices: Vec<_> = queues
             .indices()
@@ -239,7 +610,7 @@ impl SwapchainState {
                 .queue_family_indices(&unique_indices)
                 .pre_transform(capabilities.current_transform)
                 .composite_alpha(vk::CompositeAlphaFlagsKHR::OPAQUE)
-                .present_mode(*present_mode)
+                .present_mode(present_mode)
                 .clipped(true),
             None,
         )?;
@@ -253,7 +624,8 @@ impl SwapchainState {
             device.cmd_pipeline_barrier(
                 command_buffer,
                 vk::PipelineStageFlags::TOP_OF_PIPE,
-                vk::PipelineStageFlags::RAY_TRACING_SHADER_KHR,
+                // One-off, and valid whether or not the ray tracing stage exists
+                vk::PipelineStageFlags::ALL_COMMANDS,
                 vk::DependencyFlags::empty(),
                 &[],
                 &[],
@@ -318,6 +690,9 @@ impl SwapchainState {
     }
 
     unsafe fn cleanup_swapchain(&self, init_state: &InitState) {
+        if let Some(present_waiter) = &self.present_waiter {
+            present_waiter.drain();
+        }
         for &image_view in &self.image_views {
             init_state.device().destroy_image_view(image_view, None);
         }
@@ -332,6 +707,43 @@ impl SwapchainState {
             init_state
                 .device()
                 .free_memory(self.output_image_memories[i], None);
+            tracker().untrack_image(self.output_images[i], self.output_image_memories[i]);
+        }
+
+        init_state
+            .device()
+            .destroy_image_view(self.accumulation_image_view, None);
+        init_state
+            .device()
+            .destroy_image(self.accumulation_image, None);
+        init_state
+            .device()
+            .free_memory(self.accumulation_image_memory, None);
+        tracker().untrack_image(self.accumulation_image, self.accumulation_image_memory);
+
+        init_state
+            .device()
+            .destroy_image_view(self.variance_image_view, None);
+        init_state.device().destroy_image(self.variance_image, None);
+        init_state
+            .device()
+            .free_memory(self.variance_image_memory, None);
+        tracker().untrack_image(self.variance_image, self.variance_image_memory);
+
+        init_state
+            .device()
+            .destroy_image_view(self.depth_image_view, None);
+        init_state.device().destroy_image(self.depth_image, None);
+        init_state
+            .device()
+            .free_memory(self.depth_image_memory, None);
+        tracker().untrack_image(self.depth_image, self.depth_image_memory);
+
+        if let Some(msaa) = &self.msaa_color_image {
+            init_state.device().destroy_image_view(msaa.view, None);
+            init_state.device().destroy_image(msaa.image, None);
+            init_state.device().free_memory(msaa.memory, None);
+            tracker().untrack_image(msaa.image, msaa.memory);
         }
 
         self.loader.destroy_swapchain(self.swapchain, None);
@@ -351,75 +763,236 @@ impl SwapchainState {
         queue: &Queue,
         extent: vk::Extent2D,
     ) -> VkResult<(Vec<vk::Image>, Vec<vk::DeviceMemory>)> {
+        let mut images = Vec::with_capacity(MAX_FRAMES_IN_FLIGHT as usize);
+        let mut memories = Vec::with_capacity(MAX_FRAMES_IN_FLIGHT as usize);
+        for _ in 0..MAX_FRAMES_IN_FLIGHT {
+            let (image, memory) = Self::create_storage_image(
+                instance,
+                device,
+                physical_device,
+                command_fence,
+                queue,
+                extent,
+                vk::Format::R8G8B8A8_UNORM, // TODO: check if supported on device
+            )?;
+            images.push(image);
+            memories.push(memory);
+        }
+        Ok((images, memories))
+    }
+
+    /// Left in `UNDEFINED` layout: attachments are cleared or fully overwritten on every use, so
+    /// the render graph transitions them from `UNDEFINED` each frame
+    #[allow(clippy::too_many_arguments)]
+    fn create_attachment_image(
+        instance: &ash::Instance,
+        device: &ash::Device,
+        physical_device: vk::PhysicalDevice,
+        extent: vk::Extent2D,
+        format: vk::Format,
+        samples: vk::SampleCountFlags,
+        usage: vk::ImageUsageFlags,
+        aspect_mask: vk::ImageAspectFlags,
+    ) -> VkResult<AttachmentImage> {
         unsafe {
-            let mut images = Vec::with_capacity(MAX_FRAMES_IN_FLIGHT as usize);
-            let mut memories = Vec::with_capacity(MAX_FRAMES_IN_FLIGHT as usize);
-            for _ in 0..MAX_FRAMES_IN_FLIGHT {
-                let image = device.create_image(
-                    &vk::ImageCreateInfo::default()
-                        .image_type(vk::ImageType::TYPE_2D)
-                        .format(vk::Format::R8G8B8A8_UNORM) // TODO: check if supported on device
-                        .extent(vk::Extent3D {
-                            width: extent.width,
-                            height: extent.height,
-                            depth: 1,
-                        })
-                        .mip_levels(1)
-                        .array_layers(1)
-                        .samples(vk::SampleCountFlags::TYPE_1)
-                        .tiling(vk::ImageTiling::OPTIMAL)
-                        .usage(vk::ImageUsageFlags::STORAGE | vk::ImageUsageFlags::TRANSFER_SRC),
-                    None,
-                )?;
-
-                let memory_requirements = device.get_image_memory_requirements(image);
-                let (memory_type_index, _) = Buffer::find_memory_type(
-                    instance,
-                    physical_device,
-                    memory_requirements.memory_type_bits,
-                    vk::MemoryPropertyFlags::DEVICE_LOCAL,
-                )?;
-
-                let memory = device.allocate_memory(
-                    &vk::MemoryAllocateInfo::default()
-                        .allocation_size(memory_requirements.size)
-                        .memory_type_index(memory_type_index),
-                    None,
-                )?;
-
-                device.bind_image_memory(image, memory, 0)?;
-
-                let command_buffer =
-                    Buffer::begin_single_time_commands(device, queue.command_pool().unwrap())?;
-
-                device.cmd_pipeline_barrier(
-                    command_buffer,
-                    vk::PipelineStageFlags::TOP_OF_PIPE,
-                    vk::PipelineStageFlags::RAY_TRACING_SHADER_KHR,
-                    vk::DependencyFlags::empty(),
-                    &[],
-                    &[],
-                    &[vk::ImageMemoryBarrier::default()
-                        .old_layout(vk::ImageLayout::UNDEFINED)
-                        .new_layout(vk::ImageLayout::GENERAL)
-                        .src_access_mask(vk::AccessFlags::NONE)
-                        .dst_access_mask(vk::AccessFlags::SHADER_WRITE)
-                        .image(image)
-                        .subresource_range(
-                            vk::ImageSubresourceRange::default()
-                                .aspect_mask(vk::ImageAspectFlags::COLOR)
-                                .base_mip_level(0)
-                                .level_count(1)
-                                .base_array_layer(0)
-                                .layer_count(1),
-                        )],
-                );
-
-                Buffer::end_single_time_commands(device, command_buffer, command_fence, queue)?;
-                images.push(image);
-                memories.push(memory);
-            }
-            Ok((images, memories))
+            let image = device.create_image(
+                &vk::ImageCreateInfo::default()
+                    .image_type(vk::ImageType::TYPE_2D)
+                    .format(format)
+                    .extent(vk::Extent3D {
+                        width: extent.width,
+                        height: extent.height,
+                        depth: 1,
+                    })
+                    .mip_levels(1)
+                    .array_layers(1)
+                    .samples(samples)
+                    .tiling(vk::ImageTiling::OPTIMAL)
+                    .usage(usage),
+                None,
+            )?;
+
+            let memory_requirements = device.get_image_memory_requirements(image);
+            let (memory_type_index, _) = Buffer::find_memory_type(
+                instance,
+                physical_device,
+                memory_requirements.memory_type_bits,
+                vk::MemoryPropertyFlags::DEVICE_LOCAL,
+            )?;
+
+            let memory = device.allocate_memory(
+                &vk::MemoryAllocateInfo::default()
+                    .allocation_size(memory_requirements.size)
+                    .memory_type_index(memory_type_index),
+                None,
+            )?;
+
+            device.bind_image_memory(image, memory, 0)?;
+            tracker().track_image(image, memory, memory_requirements.size, "attachment image");
+
+            let view = device.create_image_view(
+                &vk::ImageViewCreateInfo::default()
+                    .image(image)
+                    .view_type(vk::ImageViewType::TYPE_2D)
+                    .format(format)
+                    .subresource_range(
+                        vk::ImageSubresourceRange::default()
+                            .aspect_mask(aspect_mask)
+                            .base_mip_level(0)
+                            .level_count(1)
+                            .base_array_layer(0)
+                            .layer_count(1),
+                    ),
+                None,
+            )?;
+            Ok(AttachmentImage {
+                image,
+                memory,
+                view,
+            })
+        }
+    }
+
+    /// Only resolved from, so its contents never need to reach memory
+    fn create_msaa_color_image(
+        init_state: &InitState,
+        extent: vk::Extent2D,
+        format: vk::Format,
+        samples: vk::SampleCountFlags,
+    ) -> VkResult<Option<AttachmentImage>> {
+        if samples == vk::SampleCountFlags::TYPE_1 {
+            return Ok(None);
         }
+        Self::create_attachment_image(
+            init_state.instance(),
+            init_state.device(),
+            init_state.physical_device(),
+            extent,
+            format,
+            samples,
+            vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSIENT_ATTACHMENT,
+            vk::ImageAspectFlags::COLOR,
+        )
+        .map(Some)
+    }
+
+    /// Creates a device-local image in `GENERAL` layout, ready for shader writes
+    fn create_storage_image(
+        instance: &ash::Instance,
+        device: &ash::Device,
+        physical_device: vk::PhysicalDevice,
+        command_fence: vk::Fence,
+        queue: &Queue,
+        extent: vk::Extent2D,
+        format: vk::Format,
+    ) -> VkResult<(vk::Image, vk::DeviceMemory)> {
+        unsafe {
+            let image = device.create_image(
+                &vk::ImageCreateInfo::default()
+                    .image_type(vk::ImageType::TYPE_2D)
+                    .format(format)
+                    .extent(vk::Extent3D {
+                        width: extent.width,
+                        height: extent.height,
+                        depth: 1,
+                    })
+                    .mip_levels(1)
+                    .array_layers(1)
+                    .samples(vk::SampleCountFlags::TYPE_1)
+                    .tiling(vk::ImageTiling::OPTIMAL)
+                    .usage(vk::ImageUsageFlags::STORAGE | vk::ImageUsageFlags::TRANSFER_SRC),
+                None,
+            )?;
+
+            let memory_requirements = device.get_image_memory_requirements(image);
+            let (memory_type_index, _) = Buffer::find_memory_type(
+                instance,
+                physical_device,
+                memory_requirements.memory_type_bits,
+                vk::MemoryPropertyFlags::DEVICE_LOCAL,
+            )?;
+
+            let memory = device.allocate_memory(
+                &vk::MemoryAllocateInfo::default()
+                    .allocation_size(memory_requirements.size)
+                    .memory_type_index(memory_type_index),
+                None,
+            )?;
+
+            device.bind_image_memory(image, memory, 0)?;
+            tracker().track_image(image, memory, memory_requirements.size, "storage image");
+
+            let command_buffer =
+                Buffer::begin_single_time_commands(device, queue.command_pool().unwrap())?;
+
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::TOP_OF_PIPE,
+                // One-off, and valid whether or not the ray tracing stage exists
+                vk::PipelineStageFlags::ALL_COMMANDS,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[vk::ImageMemoryBarrier::default()
+                    .old_layout(vk::ImageLayout::UNDEFINED)
+                    .new_layout(vk::ImageLayout::GENERAL)
+                    .src_access_mask(vk::AccessFlags::NONE)
+                    .dst_access_mask(vk::AccessFlags::SHADER_WRITE)
+                    .image(image)
+                    .subresource_range(
+                        vk::ImageSubresourceRange::default()
+                            .aspect_mask(vk::ImageAspectFlags::COLOR)
+                            .base_mip_level(0)
+                            .level_count(1)
+                            .base_array_layer(0)
+                            .layer_count(1),
+                    )],
+            );
+
+            Buffer::end_single_time_commands(device, command_buffer, command_fence, queue)?;
+            Ok((image, memory))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn each_frame_is_rewritten_once_per_generation() {
+        let mut versions = FrameDescriptorVersions::default();
+        assert!(versions.update(0, 0));
+        assert!(versions.update(1, 0));
+        assert!(!versions.update(0, 0));
+
+        // After a recreation, each frame is rewritten when it is next recorded
+        assert!(versions.update(0, 1));
+        assert!(!versions.update(0, 1));
+        assert!(versions.update(1, 1));
+    }
+
+    #[test]
+    fn image_count_stays_within_the_surface_limits() {
+        let capabilities = vk::SurfaceCapabilitiesKHR {
+            min_image_count: 2,
+            max_image_count: 4,
+            ..Default::default()
+        };
+        assert_eq!(SwapchainState::choose_image_count(&capabilities, None), 3);
+        assert_eq!(
+            SwapchainState::choose_image_count(&capabilities, Some(1)),
+            2
+        );
+        assert_eq!(
+            SwapchainState::choose_image_count(&capabilities, Some(8)),
+            4
+        );
+
+        let unbounded = vk::SurfaceCapabilitiesKHR {
+            max_image_count: 0,
+            ..capabilities
+        };
+        assert_eq!(SwapchainState::choose_image_count(&unbounded, Some(8)), 8);
     }
 }
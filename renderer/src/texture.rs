@@ -0,0 +1,426 @@
+use std::error::Error;
+
+use ash::{prelude::VkResult, vk};
+use thiserror::Error;
+
+use crate::{buffer::Buffer, init_state::InitState, tracking::tracker};
+
+/// How a texture is filtered and addressed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SamplerConfig {
+    pub mag_filter: vk::Filter,
+    pub min_filter: vk::Filter,
+    pub mipmap_mode: vk::SamplerMipmapMode,
+    pub address_mode: vk::SamplerAddressMode,
+    /// Requested anisotropy, limited to the device's maximum; `None` disables it
+    pub max_anisotropy: Option<f32>,
+    /// Generate the full mip chain on upload
+    pub mipmaps: bool,
+}
+
+impl SamplerConfig {
+    /// Trilinear with anisotropic filtering, for photographic textures
+    pub const SMOOTH: Self = Self {
+        mag_filter: vk::Filter::LINEAR,
+        min_filter: vk::Filter::LINEAR,
+        mipmap_mode: vk::SamplerMipmapMode::LINEAR,
+        address_mode: vk::SamplerAddressMode::REPEAT,
+        max_anisotropy: Some(16.0),
+        mipmaps: true,
+    };
+
+    /// Keeps texels sharp up close while still mipmapping in the distance, for voxel faces
+    pub const PIXELATED: Self = Self {
+        mag_filter: vk::Filter::NEAREST,
+        min_filter: vk::Filter::NEAREST,
+        mipmap_mode: vk::SamplerMipmapMode::NEAREST,
+        address_mode: vk::SamplerAddressMode::REPEAT,
+        max_anisotropy: None,
+        mipmaps: true,
+    };
+}
+
+impl Default for SamplerConfig {
+    fn default() -> Self {
+        Self::SMOOTH
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum TextureError {
+    #[error(
+        "expected {expected} bytes of RGBA8 data for a {width}x{height} texture, got {actual}"
+    )]
+    WrongLength {
+        width: u32,
+        height: u32,
+        expected: usize,
+        actual: usize,
+    },
+}
+
+/// Number of levels in a full mip chain down to 1x1
+pub fn mip_level_count(width: u32, height: u32) -> u32 {
+    u32::BITS - width.max(height).max(1).leading_zeros()
+}
+
+/// A sampled RGBA8 texture with its own sampler, left in `SHADER_READ_ONLY_OPTIMAL`
+pub struct Texture {
+    image: vk::Image,
+    memory: vk::DeviceMemory,
+    view: vk::ImageView,
+    sampler: vk::Sampler,
+    extent: vk::Extent2D,
+    mip_levels: u32,
+}
+
+impl Texture {
+    pub const FORMAT: vk::Format = vk::Format::R8G8B8A8_UNORM;
+
+    /// Stages that may sample textures. Uploads are one-off, so waiting on every stage costs
+    /// nothing and stays valid on devices without the ray tracing stage.
+    const SAMPLING_STAGES: vk::PipelineStageFlags = vk::PipelineStageFlags::ALL_COMMANDS;
+
+    pub const fn view(&self) -> vk::ImageView {
+        self.view
+    }
+
+    pub const fn sampler(&self) -> vk::Sampler {
+        self.sampler
+    }
+
+    pub const fn extent(&self) -> vk::Extent2D {
+        self.extent
+    }
+
+    pub const fn mip_levels(&self) -> u32 {
+        self.mip_levels
+    }
+
+    /// Uploads `rgba8` (row by row) and, if `config` asks for it, generates the mip chain by
+    /// repeatedly blitting each level into the next. Falls back to a single level when the
+    /// device cannot linearly filter blits of [`Self::FORMAT`].
+    pub fn new(
+        init_state: &InitState,
+        width: u32,
+        height: u32,
+        rgba8: &[u8],
+        config: SamplerConfig,
+    ) -> Result<Self, Box<dyn Error>> {
+        let expected = (width * height * 4) as usize;
+        if rgba8.len() != expected {
+            return Err(TextureError::WrongLength {
+                width,
+                height,
+                expected,
+                actual: rgba8.len(),
+            }
+            .into());
+        }
+
+        unsafe {
+            let device = init_state.device();
+            let extent = vk::Extent2D { width, height };
+            let mip_levels = if config.mipmaps && Self::supports_linear_blit(init_state) {
+                mip_level_count(width, height)
+            } else {
+                1
+            };
+
+            let image = device.create_image(
+                &vk::ImageCreateInfo::default()
+                    .image_type(vk::ImageType::TYPE_2D)
+                    .format(Self::FORMAT)
+                    .extent(extent.into())
+                    .mip_levels(mip_levels)
+                    .array_layers(1)
+                    .samples(vk::SampleCountFlags::TYPE_1)
+                    .tiling(vk::ImageTiling::OPTIMAL)
+                    .usage(
+                        vk::ImageUsageFlags::SAMPLED
+                            | vk::ImageUsageFlags::TRANSFER_SRC
+                            | vk::ImageUsageFlags::TRANSFER_DST,
+                    ),
+                None,
+            )?;
+
+            let memory_requirements = device.get_image_memory_requirements(image);
+            let (memory_type_index, _) = Buffer::find_memory_type(
+                init_state.instance(),
+                init_state.physical_device(),
+                memory_requirements.memory_type_bits,
+                vk::MemoryPropertyFlags::DEVICE_LOCAL,
+            )?;
+            let memory = device.allocate_memory(
+                &vk::MemoryAllocateInfo::default()
+                    .allocation_size(memory_requirements.size)
+                    .memory_type_index(memory_type_index),
+                None,
+            )?;
+            device.bind_image_memory(image, memory, 0)?;
+            tracker().track_image(image, memory, memory_requirements.size, "texture");
+
+            Self::upload(init_state, image, extent, mip_levels, rgba8)?;
+
+            let view = device.create_image_view(
+                &vk::ImageViewCreateInfo::default()
+                    .image(image)
+                    .view_type(vk::ImageViewType::TYPE_2D)
+                    .format(Self::FORMAT)
+                    .subresource_range(Self::subresource_range(0, mip_levels)),
+                None,
+            )?;
+
+            let sampler = Self::create_sampler(init_state, &config, mip_levels)?;
+
+            Ok(Self {
+                image,
+                memory,
+                view,
+                sampler,
+                extent,
+                mip_levels,
+            })
+        }
+    }
+
+    fn supports_linear_blit(init_state: &InitState) -> bool {
+        unsafe {
+            init_state
+                .instance()
+                .get_physical_device_format_properties(init_state.physical_device(), Self::FORMAT)
+                .optimal_tiling_features
+                .contains(
+                    vk::FormatFeatureFlags::BLIT_SRC
+                        | vk::FormatFeatureFlags::BLIT_DST
+                        | vk::FormatFeatureFlags::SAMPLED_IMAGE_FILTER_LINEAR,
+                )
+        }
+    }
+
+    unsafe fn create_sampler(
+        init_state: &InitState,
+        config: &SamplerConfig,
+        mip_levels: u32,
+    ) -> VkResult<vk::Sampler> {
+        let max_device_anisotropy = init_state
+            .instance()
+            .get_physical_device_properties(init_state.physical_device())
+            .limits
+            .max_sampler_anisotropy;
+        let max_anisotropy = config
+            .max_anisotropy
+            .map(|anisotropy| anisotropy.clamp(1.0, max_device_anisotropy));
+
+        init_state.device().create_sampler(
+            &vk::SamplerCreateInfo::default()
+                .mag_filter(config.mag_filter)
+                .min_filter(config.min_filter)
+                .mipmap_mode(config.mipmap_mode)
+                .address_mode_u(config.address_mode)
+                .address_mode_v(config.address_mode)
+                .address_mode_w(config.address_mode)
+                .anisotropy_enable(max_anisotropy.is_some())
+                .max_anisotropy(max_anisotropy.unwrap_or(1.0))
+                .min_lod(0.0)
+                .max_lod(mip_levels as f32),
+            None,
+        )
+    }
+
+    unsafe fn upload(
+        init_state: &InitState,
+        image: vk::Image,
+        extent: vk::Extent2D,
+        mip_levels: u32,
+        bytes: &[u8],
+    ) -> VkResult<()> {
+        let device = init_state.device();
+        let queue = init_state.queues().graphics();
+
+        let mut staging = Buffer::create(
+            init_state.instance(),
+            device,
+            init_state.physical_device(),
+            bytes.len() as vk::DeviceSize,
+            vk::BufferUsageFlags::TRANSFER_SRC,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+        )?;
+        staging.map_memory(device, 0, vk::MemoryMapFlags::empty())?;
+        staging.write(bytes);
+        staging.unmap_memory(device)?;
+
+        let command_buffer =
+            Buffer::begin_single_time_commands(device, queue.command_pool().unwrap())?;
+        let barrier = |levels: (u32, u32), old_layout, new_layout, src_access, dst_access| {
+            vk::ImageMemoryBarrier::default()
+                .old_layout(old_layout)
+                .new_layout(new_layout)
+                .src_access_mask(src_access)
+                .dst_access_mask(dst_access)
+                .image(image)
+                .subresource_range(Self::subresource_range(levels.0, levels.1))
+        };
+
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::TOP_OF_PIPE,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &[barrier(
+                (0, mip_levels),
+                vk::ImageLayout::UNDEFINED,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                vk::AccessFlags::NONE,
+                vk::AccessFlags::TRANSFER_WRITE,
+            )],
+        );
+        device.cmd_copy_buffer_to_image(
+            command_buffer,
+            staging.handle(),
+            image,
+            vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            &[vk::BufferImageCopy::default()
+                .image_subresource(Self::subresource_layers(0))
+                .image_extent(extent.into())],
+        );
+
+        // Each level is written, then becomes the source of the next and is done
+        let (mut width, mut height) = (extent.width as i32, extent.height as i32);
+        for level in 1..mip_levels {
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[barrier(
+                    (level - 1, 1),
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                    vk::AccessFlags::TRANSFER_WRITE,
+                    vk::AccessFlags::TRANSFER_READ,
+                )],
+            );
+
+            let (next_width, next_height) = ((width / 2).max(1), (height / 2).max(1));
+            device.cmd_blit_image(
+                command_buffer,
+                image,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                image,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                &[vk::ImageBlit::default()
+                    .src_subresource(Self::subresource_layers(level - 1))
+                    .src_offsets([
+                        vk::Offset3D::default(),
+                        vk::Offset3D {
+                            x: width,
+                            y: height,
+                            z: 1,
+                        },
+                    ])
+                    .dst_subresource(Self::subresource_layers(level))
+                    .dst_offsets([
+                        vk::Offset3D::default(),
+                        vk::Offset3D {
+                            x: next_width,
+                            y: next_height,
+                            z: 1,
+                        },
+                    ])],
+                vk::Filter::LINEAR,
+            );
+
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::TRANSFER,
+                Self::SAMPLING_STAGES,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[barrier(
+                    (level - 1, 1),
+                    vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                    vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                    vk::AccessFlags::TRANSFER_READ,
+                    vk::AccessFlags::SHADER_READ,
+                )],
+            );
+            (width, height) = (next_width, next_height);
+        }
+
+        // The last level was only ever written
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::TRANSFER,
+            Self::SAMPLING_STAGES,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &[barrier(
+                (mip_levels - 1, 1),
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                vk::AccessFlags::TRANSFER_WRITE,
+                vk::AccessFlags::SHADER_READ,
+            )],
+        );
+
+        Buffer::end_single_time_commands(
+            device,
+            command_buffer,
+            init_state.queues().command_fence().unwrap(),
+            queue,
+        )?;
+        staging.cleanup(device);
+        Ok(())
+    }
+
+    const fn subresource_range(base_mip_level: u32, level_count: u32) -> vk::ImageSubresourceRange {
+        vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            base_mip_level,
+            level_count,
+            base_array_layer: 0,
+            layer_count: 1,
+        }
+    }
+
+    const fn subresource_layers(mip_level: u32) -> vk::ImageSubresourceLayers {
+        vk::ImageSubresourceLayers {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            mip_level,
+            base_array_layer: 0,
+            layer_count: 1,
+        }
+    }
+
+    pub fn cleanup(&self, init_state: &InitState) {
+        unsafe {
+            let device = init_state.device();
+            device.destroy_sampler(self.sampler, None);
+            device.destroy_image_view(self.view, None);
+            device.destroy_image(self.image, None);
+            device.free_memory(self.memory, None);
+        }
+        tracker().untrack_image(self.image, self.memory);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mip_chain_ends_at_one_texel() {
+        assert_eq!(mip_level_count(1, 1), 1);
+        assert_eq!(mip_level_count(16, 16), 5);
+        assert_eq!(mip_level_count(16, 4), 5);
+        assert_eq!(mip_level_count(17, 3), 5);
+        assert_eq!(mip_level_count(0, 0), 1);
+    }
+}
@@ -0,0 +1,231 @@
+use std::{
+    collections::BTreeMap,
+    fmt,
+    sync::{Mutex, MutexGuard},
+};
+
+use ash::vk::{self, Handle};
+
+/// Kinds of Vulkan objects whose lifetimes are tracked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ObjectKind {
+    Buffer,
+    Image,
+    Memory,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct LiveObject {
+    tag: &'static str,
+    /// Allocation size for memory; zero for objects, whose bytes are counted on their memory
+    size: u64,
+    /// Memory the object is bound to
+    memory: Option<u64>,
+}
+
+/// Live buffers, images and memory allocations. Every `cleanup` destroys its objects by hand,
+/// so this is what notices the ones that were forgotten or freed in the wrong order.
+#[derive(Debug, Default)]
+pub struct Tracker {
+    live: BTreeMap<(ObjectKind, u64), LiveObject>,
+}
+
+impl Tracker {
+    pub const fn new() -> Self {
+        Self {
+            live: BTreeMap::new(),
+        }
+    }
+
+    pub fn track_memory(&mut self, memory: vk::DeviceMemory, size: u64, tag: &'static str) {
+        self.insert(ObjectKind::Memory, memory.as_raw(), tag, size, None);
+    }
+
+    /// A buffer or image, bound to `memory` if it isn't null
+    pub fn track_object(
+        &mut self,
+        kind: ObjectKind,
+        handle: impl Handle,
+        memory: vk::DeviceMemory,
+        tag: &'static str,
+    ) {
+        let memory = (memory != vk::DeviceMemory::null()).then(|| memory.as_raw());
+        self.insert(kind, handle.as_raw(), tag, 0, memory);
+    }
+
+    /// An image with its own dedicated memory allocation
+    pub fn track_image(
+        &mut self,
+        image: vk::Image,
+        memory: vk::DeviceMemory,
+        size: u64,
+        tag: &'static str,
+    ) {
+        self.track_memory(memory, size, tag);
+        self.track_object(ObjectKind::Image, image, memory, tag);
+    }
+
+    /// Counterpart of [`Self::track_image`]
+    pub fn untrack_image(&mut self, image: vk::Image, memory: vk::DeviceMemory) {
+        self.untrack_object(ObjectKind::Image, image);
+        self.untrack_memory(memory);
+    }
+
+    fn insert(
+        &mut self,
+        kind: ObjectKind,
+        raw: u64,
+        tag: &'static str,
+        size: u64,
+        memory: Option<u64>,
+    ) {
+        let previous = self
+            .live
+            .insert((kind, raw), LiveObject { tag, size, memory });
+        debug_assert!(
+            previous.is_none(),
+            "{kind:?} {raw:#x} ({tag}) tracked while still live"
+        );
+    }
+
+    /// Panics in debug builds if the object wasn't live, e.g. when destroyed twice
+    pub fn untrack_object(&mut self, kind: ObjectKind, handle: impl Handle) {
+        let raw = handle.as_raw();
+        let removed = self.live.remove(&(kind, raw));
+        debug_assert!(
+            removed.is_some(),
+            "{kind:?} {raw:#x} destroyed while not live"
+        );
+    }
+
+    /// Panics in debug builds if a buffer or image is still bound to the memory, since it must
+    /// be destroyed first
+    pub fn untrack_memory(&mut self, memory: vk::DeviceMemory) {
+        let raw = memory.as_raw();
+        if cfg!(debug_assertions) {
+            if let Some(((kind, handle), object)) = self
+                .live
+                .iter()
+                .find(|(_, object)| object.memory == Some(raw))
+            {
+                panic!(
+                    "memory {raw:#x} freed before the {kind:?} {handle:#x} ({}) bound to it",
+                    object.tag
+                );
+            }
+        }
+        self.untrack_object(ObjectKind::Memory, memory);
+    }
+
+    /// Live objects grouped by kind and tag
+    pub fn report(&self) -> TrackingReport {
+        let mut groups = BTreeMap::<(ObjectKind, &'static str), (usize, u64)>::new();
+        for ((kind, _), object) in &self.live {
+            let (count, bytes) = groups.entry((*kind, object.tag)).or_default();
+            *count += 1;
+            *bytes += object.size;
+        }
+        TrackingReport {
+            entries: groups
+                .into_iter()
+                .map(|((kind, tag), (count, bytes))| ReportEntry {
+                    kind,
+                    tag,
+                    count,
+                    bytes,
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReportEntry {
+    pub kind: ObjectKind,
+    pub tag: &'static str,
+    pub count: usize,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TrackingReport {
+    pub entries: Vec<ReportEntry>,
+}
+
+impl TrackingReport {
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn count(&self, kind: ObjectKind) -> usize {
+        self.entries
+            .iter()
+            .filter(|entry| entry.kind == kind)
+            .map(|entry| entry.count)
+            .sum()
+    }
+}
+
+impl fmt::Display for TrackingReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "No live Vulkan objects");
+        }
+        writeln!(f, "Live Vulkan objects:")?;
+        for entry in &self.entries {
+            write!(f, "  {:?} x{} {}", entry.kind, entry.count, entry.tag)?;
+            if entry.bytes > 0 {
+                write!(f, " ({} KiB)", entry.bytes.div_ceil(1024))?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+static TRACKER: Mutex<Tracker> = Mutex::new(Tracker::new());
+
+/// The process-wide tracker that the renderer's objects register with
+pub fn tracker() -> MutexGuard<'static, Tracker> {
+    // A panic while tracking leaves the counts intact, so keep going
+    TRACKER.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_counts_live_objects_by_tag() {
+        let mut tracker = Tracker::new();
+        let memory = vk::DeviceMemory::from_raw(1);
+        tracker.track_memory(memory, 2048, "light");
+        tracker.track_object(ObjectKind::Buffer, vk::Buffer::from_raw(2), memory, "light");
+        tracker.track_object(
+            ObjectKind::Image,
+            vk::Image::from_raw(3),
+            vk::DeviceMemory::null(),
+            "swapchain",
+        );
+
+        let report = tracker.report();
+        assert_eq!(report.count(ObjectKind::Buffer), 1);
+        assert_eq!(report.entries[2].bytes, 2048);
+
+        tracker.untrack_object(ObjectKind::Buffer, vk::Buffer::from_raw(2));
+        tracker.untrack_memory(memory);
+        tracker.untrack_object(ObjectKind::Image, vk::Image::from_raw(3));
+        assert!(tracker.report().is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "freed before the Buffer")]
+    #[cfg(debug_assertions)]
+    fn freeing_bound_memory_first_panics() {
+        let mut tracker = Tracker::new();
+        let memory = vk::DeviceMemory::from_raw(1);
+        tracker.track_memory(memory, 64, "pick");
+        tracker.track_object(ObjectKind::Buffer, vk::Buffer::from_raw(2), memory, "pick");
+        tracker.untrack_memory(memory);
+    }
+}
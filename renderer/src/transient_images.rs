@@ -0,0 +1,212 @@
+use ash::{prelude::VkResult, vk};
+
+use crate::{
+    init_state::InitState,
+    tracking::{tracker, ObjectKind},
+};
+
+/// An image a [`RenderGraph`](crate::render_graph::RenderGraph) creates for one frame instead
+/// of importing. Its contents don't outlive the frame, so transient images whose passes don't
+/// overlap can share memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TransientImageDesc {
+    pub format: vk::Format,
+    pub extent: vk::Extent2D,
+    pub samples: vk::SampleCountFlags,
+    pub usage: vk::ImageUsageFlags,
+    pub aspect_mask: vk::ImageAspectFlags,
+}
+
+impl TransientImageDesc {
+    /// Single-sampled, e.g. an intermediate storage or color image
+    pub const fn new(
+        format: vk::Format,
+        extent: vk::Extent2D,
+        usage: vk::ImageUsageFlags,
+        aspect_mask: vk::ImageAspectFlags,
+    ) -> Self {
+        Self {
+            format,
+            extent,
+            samples: vk::SampleCountFlags::TYPE_1,
+            usage,
+            aspect_mask,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransientImage {
+    pub image: vk::Image,
+    pub view: vk::ImageView,
+}
+
+/// Backing images and memory for one frame in flight's transient images. Images the graph put
+/// in the same memory slot are bound to the same allocation, sized for the largest of them.
+/// Kept across frames and only rebuilt when the graph asks for different images, e.g. after a
+/// resize.
+pub struct TransientImages {
+    memory_properties: vk::PhysicalDeviceMemoryProperties,
+    /// What `images` was created for: each image's description and memory slot
+    requests: Vec<(TransientImageDesc, usize)>,
+    images: Vec<TransientImage>,
+    memories: Vec<(vk::DeviceMemory, vk::DeviceSize)>,
+}
+
+impl TransientImages {
+    pub fn new(init_state: &InitState) -> Self {
+        let memory_properties = unsafe {
+            init_state
+                .instance()
+                .get_physical_device_memory_properties(init_state.physical_device())
+        };
+        Self {
+            memory_properties,
+            requests: Vec::new(),
+            images: Vec::new(),
+            memories: Vec::new(),
+        }
+    }
+
+    /// Images for `requests`, in the same order. Only call once the frame that last used
+    /// these images has finished on the GPU, since they may be recreated.
+    pub fn realize(
+        &mut self,
+        device: &ash::Device,
+        requests: &[(TransientImageDesc, usize)],
+    ) -> VkResult<&[TransientImage]> {
+        if requests != self.requests {
+            self.cleanup(device);
+            unsafe { self.create(device, requests)? };
+            self.requests = requests.to_vec();
+        }
+        Ok(&self.images)
+    }
+
+    /// Bytes of device memory currently allocated
+    pub fn allocated_size(&self) -> vk::DeviceSize {
+        self.memories.iter().map(|(_, size)| size).sum()
+    }
+
+    unsafe fn create(
+        &mut self,
+        device: &ash::Device,
+        requests: &[(TransientImageDesc, usize)],
+    ) -> VkResult<()> {
+        let images = requests
+            .iter()
+            .map(|(desc, _)| {
+                device.create_image(
+                    &vk::ImageCreateInfo::default()
+                        .image_type(vk::ImageType::TYPE_2D)
+                        .format(desc.format)
+                        .extent(vk::Extent3D {
+                            width: desc.extent.width,
+                            height: desc.extent.height,
+                            depth: 1,
+                        })
+                        .mip_levels(1)
+                        .array_layers(1)
+                        .samples(desc.samples)
+                        .tiling(vk::ImageTiling::OPTIMAL)
+                        .usage(desc.usage),
+                    None,
+                )
+            })
+            .collect::<VkResult<Vec<_>>>()?;
+        let requirements: Vec<_> = images
+            .iter()
+            .map(|&image| device.get_image_memory_requirements(image))
+            .collect();
+
+        // One allocation per slot, unless its images can't live in the same memory type
+        let mut allocations: Vec<(usize, vk::MemoryRequirements)> = Vec::new();
+        let mut bindings = Vec::with_capacity(images.len());
+        for ((_, slot), requirements) in requests.iter().zip(&requirements) {
+            let shared = allocations
+                .iter()
+                .position(|(allocation_slot, allocation)| {
+                    allocation_slot == slot
+                        && allocation.memory_type_bits & requirements.memory_type_bits != 0
+                });
+            let allocation = match shared {
+                Some(allocation) => {
+                    let (_, shared) = &mut allocations[allocation];
+                    shared.size = shared.size.max(requirements.size);
+                    shared.memory_type_bits &= requirements.memory_type_bits;
+                    allocation
+                }
+                None => {
+                    allocations.push((*slot, *requirements));
+                    allocations.len() - 1
+                }
+            };
+            bindings.push(allocation);
+        }
+
+        for (_, requirements) in &allocations {
+            let memory = device.allocate_memory(
+                &vk::MemoryAllocateInfo::default()
+                    .allocation_size(requirements.size)
+                    .memory_type_index(
+                        self.device_local_memory_type(requirements.memory_type_bits)?,
+                    ),
+                None,
+            )?;
+            tracker().track_memory(memory, requirements.size, "transient image");
+            self.memories.push((memory, requirements.size));
+        }
+
+        for ((image, allocation), (desc, _)) in images.iter().zip(&bindings).zip(requests) {
+            let memory = self.memories[*allocation].0;
+            device.bind_image_memory(*image, memory, 0)?;
+            tracker().track_object(ObjectKind::Image, *image, memory, "transient image");
+            let view = device.create_image_view(
+                &vk::ImageViewCreateInfo::default()
+                    .image(*image)
+                    .view_type(vk::ImageViewType::TYPE_2D)
+                    .format(desc.format)
+                    .subresource_range(
+                        vk::ImageSubresourceRange::default()
+                            .aspect_mask(desc.aspect_mask)
+                            .base_mip_level(0)
+                            .level_count(1)
+                            .base_array_layer(0)
+                            .layer_count(1),
+                    ),
+                None,
+            )?;
+            self.images.push(TransientImage {
+                image: *image,
+                view,
+            });
+        }
+        Ok(())
+    }
+
+    fn device_local_memory_type(&self, type_bits: u32) -> VkResult<u32> {
+        (0..self.memory_properties.memory_type_count)
+            .find(|&i| {
+                type_bits & (1 << i) != 0
+                    && self.memory_properties.memory_types[i as usize]
+                        .property_flags
+                        .contains(vk::MemoryPropertyFlags::DEVICE_LOCAL)
+            })
+            .ok_or(vk::Result::ERROR_UNKNOWN)
+    }
+
+    pub fn cleanup(&mut self, device: &ash::Device) {
+        unsafe {
+            for image in self.images.drain(..) {
+                device.destroy_image_view(image.view, None);
+                device.destroy_image(image.image, None);
+                tracker().untrack_object(ObjectKind::Image, image.image);
+            }
+            for (memory, _) in self.memories.drain(..) {
+                device.free_memory(memory, None);
+                tracker().untrack_memory(memory);
+            }
+        }
+        self.requests.clear();
+    }
+}
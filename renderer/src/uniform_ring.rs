@@ -0,0 +1,101 @@
+use ash::{prelude::VkResult, vk};
+
+use crate::buffer::Buffer;
+
+/// One persistently mapped uniform buffer with a slot per frame in flight, bound once as a
+/// `UNIFORM_BUFFER_DYNAMIC` descriptor and selected per frame with [`Self::offset`]. Adding a
+/// uniform block means growing the slot, not allocating and writing descriptors for another
+/// buffer per frame.
+pub struct UniformRing<'a> {
+    buffer: Buffer<'a>,
+    slot_size: u64,
+    frames: u8,
+}
+
+impl<'a> UniformRing<'a> {
+    /// `block_size` is rounded up to the device's `minUniformBufferOffsetAlignment`
+    pub fn new(
+        instance: &ash::Instance,
+        device: &ash::Device,
+        physical_device: vk::PhysicalDevice,
+        block_size: u64,
+        frames: u8,
+    ) -> VkResult<Self> {
+        let alignment = unsafe {
+            instance
+                .get_physical_device_properties(physical_device)
+                .limits
+                .min_uniform_buffer_offset_alignment
+        };
+        let slot_size = align_up(block_size, alignment);
+
+        let mut buffer = Buffer::create(
+            instance,
+            device,
+            physical_device,
+            slot_size * frames as u64,
+            vk::BufferUsageFlags::UNIFORM_BUFFER,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+        )?;
+        buffer.map_memory(device, 0, vk::MemoryMapFlags::empty())?;
+
+        Ok(Self {
+            buffer,
+            slot_size,
+            frames,
+        })
+    }
+
+    pub const fn buffer(&self) -> &Buffer<'a> {
+        &self.buffer
+    }
+
+    /// Aligned size of each frame's slot
+    pub const fn slot_size(&self) -> u64 {
+        self.slot_size
+    }
+
+    /// Dynamic offset selecting `frame`'s slot when binding the descriptor set
+    pub fn offset(&self, frame: u8) -> u32 {
+        debug_assert!(frame < self.frames);
+        (self.slot_size * frame as u64) as u32
+    }
+
+    /// Overwrites the start of `frame`'s slot. Only call once the frame's fence has signaled.
+    pub fn write(&mut self, frame: u8, bytes: &[u8]) {
+        assert!(
+            bytes.len() as u64 <= self.slot_size,
+            "uniform data exceeds slot"
+        );
+        let offset = self.offset(frame) as usize;
+        let mapped = self
+            .buffer
+            .mapped_mut()
+            .as_deref_mut()
+            .expect("uniform ring is persistently mapped");
+        mapped[offset..offset + bytes.len()].copy_from_slice(bytes);
+    }
+
+    pub fn cleanup(&mut self, device: &ash::Device) {
+        self.buffer.cleanup(device);
+    }
+}
+
+/// Rounds `size` up to a multiple of `alignment`, which Vulkan guarantees is a power of two
+pub fn align_up(size: u64, alignment: u64) -> u64 {
+    debug_assert!(alignment.is_power_of_two());
+    (size + alignment - 1) & !(alignment - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slots_are_aligned() {
+        assert_eq!(align_up(144, 256), 256);
+        assert_eq!(align_up(256, 256), 256);
+        assert_eq!(align_up(257, 64), 320);
+        assert_eq!(align_up(0, 16), 0);
+    }
+}
@@ -0,0 +1,194 @@
+use std::collections::HashMap;
+
+use app::localization::Localization;
+use bevy_app::{App, Plugin, Update};
+use bevy_ecs::{
+    entity::Entity,
+    event::{Event, Events},
+    query::Added,
+    removal_detection::RemovedComponents,
+    schedule::IntoSystemConfigs,
+    system::{Local, Query, Res},
+    world::World,
+};
+use data::chat::{chat_command, sanitize_chat, ChatMessage};
+
+use crate::{
+    console_plugin::{run_command, ConsoleCommand},
+    interest_plugin::{Client, Outbox, ServerMessage},
+};
+
+/// Relays chat from each [`Client`] to all of them under the sender's name, and announces
+/// players joining and leaving. Lines starting with `/` are run as [`ConsoleCommand`]s, for
+/// operators only; their output goes to the server console.
+pub struct ChatPlugin;
+
+impl Plugin for ChatPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ClientChat>()
+            .add_systems(Update, (announce_clients, relay_chat).chain());
+    }
+}
+
+/// A line of chat from a client, sent by the transport
+#[derive(Event, Debug, Clone, PartialEq, Eq)]
+pub struct ClientChat {
+    pub client: Entity,
+    pub text: String,
+}
+
+fn broadcast<'a>(outboxes: impl Iterator<Item = &'a mut Outbox>, message: &ChatMessage) {
+    println!("{}", message.line());
+    for outbox in outboxes {
+        outbox.0.push(ServerMessage::Chat(message.clone()));
+    }
+}
+
+fn announce_clients(
+    localization: Res<Localization>,
+    joined: Query<(Entity, &Client), Added<Client>>,
+    mut left: RemovedComponents<Client>,
+    mut outboxes: Query<&mut Outbox>,
+    mut names: Local<HashMap<Entity, String>>,
+) {
+    let mut announcements = Vec::new();
+    for (entity, client) in &joined {
+        names.insert(entity, client.name.clone());
+        announcements
+            .push(localization.format("player-joined", &[("player", client.name.as_str().into())]));
+    }
+    for entity in left.read() {
+        if let Some(name) = names.remove(&entity) {
+            announcements.push(localization.format("player-left", &[("player", name.into())]));
+        }
+    }
+    for announcement in announcements {
+        broadcast(
+            outboxes.iter_mut().map(|outbox| outbox.into_inner()),
+            &ChatMessage::system(announcement),
+        );
+    }
+}
+
+/// Exclusive, since commands get the whole world like they do from the console
+fn relay_chat(world: &mut World) {
+    let lines: Vec<ClientChat> = world.resource_mut::<Events<ClientChat>>().drain().collect();
+    if lines.is_empty() {
+        return;
+    }
+    let localization = world.resource::<Localization>().clone();
+    for ClientChat { client, text } in lines {
+        let Some(sender) = world.get::<Client>(client).cloned() else {
+            continue;
+        };
+        let Some(text) = sanitize_chat(&text) else {
+            continue;
+        };
+        let Some(command) = chat_command(&text) else {
+            let message = ChatMessage::player(sender.name, text);
+            let mut outboxes = world.query::<&mut Outbox>();
+            broadcast(
+                outboxes.iter_mut(world).map(|outbox| outbox.into_inner()),
+                &message,
+            );
+            continue;
+        };
+
+        let reply = if !sender.operator {
+            localization.text("not-operator")
+        } else {
+            match command.parse::<ConsoleCommand>() {
+                Ok(parsed) => {
+                    println!(
+                        "{}",
+                        localization.format(
+                            "command-from-chat",
+                            &[
+                                ("player", sender.name.as_str().into()),
+                                ("command", command.into())
+                            ],
+                        )
+                    );
+                    run_command(world, &localization, parsed);
+                    localization.format("command-ran", &[("command", command.into())])
+                }
+                Err(e) => e.localized(&localization),
+            }
+        };
+        if let Some(mut outbox) = world.get_mut::<Outbox>(client) {
+            outbox
+                .0
+                .push(ServerMessage::Chat(ChatMessage::system(reply)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_app::App;
+
+    use super::*;
+
+    fn chat(outbox: &Outbox) -> Vec<String> {
+        outbox
+            .0
+            .iter()
+            .filter_map(|message| match message {
+                ServerMessage::Chat(message) => Some(message.line()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn chat_reaches_every_client_and_commands_need_an_operator() {
+        let mut app = App::new();
+        app.init_resource::<Localization>().add_plugins(ChatPlugin);
+        let amy = app
+            .world_mut()
+            .spawn((
+                Client {
+                    name: "amy".to_owned(),
+                    operator: false,
+                },
+                Outbox::default(),
+            ))
+            .id();
+        let bob = app
+            .world_mut()
+            .spawn((
+                Client {
+                    name: "bob".to_owned(),
+                    operator: false,
+                },
+                Outbox::default(),
+            ))
+            .id();
+        app.update();
+
+        app.world_mut().send_event(ClientChat {
+            client: amy,
+            text: " hi all ".to_owned(),
+        });
+        app.world_mut().send_event(ClientChat {
+            client: bob,
+            text: "/stop".to_owned(),
+        });
+        app.update();
+
+        let world = app.world();
+        assert_eq!(
+            chat(world.get::<Outbox>(amy).unwrap()),
+            ["* amy joined", "* bob joined", "<amy> hi all"]
+        );
+        assert_eq!(
+            chat(world.get::<Outbox>(bob).unwrap()),
+            [
+                "* amy joined",
+                "* bob joined",
+                "<amy> hi all",
+                "* Only operators can run commands"
+            ]
+        );
+    }
+}
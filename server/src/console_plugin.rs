@@ -0,0 +1,262 @@
+use std::{
+    error::Error,
+    io,
+    str::FromStr,
+    sync::{
+        mpsc::{self, Receiver},
+        Mutex,
+    },
+    thread,
+};
+
+use app::{
+    localization::Localization,
+    save_plugin::{save_world, WORLD_SAVE_PATH},
+    task_plugin::{TaskGroup, TaskPools},
+    time_plugin::Time,
+};
+use bevy_app::{App, AppExit, Plugin, Update};
+use bevy_ecs::{entity::Entity, system::Resource, world::World};
+use data::{
+    block_tick::{BlockTicks, GameTick},
+    chunk_map::ChunkMap,
+    worldgen::WorldSeed,
+};
+use thiserror::Error;
+
+use crate::interest_plugin::Client;
+
+/// Reads [`ConsoleCommand`]s from stdin, one per line, and runs them between frames
+pub struct ConsolePlugin;
+
+impl Plugin for ConsolePlugin {
+    fn build(&self, app: &mut App) {
+        let (sender, receiver) = mpsc::channel();
+        // Reading stdin blocks, so it gets a thread of its own rather than one from the pools
+        thread::Builder::new()
+            .name("Console".to_owned())
+            .spawn(move || {
+                for line in io::stdin().lines() {
+                    let Ok(line) = line else {
+                        break;
+                    };
+                    if sender.send(line).is_err() {
+                        break;
+                    }
+                }
+            })
+            .expect("could not spawn the console thread");
+
+        app.insert_resource(ConsoleInput(Mutex::new(receiver)))
+            .add_systems(Update, run_console_commands);
+        let localization = app.world().get_resource::<Localization>().cloned();
+        println!(
+            "{}",
+            localization.unwrap_or_default().text("console-greeting")
+        );
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConsoleCommand {
+    Save,
+    /// Saves, then exits
+    Stop,
+    Kick(String),
+    Info,
+    Help,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ParseCommandError {
+    #[error("unknown command {0:?}, type help for a list")]
+    Unknown(String),
+    #[error("usage: {0}")]
+    Usage(&'static str),
+}
+
+impl ParseCommandError {
+    pub fn localized(&self, localization: &Localization) -> String {
+        match self {
+            Self::Unknown(command) => {
+                localization.format("unknown-command", &[("command", command.as_str().into())])
+            }
+            Self::Usage(usage) => {
+                localization.format("command-usage", &[("usage", (*usage).into())])
+            }
+        }
+    }
+}
+
+impl FromStr for ConsoleCommand {
+    type Err = ParseCommandError;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let mut words = line.split_whitespace();
+        let name = words.next().unwrap_or_default();
+        let command = match name.to_lowercase().as_str() {
+            "save" => Self::Save,
+            "stop" => Self::Stop,
+            "kick" => match words.next() {
+                Some(player) => Self::Kick(player.to_owned()),
+                None => return Err(ParseCommandError::Usage("kick <player>")),
+            },
+            "info" => Self::Info,
+            "help" => Self::Help,
+            _ => return Err(ParseCommandError::Unknown(name.to_owned())),
+        };
+        match words.next() {
+            Some(_) => Err(ParseCommandError::Usage(match command {
+                Self::Kick(_) => "kick <player>",
+                _ => "<command> without arguments",
+            })),
+            None => Ok(command),
+        }
+    }
+}
+
+/// Lines typed since the last frame
+#[derive(Resource)]
+struct ConsoleInput(Mutex<Receiver<String>>);
+
+fn run_console_commands(world: &mut World) {
+    let lines: Vec<String> = world
+        .resource::<ConsoleInput>()
+        .0
+        .lock()
+        .unwrap()
+        .try_iter()
+        .filter(|line| !line.trim().is_empty())
+        .collect();
+    let localization = world.resource::<Localization>().clone();
+    for line in lines {
+        match line.parse() {
+            Ok(command) => run_command(world, &localization, command),
+            Err(e) => eprintln!("{}", e.localized(&localization)),
+        }
+    }
+}
+
+pub fn run_command(world: &mut World, localization: &Localization, command: ConsoleCommand) {
+    match command {
+        ConsoleCommand::Save => match save_world(world) {
+            Ok(save) => {
+                let localization = localization.clone();
+                world
+                    .resource::<TaskPools>()
+                    .spawn(TaskGroup::Io, async move {
+                        report_save(&localization, save.save(WORLD_SAVE_PATH));
+                    })
+                    .detach()
+            }
+            Err(e) => eprintln!(
+                "{}",
+                localization.format("world-save-failed", &[("error", e.to_string().into())])
+            ),
+        },
+        // Saved on this thread, so the file is complete before the process exits
+        ConsoleCommand::Stop => {
+            match save_world(world) {
+                Ok(save) => report_save(localization, save.save(WORLD_SAVE_PATH)),
+                Err(e) => eprintln!(
+                    "{}",
+                    localization.format("world-save-failed", &[("error", e.to_string().into())])
+                ),
+            }
+            println!("{}", localization.text("stopping"));
+            world.send_event(AppExit::Success);
+        }
+        // The transport drops the connection once its client is gone
+        ConsoleCommand::Kick(player) => {
+            let kicked = world
+                .query::<(Entity, &Client)>()
+                .iter(world)
+                .find(|(_, client)| client.name == player)
+                .map(|(entity, _)| entity);
+            match kicked {
+                Some(entity) => {
+                    world.despawn(entity);
+                }
+                None => println!(
+                    "{}",
+                    localization.format("player-not-connected", &[("player", player.into())])
+                ),
+            }
+        }
+        ConsoleCommand::Info => {
+            // Seeds use all 64 bits, more than a Fluent number keeps
+            let seed = world.resource::<WorldSeed>().0.to_string();
+            let tick = world.resource::<GameTick>().0;
+            let uptime = world.resource::<Time>().elapsed().as_secs();
+            println!(
+                "{}",
+                localization.format(
+                    "server-info",
+                    &[
+                        ("seed", seed.into()),
+                        ("tick", tick.into()),
+                        ("uptime", uptime.into()),
+                    ],
+                )
+            );
+            println!(
+                "{}",
+                localization.format(
+                    "server-counts",
+                    &[
+                        ("chunks", world.resource::<ChunkMap>().len().into()),
+                        ("block_ticks", world.resource::<BlockTicks>().len().into()),
+                        ("entities", world.entities().len().into()),
+                    ],
+                )
+            );
+        }
+        ConsoleCommand::Help => println!("{}", localization.text("console-help")),
+    }
+}
+
+fn report_save(localization: &Localization, result: Result<(), Box<dyn Error>>) {
+    match result {
+        Ok(()) => println!(
+            "{}",
+            localization.format("saved", &[("path", WORLD_SAVE_PATH.into())])
+        ),
+        Err(e) => eprintln!(
+            "{}",
+            localization.format(
+                "save-failed",
+                &[
+                    ("path", WORLD_SAVE_PATH.into()),
+                    ("error", e.to_string().into()),
+                ],
+            )
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commands_parse_with_their_arguments() {
+        assert_eq!("save".parse(), Ok(ConsoleCommand::Save));
+        assert_eq!("  STOP ".parse(), Ok(ConsoleCommand::Stop));
+        assert_eq!(
+            "kick alice".parse(),
+            Ok(ConsoleCommand::Kick("alice".to_owned()))
+        );
+        assert_eq!(
+            "kick".parse::<ConsoleCommand>(),
+            Err(ParseCommandError::Usage("kick <player>"))
+        );
+        assert!(matches!(
+            "info now".parse::<ConsoleCommand>(),
+            Err(ParseCommandError::Usage(_))
+        ));
+        assert_eq!(
+            "teleport".parse::<ConsoleCommand>(),
+            Err(ParseCommandError::Unknown("teleport".to_owned()))
+        );
+    }
+}
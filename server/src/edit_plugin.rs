@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+
+use app::simulation_plugin::VoxelChanged;
+use bevy_app::{App, Plugin, Update};
+use bevy_ecs::{
+    entity::Entity,
+    event::{Event, EventReader, EventWriter},
+    system::{Query, Res, ResMut, Resource},
+};
+use data::{
+    block_tick::{BlockTicks, GameTick},
+    chunk_map::ChunkMap,
+    edit_history::VoxelEdit,
+    edit_prediction::{EditId, EditRejection},
+    prelude::IVec3,
+    schematic::Region,
+};
+
+use crate::interest_plugin::{Client, Outbox, ServerMessage};
+
+/// Applies the voxel edits clients already made on their side, or turns them down: edits in
+/// [`ProtectedRegions`] from anyone but operators, and edits of voxels that changed since the
+/// client saw them. Each transaction is answered in its client's [`Outbox`], and applied as a
+/// whole or not at all. Accepted edits are resent to every client that has their chunks.
+pub struct EditPlugin;
+
+impl Plugin for EditPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ProtectedRegions>()
+            .add_event::<ClientEdit>()
+            .add_systems(Update, apply_client_edits);
+    }
+}
+
+/// Where only operators may edit
+#[derive(Resource, Debug, Clone, Default)]
+pub struct ProtectedRegions(pub Vec<Region>);
+
+impl ProtectedRegions {
+    pub fn contains(&self, position: IVec3) -> bool {
+        self.0.iter().any(|region| region.contains(position))
+    }
+}
+
+/// A transaction of edits a client predicted, sent by the transport
+#[derive(Event, Debug, Clone, PartialEq, Eq)]
+pub struct ClientEdit {
+    pub client: Entity,
+    pub id: EditId,
+    pub edits: Vec<VoxelEdit>,
+}
+
+/// Whether the edits may be applied, in order, to the chunks as they are
+fn check_edits(
+    chunks: &ChunkMap,
+    protected: &ProtectedRegions,
+    operator: bool,
+    edits: &[VoxelEdit],
+) -> Result<(), EditRejection> {
+    // Voxels as the transaction leaves them, for edits of a voxel it already changed
+    let mut edited = HashMap::new();
+    for edit in edits {
+        if !operator && protected.contains(edit.position) {
+            return Err(EditRejection::Protected);
+        }
+        let current = match edited.get(&edit.position) {
+            Some(voxel) => *voxel,
+            None => chunks.voxel(edit.position).ok_or(EditRejection::Unloaded)?,
+        };
+        if current != edit.old {
+            return Err(EditRejection::Outdated);
+        }
+        edited.insert(edit.position, edit.new);
+    }
+    Ok(())
+}
+
+fn apply_client_edits(
+    mut edit_reader: EventReader<ClientEdit>,
+    protected: Res<ProtectedRegions>,
+    tick: Res<GameTick>,
+    mut chunks: ResMut<ChunkMap>,
+    mut ticks: ResMut<BlockTicks>,
+    mut changed_writer: EventWriter<VoxelChanged>,
+    mut clients: Query<(&Client, &mut Outbox)>,
+) {
+    for ClientEdit { client, id, edits } in edit_reader.read() {
+        // Disconnected since
+        let Ok((client, mut outbox)) = clients.get_mut(*client) else {
+            continue;
+        };
+        if let Err(rejection) = check_edits(&chunks, &protected, client.operator, edits) {
+            outbox.0.push(ServerMessage::EditRejected(*id, rejection));
+            continue;
+        }
+        for edit in edits {
+            chunks.set_voxel(edit.position, edit.new);
+            ticks.schedule_around(&chunks, *tick, edit.position);
+            changed_writer.send(VoxelChanged {
+                position: edit.position,
+            });
+        }
+        outbox.0.push(ServerMessage::EditAccepted(*id));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use data::{voxel::Voxel, voxel_block::VoxelBlock};
+
+    use super::*;
+
+    #[test]
+    fn protected_and_outdated_edits_are_turned_down() {
+        let mut chunks = ChunkMap::default();
+        chunks.load_around(IVec3::ZERO, 0, |_| {
+            Box::new([Voxel::Air; VoxelBlock::VOLUME as usize])
+        });
+        let protected = ProtectedRegions(vec![Region::from_corners(IVec3::X, IVec3::X)]);
+        let edit = |position, old, new| VoxelEdit { position, old, new };
+
+        // The second edit builds on the first
+        let stacked = [
+            edit(IVec3::ZERO, Voxel::Air, Voxel::Stone),
+            edit(IVec3::ZERO, Voxel::Stone, Voxel::Dirt),
+        ];
+        assert_eq!(check_edits(&chunks, &protected, false, &stacked), Ok(()));
+        assert_eq!(
+            check_edits(&chunks, &protected, false, &stacked[1..]),
+            Err(EditRejection::Outdated)
+        );
+
+        let protected_edit = [edit(IVec3::X, Voxel::Air, Voxel::Stone)];
+        assert_eq!(
+            check_edits(&chunks, &protected, false, &protected_edit),
+            Err(EditRejection::Protected)
+        );
+        assert_eq!(
+            check_edits(&chunks, &protected, true, &protected_edit),
+            Ok(())
+        );
+        assert_eq!(
+            check_edits(
+                &chunks,
+                &protected,
+                true,
+                &[edit(IVec3::splat(-1), Voxel::Air, Voxel::Stone)]
+            ),
+            Err(EditRejection::Unloaded)
+        );
+    }
+}
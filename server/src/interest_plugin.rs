@@ -0,0 +1,340 @@
+use std::{
+    collections::{HashMap, HashSet},
+    mem,
+};
+
+use app::{simulation_plugin::VoxelChanged, time_plugin::Time};
+use bevy_app::{App, Plugin, Update};
+use bevy_ecs::{
+    component::Component,
+    entity::Entity,
+    event::EventReader,
+    query::{Changed, Without},
+    removal_detection::RemovedComponents,
+    schedule::IntoSystemConfigs,
+    system::{Query, Res, Resource},
+};
+use data::{
+    chat::ChatMessage,
+    chunk_map::{chunk_of, ChunkMap, ChunkSave},
+    edit_prediction::{EditId, EditRejection},
+    prelude::{IVec3, Vec3},
+    streaming::{StreamingView, WorkQueue},
+    transform::Transform,
+    view_distance::ViewDistance,
+};
+
+/// Decides per [`Client`] which chunks and entity updates it gets: the chunks within its view
+/// distance, the ones in front of it first, as fast as the [`InterestBudget`] allows, and
+/// updates only for the entities in chunks it has. Chunks edited after being sent are resent
+/// ahead of everything else. What each client is owed goes to its [`Outbox`], for the
+/// transport to send.
+pub struct InterestPlugin;
+
+impl Plugin for InterestPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<InterestBudget>().add_systems(
+            Update,
+            (queue_edited_chunks, send_chunks, send_entity_updates).chain(),
+        );
+    }
+}
+
+/// Chunks stay with a client this many chunks beyond its view distance, so walking back and
+/// forth across a chunk border doesn't resend the same chunks
+const UNLOAD_MARGIN: u32 = 1;
+
+/// How fast chunks are sent to each client
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct InterestBudget {
+    /// Chunk data sent per second, measured as run-length encoded voxels. A client can save
+    /// up to a second of it while nothing needs sending.
+    pub bytes_per_second: usize,
+    /// Most chunks sent in one update, however small they are
+    pub max_chunks_per_update: usize,
+}
+
+impl Default for InterestBudget {
+    fn default() -> Self {
+        Self {
+            bytes_per_second: 512 << 10,
+            max_chunks_per_update: 16,
+        }
+    }
+}
+
+/// A connected player
+#[derive(Component, Debug, Clone, PartialEq, Eq)]
+pub struct Client {
+    pub name: String,
+    /// May run console commands from chat
+    pub operator: bool,
+}
+
+/// Where a client views the world from, as it last reported
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct ClientView {
+    /// In world units
+    pub position: Vec3,
+    /// Needn't be normalized
+    pub forward: Vec3,
+    pub view_distance: ViewDistance,
+}
+
+impl ClientView {
+    fn chunk(&self) -> IVec3 {
+        chunk_of(self.position.floor().as_ivec3())
+    }
+
+    fn streaming_view(&self) -> StreamingView {
+        StreamingView::new(self.position, self.forward)
+    }
+
+    fn in_range(&self, chunk: IVec3, margin: u32) -> bool {
+        let radius = (self.view_distance.chunks() + margin) as i32;
+        (chunk - self.chunk()).length_squared() <= radius * radius
+    }
+}
+
+/// What the server owes a client
+#[derive(Debug, Clone, PartialEq)]
+pub enum ServerMessage {
+    Chunk {
+        chunk: IVec3,
+        save: ChunkSave,
+    },
+    /// Out of range; the client can drop it
+    ForgetChunk(IVec3),
+    Entity {
+        entity: Entity,
+        transform: Transform,
+    },
+    /// Left the client's chunks or was despawned
+    ForgetEntity(Entity),
+    Chat(ChatMessage),
+    /// The client's predicted edits were applied
+    EditAccepted(EditId),
+    /// The client's predicted edits were not applied, and it should roll them back
+    EditRejected(EditId, EditRejection),
+}
+
+/// Messages for a client, oldest first, drained by the transport
+#[derive(Component, Debug, Default)]
+pub struct Outbox(pub Vec<ServerMessage>);
+
+/// The chunks a client has and the ones it's still owed
+#[derive(Component, Debug, Default)]
+pub struct ChunkInterest {
+    /// Chunk and view distance the queue was last refilled for
+    view: Option<(IVec3, u32)>,
+    sent: HashSet<IVec3>,
+    pending: WorkQueue,
+    /// Sent, then edited; resent before any pending chunk
+    edited: WorkQueue,
+    /// Bytes the client may be sent right now; goes below zero after a large chunk
+    allowance: f32,
+    /// Entities the client has been told about
+    entities: HashSet<Entity>,
+}
+
+impl ChunkInterest {
+    pub fn has_chunk(&self, chunk: IVec3) -> bool {
+        self.sent.contains(&chunk)
+    }
+
+    /// Queues the chunks in range when the client enters another chunk or changes its view
+    /// distance, and returns the ones it had that are now out of range
+    fn update_view(&mut self, view: &ClientView) -> Vec<IVec3> {
+        let key = (view.chunk(), view.view_distance.chunks());
+        if self.view == Some(key) {
+            return Vec::new();
+        }
+        self.view = Some(key);
+
+        let forgotten: Vec<IVec3> = self
+            .sent
+            .iter()
+            .copied()
+            .filter(|chunk| !view.in_range(*chunk, UNLOAD_MARGIN))
+            .collect();
+        for chunk in &forgotten {
+            self.sent.remove(chunk);
+            self.edited.remove(*chunk);
+        }
+        self.pending.retain(|chunk| view.in_range(chunk, 0));
+        for chunk in view.view_distance.chunks_around(key.0) {
+            if !self.sent.contains(&chunk) {
+                self.pending.push(chunk);
+            }
+        }
+        forgotten
+    }
+
+    /// A chunk the client already has changed, so it's sent again
+    fn chunk_edited(&mut self, chunk: IVec3) {
+        if self.sent.contains(&chunk) {
+            self.edited.push(chunk);
+        }
+    }
+
+    /// Saves `elapsed` seconds' worth of `budget`, then takes the chunks to send while the
+    /// allowance lasts, edited ones first. Chunks the server hasn't loaded stay queued until
+    /// it has.
+    fn take(
+        &mut self,
+        view: &ClientView,
+        budget: &InterestBudget,
+        elapsed: f32,
+        chunks: &ChunkMap,
+    ) -> Vec<(IVec3, ChunkSave)> {
+        let rate = budget.bytes_per_second as f32;
+        self.allowance = (self.allowance + rate * elapsed).min(rate);
+        let streaming_view = view.streaming_view();
+        let mut taken = Vec::new();
+        while self.allowance > 0.0 && taken.len() < budget.max_chunks_per_update {
+            let queue = if self.edited.is_empty() {
+                &mut self.pending
+            } else {
+                &mut self.edited
+            };
+            let Some(chunk) = queue
+                .take(1, &streaming_view, |chunk| chunks.contains(chunk))
+                .pop()
+            else {
+                break;
+            };
+            let Some(save) = chunks.save_chunk(chunk) else {
+                continue;
+            };
+            self.allowance -= mem::size_of_val(save.voxels.as_slice()) as f32;
+            self.sent.insert(chunk);
+            taken.push((chunk, save));
+        }
+        taken
+    }
+}
+
+fn queue_edited_chunks(
+    mut changed_reader: EventReader<VoxelChanged>,
+    mut clients: Query<&mut ChunkInterest>,
+) {
+    let edited: HashSet<IVec3> = changed_reader
+        .read()
+        .map(|changed| chunk_of(changed.position))
+        .collect();
+    if edited.is_empty() {
+        return;
+    }
+    for mut interest in &mut clients {
+        for chunk in &edited {
+            interest.chunk_edited(*chunk);
+        }
+    }
+}
+
+fn send_chunks(
+    time: Res<Time>,
+    budget: Res<InterestBudget>,
+    chunks: Res<ChunkMap>,
+    mut clients: Query<(&ClientView, &mut ChunkInterest, &mut Outbox)>,
+) {
+    for (view, mut interest, mut outbox) in &mut clients {
+        for chunk in interest.update_view(view) {
+            outbox.0.push(ServerMessage::ForgetChunk(chunk));
+        }
+        for (chunk, save) in interest.take(view, &budget, time.delta_secs(), &chunks) {
+            outbox.0.push(ServerMessage::Chunk { chunk, save });
+        }
+    }
+}
+
+/// Entities other than clients that moved since the last update
+type MovedEntity = (Changed<Transform>, Without<Client>);
+
+/// Tells each client about the entities that moved in its chunks, and to forget the ones that
+/// left them or were despawned. Clients themselves aren't sent their own entity.
+fn send_entity_updates(
+    moved: Query<(Entity, &Transform), MovedEntity>,
+    mut despawned: RemovedComponents<Transform>,
+    mut clients: Query<(&mut ChunkInterest, &mut Outbox)>,
+) {
+    let moved: HashMap<Entity, Transform> = moved
+        .iter()
+        .map(|(entity, transform)| (entity, *transform))
+        .collect();
+    let despawned: Vec<Entity> = despawned.read().collect();
+    for (mut interest, mut outbox) in &mut clients {
+        for (entity, transform) in &moved {
+            let chunk = chunk_of(transform.translation.floor().as_ivec3());
+            if interest.has_chunk(chunk) {
+                interest.entities.insert(*entity);
+                outbox.0.push(ServerMessage::Entity {
+                    entity: *entity,
+                    transform: *transform,
+                });
+            } else if interest.entities.remove(entity) {
+                outbox.0.push(ServerMessage::ForgetEntity(*entity));
+            }
+        }
+        for entity in &despawned {
+            if interest.entities.remove(entity) {
+                outbox.0.push(ServerMessage::ForgetEntity(*entity));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use data::{
+        voxel::Voxel,
+        voxel_block::{Rle, VoxelBlock},
+    };
+
+    use super::*;
+
+    #[test]
+    fn edited_chunks_jump_the_queue_within_the_budget() {
+        let mut chunks = ChunkMap::default();
+        chunks.load_around(IVec3::ZERO, 3, |_| {
+            Box::new([Voxel::Air; VoxelBlock::VOLUME as usize])
+        });
+        let view = ClientView {
+            position: Vec3::splat(VoxelBlock::WIDTH as f32 / 2.0),
+            forward: Vec3::NEG_Z,
+            view_distance: ViewDistance::new(2),
+        };
+        // Chunks of air are a single run each, so this is three chunks a second
+        let budget = InterestBudget {
+            bytes_per_second: 3 * mem::size_of::<Rle>(),
+            max_chunks_per_update: 16,
+        };
+        let mut interest = ChunkInterest::default();
+        assert!(interest.update_view(&view).is_empty());
+
+        let first: Vec<IVec3> = interest
+            .take(&view, &budget, 1.0, &chunks)
+            .into_iter()
+            .map(|(chunk, _)| chunk)
+            .collect();
+        assert_eq!(first.len(), 3);
+        assert_eq!(first[0], IVec3::ZERO);
+        assert!(interest.has_chunk(IVec3::ZERO));
+        assert!(interest.take(&view, &budget, 0.0, &chunks).is_empty());
+
+        interest.chunk_edited(first[2]);
+        // Not sent yet, so there's nothing to resend
+        interest.chunk_edited(IVec3::new(0, 2, 0));
+        let next = interest.take(&view, &budget, 0.3, &chunks);
+        assert_eq!(next.len(), 1);
+        assert_eq!(next[0].0, first[2]);
+
+        let moved = ClientView {
+            position: view.position + Vec3::X * (4 * VoxelBlock::WIDTH) as f32,
+            ..view
+        };
+        let forgotten = interest.update_view(&moved);
+        assert!(forgotten.contains(&IVec3::ZERO));
+        assert!(!interest.has_chunk(IVec3::ZERO));
+    }
+}
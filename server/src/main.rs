@@ -0,0 +1,50 @@
+//! Runs the world without a window or a GPU: chunks, block ticks and world saves, driven from
+//! commands typed into the console. Built on the `app` crate without its `client` feature, so
+//! neither winit nor ash is linked.
+
+use app::{
+    localization::LocalizationPlugin,
+    mods_plugin::ModsPlugin,
+    prefab_plugin::PrefabPlugin,
+    protocol::ProtocolPlugin,
+    save_plugin::SavePlugin,
+    simulation_plugin::SimulationPlugin,
+    task_plugin::TaskPlugin,
+    time_plugin::{FixedTimestep, TimePlugin},
+};
+use bevy_app::{App, AppExit, ScheduleRunnerPlugin};
+
+use crate::{
+    chat_plugin::ChatPlugin, console_plugin::ConsolePlugin, edit_plugin::EditPlugin,
+    interest_plugin::InterestPlugin, server_plugin::ServerPlugin,
+};
+
+mod chat_plugin;
+mod console_plugin;
+mod edit_plugin;
+mod interest_plugin;
+mod server_plugin;
+
+fn main() -> AppExit {
+    app::profiler::start();
+
+    App::new()
+        .add_plugins((
+            TaskPlugin::default(),
+            // Nothing is drawn, so there's no reason to update more often than the game ticks
+            ScheduleRunnerPlugin::run_loop(FixedTimestep::TICK),
+            TimePlugin,
+            LocalizationPlugin,
+            ProtocolPlugin,
+            SimulationPlugin,
+            SavePlugin,
+            PrefabPlugin,
+            ServerPlugin,
+            InterestPlugin,
+            EditPlugin,
+            ChatPlugin,
+            ConsolePlugin,
+            ModsPlugin,
+        ))
+        .run()
+}
@@ -0,0 +1,57 @@
+use app::{
+    localization::Localization,
+    save_plugin::{load_world_file, WORLD_SAVE_PATH},
+};
+use bevy_app::{App, Plugin, Startup};
+use bevy_ecs::{
+    schedule::IntoSystemConfigs,
+    system::{Res, ResMut},
+    world::World,
+};
+use data::{
+    chunk_map::ChunkMap,
+    prelude::IVec3,
+    worldgen::{generate_chunk, WorldSeed},
+};
+
+/// Loads the world save and generates the chunks around spawn, which stay loaded for as long
+/// as the server runs
+pub struct ServerPlugin;
+
+impl Plugin for ServerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, (load_save, generate_spawn_chunks).chain());
+    }
+}
+
+/// Chunks in each direction from the spawn chunk
+pub const SPAWN_CHUNK_RADIUS: i32 = 2;
+
+fn load_save(world: &mut World) {
+    if let Err(e) = load_world_file(world, WORLD_SAVE_PATH) {
+        eprintln!(
+            "{}",
+            world.resource::<Localization>().format(
+                "world-save-load-failed",
+                &[
+                    ("path", WORLD_SAVE_PATH.into()),
+                    ("error", e.to_string().into()),
+                ],
+            )
+        );
+    }
+}
+
+fn generate_spawn_chunks(
+    seed: Res<WorldSeed>,
+    localization: Res<Localization>,
+    mut chunks: ResMut<ChunkMap>,
+) {
+    chunks.load_around(IVec3::ZERO, SPAWN_CHUNK_RADIUS, |chunk| {
+        generate_chunk(*seed, chunk)
+    });
+    println!(
+        "{}",
+        localization.format("spawn-chunks-generated", &[("chunks", chunks.len().into())])
+    );
+}
@@ -0,0 +1,29 @@
+// Decodes the packed chunk vertices written by `data::vertex::ChunkVertex`. Include with
+// `#extension GL_GOOGLE_include_directive : require` and `#include "chunk_vertex.glsl"`.
+
+// ChunkVertex::UNORM_SCALE, for vertices bound as R8G8B8A8_UNORM
+const float CHUNK_UNORM_SCALE = 255.0;
+
+// ChunkVertex::FACE_NORMALS, indexed by the face's Direction. -Y is up.
+const vec3 CHUNK_FACE_NORMALS[6] = vec3[6](
+    vec3(-1.0, 0.0, 0.0),
+    vec3(1.0, 0.0, 0.0),
+    vec3(0.0, 1.0, 0.0),
+    vec3(0.0, -1.0, 0.0),
+    vec3(0.0, 0.0, 1.0),
+    vec3(0.0, 0.0, -1.0)
+);
+
+// Chunk-local position of a vertex bound as R8G8B8A8_UINT
+vec3 chunk_vertex_position(uvec4 packed) {
+    return vec3(packed.xyz);
+}
+
+vec3 chunk_vertex_normal(uvec4 packed) {
+    return CHUNK_FACE_NORMALS[packed.w];
+}
+
+// Same, for vertices bound as R8G8B8A8_UNORM
+uvec4 chunk_vertex_from_unorm(vec4 packed) {
+    return uvec4(round(packed * CHUNK_UNORM_SCALE));
+}
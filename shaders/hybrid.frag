@@ -0,0 +1,80 @@
+#version 460
+#extension GL_EXT_ray_query : require
+
+layout(location = 0) in vec3 in_world_position;
+
+layout(location = 0) out vec4 out_color;
+
+layout(set = 0, binding = 0) uniform accelerationStructureEXT top_level_as;
+
+layout(push_constant) uniform PushConstants {
+    mat4 view_proj;
+    vec4 color;
+    vec4 camera_position;
+    uint shadows;
+    uint ambient_occlusion;
+    float sunlight;
+} pc;
+
+// Must match closesthit.rchit so both paths light the scene the same way
+const vec3 SUN_DIRECTION = normalize(vec3(0.3, -1.0, 0.2));
+const float SHADOW_LIGHT = 0.4;
+const float AO_RADIUS = 1.0;
+const float AO_LIGHT = 0.6;
+// Nothing accumulates on this path, so AO takes several rays per pixel at once
+const uint AO_RAYS = 4u;
+
+bool occluded(vec3 origin, vec3 direction, float max_distance) {
+    rayQueryEXT query;
+    rayQueryInitializeEXT(query, top_level_as,
+        gl_RayFlagsOpaqueEXT | gl_RayFlagsTerminateOnFirstHitEXT, 0xff,
+        origin, 0.001, direction, max_distance);
+    while (rayQueryProceedEXT(query)) {}
+    return rayQueryGetIntersectionTypeEXT(query, true) != gl_RayQueryCommittedIntersectionNoneEXT;
+}
+
+// Per-pixel rotation so the few AO rays band less
+float pixel_hash() {
+    uvec2 pixel = uvec2(gl_FragCoord.xy);
+    uint h = pixel.x * 1973u + pixel.y * 9277u;
+    h = (h ^ (h >> 15)) * 0x2c1b3c6du;
+    return float(h ^ (h >> 12)) / 4294967295.0;
+}
+
+vec3 hemisphere_direction(vec3 normal, uint index, float rotation) {
+    float phi = 6.28318530718 * (float(index) / float(AO_RAYS) + rotation);
+    // Fixed elevation halfway between the horizon and the normal
+    float radius = 0.7071;
+    vec3 tangent = normalize(abs(normal.x) > 0.9 ? cross(normal, vec3(0, 1, 0)) : cross(normal, vec3(1, 0, 0)));
+    vec3 bitangent = cross(normal, tangent);
+    return normalize(tangent * cos(phi) * radius + bitangent * sin(phi) * radius + normal * 0.7071);
+}
+
+void main() {
+    // Flat normal from screen-space derivatives; there is no G-buffer to read it from
+    vec3 normal = normalize(cross(dFdx(in_world_position), dFdy(in_world_position)));
+    // Triangles are not culled, so face the normal towards the viewer
+    if (dot(normal, pc.camera_position.xyz - in_world_position) < 0.0) {
+        normal = -normal;
+    }
+    vec3 origin = in_world_position + normal * 0.001;
+    vec3 color = pc.color.rgb;
+
+    if (pc.shadows != 0u && dot(normal, SUN_DIRECTION) > 0.0
+        && occluded(origin, SUN_DIRECTION, 10000.0)) {
+        color *= SHADOW_LIGHT;
+    }
+
+    if (pc.ambient_occlusion != 0u) {
+        float rotation = pixel_hash();
+        uint blocked = 0u;
+        for (uint i = 0u; i < AO_RAYS; i++) {
+            if (occluded(origin, hemisphere_direction(normal, i, rotation), AO_RADIUS)) {
+                blocked++;
+            }
+        }
+        color *= mix(1.0, AO_LIGHT, float(blocked) / float(AO_RAYS));
+    }
+
+    out_color = vec4(color * pc.sunlight, pc.color.a);
+}
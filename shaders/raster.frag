@@ -0,0 +1,12 @@
+#version 460
+
+layout(location = 0) out vec4 out_color;
+
+layout(push_constant) uniform PushConstants {
+    mat4 view_proj;
+    vec4 color;
+} pc;
+
+void main() {
+    out_color = pc.color;
+}
@@ -0,0 +1,16 @@
+#version 460
+
+layout(location = 0) in vec3 in_position;
+
+// Only read by the hybrid fragment shader, which traces from the surface
+layout(location = 0) out vec3 out_world_position;
+
+layout(push_constant) uniform PushConstants {
+    mat4 view_proj;
+    vec4 color;
+} pc;
+
+void main() {
+    out_world_position = in_position;
+    gl_Position = pc.view_proj * vec4(in_position, 1.0);
+}
@@ -0,0 +1,82 @@
+//! The engine in one crate: the game's plugins, the renderer and the voxel data types, so a
+//! game only needs to depend on `vx`.
+//!
+//! ```no_run
+//! use vx::prelude::*;
+//!
+//! struct MyGame;
+//!
+//! impl Plugin for MyGame {
+//!     fn build(&self, _app: &mut App) {}
+//! }
+//!
+//! Vx::new().with_world(42).with_plugins(MyGame).run();
+//! ```
+
+use bevy_app::{App, AppExit, Plugins};
+use data::worldgen::WorldSeed;
+
+pub use app;
+pub use bevy_app;
+pub use bevy_ecs;
+pub use data;
+pub use glam;
+pub use renderer;
+
+pub mod prelude {
+    pub use app::default_plugins::DefaultPlugins;
+    pub use bevy_app::{App, Plugin, Startup, Update};
+    pub use bevy_ecs::prelude::*;
+    pub use data::{
+        chunk_map::ChunkMap,
+        mesh::Mesh,
+        prelude::{IVec3, Quat, Vec3},
+        transform::Transform,
+        voxel::Voxel,
+        worldgen::WorldSeed,
+    };
+    pub use renderer::settings::RendererSettings;
+
+    pub use crate::Vx;
+}
+
+/// An [`App`] with the [`DefaultPlugins`](app::default_plugins::DefaultPlugins), to add a
+/// game's own plugins to
+pub struct Vx {
+    app: App,
+}
+
+impl Default for Vx {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Vx {
+    pub fn new() -> Self {
+        let mut app = App::new();
+        app.add_plugins(app::default_plugins::DefaultPlugins);
+        Self { app }
+    }
+
+    /// Generates terrain from `seed` instead of the default one
+    pub fn with_world(mut self, seed: u64) -> Self {
+        self.app.insert_resource(WorldSeed(seed));
+        self
+    }
+
+    pub fn with_plugins<M>(mut self, plugins: impl Plugins<M>) -> Self {
+        self.app.add_plugins(plugins);
+        self
+    }
+
+    /// For anything the builder doesn't cover, like resources and systems
+    pub fn app_mut(&mut self) -> &mut App {
+        &mut self.app
+    }
+
+    pub fn run(mut self) -> AppExit {
+        app::profiler::start();
+        self.app.run()
+    }
+}